@@ -166,6 +166,17 @@ impl From<&StoredMessage> for Message {
                 format!("Join group: {}", group_name),
                 Some(group_id.clone()),
             ),
+            gigi_store::MessageContent::Poll { question, .. } => {
+                (format!("Poll: {}", question), None)
+            }
+            gigi_store::MessageContent::PollVote { .. } => ("Voted in poll".to_string(), None),
+            gigi_store::MessageContent::Location { lat, lon, label } => match label {
+                Some(label) => (
+                    format!("Location: {} ({:.5}, {:.5})", label, lat, lon),
+                    None,
+                ),
+                None => (format!("Location: {:.5}, {:.5}", lat, lon), None),
+            },
         };
 
         let message_type = match &msg.content {
@@ -183,7 +194,10 @@ impl From<&StoredMessage> for Message {
                     MessageType::File
                 }
             }
-            gigi_store::MessageContent::ShareGroup { .. } => MessageType::Text,
+            gigi_store::MessageContent::ShareGroup { .. }
+            | gigi_store::MessageContent::Poll { .. }
+            | gigi_store::MessageContent::PollVote { .. }
+            | gigi_store::MessageContent::Location { .. } => MessageType::Text,
         };
 
         let is_own = matches!(msg.direction, gigi_store::MessageDirection::Sent);
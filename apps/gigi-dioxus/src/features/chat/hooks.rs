@@ -1132,7 +1132,7 @@ pub fn use_chat_event_listeners(
                             println!("Group updated event");
                         }
                         AppEvent::FileShareReceived {
-                            from_peer_id: _,
+                            from_peer_id,
                             from_nickname,
                             share_code,
                             filename,
@@ -1159,6 +1159,14 @@ pub fn use_chat_event_listeners(
                                 let is_image = lower_file_type.starts_with("image/")
                                     || ["png", "jpg", "jpeg", "gif", "bmp", "webp"]
                                         .contains(&lower_file_type.as_str());
+                                // Images the client accepts without asking download
+                                // right away; anything else waits for a tap
+                                let auto_download = is_image
+                                    && crate::services::p2p_service::P2pService::should_auto_accept(
+                                        &from_peer_id,
+                                        file_size,
+                                    )
+                                    .await;
 
                                 let message_id = uuid::Uuid::new_v4().to_string();
 
@@ -1181,7 +1189,7 @@ pub fn use_chat_event_listeners(
                                         file_size: Some(file_size),
                                         file_type: Some(file_type),
                                         share_code: Some(share_code),
-                                        is_downloading: is_image,
+                                        is_downloading: auto_download,
                                         download_progress: None,
                                         download_id: None,
                                         file_path: None,
@@ -1195,7 +1203,7 @@ pub fn use_chat_event_listeners(
                                     println!("Added file share message to chat room");
                                 }
 
-                                if is_image {
+                                if auto_download {
                                     let chat_room_state_ref = chat_room_state_clone;
                                     spawn(async move {
                                         download_image_with_retry(
@@ -1212,7 +1220,7 @@ pub fn use_chat_event_listeners(
                             }
                         }
                         AppEvent::GroupFileShareReceived {
-                            from_peer_id: _,
+                            from_peer_id,
                             from_nickname,
                             share_code,
                             filename,
@@ -1239,6 +1247,14 @@ pub fn use_chat_event_listeners(
                                 let is_image = lower_file_type.starts_with("image/")
                                     || ["png", "jpg", "jpeg", "gif", "bmp", "webp"]
                                         .contains(&lower_file_type.as_str());
+                                // Images the client accepts without asking download
+                                // right away; anything else waits for a tap
+                                let auto_download = is_image
+                                    && crate::services::p2p_service::P2pService::should_auto_accept(
+                                        &from_peer_id,
+                                        file_size,
+                                    )
+                                    .await;
 
                                 let message_id = uuid::Uuid::new_v4().to_string();
 
@@ -1261,7 +1277,7 @@ pub fn use_chat_event_listeners(
                                         file_size: Some(file_size),
                                         file_type: Some(file_type),
                                         share_code: Some(share_code),
-                                        is_downloading: is_image,
+                                        is_downloading: auto_download,
                                         download_progress: None,
                                         download_id: None,
                                         file_path: None,
@@ -1275,7 +1291,7 @@ pub fn use_chat_event_listeners(
                                     println!("Added file share message to chat room");
                                 }
 
-                                if is_image {
+                                if auto_download {
                                     let chat_room_state_ref = chat_room_state_clone;
                                     spawn(async move {
                                         download_image_with_retry(
@@ -1375,7 +1391,7 @@ pub fn use_message_actions(
             let peer = chat_room_state.read().peer.clone();
 
             spawn(async move {
-                if is_group_chat {
+                let sent = if is_group_chat {
                     // Ensure group is joined before sending message
                     println!("Ensuring group is joined before sending: {}", chat_name);
                     if let Err(err) =
@@ -1388,39 +1404,37 @@ pub fn use_message_actions(
                     }
 
                     // Send group message
-                    if let Err(err) = crate::services::p2p_service::P2pService::send_group_message(
+                    crate::services::p2p_service::P2pService::send_group_message(
                         &chat_name,
                         &message_content,
                     )
                     .await
-                    {
-                        println!("Error sending group message: {:?}", err);
-                    } else {
-                        println!("Group message sent successfully");
-                    }
+                    .inspect(|_| println!("Group message sent successfully"))
+                    .inspect_err(|err| println!("Error sending group message: {:?}", err))
                 } else {
                     // Send direct message
-                    if let Err(err) = crate::services::p2p_service::P2pService::send_message(
+                    crate::services::p2p_service::P2pService::send_message(
                         &chat_name,
                         &message_content,
                     )
                     .await
-                    {
-                        println!("Error sending message: {:?}", err);
-                    } else {
-                        println!("Message sent successfully");
-                    }
-                }
+                    .inspect(|_| println!("Message sent successfully"))
+                    .inspect_err(|err| println!("Error sending message: {:?}", err))
+                };
 
                 // Set sending to false
                 chat_room_state.write().sending = false;
 
-                // Save message to persistence using P2pService's db worker
+                // Save the sent message under the id the recipients see
+                let Ok(message_id) = sent else {
+                    return;
+                };
                 if let Some(local_nickname) =
                     crate::services::p2p_service::P2pService::get_local_nickname().await
                 {
                     if is_group_chat {
                         crate::services::p2p_service::P2pService::store_own_group_message(
+                            message_id,
                             local_nickname,
                             chat_name,
                             message_content,
@@ -1428,6 +1442,7 @@ pub fn use_message_actions(
                         .await;
                     } else if let Some(peer) = peer {
                         crate::services::p2p_service::P2pService::store_own_direct_message(
+                            message_id,
                             local_nickname,
                             chat_name,
                             message_content,
@@ -1646,6 +1661,10 @@ async fn handle_shared_file(
 
                 if is_group_chat {
                     let _ = crate::services::persistence_service::PersistenceService::store_group_file_share_message(
+                        crate::services::persistence_service::PersistenceService::shared_message_id(
+                            &file_share_info.share_code,
+                            &chat_name,
+                        ),
                         local_nickname,
                         chat_name.clone(),
                         filename.to_string(),
@@ -1656,6 +1675,10 @@ async fn handle_shared_file(
                     ).await;
                 } else {
                     let _ = crate::services::persistence_service::PersistenceService::store_file_share_message(
+                        crate::services::persistence_service::PersistenceService::shared_message_id(
+                            &file_share_info.share_code,
+                            &chat_name,
+                        ),
                         local_nickname,
                         chat_name.clone(),
                         filename.to_string(),
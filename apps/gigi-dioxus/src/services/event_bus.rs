@@ -24,7 +24,6 @@ pub enum AppEvent {
         error: String,
     },
     FileShareReceived {
-        #[allow(dead_code)]
         from_peer_id: String,
         from_nickname: String,
         share_code: String,
@@ -35,7 +34,6 @@ pub enum AppEvent {
         conv_id: String,
     },
     GroupFileShareReceived {
-        #[allow(dead_code)]
         from_peer_id: String,
         from_nickname: String,
         share_code: String,
@@ -49,10 +49,12 @@ impl MessageService {
 
         // Use persistence service to store message
         let _ = PersistenceService::store_direct_message(
+            message.id.clone(),
             from_nickname.to_string(),
             to_nickname.to_string(),
             message.content.clone(),
             is_own,
+            None,
         )
         .await
         .map_err(|e| e.to_string())?;
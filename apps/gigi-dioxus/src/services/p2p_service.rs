@@ -21,17 +21,22 @@ pub struct FileShareInfo {
     pub file_type: String,
 }
 
+/// Largest received file downloaded without a tap, 5 MiB
+const AUTO_ACCEPT_MAX_SIZE: u64 = 5 * 1024 * 1024;
+
 static P2P_CLIENT: Lazy<Arc<Mutex<Option<P2pClient>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
 static LOCAL_NICKNAME: Lazy<Arc<Mutex<Option<String>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
 
 #[derive(Debug)]
 enum DbOperation {
     StoreDirectMessage {
+        message_id: String,
         from_nickname: String,
         to_nickname: String,
         message: String,
         is_own: bool,
         from_peer_id: String,
+        disappear_after_secs: Option<u64>,
     },
     StoreFileShareMessage {
         from_nickname: String,
@@ -52,6 +57,7 @@ enum DbOperation {
         from_peer_id: String,
     },
     StoreGroupMessage {
+        message_id: String,
         from_nickname: String,
         group_name: String,
         message: String,
@@ -87,17 +93,21 @@ async fn db_worker(mut rx: mpsc::Receiver<DbOperation>) {
     while let Some(op) = rx.recv().await {
         match op {
             DbOperation::StoreDirectMessage {
+                message_id,
                 from_nickname,
                 to_nickname,
                 message,
                 is_own,
                 from_peer_id,
+                disappear_after_secs,
             } => {
                 if crate::services::persistence_service::PersistenceService::store_direct_message(
+                    message_id,
                     from_nickname.clone(),
                     to_nickname.clone(),
                     message.clone(),
                     is_own,
+                    disappear_after_secs,
                 )
                 .await
                 .is_ok()
@@ -138,7 +148,13 @@ async fn db_worker(mut rx: mpsc::Receiver<DbOperation>) {
             } => {
                 let from_nickname_clone = from_nickname.clone();
                 let from_peer_id_clone = from_peer_id.clone();
+                let message_id =
+                    crate::services::persistence_service::PersistenceService::shared_message_id(
+                        &share_code,
+                        &to_nickname,
+                    );
                 if crate::services::persistence_service::PersistenceService::store_file_share_message(
+                    message_id,
                     from_nickname.clone(),
                     to_nickname,
                     filename.clone(),
@@ -180,7 +196,13 @@ async fn db_worker(mut rx: mpsc::Receiver<DbOperation>) {
                 is_own,
                 from_peer_id,
             } => {
+                let message_id =
+                    crate::services::persistence_service::PersistenceService::shared_message_id(
+                        &group_id,
+                        &to_nickname,
+                    );
                 if crate::services::persistence_service::PersistenceService::store_group_share_message(
+                    message_id,
                     from_nickname.clone(),
                     to_nickname,
                     group_id.clone(),
@@ -227,12 +249,14 @@ async fn db_worker(mut rx: mpsc::Receiver<DbOperation>) {
                 }
             }
             DbOperation::StoreGroupMessage {
+                message_id,
                 from_nickname,
                 group_name,
                 message,
                 is_own,
             } => {
                 if crate::services::persistence_service::PersistenceService::store_group_message(
+                    message_id,
                     from_nickname.clone(),
                     group_name.clone(),
                     message.clone(),
@@ -269,7 +293,13 @@ async fn db_worker(mut rx: mpsc::Receiver<DbOperation>) {
                 is_own,
             } => {
                 let group_name_clone = group_name.clone();
+                let message_id =
+                    crate::services::persistence_service::PersistenceService::shared_message_id(
+                        &share_code,
+                        &group_name,
+                    );
                 if crate::services::persistence_service::PersistenceService::store_group_file_share_message(
+                    message_id,
                     from_nickname.clone(),
                     group_name.clone(),
                     filename.clone(),
@@ -342,28 +372,35 @@ async fn send_db_operation(op: DbOperation) {
 pub struct P2pService;
 
 impl P2pService {
+    /// Store a sent direct message under the id `send_message` returned
     pub async fn store_own_direct_message(
+        message_id: String,
         from_nickname: String,
         to_nickname: String,
         message: String,
         from_peer_id: String,
     ) {
         send_db_operation(DbOperation::StoreDirectMessage {
+            message_id,
             from_nickname,
             to_nickname,
             message,
             is_own: true,
             from_peer_id,
+            disappear_after_secs: None,
         })
         .await;
     }
 
+    /// Store a sent group message under the id `send_group_message` returned
     pub async fn store_own_group_message(
+        message_id: String,
         from_nickname: String,
         group_name: String,
         message: String,
     ) {
         send_db_operation(DbOperation::StoreGroupMessage {
+            message_id,
             from_nickname,
             group_name,
             message,
@@ -407,6 +444,7 @@ impl P2pService {
         // Create P2P config without bootstrap nodes (use mDNS for local discovery)
         let p2p_config = P2pConfig {
             bootstrap_nodes: vec![],
+            auto_accept_max_size: Some(AUTO_ACCEPT_MAX_SIZE),
             ..Default::default()
         };
 
@@ -471,17 +509,21 @@ impl P2pService {
             P2pEvent::DirectMessage {
                 from_nickname,
                 message,
+                message_id,
                 from,
+                disappear_after_secs,
                 ..
             } => {
                 println!("Message from {}: {}", from_nickname, message);
                 let local_nickname = LOCAL_NICKNAME.lock().await.clone().unwrap_or_default();
                 send_db_operation(DbOperation::StoreDirectMessage {
+                    message_id,
                     from_nickname,
                     to_nickname: local_nickname,
                     message,
                     is_own: false,
                     from_peer_id: from.to_string(),
+                    disappear_after_secs,
                 })
                 .await;
             }
@@ -604,6 +646,7 @@ impl P2pService {
                 from_nickname,
                 group,
                 message,
+                message_id,
                 ..
             } => {
                 println!(
@@ -611,6 +654,7 @@ impl P2pService {
                     from_nickname, group, message
                 );
                 send_db_operation(DbOperation::StoreGroupMessage {
+                    message_id,
                     from_nickname,
                     group_name: group,
                     message,
@@ -649,26 +693,29 @@ impl P2pService {
         }
     }
 
-    pub async fn send_message(to_nickname: &str, message: &str) -> Result<()> {
+    /// Send a direct message, returning the id the peer receives it under
+    pub async fn send_message(to_nickname: &str, message: &str) -> Result<String> {
         if let Ok(Some(mut client_guard)) = Self::get_client().await {
             if let Some(client) = client_guard.as_mut() {
                 println!("Sending message to {}: {}", to_nickname, message);
                 match client.send_direct_message(to_nickname, message.to_string()) {
-                    Ok(_) => println!("Message sent successfully to {}", to_nickname),
+                    Ok(message_id) => {
+                        println!("Message sent successfully to {}", to_nickname);
+                        Ok(message_id)
+                    }
                     Err(e) => {
                         println!("Failed to send message to {}: {:?}", to_nickname, e);
-                        return Err(e);
+                        Err(e)
                     }
                 }
             } else {
                 println!("P2P client is not initialized");
-                return Err(anyhow::anyhow!("P2P client not initialized"));
+                Err(anyhow::anyhow!("P2P client not initialized"))
             }
         } else {
             println!("Failed to get P2P client");
-            return Err(anyhow::anyhow!("Failed to get P2P client"));
+            Err(anyhow::anyhow!("Failed to get P2P client"))
         }
-        Ok(())
     }
 
     pub async fn deliver_pending_messages(nickname: &str) -> Result<()> {
@@ -700,13 +747,15 @@ impl P2pService {
         Ok(())
     }
 
-    pub async fn send_group_message(group_name: &str, message: &str) -> Result<()> {
+    /// Send a group message, returning the id members receive it under
+    pub async fn send_group_message(group_name: &str, message: &str) -> Result<String> {
         if let Ok(Some(mut client_guard)) = Self::get_client().await {
             if let Some(client) = client_guard.as_mut() {
-                client.send_group_message(group_name, message.to_string())?;
+                let status = client.send_group_message(group_name, message.to_string())?;
+                return Ok(status.message_id().to_string());
             }
         }
-        Ok(())
+        Err(anyhow::anyhow!("P2P client not initialized"))
     }
 
     pub async fn send_group_file(group_name: &str, file_path: &PathBuf) -> Result<FileShareInfo> {
@@ -927,6 +976,19 @@ impl P2pService {
         Err(anyhow::anyhow!("P2P client not initialized"))
     }
 
+    /// Whether a file shared by a peer is downloaded without a tap
+    pub async fn should_auto_accept(from_peer_id: &str, file_size: u64) -> bool {
+        let Ok(peer_id) = from_peer_id.parse() else {
+            return false;
+        };
+        if let Ok(Some(client_guard)) = Self::get_client().await {
+            if let Some(client) = client_guard.as_ref() {
+                return client.should_auto_accept(&peer_id, file_size);
+            }
+        }
+        false
+    }
+
     #[allow(dead_code)]
     pub async fn shutdown() -> Result<()> {
         if let Ok(Some(mut client_guard)) = Self::get_client().await {
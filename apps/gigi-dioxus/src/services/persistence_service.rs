@@ -65,14 +65,22 @@ impl PersistenceService {
         Ok(())
     }
 
+    /// Id of a file share or group invitation, which carry no message id on
+    /// the wire; built from what sender and receiver both know, so both
+    /// store the message under the same id
+    pub fn shared_message_id(key: &str, conversation: &str) -> String {
+        format!("{}:{}", key, conversation)
+    }
+
     // Helper methods
     pub async fn store_direct_message(
+        msg_id: String,
         from_nickname: String,
         to_nickname: String,
         message: String,
         is_own: bool,
+        disappear_after_secs: Option<u64>,
     ) -> Result<String> {
-        let msg_id = uuid::Uuid::new_v4().to_string();
         let stored_msg = StoredMessage {
             id: msg_id.clone(),
             msg_type: gigi_store::MessageType::Direct,
@@ -96,6 +104,8 @@ impl PersistenceService {
             sync_attempts: 0,
             last_sync_attempt: None,
             expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+            disappear_after_secs,
+            forwarded_from: None,
         };
 
         let mut store_guard = MESSAGE_STORE.lock().await;
@@ -107,6 +117,7 @@ impl PersistenceService {
     }
 
     pub async fn store_file_share_message(
+        msg_id: String,
         from_nickname: String,
         to_nickname: String,
         filename: String,
@@ -115,7 +126,6 @@ impl PersistenceService {
         file_type: String,
         is_own: bool,
     ) -> Result<String> {
-        let msg_id = uuid::Uuid::new_v4().to_string();
         let stored_msg = StoredMessage {
             id: msg_id.clone(),
             msg_type: gigi_store::MessageType::Direct,
@@ -129,6 +139,7 @@ impl PersistenceService {
                 share_code,
                 file_size,
                 file_type,
+                from_peer: None,
             },
             sender_nickname: from_nickname.clone(),
             recipient_nickname: Some(to_nickname),
@@ -144,6 +155,8 @@ impl PersistenceService {
             sync_attempts: 0,
             last_sync_attempt: None,
             expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+            disappear_after_secs: None,
+            forwarded_from: None,
         };
 
         let mut store_guard = MESSAGE_STORE.lock().await;
@@ -155,6 +168,7 @@ impl PersistenceService {
     }
 
     pub async fn store_group_file_share_message(
+        msg_id: String,
         from_nickname: String,
         group_name: String,
         filename: String,
@@ -163,7 +177,6 @@ impl PersistenceService {
         file_type: String,
         is_own: bool,
     ) -> Result<String> {
-        let msg_id = uuid::Uuid::new_v4().to_string();
         let stored_msg = StoredMessage {
             id: msg_id.clone(),
             msg_type: gigi_store::MessageType::Group,
@@ -177,6 +190,7 @@ impl PersistenceService {
                 share_code,
                 file_size,
                 file_type,
+                from_peer: None,
             },
             sender_nickname: from_nickname.clone(),
             recipient_nickname: None,
@@ -192,6 +206,8 @@ impl PersistenceService {
             sync_attempts: 0,
             last_sync_attempt: None,
             expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+            disappear_after_secs: None,
+            forwarded_from: None,
         };
 
         let mut store_guard = MESSAGE_STORE.lock().await;
@@ -203,13 +219,13 @@ impl PersistenceService {
     }
 
     pub async fn store_group_share_message(
+        msg_id: String,
         from_nickname: String,
         to_nickname: String,
         group_id: String,
         group_name: String,
         is_own: bool,
     ) -> Result<String> {
-        let msg_id = uuid::Uuid::new_v4().to_string();
         let stored_msg = StoredMessage {
             id: msg_id.clone(),
             msg_type: gigi_store::MessageType::Direct,
@@ -237,6 +253,8 @@ impl PersistenceService {
             sync_attempts: 0,
             last_sync_attempt: None,
             expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+            disappear_after_secs: None,
+            forwarded_from: None,
         };
 
         let mut store_guard = MESSAGE_STORE.lock().await;
@@ -248,31 +266,13 @@ impl PersistenceService {
     }
 
     pub async fn store_group_message(
-        from_nickname: String,
-        group_name: String,
-        message: String,
-        is_own: bool,
-    ) -> Result<String> {
-        let msg_id = uuid::Uuid::new_v4().to_string();
-        Self::store_group_message_with_id(
-            msg_id.clone(),
-            from_nickname,
-            group_name,
-            message,
-            is_own,
-        )
-        .await?;
-        Ok(msg_id)
-    }
-
-    pub async fn store_group_message_with_id(
         msg_id: String,
         from_nickname: String,
         group_name: String,
         message: String,
         is_own: bool,
     ) -> Result<String> {
-        println!("store_group_message_with_id called - msg_id: {}, from_nickname: {}, group_name: {}, message: {}", msg_id, from_nickname, group_name, message);
+        println!("store_group_message called - msg_id: {}, from_nickname: {}, group_name: {}, message: {}", msg_id, from_nickname, group_name, message);
 
         let stored_msg = StoredMessage {
             id: msg_id.clone(),
@@ -297,6 +297,8 @@ impl PersistenceService {
             sync_attempts: 0,
             last_sync_attempt: None,
             expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+            disappear_after_secs: None,
+            forwarded_from: None,
         };
 
         let mut store_guard = MESSAGE_STORE.lock().await;
@@ -16,6 +16,7 @@
 //! | Peer ID (libp2p) | Ed25519 | `m/44'/60'/2'/0/0` | `derive_peer_id()` |
 //! | Peer Private Key | Ed25519 | `m/44'/60'/2'/0/0` | `derive_peer_private_key()` |
 //! | Group ID | Ed25519 | `m/44'/60'/1'/0/0` | `derive_group_id()` |
+//! | Salted Group ID | Ed25519 | HKDF-SHA256 of name and salt | `derive_salted_group_id()` |
 //!
 //! # Path Structure
 //!
@@ -63,11 +64,13 @@ use bip32::{DerivationPath, XPrv};
 use bip39::Mnemonic;
 use ed25519_compact::KeyPair;
 use hex;
+use hkdf::Hkdf;
 use keccak_hash::keccak;
 use libp2p::{identity, PeerId};
 use rand::rngs::OsRng;
 use rand::RngCore;
 use secp256k1::{PublicKey, Secp256k1};
+use sha2::Sha256;
 
 /// Derive peer_id (Ed25519) from mnemonic using BIP-32 path m/44'/60'/2'/0/0
 /// Returns peer_id_string
@@ -219,6 +222,46 @@ pub fn derive_group_id(mnemonic: &str) -> Result<String> {
     Ok(peer_id.to_string())
 }
 
+/// Derive a group ID from a group name and a secret salt
+///
+/// Like `derive_group_id`, the ID is the peer ID of an Ed25519 key, but the
+/// key seed comes from HKDF-SHA256 over the name, keyed by the salt chosen
+/// by the group's creator. Members need both to compute the ID, so groups
+/// with the same name but different salts never share a topic, and knowing
+/// the name alone is not enough to join.
+///
+/// # Arguments
+///
+/// * `name` - The group's display name
+/// * `salt` - The secret salt chosen by the group's creator
+///
+/// # Returns
+///
+/// Returns `Ok(String)` containing the base58-encoded group ID.
+///
+/// # Example
+///
+/// ```
+/// use gigi_auth::key_derivation::derive_salted_group_id;
+///
+/// let friends = derive_salted_group_id("friends", "s3cret").unwrap();
+/// let others = derive_salted_group_id("friends", "other").unwrap();
+/// assert_ne!(friends, others);
+/// ```
+pub fn derive_salted_group_id(name: &str, salt: &str) -> Result<String> {
+    let hk = Hkdf::<Sha256>::new(Some(salt.as_bytes()), name.as_bytes());
+    let mut seed = [0u8; 32];
+    hk.expand(b"gigi-group-id-v1", &mut seed)
+        .map_err(|e| anyhow::anyhow!("Failed to derive group key: {}", e))?;
+
+    let keypair = identity::ed25519::Keypair::from(
+        identity::ed25519::SecretKey::try_from_bytes(&mut seed)
+            .context("Failed to convert to libp2p keypair")?,
+    );
+    let generic_keypair: identity::Keypair = keypair.into();
+    Ok(PeerId::from_public_key(&generic_keypair.public()).to_string())
+}
+
 /// Derive EVM address from mnemonic using path m/44'/60'/0'/0/0
 /// Returns EVM address string
 ///
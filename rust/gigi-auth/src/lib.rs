@@ -66,10 +66,12 @@
 //! | EVM Address | Secp256k1 | `m/44'/60'/0'/0/0` | [`derive_evm_address()`] |
 //! | Peer ID | Ed25519 | `m/44'/60'/2'/0/0` | [`derive_peer_id()`] |
 //! | Group ID | Ed25519 | `m/44'/60'/1'/0/0` | [`derive_group_id()`] |
+//! | Salted Group ID | Ed25519 | HKDF-SHA256 of name and salt | [`derive_salted_group_id()`] |
 //!
 //! [`derive_evm_address()`]: crate::key_derivation::derive_evm_address
 //! [`derive_peer_id()`]: crate::key_derivation::derive_peer_id
 //! [`derive_group_id()`]: crate::key_derivation::derive_group_id
+//! [`derive_salted_group_id()`]: crate::key_derivation::derive_salted_group_id
 
 pub mod auth_manager;
 pub mod encryption;
@@ -81,5 +83,7 @@ pub mod settings_manager;
 pub use auth_manager::{AccountInfo, AuthManager, LoginResult};
 pub use encryption::{EncryptedAccountData, EncryptionError};
 pub use group_manager::GroupManager;
-pub use key_derivation::{derive_evm_address, derive_group_id, derive_peer_id, generate_mnemonic};
+pub use key_derivation::{
+    derive_evm_address, derive_group_id, derive_peer_id, derive_salted_group_id, generate_mnemonic,
+};
 pub use settings_manager::{GroupInfo, SettingsManager};
@@ -9,7 +9,7 @@
 use gigi_auth::encryption::encrypt_mnemonic;
 use gigi_auth::settings_manager::SettingsManager;
 use gigi_auth::AuthManager;
-use gigi_auth::{derive_evm_address, derive_group_id, derive_peer_id};
+use gigi_auth::{derive_evm_address, derive_group_id, derive_peer_id, derive_salted_group_id};
 use sea_orm::{ConnectionTrait, Database, DatabaseConnection, DbErr, Statement};
 
 /// Helper function to create an in-memory database for testing
@@ -404,3 +404,14 @@ async fn test_verify_password_with_corrupted_data() {
     let result = auth.verify_password(TEST_PASSWORD).await.unwrap();
     assert!(!result);
 }
+
+#[test]
+fn test_salted_group_id_depends_on_salt() {
+    let id = derive_salted_group_id("friends", "salt-a").unwrap();
+
+    // Same inputs always give the same id
+    assert_eq!(id, derive_salted_group_id("friends", "salt-a").unwrap());
+    // Changing either input changes the id
+    assert_ne!(id, derive_salted_group_id("friends", "salt-b").unwrap());
+    assert_ne!(id, derive_salted_group_id("family", "salt-a").unwrap());
+}
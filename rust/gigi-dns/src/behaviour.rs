@@ -13,7 +13,7 @@
 // - Broadcast address changes to all interface tasks
 // - Implement NetworkBehaviour trait for libp2p integration
 
-use crate::interface::{handle_if_event, InterfaceEvent, InterfaceTask};
+use crate::interface::{handle_if_event, InterfaceEvent, InterfaceTask, InterfaceUpdate};
 use crate::types::*;
 use futures::stream::StreamExt;
 use gigi_logging::warn;
@@ -65,8 +65,8 @@ pub struct GigiDnsBehaviour {
     interface_rx: tokio::sync::mpsc::UnboundedReceiver<InterfaceEvent>,
     /// Sender that will be passed to all interface tasks
     interface_tx: tokio::sync::mpsc::UnboundedSender<InterfaceEvent>,
-    /// Senders for address updates to interface tasks
-    address_update_txs: HashMap<IpAddr, tokio::sync::mpsc::UnboundedSender<Vec<Multiaddr>>>,
+    /// Senders for advertised-info updates to interface tasks
+    update_txs: HashMap<IpAddr, tokio::sync::mpsc::UnboundedSender<InterfaceUpdate>>,
     /// Track discovered peers by peer_id for outbound connections (single source of truth)
     discovered_peers: HashMap<PeerId, GigiPeerInfo>,
     /// Rate limiting for Updated events - track last emission time per peer
@@ -122,13 +122,46 @@ impl GigiDnsBehaviour {
             if_tasks,
             interface_rx,
             interface_tx,
-            address_update_txs: HashMap::new(),
+            update_txs: HashMap::new(),
             discovered_peers: HashMap::new(),
             last_updated: HashMap::new(),
             update_interval: Duration::from_secs(1),
         })
     }
 
+    /// Number of network interfaces discovery is currently running on
+    ///
+    /// Zero until the first interfaces are reported by the if-watcher, or when
+    /// no usable interface is up.
+    pub fn active_interface_count(&self) -> usize {
+        self.if_tasks.len()
+    }
+
+    /// Change the advertised nickname at runtime
+    ///
+    /// Every interface announces the new nickname immediately, so peers see
+    /// an `Updated` event without waiting for the next announce interval.
+    ///
+    /// # Arguments
+    /// * `nickname` - New nickname string
+    pub fn update_nickname(&mut self, nickname: String) {
+        self.config.nickname = nickname.clone();
+        for tx in self.update_txs.values() {
+            let _ = tx.send(InterfaceUpdate::Nickname(nickname.clone()));
+        }
+    }
+
+    /// Query for peers on every interface right away
+    ///
+    /// Useful when looking for a peer that was not discovered yet, instead
+    /// of waiting for the next query interval. Responders rate limit their
+    /// answers, so callers should not query in a tight loop.
+    pub fn query_now(&mut self) {
+        for tx in self.update_txs.values() {
+            let _ = tx.send(InterfaceUpdate::Query);
+        }
+    }
+
     /// Spawns a new interface task for the given IP address
     ///
     /// Creates an InterfaceTask that will handle DNS communication on this interface.
@@ -146,8 +179,8 @@ impl GigiDnsBehaviour {
             self.stop_interface_task(interface_ip);
         }
 
-        // Create channel for address updates from main behaviour
-        let (address_update_tx, address_update_rx) = tokio::sync::mpsc::unbounded_channel();
+        // Create channel for advertised-info updates from main behaviour
+        let (update_tx, update_rx) = tokio::sync::mpsc::unbounded_channel();
 
         // Spawn the interface task
         let handle = InterfaceTask::spawn(
@@ -155,13 +188,12 @@ impl GigiDnsBehaviour {
             self.config.clone(),
             self.local_peer_id,
             self.interface_tx.clone(),
-            address_update_rx,
+            update_rx,
         )?;
 
-        // Store the task handle and address update sender
+        // Store the task handle and update sender
         self.if_tasks.insert(interface_ip, handle);
-        self.address_update_txs
-            .insert(interface_ip, address_update_tx);
+        self.update_txs.insert(interface_ip, update_tx);
 
         // Send current addresses if available
         let addrs = match self.listen_addresses.read() {
@@ -177,10 +209,10 @@ impl GigiDnsBehaviour {
         let libp2p_addrs: Vec<Multiaddr> = addrs.iter().cloned().collect();
         if !libp2p_addrs.is_empty() {
             let _ = self
-                .address_update_txs
+                .update_txs
                 .get(&interface_ip)
                 .unwrap()
-                .send(libp2p_addrs);
+                .send(InterfaceUpdate::ListenAddresses(libp2p_addrs));
         }
 
         Ok(())
@@ -196,7 +228,7 @@ impl GigiDnsBehaviour {
         if let Some(handle) = self.if_tasks.remove(&interface_ip) {
             handle.abort();
         }
-        self.address_update_txs.remove(&interface_ip);
+        self.update_txs.remove(&interface_ip);
     }
 }
 
@@ -291,8 +323,8 @@ impl NetworkBehaviour for GigiDnsBehaviour {
         let libp2p_addrs: Vec<Multiaddr> = addrs.iter().cloned().collect();
 
         if !libp2p_addrs.is_empty() {
-            for tx in self.address_update_txs.values() {
-                let _ = tx.send(libp2p_addrs.clone());
+            for tx in self.update_txs.values() {
+                let _ = tx.send(InterfaceUpdate::ListenAddresses(libp2p_addrs.clone()));
             }
         }
     }
@@ -67,8 +67,7 @@ impl From<InterfaceEvent> for GigiDnsEvent {
 pub struct InterfaceTask {
     /// Configuration for this interface
     config: GigiDnsConfig,
-    /// IP address of the interface
-    #[allow(dead_code)]
+    /// IP address of the interface (selects IPv4 or IPv6 multicast)
     interface_ip: IpAddr,
     /// DNS protocol handler
     protocol: GigiDnsProtocol,
@@ -92,12 +91,22 @@ pub struct InterfaceTask {
     first_run: bool,
     /// Handle for background I/O task
     _io_handle: tokio::task::JoinHandle<()>,
-    /// Channel to receive address updates from main behaviour
-    address_update_rx: tokio::sync::mpsc::UnboundedReceiver<Vec<libp2p::Multiaddr>>,
+    /// Channel to receive advertised-info updates from main behaviour
+    update_rx: tokio::sync::mpsc::UnboundedReceiver<InterfaceUpdate>,
     /// Recent query response timestamps for rate limiting
     recent_query_responses: std::collections::VecDeque<std::time::Instant>,
 }
 
+/// Changes to the advertised information pushed from the behaviour to interface tasks
+pub enum InterfaceUpdate {
+    /// New list of libp2p listen addresses
+    ListenAddresses(Vec<libp2p::Multiaddr>),
+    /// New nickname, announced immediately
+    Nickname(String),
+    /// Query for peers immediately instead of at the next query deadline
+    Query,
+}
+
 /// Internal packet type for communication between I/O task and main task
 pub enum InterfacePacket {
     /// Received packet with source address
@@ -115,7 +124,7 @@ impl InterfaceTask {
     /// * `config` - Configuration for DNS behavior
     /// * `local_peer_id` - Our libp2p peer ID
     /// * `event_tx` - Channel for sending events to main behaviour
-    /// * `address_update_rx` - Channel for receiving address updates from main behaviour
+    /// * `update_rx` - Channel for receiving advertised-info updates from main behaviour
     ///
     /// # Returns
     /// - `Ok(JoinHandle<()>)` - Handle for the spawned task
@@ -125,7 +134,7 @@ impl InterfaceTask {
         config: GigiDnsConfig,
         local_peer_id: libp2p_identity::PeerId,
         event_tx: UnboundedSender<InterfaceEvent>,
-        address_update_rx: tokio::sync::mpsc::UnboundedReceiver<Vec<libp2p::Multiaddr>>,
+        update_rx: tokio::sync::mpsc::UnboundedReceiver<InterfaceUpdate>,
     ) -> std::io::Result<tokio::task::JoinHandle<()>> {
         // Create receive socket bound to this interface
         let recv_socket = Self::create_recv_socket(&interface_ip, &config)?;
@@ -197,7 +206,7 @@ impl InterfaceTask {
             has_discovered_peers: false,
             first_run: true,
             _io_handle: io_handle,
-            address_update_rx,
+            update_rx,
             recent_query_responses: VecDeque::new(),
         };
 
@@ -215,17 +224,17 @@ impl InterfaceTask {
     /// on the specified interface.
     ///
     /// # Arguments
-    /// * `interface_ip` - IP address of the network interface
-    /// * `config` - Configuration (for IPv4/IPv6 selection)
+    /// * `interface_ip` - IP address of the network interface (selects IPv4/IPv6)
+    /// * `_config` - Configuration (unused but kept for consistency)
     ///
     /// # Returns
     /// - `Ok(UdpSocket)` - Configured receive socket
     /// - `Err(std::io::Error)` - Failed to create or configure socket
     fn create_recv_socket(
         interface_ip: &IpAddr,
-        config: &GigiDnsConfig,
+        _config: &GigiDnsConfig,
     ) -> std::io::Result<UdpSocket> {
-        let multicast_ip = if config.enable_ipv6 {
+        let multicast_ip = if interface_ip.is_ipv6() {
             std::net::IpAddr::V6(IPV6_MDNS_MULTICAST_ADDRESS)
         } else {
             std::net::IpAddr::V4(IPV4_MDNS_MULTICAST_ADDRESS)
@@ -233,7 +242,7 @@ impl InterfaceTask {
 
         // Bind to INADDR_ANY (0.0.0.0) for receiving multicast
         let bind_addr = SocketAddr::new(
-            if config.enable_ipv6 {
+            if interface_ip.is_ipv6() {
                 std::net::IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED)
             } else {
                 std::net::IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED)
@@ -241,7 +250,7 @@ impl InterfaceTask {
             GIGI_DNS_PORT,
         );
 
-        let domain = if config.enable_ipv6 {
+        let domain = if interface_ip.is_ipv6() {
             socket2::Domain::IPV6
         } else {
             socket2::Domain::IPV4
@@ -251,6 +260,10 @@ impl InterfaceTask {
         socket.set_reuse_address(true)?;
         #[cfg(unix)]
         socket.set_reuse_port(true)?;
+        // Keep IPv6 sockets off the IPv4 port so both families can run side by side
+        if interface_ip.is_ipv6() {
+            socket.set_only_v6(true)?;
+        }
 
         socket.bind(&bind_addr.into())?;
 
@@ -342,7 +355,7 @@ impl InterfaceTask {
         let jitter = Duration::from_millis(rand::random::<u64>() % 100);
         let query_interval = self.get_current_query_interval() + jitter;
 
-        let addr = if self.config.enable_ipv6 {
+        let addr = if self.interface_ip.is_ipv6() {
             SocketAddr::new(
                 std::net::IpAddr::V6(IPV6_MDNS_MULTICAST_ADDRESS),
                 GIGI_DNS_PORT,
@@ -376,7 +389,7 @@ impl InterfaceTask {
             .build_response()
             .map_err(std::io::Error::other)?;
 
-        let addr = if self.config.enable_ipv6 {
+        let addr = if self.interface_ip.is_ipv6() {
             SocketAddr::new(
                 std::net::IpAddr::V6(IPV6_MDNS_MULTICAST_ADDRESS),
                 GIGI_DNS_PORT,
@@ -407,6 +420,11 @@ impl InterfaceTask {
     /// * `packet` - Raw DNS packet bytes
     /// * `src` - Source address of the packet
     fn process_packet(&mut self, packet: &[u8], src: SocketAddr) {
+        // Ignore other services sharing the port
+        if !self.protocol.is_for_service(packet) {
+            return;
+        }
+
         // Check if it's a query and respond
         if self.protocol.is_query(packet) {
             // Rate limiting: check if we're responding too frequently
@@ -501,10 +519,21 @@ impl InterfaceTask {
             };
 
             tokio::select! {
-                // Process address updates - highest priority
-                Some(addresses) = self.address_update_rx.recv() => {
-                    self.protocol.update_listen_addresses(addresses);
-                }
+                // Process advertised-info updates - highest priority
+                Some(update) = self.update_rx.recv() => match update {
+                    InterfaceUpdate::ListenAddresses(addresses) => {
+                        self.protocol.update_listen_addresses(addresses);
+                    }
+                    InterfaceUpdate::Nickname(nickname) => {
+                        self.config.nickname = nickname.clone();
+                        self.protocol.update_nickname(nickname);
+                        // Let peers learn the new nickname without waiting a full interval
+                        self.announce_deadline = Instant::now();
+                    }
+                    InterfaceUpdate::Query => {
+                        self.query_deadline = Instant::now();
+                    }
+                },
                 // Process packets from I/O task - highest priority
                 result = self.multicast_rx.recv() => {
                     match result {
@@ -550,12 +579,12 @@ impl InterfaceTask {
 ///
 /// # Arguments
 /// * `event` - The if-watch event
-/// * `config` - Configuration (for IPv4/IPv6 filtering)
+/// * `config` - Configuration (for IPv6 filtering)
 ///
 /// # Returns
 /// - `Some((ip, true))` - Interface came up
 /// - `Some((ip, false))` - Interface went down
-/// - `None` - Event should be ignored (loopback, wrong IP version, error)
+/// - `None` - Event should be ignored (loopback, IPv6 while disabled, error)
 pub fn handle_if_event(
     event: std::io::Result<IfEvent>,
     config: &GigiDnsConfig,
@@ -569,8 +598,8 @@ pub fn handle_if_event(
                 return None;
             }
 
-            // Filter by IPv4/IPv6 config
-            if addr.is_ipv4() && config.enable_ipv6 || addr.is_ipv6() && !config.enable_ipv6 {
+            // IPv4 is always used, IPv6 only when enabled (dual-stack)
+            if addr.is_ipv6() && !config.enable_ipv6 {
                 return None;
             }
 
@@ -72,7 +72,7 @@ impl GigiDnsProtocol {
     ///
     /// The query follows DNS format (RFC 1035):
     /// - Header: Transaction ID, Flags (query=0x0000), QDCOUNT=1, others=0
-    /// - Question: QNAME="_gigi-dns._udp.local", QTYPE=0x000C (PTR), QCLASS=0x0001 (IN)
+    /// - Question: QNAME=`service_name` (default "_gigi-dns._udp.local"), QTYPE=0x000C (PTR), QCLASS=0x0001 (IN)
     ///
     /// # Returns
     /// Raw bytes of the DNS query packet
@@ -89,7 +89,7 @@ impl GigiDnsProtocol {
         append_u16(&mut packet, 0x0000);
         append_u16(&mut packet, 0x0000);
 
-        append_qname(&mut packet, crate::SERVICE_NAME);
+        append_qname(&mut packet, self.config.service_name.as_bytes());
         append_u16(&mut packet, 0x000C);
         append_u16(&mut packet, 0x0001);
 
@@ -153,7 +153,7 @@ impl GigiDnsProtocol {
             append_u16(&mut packet, 0x0000); // ARCOUNT: 0 additional records
 
             // Answer section: QNAME
-            append_qname(&mut packet, crate::SERVICE_NAME);
+            append_qname(&mut packet, self.config.service_name.as_bytes());
             append_u16(&mut packet, 0x0010); // TYPE: TXT (16)
             append_u16(&mut packet, 0x0001); // CLASS: IN (1)
             append_u32(&mut packet, self.config.ttl.as_secs() as u32); // TTL
@@ -220,6 +220,11 @@ impl GigiDnsProtocol {
             return Err("Packet too short".to_string());
         }
 
+        // Packets of other services are not for us
+        if !self.is_for_service(packet) {
+            return Ok(None);
+        }
+
         // Parse DNS header
         let transaction_id = parse_u16(&packet[0..2]);
         let flags = parse_u16(&packet[2..4]);
@@ -373,6 +378,20 @@ impl GigiDnsProtocol {
         flags & 0x8000 == 0
     }
 
+    /// Checks if a DNS packet is about our configured service name
+    ///
+    /// The first name after the header is the question of a query or the
+    /// answer of a response; it is compared case-insensitively.
+    ///
+    /// # Returns
+    /// `true` if the packet names our service, `false` otherwise
+    pub fn is_for_service(&self, packet: &[u8]) -> bool {
+        let mut expected = Vec::new();
+        append_qname(&mut expected, self.config.service_name.as_bytes());
+        packet.len() >= 12 + expected.len()
+            && packet[12..12 + expected.len()].eq_ignore_ascii_case(&expected)
+    }
+
     /// Processes a discovered peer from a DNS TXT record
     ///
     /// Parses peer information from a GigiDnsRecord and creates a Discovered event.
@@ -44,7 +44,7 @@ pub struct GigiDnsConfig {
     pub announce_interval: Duration,
     /// Interval for cleanup operations (min: 10s, max: 5min)
     pub cleanup_interval: Duration,
-    /// Enable IPv6 multicast (disabled by default)
+    /// Enable IPv6 multicast alongside IPv4 (disabled by default)
     pub enable_ipv6: bool,
     /// List of capabilities this peer provides (e.g., "file-sharing", "chat")
     pub capabilities: Vec<String>,
@@ -52,6 +52,9 @@ pub struct GigiDnsConfig {
     pub metadata: HashMap<String, String>,
     /// Use localhost unicast instead of multicast for testing
     pub use_localhost: bool,
+    /// DNS name peers are discovered under; only peers using the same name
+    /// discover each other, so apps can keep their discovery apart
+    pub service_name: String,
 }
 
 impl Default for GigiDnsConfig {
@@ -66,6 +69,7 @@ impl Default for GigiDnsConfig {
             capabilities: Vec::new(),
             metadata: HashMap::new(),
             use_localhost: false,
+            service_name: String::from_utf8_lossy(crate::SERVICE_NAME).into_owned(),
         };
         config.validate().expect("Default config should be valid");
         config
@@ -91,6 +95,10 @@ impl GigiDnsConfig {
     const MAX_CLEANUP_INTERVAL: Duration = Duration::from_secs(5 * 60);
     /// Maximum nickname length: 64 characters
     const MAX_NICKNAME_LENGTH: usize = 64;
+    /// Maximum length of one service name label: 63 bytes
+    const MAX_SERVICE_LABEL_LENGTH: usize = 63;
+    /// Maximum service name length: 253 bytes
+    const MAX_SERVICE_NAME_LENGTH: usize = 253;
 
     /// Validates the configuration parameters
     ///
@@ -177,6 +185,29 @@ impl GigiDnsConfig {
             ));
         }
 
+        // Validate service_name
+        let service_name = self.service_name.trim_end_matches('.');
+        if service_name.is_empty() {
+            return Err("Service name cannot be empty".to_string());
+        }
+        if service_name.len() > Self::MAX_SERVICE_NAME_LENGTH {
+            return Err(format!(
+                "Service name too long: {} bytes (max: {})",
+                service_name.len(),
+                Self::MAX_SERVICE_NAME_LENGTH
+            ));
+        }
+        if service_name
+            .split('.')
+            .any(|label| label.is_empty() || label.len() > Self::MAX_SERVICE_LABEL_LENGTH)
+        {
+            return Err(format!(
+                "Invalid service name: {} (labels must be 1 to {} bytes)",
+                self.service_name,
+                Self::MAX_SERVICE_LABEL_LENGTH
+            ));
+        }
+
         Ok(())
     }
 }
@@ -322,3 +322,42 @@ fn test_query_response_roundtrip() {
         }
     }
 }
+
+#[test]
+fn test_different_service_names_do_not_discover_each_other() {
+    let peer_id1 = PeerId::random();
+    let mut config1 = GigiDnsConfig::default();
+    config1.nickname = "Peer1".to_string();
+    config1.service_name = "_app-one._udp.local".to_string();
+
+    let peer_id2 = PeerId::random();
+    let mut config2 = GigiDnsConfig::default();
+    config2.nickname = "Peer2".to_string();
+    config2.service_name = "_app-two._udp.local".to_string();
+
+    let mut protocol1 = GigiDnsProtocol::new(peer_id1, config1);
+    let mut protocol2 = GigiDnsProtocol::new(peer_id2, config2.clone());
+    protocol1.update_listen_addresses(vec!["/ip4/192.168.1.10/tcp/7174".parse().unwrap()]);
+    protocol2.update_listen_addresses(vec!["/ip4/192.168.1.11/tcp/7174".parse().unwrap()]);
+
+    // Neither answers nor accepts the other's packets
+    let query = protocol1.build_query();
+    assert!(protocol1.is_for_service(&query));
+    assert!(!protocol2.is_for_service(&query));
+    for response in protocol2.build_response().unwrap() {
+        assert!(matches!(protocol1.handle_packet(&response), Ok(None)));
+    }
+    for response in protocol1.build_response().unwrap() {
+        assert!(matches!(protocol2.handle_packet(&response), Ok(None)));
+    }
+
+    // A peer of the same service still discovers peer 2, regardless of case
+    let mut config3 = config2;
+    config3.service_name = "_APP-TWO._udp.local.".to_string();
+    let mut protocol3 = GigiDnsProtocol::new(PeerId::random(), config3);
+    let response = &protocol2.build_response().unwrap()[0];
+    match protocol3.handle_packet(response) {
+        Ok(Some(GigiDnsEvent::Discovered(info))) => assert_eq!(info.peer_id, peer_id2),
+        other => panic!("Expected Discovered event, got {:?}", other),
+    }
+}
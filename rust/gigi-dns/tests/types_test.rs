@@ -100,6 +100,22 @@ fn test_gigidnsconfig_validate_cleanup_interval_too_long() {
     assert!(config.validate().is_err());
 }
 
+#[test]
+fn test_gigidnsconfig_validate_service_name() {
+    let mut config = GigiDnsConfig::default();
+    assert_eq!(config.service_name, "_gigi-dns._udp.local");
+
+    config.service_name = "_my-app._udp.local.".to_string();
+    assert!(config.validate().is_ok());
+
+    config.service_name = String::new();
+    assert!(config.validate().is_err());
+    config.service_name = "_my-app..local".to_string();
+    assert!(config.validate().is_err());
+    config.service_name = format!("{}._udp.local", "a".repeat(64));
+    assert!(config.validate().is_err());
+}
+
 #[test]
 fn test_gigidnsrecord_encode_basic() {
     let record = GigiDnsRecord {
@@ -34,7 +34,7 @@ async fn main() -> anyhow::Result<()> {
     }
 
     // Generate a share code for a filename
-    let generated_code = manager.generate_share_code("example.txt");
+    let generated_code = manager.generate_share_code("example.txt")?;
     println!("\nGenerated share code: {}", generated_code);
 
     // Calculate file hash
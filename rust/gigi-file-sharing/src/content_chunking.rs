@@ -0,0 +1,112 @@
+//! Content-defined chunk boundaries
+//!
+//! Fixed size chunks start every `CHUNK_SIZE` bytes, so inserting a byte
+//! near the start of a file shifts every later chunk and none of them match
+//! the old file any more. Content-defined chunking places boundaries where a
+//! rolling Gear hash of the last 64 bytes matches a mask, as in FastCDC, so
+//! boundaries move with the content and an edit only changes the chunks
+//! around it.
+//!
+//! Chunks are between `CDC_MIN_CHUNK_SIZE` and `CDC_MAX_CHUNK_SIZE` bytes,
+//! about `CDC_AVG_CHUNK_SIZE` on average. A stricter mask before the average
+//! size and a looser one after it keep sizes close to the average.
+
+use crate::CHUNK_SIZE;
+use std::io::Read;
+use std::path::Path;
+
+/// Smallest content-defined chunk, except for the last chunk of a file
+pub const CDC_MIN_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Average content-defined chunk size, the same as fixed size chunks
+pub const CDC_AVG_CHUNK_SIZE: usize = CHUNK_SIZE;
+
+/// Largest content-defined chunk; a boundary is forced after this many bytes
+pub const CDC_MAX_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Mask checked before `CDC_AVG_CHUNK_SIZE`, two bits stricter than average
+const MASK_SMALL: u64 = !0 << (64 - (CDC_AVG_CHUNK_SIZE.trailing_zeros() + 2));
+
+/// Mask checked after `CDC_AVG_CHUNK_SIZE`, two bits looser than average
+const MASK_LARGE: u64 = !0 << (64 - (CDC_AVG_CHUNK_SIZE.trailing_zeros() - 2));
+
+/// Random value per byte for the Gear hash, fixed so every peer finds the
+/// same boundaries
+const GEAR: [u64; 256] = gear_table();
+
+/// Fill the Gear table from a SplitMix64 sequence
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x6769_6769_6364_6331;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Find the start offset of each content-defined chunk in `reader`
+///
+/// # Returns
+///
+/// The offsets in increasing order, starting with 0; empty for empty input
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # fn example() -> std::io::Result<()> {
+/// use gigi_file_sharing::content_defined_offsets;
+///
+/// let data = vec![7u8; 3 * 1024 * 1024];
+/// let offsets = content_defined_offsets(&data[..])?;
+/// assert_eq!(offsets[0], 0);
+/// # Ok(())
+/// # }
+/// ```
+pub fn content_defined_offsets<R: Read>(mut reader: R) -> std::io::Result<Vec<u64>> {
+    let mut offsets = Vec::new();
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut position = 0u64;
+    let mut chunk_len = 0usize;
+    let mut hash = 0u64;
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for &byte in &buffer[..read] {
+            if chunk_len == 0 {
+                offsets.push(position);
+            }
+            position += 1;
+            chunk_len += 1;
+            hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+            if chunk_len < CDC_MIN_CHUNK_SIZE {
+                continue;
+            }
+            let mask = if chunk_len < CDC_AVG_CHUNK_SIZE {
+                MASK_SMALL
+            } else {
+                MASK_LARGE
+            };
+            if hash & mask == 0 || chunk_len >= CDC_MAX_CHUNK_SIZE {
+                chunk_len = 0;
+                hash = 0;
+            }
+        }
+    }
+    Ok(offsets)
+}
+
+/// Find the content-defined chunk offsets of a file on disk
+pub(crate) fn file_offsets(path: &Path) -> std::io::Result<Vec<u64>> {
+    content_defined_offsets(std::fs::File::open(path)?)
+}
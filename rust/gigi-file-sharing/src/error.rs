@@ -19,6 +19,21 @@ use thiserror::Error;
 /// ## InvalidUri
 /// Returned when parsing a malformed content URI string.
 ///
+/// ## InvalidShareCodeLength
+/// Returned when configuring a share code length outside the supported range.
+///
+/// ## InvalidHashConcurrency
+/// Returned when configuring a limit of 0 concurrent file hashes.
+///
+/// ## FileChanged
+/// Returned when re-sharing a changed file under `ShareOverwritePolicy::Reject`.
+///
+/// ## FileUnstable
+/// Returned when the stability check finds a file still being written.
+///
+/// ## ShareCodesExhausted
+/// Returned when every candidate share code for a new share is in use.
+///
 /// ## IoError
 /// Propagated from underlying filesystem I/O operations (reading files, etc.).
 ///
@@ -69,6 +84,33 @@ pub enum FileSharingError {
     #[error("Invalid URI: {0}")]
     InvalidUri(String),
 
+    /// Share code length outside `MIN_SHARE_CODE_LENGTH..=MAX_SHARE_CODE_LENGTH`
+    #[error("Share code length must be between 6 and 32 characters, got {0}")]
+    InvalidShareCodeLength(usize),
+
+    /// Limit of concurrent file hashes that allows none
+    #[error("Maximum concurrent hashes must be at least 1, got {0}")]
+    InvalidHashConcurrency(usize),
+
+    /// A shared file changed and `ShareOverwritePolicy::Reject` is set
+    ///
+    /// Holds the share code the file is still shared under with its old
+    /// content.
+    #[error("File shared under code {0} has changed")]
+    FileChanged(String),
+
+    /// A file's size or modification time changed between the two samples
+    /// of the stability check, so another process is still writing it
+    #[error("File is still being written: {0}")]
+    FileUnstable(PathBuf),
+
+    /// Every candidate share code for a new share is already in use
+    ///
+    /// Holds the number of candidates tried. Only likely with very short
+    /// share codes and a large library.
+    #[error("No unused share code found after {0} attempts")]
+    ShareCodesExhausted(u32),
+
     /// I/O error from filesystem operations
     ///
     /// Propagated from:
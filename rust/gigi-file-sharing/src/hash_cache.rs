@@ -0,0 +1,119 @@
+//! Cache of whole-file hashes
+//!
+//! Hashing a multi-gigabyte file takes long enough that the app may be
+//! backgrounded or killed before it finishes. Neither hasher state can be
+//! persisted portably, so instead of resuming a partial hash the final hash
+//! is cached, keyed by path, size, modification time and algorithm. A file
+//! that is shared again unchanged is then not read at all.
+//!
+//! Entries of shared files are persisted with the share (`modified_at`) and
+//! restored by `FileSharingManager::load_from_store`, so the cache survives
+//! restarts.
+
+use crate::types::HashAlgo;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Files modified this recently are not cached
+///
+/// Filesystems update mtimes with coarse granularity, so a same-size write
+/// right after hashing could keep the mtime the hash was cached under.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Hash cache hit and miss counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HashCacheStats {
+    /// Hashes served from the cache without reading the file
+    pub hits: u64,
+    /// Files that had to be hashed
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone)]
+struct HashCacheEntry {
+    size: u64,
+    modified: SystemTime,
+    algo: HashAlgo,
+    hash: String,
+}
+
+/// Final file hashes keyed by path, valid while size and mtime are unchanged
+#[derive(Debug, Default)]
+pub(crate) struct HashCache {
+    entries: HashMap<PathBuf, HashCacheEntry>,
+    stats: HashCacheStats,
+}
+
+impl HashCache {
+    /// Cached hash of `path`, if its size and mtime still match
+    pub(crate) fn get(
+        &mut self,
+        path: &Path,
+        size: u64,
+        modified: SystemTime,
+        algo: HashAlgo,
+    ) -> Option<String> {
+        let hash = self
+            .entries
+            .get(path)
+            .filter(|entry| entry.size == size && entry.modified == modified && entry.algo == algo)
+            .map(|entry| entry.hash.clone());
+        if hash.is_some() {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+        hash
+    }
+
+    /// Remember the hash of `path` at the given size and mtime
+    ///
+    /// Ignored for files modified within the last `RACY_WINDOW`.
+    pub(crate) fn insert(
+        &mut self,
+        path: PathBuf,
+        size: u64,
+        modified: SystemTime,
+        algo: HashAlgo,
+        hash: String,
+    ) {
+        let settled = SystemTime::now()
+            .duration_since(modified)
+            .is_ok_and(|age| age >= RACY_WINDOW);
+        if !settled {
+            self.entries.remove(&path);
+            return;
+        }
+        self.entries.insert(
+            path,
+            HashCacheEntry {
+                size,
+                modified,
+                algo,
+                hash,
+            },
+        );
+    }
+
+    /// Modification time the cached hash of `path` was computed at
+    pub(crate) fn modified(&self, path: &Path) -> Option<SystemTime> {
+        self.entries.get(path).map(|entry| entry.modified)
+    }
+
+    pub(crate) fn stats(&self) -> HashCacheStats {
+        self.stats
+    }
+}
+
+/// Modification time as nanoseconds since the Unix epoch, for persistence
+pub(crate) fn modified_to_nanos(modified: SystemTime) -> Option<i64> {
+    let nanos = modified.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    i64::try_from(nanos).ok()
+}
+
+/// Inverse of `modified_to_nanos`
+pub(crate) fn modified_from_nanos(nanos: i64) -> Option<SystemTime> {
+    let nanos = u64::try_from(nanos).ok()?;
+    Some(UNIX_EPOCH + Duration::from_nanos(nanos))
+}
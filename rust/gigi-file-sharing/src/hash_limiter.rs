@@ -0,0 +1,110 @@
+//! Bound on whole-file hashes computed at once
+//!
+//! Sharing many files at once, through directory shares or quick repeated
+//! UI actions, would otherwise hash them all in parallel and thrash disk and
+//! CPU, which hurts most on mobile. Each hash runs on the blocking thread
+//! pool while holding a permit of a semaphore. Managers that should share
+//! one bound use clones of the same `HashLimiter`.
+
+use crate::error::FileSharingError;
+use crate::types::HashAlgo;
+use anyhow::Result;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Default number of files hashed at once; mobile devices hash one at a time
+#[cfg(any(target_os = "android", target_os = "ios"))]
+pub const DEFAULT_MAX_CONCURRENT_HASHES: usize = 1;
+
+/// Default number of files hashed at once; mobile devices hash one at a time
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub const DEFAULT_MAX_CONCURRENT_HASHES: usize = 4;
+
+#[derive(Debug, Default)]
+struct HashCounters {
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+/// Counts a hash as in flight until dropped
+struct InFlightGuard(Arc<HashCounters>);
+
+impl InFlightGuard {
+    fn new(counters: Arc<HashCounters>) -> Self {
+        let in_flight = counters.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        counters
+            .peak_in_flight
+            .fetch_max(in_flight, Ordering::SeqCst);
+        Self(counters)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Limit on concurrent whole-file hashes, shared by its clones
+#[derive(Debug, Clone)]
+pub struct HashLimiter {
+    semaphore: Arc<Semaphore>,
+    max_concurrent: usize,
+    counters: Arc<HashCounters>,
+}
+
+impl HashLimiter {
+    /// Create a limiter allowing `max_concurrent` hashes at once
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidHashConcurrency`: If `max_concurrent` is 0
+    pub fn new(max_concurrent: usize) -> Result<Self> {
+        if max_concurrent == 0 {
+            return Err(FileSharingError::InvalidHashConcurrency(max_concurrent).into());
+        }
+        Ok(Self {
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            counters: Arc::default(),
+        })
+    }
+
+    /// Hashes allowed at once
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    /// Hashes currently running
+    pub fn in_flight(&self) -> usize {
+        self.counters.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Most hashes that ran at once since the limiter was created
+    pub fn peak_in_flight(&self) -> usize {
+        self.counters.peak_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Hash a file once a permit is free
+    ///
+    /// The permit is held by the blocking task, so a hash keeps counting
+    /// against the limit even if the caller stops waiting for it.
+    pub(crate) async fn hash_file(&self, path: PathBuf, algo: HashAlgo) -> Result<String> {
+        let permit = self.semaphore.clone().acquire_owned().await?;
+        let counters = self.counters.clone();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let _in_flight = InFlightGuard::new(counters);
+            algo.hash_file(&path)
+        })
+        .await?
+    }
+}
+
+impl Default for HashLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_HASHES).expect("Default hash concurrency is non-zero")
+    }
+}
@@ -47,6 +47,12 @@
 //! chunk_count = ceil(1,048,576 / 262,144) = 4 chunks
 //! ```
 //!
+//! With content-defined chunking enabled, chunk boundaries follow the file's
+//! content instead (see `content_chunking`), so editing a large file only
+//! changes the chunks around the edit. The chunk start offsets are sent in
+//! `FileInfo::chunk_offsets`; recipients that predate them cannot download
+//! such files correctly.
+//!
 //! ## Platform Support
 //!
 //! ### Desktop (Linux, macOS, Windows)
@@ -74,6 +80,8 @@
 //! When configured with a `FileSharingStore`, the manager persists:
 //! - Share code mappings
 //! - File metadata (name, size, hash, chunk count)
+//! - The modification time each hash was computed at, so unchanged files
+//!   are not re-hashed when shared again after a restart
 //! - Thumbnail paths (for preview images)
 //! - Revocation status
 //!
@@ -122,25 +130,40 @@
 //! - `FileSharingManager` is not thread-safe internally
 //! - Use external synchronization if sharing files from multiple threads
 //! - `FileSharingStore` operations are wrapped in Arc for thread-safe access
+//! - Whole-file hashes run on the blocking thread pool, bounded by a
+//!   `HashLimiter` that several managers can share
 
+mod content_chunking;
 pub mod error;
+mod hash_cache;
+mod hash_limiter;
 pub mod types;
 
 // Re-export types for convenience
+pub use content_chunking::{
+    content_defined_offsets, CDC_AVG_CHUNK_SIZE, CDC_MAX_CHUNK_SIZE, CDC_MIN_CHUNK_SIZE,
+};
 pub use error::FileSharingError;
-pub use types::{FileInfo, FilePath, SharedFile};
+pub use gigi_store::ShareStats;
+pub use hash_cache::HashCacheStats;
+pub use hash_limiter::{HashLimiter, DEFAULT_MAX_CONCURRENT_HASHES};
+pub use types::{
+    DirectoryShare, FileInfo, FilePath, HashAlgo, ShareCancelToken, ShareCodeConflict,
+    ShareOverwritePolicy, SharedFile,
+};
 
 use anyhow::Result;
 use blake3::Hasher;
-use gigi_logging::{error, info};
+use gigi_logging::{error, info, warn};
 use std::collections::HashMap;
-use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::fs;
+use tokio::task::JoinHandle;
 use url::Url;
 
 use gigi_store::FileSharingStore;
+use hash_cache::HashCache;
 
 /// Size of each file chunk in bytes (256KB)
 ///
@@ -188,6 +211,78 @@ pub const CHUNK_SIZE: usize = 256 * 1024;
 /// ```
 pub type FileChunkReader = Arc<dyn Fn(&FilePath, u64, usize) -> Result<Vec<u8>> + Send + Sync>;
 
+/// Time source used when generating share codes
+///
+/// Defaults to `SystemTime::now`. Tests can supply a fixed clock to make
+/// share codes deterministic and exercise collision handling.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gigi_file_sharing::FileSharingManager;
+/// use std::sync::Arc;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let manager = FileSharingManager::new()
+///     .with_clock(Arc::new(|| UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+/// ```
+pub type Clock = Arc<dyn Fn() -> std::time::SystemTime + Send + Sync>;
+
+/// Maximum attempts to find an unused share code before giving up
+const MAX_SHARE_CODE_ATTEMPTS: u32 = 16;
+
+/// Default share code length in hex characters (32 bits)
+pub const DEFAULT_SHARE_CODE_LENGTH: usize = 8;
+
+/// Shortest configurable share code length in hex characters
+pub const MIN_SHARE_CODE_LENGTH: usize = 6;
+
+/// Longest configurable share code length in hex characters
+pub const MAX_SHARE_CODE_LENGTH: usize = 32;
+
+/// Whether `share_code` has the format of a generated share code
+///
+/// Checks the format only, not whether any peer shares the code: between
+/// `MIN_SHARE_CODE_LENGTH` and `MAX_SHARE_CODE_LENGTH` lowercase hex
+/// characters, so codes of any configurable length are accepted.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gigi_file_sharing::is_valid_share_code;
+///
+/// assert!(is_valid_share_code("a1b2c3d4"));
+/// assert!(!is_valid_share_code("a1b2"));
+/// assert!(!is_valid_share_code("A1B2C3D4"));
+/// ```
+pub fn is_valid_share_code(share_code: &str) -> bool {
+    (MIN_SHARE_CODE_LENGTH..=MAX_SHARE_CODE_LENGTH).contains(&share_code.len())
+        && share_code
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
+
+/// Clean up a share code typed or pasted by a user
+///
+/// Trims surrounding whitespace and lowercases hex letters.
+///
+/// # Returns
+///
+/// The normalized code, or `None` if it is still not a valid share code
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gigi_file_sharing::normalize_share_code;
+///
+/// assert_eq!(normalize_share_code(" A1B2C3D4\n"), Some("a1b2c3d4".to_string()));
+/// assert_eq!(normalize_share_code("a1b2-c3d4"), None);
+/// ```
+pub fn normalize_share_code(share_code: &str) -> Option<String> {
+    let normalized = share_code.trim().to_ascii_lowercase();
+    is_valid_share_code(&normalized).then_some(normalized)
+}
+
 /// File sharing manager
 ///
 /// Manages file sharing operations including:
@@ -201,6 +296,7 @@ pub type FileChunkReader = Arc<dyn Fn(&FilePath, u64, usize) -> Result<Vec<u8>>
 /// - `shared_files`: In-memory mapping of share_code → SharedFile
 /// - `chunk_reader`: Optional callback for reading chunks from URIs
 /// - `file_sharing_store`: Optional persistent storage backend
+/// - `clock`: Time source for share code generation
 ///
 /// # Example
 ///
@@ -222,6 +318,24 @@ pub struct FileSharingManager {
     chunk_reader: Option<FileChunkReader>,
     /// Persistent storage backend (optional, from gigi-store)
     file_sharing_store: Option<Arc<FileSharingStore>>,
+    /// Time source for share code generation
+    clock: Clock,
+    /// Algorithm for whole-file hashes of newly shared files
+    hash_algo: HashAlgo,
+    /// Hashes of unchanged files, so re-sharing them skips hashing
+    hash_cache: HashCache,
+    /// Hex characters of the BLAKE3 output used for new share codes
+    share_code_length: usize,
+    /// Background store writes not known to be finished, awaited by `flush_store`
+    pending_writes: Mutex<Vec<JoinHandle<()>>>,
+    /// What re-sharing a changed file does to its share code
+    overwrite_policy: ShareOverwritePolicy,
+    /// Bound on files hashed at once, possibly shared with other managers
+    hash_limiter: HashLimiter,
+    /// Delay between the two samples of the stability check, `None` to skip it
+    stability_check: Option<std::time::Duration>,
+    /// Whether newly shared files get content-defined chunk boundaries
+    content_defined_chunking: bool,
 }
 
 impl FileSharingManager {
@@ -246,6 +360,15 @@ impl FileSharingManager {
             shared_files: HashMap::new(),
             chunk_reader: None,
             file_sharing_store: None,
+            clock: Arc::new(std::time::SystemTime::now),
+            hash_algo: HashAlgo::default(),
+            hash_cache: HashCache::default(),
+            share_code_length: DEFAULT_SHARE_CODE_LENGTH,
+            pending_writes: Mutex::new(Vec::new()),
+            overwrite_policy: ShareOverwritePolicy::default(),
+            hash_limiter: HashLimiter::default(),
+            stability_check: None,
+            content_defined_chunking: false,
         }
     }
 
@@ -277,6 +400,37 @@ impl FileSharingManager {
         self
     }
 
+    /// Replace the time source used for share code generation
+    ///
+    /// # Arguments
+    ///
+    /// * `clock` - A callback returning the current time
+    ///
+    /// # Returns
+    ///
+    /// Self (builder pattern for chaining)
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Share a limit on concurrent file hashes with other managers
+    ///
+    /// Hashes started by any manager holding a clone of `limiter` count
+    /// against the same limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `limiter` - The limiter to hash files through
+    ///
+    /// # Returns
+    ///
+    /// Self (builder pattern for chaining)
+    pub fn with_hash_limiter(mut self, limiter: HashLimiter) -> Self {
+        self.hash_limiter = limiter;
+        self
+    }
+
     /// Set the chunk reader callback for URI-based files
     ///
     /// # Arguments
@@ -309,6 +463,138 @@ impl FileSharingManager {
         self.chunk_reader = Some(reader);
     }
 
+    /// Set the whole-file hash algorithm for files shared from now on
+    ///
+    /// Already shared files keep the algorithm they were hashed with, and
+    /// recipients verify against the algorithm in each `FileInfo`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash_algo` - `HashAlgo::Sha256` (default) or the faster `HashAlgo::Blake3`
+    pub fn set_hash_algo(&mut self, hash_algo: HashAlgo) {
+        self.hash_algo = hash_algo;
+    }
+
+    /// Whole-file hash algorithm used for newly shared files
+    pub fn hash_algo(&self) -> HashAlgo {
+        self.hash_algo
+    }
+
+    /// Set the length of share codes generated from now on
+    ///
+    /// Longer codes are harder to type but collide less often, e.g. 12+
+    /// characters for large deployments. Existing codes keep their length.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - Hex characters, between `MIN_SHARE_CODE_LENGTH` and
+    ///   `MAX_SHARE_CODE_LENGTH`
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidShareCodeLength`: If `length` is out of range
+    pub fn set_share_code_length(&mut self, length: usize) -> Result<()> {
+        if !(MIN_SHARE_CODE_LENGTH..=MAX_SHARE_CODE_LENGTH).contains(&length) {
+            return Err(FileSharingError::InvalidShareCodeLength(length).into());
+        }
+        self.share_code_length = length;
+        Ok(())
+    }
+
+    /// Length of newly generated share codes in hex characters
+    pub fn share_code_length(&self) -> usize {
+        self.share_code_length
+    }
+
+    /// Set what `share_file` does when a shared file's content changed
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - `ShareOverwritePolicy::KeepCode` (default), `NewCode` or
+    ///   `Reject`
+    pub fn set_overwrite_policy(&mut self, policy: ShareOverwritePolicy) {
+        self.overwrite_policy = policy;
+    }
+
+    /// What `share_file` does when a shared file's content changed
+    pub fn overwrite_policy(&self) -> ShareOverwritePolicy {
+        self.overwrite_policy
+    }
+
+    /// Set how many files `share_file` hashes at once
+    ///
+    /// Replaces the limiter with a new one, so a limiter set with
+    /// `with_hash_limiter` is no longer shared. Hashes already running keep
+    /// counting against the old limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_concurrent` - Hashes allowed at once, at least 1
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidHashConcurrency`: If `max_concurrent` is 0
+    pub fn set_max_concurrent_hashes(&mut self, max_concurrent: usize) -> Result<()> {
+        self.hash_limiter = HashLimiter::new(max_concurrent)?;
+        Ok(())
+    }
+
+    /// How many files `share_file` hashes at once
+    pub fn max_concurrent_hashes(&self) -> usize {
+        self.hash_limiter.max_concurrent()
+    }
+
+    /// Make `share_file` refuse files that are still being written
+    ///
+    /// With a delay set, a file's size and modification time are sampled
+    /// twice, `delay` apart, before it is hashed; a file that changed in
+    /// between fails with `FileUnstable` instead of being shared with a
+    /// snapshot that is stale right away. Every share then takes `delay`
+    /// longer. Off by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - Time between the samples, `None` to skip the check
+    pub fn set_stability_check(&mut self, delay: Option<std::time::Duration>) {
+        self.stability_check = delay;
+    }
+
+    /// Delay between the samples of the stability check, if enabled
+    pub fn stability_check(&self) -> Option<std::time::Duration> {
+        self.stability_check
+    }
+
+    /// Split files shared from now on at content-defined boundaries
+    ///
+    /// Chunk boundaries then follow the content, so after a large file is
+    /// edited and shared again most chunks stay the same, and downloads of
+    /// it can resume and deduplicate. Sharing reads the file once more to
+    /// find the boundaries. Only files on disk are chunked this way; content
+    /// URIs keep `CHUNK_SIZE` chunks. Off by default, as recipients that
+    /// predate `FileInfo::chunk_offsets` cannot download such files.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` for content-defined chunks, `false` for `CHUNK_SIZE` chunks
+    pub fn set_content_defined_chunking(&mut self, enabled: bool) {
+        self.content_defined_chunking = enabled;
+    }
+
+    /// Whether newly shared files get content-defined chunk boundaries
+    pub fn content_defined_chunking(&self) -> bool {
+        self.content_defined_chunking
+    }
+
+    /// The limiter bounding concurrent file hashes
+    pub fn hash_limiter(&self) -> &HashLimiter {
+        &self.hash_limiter
+    }
+
+    /// How often sharing reused a cached file hash instead of hashing
+    pub fn hash_cache_stats(&self) -> HashCacheStats {
+        self.hash_cache.stats()
+    }
+
     /// Generate a unique share code for a file
     ///
     /// # Arguments
@@ -317,7 +603,7 @@ impl FileSharingManager {
     ///
     /// # Returns
     ///
-    /// An 8-character hexadecimal share code
+    /// A hexadecimal share code of the configured length, 8 characters by default
     ///
     /// # Algorithm
     ///
@@ -325,7 +611,14 @@ impl FileSharingManager {
     /// 1. Filename bytes (provides determinism for same file names)
     /// 2. Current timestamp in nanoseconds (ensures uniqueness)
     ///
-    /// The first 8 hex characters (32 bits) are used as the share code.
+    /// The first `share_code_length` hex characters (32 bits by default) are
+    /// used as the share code.
+    /// If the code is already in use, the hash is salted with a retry
+    /// counter until an unused code is found.
+    ///
+    /// # Errors
+    ///
+    /// - `ShareCodesExhausted`: If every salted candidate is already in use
     ///
     /// # Collision Probability
     ///
@@ -333,6 +626,7 @@ impl FileSharingManager {
     /// - 4,294,967,296 possible values
     /// - ~0.000023% collision chance with 1M simultaneous shares
     /// - Timestamp adds additional uniqueness
+    /// - Every extra character multiplies the possible values by 16
     ///
     /// # Example
     ///
@@ -342,18 +636,31 @@ impl FileSharingManager {
     /// BLAKE3 Hash: "a1b2c3d4e5f67890..."
     /// Share Code: "a1b2c3d4"
     /// ```
-    pub fn generate_share_code(&self, filename: &str) -> String {
+    pub fn generate_share_code(&self, filename: &str) -> Result<String> {
+        let timestamp = (self.clock)()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        let length = self.share_code_length;
+        (0..MAX_SHARE_CODE_ATTEMPTS)
+            .map(|attempt| Self::derive_share_code(filename, timestamp, attempt, length))
+            .find(|share_code| !self.shared_files.contains_key(share_code))
+            .ok_or_else(|| FileSharingError::ShareCodesExhausted(MAX_SHARE_CODE_ATTEMPTS).into())
+    }
+
+    /// Derive a share code of `length` hex characters from a filename,
+    /// timestamp and retry attempt
+    fn derive_share_code(filename: &str, timestamp: u128, attempt: u32, length: usize) -> String {
         let mut hasher = Hasher::new();
         hasher.update(filename.as_bytes());
-        hasher.update(
-            &std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_nanos()
-                .to_le_bytes(),
-        );
+        hasher.update(&timestamp.to_le_bytes());
+        // Salt retries so a colliding code is never regenerated
+        if attempt > 0 {
+            hasher.update(&attempt.to_le_bytes());
+        }
 
-        format!("{}", hasher.finalize().to_hex())[..8].to_string()
+        format!("{}", hasher.finalize().to_hex())[..length].to_string()
     }
 
     /// Share a file from the filesystem
@@ -368,21 +675,31 @@ impl FileSharingManager {
     ///
     /// # Process
     ///
-    /// 1. Canonicalize the path (falls back on error for URIs)
-    /// 2. Verify file exists and is accessible
+    /// 1. Hand URIs such as `content://...` to `share_content_uri`, see below
+    /// 2. Canonicalize the path and verify the file exists and is accessible
     /// 3. Extract filename from path
     /// 4. Calculate SHA256 hash of the entire file
     /// 5. Check if file is already shared:
     ///    - If unchanged hash: Return existing share code
-    ///    - If changed: Update metadata with new hash
+    ///    - If changed: Apply the `ShareOverwritePolicy`, by default update
+    ///      metadata with new hash under the existing share code
     ///    - If new: Create new share entry
     /// 6. Calculate chunk count based on file size
     /// 7. Save metadata to persistent storage (if configured)
     /// 8. Return share code
     ///
+    /// # URIs
+    ///
+    /// A path that is really a URI (`scheme://...`) is never canonicalized.
+    /// It is shared like `share_content_uri` with the last path segment as
+    /// its name and the size measured through the chunk reader.
+    ///
     /// # Errors
     ///
     /// - `FileNotFound`: If the file doesn't exist
+    /// - `FileChanged`: If the file changed under `ShareOverwritePolicy::Reject`
+    /// - `FileUnstable`: If the stability check finds the file still changing,
+    ///   see `set_stability_check`
     /// - `IoError`: If file cannot be read
     /// - `SerializationError`: If metadata cannot be serialized
     ///
@@ -397,7 +714,32 @@ impl FileSharingManager {
     /// println!("Share code: {}", code);
     /// ```
     pub async fn share_file(&mut self, file_path: &Path) -> Result<String> {
-        // Try canonicalize, but fall back to original path if it fails (Android content URIs)
+        self.share_path(file_path, true).await
+    }
+
+    /// Share a file without computing its whole-file hash
+    ///
+    /// For transfers between a user's own devices, where hashing a large
+    /// file costs more than it protects. The `FileInfo` has an empty `hash`,
+    /// so recipients do not verify the whole file; chunk hashes still catch
+    /// corruption in transit. Unhashed and hashed shares of the same file
+    /// get separate share codes. URIs are shared like `share_file`.
+    ///
+    /// # Errors
+    ///
+    /// The same as `share_file`
+    pub async fn share_file_unhashed(&mut self, file_path: &Path) -> Result<String> {
+        self.share_path(file_path, false).await
+    }
+
+    /// Share a file, computing its whole-file hash only if `hashed`
+    async fn share_path(&mut self, file_path: &Path, hashed: bool) -> Result<String> {
+        // Canonicalizing can rewrite a URI into a bogus filesystem path
+        if let Some(url) = Self::path_as_uri(file_path) {
+            return self.share_uri(url).await;
+        }
+
+        // Try canonicalize, but fall back to original path if it fails
         let path = file_path
             .canonicalize()
             .unwrap_or_else(|_| file_path.to_path_buf());
@@ -406,6 +748,9 @@ impl FileSharingManager {
         if !path.exists() {
             return Err(FileSharingError::FileNotFound(path.clone()).into());
         }
+        if let Some(delay) = self.stability_check {
+            Self::ensure_stable(&path, delay).await?;
+        }
 
         let metadata = fs::metadata(&path).await?;
         let filename = path
@@ -414,42 +759,77 @@ impl FileSharingManager {
             .ok_or_else(|| FileSharingError::FileNotFound(path.clone()))?
             .to_string();
 
-        // Calculate file hash
-        let hash = self.calculate_file_hash(&path)?;
+        // Calculate file hash, unless it is cached for this size and mtime
+        let hash = if hashed {
+            self.limited_file_hash(&path, self.hash_algo).await?
+        } else {
+            String::new()
+        };
+        let modified_at = metadata
+            .modified()
+            .ok()
+            .and_then(hash_cache::modified_to_nanos);
 
-        // Check if file is already shared
+        // Check if file is already shared; codes revoked by
+        // `ShareOverwritePolicy::NewCode` keep their entry but no longer count
         if let Some((existing_share_code, existing_shared_file)) =
             self.shared_files
                 .iter()
                 .find(|(_, shared_file)| match &shared_file.path {
-                    FilePath::Path(existing_path) => existing_path == &path,
+                    FilePath::Path(existing_path) => {
+                        existing_path == &path
+                            && !shared_file.revoked
+                            && shared_file.info.hash.is_empty() != hashed
+                    }
                     _ => false,
                 })
         {
-            // File already shared, check if it has changed
-            if existing_shared_file.info.hash == hash {
+            // File already shared, check if it has changed; without a hash,
+            // by its size and modification time
+            let unchanged = if hashed {
+                existing_shared_file.info.hash == hash
+            } else {
+                modified_at.is_some()
+                    && existing_shared_file.info.modified_at == modified_at
+                    && existing_shared_file.info.size == metadata.len()
+            };
+            if unchanged {
                 // File unchanged, return existing share code
                 info!(
                     "File '{}' already shared with code: {} (unchanged)",
                     filename, existing_share_code
                 );
                 return Ok(existing_share_code.clone());
+            } else if self.overwrite_policy == ShareOverwritePolicy::Reject {
+                return Err(FileSharingError::FileChanged(existing_share_code.clone()).into());
+            } else if self.overwrite_policy == ShareOverwritePolicy::NewCode {
+                // Keep the old entry, revoked, and share the new content below
+                let old_share_code = existing_share_code.clone();
+                let mut revoked_file = existing_shared_file.clone();
+                revoked_file.revoked = true;
+                self.save_to_store(&old_share_code, &revoked_file)?;
+                self.shared_files
+                    .insert(old_share_code.clone(), revoked_file);
+                info!(
+                    "File '{}' changed, revoked its old code: {}",
+                    filename, old_share_code
+                );
             } else {
                 // File changed, update the existing entry
+                let (chunk_count, chunk_offsets) = self.chunk_layout(&path, metadata.len()).await?;
                 let updated_info = FileInfo {
                     id: existing_shared_file.info.id.clone(),
                     name: filename.clone(),
                     size: metadata.len(),
                     hash: hash.clone(),
-                    chunk_count: (metadata.len() / CHUNK_SIZE as u64) as usize
-                        + if metadata.len() % CHUNK_SIZE as u64 != 0 {
-                            1
-                        } else {
-                            0
-                        },
+                    hash_algo: self.hash_algo,
+                    chunk_count,
                     created_at: std::time::SystemTime::now()
                         .duration_since(std::time::UNIX_EPOCH)?
                         .as_secs(),
+                    modified_at,
+                    relative_path: existing_shared_file.info.relative_path.clone(),
+                    chunk_offsets,
                 };
 
                 let share_code = existing_share_code.clone();
@@ -466,7 +846,7 @@ impl FileSharingManager {
                 info!(
                     "Updated file '{}' (hash: {}) with existing code: {}",
                     filename,
-                    &hash[..8],
+                    hash.get(..8).unwrap_or("none"),
                     share_code
                 );
                 return Ok(share_code);
@@ -474,16 +854,11 @@ impl FileSharingManager {
         }
 
         // New file, create new entry
-        let share_code = self.generate_share_code(&filename);
+        let share_code = self.generate_share_code(&filename)?;
         let file_id = share_code.clone();
 
         // Calculate chunk count
-        let chunk_count = (metadata.len() / CHUNK_SIZE as u64) as usize
-            + if metadata.len() % CHUNK_SIZE as u64 != 0 {
-                1
-            } else {
-                0
-            };
+        let (chunk_count, chunk_offsets) = self.chunk_layout(&path, metadata.len()).await?;
 
         // Create FileInfo
         let file_info = FileInfo {
@@ -491,10 +866,14 @@ impl FileSharingManager {
             name: filename.clone(),
             size: metadata.len(),
             hash: hash.clone(),
+            hash_algo: self.hash_algo,
             chunk_count,
             created_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)?
                 .as_secs(),
+            modified_at,
+            relative_path: None,
+            chunk_offsets,
         };
 
         let shared_file = SharedFile {
@@ -508,18 +887,147 @@ impl FileSharingManager {
             .insert(share_code.clone(), shared_file.clone());
 
         // Save to persistent storage
-        self.save_to_store(&share_code, &shared_file).await?;
+        self.save_to_store(&share_code, &shared_file)?;
 
         info!(
             "Shared file '{}' (hash: {}) with code: {}",
             filename,
-            &hash[..8],
+            hash.get(..8).unwrap_or("none"),
             share_code
         );
 
         Ok(share_code)
     }
 
+    /// Chunk count and, with content-defined chunking, chunk start offsets
+    /// of a file about to be shared
+    async fn chunk_layout(&self, path: &Path, size: u64) -> Result<(usize, Option<Vec<u64>>)> {
+        if !self.content_defined_chunking {
+            return Ok((size.div_ceil(CHUNK_SIZE as u64) as usize, None));
+        }
+        let path = path.to_path_buf();
+        let offsets =
+            tokio::task::spawn_blocking(move || content_chunking::file_offsets(&path)).await??;
+        Ok((offsets.len(), Some(offsets)))
+    }
+
+    /// Share every file in a directory and its subdirectories
+    ///
+    /// Files are shared one at a time in path order, like `share_file`.
+    /// `cancel` is checked before each file; once cancelled, the files shared
+    /// so far are returned and stay shared. A file that cannot be shared is
+    /// reported in `failed` without stopping the rest. Symlinked directories
+    /// are not followed.
+    ///
+    /// Each file's `FileInfo::relative_path` is set to its path from the
+    /// directory, prefixed with the directory's name, so recipients can
+    /// recreate the folder structure.
+    ///
+    /// # Errors
+    ///
+    /// - `FileNotFound`: If `directory` is not a directory
+    /// - `IoError`: If a directory cannot be listed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gigi_file_sharing::{FileSharingManager, ShareCancelToken};
+    /// use std::path::Path;
+    ///
+    /// let mut manager = FileSharingManager::new();
+    /// let token = ShareCancelToken::new();
+    /// let result = manager.share_directory(Path::new("photos"), &token).await?;
+    /// println!("Shared {} files", result.shared.len());
+    /// ```
+    pub async fn share_directory(
+        &mut self,
+        directory: &Path,
+        cancel: &ShareCancelToken,
+    ) -> Result<DirectoryShare> {
+        if !fs::metadata(directory)
+            .await
+            .map(|metadata| metadata.is_dir())
+            .unwrap_or(false)
+        {
+            return Err(FileSharingError::FileNotFound(directory.to_path_buf()).into());
+        }
+
+        let mut files = Vec::new();
+        let mut pending = vec![directory.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            let mut entries = fs::read_dir(&current).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let file_type = entry.file_type().await?;
+                if file_type.is_dir() {
+                    pending.push(entry.path());
+                } else if file_type.is_file()
+                    || fs::metadata(entry.path())
+                        .await
+                        .is_ok_and(|metadata| metadata.is_file())
+                {
+                    files.push(entry.path());
+                }
+            }
+        }
+        files.sort();
+
+        // Relative paths start with the directory's own name
+        let root_name = directory.canonicalize().ok().and_then(|root| {
+            root.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        });
+
+        let mut result = DirectoryShare::default();
+        for file in files {
+            if cancel.is_cancelled() {
+                result.cancelled = true;
+                break;
+            }
+            match self.share_file(&file).await {
+                Ok(share_code) => {
+                    let relative_path = root_name
+                        .iter()
+                        .cloned()
+                        .chain(
+                            file.strip_prefix(directory)
+                                .unwrap_or(&file)
+                                .components()
+                                .map(|part| part.as_os_str().to_string_lossy().into_owned()),
+                        )
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    self.set_relative_path(&share_code, relative_path)?;
+                    result.shared.push((file, share_code));
+                }
+                Err(e) => {
+                    warn!("Failed to share {}: {}", file.display(), e);
+                    result.failed.push((file, e.to_string()));
+                }
+            }
+        }
+
+        info!(
+            "Shared {} files from '{}'{}",
+            result.shared.len(),
+            directory.display(),
+            if result.cancelled { " (cancelled)" } else { "" }
+        );
+        Ok(result)
+    }
+
+    /// Record where a shared file sits within a directory share
+    fn set_relative_path(&mut self, share_code: &str, relative_path: String) -> Result<()> {
+        let Some(shared_file) = self.shared_files.get_mut(share_code) else {
+            return Ok(());
+        };
+        if shared_file.info.relative_path.as_deref() == Some(relative_path.as_str()) {
+            return Ok(());
+        }
+        shared_file.info.relative_path = Some(relative_path);
+        let shared_file = shared_file.clone();
+        self.save_to_store(share_code, &shared_file)
+    }
+
     /// Share a file from a content URI
     ///
     /// # Arguments
@@ -561,7 +1069,7 @@ impl FileSharingManager {
     pub async fn share_content_uri(&mut self, uri: &str, name: &str, size: u64) -> Result<String> {
         let url = Url::parse(uri)
             .map_err(|e: url::ParseError| FileSharingError::InvalidUri(e.to_string()))?;
-        let share_code = self.generate_share_code(name);
+        let share_code = self.generate_share_code(name)?;
 
         let file_id = share_code.clone();
 
@@ -574,10 +1082,14 @@ impl FileSharingManager {
             name: name.to_string(),
             size,
             hash: String::new(), // Will be calculated by the caller if needed
+            hash_algo: self.hash_algo,
             chunk_count,
             created_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)?
                 .as_secs(),
+            modified_at: None,
+            relative_path: None,
+            chunk_offsets: None,
         };
 
         let shared_file = SharedFile {
@@ -590,14 +1102,52 @@ impl FileSharingManager {
         self.shared_files.insert(share_code.clone(), shared_file);
 
         // Save to persistent storage
-        self.save_to_store(&share_code, &self.shared_files[&share_code])
-            .await?;
+        self.save_to_store(&share_code, &self.shared_files[&share_code])?;
 
         info!("Shared content URI '{}' with code: {}", name, share_code);
 
         Ok(share_code)
     }
 
+    /// Parse a path passed to `share_file` as a URI, if it has a scheme
+    ///
+    /// Single-letter schemes are not URIs but Windows drive letters.
+    fn path_as_uri(file_path: &Path) -> Option<Url> {
+        let text = file_path.to_str()?;
+        let (scheme, _) = text.split_once("://")?;
+        if scheme.len() < 2 {
+            return None;
+        }
+        Url::parse(text).ok()
+    }
+
+    /// Share a URI that was passed to `share_file`
+    ///
+    /// Reads the URI through the chunk reader to measure its size.
+    async fn share_uri(&mut self, url: Url) -> Result<String> {
+        let reader = self.chunk_reader.clone().ok_or_else(|| {
+            FileSharingError::InvalidUri(format!("No chunk reader configured for {}", url))
+        })?;
+        let name = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|segment| !segment.is_empty())
+            .unwrap_or("file")
+            .to_string();
+
+        let file_path = FilePath::Url(url.clone());
+        let mut size = 0u64;
+        loop {
+            let chunk = reader(&file_path, size, CHUNK_SIZE)?;
+            size += chunk.len() as u64;
+            if chunk.len() < CHUNK_SIZE {
+                break;
+            }
+        }
+
+        self.share_content_uri(url.as_str(), &name, size).await
+    }
+
     /// List all currently shared files
     ///
     /// # Returns
@@ -625,6 +1175,97 @@ impl FileSharingManager {
         self.shared_files.values().collect()
     }
 
+    /// Count shared files and sum their sizes
+    ///
+    /// # Returns
+    ///
+    /// A `ShareStats` with the number of shared files, their total size in
+    /// bytes and the number of revoked entries
+    ///
+    /// # Persistence
+    ///
+    /// When a store is attached the totals come from a single aggregate query
+    /// over the whole library. Otherwise they are summed from memory, where
+    /// unshared files are already removed, so `revoked_count` counts only
+    /// files marked revoked but still registered.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gigi_file_sharing::FileSharingManager;
+    ///
+    /// let manager = FileSharingManager::new();
+    /// let stats = manager.shared_stats().await?;
+    /// println!("Sharing {} files ({} bytes)", stats.file_count, stats.total_bytes);
+    /// ```
+    pub async fn shared_stats(&self) -> Result<ShareStats> {
+        if let Some(store) = &self.file_sharing_store {
+            return store.shared_stats().await;
+        }
+
+        let mut stats = ShareStats::default();
+        for file in self.shared_files.values() {
+            if file.revoked {
+                stats.revoked_count += 1;
+            } else {
+                stats.file_count += 1;
+                stats.total_bytes += file.info.size;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Search shared files by filename
+    ///
+    /// Performs a case-insensitive substring match against each shared
+    /// file's name. An empty query matches every file.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Text to look for in file names
+    ///
+    /// # Returns
+    ///
+    /// A vector of references to the matching shared files
+    ///
+    /// # Persistence
+    ///
+    /// When a store is attached the match runs as a query in the store, and
+    /// only entries also registered in memory are returned. Otherwise the
+    /// in-memory registry is scanned.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use gigi_file_sharing::FileSharingManager;
+    ///
+    /// let manager = FileSharingManager::new();
+    /// // ... share some files ...
+    ///
+    /// for file in manager.search_shared_files("report").await? {
+    ///     println!("{}: {}", file.share_code, file.info.name);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn search_shared_files(&self, query: &str) -> Result<Vec<&SharedFile>> {
+        if let Some(store) = &self.file_sharing_store {
+            let matches = store.search_shared_files(query).await?;
+            return Ok(matches
+                .iter()
+                .filter_map(|info| self.shared_files.get(&info.share_code))
+                .collect());
+        }
+
+        let query = query.to_lowercase();
+        Ok(self
+            .shared_files
+            .values()
+            .filter(|file| file.info.name.to_lowercase().contains(&query))
+            .collect())
+    }
+
     /// Unshare (revoke access to) a file
     ///
     /// # Arguments
@@ -671,29 +1312,81 @@ impl FileSharingManager {
         }
     }
 
-    /// Calculate SHA256 hash of a file
+    /// Unshare every file matching a predicate
     ///
     /// # Arguments
     ///
-    /// * `file_path` - Path to the file to hash
+    /// * `predicate` - Returns `true` for files that should be unshared
     ///
     /// # Returns
     ///
-    /// Hexadecimal string of the SHA256 hash
+    /// The share codes of all unshared files
     ///
-    /// # Algorithm
+    /// # Persistence
+    ///
+    /// When a store is attached, all matching entries are removed from it in
+    /// one transaction before they are unshared in memory; if that fails,
+    /// the error is returned and nothing is unshared.
+    ///
+    /// # Example
     ///
-    /// Uses SHA256 for:
-    /// - **Security**: Cryptographically secure
-    /// - **Performance**: Fast computation on modern CPUs
-    /// - **Uniqueness**: 256-bit output provides virtually no collisions
-    /// - **Integrity**: Recipients can verify file wasn't corrupted
+    /// ```rust,no_run
+    /// use gigi_file_sharing::FileSharingManager;
     ///
-    /// # Buffer Size
+    /// let mut manager = FileSharingManager::new();
+    /// // ... share some files ...
     ///
-    /// Uses 8KB buffer for:
-    /// - Good I/O performance (matches typical block sizes)
-    /// - Low memory usage (even on embedded devices)
+    /// // Unshare all PNG images
+    /// let removed = manager
+    ///     .unshare_where(|file| file.info.name.ends_with(".png"))
+    ///     .await?;
+    /// println!("Unshared {} files", removed.len());
+    /// ```
+    pub async fn unshare_where(
+        &mut self,
+        predicate: impl Fn(&SharedFile) -> bool,
+    ) -> Result<Vec<String>> {
+        let share_codes: Vec<String> = self
+            .shared_files
+            .values()
+            .filter(|file| predicate(file))
+            .map(|file| file.share_code.clone())
+            .collect();
+        if share_codes.is_empty() {
+            return Ok(share_codes);
+        }
+
+        if let Some(store) = &self.file_sharing_store {
+            store.delete_shared_files(&share_codes).await?;
+        }
+
+        for share_code in &share_codes {
+            if let Some(shared_file) = self.shared_files.remove(share_code) {
+                info!(
+                    "Unshared file '{}' with share code: {}",
+                    shared_file.info.name, share_code
+                );
+            }
+        }
+
+        Ok(share_codes)
+    }
+
+    /// Calculate the whole-file hash with the configured algorithm
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - Path to the file to hash
+    ///
+    /// # Returns
+    ///
+    /// Hexadecimal string of the hash (64 characters for both algorithms)
+    ///
+    /// # Algorithm
+    ///
+    /// SHA256 by default, for compatibility with existing peers and records.
+    /// BLAKE3, selected with `set_hash_algo`, is markedly faster on large
+    /// media and is the algorithm already used for chunk hashes.
     ///
     /// # Example
     ///
@@ -702,21 +1395,182 @@ impl FileSharingManager {
     /// SHA256: "3b4c5e8b5f2a1c9d5e0f7a6b3c8d5e2f1a9c4d8e6f7a0b1c2d3e4f5a6"
     /// ```
     pub fn calculate_file_hash(&self, file_path: &Path) -> Result<String> {
-        use sha2::{Digest, Sha256};
+        self.hash_algo.hash_file(file_path)
+    }
+
+    /// Hash a file, reusing the cached hash while its size and mtime are unchanged
+    ///
+    /// Files without a modification time (some platforms) are always hashed.
+    fn cached_file_hash(hash_cache: &mut HashCache, path: &Path, algo: HashAlgo) -> Result<String> {
+        let metadata = std::fs::metadata(path)?;
+        let size = metadata.len();
+        let Ok(modified) = metadata.modified() else {
+            return algo.hash_file(path);
+        };
+        if let Some(hash) = hash_cache.get(path, size, modified, algo) {
+            return Ok(hash);
+        }
 
-        let mut file = std::fs::File::open(file_path)?;
-        let mut hasher = Sha256::new();
-        let mut buffer = [0; 8192];
+        let hash = algo.hash_file(path)?;
+        hash_cache.insert(path.to_path_buf(), size, modified, algo, hash.clone());
+        Ok(hash)
+    }
 
-        loop {
-            let bytes_read = file.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
-            }
-            hasher.update(&buffer[..bytes_read]);
+    /// Fail with `FileUnstable` if the file's size or mtime changes within `delay`
+    async fn ensure_stable(path: &Path, delay: std::time::Duration) -> Result<()> {
+        let sample = |metadata: std::fs::Metadata| (metadata.len(), metadata.modified().ok());
+        let before = sample(fs::metadata(path).await?);
+        tokio::time::sleep(delay).await;
+        let after = sample(fs::metadata(path).await?);
+        if before != after {
+            warn!(
+                "File {} is still being written, not sharing it",
+                path.display()
+            );
+            return Err(FileSharingError::FileUnstable(path.to_path_buf()).into());
+        }
+        Ok(())
+    }
+
+    /// Like `cached_file_hash`, but hashes through the hash limiter
+    async fn limited_file_hash(&mut self, path: &Path, algo: HashAlgo) -> Result<String> {
+        let metadata = fs::metadata(path).await?;
+        let size = metadata.len();
+        let Ok(modified) = metadata.modified() else {
+            return self.hash_limiter.hash_file(path.to_path_buf(), algo).await;
+        };
+        if let Some(hash) = self.hash_cache.get(path, size, modified, algo) {
+            return Ok(hash);
+        }
+
+        let hash = self
+            .hash_limiter
+            .hash_file(path.to_path_buf(), algo)
+            .await?;
+        self.hash_cache
+            .insert(path.to_path_buf(), size, modified, algo, hash.clone());
+        Ok(hash)
+    }
+
+    /// Re-read a shared file's size and hash after it changed on disk
+    ///
+    /// The share code stays the same, so peers can download the new contents
+    /// with it. Content URI shares are left unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `share_code` - The share code of the file to refresh
+    ///
+    /// # Returns
+    ///
+    /// `true` if the file info changed, `false` if it still matches
+    ///
+    /// # Errors
+    ///
+    /// - `InvalidShareCode`: If no file is shared with this code
+    /// - `IoError`: If the file can no longer be read
+    pub fn refresh_shared_file(&mut self, share_code: &str) -> Result<bool> {
+        let shared_file = self
+            .shared_files
+            .get(share_code)
+            .ok_or_else(|| FileSharingError::InvalidShareCode(share_code.to_string()))?;
+        let hash_cache = &mut self.hash_cache;
+        let updated = Self::refreshed(shared_file, |path, algo| {
+            Self::cached_file_hash(hash_cache, path, algo)
+        })?;
+        match updated {
+            Some(updated) => self.apply_refresh(share_code, updated),
+            None => Ok(false),
         }
+    }
 
-        Ok(format!("{:x}", hasher.finalize()))
+    /// Re-read a shared file like `refresh_shared_file`, without the manager
+    ///
+    /// Does the blocking part of a refresh, reading the file's metadata,
+    /// hashing it and finding its content-defined chunks, so it can run on a
+    /// blocking thread. Store the result with `apply_refresh`. Does not use
+    /// the hash cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `shared_file` - The shared file as currently registered
+    ///
+    /// # Returns
+    ///
+    /// The updated shared file, `None` if it still matches
+    ///
+    /// # Errors
+    ///
+    /// - `IoError`: If the file can no longer be read
+    pub fn compute_refresh(shared_file: &SharedFile) -> Result<Option<SharedFile>> {
+        Self::refreshed(shared_file, |path, algo| algo.hash_file(path))
+    }
+
+    /// Store a shared file updated by `compute_refresh`
+    ///
+    /// Ignored if the file was unshared, or shared again from another path,
+    /// while it was being refreshed.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the update was stored
+    pub fn apply_refresh(&mut self, share_code: &str, updated: SharedFile) -> Result<bool> {
+        match self.shared_files.get(share_code) {
+            Some(current) if current.path == updated.path => {}
+            _ => return Ok(false),
+        }
+        self.save_to_store(share_code, &updated)?;
+
+        info!(
+            "Refreshed changed file '{}' ({} bytes) with code: {}",
+            updated.info.name, updated.info.size, share_code
+        );
+        self.shared_files.insert(share_code.to_string(), updated);
+        Ok(true)
+    }
+
+    /// The updated shared file if it changed on disk, hashing with `hash`
+    fn refreshed(
+        shared_file: &SharedFile,
+        hash: impl FnOnce(&Path, HashAlgo) -> Result<String>,
+    ) -> Result<Option<SharedFile>> {
+        let FilePath::Path(path) = &shared_file.path else {
+            return Ok(None);
+        };
+        let mut updated = shared_file.clone();
+
+        let metadata = std::fs::metadata(path)?;
+        let size = metadata.len();
+        let modified_at = metadata
+            .modified()
+            .ok()
+            .and_then(hash_cache::modified_to_nanos);
+        // Keep the algorithm recipients were told about, and unhashed
+        // shares unhashed
+        let hash = if updated.info.hash.is_empty() {
+            String::new()
+        } else {
+            hash(path, updated.info.hash_algo)?
+        };
+        if size == updated.info.size
+            && hash == updated.info.hash
+            && (!hash.is_empty() || modified_at == updated.info.modified_at)
+        {
+            return Ok(None);
+        }
+
+        updated.info.size = size;
+        updated.info.hash = hash;
+        updated.info.modified_at = modified_at;
+        // Keep the chunking the file was shared with
+        if updated.info.chunk_offsets.is_some() {
+            let offsets = content_chunking::file_offsets(path)?;
+            updated.info.chunk_count = offsets.len();
+            updated.info.chunk_offsets = Some(offsets);
+        } else {
+            updated.info.chunk_count = size.div_ceil(CHUNK_SIZE as u64) as usize;
+        }
+        Ok(Some(updated))
     }
 
     /// Save shared file metadata to persistent storage
@@ -743,14 +1597,14 @@ impl FileSharingManager {
     /// - Avoid blocking the main async runtime
     /// - Allow the caller to continue immediately
     /// - Handle storage I/O independently
-    async fn save_to_store(&self, share_code: &str, shared_file: &SharedFile) -> Result<()> {
+    fn save_to_store(&self, share_code: &str, shared_file: &SharedFile) -> Result<()> {
         if let Some(store) = &self.file_sharing_store {
             let file_path = match &shared_file.path {
                 FilePath::Path(p) => p.to_string_lossy().to_string(),
                 FilePath::Url(u) => u.to_string(),
             };
 
-            let info = gigi_store::SharedFileInfo::new(
+            let mut info = gigi_store::SharedFileInfo::new(
                 share_code.to_string(),
                 shared_file.info.name.clone(),
                 file_path,
@@ -759,22 +1613,65 @@ impl FileSharingManager {
                 shared_file.info.chunk_count,
                 shared_file.info.created_at as i64,
             );
+            info.hash_algo = shared_file.info.hash_algo;
+            info.revoked = shared_file.revoked;
+            info.relative_path = shared_file.info.relative_path.clone();
+            info.chunk_offsets = shared_file.info.chunk_offsets.clone();
+            if let FilePath::Path(path) = &shared_file.path {
+                info.modified_at = self
+                    .hash_cache
+                    .modified(path)
+                    .and_then(hash_cache::modified_to_nanos)
+                    .or(shared_file.info.modified_at);
+            }
 
             let store_clone = Arc::clone(store);
-            tokio::task::spawn(async move {
+            self.track_write(tokio::task::spawn(async move {
                 if let Err(e) = store_clone.store_shared_file(&info).await {
                     error!("Failed to save shared file to store: {}", e);
                 }
-            });
+            }));
         }
         Ok(())
     }
 
+    /// Remember a background store write so `flush_store` can await it
+    fn track_write(&self, handle: JoinHandle<()>) {
+        let mut pending = self.pending_writes.lock().unwrap();
+        pending.retain(|handle| !handle.is_finished());
+        pending.push(handle);
+    }
+
+    /// Wait until every store write started so far has finished
+    ///
+    /// Saving and unsharing write to the store in the background; call this
+    /// before exiting so those writes are not lost.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use gigi_file_sharing::FileSharingManager;
+    /// use std::path::PathBuf;
+    ///
+    /// let mut manager = FileSharingManager::new();
+    /// manager.share_file(&PathBuf::from("file.txt")).await?;
+    /// manager.flush_store().await;
+    /// ```
+    pub async fn flush_store(&self) {
+        let pending = std::mem::take(&mut *self.pending_writes.lock().unwrap());
+        for handle in pending {
+            if let Err(e) = handle.await {
+                error!("Store write task failed: {}", e);
+            }
+        }
+    }
+
     /// Load shared files from persistent storage
     ///
     /// # Returns
     ///
-    /// `Ok(())` on success (even if store is not configured)
+    /// The share code conflicts found and resolved; empty if there were none
+    /// or the store is not configured
     ///
     /// # Process
     ///
@@ -783,6 +1680,9 @@ impl FileSharingManager {
     ///    - Check if file still exists on disk
     ///    - If exists: Add to in-memory registry
     ///    - If doesn't exist: Skip (orphaned entry)
+    ///    - If its share code is already registered, ignoring case: keep the
+    ///      entry created later (on a tie, the smaller share code), drop the
+    ///      other and log a warning
     /// 3. Log summary of loaded files
     ///
     /// # Error Handling
@@ -797,29 +1697,65 @@ impl FileSharingManager {
     /// use gigi_file_sharing::FileSharingManager;
     ///
     /// let mut manager = FileSharingManager::new();
-    /// manager.load_from_store().await?;
+    /// let conflicts = manager.load_from_store().await?;
     /// println!("Loaded {} files", manager.list_shared_files().len());
     /// ```
-    pub async fn load_from_store(&mut self) -> Result<()> {
+    pub async fn load_from_store(&mut self) -> Result<Vec<ShareCodeConflict>> {
+        let mut conflicts = Vec::new();
         if let Some(store) = &self.file_sharing_store {
             let files = store.list_shared_files().await?;
+            // Registered share code per normalized code
+            let mut registered: HashMap<String, String> = self
+                .shared_files
+                .keys()
+                .map(|code| (code.trim().to_ascii_lowercase(), code.clone()))
+                .collect();
             for file_info in files {
                 let file_path = PathBuf::from(&file_info.file_path);
                 // Only load files that still exist
                 if file_path.exists() {
+                    // Unhashed shares have no hash to cache
+                    if let Some(modified) = file_info
+                        .modified_at
+                        .filter(|_| !file_info.hash.is_empty())
+                        .and_then(hash_cache::modified_from_nanos)
+                    {
+                        self.hash_cache.insert(
+                            file_path.clone(),
+                            file_info.file_size,
+                            modified,
+                            file_info.hash_algo,
+                            file_info.hash.clone(),
+                        );
+                    }
                     let shared_file = SharedFile {
                         info: FileInfo {
                             id: file_info.share_code.clone(),
                             name: file_info.file_name.clone(),
                             size: file_info.file_size,
                             hash: file_info.hash.clone(),
+                            hash_algo: file_info.hash_algo,
                             chunk_count: file_info.chunk_count,
                             created_at: file_info.created_at as u64,
+                            modified_at: file_info.modified_at,
+                            relative_path: file_info.relative_path.clone(),
+                            chunk_offsets: file_info.chunk_offsets.clone(),
                         },
                         path: FilePath::Path(file_path),
                         share_code: file_info.share_code.clone(),
                         revoked: file_info.revoked,
                     };
+                    let normalized = file_info.share_code.trim().to_ascii_lowercase();
+                    if let Some(existing_code) = registered.get(&normalized).cloned() {
+                        conflicts.push(self.resolve_share_code_conflict(
+                            normalized,
+                            &existing_code,
+                            shared_file,
+                            &mut registered,
+                        ));
+                        continue;
+                    }
+                    registered.insert(normalized, file_info.share_code.clone());
                     self.shared_files.insert(file_info.share_code, shared_file);
                 }
             }
@@ -828,7 +1764,52 @@ impl FileSharingManager {
                 self.shared_files.len()
             );
         }
-        Ok(())
+        Ok(conflicts)
+    }
+
+    /// Keep one of two shared files with the same normalized share code
+    ///
+    /// The one created later wins; on a tie, the smaller share code.
+    fn resolve_share_code_conflict(
+        &mut self,
+        share_code: String,
+        existing_code: &str,
+        loaded: SharedFile,
+        registered: &mut HashMap<String, String>,
+    ) -> ShareCodeConflict {
+        let existing = &self.shared_files[existing_code];
+        let keep_loaded = match loaded.info.created_at.cmp(&existing.info.created_at) {
+            std::cmp::Ordering::Greater => true,
+            std::cmp::Ordering::Less => false,
+            std::cmp::Ordering::Equal => loaded.share_code.as_str() < existing_code,
+        };
+        let (kept, dropped) = if keep_loaded {
+            let dropped = self
+                .shared_files
+                .remove(existing_code)
+                .map(|file| file.info);
+            registered.insert(share_code.clone(), loaded.share_code.clone());
+            let kept = loaded.info.clone();
+            self.shared_files.insert(loaded.share_code.clone(), loaded);
+            (kept, dropped.expect("registered share code is shared"))
+        } else {
+            (existing.info.clone(), loaded.info)
+        };
+        warn!(
+            "Duplicate share code {}: keeping {} ({}, created {}), dropping {} ({}, created {})",
+            share_code,
+            kept.name,
+            kept.id,
+            kept.created_at,
+            dropped.name,
+            dropped.id,
+            dropped.created_at
+        );
+        ShareCodeConflict {
+            share_code,
+            kept,
+            dropped,
+        }
     }
 
     /// Update the thumbnail path for a shared file
@@ -5,8 +5,12 @@
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use url::Url;
 
+pub use gigi_store::HashAlgo;
+
 /// File path representation supporting both filesystem paths and URIs
 ///
 /// This enum enables the file sharing system to work across different platforms:
@@ -58,9 +62,13 @@ pub enum FilePath {
 /// - `id`: Unique identifier (typically the share code)
 /// - `name`: Display filename for the user
 /// - `size`: Total file size in bytes
-/// - `hash`: SHA256 hash for integrity verification
+/// - `hash`: Whole-file hash for integrity verification
+/// - `hash_algo`: Algorithm of `hash`, SHA256 unless the sharer chose BLAKE3
 /// - `chunk_count`: Number of chunks (ceil(size / CHUNK_SIZE))
 /// - `created_at`: Unix timestamp (seconds since epoch)
+/// - `modified_at`: Modification time of the source file, if known
+/// - `relative_path`: Path within a shared directory, for directory shares
+/// - `chunk_offsets`: Chunk start offsets, for content-defined chunking
 ///
 /// # Example
 ///
@@ -72,8 +80,12 @@ pub enum FilePath {
 ///     name: "document.pdf".to_string(),
 ///     size: 1024 * 1024,  // 1MB
 ///     hash: "3b4c5e8b5f2...".to_string(),
+///     hash_algo: Default::default(),
 ///     chunk_count: 4,
 ///     created_at: 1640995200,
+///     modified_at: None,
+///     relative_path: None,
+///     chunk_offsets: None,
 /// };
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,12 +96,52 @@ pub struct FileInfo {
     pub name: String,
     /// File size in bytes
     pub size: u64,
-    /// SHA256 hash for integrity verification (64 hex characters)
+    /// Whole-file hash for integrity verification (64 hex characters);
+    /// empty when the sharer did not hash the file, as for content URIs and
+    /// `share_file_unhashed`, so recipients skip whole-file verification
     pub hash: String,
+    /// Algorithm of `hash`; absent from peers that predate it, meaning SHA256
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
     /// Number of chunks for chunked transfer
     pub chunk_count: usize,
     /// Creation timestamp (Unix epoch seconds)
     pub created_at: u64,
+    /// Modification time of the source file (ns since the Unix epoch), so
+    /// downloads can keep it; absent for URIs and from peers that predate it
+    #[serde(default)]
+    pub modified_at: Option<i64>,
+    /// Path of the file from the root of a directory share, `/`-separated
+    /// and starting with the directory's name, e.g. `photos/2024/beach.jpg`;
+    /// `None` for single file shares and from peers that predate it
+    #[serde(default)]
+    pub relative_path: Option<String>,
+    /// Start offset of each chunk when the sharer used content-defined
+    /// chunking, so chunks vary in size; `None` for `CHUNK_SIZE` chunks
+    #[serde(default)]
+    pub chunk_offsets: Option<Vec<u64>>,
+}
+
+impl FileInfo {
+    /// Byte offset of a chunk and the most bytes it can hold
+    ///
+    /// Fixed size chunks hold `CHUNK_SIZE` bytes, fewer for the last one;
+    /// content-defined chunks span exactly to the next offset or the end
+    /// of the file.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the offset overflows or `chunk_index` is past the last
+    /// content-defined chunk
+    pub fn chunk_span(&self, chunk_index: usize) -> Option<(u64, usize)> {
+        let Some(offsets) = &self.chunk_offsets else {
+            let offset = (chunk_index as u64).checked_mul(crate::CHUNK_SIZE as u64)?;
+            return Some((offset, crate::CHUNK_SIZE));
+        };
+        let start = *offsets.get(chunk_index)?;
+        let end = offsets.get(chunk_index + 1).copied().unwrap_or(self.size);
+        Some((start, usize::try_from(end.checked_sub(start)?).ok()?))
+    }
 }
 
 /// Complete shared file record
@@ -127,8 +179,12 @@ pub struct FileInfo {
 ///         name: "file.txt".to_string(),
 ///         size: 512,
 ///         hash: "abc123".to_string(),
+///         hash_algo: Default::default(),
 ///         chunk_count: 1,
 ///         created_at: 1640995200,
+///         modified_at: None,
+///         relative_path: None,
+///         chunk_offsets: None,
 ///     },
 ///     path: FilePath::Path(PathBuf::from("/path/to/file.pdf")),
 ///     share_code: "a1b2c3d4".to_string(),
@@ -146,3 +202,83 @@ pub struct SharedFile {
     /// Whether file sharing has been revoked
     pub revoked: bool,
 }
+
+/// What `share_file` does when a file shared before has changed content
+///
+/// Recipients holding the old share code get whatever the code points to
+/// when they download, so keeping the code silently swaps the content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShareOverwritePolicy {
+    /// Update the existing entry, so the old code serves the new content
+    #[default]
+    KeepCode,
+    /// Revoke the old code and share the new content under a fresh one
+    NewCode,
+    /// Fail with `FileSharingError::FileChanged` and leave the entry as is
+    Reject,
+}
+
+/// Cancels a running `share_directory` between files
+///
+/// Clones share the same flag, so keep one to cancel from another task while
+/// the directory is being shared.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use gigi_file_sharing::ShareCancelToken;
+///
+/// let token = ShareCancelToken::new();
+/// let handle = token.clone();
+/// // Later, e.g. when the user presses "Cancel"
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ShareCancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ShareCancelToken {
+    /// Create a token that is not cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stop sharing before the next file
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether `cancel` was called on this token or one of its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Outcome of sharing a directory
+///
+/// Files shared before a cancellation stay shared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectoryShare {
+    /// Shared files and their share codes, in path order
+    pub shared: Vec<(PathBuf, String)>,
+    /// Files that could not be shared and why
+    pub failed: Vec<(PathBuf, String)>,
+    /// Whether the share was cancelled before every file was shared
+    pub cancelled: bool,
+}
+
+/// Two shared files found under the same share code by `load_from_store`
+///
+/// Share codes are compared ignoring case and surrounding whitespace, like
+/// codes typed by users. The entry created later is kept.
+#[derive(Debug, Clone)]
+pub struct ShareCodeConflict {
+    /// The share code both entries use, normalized
+    pub share_code: String,
+    /// The entry kept
+    pub kept: FileInfo,
+    /// The entry dropped from the registry; the store is left unchanged
+    pub dropped: FileInfo,
+}
@@ -2,7 +2,10 @@
 //
 // Comprehensive tests for FileSharingManager core functionality
 
-use gigi_file_sharing::{FileSharingManager, CHUNK_SIZE};
+use gigi_file_sharing::{
+    is_valid_share_code, normalize_share_code, FileSharingError, FileSharingManager, HashAlgo,
+    ShareOverwritePolicy, CHUNK_SIZE,
+};
 use std::fs;
 use tempfile::TempDir;
 
@@ -27,8 +30,8 @@ fn test_share_code_generation() {
     let manager = FileSharingManager::new();
 
     // Generate share codes
-    let code1 = manager.generate_share_code("file.txt");
-    let code2 = manager.generate_share_code("file.txt");
+    let code1 = manager.generate_share_code("file.txt").unwrap();
+    let code2 = manager.generate_share_code("file.txt").unwrap();
 
     // Codes should be different (due to timestamp)
     assert_ne!(code1, code2);
@@ -46,13 +49,245 @@ fn test_share_code_determinism() {
     let manager = FileSharingManager::new();
 
     // Same filename should produce different codes (timestamp)
-    let code1 = manager.generate_share_code("test.txt");
+    let code1 = manager.generate_share_code("test.txt").unwrap();
     std::thread::sleep(std::time::Duration::from_millis(10));
-    let code2 = manager.generate_share_code("test.txt");
+    let code2 = manager.generate_share_code("test.txt").unwrap();
 
     assert_ne!(code1, code2);
 }
 
+#[test]
+fn test_share_code_with_pinned_clock_is_deterministic() {
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let clock = Arc::new(|| UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    let manager1 = FileSharingManager::new().with_clock(clock.clone());
+    let manager2 = FileSharingManager::new().with_clock(clock);
+
+    // Same filename and time produce the same code
+    assert_eq!(
+        manager1.generate_share_code("file.txt").unwrap(),
+        manager2.generate_share_code("file.txt").unwrap()
+    );
+    assert_ne!(
+        manager1.generate_share_code("file.txt").unwrap(),
+        manager1.generate_share_code("other.txt").unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_share_code_generation_fails_when_attempts_run_out() {
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let temp_dir = TempDir::new().unwrap();
+    let dir_a = temp_dir.path().join("a");
+    let dir_b = temp_dir.path().join("b");
+    fs::create_dir_all(&dir_a).unwrap();
+    fs::create_dir_all(&dir_b).unwrap();
+    fs::write(dir_a.join("same.txt"), b"first").unwrap();
+    fs::write(dir_b.join("same.txt"), b"second").unwrap();
+
+    let clock: gigi_file_sharing::Clock =
+        Arc::new(|| UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    let mut manager = FileSharingManager::new().with_clock(clock);
+    let code = manager.share_file(&dir_a.join("same.txt")).await.unwrap();
+    let template = manager.shared_files[&code].clone();
+
+    // Take every candidate code for this name and time
+    let error = loop {
+        match manager.generate_share_code("same.txt") {
+            Ok(code) => {
+                let mut shared_file = template.clone();
+                shared_file.share_code = code.clone();
+                manager.shared_files.insert(code, shared_file);
+            }
+            Err(e) => break e,
+        }
+    };
+    assert!(matches!(
+        error.downcast_ref::<FileSharingError>(),
+        Some(FileSharingError::ShareCodesExhausted(_))
+    ));
+
+    // Sharing fails instead of reusing a code that is taken
+    let shared = manager.list_shared_files().len();
+    assert!(manager.share_file(&dir_b.join("same.txt")).await.is_err());
+    assert_eq!(manager.list_shared_files().len(), shared);
+}
+
+#[test]
+fn test_share_code_length_is_configurable() {
+    use gigi_file_sharing::{DEFAULT_SHARE_CODE_LENGTH, MAX_SHARE_CODE_LENGTH};
+
+    let mut manager = FileSharingManager::new();
+    assert_eq!(manager.share_code_length(), DEFAULT_SHARE_CODE_LENGTH);
+
+    for length in [12, MAX_SHARE_CODE_LENGTH] {
+        manager.set_share_code_length(length).unwrap();
+        let code = manager.generate_share_code("file.txt").unwrap();
+        assert_eq!(code.len(), length);
+        assert!(code.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    assert!(manager.set_share_code_length(5).is_err());
+    assert!(manager.set_share_code_length(33).is_err());
+    assert_eq!(manager.share_code_length(), MAX_SHARE_CODE_LENGTH);
+}
+
+#[tokio::test]
+async fn test_share_code_collision_retry_with_pinned_clock() {
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let temp_dir = TempDir::new().unwrap();
+    let dir_a = temp_dir.path().join("a");
+    let dir_b = temp_dir.path().join("b");
+    fs::create_dir_all(&dir_a).unwrap();
+    fs::create_dir_all(&dir_b).unwrap();
+    let file_a = dir_a.join("same.txt");
+    let file_b = dir_b.join("same.txt");
+    fs::write(&file_a, b"first").unwrap();
+    fs::write(&file_b, b"second").unwrap();
+
+    let clock: gigi_file_sharing::Clock =
+        Arc::new(|| UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    let mut manager = FileSharingManager::new().with_clock(clock.clone());
+
+    // With an identical clock and filename, the first candidate for the
+    // second file is already taken and must be retried
+    let code_a = manager.share_file(&file_a).await.unwrap();
+    let first_candidate = FileSharingManager::new()
+        .with_clock(clock)
+        .generate_share_code("same.txt")
+        .unwrap();
+    assert_eq!(first_candidate, code_a);
+
+    let code_b = manager.share_file(&file_b).await.unwrap();
+    assert_ne!(code_a, code_b);
+    assert_eq!(code_b.len(), 8);
+    assert!(code_b.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(manager.list_shared_files().len(), 2);
+}
+
+#[tokio::test]
+async fn test_share_code_collision_retry_at_custom_length() {
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = FileSharingManager::new()
+        .with_clock(Arc::new(|| UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+    manager.set_share_code_length(12).unwrap();
+
+    let mut codes = Vec::new();
+    for dir in ["a", "b"] {
+        let file = temp_dir.path().join(dir).join("same.txt");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, dir.as_bytes()).unwrap();
+        codes.push(manager.share_file(&file).await.unwrap());
+    }
+
+    assert_ne!(codes[0], codes[1]);
+    assert!(codes.iter().all(|code| code.len() == 12));
+}
+
+#[test]
+fn test_max_concurrent_hashes_is_configurable() {
+    use gigi_file_sharing::DEFAULT_MAX_CONCURRENT_HASHES;
+
+    let mut manager = FileSharingManager::new();
+    assert_eq!(
+        manager.max_concurrent_hashes(),
+        DEFAULT_MAX_CONCURRENT_HASHES
+    );
+
+    manager.set_max_concurrent_hashes(1).unwrap();
+    assert_eq!(manager.max_concurrent_hashes(), 1);
+
+    let err = manager.set_max_concurrent_hashes(0).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<FileSharingError>(),
+        Some(FileSharingError::InvalidHashConcurrency(0))
+    ));
+    assert_eq!(manager.max_concurrent_hashes(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_shares_stay_within_hash_limit() {
+    use gigi_file_sharing::HashLimiter;
+
+    let temp_dir = TempDir::new().unwrap();
+    let limiter = HashLimiter::new(2).unwrap();
+
+    let mut tasks = Vec::new();
+    for i in 0..8 {
+        let file = temp_dir.path().join(format!("bulk-{}.bin", i));
+        fs::write(&file, vec![i as u8; 4 * 1024 * 1024]).unwrap();
+        let mut manager = FileSharingManager::new().with_hash_limiter(limiter.clone());
+        tasks.push(tokio::spawn(async move {
+            manager.share_file(&file).await.unwrap()
+        }));
+    }
+
+    for task in tasks {
+        let share_code = task.await.unwrap();
+        assert!(!share_code.is_empty());
+    }
+
+    assert!(limiter.peak_in_flight() >= 1);
+    assert!(limiter.peak_in_flight() <= 2);
+    assert_eq!(limiter.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn test_single_share_with_serialized_hashing() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("single.txt");
+    fs::write(&test_file, b"Hello, World!").unwrap();
+
+    let mut manager = FileSharingManager::new();
+    manager.set_max_concurrent_hashes(1).unwrap();
+    let share_code = manager.share_file(&test_file).await.unwrap();
+
+    assert_eq!(manager.shared_files[&share_code].info.size, 13);
+    assert_eq!(manager.hash_limiter().peak_in_flight(), 1);
+}
+
+#[tokio::test]
+async fn test_stability_check_detects_file_being_written() {
+    use std::io::Write;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("recording.bin");
+    fs::write(&test_file, b"first part").unwrap();
+
+    let mut manager = FileSharingManager::new();
+    assert_eq!(manager.stability_check(), None);
+    manager.set_stability_check(Some(Duration::from_millis(300)));
+
+    // Another writer appends between the two samples
+    let writing = test_file.clone();
+    let writer = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut file = fs::OpenOptions::new().append(true).open(writing).unwrap();
+        file.write_all(b", second part").unwrap();
+    });
+    let err = manager.share_file(&test_file).await.unwrap_err();
+    writer.await.unwrap();
+    assert!(matches!(
+        err.downcast_ref::<FileSharingError>(),
+        Some(FileSharingError::FileUnstable(path)) if path.ends_with("recording.bin")
+    ));
+    assert!(manager.shared_files.is_empty());
+
+    // Once the writer is done the file is shared
+    let share_code = manager.share_file(&test_file).await.unwrap();
+    assert_eq!(manager.shared_files[&share_code].info.size, 23);
+}
+
 #[tokio::test]
 async fn test_share_file() {
     let temp_dir = TempDir::new().unwrap();
@@ -124,6 +359,201 @@ async fn test_share_modified_file() {
     assert_eq!(files.len(), 1);
 }
 
+#[tokio::test]
+async fn test_share_changed_file_keep_code_policy() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("test.txt");
+    fs::write(&test_file, b"Original content").unwrap();
+
+    let mut manager = FileSharingManager::new();
+    manager.set_overwrite_policy(ShareOverwritePolicy::KeepCode);
+    let code = manager.share_file(&test_file).await.unwrap();
+    fs::write(&test_file, b"Modified content").unwrap();
+
+    assert_eq!(manager.share_file(&test_file).await.unwrap(), code);
+    let shared = &manager.shared_files[&code];
+    assert_eq!(
+        shared.info.hash,
+        manager.calculate_file_hash(&test_file).unwrap()
+    );
+    assert!(!shared.revoked);
+}
+
+#[tokio::test]
+async fn test_share_changed_file_new_code_policy() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("test.txt");
+    fs::write(&test_file, b"Original content").unwrap();
+
+    let mut manager = FileSharingManager::new();
+    manager.set_overwrite_policy(ShareOverwritePolicy::NewCode);
+    let old_code = manager.share_file(&test_file).await.unwrap();
+    let old_hash = manager.shared_files[&old_code].info.hash.clone();
+    fs::write(&test_file, b"Modified content").unwrap();
+
+    let new_code = manager.share_file(&test_file).await.unwrap();
+    assert_ne!(new_code, old_code);
+
+    // The old code keeps describing the old content, revoked
+    let old = &manager.shared_files[&old_code];
+    assert!(old.revoked);
+    assert_eq!(old.info.hash, old_hash);
+    let new = &manager.shared_files[&new_code];
+    assert!(!new.revoked);
+    assert_eq!(
+        new.info.hash,
+        manager.calculate_file_hash(&test_file).unwrap()
+    );
+
+    // Re-sharing the unchanged file finds the new entry
+    assert_eq!(manager.share_file(&test_file).await.unwrap(), new_code);
+}
+
+#[tokio::test]
+async fn test_share_changed_file_reject_policy() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("test.txt");
+    fs::write(&test_file, b"Original content").unwrap();
+
+    let mut manager = FileSharingManager::new();
+    manager.set_overwrite_policy(ShareOverwritePolicy::Reject);
+    let code = manager.share_file(&test_file).await.unwrap();
+    let hash = manager.shared_files[&code].info.hash.clone();
+    fs::write(&test_file, b"Modified content").unwrap();
+
+    let error = manager.share_file(&test_file).await.unwrap_err();
+    match error.downcast_ref::<FileSharingError>() {
+        Some(FileSharingError::FileChanged(changed_code)) => assert_eq!(changed_code, &code),
+        other => panic!("Expected FileChanged, got {:?}", other),
+    }
+    assert_eq!(manager.list_shared_files().len(), 1);
+    assert_eq!(manager.shared_files[&code].info.hash, hash);
+}
+
+#[tokio::test]
+async fn test_share_file_unhashed() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("unhashed.bin");
+    fs::write(&test_file, vec![3u8; CHUNK_SIZE + 1]).unwrap();
+
+    let mut manager = FileSharingManager::new();
+    let code = manager.share_file_unhashed(&test_file).await.unwrap();
+    let info = &manager.shared_files[&code].info;
+    assert!(info.hash.is_empty());
+    assert_eq!(info.size, (CHUNK_SIZE + 1) as u64);
+    assert_eq!(info.chunk_count, 2);
+
+    // Sharing it unhashed again keeps the code; a hashed share gets its own
+    assert_eq!(manager.share_file_unhashed(&test_file).await.unwrap(), code);
+    let hashed_code = manager.share_file(&test_file).await.unwrap();
+    assert_ne!(hashed_code, code);
+    assert_eq!(
+        manager.shared_files[&hashed_code].info.hash,
+        manager.calculate_file_hash(&test_file).unwrap()
+    );
+
+    // Refreshing a changed file keeps it unhashed
+    fs::write(&test_file, vec![3u8; 10]).unwrap();
+    assert!(manager.refresh_shared_file(&code).unwrap());
+    let info = &manager.shared_files[&code].info;
+    assert!(info.hash.is_empty());
+    assert_eq!(info.size, 10);
+    assert_eq!(info.chunk_count, 1);
+}
+
+#[tokio::test]
+async fn test_refresh_truncated_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("truncated.bin");
+    fs::write(&test_file, vec![7u8; CHUNK_SIZE * 3]).unwrap();
+
+    let mut manager = FileSharingManager::new();
+    let code = manager.share_file(&test_file).await.unwrap();
+    assert!(!manager.refresh_shared_file(&code).unwrap());
+
+    // Truncate the file behind the manager's back
+    fs::write(&test_file, vec![7u8; CHUNK_SIZE + 10]).unwrap();
+
+    assert!(manager.refresh_shared_file(&code).unwrap());
+    let info = &manager.list_shared_files()[0].info;
+    assert_eq!(info.size, (CHUNK_SIZE + 10) as u64);
+    assert_eq!(info.chunk_count, 2);
+    assert_eq!(info.hash, manager.calculate_file_hash(&test_file).unwrap());
+
+    assert!(manager.refresh_shared_file("unknown").is_err());
+}
+
+#[tokio::test]
+async fn test_compute_refresh_applies_only_to_current_share() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("background.bin");
+    fs::write(&test_file, vec![5u8; CHUNK_SIZE * 2]).unwrap();
+
+    let mut manager = FileSharingManager::new();
+    let code = manager.share_file(&test_file).await.unwrap();
+    let snapshot = manager.shared_files[&code].clone();
+    assert!(FileSharingManager::compute_refresh(&snapshot)
+        .unwrap()
+        .is_none());
+
+    fs::write(&test_file, vec![5u8; 10]).unwrap();
+    let updated = FileSharingManager::compute_refresh(&snapshot)
+        .unwrap()
+        .expect("File changed");
+    assert_eq!(updated.info.size, 10);
+    assert_eq!(updated.info.chunk_count, 1);
+    // Computing does not touch the manager
+    assert_eq!(
+        manager.shared_files[&code].info.size,
+        (CHUNK_SIZE * 2) as u64
+    );
+
+    assert!(manager.apply_refresh(&code, updated.clone()).unwrap());
+    assert_eq!(manager.shared_files[&code].info.size, 10);
+
+    // A file unshared while it was refreshed stays unshared
+    manager.unshare_file(&code).unwrap();
+    assert!(!manager.apply_refresh(&code, updated).unwrap());
+    assert!(manager.list_shared_files().is_empty());
+}
+
+#[tokio::test]
+async fn test_reshare_unchanged_file_skips_hashing() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("large.bin");
+    fs::write(&test_file, vec![1u8; CHUNK_SIZE + 3]).unwrap();
+    // Files modified within the last seconds are never cached
+    let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+    let set_modified = |modified| {
+        fs::File::options()
+            .write(true)
+            .open(&test_file)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    };
+    set_modified(modified);
+
+    let mut manager = FileSharingManager::new();
+    let code = manager.share_file(&test_file).await.unwrap();
+    let hash = manager.shared_files[&code].info.hash.clone();
+    assert_eq!(manager.hash_cache_stats().misses, 1);
+
+    // Same size and mtime with different bytes: only a cache hit keeps the old hash
+    fs::write(&test_file, vec![2u8; CHUNK_SIZE + 3]).unwrap();
+    set_modified(modified);
+    manager.unshare_file(&code).unwrap();
+    let code = manager.share_file(&test_file).await.unwrap();
+    assert_eq!(manager.shared_files[&code].info.hash, hash);
+    assert_eq!(manager.hash_cache_stats().hits, 1);
+
+    // A new mtime invalidates the cached hash
+    set_modified(modified + std::time::Duration::from_secs(1));
+    assert!(manager.refresh_shared_file(&code).unwrap());
+    assert_ne!(manager.shared_files[&code].info.hash, hash);
+    assert_eq!(manager.hash_cache_stats().misses, 2);
+}
+
 #[tokio::test]
 async fn test_chunk_count_calculation() {
     let temp_dir = TempDir::new().unwrap();
@@ -239,6 +669,47 @@ async fn test_file_hash_empty_file() {
     );
 }
 
+#[tokio::test]
+async fn test_share_file_with_blake3_hash() {
+    let temp_dir = TempDir::new().unwrap();
+    let test_file = temp_dir.path().join("media.bin");
+    fs::write(&test_file, b"large media").unwrap();
+
+    let mut manager = FileSharingManager::new();
+    assert_eq!(manager.hash_algo(), HashAlgo::Sha256);
+    manager.set_hash_algo(HashAlgo::Blake3);
+    let share_code = manager.share_file(&test_file).await.unwrap();
+
+    let info = &manager.shared_files[&share_code].info;
+    assert_eq!(info.hash_algo, HashAlgo::Blake3);
+    assert_eq!(info.hash, blake3::hash(b"large media").to_hex().to_string());
+    assert_eq!(info.hash, HashAlgo::Blake3.hash_file(&test_file).unwrap());
+    assert_ne!(info.hash, HashAlgo::Sha256.hash_file(&test_file).unwrap());
+}
+
+#[tokio::test]
+async fn test_shared_stats_from_memory() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = FileSharingManager::new();
+    assert_eq!(manager.shared_stats().await.unwrap().file_count, 0);
+
+    for (name, size) in [("a.bin", 10usize), ("b.bin", CHUNK_SIZE + 5), ("c.bin", 0)] {
+        let path = temp_dir.path().join(name);
+        fs::write(&path, vec![1u8; size]).unwrap();
+        manager.share_file(&path).await.unwrap();
+    }
+    let code = manager
+        .share_content_uri("content://test/doc", "doc.pdf", 99)
+        .await
+        .unwrap();
+    manager.shared_files.get_mut(&code).unwrap().revoked = true;
+
+    let stats = manager.shared_stats().await.unwrap();
+    assert_eq!(stats.file_count, 3);
+    assert_eq!(stats.total_bytes, (10 + CHUNK_SIZE + 5) as u64);
+    assert_eq!(stats.revoked_count, 1);
+}
+
 #[tokio::test]
 async fn test_share_content_uri() {
     let mut manager = FileSharingManager::new();
@@ -273,6 +744,36 @@ async fn test_share_content_uri_with_valid_scheme() {
     assert!(share_code.chars().all(|c| c.is_ascii_hexdigit()));
 }
 
+#[tokio::test]
+async fn test_share_file_routes_content_uri() {
+    use gigi_file_sharing::FilePath;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    let uri = "content://com.android.providers.media.documents/document/photo.jpg";
+    let contents = vec![4u8; CHUNK_SIZE + 10];
+    let mut manager = FileSharingManager::new();
+
+    // Without a chunk reader the URI cannot be read, but it is not looked up on disk
+    let error = manager.share_file(Path::new(uri)).await.unwrap_err();
+    assert!(error.to_string().contains("Invalid URI"), "{}", error);
+
+    let served = contents.clone();
+    manager.set_chunk_reader(Arc::new(move |path, offset, length| {
+        assert!(matches!(path, FilePath::Url(url) if url.as_str() == uri));
+        let start = (offset as usize).min(served.len());
+        let end = (start + length).min(served.len());
+        Ok(served[start..end].to_vec())
+    }));
+    let share_code = manager.share_file(Path::new(uri)).await.unwrap();
+
+    let shared = &manager.shared_files[&share_code];
+    assert!(matches!(&shared.path, FilePath::Url(url) if url.as_str() == uri));
+    assert_eq!(shared.info.name, "photo.jpg");
+    assert_eq!(shared.info.size, contents.len() as u64);
+    assert_eq!(shared.info.chunk_count, 2);
+}
+
 #[tokio::test]
 async fn test_chunk_reader_setter() {
     use std::sync::Arc;
@@ -290,3 +791,448 @@ async fn test_chunk_reader_setter() {
 
     // Cannot directly test reader usage without URI, but should not panic
 }
+
+#[tokio::test]
+async fn test_search_shared_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = FileSharingManager::new();
+
+    for name in [
+        "Report-2024.pdf",
+        "annual_report.docx",
+        "photo.jpg",
+        "notes.txt",
+    ] {
+        let path = temp_dir.path().join(name);
+        fs::write(&path, name.as_bytes()).unwrap();
+        manager.share_file(&path).await.unwrap();
+    }
+
+    // Case-insensitive substring match
+    let mut names: Vec<_> = manager
+        .search_shared_files("REPORT")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|file| file.info.name.clone())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["Report-2024.pdf", "annual_report.docx"]);
+
+    assert_eq!(manager.search_shared_files(".jpg").await.unwrap().len(), 1);
+    assert!(manager
+        .search_shared_files("missing")
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Empty query matches everything
+    assert_eq!(manager.search_shared_files("").await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_search_shared_files_queries_store() {
+    use gigi_store::FileSharingStore;
+    use std::sync::Arc;
+
+    let db_file = tempfile::NamedTempFile::new().unwrap();
+    let db = sea_orm::Database::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        db_file.path().to_str().unwrap().replace("\\", "/")
+    ))
+    .await
+    .unwrap();
+    <gigi_store::migration::Migrator as gigi_store::migration::MigratorTrait>::up(&db, None)
+        .await
+        .unwrap();
+    let store = Arc::new(FileSharingStore::new(db).await.unwrap());
+    let mut manager = FileSharingManager::new().with_store(Arc::clone(&store));
+
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["Report-2024.pdf", "photo.jpg"] {
+        let path = temp_dir.path().join(name);
+        fs::write(&path, name.as_bytes()).unwrap();
+        manager.share_file(&path).await.unwrap();
+    }
+    manager.flush_store().await;
+
+    let found = manager.search_shared_files("report").await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].info.name, "Report-2024.pdf");
+    assert!(manager.search_shared_files("50%").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_unshare_where() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut manager = FileSharingManager::new();
+
+    let mut image_codes = Vec::new();
+    for name in ["cat.png", "dog.jpg", "notes.txt", "song.mp3"] {
+        let path = temp_dir.path().join(name);
+        fs::write(&path, name.as_bytes()).unwrap();
+        let code = manager.share_file(&path).await.unwrap();
+        if name.ends_with(".png") || name.ends_with(".jpg") {
+            image_codes.push(code);
+        }
+    }
+
+    let mut removed = manager
+        .unshare_where(|file| file.info.name.ends_with(".png") || file.info.name.ends_with(".jpg"))
+        .await
+        .unwrap();
+    removed.sort();
+    image_codes.sort();
+    assert_eq!(removed, image_codes);
+
+    let mut remaining: Vec<_> = manager
+        .list_shared_files()
+        .into_iter()
+        .map(|file| file.info.name.clone())
+        .collect();
+    remaining.sort();
+    assert_eq!(remaining, vec!["notes.txt", "song.mp3"]);
+
+    // Nothing left to match
+    assert!(manager
+        .unshare_where(|file| file.info.name.ends_with(".png"))
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_unshare_where_deletes_from_store_before_returning() {
+    use gigi_store::FileSharingStore;
+    use sea_orm::ConnectionTrait;
+    use std::sync::Arc;
+
+    let db_file = tempfile::NamedTempFile::new().unwrap();
+    let db = sea_orm::Database::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        db_file.path().to_str().unwrap().replace("\\", "/")
+    ))
+    .await
+    .unwrap();
+    <gigi_store::migration::Migrator as gigi_store::migration::MigratorTrait>::up(&db, None)
+        .await
+        .unwrap();
+    let store = Arc::new(FileSharingStore::new(db.clone()).await.unwrap());
+    let mut manager = FileSharingManager::new().with_store(Arc::clone(&store));
+
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["cat.png", "dog.png", "notes.txt"] {
+        let path = temp_dir.path().join(name);
+        fs::write(&path, name.as_bytes()).unwrap();
+        manager.share_file(&path).await.unwrap();
+    }
+    manager.flush_store().await;
+
+    let removed = manager
+        .unshare_where(|file| file.info.name == "cat.png")
+        .await
+        .unwrap();
+    assert_eq!(removed.len(), 1);
+    // Already gone from the store without flushing
+    let stored = store.list_shared_files().await.unwrap();
+    assert_eq!(stored.len(), 2);
+    assert!(stored.iter().all(|file| file.file_name != "cat.png"));
+
+    // A failed delete is returned and leaves the files shared
+    db.execute_unprepared("DROP TABLE shared_files")
+        .await
+        .unwrap();
+    assert!(manager
+        .unshare_where(|file| file.info.name.ends_with(".png"))
+        .await
+        .is_err());
+    assert_eq!(manager.list_shared_files().len(), 2);
+}
+
+#[tokio::test]
+async fn test_share_directory_recursively() {
+    use gigi_file_sharing::ShareCancelToken;
+
+    let temp_dir = TempDir::new().unwrap();
+    let nested = temp_dir.path().join("album").join("2024");
+    fs::create_dir_all(&nested).unwrap();
+    fs::write(temp_dir.path().join("a.txt"), b"a").unwrap();
+    fs::write(nested.join("b.jpg"), b"b").unwrap();
+
+    let mut manager = FileSharingManager::new();
+    let result = manager
+        .share_directory(temp_dir.path(), &ShareCancelToken::new())
+        .await
+        .unwrap();
+
+    assert!(!result.cancelled);
+    assert!(result.failed.is_empty());
+    let paths: Vec<_> = result.shared.iter().map(|(path, _)| path.clone()).collect();
+    assert_eq!(
+        paths,
+        vec![temp_dir.path().join("a.txt"), nested.join("b.jpg")]
+    );
+    assert_eq!(manager.list_shared_files().len(), 2);
+
+    // Not a directory
+    assert!(manager
+        .share_directory(&temp_dir.path().join("a.txt"), &ShareCancelToken::new())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_share_directory_records_relative_paths() {
+    use gigi_file_sharing::ShareCancelToken;
+
+    let temp_dir = TempDir::new().unwrap();
+    let album = temp_dir.path().join("album");
+    fs::create_dir_all(album.join("2024")).unwrap();
+    fs::write(album.join("cover.jpg"), b"cover").unwrap();
+    fs::write(album.join("2024").join("beach.jpg"), b"beach").unwrap();
+    let single = temp_dir.path().join("single.txt");
+    fs::write(&single, b"single").unwrap();
+
+    let mut manager = FileSharingManager::new();
+    let result = manager
+        .share_directory(&album, &ShareCancelToken::new())
+        .await
+        .unwrap();
+
+    let relative_paths: Vec<_> = result
+        .shared
+        .iter()
+        .map(|(_, code)| manager.shared_files[code].info.relative_path.clone())
+        .collect();
+    assert_eq!(
+        relative_paths,
+        vec![
+            Some("album/2024/beach.jpg".to_string()),
+            Some("album/cover.jpg".to_string()),
+        ]
+    );
+
+    // Single file shares stay flat
+    let code = manager.share_file(&single).await.unwrap();
+    assert_eq!(manager.shared_files[&code].info.relative_path, None);
+}
+
+/// Split a shared file into its chunks as `FileInfo` describes them
+fn chunks_of(info: &gigi_file_sharing::FileInfo, data: &[u8]) -> Vec<Vec<u8>> {
+    (0..info.chunk_count)
+        .map(|index| {
+            let (offset, max_len) = info.chunk_span(index).unwrap();
+            let start = offset as usize;
+            data[start..(start + max_len).min(data.len())].to_vec()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_content_defined_chunks_survive_edit_at_start() {
+    let temp_dir = TempDir::new().unwrap();
+    // Pseudo-random content, so boundaries depend on the data
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let original: Vec<u8> = (0..4 * 1024 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let mut edited = b"edit".to_vec();
+    edited.extend_from_slice(&original);
+    let original_path = temp_dir.path().join("original.bin");
+    let edited_path = temp_dir.path().join("edited.bin");
+    fs::write(&original_path, &original).unwrap();
+    fs::write(&edited_path, &edited).unwrap();
+
+    let changed_chunks =
+        |old: &[Vec<u8>], new: &[Vec<u8>]| new.iter().filter(|chunk| !old.contains(chunk)).count();
+
+    // Fixed chunks all shift
+    let mut manager = FileSharingManager::new();
+    assert!(!manager.content_defined_chunking());
+    let old_code = manager.share_file(&original_path).await.unwrap();
+    let new_code = manager.share_file(&edited_path).await.unwrap();
+    let old_info = &manager.shared_files[&old_code].info;
+    let new_info = &manager.shared_files[&new_code].info;
+    assert_eq!(old_info.chunk_offsets, None);
+    let fixed_changed = changed_chunks(
+        &chunks_of(old_info, &original),
+        &chunks_of(new_info, &edited),
+    );
+    assert_eq!(fixed_changed, new_info.chunk_count);
+
+    // Content-defined chunks only change around the edit
+    let mut manager = FileSharingManager::new();
+    manager.set_content_defined_chunking(true);
+    let old_code = manager.share_file(&original_path).await.unwrap();
+    let new_code = manager.share_file(&edited_path).await.unwrap();
+    let old_info = &manager.shared_files[&old_code].info;
+    let new_info = &manager.shared_files[&new_code].info;
+    let offsets = new_info.chunk_offsets.as_ref().unwrap();
+    assert_eq!(offsets.len(), new_info.chunk_count);
+    assert_eq!(offsets[0], 0);
+    let new_chunks = chunks_of(new_info, &edited);
+    assert_eq!(new_chunks.concat(), edited);
+    assert!(new_info.chunk_count > 4, "{} chunks", new_info.chunk_count);
+    let cdc_changed = changed_chunks(&chunks_of(old_info, &original), &new_chunks);
+    assert!(
+        cdc_changed <= 2,
+        "{} of {} chunks changed",
+        cdc_changed,
+        new_info.chunk_count
+    );
+}
+
+#[tokio::test]
+async fn test_share_directory_cancelled_after_first_file() {
+    use gigi_file_sharing::ShareCancelToken;
+    use std::sync::Arc;
+
+    let temp_dir = TempDir::new().unwrap();
+    for name in ["1.txt", "2.txt", "3.txt"] {
+        fs::write(temp_dir.path().join(name), name.as_bytes()).unwrap();
+    }
+
+    // The clock is read once per generated share code, so it cancels the
+    // share as soon as the first file has been given a code
+    let token = ShareCancelToken::new();
+    let canceller = token.clone();
+    let mut manager = FileSharingManager::new().with_clock(Arc::new(move || {
+        canceller.cancel();
+        std::time::SystemTime::now()
+    }));
+
+    let result = manager
+        .share_directory(temp_dir.path(), &token)
+        .await
+        .unwrap();
+
+    assert!(result.cancelled);
+    assert_eq!(result.shared.len(), 1);
+    assert_eq!(result.shared[0].0, temp_dir.path().join("1.txt"));
+    // The file shared before cancelling stays shared
+    let shared = manager.list_shared_files();
+    assert_eq!(shared.len(), 1);
+    assert_eq!(shared[0].share_code, result.shared[0].1);
+    assert_eq!(shared[0].info.name, "1.txt");
+}
+
+#[test]
+fn test_valid_share_codes() {
+    use gigi_file_sharing::{MAX_SHARE_CODE_LENGTH, MIN_SHARE_CODE_LENGTH};
+
+    assert!(is_valid_share_code("a1b2c3d4"));
+    assert!(is_valid_share_code(&"0".repeat(MIN_SHARE_CODE_LENGTH)));
+    assert!(is_valid_share_code(&"f".repeat(MAX_SHARE_CODE_LENGTH)));
+
+    // Generated codes of any configurable length are valid
+    let mut manager = FileSharingManager::new();
+    for length in [MIN_SHARE_CODE_LENGTH, 8, 16, MAX_SHARE_CODE_LENGTH] {
+        manager.set_share_code_length(length).unwrap();
+        assert!(is_valid_share_code(
+            &manager.generate_share_code("photo.jpg").unwrap()
+        ));
+    }
+}
+
+#[test]
+fn test_wrong_length_share_codes() {
+    use gigi_file_sharing::{MAX_SHARE_CODE_LENGTH, MIN_SHARE_CODE_LENGTH};
+
+    assert!(!is_valid_share_code(""));
+    assert!(!is_valid_share_code(&"a".repeat(MIN_SHARE_CODE_LENGTH - 1)));
+    assert!(!is_valid_share_code(&"a".repeat(MAX_SHARE_CODE_LENGTH + 1)));
+    assert_eq!(normalize_share_code("   "), None);
+    assert_eq!(normalize_share_code(" abc "), None);
+}
+
+#[test]
+fn test_non_hex_share_codes() {
+    assert!(!is_valid_share_code("a1b2c3g4"));
+    assert!(!is_valid_share_code("a1b2 c3d4"));
+    assert!(!is_valid_share_code("a1b2-c3d4"));
+    assert!(!is_valid_share_code("ä1b2c3d4"));
+    // Uppercase is only accepted after normalizing
+    assert!(!is_valid_share_code("A1B2C3D4"));
+    assert_eq!(normalize_share_code("a1b2-c3d4"), None);
+    assert_eq!(normalize_share_code("xyz12345"), None);
+}
+
+#[test]
+fn test_normalize_share_code() {
+    assert_eq!(
+        normalize_share_code("  A1B2C3D4\n"),
+        Some("a1b2c3d4".to_string())
+    );
+    assert_eq!(
+        normalize_share_code("deadbeef"),
+        Some("deadbeef".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_load_from_store_keeps_newer_duplicate_share_code() {
+    use gigi_store::{FileSharingStore, SharedFileInfo};
+    use std::sync::Arc;
+
+    let db_file = tempfile::NamedTempFile::new().unwrap();
+    let db = sea_orm::Database::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        db_file.path().to_str().unwrap().replace("\\", "/")
+    ))
+    .await
+    .unwrap();
+    <gigi_store::migration::Migrator as gigi_store::migration::MigratorTrait>::up(&db, None)
+        .await
+        .unwrap();
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    let temp_dir = TempDir::new().unwrap();
+    // Same code differing only by case, as left behind by an older version
+    for (code, name, created_at) in [
+        ("abcd1234", "old.txt", 1_000),
+        ("ABCD1234", "new.txt", 2_000),
+        ("ef567890", "other.txt", 1_500),
+    ] {
+        let path = temp_dir.path().join(name);
+        fs::write(&path, name).unwrap();
+        store
+            .store_shared_file(&SharedFileInfo {
+                share_code: code.to_string(),
+                file_name: name.to_string(),
+                file_path: path.to_string_lossy().into_owned(),
+                file_size: name.len() as u64,
+                hash: "hash".to_string(),
+                hash_algo: HashAlgo::Sha256,
+                chunk_count: 1,
+                thumbnail_path: None,
+                created_at,
+                revoked: false,
+                modified_at: None,
+                relative_path: None,
+                chunk_offsets: None,
+            })
+            .await
+            .unwrap();
+    }
+
+    let mut manager = FileSharingManager::new().with_store(Arc::new(store));
+    let conflicts = manager.load_from_store().await.unwrap();
+
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].share_code, "abcd1234");
+    assert_eq!(conflicts[0].kept.name, "new.txt");
+    assert_eq!(conflicts[0].dropped.name, "old.txt");
+
+    let mut names: Vec<_> = manager
+        .list_shared_files()
+        .into_iter()
+        .map(|file| file.info.name.clone())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["new.txt", "other.txt"]);
+}
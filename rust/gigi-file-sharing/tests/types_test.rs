@@ -2,7 +2,7 @@
 //
 // Comprehensive tests for file sharing types
 
-use gigi_file_sharing::{FileInfo, FilePath, SharedFile};
+use gigi_file_sharing::{FileInfo, FilePath, HashAlgo, SharedFile, CHUNK_SIZE};
 use std::path::PathBuf;
 use url::Url;
 
@@ -54,8 +54,12 @@ fn test_file_info_creation() {
         name: "document.pdf".to_string(),
         size: 1024 * 1024,
         hash: "abc123def456".to_string(),
+        hash_algo: Default::default(),
         chunk_count: 4,
         created_at: 1640995200,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: None,
     };
 
     assert_eq!(info.id, "test123");
@@ -73,8 +77,12 @@ fn test_file_info_serialization() {
         name: "file.txt".to_string(),
         size: 512,
         hash: "123abc".to_string(),
+        hash_algo: Default::default(),
         chunk_count: 1,
         created_at: 1234567890,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: None,
     };
 
     let json = serde_json::to_string(&info).unwrap();
@@ -95,8 +103,12 @@ fn test_shared_file_creation() {
         name: "image.jpg".to_string(),
         size: 2048,
         hash: "hash456".to_string(),
+        hash_algo: Default::default(),
         chunk_count: 1,
         created_at: 1640995200,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: None,
     };
 
     let shared_file = SharedFile {
@@ -119,8 +131,12 @@ fn test_shared_file_serialization() {
             name: "file.txt".to_string(),
             size: 512,
             hash: "abc123".to_string(),
+            hash_algo: Default::default(),
             chunk_count: 1,
             created_at: 1640995200,
+            modified_at: None,
+            relative_path: None,
+            chunk_offsets: None,
         },
         path: FilePath::Path(PathBuf::from("/test/file.txt")),
         share_code: "share123".to_string(),
@@ -142,8 +158,12 @@ fn test_shared_file_equality() {
         name: "file.txt".to_string(),
         size: 512,
         hash: "abc".to_string(),
+        hash_algo: Default::default(),
         chunk_count: 1,
         created_at: 1640995200,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: None,
     };
 
     let file1 = SharedFile {
@@ -172,8 +192,12 @@ fn test_shared_file_path_equality() {
         name: "file.txt".to_string(),
         size: 512,
         hash: "abc".to_string(),
+        hash_algo: Default::default(),
         chunk_count: 1,
         created_at: 1640995200,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: None,
     };
 
     let path = PathBuf::from("/test/file.txt");
@@ -222,8 +246,12 @@ fn test_file_info_clone() {
         name: "file.txt".to_string(),
         size: 512,
         hash: "abc".to_string(),
+        hash_algo: Default::default(),
         chunk_count: 1,
         created_at: 1640995200,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: None,
     };
 
     let cloned = info.clone();
@@ -240,8 +268,12 @@ fn test_shared_file_clone() {
             name: "file.txt".to_string(),
             size: 512,
             hash: "abc".to_string(),
+            hash_algo: Default::default(),
             chunk_count: 1,
             created_at: 1640995200,
+            modified_at: None,
+            relative_path: None,
+            chunk_offsets: None,
         },
         path: FilePath::Path(PathBuf::from("/test/file.txt")),
         share_code: "code123".to_string(),
@@ -253,3 +285,66 @@ fn test_shared_file_clone() {
     assert_eq!(shared_file.share_code, cloned.share_code);
     assert_eq!(shared_file.revoked, cloned.revoked);
 }
+
+#[test]
+fn test_file_info_hash_algo_round_trip() {
+    for hash_algo in [HashAlgo::Sha256, HashAlgo::Blake3] {
+        let info = FileInfo {
+            id: "algo123".to_string(),
+            name: "video.mp4".to_string(),
+            size: 4096,
+            hash: "ab".repeat(32),
+            hash_algo,
+            chunk_count: 1,
+            created_at: 1640995200,
+            modified_at: None,
+            relative_path: None,
+            chunk_offsets: None,
+        };
+
+        let json = serde_json::to_string(&info).unwrap();
+        let deserialized: FileInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.hash_algo, hash_algo);
+        assert_eq!(deserialized.hash, info.hash);
+    }
+}
+
+#[test]
+fn test_file_info_without_hash_algo_defaults_to_sha256() {
+    // As sent by peers that predate the hash algorithm field
+    let json =
+        r#"{"id":"old123","name":"old.txt","size":10,"hash":"abc","chunk_count":1,"created_at":0}"#;
+    let info: FileInfo = serde_json::from_str(json).unwrap();
+
+    assert_eq!(info.hash_algo, HashAlgo::Sha256);
+    assert_eq!(info.modified_at, None);
+    assert_eq!(info.relative_path, None);
+    assert_eq!(info.chunk_offsets, None);
+}
+
+#[test]
+fn test_file_info_chunk_span() {
+    let mut info = FileInfo {
+        id: "span".to_string(),
+        name: "span.bin".to_string(),
+        size: 300_000,
+        hash: String::new(),
+        hash_algo: HashAlgo::Sha256,
+        chunk_count: 2,
+        created_at: 0,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: None,
+    };
+    // Fixed chunks, the last one holds the remainder
+    assert_eq!(info.chunk_span(0), Some((0, CHUNK_SIZE)));
+    assert_eq!(info.chunk_span(1), Some((CHUNK_SIZE as u64, CHUNK_SIZE)));
+
+    // Content-defined chunks span to the next offset
+    info.chunk_offsets = Some(vec![0, 100_000, 250_000]);
+    info.chunk_count = 3;
+    assert_eq!(info.chunk_span(0), Some((0, 100_000)));
+    assert_eq!(info.chunk_span(1), Some((100_000, 150_000)));
+    assert_eq!(info.chunk_span(2), Some((250_000, 50_000)));
+    assert_eq!(info.chunk_span(3), None);
+}
@@ -78,7 +78,34 @@ pub fn init_logging() {
 /// init_logging_with_config(config);
 /// ```
 pub fn init_logging_with_config(config: LogConfig) {
+    try_init_logging_with_config(config);
+}
+
+/// Initialize logging with custom configuration, unless a global tracing
+/// subscriber is already set
+///
+/// Several components of one app, e.g. the app and a plugin, may each
+/// initialize logging; only the first call installs a subscriber, and later
+/// calls, or calls after the app set up its own subscriber, do nothing.
+///
+/// # Returns
+/// Whether this call installed the subscriber
+///
+/// # Examples
+/// ```rust
+/// use gigi_logging::{try_init_logging_with_config, LogConfig};
+///
+/// try_init_logging_with_config(LogConfig::default());
+/// // Already initialized
+/// assert!(!try_init_logging_with_config(LogConfig::default()));
+/// ```
+pub fn try_init_logging_with_config(config: LogConfig) -> bool {
+    let mut installed = false;
     INIT_ONCE.call_once(|| {
+        if tracing::dispatcher::has_been_set() {
+            return;
+        }
+
         let env_filter = EnvFilter::try_from_default_env()
             .unwrap_or_else(|_| EnvFilter::new(format!("{} ,sqlx=warn", config.level)));
 
@@ -219,15 +246,18 @@ pub fn init_logging_with_config(config: LogConfig) {
             }
         };
 
-        // Set the global default subscriber
-        tracing::subscriber::set_global_default(subscriber)
-            .expect("Failed to set up global tracing subscriber");
+        // Set the global default subscriber, unless one was set meanwhile
+        if tracing::subscriber::set_global_default(subscriber).is_err() {
+            return;
+        }
+        installed = true;
 
-        // Redirect log crate logs to tracing
-        tracing_log::LogTracer::init().expect("Failed to initialize log tracer");
+        // Redirect log crate logs to tracing, unless a logger is already set
+        let _ = tracing_log::LogTracer::init();
 
         info!("Logging initialized with config: {:?}", config);
     });
+    installed
 }
 
 /// Get logger for a specific module
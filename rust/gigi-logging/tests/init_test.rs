@@ -0,0 +1,21 @@
+use gigi_logging::{init_logging, try_init_logging_with_config, LogConfig};
+use tracing::Level;
+
+#[test]
+fn test_init_after_existing_subscriber_is_a_no_op() {
+    // The embedding app set up its own subscriber first
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(Level::WARN)
+        .finish();
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let config = LogConfig {
+        level: Level::DEBUG,
+        json: true,
+        ..Default::default()
+    };
+    assert!(!try_init_logging_with_config(config));
+    // A second initialization does not panic either
+    init_logging();
+    assert!(!try_init_logging_with_config(LogConfig::default()));
+}
@@ -1,10 +1,11 @@
 use clap::Parser;
 use futures::StreamExt;
-use gigi_p2p::{P2pClient, P2pEvent, PersistenceConfig};
+use gigi_p2p::{P2pClient, P2pError, P2pEvent, PersistenceConfig};
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::time::Duration;
 use tokio::fs;
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
 
 /// Gigi P2P Chat - Simple and clean terminal chat
 #[derive(Parser)]
@@ -79,10 +80,20 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
         P2pEvent::NicknameUpdated { peer_id, nickname } => {
             println!("📝 Nickname updated: {} ({})", nickname, peer_id);
         }
+        P2pEvent::PresenceChanged {
+            nickname, status, ..
+        } => match status {
+            Some(status) => println!("💬 {} is now: {}", nickname, status),
+            None => println!("💬 {} cleared their status", nickname),
+        },
+        P2pEvent::ContactProfileReceived { peer_id, profile } => {
+            println!("📇 Profile of {}: {}", peer_id, profile.display_name);
+        }
         P2pEvent::DirectMessage {
             from,
             from_nickname,
             message,
+            ..
         } => {
             println!("💬 {} ({}): {}", from_nickname, from, message);
         }
@@ -93,6 +104,7 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
             filename,
             file_size,
             file_type,
+            ..
         } => {
             info!(
                 from = %from,
@@ -125,6 +137,7 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
             from_nickname,
             group,
             message,
+            ..
         } => {
             println!(
                 "📢 [{}/{}]: {} ({}): {}",
@@ -140,6 +153,7 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
             file_size,
             file_type,
             message,
+            ..
         } => {
             info!(
                 group = %group,
@@ -164,12 +178,54 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
             );
             println!("   💬 {}", message);
         }
+        P2pEvent::RateLimited { peer } => {
+            println!("🚫 Dropping messages from {}: rate limit exceeded", peer);
+        }
+        P2pEvent::DirectLocationMessage {
+            from_nickname,
+            location,
+            ..
+        } => {
+            println!("📍 {}: {}", from_nickname, location.fallback_text());
+        }
+        P2pEvent::GroupLocationMessage {
+            from_nickname,
+            group,
+            location,
+            ..
+        } => {
+            println!(
+                "📍 [{}/{}]: {}",
+                group,
+                from_nickname,
+                location.fallback_text()
+            );
+        }
+        P2pEvent::PollUpdated {
+            from_nickname,
+            group,
+            results,
+            ..
+        } => match results {
+            Some(results) => println!(
+                "📊 [{}] {} updated poll \"{}\": {:?}",
+                group, from_nickname, results.question, results.votes
+            ),
+            None => println!("📊 [{}] {} updated a poll", group, from_nickname),
+        },
         P2pEvent::GroupJoined { group } => {
             println!("✅ Joined group: {}", group);
         }
         P2pEvent::GroupLeft { group } => {
             println!("🚪 Left group: {}", group);
         }
+        P2pEvent::GroupMembershipChanged {
+            group,
+            online_count,
+            ..
+        } => {
+            println!("👥 {} members online in {}", online_count, group);
+        }
         P2pEvent::FileShared { file_id, info } => {
             println!(
                 "📎 File shared: {} (ID: {}) - {} bytes",
@@ -220,6 +276,45 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
                 filename, from_nickname, progress, downloaded_chunks, total_chunks
             );
         }
+        P2pEvent::GroupFileDownloadedByMember {
+            group,
+            filename,
+            nickname,
+            completed_count,
+            member_count,
+            ..
+        } => {
+            println!(
+                "📤 {} downloaded {} ({} of {} in {})",
+                nickname, filename, completed_count, member_count, group
+            );
+        }
+        P2pEvent::FileRangeReceived { range, .. } => {
+            println!(
+                "📦 Received {} bytes of {} from offset {}",
+                range.data.len(),
+                range.file_id,
+                range.start
+            );
+        }
+        P2pEvent::FileRangeFailed {
+            share_code, error, ..
+        } => {
+            println!("❌ Range request for {} failed: {}", share_code, error);
+        }
+        P2pEvent::FileUploadProgress {
+            filename,
+            to_nickname,
+            uploaded_chunks,
+            total_chunks,
+            ..
+        } => {
+            let progress = (uploaded_chunks as f32 / total_chunks as f32) * 100.0;
+            println!(
+                "📤 Upload progress for {} to {}: {:.1}% ({}/{})",
+                filename, to_nickname, progress, uploaded_chunks, total_chunks
+            );
+        }
         P2pEvent::FileDownloadCompleted {
             download_id: _,
             filename,
@@ -227,14 +322,25 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
             from_nickname,
             from_peer_id: _,
             path,
+            duration,
+            average_speed,
+            ..
         } => {
             println!(
-                "✅ Download completed: {} from {} saved to {}",
+                "✅ Download completed: {} from {} saved to {} in {:.1}s ({:.1} KB/s)",
                 filename,
                 from_nickname,
-                path.display()
+                path.display(),
+                duration.as_secs_f64(),
+                average_speed / 1024.0
             );
         }
+        P2pEvent::ConfigChanged { changed_fields } => {
+            info!("Configuration changed: {}", changed_fields.join(", "));
+        }
+        P2pEvent::ListenStopped { address } => {
+            warn!("Stopped listening on: {}", address);
+        }
         P2pEvent::ListeningOn { address } => {
             println!("🎯 Listening on: {}", address);
         }
@@ -244,6 +350,13 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
         P2pEvent::Disconnected { peer_id, nickname } => {
             println!("❌ Disconnected from: {} ({})", nickname, peer_id);
         }
+        P2pEvent::PeerProtocolsDiscovered {
+            nickname,
+            protocols,
+            ..
+        } => {
+            info!("{} supports: {}", nickname, protocols.join(", "));
+        }
         P2pEvent::FileShareRequest {
             from,
             from_nickname,
@@ -259,12 +372,22 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
         P2pEvent::FileRevoked { file_id } => {
             println!("🚫 File revoked: {}", file_id);
         }
+        P2pEvent::AvatarFetched {
+            peer_id,
+            avatar_hash,
+            ..
+        } => {
+            println!("🖼️ Avatar {} of {} cached", avatar_hash, peer_id);
+        }
         P2pEvent::FileListReceived { from, files } => {
             println!("📋 File list received from {}:", from);
             for file in files {
                 println!("  - {} ({} bytes)", file.name, file.size);
             }
         }
+        P2pEvent::RemoteFilesUpdated { from, files } => {
+            println!("📋 {} now shares {} file(s)", from, files.len());
+        }
         P2pEvent::FileDownloadFailed {
             download_id: _,
             filename,
@@ -272,11 +395,13 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
             from_nickname,
             from_peer_id: _,
             error,
+            reason,
         } => {
             error!(
                 filename = %filename,
                 from_nickname = %from_nickname,
                 error = %error,
+                reason = ?reason,
                 "File download failed"
             );
             println!(
@@ -288,6 +413,33 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
             error!(error = %err, "P2P error occurred");
             println!("❌ Error: {}", err);
         }
+        P2pEvent::ConnectivityChanged(status) => {
+            info!(
+                listening = status.listening,
+                connected_peers = status.connected_peers,
+                mdns_active = status.mdns_active,
+                "Connectivity changed"
+            );
+        }
+        P2pEvent::InboundConnectionRejected { address, limit } => {
+            warn!(%address, limit, "Rejected inbound connection, limit reached");
+        }
+        P2pEvent::PeerIdChanged {
+            old_peer_id,
+            new_peer_id,
+        } => {
+            println!("🔑 Peer ID changed: {} -> {}", old_peer_id, new_peer_id);
+        }
+        P2pEvent::SyncProgress {
+            attempted, synced, ..
+        } => {
+            println!("📤 Synced {} of {} queued message(s)", synced, attempted);
+        }
+        P2pEvent::SyncFailed {
+            nickname, error, ..
+        } => {
+            println!("   ❌ Failed to sync a message to {}: {}", nickname, error);
+        }
         P2pEvent::PendingMessagesAvailable { peer, nickname } => {
             println!("📬 {} ({}) is now online!", nickname, peer);
 
@@ -311,6 +463,18 @@ async fn handle_p2p_event(event: P2pEvent, _output_dir: &PathBuf, _client: &mut
     }
 }
 
+/// Whether a send failed because the peer or this node is offline
+fn is_offline_error(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<P2pError>(),
+        Some(
+            P2pError::PeerNotDiscovered(_)
+                | P2pError::PeerUnreachable(_)
+                | P2pError::NotConnectedToNetwork
+        )
+    )
+}
+
 #[instrument(skip(client), fields(command = input))]
 async fn process_command(input: &str, client: &mut P2pClient, persistence_enabled: bool) -> bool {
     let parts: Vec<&str> = input.split_whitespace().collect();
@@ -357,13 +521,13 @@ async fn process_command(input: &str, client: &mut P2pClient, persistence_enable
                 );
                 if persistence_enabled {
                     match client.send_persistent_message(nickname, message).await {
-                        Ok(()) => {
+                        Ok(_) => {
                             println!("✅ Message sent to {}", nickname);
                             debug!("Direct message sent successfully");
                         }
                         Err(e) => {
                             // Check if this is an "offline" error (message saved)
-                            if e.to_string().contains("not online") {
+                            if is_offline_error(&e) {
                                 println!("✅ Message saved ({} is offline). Will send when they come online.", nickname);
                                 info!("Message saved for offline peer: {}", nickname);
                             } else {
@@ -378,13 +542,13 @@ async fn process_command(input: &str, client: &mut P2pClient, persistence_enable
                     }
                 } else {
                     match client.send_direct_message(nickname, message) {
-                        Ok(()) => {
+                        Ok(_) => {
                             println!("✅ Message sent to {}", nickname);
                             debug!("Direct message sent successfully");
                         }
                         Err(e) => {
                             // Check if this is an "offline" error (message saved)
-                            if e.to_string().contains("not online") {
+                            if is_offline_error(&e) {
                                 println!("✅ Message saved ({} is offline). Will send when they come online.", nickname);
                                 info!("Message saved for offline peer: {}", nickname);
                             } else {
@@ -549,7 +713,7 @@ async fn process_command(input: &str, client: &mut P2pClient, persistence_enable
                 let group = parts[1];
                 let message = parts[2..].join(" ");
                 match client.send_group_message(group, message) {
-                    Ok(()) => println!("✅ Message sent to group: {}", group),
+                    Ok(_) => println!("✅ Message sent to group: {}", group),
                     Err(e) => println!("❌ Failed to send to group: {}", e),
                 }
             }
@@ -564,7 +728,7 @@ async fn process_command(input: &str, client: &mut P2pClient, persistence_enable
                     .send_group_file(group, &PathBuf::from(file_path))
                     .await
                 {
-                    Ok(()) => println!("✅ File sent to group: {}", group),
+                    Ok(_) => println!("✅ File sent to group: {}", group),
                     Err(e) => println!("❌ Failed to send file to group: {}", e),
                 }
             }
@@ -654,7 +818,7 @@ async fn process_command(input: &str, client: &mut P2pClient, persistence_enable
             println!("👋 Goodbye!");
             info!("Initiating graceful shutdown");
             // Gracefully shutdown the P2P client
-            if let Err(e) = client.shutdown() {
+            if let Err(e) = client.drain_and_shutdown(Duration::from_secs(5)).await {
                 error!(error = %e, "Error during shutdown");
                 println!("⚠️ Error during shutdown: {}", e);
             } else {
@@ -5,6 +5,8 @@
 //! - **Direct Messaging**: Request-response protocol for 1-to-1 communication
 //! - **GossipSub**: Pub-sub protocol for group messaging
 //! - **File Sharing**: Request-response protocol for file chunk transfer
+//! - **Profile Exchange**: Request-response protocol for contact profiles
+//! - **Identify**: Exchange of the protocols each peer supports
 //!
 //! # Protocol Details
 //!
@@ -16,14 +18,18 @@
 //! Request                          Response
 //! ─────────                        ─────────
 //! DirectMessage::Text {           DirectResponse::Ack
-//!     message: String
+//!     message: String,
+//!     message_id: String,
+//!     disappear_after_secs: Option<u64>,
+//!     forwarded_from: Option<String>
 //! }
 //!
 //! DirectMessage::FileShare {
 //!     share_code: String,
 //!     filename: String,
 //!     file_size: u64,
-//!     file_type: String
+//!     file_type: String,
+//!     forwarded_from: Option<String>
 //! }                           DirectResponse::Ack
 //!
 //! DirectMessage::ShareGroup {
@@ -31,11 +37,19 @@
 //!     group_name: String,
 //!     inviter_nickname: String
 //! }                           DirectResponse::Ack
+//!
+//! DirectMessage::Location {
+//!     message_id: String,
+//!     lat: f64,
+//!     lon: f64,
+//!     label: Option<String>
+//! }                           DirectResponse::Ack
 //! ```
 //!
-//! ## File Sharing (`/file/1.0.0`)
+//! ## File Sharing (`/file/1.4.0` down to `/file/1.0.0`)
 //!
-//! Pull-based protocol for chunked file transfer:
+//! Pull-based protocol for chunked file transfer. Each connection uses the
+//! highest `FileTransferVersion` both peers support:
 //!
 //! ```text
 //! Request                          Response
@@ -47,8 +61,46 @@
 //!
 //! ListFiles                    FileList(Vec<FileInfo>)
 //!                                 or Error(String)
+//!
+//! GetAvatar(avatar_hash)        Avatar(Option<Vec<u8>>)
+//!
+//! GetRange(share_code,          Range(Option<FileRange>)
+//!          start, len)            or Range(None) if file unknown
+//!
+//! FilesChanged(Vec<FileInfo>)   Ack
 //! ```
 //!
+//! Chunk data is a CBOR byte string over 1.2, and an array of integers,
+//! nearly twice the size, over earlier versions. `GetRange` needs 1.3.
+//! Files with content-defined chunks (`FileInfo::chunk_offsets`) need 1.4:
+//! over earlier versions `GetFileInfo` for them is answered with
+//! `CONTENT_DEFINED_CHUNKS_ERROR`, as those peers would request fixed-size
+//! chunks.
+//!
+//! `FilesChanged` is pushed by a sharer to its connected peers whenever it
+//! shares or unshares files, so their view of its files stays current
+//! without polling `ListFiles`.
+//!
+//! ## Profile Exchange (`/gigi/profile/1.0.0`)
+//!
+//! Both sides send their profile, tagged with `PROFILE_EXCHANGE_VERSION`:
+//!
+//! ```text
+//! Request                          Response
+//! ─────────                        ─────────
+//! ProfileExchange {               ProfileExchange {
+//!     version: u32,                   version: u32,
+//!     profile: Profile                profile: Profile
+//! }                               }
+//! ```
+//!
+//! ## Identify (`/ipfs/id/1.0.0`)
+//!
+//! Sent by both sides on every new connection: the peer's agent version
+//! (`gigi/<version>`), listen addresses and the protocols it supports. Used
+//! to tell which protocols a peer speaks, e.g. whether an older app version
+//! lacks a file transfer version.
+//!
 //! ## GossipSub Configuration
 //!
 //! The GossipSub behaviour uses:
@@ -57,15 +109,20 @@
 //! - **Strict validation** to prevent message flood attacks
 //! - **10-second heartbeat** for mesh maintenance
 
+use async_trait::async_trait;
 use blake3::Hasher;
+use futures::{AsyncRead, AsyncWrite};
 use gigi_dns::GigiDnsBehaviour;
 use libp2p::{
+    connection_limits,
     gossipsub::{self, MessageAuthenticity, MessageId, ValidationMode},
-    kad, relay,
-    request_response::{self},
-    swarm::NetworkBehaviour,
+    identify, kad, relay,
+    request_response::{self, ProtocolSupport},
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour},
+    StreamProtocol,
 };
 use serde::{Deserialize, Serialize};
+use std::io;
 
 /// Direct messaging messages
 ///
@@ -76,10 +133,20 @@ use serde::{Deserialize, Serialize};
 /// - **Text**: Plain text message
 /// - **FileShare**: Announce a file share code to a peer
 /// - **ShareGroup**: Invite a peer to join a group
+/// - **Location**: Share a pinned location
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DirectMessage {
+    /// Text message with the sender-generated id used for receipts and storage
     Text {
         message: String,
+        #[serde(default)]
+        message_id: String,
+        /// Disappearing message timer in seconds, started when read
+        #[serde(default)]
+        disappear_after_secs: Option<u64>,
+        /// Nickname of the original author, set on forwarded messages
+        #[serde(default)]
+        forwarded_from: Option<String>,
     },
     /// File share announcement with share code and metadata
     /// The receiver should use the share_code to initiate download via `download_file()`
@@ -88,6 +155,9 @@ pub enum DirectMessage {
         filename: String,
         file_size: u64,
         file_type: String,
+        /// Nickname of the original author, set on forwarded file shares
+        #[serde(default)]
+        forwarded_from: Option<String>,
     },
     /// Group invitation with group ID and name
     /// The receiver can use group_id to join via `join_group()`
@@ -96,6 +166,13 @@ pub enum DirectMessage {
         group_name: String,
         inviter_nickname: String,
     },
+    /// Pinned location; rejected by the receiver if out of range
+    Location {
+        message_id: String,
+        lat: f64,
+        lon: f64,
+        label: Option<String>,
+    },
 }
 
 /// Direct messaging response
@@ -111,7 +188,7 @@ pub enum DirectResponse {
 
 /// File sharing request messages
 ///
-/// Requests sent via `/file/1.0.0` protocol for file chunk transfer.
+/// Requests sent via the `/file/*` protocols for file chunk transfer.
 ///
 /// This is a **pull-based** protocol:
 /// 1. Sender announces share code via direct/group message
@@ -124,6 +201,9 @@ pub enum DirectResponse {
 /// - **GetFileInfo**: Get file metadata (name, size, hash, chunk count)
 /// - **GetChunk**: Get specific chunk (0 to chunk_count-1)
 /// - **ListFiles**: Get list of all shared files (for browsing)
+/// - **GetAvatar**: Get the peer's avatar image by hash
+/// - **FilesChanged**: Announce the sender's shared files after they changed
+/// - **GetRange**: Get any byte range of a file, e.g. to seek in media
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FileSharingRequest {
     /// Request file metadata by share code
@@ -138,8 +218,24 @@ pub enum FileSharingRequest {
     /// Request list of all shared files
     /// Returns FileList with all shared files
     ListFiles,
+
+    /// Request the peer's current avatar image by its hash
+    /// Returns Avatar with the image, at most `MAX_AVATAR_SIZE` bytes
+    GetAvatar(String),
+
+    /// Announce every file the sender currently shares
+    /// Returns Ack
+    FilesChanged(Vec<super::events::FileInfo>),
+
+    /// Request `len` bytes from offset `start` of a file by share code
+    /// Returns Range with at most `MAX_RANGE_SIZE` bytes, fewer at the end
+    /// of the file; needs file transfer 1.3
+    GetRange(String, u64, u64),
 }
 
+/// Most bytes served for one `GetRange` request
+pub const MAX_RANGE_SIZE: u64 = 1024 * 1024;
+
 /// File sharing response messages
 ///
 /// Responses to file sharing requests.
@@ -149,6 +245,9 @@ pub enum FileSharingRequest {
 /// - **FileInfo**: File metadata or None if share code invalid
 /// - **Chunk**: Chunk data with hash or None if chunk unavailable
 /// - **FileList**: All shared files or error if listing fails
+/// - **Avatar**: Avatar image or None if the hash is not the current avatar
+/// - **Range**: Bytes of a file or None if share code invalid
+/// - **Ack**: Receipt of a `FilesChanged` announcement
 /// - **Error**: General error message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FileSharingResponse {
@@ -165,10 +264,333 @@ pub enum FileSharingResponse {
     /// Returns Vec<FileInfo> or Error if listing fails
     FileList(Vec<super::events::FileInfo>),
 
+    /// Avatar image
+    /// Returns None if the hash is not the peer's current avatar
+    Avatar(Option<Vec<u8>>),
+
+    /// Receipt of a `FilesChanged` announcement
+    Ack,
+
+    /// Requested bytes of a file
+    /// Returns None if share code is invalid or file not shared
+    Range(Option<super::events::FileRange>),
+
     /// General error message
+    /// `FILE_REVOKED_ERROR` when the requested file has been revoked,
+    /// `FILE_CHANGED_ERROR` when it changed on disk after sharing
     Error(String),
 }
 
+/// Error message sent in `FileSharingResponse::Error` for revoked files
+pub const FILE_REVOKED_ERROR: &str = "File has been revoked";
+
+/// Error message sent in `FileSharingResponse::Error` when a shared file's
+/// contents no longer match its `FileInfo`, e.g. after truncation
+pub const FILE_CHANGED_ERROR: &str = "File has changed since it was shared";
+
+/// Error message sent in `FileSharingResponse::Error` instead of the
+/// `FileInfo` of a file with content-defined chunks, to peers before 1.4
+pub const CONTENT_DEFINED_CHUNKS_ERROR: &str =
+    "File uses content-defined chunks, which the peer does not support";
+
+/// Version of the file sharing protocol
+///
+/// Every supported version is offered when opening a stream, highest first,
+/// so each connection uses the highest version both peers support. Later
+/// versions may change the wire format; `FileSharingCodec` encodes each
+/// stream in its negotiated version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FileTransferVersion {
+    /// `/file/1.0.0`
+    V1_0,
+    /// `/file/1.1.0`
+    V1_1,
+    /// `/file/1.2.0`
+    V1_2,
+    /// `/file/1.3.0`
+    V1_3,
+    /// `/file/1.4.0`
+    V1_4,
+}
+
+impl FileTransferVersion {
+    /// Every version this build speaks, highest first
+    pub const ALL: [FileTransferVersion; 5] =
+        [Self::V1_4, Self::V1_3, Self::V1_2, Self::V1_1, Self::V1_0];
+
+    /// Protocol name negotiated for this version
+    pub fn protocol(self) -> StreamProtocol {
+        match self {
+            Self::V1_0 => StreamProtocol::new("/file/1.0.0"),
+            Self::V1_1 => StreamProtocol::new("/file/1.1.0"),
+            Self::V1_2 => StreamProtocol::new("/file/1.2.0"),
+            Self::V1_3 => StreamProtocol::new("/file/1.3.0"),
+            Self::V1_4 => StreamProtocol::new("/file/1.4.0"),
+        }
+    }
+
+    /// Version with the protocol name `protocol`
+    pub fn from_protocol(protocol: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|version| version.protocol().as_ref() == protocol)
+    }
+
+    /// Features available when this version was negotiated
+    pub fn capabilities(self) -> FileTransferCapabilities {
+        FileTransferCapabilities {
+            hash_algo: self >= Self::V1_1,
+            byte_string_chunks: self >= Self::V1_2,
+            byte_ranges: self >= Self::V1_3,
+            content_defined_chunks: self >= Self::V1_4,
+        }
+    }
+}
+
+/// Features of the file sharing protocol, derived from its negotiated version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileTransferCapabilities {
+    /// `FileInfo::hash_algo` is always sent; over 1.0 the peer may predate
+    /// it, and a missing algorithm means SHA256
+    pub hash_algo: bool,
+    /// Chunk data is sent as a CBOR byte string rather than an array of
+    /// integers
+    pub byte_string_chunks: bool,
+    /// `GetRange` requests are served
+    pub byte_ranges: bool,
+    /// Files with `FileInfo::chunk_offsets` can be downloaded; earlier
+    /// peers ignore the offsets and would request fixed-size chunks
+    pub content_defined_chunks: bool,
+}
+
+/// A file sharing response and the version of the stream it arrived on
+///
+/// The version is not part of the wire format; it is filled in from the
+/// negotiated protocol when the response is read, and ignored when it is
+/// written, as the stream's version is fixed by then.
+#[derive(Debug, Clone)]
+pub struct VersionedResponse {
+    pub version: FileTransferVersion,
+    pub response: FileSharingResponse,
+}
+
+impl From<FileSharingResponse> for VersionedResponse {
+    fn from(response: FileSharingResponse) -> Self {
+        Self {
+            version: FileTransferVersion::ALL[0],
+            response,
+        }
+    }
+}
+
+/// Chunk data as a CBOR byte string, or as an array of integers as sent
+/// before 1.2
+pub(crate) mod chunk_data {
+    use serde::de::{Deserializer, SeqAccess, Visitor};
+    use serde::Serializer;
+    use std::fmt;
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(data)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        struct ChunkDataVisitor;
+
+        impl<'de> Visitor<'de> for ChunkDataVisitor {
+            type Value = Vec<u8>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a byte string or an array of bytes")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Vec<u8>, E> {
+                Ok(bytes.to_vec())
+            }
+
+            fn visit_byte_buf<E: serde::de::Error>(self, bytes: Vec<u8>) -> Result<Vec<u8>, E> {
+                Ok(bytes)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<u8>, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(1 << 20));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(bytes)
+            }
+        }
+
+        deserializer.deserialize_any(ChunkDataVisitor)
+    }
+}
+
+/// `ChunkInfo` with its data as an array of integers, as sent before 1.2
+#[derive(Serialize, Deserialize)]
+struct IntegerArrayChunk {
+    file_id: String,
+    chunk_index: usize,
+    data: Vec<u8>,
+    hash: String,
+}
+
+/// `FileSharingResponse::Chunk` as sent before 1.2
+///
+/// Encodes like the `Chunk` variant of `FileSharingResponse`, apart from the
+/// chunk data, so it is read back as a `FileSharingResponse`.
+#[derive(Serialize, Deserialize)]
+enum IntegerArrayChunkResponse {
+    Chunk(Option<IntegerArrayChunk>),
+}
+
+/// Codec for the file sharing protocol in all its versions
+///
+/// Every version uses CBOR for requests and responses. Chunk data is
+/// written as a byte string, except to peers before 1.2. The `FileInfo` of
+/// a file with content-defined chunks is replaced by
+/// `CONTENT_DEFINED_CHUNKS_ERROR` for peers before 1.4.
+#[derive(Clone, Default)]
+pub struct FileSharingCodec {
+    cbor: request_response::cbor::codec::Codec<FileSharingRequest, FileSharingResponse>,
+    integer_array_chunks:
+        request_response::cbor::codec::Codec<FileSharingRequest, IntegerArrayChunkResponse>,
+}
+
+/// Version negotiated as `protocol`
+fn negotiated_version(protocol: &StreamProtocol) -> io::Result<FileTransferVersion> {
+    FileTransferVersion::from_protocol(protocol.as_ref()).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unknown file sharing protocol {}", protocol),
+        )
+    })
+}
+
+#[async_trait]
+impl request_response::Codec for FileSharingCodec {
+    type Protocol = StreamProtocol;
+    type Request = FileSharingRequest;
+    type Response = VersionedResponse;
+
+    async fn read_request<T>(
+        &mut self,
+        protocol: &StreamProtocol,
+        io: &mut T,
+    ) -> io::Result<FileSharingRequest>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        self.cbor.read_request(protocol, io).await
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        protocol: &StreamProtocol,
+        io: &mut T,
+    ) -> io::Result<VersionedResponse>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let version = negotiated_version(protocol)?;
+        let response = self.cbor.read_response(protocol, io).await?;
+        Ok(VersionedResponse { version, response })
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        protocol: &StreamProtocol,
+        io: &mut T,
+        request: FileSharingRequest,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        if matches!(request, FileSharingRequest::GetRange(..))
+            && !negotiated_version(protocol)?.capabilities().byte_ranges
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Peer does not support byte range requests",
+            ));
+        }
+        self.cbor.write_request(protocol, io, request).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        protocol: &StreamProtocol,
+        io: &mut T,
+        response: VersionedResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let capabilities = negotiated_version(protocol)?.capabilities();
+        match response.response {
+            FileSharingResponse::FileInfo(Some(info))
+                if info.chunk_offsets.is_some() && !capabilities.content_defined_chunks =>
+            {
+                let refusal = FileSharingResponse::Error(CONTENT_DEFINED_CHUNKS_ERROR.to_string());
+                self.cbor.write_response(protocol, io, refusal).await
+            }
+            FileSharingResponse::Chunk(chunk) if !capabilities.byte_string_chunks => {
+                let chunk = chunk.map(|chunk| IntegerArrayChunk {
+                    file_id: chunk.file_id,
+                    chunk_index: chunk.chunk_index,
+                    data: chunk.data,
+                    hash: chunk.hash,
+                });
+                self.integer_array_chunks
+                    .write_response(protocol, io, IntegerArrayChunkResponse::Chunk(chunk))
+                    .await
+            }
+            response => self.cbor.write_response(protocol, io, response).await,
+        }
+    }
+}
+
+/// Create the file sharing behaviour speaking `versions`
+///
+/// # Errors
+/// Fails if `versions` is empty.
+pub fn create_file_sharing_behaviour(
+    versions: &[FileTransferVersion],
+) -> anyhow::Result<request_response::Behaviour<FileSharingCodec>> {
+    if versions.is_empty() {
+        anyhow::bail!("At least one file transfer version must be enabled");
+    }
+    let mut versions = versions.to_vec();
+    versions.sort_unstable_by(|a, b| b.cmp(a));
+    versions.dedup();
+    Ok(request_response::Behaviour::with_codec(
+        FileSharingCodec::default(),
+        versions
+            .into_iter()
+            .map(|version| (version.protocol(), ProtocolSupport::Full)),
+        request_response::Config::default(),
+    ))
+}
+
+/// Profile exchange version spoken by this build
+///
+/// Sent in every `ProfileExchange`. Fields unknown to a peer are ignored, so
+/// later versions may only add fields that have defaults.
+pub const PROFILE_EXCHANGE_VERSION: u32 = 1;
+
+/// Profile exchange message
+///
+/// Sent via the `/gigi/profile/1.0.0` protocol, as both request and
+/// response: the peer adding a contact sends its own profile and receives the
+/// contact's in return. Peers without the protocol fail the request with
+/// `UnsupportedProtocols`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileExchange {
+    /// `PROFILE_EXCHANGE_VERSION` of the sender
+    pub version: u32,
+    /// The sender's profile
+    pub profile: super::events::Profile,
+}
+
 /// Unified network behaviour combining all protocols
 ///
 /// Combines multiple libp2p behaviours into a single NetworkBehaviour implementation.
@@ -176,12 +598,15 @@ pub enum FileSharingResponse {
 ///
 /// # Behaviours
 ///
-/// - **gigi_dns**: mDNS-based discovery with nicknames and metadata (local network)
+/// - **limits**: Caps established and pending connections
+/// - **gigi_dns**: mDNS-based discovery with nicknames and metadata (local network),
+///   disabled when another discovery backend is used
 /// - **kademlia**: Kademlia DHT for WAN peer discovery and routing
 /// - **relay**: Circuit relay for NAT traversal
 /// - **direct_msg**: Request-response for 1-to-1 messaging
 /// - **gossipsub**: Pub-sub for group messaging
 /// - **file_sharing**: Request-response for chunked file transfer
+/// - **profile**: Request-response for swapping profiles with contacts
 ///
 /// # Event Handling
 ///
@@ -190,8 +615,12 @@ pub enum FileSharingResponse {
 #[derive(NetworkBehaviour)]
 #[behaviour(out_event = "UnifiedEvent")]
 pub struct UnifiedBehaviour {
-    /// mDNS-based discovery with nicknames and capabilities (local network)
-    pub gigi_dns: GigiDnsBehaviour,
+    /// Connection limits, checked before the other behaviours see a connection
+    pub limits: connection_limits::Behaviour,
+
+    /// mDNS-based discovery with nicknames and capabilities (local network),
+    /// disabled when the client uses another `Discovery` backend
+    pub gigi_dns: Toggle<GigiDnsBehaviour>,
 
     /// Kademlia DHT for WAN peer discovery and content routing
     pub kademlia: kad::Behaviour<kad::store::MemoryStore>,
@@ -206,7 +635,13 @@ pub struct UnifiedBehaviour {
     pub gossipsub: gossipsub::Behaviour,
 
     /// Request-response for chunked file transfer
-    pub file_sharing: request_response::cbor::Behaviour<FileSharingRequest, FileSharingResponse>,
+    pub file_sharing: request_response::Behaviour<FileSharingCodec>,
+
+    /// Request-response for exchanging profiles with contacts
+    pub profile: request_response::cbor::Behaviour<ProfileExchange, ProfileExchange>,
+
+    /// Exchange of supported protocols with every connected peer
+    pub identify: identify::Behaviour,
 }
 
 /// Unified event from network behaviour
@@ -222,6 +657,8 @@ pub struct UnifiedBehaviour {
 /// - **DirectMessage**: Direct messaging events (requests, responses, failures)
 /// - **Gossipsub**: Group messaging events (subscribed, published, etc.)
 /// - **FileSharing**: File transfer events (requests, responses, failures)
+/// - **Profile**: Contact profile exchange events
+/// - **Identify**: Protocols and agent version received from a peer
 #[derive(Debug)]
 pub enum UnifiedEvent {
     GigiDns(gigi_dns::GigiDnsEvent),
@@ -229,7 +666,15 @@ pub enum UnifiedEvent {
     Relay(relay::Event),
     DirectMessage(request_response::Event<DirectMessage, DirectResponse>),
     Gossipsub(gossipsub::Event),
-    FileSharing(request_response::Event<FileSharingRequest, FileSharingResponse>),
+    FileSharing(request_response::Event<FileSharingRequest, VersionedResponse>),
+    Profile(request_response::Event<ProfileExchange, ProfileExchange>),
+    Identify(Box<identify::Event>),
+}
+
+impl From<std::convert::Infallible> for UnifiedEvent {
+    fn from(event: std::convert::Infallible) -> Self {
+        match event {}
+    }
 }
 
 impl From<gigi_dns::GigiDnsEvent> for UnifiedEvent {
@@ -262,12 +707,96 @@ impl From<gossipsub::Event> for UnifiedEvent {
     }
 }
 
-impl From<request_response::Event<FileSharingRequest, FileSharingResponse>> for UnifiedEvent {
-    fn from(event: request_response::Event<FileSharingRequest, FileSharingResponse>) -> Self {
+impl From<request_response::Event<FileSharingRequest, VersionedResponse>> for UnifiedEvent {
+    fn from(event: request_response::Event<FileSharingRequest, VersionedResponse>) -> Self {
         Self::FileSharing(event)
     }
 }
 
+impl From<request_response::Event<ProfileExchange, ProfileExchange>> for UnifiedEvent {
+    fn from(event: request_response::Event<ProfileExchange, ProfileExchange>) -> Self {
+        Self::Profile(event)
+    }
+}
+
+impl From<identify::Event> for UnifiedEvent {
+    fn from(event: identify::Event) -> Self {
+        Self::Identify(Box::new(event))
+    }
+}
+
+/// Protocol version announced in identify, shared by all gigi peers
+pub const IDENTIFY_PROTOCOL_VERSION: &str = "/gigi/1.0.0";
+
+/// Create the identify behaviour announcing `public_key`
+///
+/// The agent version is `gigi/` followed by this crate's version, so
+/// interop problems can be traced to the app version of a peer.
+pub fn create_identify_behaviour(public_key: libp2p::identity::PublicKey) -> identify::Behaviour {
+    identify::Behaviour::new(
+        identify::Config::new(IDENTIFY_PROTOCOL_VERSION.to_string(), public_key)
+            .with_agent_version(format!("gigi/{}", env!("CARGO_PKG_VERSION"))),
+    )
+}
+
+/// Smallest `max_connections` for which outbound slots are reserved
+///
+/// With fewer slots, reserving one would leave too few, or none, for
+/// inbound connections.
+pub const MIN_CONNECTIONS_FOR_OUTBOUND_RESERVE: u32 = 4;
+
+/// Slots of `max_connections` reserved for outbound connections
+///
+/// A quarter of the slots from `MIN_CONNECTIONS_FOR_OUTBOUND_RESERVE` on,
+/// none below it.
+pub fn reserved_outbound_connections(max_connections: u32) -> u32 {
+    if max_connections < MIN_CONNECTIONS_FOR_OUTBOUND_RESERVE {
+        0
+    } else {
+        max_connections / 4
+    }
+}
+
+/// Create connection limits for `max_connections` established connections
+///
+/// Slots from `reserved_outbound_connections` are kept free for outbound
+/// connections, so dials for user actions still succeed when inbound
+/// connections fill the rest. Pending inbound handshakes are capped at the
+/// same total.
+///
+/// # Arguments
+///
+/// * `max_connections` - Maximum established connections in total
+pub fn create_connection_limits(max_connections: u32) -> connection_limits::Behaviour {
+    let reserved_outbound = reserved_outbound_connections(max_connections);
+    let limits = connection_limits::ConnectionLimits::default()
+        .with_max_established(Some(max_connections))
+        .with_max_established_incoming(Some(max_connections - reserved_outbound))
+        .with_max_pending_incoming(Some(max_connections));
+    connection_limits::Behaviour::new(limits)
+}
+
+/// Largest gossipsub frame accepted or sent, in bytes
+///
+/// Group messages larger than `P2pConfig::max_group_message_size` are split
+/// into parts that stay well below this limit.
+pub const GOSSIPSUB_MAX_TRANSMIT_SIZE: usize = 65536;
+
+/// GossipSub topic every client subscribes to for presence statuses
+///
+/// Kept apart from group topics so presence never shows up as a group.
+pub const PRESENCE_TOPIC: &str = "gigi-presence";
+
+/// Presence status published on `PRESENCE_TOPIC`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceMessage {
+    /// Free-form status such as "Available" or "Away"; empty clears it
+    pub status: String,
+    /// Send time in Unix milliseconds, so rebroadcasts of the same status
+    /// get distinct content-based message IDs
+    pub timestamp_ms: u64,
+}
+
 /// Create gossipsub configuration
 ///
 /// Creates a GossipSub configuration optimized for group messaging.
@@ -277,6 +806,7 @@ impl From<request_response::Event<FileSharingRequest, FileSharingResponse>> for
 /// - **Heartbeat Interval**: 10 seconds - balances mesh maintenance vs. overhead
 /// - **Validation Mode**: Strict - ensures only valid messages are forwarded
 /// - **Message ID**: Blake3 hash of message content for deduplication
+/// - **Max Transmit Size**: `GOSSIPSUB_MAX_TRANSMIT_SIZE`
 ///
 /// # Message ID Function
 ///
@@ -299,6 +829,8 @@ pub fn create_gossipsub_config(
         .heartbeat_interval(std::time::Duration::from_secs(10))
         // Strict validation ensures only valid messages are forwarded
         .validation_mode(ValidationMode::Strict)
+        // Bound frame size; long texts are chunked by the group manager
+        .max_transmit_size(GOSSIPSUB_MAX_TRANSMIT_SIZE)
         // Use Blake3 hash for message deduplication
         .message_id_fn(|message| {
             let mut hasher = Hasher::new();
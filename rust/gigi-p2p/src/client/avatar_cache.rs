@@ -0,0 +1,132 @@
+//! Avatar images, cached on disk by hash
+//!
+//! A profile only carries the BLAKE3 hash of the avatar. When a contact's
+//! profile names an avatar that is not cached yet, the image is fetched from
+//! the contact with `FileSharingRequest::GetAvatar` over the file-transfer
+//! protocol and written to the cache directory under its hash. Avatars are
+//! small, so the image travels in a single response rather than in chunks.
+//!
+//! Since files are named by the hash of their contents, a cached avatar
+//! never goes stale: an unchanged avatar is not fetched again, and a changed
+//! one has a new hash.
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::P2pError;
+use crate::validation;
+
+/// Largest avatar image served or accepted, in bytes
+pub const MAX_AVATAR_SIZE: usize = 256 * 1024;
+
+/// Avatar cache hit and miss counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AvatarCacheStats {
+    /// Lookups answered from the cache without fetching
+    pub hits: u64,
+    /// Lookups that had to fetch the avatar from a peer
+    pub misses: u64,
+}
+
+/// Directory of avatar images named by hash, and the fetches in flight
+pub struct AvatarCache {
+    dir: PathBuf,
+    /// Avatar hash by outbound request id
+    pending: HashMap<String, String>,
+    stats: AvatarCacheStats,
+}
+
+impl AvatarCache {
+    /// Create a cache in `dir`, which is created on first write
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            pending: HashMap::new(),
+            stats: AvatarCacheStats::default(),
+        }
+    }
+
+    /// Hash of an avatar image, as used in profiles
+    pub fn hash(data: &[u8]) -> String {
+        blake3::hash(data).to_hex().to_string()
+    }
+
+    /// Path an avatar is cached at, whether or not it exists
+    ///
+    /// `avatar_hash` must have passed `validate_avatar_hash`, so it cannot
+    /// escape the cache directory.
+    pub fn path(&self, avatar_hash: &str) -> PathBuf {
+        self.dir.join(avatar_hash)
+    }
+
+    /// Cached avatar with the given hash, counting a hit or a miss
+    pub fn lookup(&mut self, avatar_hash: &str) -> Option<PathBuf> {
+        let path = self.path(avatar_hash);
+        if path.is_file() {
+            self.stats.hits += 1;
+            Some(path)
+        } else {
+            self.stats.misses += 1;
+            None
+        }
+    }
+
+    /// Whether a fetch of this avatar is already in flight
+    pub fn is_pending(&self, avatar_hash: &str) -> bool {
+        self.pending.values().any(|hash| hash == avatar_hash)
+    }
+
+    /// Remember an outbound avatar request
+    pub fn track(&mut self, request_id: String, avatar_hash: String) {
+        self.pending.insert(request_id, avatar_hash);
+    }
+
+    /// Forget an outbound avatar request, returning its avatar hash
+    pub fn untrack(&mut self, request_id: &str) -> Option<String> {
+        self.pending.remove(request_id)
+    }
+
+    /// Read an avatar image to cache or serve, enforcing `MAX_AVATAR_SIZE`
+    pub fn read_image(path: &Path) -> Result<Vec<u8>> {
+        let size = std::fs::metadata(path)?.len();
+        if size > MAX_AVATAR_SIZE as u64 {
+            return Err(P2pError::InvalidInput(format!(
+                "Avatar too large ({} bytes, max {})",
+                size, MAX_AVATAR_SIZE
+            ))
+            .into());
+        }
+        Ok(std::fs::read(path)?)
+    }
+
+    /// Verify and cache an avatar image
+    ///
+    /// # Returns
+    /// Path of the cached image
+    pub fn insert(&self, avatar_hash: &str, data: &[u8]) -> Result<PathBuf> {
+        validation::validate_avatar_hash(avatar_hash)?;
+        if data.len() > MAX_AVATAR_SIZE {
+            return Err(P2pError::InvalidInput(format!(
+                "Avatar too large ({} bytes, max {})",
+                data.len(),
+                MAX_AVATAR_SIZE
+            ))
+            .into());
+        }
+        if Self::hash(data) != avatar_hash {
+            return Err(P2pError::InvalidInput("Avatar does not match its hash".into()).into());
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.path(avatar_hash);
+        // Write under a temporary name so a partial file never counts as cached
+        let tmp_path = self.dir.join(format!("{}.tmp", avatar_hash));
+        std::fs::write(&tmp_path, data)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(path)
+    }
+
+    pub fn stats(&self) -> AvatarCacheStats {
+        self.stats
+    }
+}
@@ -0,0 +1,214 @@
+//! Server-side cache of recently served chunks
+//!
+//! When several peers download the same file around the same time, each of
+//! them requests the same chunks. The cache keeps recently read chunks in
+//! memory so only the first request for a chunk reads the file (or calls the
+//! content URI reader).
+//!
+//! The chunk read-ahead (`ChunkPrefetcher`) loads upcoming chunks into the
+//! same cache, so all served chunks share one memory bound.
+//!
+//! # Bounds
+//!
+//! - Chunk data held is limited to `max_bytes` in total; the least recently
+//!   used chunks are evicted first
+//! - A chunk larger than `max_bytes` is never cached
+//! - Entries of a file are dropped when it is unshared or found changed on
+//!   disk; each entry also records the whole-file hash it was read under, so
+//!   chunks of a file re-shared with new contents are never served
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::events::ChunkInfo;
+
+/// Cached chunk and when it was last used
+struct CachedChunk {
+    chunk: ChunkInfo,
+    /// Whole-file hash of the shared file when the chunk was read
+    file_hash: String,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct ChunkCacheState {
+    max_bytes: usize,
+    chunks: HashMap<(String, usize), CachedChunk>,
+    /// Keys by last use, oldest first
+    recency: BTreeMap<u64, (String, usize)>,
+    /// Incremented on every use, orders `recency`
+    clock: u64,
+    cached_bytes: usize,
+    hits: u64,
+    misses: u64,
+}
+
+impl ChunkCacheState {
+    fn touch(&mut self, key: &(String, usize)) {
+        self.clock += 1;
+        if let Some(cached) = self.chunks.get_mut(key) {
+            self.recency.remove(&cached.last_used);
+            cached.last_used = self.clock;
+            self.recency.insert(self.clock, key.clone());
+        }
+    }
+
+    fn remove(&mut self, key: &(String, usize)) {
+        if let Some(cached) = self.chunks.remove(key) {
+            self.recency.remove(&cached.last_used);
+            self.cached_bytes -= cached.chunk.data.len();
+        }
+    }
+
+    /// Evict least recently used chunks until at most `max_bytes` are held
+    fn shrink_to(&mut self, max_bytes: usize) {
+        while self.cached_bytes > max_bytes {
+            let Some((_, key)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some(cached) = self.chunks.remove(&key) {
+                self.cached_bytes -= cached.chunk.data.len();
+            }
+        }
+    }
+}
+
+/// Cache counters for monitoring
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkCacheStats {
+    /// Requests served from the cache
+    pub hits: u64,
+    /// Requests that had to be read from the file
+    pub misses: u64,
+    /// Chunks currently held in memory
+    pub cached_chunks: usize,
+    /// Bytes of chunk data currently held in memory
+    pub cached_bytes: usize,
+}
+
+/// Size-bounded LRU cache of served chunks, keyed by share code and chunk index
+///
+/// Clones share the same cache.
+#[derive(Clone)]
+pub struct ChunkCache {
+    state: Arc<Mutex<ChunkCacheState>>,
+}
+
+impl ChunkCache {
+    /// Create a cache holding at most `max_bytes` of chunk data (0 disables it)
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ChunkCacheState {
+                max_bytes,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Change the size limit, evicting chunks that no longer fit
+    pub fn set_max_bytes(&self, max_bytes: usize) {
+        let mut state = self.lock();
+        state.max_bytes = max_bytes;
+        state.shrink_to(max_bytes);
+    }
+
+    /// Whether chunks are cached at all, i.e. the size limit is not 0
+    pub fn is_enabled(&self) -> bool {
+        self.lock().max_bytes > 0
+    }
+
+    /// Whether a current chunk is cached, without counting or using it
+    pub fn contains(&self, file_id: &str, chunk_index: usize, file_hash: &str) -> bool {
+        self.lock()
+            .chunks
+            .get(&(file_id.to_string(), chunk_index))
+            .is_some_and(|cached| cached.file_hash == file_hash)
+    }
+
+    /// Look up a chunk, counting a hit or a miss
+    ///
+    /// A chunk read while the file had a different hash is stale: it is
+    /// dropped and counted as a miss. Always a miss while the cache is disabled.
+    pub fn get(&self, file_id: &str, chunk_index: usize, file_hash: &str) -> Option<ChunkInfo> {
+        let mut state = self.lock();
+        if state.max_bytes == 0 {
+            return None;
+        }
+        let key = (file_id.to_string(), chunk_index);
+        if state
+            .chunks
+            .get(&key)
+            .is_some_and(|cached| cached.file_hash != file_hash)
+        {
+            state.remove(&key);
+        }
+        let chunk = state.chunks.get(&key).map(|cached| cached.chunk.clone());
+        match chunk {
+            Some(_) => {
+                state.hits += 1;
+                state.touch(&key);
+            }
+            None => state.misses += 1,
+        }
+        chunk
+    }
+
+    /// Cache a chunk read from the file, evicting older chunks to make room
+    ///
+    /// # Arguments
+    /// * `chunk` - The chunk as served
+    /// * `file_hash` - Current whole-file hash of the shared file
+    pub fn insert(&self, chunk: ChunkInfo, file_hash: &str) {
+        let mut state = self.lock();
+        let size = chunk.data.len();
+        if size > state.max_bytes {
+            return;
+        }
+        let key = (chunk.file_id.clone(), chunk.chunk_index);
+        state.remove(&key);
+        let max_bytes = state.max_bytes - size;
+        state.shrink_to(max_bytes);
+
+        state.clock += 1;
+        let last_used = state.clock;
+        state.recency.insert(last_used, key.clone());
+        state.cached_bytes += size;
+        state.chunks.insert(
+            key,
+            CachedChunk {
+                chunk,
+                file_hash: file_hash.to_string(),
+                last_used,
+            },
+        );
+    }
+
+    /// Drop all cached chunks of a file, e.g. when it is unshared or changed
+    pub fn forget_file(&self, file_id: &str) {
+        let mut state = self.lock();
+        let keys: Vec<_> = state
+            .chunks
+            .keys()
+            .filter(|(cached_file, _)| cached_file == file_id)
+            .cloned()
+            .collect();
+        for key in keys {
+            state.remove(&key);
+        }
+    }
+
+    /// Current cache counters
+    pub fn stats(&self) -> ChunkCacheStats {
+        let state = self.lock();
+        ChunkCacheStats {
+            hits: state.hits,
+            misses: state.misses,
+            cached_chunks: state.chunks.len(),
+            cached_bytes: state.cached_bytes,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ChunkCacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,264 @@
+//! Server-side chunk read-ahead
+//!
+//! When a peer downloads a file sequentially, serving chunk N is a good hint
+//! that chunk N+1 is requested next. The prefetcher reads the following chunks
+//! in the background while the network is busy and puts them into the
+//! `ChunkCache` of served chunks, so later requests are served from memory
+//! instead of hitting the disk (or a content URI reader).
+//!
+//! # Bounds
+//!
+//! - Prefetched chunks live in the `ChunkCache` and count against its size limit
+//! - At most `read_ahead` chunks are read ahead per (peer, file) stream
+//! - At most `max_streams` streams are tracked; the least recently used is evicted
+//! - A request that jumps away from the expected index discards the stream's
+//!   pending reads
+//!
+//! Downloaders pipeline their requests, so a request may arrive slightly out
+//! of order, which is tolerated within `read_ahead` chunks of the expected
+//! index, or while its chunk is still being read ahead. Lookups never wait:
+//! the event loop must not block, so such a request reads the chunk directly.
+
+use anyhow::Result;
+use libp2p::PeerId;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::chunk_cache::ChunkCache;
+use crate::events::ChunkInfo;
+
+/// Read-ahead state for one peer downloading one file
+struct PrefetchStream {
+    /// Chunk index expected in the next request
+    next_index: usize,
+    /// Bumped on every jump so reads for the old position are discarded
+    generation: u64,
+    /// Chunk indexes currently being read in the background
+    in_flight: HashSet<usize>,
+    last_used: Instant,
+}
+
+impl PrefetchStream {
+    fn new(next_index: usize) -> Self {
+        Self {
+            next_index,
+            generation: 0,
+            in_flight: HashSet::new(),
+            last_used: Instant::now(),
+        }
+    }
+
+    /// Discard the reads for the old position
+    fn reset(&mut self, next_index: usize) {
+        self.next_index = next_index;
+        self.generation += 1;
+        self.in_flight.clear();
+    }
+}
+
+#[derive(Default)]
+struct PrefetchState {
+    streams: HashMap<(PeerId, String), PrefetchStream>,
+    hits: u64,
+    misses: u64,
+}
+
+/// Read-ahead counters for monitoring
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrefetchStats {
+    /// Requests served from the chunk cache
+    pub hits: u64,
+    /// Requests that had to be read from the file
+    pub misses: u64,
+    /// Chunks currently being read in the background
+    pub in_flight: usize,
+}
+
+/// Per-(peer, file) chunk read-ahead into the served chunk cache
+#[derive(Clone)]
+pub struct ChunkPrefetcher {
+    read_ahead: usize,
+    max_streams: usize,
+    cache: ChunkCache,
+    state: Arc<Mutex<PrefetchState>>,
+}
+
+impl ChunkPrefetcher {
+    /// Create a prefetcher reading `read_ahead` chunks past each served chunk
+    ///
+    /// # Arguments
+    /// * `read_ahead` - Chunks to read ahead per stream
+    /// * `max_streams` - Maximum number of (peer, file) streams tracked at once
+    /// * `cache` - Cache the chunks are read into and served from; nothing is
+    ///   read ahead while it is disabled
+    pub fn new(read_ahead: usize, max_streams: usize, cache: ChunkCache) -> Self {
+        Self {
+            read_ahead,
+            max_streams: max_streams.max(1),
+            cache,
+            state: Arc::new(Mutex::new(PrefetchState::default())),
+        }
+    }
+
+    /// Look up a chunk requested by `peer` in the cache
+    ///
+    /// Counts a hit or a miss. Never waits: a chunk still being read in the
+    /// background is a miss. A request more than `read_ahead` chunks away from
+    /// the one that follows the last served chunk discards the stream's
+    /// pending reads.
+    ///
+    /// # Returns
+    /// The cached chunk, `None` if it must be read directly
+    pub fn get(
+        &self,
+        peer: PeerId,
+        file_id: &str,
+        chunk_index: usize,
+        file_hash: &str,
+    ) -> Option<ChunkInfo> {
+        let mut state = self.lock();
+        if let Some(stream) = state.streams.get_mut(&(peer, file_id.to_string())) {
+            stream.last_used = Instant::now();
+            if !self.near(stream, chunk_index) {
+                stream.reset(chunk_index);
+            }
+        }
+        let chunk = self.cache.get(file_id, chunk_index, file_hash);
+        match chunk {
+            Some(_) => state.hits += 1,
+            None => state.misses += 1,
+        }
+        chunk
+    }
+
+    /// Record that `chunk_index` was served and read the following chunks
+    ///
+    /// Reads chunks `chunk_index + 1 ..= chunk_index + read_ahead` (bounded by
+    /// `total_chunks`) that are neither cached nor already being read, on a
+    /// background thread using `load`.
+    ///
+    /// # Arguments
+    /// * `peer` - Peer the chunk was served to
+    /// * `file_id` - Share code of the file
+    /// * `chunk_index` - Index of the chunk just served
+    /// * `total_chunks` - Number of chunks in the file
+    /// * `file_hash` - Current whole-file hash, cached with the chunks
+    /// * `load` - Reads a chunk by index
+    pub fn prefetch_after<F>(
+        &self,
+        peer: PeerId,
+        file_id: &str,
+        chunk_index: usize,
+        total_chunks: usize,
+        file_hash: &str,
+        load: F,
+    ) where
+        F: Fn(usize) -> Result<ChunkInfo> + Send + 'static,
+    {
+        if self.read_ahead == 0 || !self.cache.is_enabled() {
+            return;
+        }
+
+        let key = (peer, file_id.to_string());
+        let next_index = chunk_index + 1;
+        let end = (chunk_index + self.read_ahead).min(total_chunks.saturating_sub(1));
+
+        let (generation, wanted) = {
+            let mut state = self.lock();
+            if !state.streams.contains_key(&key) && state.streams.len() >= self.max_streams {
+                Self::evict_least_recently_used(&mut state);
+            }
+            let stream = state
+                .streams
+                .entry(key.clone())
+                .or_insert_with(|| PrefetchStream::new(next_index));
+            if !self.near(stream, chunk_index) {
+                stream.reset(next_index);
+            }
+            // Pipelined requests may be served out of order; never step back
+            stream.next_index = stream.next_index.max(next_index);
+            stream.last_used = Instant::now();
+
+            let wanted: Vec<usize> = (stream.next_index..=end)
+                .filter(|index| {
+                    !stream.in_flight.contains(index)
+                        && !self.cache.contains(file_id, *index, file_hash)
+                })
+                .collect();
+            stream.in_flight.extend(wanted.iter().copied());
+            (stream.generation, wanted)
+        };
+
+        if wanted.is_empty() {
+            return;
+        }
+
+        let state = Arc::clone(&self.state);
+        let cache = self.cache.clone();
+        let file_hash = file_hash.to_string();
+        let task = move || {
+            for index in wanted {
+                let chunk = load(index);
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                match state.streams.get_mut(&key) {
+                    Some(stream) if stream.generation == generation => {
+                        stream.in_flight.remove(&index);
+                        if let Ok(chunk) = chunk {
+                            cache.insert(chunk, &file_hash);
+                        }
+                    }
+                    _ => return,
+                }
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(task);
+            }
+            Err(_) => {
+                std::thread::spawn(task);
+            }
+        }
+    }
+
+    /// Drop all read-ahead state for a file, e.g. when it is unshared
+    ///
+    /// Chunks already read are dropped with `ChunkCache::forget_file`.
+    pub fn forget_file(&self, file_id: &str) {
+        self.lock()
+            .streams
+            .retain(|(_, stream_file), _| stream_file != file_id);
+    }
+
+    /// Current read-ahead counters
+    pub fn stats(&self) -> PrefetchStats {
+        let state = self.lock();
+        PrefetchStats {
+            hits: state.hits,
+            misses: state.misses,
+            in_flight: state.streams.values().map(|s| s.in_flight.len()).sum(),
+        }
+    }
+
+    /// Whether a request for `chunk_index` continues the stream's sequence
+    fn near(&self, stream: &PrefetchStream, chunk_index: usize) -> bool {
+        chunk_index + self.read_ahead >= stream.next_index
+            && chunk_index < stream.next_index + self.read_ahead
+    }
+
+    fn evict_least_recently_used(state: &mut PrefetchState) {
+        if let Some(key) = state
+            .streams
+            .iter()
+            .min_by_key(|(_, stream)| stream.last_used)
+            .map(|(key, _)| key.clone())
+        {
+            state.streams.remove(&key);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PrefetchState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,156 @@
+//! Self-test of the P2P stack for support reports
+//!
+//! `P2pClient::run_diagnostics` runs every check in `DiagnosticCheck` and
+//! returns a `Diagnostics` report. A failed check carries a hint on what to
+//! look at, so "file transfer doesn't work" comes with something to go on.
+
+use libp2p::multiaddr::Protocol;
+use libp2p::Multiaddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::time::Duration;
+
+/// How long the loopback check waits for its own listener to accept
+const LOOPBACK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A part of the stack checked by `P2pClient::run_diagnostics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticCheck {
+    /// At least one listener is bound
+    Listener,
+    /// Discovery has found at least one peer
+    Discovery,
+    /// A TCP listener accepts a connection over loopback
+    Loopback,
+    /// The download directory (and temp directory, if set) is writable
+    DownloadDirectory,
+    /// The persistence store answers queries
+    Store,
+}
+
+/// Outcome of one diagnostic check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticStatus {
+    Passed,
+    Failed,
+    /// Not applicable to this configuration, e.g. the store without persistence
+    Skipped,
+}
+
+/// Result of one diagnostic check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticResult {
+    pub check: DiagnosticCheck,
+    pub status: DiagnosticStatus,
+    /// What was found, e.g. the bound addresses or the error
+    pub detail: String,
+    /// What to look at when the check failed
+    pub hint: Option<String>,
+}
+
+impl DiagnosticResult {
+    pub(crate) fn passed(check: DiagnosticCheck, detail: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: DiagnosticStatus::Passed,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    pub(crate) fn failed(
+        check: DiagnosticCheck,
+        detail: impl Into<String>,
+        hint: impl Into<String>,
+    ) -> Self {
+        Self {
+            check,
+            status: DiagnosticStatus::Failed,
+            detail: detail.into(),
+            hint: Some(hint.into()),
+        }
+    }
+
+    pub(crate) fn skipped(check: DiagnosticCheck, detail: impl Into<String>) -> Self {
+        Self {
+            check,
+            status: DiagnosticStatus::Skipped,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+}
+
+/// Report returned by `P2pClient::run_diagnostics`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostics {
+    /// One result per check, in the order of `DiagnosticCheck`
+    pub results: Vec<DiagnosticResult>,
+}
+
+impl Diagnostics {
+    /// Whether no check failed; skipped checks count as passed
+    pub fn all_passed(&self) -> bool {
+        self.results
+            .iter()
+            .all(|result| result.status != DiagnosticStatus::Failed)
+    }
+
+    /// Result of `check`
+    pub fn get(&self, check: DiagnosticCheck) -> Option<&DiagnosticResult> {
+        self.results.iter().find(|result| result.check == check)
+    }
+
+    /// Results of the checks that failed
+    pub fn failures(&self) -> impl Iterator<Item = &DiagnosticResult> {
+        self.results
+            .iter()
+            .filter(|result| result.status == DiagnosticStatus::Failed)
+    }
+}
+
+/// Check that `directory` can be created, written, read back and cleaned up
+pub(crate) fn check_directory(directory: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(directory)?;
+    let probe = directory.join(".gigi-diagnostics.probe");
+    let contents = b"gigi diagnostics";
+    let result = std::fs::write(&probe, contents).and_then(|()| {
+        if std::fs::read(&probe)? == contents {
+            Ok(())
+        } else {
+            Err(std::io::Error::other("probe file read back differently"))
+        }
+    });
+    let _ = std::fs::remove_file(&probe);
+    result
+}
+
+/// Loopback address of a bound TCP listen address
+///
+/// Listeners on the unspecified address are reached on the loopback address
+/// of the same family; other addresses are used as they are.
+pub(crate) fn tcp_loopback_target(addr: &Multiaddr) -> Option<SocketAddr> {
+    let mut protocols = addr.iter();
+    let ip = match protocols.next()? {
+        Protocol::Ip4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        Protocol::Ip4(ip) => IpAddr::V4(ip),
+        Protocol::Ip6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        Protocol::Ip6(ip) => IpAddr::V6(ip),
+        _ => return None,
+    };
+    match protocols.next()? {
+        Protocol::Tcp(port) => Some(SocketAddr::new(ip, port)),
+        _ => None,
+    }
+}
+
+/// Open and close a TCP connection to `target`
+pub(crate) async fn check_loopback(target: SocketAddr) -> std::io::Result<()> {
+    match tokio::time::timeout(LOOPBACK_TIMEOUT, tokio::net::TcpStream::connect(target)).await {
+        Ok(stream) => stream.map(drop),
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "connection timed out",
+        )),
+    }
+}
@@ -0,0 +1,141 @@
+//! Pluggable peer discovery backends
+//!
+//! Discovered and expired peers reach `P2pClient` as `DiscoveryEvent`s, no
+//! matter where they come from:
+//!
+//! - By default gigi-dns finds peers over mDNS. It runs inside the swarm
+//!   because it announces the swarm's listen addresses, and its events are
+//!   converted with `From<GigiDnsEvent>`.
+//! - A client created with `P2pClient::new_with_discovery` disables gigi-dns
+//!   and polls the given `Discovery` backend instead, e.g. a `StaticDiscovery`
+//!   with a fixed peer list, a DHT lookup or a test double.
+
+use anyhow::Result;
+use futures::Stream;
+use libp2p::{Multiaddr, PeerId};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+/// A change in the set of discovered peers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscoveryEvent {
+    /// A peer was found at `address`; the client dials it
+    Discovered {
+        peer_id: PeerId,
+        nickname: String,
+        address: Multiaddr,
+    },
+    /// A known peer announced a new nickname
+    Updated { peer_id: PeerId, nickname: String },
+    /// A peer is no longer available
+    Expired { peer_id: PeerId },
+}
+
+impl From<gigi_dns::GigiDnsEvent> for DiscoveryEvent {
+    fn from(event: gigi_dns::GigiDnsEvent) -> Self {
+        match event {
+            gigi_dns::GigiDnsEvent::Discovered(peer_info) => Self::Discovered {
+                peer_id: peer_info.peer_id,
+                nickname: peer_info.nickname,
+                address: peer_info.multiaddr,
+            },
+            gigi_dns::GigiDnsEvent::Updated {
+                peer_id, new_info, ..
+            } => Self::Updated {
+                peer_id,
+                nickname: new_info.nickname,
+            },
+            gigi_dns::GigiDnsEvent::Expired { peer_id, .. }
+            | gigi_dns::GigiDnsEvent::Offline { peer_id, .. } => Self::Expired { peer_id },
+        }
+    }
+}
+
+/// A source of discovered and expired peers
+///
+/// The client calls `start` once when it is created and `stop` on
+/// `P2pClient::shutdown`, and polls the stream alongside the swarm. The
+/// stream should stay pending rather than end while there is nothing new.
+pub trait Discovery: Stream<Item = DiscoveryEvent> + Send + Unpin {
+    /// Begin discovering peers
+    fn start(&mut self) -> Result<()>;
+
+    /// Stop discovering peers; no further events are expected
+    fn stop(&mut self);
+
+    /// Look for peers again right away, e.g. when a nickname could not be
+    /// resolved; does nothing by default
+    fn refresh(&mut self) {}
+}
+
+/// A peer announced by `StaticDiscovery`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticPeer {
+    pub peer_id: PeerId,
+    pub nickname: String,
+    pub address: Multiaddr,
+}
+
+/// Discovery backend that reports a fixed list of peers
+///
+/// Every peer is reported as discovered once when the backend starts.
+/// Useful for known peers on networks without multicast and in tests.
+pub struct StaticDiscovery {
+    peers: Vec<StaticPeer>,
+    pending: VecDeque<DiscoveryEvent>,
+    waker: Option<Waker>,
+}
+
+impl StaticDiscovery {
+    /// Create a backend reporting `peers`
+    pub fn new(peers: Vec<StaticPeer>) -> Self {
+        Self {
+            peers,
+            pending: VecDeque::new(),
+            waker: None,
+        }
+    }
+
+    fn report_peers(&mut self) {
+        self.pending
+            .extend(self.peers.iter().map(|peer| DiscoveryEvent::Discovered {
+                peer_id: peer.peer_id,
+                nickname: peer.nickname.clone(),
+                address: peer.address.clone(),
+            }));
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Discovery for StaticDiscovery {
+    fn start(&mut self) -> Result<()> {
+        self.report_peers();
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Report every peer again, so expired ones are dialed again
+    fn refresh(&mut self) {
+        self.report_peers();
+    }
+}
+
+impl Stream for StaticDiscovery {
+    type Item = DiscoveryEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.pending.pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None => {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Placeholder names for peers without a nickname
+//!
+//! Until a peer announces its nickname, the UI needs something to show.
+//! A raw `PeerId` is 52 characters long, so by default a short
+//! adjective-animal label such as `quick-fox-Xk3z` is derived from it
+//! instead. The last characters of the `PeerId` keep peers whose words
+//! collide apart. The label only depends on the `PeerId`, so a peer keeps
+//! the same placeholder across restarts and on every device.
+
+use libp2p::PeerId;
+
+const ADJECTIVES: [&str; 32] = [
+    "amber", "brave", "calm", "clever", "cosmic", "crisp", "eager", "fancy", "gentle", "golden",
+    "happy", "hidden", "jolly", "kind", "lively", "lucky", "mellow", "misty", "noble", "polite",
+    "proud", "quick", "quiet", "rapid", "shiny", "silent", "sleepy", "snowy", "sunny", "swift",
+    "witty", "zesty",
+];
+
+const ANIMALS: [&str; 32] = [
+    "badger", "bear", "beaver", "bison", "crane", "deer", "dolphin", "eagle", "falcon", "ferret",
+    "fox", "gecko", "heron", "koala", "lemur", "lynx", "marmot", "moose", "otter", "owl", "panda",
+    "parrot", "puffin", "rabbit", "raven", "seal", "sparrow", "tiger", "turtle", "walrus", "whale",
+    "wolf",
+];
+
+/// How peers without a nickname are labelled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnnamedPeerLabel {
+    /// Short adjective-animal name derived from the peer ID, ending in its
+    /// last characters
+    #[default]
+    Derived,
+    /// The full peer ID string
+    PeerId,
+}
+
+impl UnnamedPeerLabel {
+    /// Placeholder name for a peer that has not announced a nickname
+    pub fn label_for(self, peer_id: &PeerId) -> String {
+        match self {
+            Self::Derived => display_name_for(peer_id),
+            Self::PeerId => peer_id.to_string(),
+        }
+    }
+}
+
+/// Characters of the peer ID appended to derived names
+const PEER_ID_SUFFIX_LEN: usize = 4;
+
+/// Derive a short, stable adjective-animal name from a peer ID
+///
+/// # Arguments
+/// * `peer_id` - Peer to name
+///
+/// # Returns
+/// A name such as `"quick-fox-Xk3z"`, ending in the last characters of the
+/// peer ID; the same peer always gets the same name
+pub fn display_name_for(peer_id: &PeerId) -> String {
+    let digest = blake3::hash(&peer_id.to_bytes());
+    let bytes = digest.as_bytes();
+    let adjective = ADJECTIVES[bytes[0] as usize % ADJECTIVES.len()];
+    let animal = ANIMALS[bytes[1] as usize % ANIMALS.len()];
+    let peer_id = peer_id.to_string();
+    let suffix = &peer_id[peer_id.len().saturating_sub(PEER_ID_SUFFIX_LEN)..];
+    format!("{}-{}-{}", adjective, animal, suffix)
+}
@@ -1,13 +1,15 @@
 //! Download management functionality for mobile apps
 
 use anyhow::Result;
-use sha2::Digest;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
-use crate::events::{ActiveDownload, FileInfo};
+use super::download_request::{DownloadPriority, DownloadRequest};
+use super::file_sharing::HashAlgo;
+use super::progress::{ProgressGranularity, ProgressThrottle};
+use crate::events::{ActiveDownload, DownloadDetail, DownloadFailureReason, FileInfo};
 
 /// Downloading file information
 #[derive(Debug, Clone)]
@@ -16,6 +18,37 @@ pub struct DownloadingFile {
     pub output_path: PathBuf,
     pub temp_path: PathBuf,
     pub downloaded_chunks: HashMap<usize, bool>,
+    /// Hash each written chunk arrived with, used to find corrupted chunks
+    pub chunk_hashes: HashMap<usize, String>,
+    /// Repair rounds already run after a whole-file hash mismatch
+    pub repair_rounds: usize,
+    /// Peers already used or asked as the source of this download
+    pub tried_sources: HashSet<libp2p::PeerId>,
+    /// Share code the current source knows the file by; differs from
+    /// `info.id` once another peer sharing the file took over
+    pub source_file_id: String,
+}
+
+impl DownloadingFile {
+    /// Bytes of the chunks received so far
+    pub fn received_bytes(&self) -> u64 {
+        self.downloaded_chunks
+            .iter()
+            .filter(|(_, &received)| received)
+            .filter_map(|(&index, _)| self.info.chunk_span(index))
+            .map(|(offset, len)| (len as u64).min(self.info.size.saturating_sub(offset)))
+            .sum()
+    }
+}
+
+/// Pending `GetFileInfo` request asking another peer to take over a
+/// stalled download
+#[derive(Debug, Clone)]
+pub struct FailoverProbe {
+    pub download_id: String,
+    /// Failure reported if no other peer can take over
+    pub error: String,
+    pub reason: DownloadFailureReason,
 }
 
 /// Download management functionality
@@ -26,6 +59,18 @@ pub struct DownloadManager {
     output_directory: PathBuf,
     chunk_reader: Option<super::file_sharing::FileChunkReader>,
     request_id_to_download: HashMap<String, String>, // request_id (as string) -> download_id mapping
+    request_chunks: HashMap<String, usize>, // request_id (as string) -> requested chunk index
+    failover_probes: HashMap<String, FailoverProbe>, // request_id (as string) -> probe
+    organize_by_sender: bool,
+    /// Give completed downloads the modification time of the shared file
+    preserve_modified_time: bool,
+    temp_directory: Option<PathBuf>,
+    /// Which progress updates of each download become events
+    progress: ProgressThrottle,
+    /// Repair rounds allowed per download after a whole-file hash mismatch
+    max_repair_rounds: usize,
+    /// Hints of downloads started with a `DownloadRequest`, by download_id
+    requests: HashMap<String, DownloadRequest>,
 }
 
 impl DownloadManager {
@@ -38,9 +83,62 @@ impl DownloadManager {
             output_directory,
             chunk_reader: None,
             request_id_to_download: HashMap::new(),
+            request_chunks: HashMap::new(),
+            failover_probes: HashMap::new(),
+            organize_by_sender: false,
+            preserve_modified_time: true,
+            temp_directory: None,
+            progress: ProgressThrottle::new(ProgressGranularity::default()),
+            max_repair_rounds: 0,
+            requests: HashMap::new(),
         }
     }
 
+    /// Directory completed downloads are saved to
+    pub fn output_directory(&self) -> &Path {
+        &self.output_directory
+    }
+
+    /// Set how often download progress is reported
+    pub fn set_progress_granularity(&mut self, granularity: ProgressGranularity) {
+        self.progress.set_granularity(granularity);
+    }
+
+    /// Whether a progress update should be emitted as an event
+    ///
+    /// The final chunk is always reported.
+    pub fn should_report_progress(
+        &mut self,
+        download_id: &str,
+        downloaded_chunks: usize,
+        total_chunks: usize,
+    ) -> bool {
+        self.progress
+            .should_report(download_id, downloaded_chunks, total_chunks)
+    }
+
+    /// Re-request corrupted chunks up to `rounds` times when the whole-file hash mismatches
+    pub fn set_max_repair_rounds(&mut self, rounds: usize) {
+        self.max_repair_rounds = rounds;
+    }
+
+    /// Write in-progress `.downloading` files under `directory` instead of the output directory
+    ///
+    /// Completed files are moved into the output directory afterwards.
+    pub fn set_temp_directory(&mut self, directory: Option<PathBuf>) {
+        self.temp_directory = directory;
+    }
+
+    /// Enable or disable saving downloads into per-sender subfolders
+    pub fn set_organize_by_sender(&mut self, enabled: bool) {
+        self.organize_by_sender = enabled;
+    }
+
+    /// Enable or disable giving completed downloads the sharer's modification time
+    pub fn set_preserve_modified_time(&mut self, enabled: bool) {
+        self.preserve_modified_time = enabled;
+    }
+
     /// Set the chunk reader callback for URI-based files
     pub fn set_chunk_reader(&mut self, reader: super::file_sharing::FileChunkReader) {
         self.chunk_reader = Some(reader);
@@ -82,6 +180,7 @@ impl DownloadManager {
             total_chunks: 0,
             downloaded_chunks: 0,
             started_at: Instant::now(),
+            last_progress_at: Instant::now(),
             completed: false,
             failed: false,
             error_message: None,
@@ -130,6 +229,7 @@ impl DownloadManager {
             total_chunks,
             downloaded_chunks: 0,
             started_at,
+            last_progress_at: Instant::now(),
             completed: false,
             failed: false,
             error_message: None,
@@ -146,15 +246,41 @@ impl DownloadManager {
     pub fn update_download_progress(&mut self, download_id: &str, downloaded_chunks: usize) {
         if let Some(active_download) = self.active_downloads.get_mut(download_id) {
             active_download.downloaded_chunks = downloaded_chunks;
+            active_download.last_progress_at = Instant::now();
         }
     }
 
+    /// Downloads in flight that made no progress for `idle_timeout`
+    ///
+    /// Downloads waiting for another peer to take over are left out.
+    pub fn get_idle_downloads(&self, idle_timeout: Duration) -> Vec<String> {
+        let now = Instant::now();
+        self.active_downloads
+            .values()
+            .filter(|download| !download.completed && !download.failed)
+            .filter(|download| !self.is_failing_over(&download.download_id))
+            .filter(|download| now.duration_since(download.last_progress_at) >= idle_timeout)
+            .map(|download| download.download_id.clone())
+            .collect()
+    }
+
+    /// When the first download in flight becomes idle for `idle_timeout`
+    pub fn next_idle_deadline(&self, idle_timeout: Duration) -> Option<Instant> {
+        self.active_downloads
+            .values()
+            .filter(|download| !download.completed && !download.failed)
+            .filter(|download| !self.is_failing_over(&download.download_id))
+            .map(|download| download.last_progress_at + idle_timeout)
+            .min()
+    }
+
     /// Mark download as completed
     pub fn complete_download(
         &mut self,
         download_id: &str,
         final_path: std::path::PathBuf,
     ) -> Option<ActiveDownload> {
+        self.progress.forget(download_id);
         if let Some(mut active_download) = self.active_downloads.remove(download_id) {
             active_download.completed = true;
             active_download.final_path = Some(final_path);
@@ -170,6 +296,9 @@ impl DownloadManager {
         download_id: &str,
         error_message: String,
     ) -> Option<ActiveDownload> {
+        // Failures that leave the downloading file behind, e.g. a failed
+        // move, would otherwise keep its progress state forever
+        self.progress.forget(download_id);
         if let Some(mut active_download) = self.active_downloads.remove(download_id) {
             active_download.failed = true;
             active_download.error_message = Some(error_message);
@@ -184,6 +313,39 @@ impl DownloadManager {
         self.active_downloads.values().collect()
     }
 
+    /// Get detailed progress for all active downloads
+    pub fn get_download_details(&self) -> Vec<DownloadDetail> {
+        self.active_downloads
+            .values()
+            .map(|download| {
+                let bytes_done = self
+                    .downloading_files
+                    .get(&download.download_id)
+                    .map_or(0, DownloadingFile::received_bytes);
+                let elapsed = download.started_at.elapsed().as_secs_f64();
+                let speed = if elapsed > 0.0 {
+                    bytes_done as f64 / elapsed
+                } else {
+                    0.0
+                };
+
+                DownloadDetail {
+                    download_id: download.download_id.clone(),
+                    share_code: self
+                        .get_share_code_for_download(&download.download_id)
+                        .unwrap_or_else(|| download.share_code.clone()),
+                    filename: download.filename.clone(),
+                    from_nickname: download.from_nickname.clone(),
+                    downloaded_chunks: download.downloaded_chunks,
+                    total_chunks: download.total_chunks,
+                    bytes_done,
+                    speed,
+                    final_path: download.final_path.clone(),
+                }
+            })
+            .collect()
+    }
+
     /// Get active download by download_id
     pub fn get_active_download(&self, download_id: &str) -> Option<&ActiveDownload> {
         self.active_downloads.get(download_id)
@@ -196,6 +358,22 @@ impl DownloadManager {
             .find(|download| download.share_code == share_code)
     }
 
+    /// Get the download of `share_code` from `peer_id` that is still in flight
+    ///
+    /// Completed and failed downloads are ignored.
+    pub fn get_in_flight_download(
+        &self,
+        share_code: &str,
+        peer_id: &libp2p::PeerId,
+    ) -> Option<&ActiveDownload> {
+        self.active_downloads.values().find(|download| {
+            download.share_code == share_code
+                && download.from_peer_id == *peer_id
+                && !download.completed
+                && !download.failed
+        })
+    }
+
     /// Remove completed or failed downloads (cleanup)
     pub fn cleanup_downloads(&mut self) {
         self.active_downloads
@@ -227,6 +405,8 @@ impl DownloadManager {
         // Clean up stale request_id mappings
         self.request_id_to_download
             .retain(|_, download_id| self.active_downloads.contains_key(download_id));
+        self.request_chunks
+            .retain(|request_id, _| self.request_id_to_download.contains_key(request_id));
     }
 
     /// Get downloads from a specific peer
@@ -237,6 +417,56 @@ impl DownloadManager {
             .collect()
     }
 
+    /// Ids of the unfinished downloads not being moved to another source
+    pub fn get_downloading_ids(&self) -> Vec<String> {
+        self.downloading_files
+            .keys()
+            .filter(|download_id| {
+                self.active_downloads
+                    .get(*download_id)
+                    .is_some_and(|download| !download.completed && !download.failed)
+            })
+            .filter(|download_id| !self.is_failing_over(download_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Ids of all downloads not finished yet, including those still waiting
+    /// for their file info
+    pub fn get_unfinished_ids(&self) -> Vec<String> {
+        self.active_downloads
+            .values()
+            .filter(|download| !download.completed && !download.failed)
+            .map(|download| download.download_id.clone())
+            .collect()
+    }
+
+    /// Remember the hints a download was started with
+    pub fn set_download_request(&mut self, download_id: &str, request: DownloadRequest) {
+        self.requests.insert(download_id.to_string(), request);
+    }
+
+    /// Hints a download was started with, if it came from a `DownloadRequest`
+    pub fn download_request(&self, download_id: &str) -> Option<&DownloadRequest> {
+        self.requests.get(download_id)
+    }
+
+    /// Priority of a download, `Normal` unless its request set one
+    pub fn download_priority(&self, download_id: &str) -> DownloadPriority {
+        self.requests
+            .get(download_id)
+            .map(|request| request.priority)
+            .unwrap_or_default()
+    }
+
+    /// Number of chunk requests of a download still waiting for a response
+    pub fn requests_in_flight(&self, download_id: &str) -> usize {
+        self.request_id_to_download
+            .values()
+            .filter(|mapped_id| *mapped_id == download_id)
+            .count()
+    }
+
     /// Associate a request_id with a download_id
     pub fn map_request_to_download(&mut self, request_id: String, download_id: String) {
         self.request_id_to_download.insert(request_id, download_id);
@@ -250,6 +480,121 @@ impl DownloadManager {
     /// Clean up request_id to download_id mapping
     pub fn cleanup_request_mapping(&mut self, request_id: &str) {
         self.request_id_to_download.remove(request_id);
+        self.request_chunks.remove(request_id);
+    }
+
+    /// Remember which chunk a request asked for
+    pub fn map_request_to_chunk(&mut self, request_id: String, chunk_index: usize) {
+        self.request_chunks.insert(request_id, chunk_index);
+    }
+
+    /// Give up a chunk request, so its chunk is requested again
+    ///
+    /// # Returns
+    /// The download_id of the request, if it was a chunk request
+    pub fn release_chunk_request(&mut self, request_id: &str) -> Option<String> {
+        let chunk_index = self.request_chunks.remove(request_id)?;
+        let download_id = self.request_id_to_download.remove(request_id)?;
+        if let Some(downloading_file) = self.downloading_files.get_mut(&download_id) {
+            if downloading_file.downloaded_chunks.get(&chunk_index) == Some(&false) {
+                downloading_file.downloaded_chunks.remove(&chunk_index);
+            }
+        }
+        Some(download_id)
+    }
+
+    /// Stop requesting chunks of a stalled download from its source
+    ///
+    /// The source is marked as tried and the chunk requests still in flight
+    /// to it are forgotten, so their chunks are requested again from the
+    /// next source.
+    ///
+    /// # Returns
+    /// Whether the download was in progress
+    pub fn release_stalled_source(&mut self, download_id: &str) -> bool {
+        let Some(source) = self
+            .active_downloads
+            .get(download_id)
+            .map(|download| download.from_peer_id)
+        else {
+            return false;
+        };
+        let Some(downloading_file) = self.downloading_files.get_mut(download_id) else {
+            return false;
+        };
+        downloading_file.tried_sources.insert(source);
+        downloading_file
+            .downloaded_chunks
+            .retain(|_, received| *received);
+        self.request_id_to_download
+            .retain(|_, mapped_id| mapped_id != download_id);
+        self.request_chunks
+            .retain(|request_id, _| self.request_id_to_download.contains_key(request_id));
+        true
+    }
+
+    /// Pick the next peer to ask for a stalled download and mark it as tried
+    ///
+    /// # Arguments
+    /// * `candidates` - Peers sharing the same content, with the share code
+    ///   each knows the file by
+    ///
+    /// # Returns
+    /// The first of `candidates` not tried yet with its share code, or
+    /// `None` if every peer was tried or the download is no longer in progress
+    pub fn next_failover_source(
+        &mut self,
+        download_id: &str,
+        candidates: impl IntoIterator<Item = (libp2p::PeerId, String)>,
+    ) -> Option<(libp2p::PeerId, String)> {
+        let downloading_file = self.downloading_files.get_mut(download_id)?;
+        let (peer, file_id) = candidates
+            .into_iter()
+            .find(|(peer, _)| !downloading_file.tried_sources.contains(peer))?;
+        downloading_file.tried_sources.insert(peer);
+        Some((peer, file_id))
+    }
+
+    /// Continue a stalled download from `peer`, which knows the file as `file_id`
+    pub fn switch_download_source(
+        &mut self,
+        download_id: &str,
+        peer: libp2p::PeerId,
+        nickname: String,
+        file_id: String,
+    ) {
+        if let Some(active_download) = self.active_downloads.get_mut(download_id) {
+            active_download.from_peer_id = peer;
+            active_download.from_nickname = nickname;
+            active_download.last_progress_at = Instant::now();
+        }
+        if let Some(downloading_file) = self.downloading_files.get_mut(download_id) {
+            downloading_file.source_file_id = file_id;
+        }
+    }
+
+    /// Forget every request in flight, e.g. when the swarm that sent them is gone
+    pub fn forget_requests(&mut self) {
+        self.request_id_to_download.clear();
+        self.request_chunks.clear();
+        self.failover_probes.clear();
+    }
+
+    /// Associate a failover `GetFileInfo` request_id with its probe
+    pub fn track_failover_probe(&mut self, request_id: String, probe: FailoverProbe) {
+        self.failover_probes.insert(request_id, probe);
+    }
+
+    /// Remove and return the failover probe of a request_id
+    pub fn take_failover_probe(&mut self, request_id: &str) -> Option<FailoverProbe> {
+        self.failover_probes.remove(request_id)
+    }
+
+    /// Whether another peer is being asked to take over a download
+    pub fn is_failing_over(&self, download_id: &str) -> bool {
+        self.failover_probes
+            .values()
+            .any(|probe| probe.download_id == download_id)
     }
 
     /// Get recent downloads (useful for UI history)
@@ -335,9 +680,9 @@ impl DownloadManager {
 
     // ===== File System and Download Management Methods =====
 
-    /// Find available filename (append number if exists)
-    pub fn find_available_filename(&self, filename: &str) -> String {
-        let path = self.output_directory.join(filename);
+    /// Find available filename in `directory` (append number if exists)
+    pub fn find_available_filename(&self, directory: &Path, filename: &str) -> String {
+        let path = directory.join(filename);
 
         if !path.exists() {
             return filename.to_string();
@@ -354,7 +699,7 @@ impl DownloadManager {
                 format!("{}_{}.{}", stem, i, extension)
             };
 
-            if !self.output_directory.join(&candidate).exists() {
+            if !directory.join(&candidate).exists() {
                 return candidate;
             }
         }
@@ -367,31 +712,111 @@ impl DownloadManager {
         format!("{}_{}.{}", stem, timestamp, extension)
     }
 
+    /// Directory a download from `sender_nickname` should be saved into
+    ///
+    /// Creates the per-sender subfolder on demand when organizing by sender.
+    fn destination_directory(&self, sender_nickname: &str) -> Result<PathBuf> {
+        if !self.organize_by_sender {
+            return Ok(self.output_directory.clone());
+        }
+
+        let directory = self
+            .output_directory
+            .join(crate::validation::sanitize_folder_name(sender_nickname));
+        std::fs::create_dir_all(&directory)?;
+        Ok(directory)
+    }
+
+    /// Directory in-progress downloads are written to, created on demand
+    fn scratch_directory(&self) -> Result<PathBuf> {
+        match &self.temp_directory {
+            Some(directory) => {
+                std::fs::create_dir_all(directory)?;
+                Ok(directory.clone())
+            }
+            None => Ok(self.output_directory.clone()),
+        }
+    }
+
+    /// Move a finished download from its temp path to the final output path
+    ///
+    /// Falls back to copy + remove when a rename is not possible, e.g. when the
+    /// temp directory is on a different volume than the output directory.
+    /// `modified_at` (ns since the Unix epoch) from the sharer's `FileInfo` is
+    /// applied to the moved file if enabled; where that is not supported the
+    /// file keeps the current time.
+    pub fn move_to_output(
+        &self,
+        temp_path: &Path,
+        output_path: &Path,
+        modified_at: Option<i64>,
+    ) -> Result<()> {
+        if std::fs::rename(temp_path, output_path).is_err() {
+            std::fs::copy(temp_path, output_path)?;
+            std::fs::remove_file(temp_path)?;
+        }
+        if let Some(modified_at) = modified_at.filter(|_| self.preserve_modified_time) {
+            if let Err(e) = set_modified_time(output_path, modified_at) {
+                gigi_logging::debug!(
+                    "Keeping current modification time of {}: {}",
+                    output_path.display(),
+                    e
+                );
+            }
+        }
+        Ok(())
+    }
+
     /// Start downloading a file after receiving file info
     pub fn start_download_file(
         &mut self,
         _peer_id: libp2p::PeerId,
+        sender_nickname: &str,
         info: FileInfo,
         download_id: Option<&str>,
     ) -> Result<()> {
-        // Find available filename
-        let filename = self.find_available_filename(&info.name);
-        let output_path = self.output_directory.join(&filename);
+        // The name comes from the sharing peer, so never let it pick the directory
+        let requested_directory = download_id
+            .and_then(|id| self.requests.get(id))
+            .and_then(|request| request.target_directory.clone());
+        let mut directory = match requested_directory {
+            Some(directory) => {
+                std::fs::create_dir_all(&directory)?;
+                directory
+            }
+            None => self.destination_directory(sender_nickname)?,
+        };
+        // Files of a directory share go into their folders below it
+        if let Some(relative_path) = &info.relative_path {
+            let folders = crate::validation::sanitize_relative_directory(relative_path);
+            if !folders.as_os_str().is_empty() {
+                directory = directory.join(folders);
+                std::fs::create_dir_all(&directory)?;
+            }
+        }
+        let safe_name = crate::validation::sanitize_filename(&info.name);
+        let filename = self.find_available_filename(&directory, &safe_name);
+        let output_path = directory.join(&filename);
+        if output_path.parent() != Some(directory.as_path()) {
+            return Err(
+                crate::P2pError::InvalidInput(format!("Unsafe filename: {}", info.name)).into(),
+            );
+        }
+        let scratch_directory = self.scratch_directory()?;
 
         // Use download_id for temp path to ensure uniqueness when same file is downloaded multiple times
         // If download_id is provided, use it; otherwise fall back to info.id with timestamp
         let temp_path = if let Some(dl_id) = download_id {
             // Extract the unique part from download_id (e.g., "dl_..." or "pending_...")
             // Use the download_id directly to ensure unique temp paths
-            self.output_directory.join(format!("{}.downloading", dl_id))
+            scratch_directory.join(format!("{}.downloading", dl_id))
         } else {
             // Fallback: use info.id with timestamp for uniqueness
             let timestamp = std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .map_err(|e| anyhow::anyhow!("System time error: {}", e))?
                 .as_nanos();
-            self.output_directory
-                .join(format!("{}_{}.downloading", info.id, timestamp))
+            scratch_directory.join(format!("{}_{}.downloading", info.id, timestamp))
         };
 
         // Create downloading file entry
@@ -400,10 +825,15 @@ impl DownloadManager {
             output_path: output_path.clone(),
             temp_path: temp_path.clone(),
             downloaded_chunks: HashMap::new(),
+            chunk_hashes: HashMap::new(),
+            repair_rounds: 0,
+            tried_sources: HashSet::new(),
+            source_file_id: info.id.clone(),
         };
 
         // Use download_id as key instead of info.id to support parallel downloads of the same file
         let key = download_id.unwrap_or(&info.id);
+        self.progress.start(key);
         self.downloading_files
             .insert(key.to_string(), downloading_file);
 
@@ -422,24 +852,46 @@ impl DownloadManager {
 
     /// Remove downloading file
     pub fn remove_downloading_file(&mut self, download_id: &str) -> Option<DownloadingFile> {
+        self.progress.forget(download_id);
+        self.requests.remove(download_id);
         self.downloading_files.remove(download_id)
     }
 
-    /// Calculate SHA256 hash of a file
-    pub fn calculate_file_hash(&self, file_path: &Path) -> Result<String> {
-        let mut file = std::fs::File::open(file_path)?;
-        let mut hasher = sha2::Sha256::new();
-        let mut buffer = [0; 8192];
-
-        loop {
-            let bytes_read = file.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
+    /// Stop an in-progress download and clean up its transfer state
+    ///
+    /// Removes the downloading file entry, deletes its temp file and drops the
+    /// request mappings of chunk requests still in flight. The active download
+    /// entry is kept so the failure can still be reported.
+    ///
+    /// # Returns
+    /// The removed downloading file, or `None` if it was not in progress
+    pub fn abort_download(&mut self, download_id: &str) -> Option<DownloadingFile> {
+        self.progress.forget(download_id);
+        self.requests.remove(download_id);
+        let downloading_file = self.downloading_files.remove(download_id)?;
+        if let Err(e) = std::fs::remove_file(&downloading_file.temp_path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                gigi_logging::warn!(
+                    "Failed to remove temp file {}: {}",
+                    downloading_file.temp_path.display(),
+                    e
+                );
             }
-            hasher.update(&buffer[..bytes_read]);
         }
+        self.request_id_to_download
+            .retain(|_, mapped_id| mapped_id != download_id);
+        self.request_chunks
+            .retain(|request_id, _| self.request_id_to_download.contains_key(request_id));
+        Some(downloading_file)
+    }
 
-        Ok(format!("{:x}", hasher.finalize()))
+    /// Calculate the whole-file hash of a file
+    ///
+    /// # Arguments
+    /// * `file_path` - File to hash
+    /// * `hash_algo` - Algorithm the sharer announced in its `FileInfo`
+    pub fn calculate_file_hash(&self, file_path: &Path, hash_algo: HashAlgo) -> Result<String> {
+        hash_algo.hash_file(file_path)
     }
 
     /// Calculate hash of data chunk
@@ -452,56 +904,42 @@ impl DownloadManager {
     /// Read a chunk from a shared file (for serving downloads to others)
     pub fn read_chunk(
         &self,
-        file_path: &crate::events::FilePath,
+        shared_file: &crate::events::SharedFile,
         chunk_index: usize,
         file_id: &str,
     ) -> Result<crate::events::ChunkInfo> {
-        use crate::events::{ChunkInfo, FilePath};
-        use gigi_file_sharing::CHUNK_SIZE;
-
-        let offset = chunk_index
-            .checked_mul(CHUNK_SIZE)
-            .ok_or_else(|| anyhow::anyhow!("Chunk index overflow: {}", chunk_index))?;
-
-        match file_path {
-            FilePath::Path(path) => {
-                // Regular file - use std::fs
-                let mut file = std::fs::File::open(path)?;
-                file.seek(std::io::SeekFrom::Start(offset as u64))?;
-
-                let mut buffer = vec![0u8; CHUNK_SIZE];
-                let bytes_read = file.read(&mut buffer)?;
-                buffer.truncate(bytes_read);
-
-                let hash = self.calculate_chunk_hash(&buffer);
+        read_chunk_at(
+            &shared_file.path,
+            chunk_index,
+            chunk_span(&shared_file.info, chunk_index)?,
+            file_id,
+            self.chunk_reader.as_ref(),
+        )
+    }
 
-                Ok(ChunkInfo {
-                    file_id: file_id.to_string(),
-                    chunk_index,
-                    data: buffer,
-                    hash,
-                })
-            }
-            FilePath::Url(_url) => {
-                // Content URI or file:// URI - use callback
-                if let Some(reader) = &self.chunk_reader {
-                    match reader(file_path, offset as u64, CHUNK_SIZE) {
-                        Ok(data) => {
-                            let hash = self.calculate_chunk_hash(&data);
-                            Ok(ChunkInfo {
-                                file_id: file_id.to_string(),
-                                chunk_index,
-                                data,
-                                hash,
-                            })
-                        }
-                        Err(e) => Err(anyhow::anyhow!("Failed to read chunk from URI: {}", e)),
-                    }
-                } else {
-                    Err(anyhow::anyhow!("No chunk reader configured for URIs"))
-                }
-            }
+    /// Read `len` bytes from offset `start` of a shared file, fewer at the
+    /// end of the file (for serving byte range requests)
+    pub fn read_range(
+        &self,
+        shared_file: &crate::events::SharedFile,
+        start: u64,
+        len: u64,
+    ) -> Result<Vec<u8>> {
+        let len = len.min(shared_file.info.size.saturating_sub(start));
+        if len == 0 {
+            return Ok(Vec::new());
         }
+        read_bytes_at(
+            &shared_file.path,
+            start,
+            len as usize,
+            self.chunk_reader.as_ref(),
+        )
+    }
+
+    /// Chunk reader callback for URI-based files, if configured
+    pub fn chunk_reader(&self) -> Option<super::file_sharing::FileChunkReader> {
+        self.chunk_reader.clone()
     }
 
     /// Process a received chunk - handles verification, storage, and progress tracking
@@ -518,20 +956,34 @@ impl DownloadManager {
         }
 
         // Get downloading file info and extract needed data before borrowing
-        let (temp_path, output_path, expected_hash, total_chunks) = {
+        let (temp_path, output_path, expected_hash, hash_algo, total_chunks, length_check, span) = {
             let downloading_file = self
                 .get_downloading_file(download_id)
                 .ok_or_else(|| anyhow::anyhow!("Download not found: {}", download_id))?;
+            let info = &downloading_file.info;
             (
                 downloading_file.temp_path.clone(),
                 downloading_file.output_path.clone(),
-                downloading_file.info.hash.clone(),
-                downloading_file.info.chunk_count,
+                info.hash.clone(),
+                info.hash_algo,
+                info.chunk_count,
+                // Reject chunks whose length doesn't fit their position in the file
+                crate::validation::validate_file_chunk_length(info, chunk_index, chunk.data.len()),
+                info.chunk_span(chunk_index),
             )
         };
+        if let Err(e) = length_check {
+            return Ok(ChunkProcessResult::InvalidLength(e));
+        }
+        let Some((offset, _)) = span else {
+            return Ok(ChunkProcessResult::WriteFailed(format!(
+                "Chunk index overflow: {}",
+                chunk_index
+            )));
+        };
 
         // Write chunk to temp file
-        if let Err(e) = self.write_chunk_to_file(&temp_path, chunk_index, &chunk.data) {
+        if let Err(e) = self.write_chunk_to_file(&temp_path, offset, &chunk.data) {
             return Ok(ChunkProcessResult::WriteFailed(e.to_string()));
         }
 
@@ -542,6 +994,9 @@ impl DownloadManager {
 
         // Mark chunk as downloaded
         downloading_file.downloaded_chunks.insert(chunk_index, true);
+        downloading_file
+            .chunk_hashes
+            .insert(chunk_index, chunk.hash.clone());
 
         // Calculate progress
         let downloaded_count = downloading_file
@@ -560,23 +1015,114 @@ impl DownloadManager {
             output_path,
             temp_path,
             expected_hash,
+            hash_algo,
         })
     }
 
+    /// Prepare a download whose whole-file hash mismatched for repair
+    ///
+    /// Re-reads every chunk from the temp file and checks it against the hash
+    /// it arrived with. Mismatching chunks are marked as missing so that
+    /// `get_next_chunks_to_request` requests them again.
+    ///
+    /// # Returns
+    /// The chunks to re-request, or `None` when repair is disabled, the
+    /// rounds are used up or every chunk still matches
+    pub fn prepare_repair(&mut self, download_id: &str) -> Result<Option<Vec<usize>>> {
+        let max_repair_rounds = self.max_repair_rounds;
+        let downloading_file = self
+            .get_downloading_file_mut(download_id)
+            .ok_or_else(|| anyhow::anyhow!("Download not found: {}", download_id))?;
+        if downloading_file.repair_rounds >= max_repair_rounds {
+            return Ok(None);
+        }
+
+        let temp_path = crate::events::FilePath::Path(downloading_file.temp_path.clone());
+        let mut corrupted = Vec::new();
+        for chunk_index in 0..downloading_file.info.chunk_count {
+            let Some(expected_hash) = downloading_file.chunk_hashes.get(&chunk_index) else {
+                continue;
+            };
+            let matches = chunk_span(&downloading_file.info, chunk_index)
+                .and_then(|span| read_chunk_at(&temp_path, chunk_index, span, download_id, None))
+                .is_ok_and(|chunk| chunk.hash == *expected_hash);
+            if !matches {
+                corrupted.push(chunk_index);
+            }
+        }
+        if corrupted.is_empty() {
+            return Ok(None);
+        }
+
+        downloading_file.repair_rounds += 1;
+        for chunk_index in &corrupted {
+            downloading_file.downloaded_chunks.remove(chunk_index);
+            downloading_file.chunk_hashes.remove(chunk_index);
+        }
+        Ok(Some(corrupted))
+    }
+
+    /// Get the id of the in-progress download of shared file `file_id`
+    pub fn get_downloading_id_by_file_id(&self, file_id: &str) -> Option<String> {
+        self.downloading_files
+            .iter()
+            .find(|(_, file)| file.info.id == file_id)
+            .map(|(download_id, _)| download_id.clone())
+    }
+
+    /// Prepare one chunk of an in-progress download to be requested again
+    ///
+    /// A received chunk is re-read from the temp file and checked against
+    /// the hash it arrived with; only a chunk that no longer matches, or was
+    /// not received yet, is marked as requested.
+    ///
+    /// # Returns
+    /// Whether the chunk should be requested
+    pub fn prepare_chunk_request(&mut self, download_id: &str, chunk_index: usize) -> Result<bool> {
+        let downloading_file = self
+            .get_downloading_file_mut(download_id)
+            .ok_or_else(|| anyhow::anyhow!("Download not found: {}", download_id))?;
+        if chunk_index >= downloading_file.info.chunk_count {
+            return Err(crate::P2pError::InvalidInput(format!(
+                "Chunk {} out of range for {} chunks",
+                chunk_index, downloading_file.info.chunk_count
+            ))
+            .into());
+        }
+
+        if downloading_file.downloaded_chunks.get(&chunk_index) == Some(&true) {
+            let temp_path = crate::events::FilePath::Path(downloading_file.temp_path.clone());
+            let span = chunk_span(&downloading_file.info, chunk_index)?;
+            let intact =
+                downloading_file
+                    .chunk_hashes
+                    .get(&chunk_index)
+                    .is_some_and(|expected_hash| {
+                        read_chunk_at(&temp_path, chunk_index, span, download_id, None)
+                            .is_ok_and(|chunk| chunk.hash == *expected_hash)
+                    });
+            if intact {
+                return Ok(false);
+            }
+            downloading_file.chunk_hashes.remove(&chunk_index);
+        }
+        downloading_file
+            .downloaded_chunks
+            .insert(chunk_index, false);
+        Ok(true)
+    }
+
     /// Write chunk data to file at specific offset
-    fn write_chunk_to_file(&self, temp_path: &Path, chunk_index: usize, data: &[u8]) -> Result<()> {
+    fn write_chunk_to_file(&self, temp_path: &Path, offset: u64, data: &[u8]) -> Result<()> {
         use std::io::{Seek, Write};
 
-        let offset = chunk_index
-            .checked_mul(gigi_file_sharing::CHUNK_SIZE)
-            .ok_or_else(|| anyhow::anyhow!("Chunk index overflow: {}", chunk_index))?;
         let mut file = std::fs::OpenOptions::new()
             .create(true)
             .truncate(false)
             .write(true)
             .open(temp_path)?;
 
-        file.seek(std::io::SeekFrom::Start(offset as u64))?;
+        file.seek(std::io::SeekFrom::Start(offset))?;
         file.write_all(data)?;
         file.flush()?;
         Ok(())
@@ -654,8 +1200,10 @@ pub enum ChunkProcessResult {
         output_path: PathBuf,
         temp_path: PathBuf,
         expected_hash: String,
+        hash_algo: HashAlgo,
     },
     HashMismatch,
+    InvalidLength(crate::P2pError),
     WriteFailed(String),
 }
 
@@ -664,3 +1212,107 @@ impl Default for DownloadManager {
         Self::new(std::path::PathBuf::from("./downloads"))
     }
 }
+
+/// Set the modification time of `path` to `nanos` since the Unix epoch
+fn set_modified_time(path: &Path, nanos: i64) -> std::io::Result<()> {
+    let nanos = u64::try_from(nanos)
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "before the epoch"))?;
+    let modified = std::time::UNIX_EPOCH + std::time::Duration::from_nanos(nanos);
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(modified)
+}
+
+/// Offset and maximum length of a chunk of `info`, see `FileInfo::chunk_span`
+///
+/// # Errors
+/// `ChunkPastEof` if `chunk_index` is past the last content-defined chunk
+pub(crate) fn chunk_span(info: &FileInfo, chunk_index: usize) -> Result<(u64, usize)> {
+    info.chunk_span(chunk_index).ok_or_else(|| {
+        crate::P2pError::ChunkPastEof {
+            chunk_index,
+            file_size: info.size,
+        }
+        .into()
+    })
+}
+
+/// Read at most `max_len` bytes from `offset` of a shared file, using
+/// `chunk_reader` for URI-based files
+fn read_bytes_at(
+    file_path: &crate::events::FilePath,
+    offset: u64,
+    max_len: usize,
+    chunk_reader: Option<&super::file_sharing::FileChunkReader>,
+) -> Result<Vec<u8>> {
+    use crate::events::FilePath;
+
+    match file_path {
+        FilePath::Path(path) => {
+            let mut file = std::fs::File::open(path)?;
+            file.seek(std::io::SeekFrom::Start(offset))?;
+            let mut buffer = Vec::with_capacity(max_len);
+            file.take(max_len as u64).read_to_end(&mut buffer)?;
+            Ok(buffer)
+        }
+        FilePath::Url(_url) => {
+            let reader = chunk_reader
+                .ok_or_else(|| anyhow::anyhow!("No chunk reader configured for URIs"))?;
+            reader(file_path, offset, max_len)
+                .map_err(|e| anyhow::anyhow!("Failed to read range from URI: {}", e))
+        }
+    }
+}
+
+/// Read a chunk of a shared file, using `chunk_reader` for URI-based files
+///
+/// Reads at most `span.1` bytes from offset `span.0`, as given by
+/// `chunk_span`. Free function so chunks can also be read off the event
+/// loop, e.g. by the server-side read-ahead.
+pub(crate) fn read_chunk_at(
+    file_path: &crate::events::FilePath,
+    chunk_index: usize,
+    (offset, max_len): (u64, usize),
+    file_id: &str,
+    chunk_reader: Option<&super::file_sharing::FileChunkReader>,
+) -> Result<crate::events::ChunkInfo> {
+    use crate::events::{ChunkInfo, FilePath};
+
+    let data = match file_path {
+        FilePath::Path(path) => {
+            // Regular file - use std::fs
+            let mut file = std::fs::File::open(path)?;
+            // Seeking past EOF succeeds and reads nothing, which would look
+            // like a valid empty chunk
+            let file_size = file.metadata()?.len();
+            if offset >= file_size && chunk_index > 0 {
+                return Err(crate::P2pError::ChunkPastEof {
+                    chunk_index,
+                    file_size,
+                }
+                .into());
+            }
+            file.seek(std::io::SeekFrom::Start(offset))?;
+
+            let mut buffer = Vec::with_capacity(max_len);
+            file.take(max_len as u64).read_to_end(&mut buffer)?;
+            buffer
+        }
+        FilePath::Url(_url) => {
+            // Content URI or file:// URI - use callback
+            let reader = chunk_reader
+                .ok_or_else(|| anyhow::anyhow!("No chunk reader configured for URIs"))?;
+            reader(file_path, offset, max_len)
+                .map_err(|e| anyhow::anyhow!("Failed to read chunk from URI: {}", e))?
+        }
+    };
+
+    let hash = blake3::hash(&data).to_hex().to_string();
+    Ok(ChunkInfo {
+        file_id: file_id.to_string(),
+        chunk_index,
+        data,
+        hash,
+    })
+}
@@ -0,0 +1,130 @@
+//! Download requests with hints about the file and where it comes from
+//!
+//! `P2pClient::download_file` only takes a nickname and a share code. A
+//! `DownloadRequest` carries everything else a caller may know, e.g. from
+//! a share link: the sharing peer, the expected size and hash, where to put
+//! the file and how urgent it is.
+
+use crate::events::FileInfo;
+use libp2p::PeerId;
+use std::path::PathBuf;
+
+/// How a download competes with other downloads from the same peer
+///
+/// Downloads with a higher priority get free chunk request slots first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DownloadPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// A download and the hints known about it, for `P2pClient::download_file_with`
+///
+/// Only the share code is required. Without a peer ID or nickname, the
+/// peer a share message with the code came from is asked, like
+/// `download_file_by_code`.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use gigi_p2p::{DownloadPriority, DownloadRequest};
+///
+/// let request = DownloadRequest::new("a1b2c3d4")
+///     .with_nickname("alice")
+///     .with_expected_size(1024)
+///     .with_target_directory("/home/user/Inbox")
+///     .with_priority(DownloadPriority::High);
+/// let download_id = client.download_file_with(request)?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadRequest {
+    /// Share code of the file
+    pub share_code: String,
+    /// Nickname of the sharer, looked up unless `peer_id` is set
+    pub nickname: Option<String>,
+    /// The sharer; takes precedence over `nickname`
+    pub peer_id: Option<PeerId>,
+    /// Whole-file hash the file must have, in hex; verifies files shared
+    /// without a hash, such as content URIs
+    pub expected_hash: Option<String>,
+    /// Size in bytes the file must have
+    pub expected_size: Option<u64>,
+    /// Directory to save the file in instead of the output directory
+    pub target_directory: Option<PathBuf>,
+    /// How the download competes with others from the same peer
+    pub priority: DownloadPriority,
+}
+
+impl DownloadRequest {
+    /// Request the file shared under `share_code`, without further hints
+    pub fn new(share_code: impl Into<String>) -> Self {
+        Self {
+            share_code: share_code.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Download from the peer with this nickname
+    pub fn with_nickname(mut self, nickname: impl Into<String>) -> Self {
+        self.nickname = Some(nickname.into());
+        self
+    }
+
+    /// Download from this peer, even if its nickname is not known yet
+    pub fn with_peer_id(mut self, peer_id: PeerId) -> Self {
+        self.peer_id = Some(peer_id);
+        self
+    }
+
+    /// Fail the download unless the file has this whole-file hash
+    pub fn with_expected_hash(mut self, hash: impl Into<String>) -> Self {
+        self.expected_hash = Some(hash.into());
+        self
+    }
+
+    /// Fail the download unless the file has this size in bytes
+    pub fn with_expected_size(mut self, size: u64) -> Self {
+        self.expected_size = Some(size);
+        self
+    }
+
+    /// Save the file in `directory`, created if missing
+    pub fn with_target_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.target_directory = Some(directory.into());
+        self
+    }
+
+    /// Set how the download competes with others from the same peer
+    pub fn with_priority(mut self, priority: DownloadPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Check the sharer's file info against the expected size and hash
+    ///
+    /// A file shared without a hash takes the expected hash, so the
+    /// download is verified against it.
+    ///
+    /// # Returns
+    /// Why the file is not the one requested, if it is not
+    pub(crate) fn apply_to(&self, info: &mut FileInfo) -> Result<(), String> {
+        if let Some(expected_size) = self.expected_size {
+            if info.size != expected_size {
+                return Err(format!(
+                    "Expected {} bytes, but the shared file has {}",
+                    expected_size, info.size
+                ));
+            }
+        }
+        if let Some(expected_hash) = &self.expected_hash {
+            if info.hash.is_empty() {
+                info.hash = expected_hash.clone();
+            } else if !info.hash.eq_ignore_ascii_case(expected_hash) {
+                return Err("The shared file does not have the expected hash".to_string());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+//! Event channel between the client and the application
+//!
+//! By default the channel is unbounded, like a plain `mpsc::unbounded`. With a
+//! capacity, a slow consumer cannot make progress events pile up:
+//!
+//! - When the queue is full, a new `FileDownloadProgress` or
+//!   `FileUploadProgress` replaces the queued progress events of the same
+//!   transfer, so only the latest one is kept
+//! - If no progress event of that transfer is queued, the oldest queued
+//!   progress event is dropped, or the new one if there is none
+//! - Any other event evicts the oldest queued progress event when full and is
+//!   always delivered; completion, failure and message events are never dropped
+//!
+//! The queue can thus only exceed its capacity by events other than progress.
+
+use futures::stream::{FusedStream, Stream};
+use futures::task::AtomicWaker;
+use libp2p::PeerId;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use thiserror::Error;
+
+use crate::events::P2pEvent;
+
+/// Errors from sending or receiving on the event channel
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventChannelError {
+    /// The receiver was dropped; the event was discarded
+    #[error("Event receiver was dropped")]
+    ReceiverDropped,
+
+    /// No event is queued right now
+    #[error("No event available")]
+    Empty,
+
+    /// No event is queued and every sender was dropped
+    #[error("Event channel closed")]
+    Closed,
+}
+
+struct Shared {
+    queue: Mutex<VecDeque<P2pEvent>>,
+    capacity: Option<usize>,
+    waker: AtomicWaker,
+    senders: AtomicUsize,
+    receiver_alive: AtomicBool,
+    dropped: AtomicU64,
+}
+
+/// Create an event channel
+///
+/// # Arguments
+/// * `capacity` - Queue size above which progress events are coalesced,
+///   `None` for an unbounded channel
+pub fn event_channel(capacity: Option<usize>) -> (EventSender, EventReceiver) {
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::new()),
+        capacity: capacity.map(|capacity| capacity.max(1)),
+        waker: AtomicWaker::new(),
+        senders: AtomicUsize::new(1),
+        receiver_alive: AtomicBool::new(true),
+        dropped: AtomicU64::new(0),
+    });
+    (
+        EventSender {
+            shared: Arc::clone(&shared),
+        },
+        EventReceiver { shared },
+    )
+}
+
+/// Transfer a progress event belongs to
+#[derive(PartialEq, Eq)]
+enum ProgressKey<'a> {
+    Download(&'a str),
+    Upload(&'a str, &'a PeerId),
+}
+
+/// Transfer of a progress event, the only kind that may be dropped
+fn progress_key(event: &P2pEvent) -> Option<ProgressKey<'_>> {
+    match event {
+        P2pEvent::FileDownloadProgress { download_id, .. } => {
+            Some(ProgressKey::Download(download_id))
+        }
+        P2pEvent::FileUploadProgress {
+            share_code,
+            to_peer_id,
+            ..
+        } => Some(ProgressKey::Upload(share_code, to_peer_id)),
+        _ => None,
+    }
+}
+
+/// Sending half of the event channel
+pub struct EventSender {
+    shared: Arc<Shared>,
+}
+
+impl EventSender {
+    /// Queue an event, applying the capacity policy described in the module docs
+    ///
+    /// Never blocks; named after `UnboundedSender::unbounded_send`.
+    pub fn unbounded_send(&self, event: P2pEvent) -> Result<(), EventChannelError> {
+        if !self.shared.receiver_alive.load(Ordering::Acquire) {
+            return Err(EventChannelError::ReceiverDropped);
+        }
+
+        {
+            let mut queue = self.shared.queue.lock().unwrap_or_else(|e| e.into_inner());
+            match self.shared.capacity {
+                Some(capacity) if queue.len() >= capacity => {
+                    let dropped = self.push_when_full(&mut queue, event);
+                    self.shared.dropped.fetch_add(dropped, Ordering::Relaxed);
+                }
+                _ => queue.push_back(event),
+            }
+        }
+
+        self.shared.waker.wake();
+        Ok(())
+    }
+
+    /// Push `event` onto a full queue
+    ///
+    /// # Returns
+    /// The number of events (queued or new) dropped
+    fn push_when_full(&self, queue: &mut VecDeque<P2pEvent>, event: P2pEvent) -> u64 {
+        if let Some(key) = progress_key(&event) {
+            let before = queue.len();
+            queue.retain(|queued| progress_key(queued).as_ref() != Some(&key));
+            let replaced = before - queue.len();
+            if replaced > 0 {
+                queue.push_back(event);
+                return replaced as u64;
+            }
+        }
+
+        let oldest_progress = queue
+            .iter()
+            .position(|queued| progress_key(queued).is_some());
+        match oldest_progress {
+            Some(index) => {
+                queue.remove(index);
+                queue.push_back(event);
+                1
+            }
+            None if progress_key(&event).is_some() => 1,
+            None => {
+                queue.push_back(event);
+                0
+            }
+        }
+    }
+
+    /// Number of progress events dropped or coalesced because the queue was full
+    pub fn dropped_events(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl std::fmt::Debug for EventSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventSender")
+            .field("capacity", &self.shared.capacity)
+            .field("dropped", &self.dropped_events())
+            .finish()
+    }
+}
+
+impl Clone for EventSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.waker.wake();
+        }
+    }
+}
+
+/// Receiving half of the event channel, a `Stream` of `P2pEvent`s
+///
+/// The stream ends once every sender (i.e. the client) is dropped and all
+/// queued events were received.
+pub struct EventReceiver {
+    shared: Arc<Shared>,
+}
+
+impl EventReceiver {
+    /// Take the next queued event without waiting
+    pub fn try_recv(&mut self) -> Result<P2pEvent, EventChannelError> {
+        let mut queue = self.shared.queue.lock().unwrap_or_else(|e| e.into_inner());
+        match queue.pop_front() {
+            Some(event) => Ok(event),
+            None if self.shared.senders.load(Ordering::Acquire) == 0 => {
+                Err(EventChannelError::Closed)
+            }
+            None => Err(EventChannelError::Empty),
+        }
+    }
+
+    /// Number of queued events
+    pub fn len(&self) -> usize {
+        self.shared
+            .queue
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// Whether no event is queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl std::fmt::Debug for EventReceiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventReceiver")
+            .field("capacity", &self.shared.capacity)
+            .field("queued", &self.len())
+            .finish()
+    }
+}
+
+impl Stream for EventReceiver {
+    type Item = P2pEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<P2pEvent>> {
+        let this = self.get_mut();
+        match this.try_recv() {
+            Ok(event) => return Poll::Ready(Some(event)),
+            Err(EventChannelError::Closed) => return Poll::Ready(None),
+            Err(_) => {}
+        }
+
+        // Register before re-checking so a concurrent send is not missed
+        this.shared.waker.register(cx.waker());
+        match this.try_recv() {
+            Ok(event) => Poll::Ready(Some(event)),
+            Err(EventChannelError::Closed) => Poll::Ready(None),
+            Err(_) => Poll::Pending,
+        }
+    }
+}
+
+impl FusedStream for EventReceiver {
+    fn is_terminated(&self) -> bool {
+        self.shared.senders.load(Ordering::Acquire) == 0 && self.is_empty()
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.shared.receiver_alive.store(false, Ordering::Release);
+    }
+}
@@ -5,9 +5,11 @@
 //!
 //! - **SwarmEventHandler**: Top-level swarm events (connection, listening, etc.)
 //! - **GigiDnsEventHandler**: Peer discovery events from gigi-dns
+//! - **DiscoveryEventHandler**: Peer discovery events of any discovery backend
 //! - **DirectMessageEventHandler**: Direct messaging events
 //! - **GossipsubEventHandler**: Group messaging events
 //! - **FileSharingEventHandler**: File transfer events
+//! - **ProfileEventHandler**: Contact profile exchange events
 //!
 //! # Event Flow
 //!
@@ -24,12 +26,31 @@
 //! ```
 
 use anyhow::Result;
-use gigi_logging::info;
-use libp2p::{swarm::SwarmEvent, PeerId};
+use gigi_logging::{info, warn};
+use libp2p::{
+    connection_limits,
+    swarm::{ListenError, SwarmEvent},
+    PeerId,
+};
+use std::collections::HashMap;
 
+use super::avatar_cache::AvatarCache;
+use super::discovery::DiscoveryEvent;
+use super::download_manager::{read_chunk_at, FailoverProbe};
+use super::group_distribution::MemberDownloadStatus;
+use super::group_manager::ReceivedGroupMessage;
+use super::p2p_client::{location_stored_message, ShareRefresh};
+use super::presence::PresenceManager;
+use super::profile;
 use super::P2pClient;
 use crate::behaviour::UnifiedEvent;
-use crate::events::{P2pEvent, PeerInfo};
+use crate::events::ActiveDownload;
+use crate::events::{
+    DownloadDetail, DownloadFailureReason, Location, P2pErrorKind, P2pEvent, PeerInfo,
+};
+use gigi_store::{
+    DownloadHistoryEntry, DownloadHistoryStatus, DownloadedFileInfo, MessageDirection, MessageType,
+};
 
 /// Handles all swarm-level events from the libp2p network stack.
 ///
@@ -58,8 +79,11 @@ impl<'a> SwarmEventHandler<'a> {
     /// Routes events to appropriate handlers:
     /// - **Behaviour events**: Delegated to protocol-specific handlers
     /// - **NewListenAddr**: Emit ListeningOn event with address
-    /// - **ConnectionEstablished**: Update peer manager, trigger sync if needed
+    /// - **ExpiredListenAddr / ListenerClosed**: Emit ListenStopped per address
+    /// - **ConnectionEstablished**: Update peer manager, trigger sync if needed,
+    ///   announce shared files if configured
     /// - **ConnectionClosed**: Update peer manager, notify sync manager
+    /// - **IncomingConnectionError**: Emit InboundConnectionRejected when over the limit
     pub fn handle_event(&mut self, event: SwarmEvent<UnifiedEvent>) -> Result<()> {
         match event {
             // Protocol events from unified behaviour - delegate to handlers
@@ -70,8 +94,25 @@ impl<'a> SwarmEventHandler<'a> {
             SwarmEvent::NewListenAddr { address, .. } => {
                 self.client.send_event(P2pEvent::ListeningOn { address });
             }
+            // Listen address lost - emit stopped event
+            SwarmEvent::ExpiredListenAddr { address, .. } => {
+                warn!("No longer listening on {}", address);
+                self.client.send_event(P2pEvent::ListenStopped { address });
+            }
+            SwarmEvent::ListenerClosed {
+                addresses, reason, ..
+            } => {
+                warn!("Listener closed: {:?}", reason);
+                for address in addresses {
+                    self.client.send_event(P2pEvent::ListenStopped { address });
+                }
+            }
             // New connection - update peer state and trigger sync if persistence enabled
-            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+            SwarmEvent::ConnectionEstablished {
+                peer_id,
+                num_established,
+                ..
+            } => {
                 info!("Connection established with peer: {}", peer_id);
 
                 // Mark as reconnected if this peer was being tracked for recovery
@@ -81,6 +122,19 @@ impl<'a> SwarmEventHandler<'a> {
                     .peer_manager
                     .handle_connection_established(peer_id, &mut self.client.event_sender);
 
+                // Tell a newly connected peer what is shared, once per peer
+                if num_established.get() == 1
+                    && self.client.p2p_config.announce_shared_files_on_connect
+                    && self
+                        .client
+                        .file_manager
+                        .shared_files
+                        .values()
+                        .any(|f| !f.revoked)
+                {
+                    self.client.announce_shared_files_to(peer_id);
+                }
+
                 // Trigger sync if persistence is enabled (simplified - no async for now)
                 if let Some(ref _sync_manager) = self.client.sync_manager {
                     if let Ok(nickname) = self.client.peer_manager.get_peer_nickname(&peer_id) {
@@ -93,7 +147,12 @@ impl<'a> SwarmEventHandler<'a> {
                     }
                 }
             }
-            SwarmEvent::ConnectionClosed { peer_id, cause, .. } => {
+            SwarmEvent::ConnectionClosed {
+                peer_id,
+                cause,
+                num_established,
+                ..
+            } => {
                 // Get peer address before removal for reconnection
                 let peer_address = self
                     .client
@@ -104,6 +163,18 @@ impl<'a> SwarmEventHandler<'a> {
                 self.client
                     .peer_manager
                     .handle_connection_closed(peer_id, &mut self.client.event_sender);
+                if num_established == 0 {
+                    self.client
+                        .group_manager
+                        .remove_peer(peer_id, &mut self.client.event_sender);
+                    self.client.peer_protocols.remove(&peer_id);
+                    self.client.remote_files.remove(&peer_id);
+                    let keep_counts = self.client.p2p_config.persist_upload_progress
+                        && self.client.upload_progress_store.is_some();
+                    self.client
+                        .upload_tracker
+                        .forget_peer(&peer_id, keep_counts);
+                }
 
                 // Track for reconnection with exponential backoff
                 if let Some(address) = peer_address {
@@ -120,6 +191,19 @@ impl<'a> SwarmEventHandler<'a> {
 
                 gigi_logging::debug!("Connection to {} closed: {:?}", peer_id, cause);
             }
+            SwarmEvent::IncomingConnectionError {
+                send_back_addr,
+                error: ListenError::Denied { cause },
+                ..
+            } => {
+                if let Some(exceeded) = cause.downcast_ref::<connection_limits::Exceeded>() {
+                    warn!("Rejected connection from {}: {}", send_back_addr, exceeded);
+                    self.client.send_event(P2pEvent::InboundConnectionRejected {
+                        address: send_back_addr,
+                        limit: exceeded.limit(),
+                    });
+                }
+            }
             _ => {}
         }
         Ok(())
@@ -132,6 +216,8 @@ impl<'a> SwarmEventHandler<'a> {
     /// - **DirectMessage events**: Request-response messaging
     /// - **Gossipsub events**: Group messaging
     /// - **FileSharing events**: File transfer requests and responses
+    /// - **Profile events**: Contact profile exchange
+    /// - **Identify events**: Protocols supported by connected peers
     fn handle_unified_event(&mut self, event: UnifiedEvent) -> Result<()> {
         match event {
             UnifiedEvent::GigiDns(gigi_dns_event) => {
@@ -146,6 +232,12 @@ impl<'a> SwarmEventHandler<'a> {
             UnifiedEvent::FileSharing(file_event) => {
                 FileSharingEventHandler::new(self.client).handle_event(file_event)?
             }
+            UnifiedEvent::Profile(profile_event) => {
+                ProfileEventHandler::new(self.client).handle_event(profile_event)
+            }
+            UnifiedEvent::Identify(identify_event) => {
+                IdentifyEventHandler::new(self.client).handle_event(*identify_event)
+            }
             UnifiedEvent::Kademlia(_) | UnifiedEvent::Relay(_) => {
                 // Kademlia and Relay events are handled internally by the swarm
                 // We can add specific logging here if needed
@@ -157,7 +249,8 @@ impl<'a> SwarmEventHandler<'a> {
 
 /// Handles gigi-dns discovery events
 ///
-/// Processes peer discovery events from the gigi-dns protocol:
+/// Converts gigi-dns events to `DiscoveryEvent`s and hands them to
+/// `DiscoveryEventHandler`, like events of any other discovery backend:
 /// - **Discovered**: New peer found via mDNS
 /// - **Updated**: Peer metadata updated (nickname, capabilities)
 /// - **Expired**: Peer no longer available (timeout)
@@ -172,51 +265,64 @@ impl<'a> GigiDnsEventHandler<'a> {
     }
 
     /// Handle a gigi-dns discovery event
-    ///
-    /// Emits P2pEvent for peer lifecycle:
-    /// - Discovered → PeerDiscovered
-    /// - Updated → NicknameUpdated
-    /// - Expired/Offline → PeerExpired
     pub fn handle_event(&mut self, event: gigi_dns::GigiDnsEvent) -> Result<()> {
+        if let gigi_dns::GigiDnsEvent::Offline {
+            peer_id,
+            info,
+            reason,
+        } = &event
+        {
+            info!(
+                "gigi-dns peer offline: {} ({}) - reason: {:?}",
+                info.nickname, peer_id, reason
+            );
+        }
+        DiscoveryEventHandler::new(self.client).handle_event(event.into())
+    }
+}
+
+/// Handles events of the active discovery backend
+///
+/// Keeps the peer table in sync with discovery:
+/// - Discovered → PeerDiscovered, and the peer is dialed
+/// - Updated → NicknameUpdated
+/// - Expired → PeerExpired
+pub struct DiscoveryEventHandler<'a> {
+    client: &'a mut P2pClient,
+}
+
+impl<'a> DiscoveryEventHandler<'a> {
+    pub fn new(client: &'a mut P2pClient) -> Self {
+        Self { client }
+    }
+
+    /// Handle a discovery event
+    pub fn handle_event(&mut self, event: DiscoveryEvent) -> Result<()> {
         match event {
-            gigi_dns::GigiDnsEvent::Discovered(peer_info) => {
-                info!(
-                    "gigi-dns discovered peer: {} ({})",
-                    peer_info.nickname, peer_info.peer_id
-                );
+            DiscoveryEvent::Discovered {
+                peer_id,
+                nickname,
+                address,
+            } => {
+                info!("Discovered peer: {} ({})", nickname, peer_id);
                 self.client.peer_manager.handle_peer_discovered(
-                    peer_info.peer_id,
-                    peer_info.multiaddr.clone(),
+                    peer_id,
+                    address,
                     &mut self.client.swarm,
-                    &peer_info.nickname,
+                    &nickname,
                     &mut self.client.event_sender,
                 )?;
             }
-            gigi_dns::GigiDnsEvent::Updated {
-                peer_id, new_info, ..
-            } => {
-                info!("gigi-dns updated peer: {} ({})", new_info.nickname, peer_id);
+            DiscoveryEvent::Updated { peer_id, nickname } => {
+                info!("Updated peer: {} ({})", nickname, peer_id);
                 self.client.peer_manager.update_peer_nickname(
                     peer_id,
-                    new_info.nickname.clone(),
+                    nickname,
                     &mut self.client.event_sender,
                 );
             }
-            gigi_dns::GigiDnsEvent::Expired { peer_id, info } => {
-                info!("gigi-dns expired peer: {} ({})", info.nickname, peer_id);
-                self.client
-                    .peer_manager
-                    .handle_peer_expired(peer_id, &mut self.client.event_sender)?;
-            }
-            gigi_dns::GigiDnsEvent::Offline {
-                peer_id,
-                info,
-                reason,
-            } => {
-                info!(
-                    "gigi-dns peer offline: {} ({}) - reason: {:?}",
-                    info.nickname, peer_id, reason
-                );
+            DiscoveryEvent::Expired { peer_id } => {
+                info!("Expired peer: {}", peer_id);
                 self.client
                     .peer_manager
                     .handle_peer_expired(peer_id, &mut self.client.event_sender)?;
@@ -248,7 +354,11 @@ impl<'a> DirectMessageEventHandler<'a> {
     /// - Text → P2pEvent::DirectMessage
     /// - FileShare → P2pEvent::DirectFileShareMessage
     /// - ShareGroup → P2pEvent::DirectGroupShareMessage
-    /// - Outbound request failures → P2pEvent::Error
+    /// - Location → P2pEvent::DirectLocationMessage, or an error response if
+    ///   out of range
+    /// - Requests over the peer's inbound rate limit → error response
+    /// - Invalid locations → P2pErrorKind::Protocol error event
+    /// - Outbound request failures → P2pErrorKind::Network error event
     pub fn handle_event(
         &mut self,
         event: libp2p::request_response::Event<
@@ -267,9 +377,23 @@ impl<'a> DirectMessageEventHandler<'a> {
             ..
         } = event
         {
-            let nickname = self.client.peer_manager.get_peer_nickname(&peer)?;
+            if !self.client.admit_inbound_message(peer) {
+                let _ = self
+                    .client
+                    .swarm
+                    .behaviour_mut()
+                    .direct_msg
+                    .send_response(channel, DirectResponse::Error("Rate limited".to_string()));
+                return Ok(());
+            }
+            let nickname = self.client.peer_manager.display_name(&peer);
             match request {
-                DirectMessage::Text { message } => {
+                DirectMessage::Text {
+                    message,
+                    message_id,
+                    disappear_after_secs,
+                    forwarded_from,
+                } => {
                     // Note: Message storage is handled by the plugin event handler (handle_direct_message in events.rs)
                     // to avoid duplicates and ensure consistent UUID across storage and event
 
@@ -277,6 +401,9 @@ impl<'a> DirectMessageEventHandler<'a> {
                         from: peer,
                         from_nickname: nickname,
                         message,
+                        message_id,
+                        disappear_after_secs,
+                        forwarded_from,
                     });
                 }
                 DirectMessage::FileShare {
@@ -284,15 +411,32 @@ impl<'a> DirectMessageEventHandler<'a> {
                     filename,
                     file_size,
                     file_type,
+                    forwarded_from,
                 } => {
+                    self.client.record_share_source(share_code.clone(), peer);
+                    let auto_accept = self.client.should_auto_accept(&peer, file_size);
                     self.client.send_event(P2pEvent::DirectFileShareMessage {
                         from: peer,
                         from_nickname: nickname,
-                        share_code,
+                        share_code: share_code.clone(),
                         filename,
                         file_size,
                         file_type,
+                        forwarded_from,
                     });
+                    if auto_accept {
+                        match self.client.download_file_by_code(&share_code) {
+                            Ok(download_id) => {
+                                info!(
+                                    "Auto-accepted {} from {} as {}",
+                                    share_code, peer, download_id
+                                )
+                            }
+                            Err(e) => {
+                                warn!("Not auto-accepting {} from {}: {}", share_code, peer, e)
+                            }
+                        }
+                    }
                 }
                 DirectMessage::ShareGroup {
                     group_id,
@@ -306,6 +450,47 @@ impl<'a> DirectMessageEventHandler<'a> {
                         group_name,
                     });
                 }
+                DirectMessage::Location {
+                    message_id,
+                    lat,
+                    lon,
+                    label,
+                } => {
+                    if let Err(e) = crate::validation::validate_location(lat, lon, label.as_deref())
+                    {
+                        warn!("Rejecting invalid location from {}: {}", peer, e);
+                        self.client
+                            .send_event(P2pEvent::Error(P2pErrorKind::Protocol(format!(
+                                "Rejected location from {}: {}",
+                                peer, e
+                            ))));
+                        let _ = self
+                            .client
+                            .swarm
+                            .behaviour_mut()
+                            .direct_msg
+                            .send_response(channel, DirectResponse::Error(e.to_string()));
+                        return Ok(());
+                    }
+                    let location = Location { lat, lon, label };
+                    let mut stored_msg = location_stored_message(
+                        message_id.clone(),
+                        peer,
+                        MessageDirection::Received,
+                        nickname.clone(),
+                        location.clone(),
+                    );
+                    stored_msg.recipient_nickname = Some(self.client.local_nickname.clone());
+                    self.client.record_location_message(
+                        stored_msg,
+                        Some(P2pEvent::DirectLocationMessage {
+                            from: peer,
+                            from_nickname: nickname,
+                            message_id,
+                            location,
+                        }),
+                    );
+                }
             }
             let _ = self
                 .client
@@ -313,11 +498,145 @@ impl<'a> DirectMessageEventHandler<'a> {
                 .behaviour_mut()
                 .direct_msg
                 .send_response(channel, DirectResponse::Ack);
+        } else if let libp2p::request_response::Event::OutboundFailure { peer, error, .. } = event {
+            warn!("Direct message to {} failed: {}", peer, error);
+            self.client
+                .send_event(P2pEvent::Error(P2pErrorKind::Network(format!(
+                    "Direct message to {} failed: {}",
+                    peer, error
+                ))));
         }
         Ok(())
     }
 }
 
+/// Handles contact profile exchange events
+///
+/// Answers every profile request with the local profile, and stores profiles
+/// received in requests and responses if they come from a contact. Avatars
+/// named in responses, which come from peers added as contacts, are fetched
+/// if not cached. Peers that do not support the exchange are only logged.
+pub struct ProfileEventHandler<'a> {
+    client: &'a mut P2pClient,
+}
+
+impl<'a> ProfileEventHandler<'a> {
+    pub fn new(client: &'a mut P2pClient) -> Self {
+        Self { client }
+    }
+
+    /// Handle profile exchange request-response events
+    pub fn handle_event(
+        &mut self,
+        event: libp2p::request_response::Event<
+            crate::behaviour::ProfileExchange,
+            crate::behaviour::ProfileExchange,
+        >,
+    ) {
+        use libp2p::request_response::{Event, Message, OutboundFailure};
+
+        match event {
+            Event::Message { peer, message, .. } => {
+                let (exchange, from_contact) = match message {
+                    Message::Request {
+                        request, channel, ..
+                    } => {
+                        let response = self
+                            .client
+                            .local_profile
+                            .exchange(&self.client.local_nickname);
+                        let _ = self
+                            .client
+                            .swarm
+                            .behaviour_mut()
+                            .profile
+                            .send_response(channel, response);
+                        (request, false)
+                    }
+                    Message::Response { response, .. } => (response, true),
+                };
+                match profile::accept(&peer, exchange) {
+                    Ok(profile) => {
+                        if let (true, Some(avatar_hash)) = (from_contact, &profile.avatar_hash) {
+                            if let Err(e) = self.client.fetch_avatar(peer, avatar_hash) {
+                                warn!("Failed to fetch avatar of {}: {}", peer, e);
+                            }
+                        }
+                        self.client.store_contact_profile(peer, profile);
+                    }
+                    Err(e) => {
+                        warn!("Rejected profile from {}: {}", peer, e);
+                        self.client
+                            .send_event(P2pEvent::Error(P2pErrorKind::Protocol(format!(
+                                "Rejected profile from {}: {}",
+                                peer, e
+                            ))));
+                    }
+                }
+            }
+            Event::OutboundFailure {
+                peer,
+                error: OutboundFailure::UnsupportedProtocols,
+                ..
+            } => {
+                info!("Peer {} does not support profile exchange", peer);
+            }
+            Event::OutboundFailure { peer, error, .. } => {
+                warn!("Profile exchange with {} failed: {}", peer, error);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Handles identify events
+///
+/// Records the protocols each connected peer reports and emits
+/// `PeerProtocolsDiscovered` when they are new or changed. Other identify
+/// events are only logged.
+pub struct IdentifyEventHandler<'a> {
+    client: &'a mut P2pClient,
+}
+
+impl<'a> IdentifyEventHandler<'a> {
+    pub fn new(client: &'a mut P2pClient) -> Self {
+        Self { client }
+    }
+
+    pub fn handle_event(&mut self, event: libp2p::identify::Event) {
+        match event {
+            libp2p::identify::Event::Received { peer_id, info, .. } => {
+                let mut protocols: Vec<String> =
+                    info.protocols.iter().map(ToString::to_string).collect();
+                protocols.sort_unstable();
+                protocols.dedup();
+                gigi_logging::debug!(
+                    "Peer {} ({}) supports {:?}",
+                    peer_id,
+                    info.agent_version,
+                    protocols
+                );
+                if self.client.peer_protocols.get(&peer_id) == Some(&protocols) {
+                    return;
+                }
+                self.client
+                    .peer_protocols
+                    .insert(peer_id, protocols.clone());
+                let nickname = self.client.peer_manager.display_name(&peer_id);
+                self.client.send_event(P2pEvent::PeerProtocolsDiscovered {
+                    peer_id,
+                    nickname,
+                    protocols,
+                });
+            }
+            libp2p::identify::Event::Error { peer_id, error, .. } => {
+                gigi_logging::debug!("Identify with {} failed: {}", peer_id, error);
+            }
+            _ => {}
+        }
+    }
+}
+
 /// Handles GossipSub pub-sub events for group messaging
 ///
 /// Processes group messaging events:
@@ -336,10 +655,28 @@ impl<'a> GossipsubEventHandler<'a> {
     /// Handle GossipSub events for group messaging
     ///
     /// Delegates group events to the GroupManager:
-    /// - Subscribed → Notified to update peer info
-    /// - Messages → P2pEvent::GroupMessage or GroupFileShareMessage
+    /// - Subscribed → Notified to update peer info, buffered messages flushed
+    /// - Messages → P2pEvent::GroupMessage, GroupFileShareMessage or PollUpdated
     /// - Publish failures → P2pEvent::Error
+    ///
+    /// Presence topic events are handled here and never reach the GroupManager.
+    /// Messages over the author's inbound rate limit are dropped.
     pub fn handle_event(&mut self, event: libp2p::gossipsub::Event) -> Result<()> {
+        if self.handle_presence_event(&event) {
+            return Ok(());
+        }
+        if let libp2p::gossipsub::Event::Message {
+            propagation_source,
+            message,
+            ..
+        } = &event
+        {
+            let author = message.source.unwrap_or(*propagation_source);
+            if !self.client.admit_inbound_message(author) {
+                return Ok(());
+            }
+        }
+
         // Convert PeerInfo references to owned PeerInfo values for GroupManager
         let peers: std::collections::HashMap<PeerId, PeerInfo> = self
             .client
@@ -348,11 +685,113 @@ impl<'a> GossipsubEventHandler<'a> {
             .into_iter()
             .map(|peer| (peer.peer_id, peer.clone()))
             .collect();
-        self.client.group_manager.handle_gossipsub_event(
+        // A newly subscribed peer can receive messages buffered while alone
+        let subscribed_topic = match &event {
+            libp2p::gossipsub::Event::Subscribed { topic, .. } => Some(topic.clone()),
+            _ => None,
+        };
+        match self.client.group_manager.handle_gossipsub_event(
             event,
             &peers,
             &mut self.client.event_sender,
-        )
+        )? {
+            Some(ReceivedGroupMessage::FileShare { share_code, source }) => {
+                self.client.record_share_source(share_code, source);
+            }
+            Some(ReceivedGroupMessage::Poll {
+                group,
+                from,
+                from_nickname,
+                message_id,
+                timestamp,
+                poll,
+            }) => {
+                let timestamp = chrono::DateTime::from_timestamp(timestamp as i64, 0)
+                    .unwrap_or_else(chrono::Utc::now);
+                self.client.record_poll_message(
+                    group,
+                    from,
+                    from_nickname,
+                    message_id,
+                    timestamp,
+                    poll,
+                );
+            }
+            Some(ReceivedGroupMessage::Location {
+                group,
+                from,
+                from_nickname,
+                message_id,
+                timestamp,
+                location,
+            }) => {
+                let mut stored_msg = location_stored_message(
+                    message_id.clone(),
+                    from,
+                    MessageDirection::Received,
+                    from_nickname.clone(),
+                    location.clone(),
+                );
+                stored_msg.msg_type = MessageType::Group;
+                stored_msg.group_name = Some(group.clone());
+                if let Some(timestamp) = chrono::DateTime::from_timestamp(timestamp as i64, 0) {
+                    stored_msg.timestamp = timestamp;
+                }
+                self.client.record_location_message(
+                    stored_msg,
+                    Some(P2pEvent::GroupLocationMessage {
+                        from,
+                        from_nickname,
+                        group,
+                        message_id,
+                        location,
+                    }),
+                );
+            }
+            None => {}
+        }
+        if let Some(topic) = subscribed_topic {
+            self.client
+                .group_manager
+                .flush_pending(&mut self.client.swarm, &topic);
+        }
+        Ok(())
+    }
+
+    /// Handle an event on the presence topic
+    ///
+    /// # Returns
+    /// `true` if the event belonged to the presence topic
+    fn handle_presence_event(&mut self, event: &libp2p::gossipsub::Event) -> bool {
+        let presence_topic = PresenceManager::topic().hash();
+        match event {
+            libp2p::gossipsub::Event::Message {
+                propagation_source,
+                message,
+                ..
+            } if message.topic == presence_topic => {
+                let from = message.source.unwrap_or(*propagation_source);
+                match PresenceManager::decode(&message.data) {
+                    Some(status) => self.client.peer_manager.update_peer_status(
+                        from,
+                        status,
+                        &mut self.client.event_sender,
+                    ),
+                    None => warn!("Ignoring invalid presence message from {}", from),
+                }
+                self.client
+                    .group_manager
+                    .reconcile_rosters(&self.client.swarm, &mut self.client.event_sender);
+                true
+            }
+            // Tell a newly subscribed peer our status right away
+            libp2p::gossipsub::Event::Subscribed { topic, .. } if *topic == presence_topic => {
+                self.client.presence.broadcast(&mut self.client.swarm);
+                true
+            }
+            libp2p::gossipsub::Event::Unsubscribed { topic, .. } => *topic == presence_topic,
+            _ => false,
+        }
     }
 }
 
@@ -375,11 +814,365 @@ impl<'a> FileSharingEventHandler<'a> {
         Self { client }
     }
 
+    /// Read a chunk to serve, from the cache of served chunks when possible
+    ///
+    /// With read-ahead enabled, schedules reading the following chunks into
+    /// the cache in the background afterwards.
+    fn read_served_chunk(
+        &self,
+        peer: PeerId,
+        shared_file: &crate::events::SharedFile,
+        chunk_index: usize,
+        file_id: &str,
+    ) -> Result<crate::events::ChunkInfo> {
+        let cache = &self.client.chunk_cache;
+        let file_hash = &shared_file.info.hash;
+        let prefetcher = self.client.chunk_prefetcher.as_ref();
+        let cached = match prefetcher {
+            Some(prefetcher) => prefetcher.get(peer, file_id, chunk_index, file_hash),
+            None => cache.get(file_id, chunk_index, file_hash),
+        };
+        let chunk = match cached {
+            Some(chunk) => chunk,
+            None => {
+                let chunk =
+                    self.client
+                        .download_manager
+                        .read_chunk(shared_file, chunk_index, file_id)?;
+                cache.insert(chunk.clone(), file_hash);
+                chunk
+            }
+        };
+        let Some(prefetcher) = prefetcher else {
+            return Ok(chunk);
+        };
+
+        let path = shared_file.path.clone();
+        let id = file_id.to_string();
+        let chunk_reader = self.client.download_manager.chunk_reader();
+        // Spans of the chunks the read-ahead may load, so the background
+        // reads need not copy the whole chunk layout
+        let spans: HashMap<usize, (u64, usize)> = (chunk_index + 1
+            ..=chunk_index + self.client.p2p_config.chunk_read_ahead)
+            .filter_map(|index| Some((index, shared_file.info.chunk_span(index)?)))
+            .collect();
+        prefetcher.prefetch_after(
+            peer,
+            file_id,
+            chunk_index,
+            shared_file.info.chunk_count,
+            file_hash,
+            move |index| {
+                let span = spans
+                    .get(&index)
+                    .copied()
+                    .ok_or_else(|| anyhow::anyhow!("Chunk {} outside read-ahead", index))?;
+                read_chunk_at(&path, index, span, &id, chunk_reader.as_ref())
+            },
+        );
+        Ok(chunk)
+    }
+
+    /// Read the local avatar for an avatar request
+    ///
+    /// Only the current avatar is served, so peers cannot probe the cache
+    /// for avatars of other contacts.
+    fn read_served_avatar(&self, avatar_hash: &str) -> Option<Vec<u8>> {
+        if self.client.local_profile.avatar_hash() != Some(avatar_hash) {
+            return None;
+        }
+        let path = self.client.avatar_cache.path(avatar_hash);
+        match AvatarCache::read_image(&path) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!("Failed to read avatar {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Cache an avatar received for an avatar request
+    fn handle_avatar_response(&mut self, peer: PeerId, data: Option<Vec<u8>>, request_id: &str) {
+        let Some(avatar_hash) = self.client.avatar_cache.untrack(request_id) else {
+            return;
+        };
+        let Some(data) = data else {
+            warn!("Peer {} no longer has avatar {}", peer, avatar_hash);
+            return;
+        };
+        match self.client.avatar_cache.insert(&avatar_hash, &data) {
+            Ok(path) => self.client.send_event(P2pEvent::AvatarFetched {
+                peer_id: peer,
+                avatar_hash,
+                path,
+            }),
+            Err(e) => {
+                warn!("Rejected avatar {} from {}: {}", avatar_hash, peer, e);
+                self.client
+                    .send_event(P2pEvent::Error(P2pErrorKind::Protocol(format!(
+                        "Rejected avatar {} from {}: {}",
+                        avatar_hash, peer, e
+                    ))));
+            }
+        }
+    }
+
+    /// Count a chunk served to `peer` and emit upload progress if due
+    fn record_uploaded_chunk(&mut self, peer: PeerId, file_id: &str, chunk_index: usize) {
+        let Some(info) = self
+            .client
+            .file_manager
+            .shared_files
+            .get(file_id)
+            .map(|shared_file| &shared_file.info)
+        else {
+            return;
+        };
+        let (filename, total_chunks) = (info.name.clone(), info.chunk_count);
+        self.advance_group_distribution(file_id, peer, MemberDownloadStatus::InProgress);
+        let upload_id = super::progress::upload_id(file_id, &peer);
+        let Some(uploaded_chunks) =
+            self.client
+                .upload_tracker
+                .record_chunk(&upload_id, chunk_index, total_chunks)
+        else {
+            return;
+        };
+        self.client
+            .save_upload_progress(peer, file_id, uploaded_chunks, total_chunks);
+        let event = P2pEvent::FileUploadProgress {
+            share_code: file_id.to_string(),
+            filename,
+            to_peer_id: peer,
+            to_nickname: self.client.peer_manager.display_name(&peer),
+            uploaded_chunks,
+            total_chunks,
+        };
+        self.client.send_event(event);
+        if uploaded_chunks >= total_chunks {
+            self.advance_group_distribution(file_id, peer, MemberDownloadStatus::Completed);
+        }
+    }
+
+    /// Advance a member's download of a file sent to a group
+    ///
+    /// Emits `GroupFileDownloadedByMember` when the member completes it.
+    fn advance_group_distribution(
+        &mut self,
+        share_code: &str,
+        peer: PeerId,
+        status: MemberDownloadStatus,
+    ) {
+        let group_manager = &self.client.group_manager;
+        let Some(completed) =
+            self.client
+                .group_distributions
+                .advance(share_code, peer, status, |group, peer| {
+                    group_manager.is_member(group, peer)
+                })
+        else {
+            return;
+        };
+        info!(
+            "{} of {} members of {} downloaded {}",
+            completed.completed_count, completed.member_count, completed.group, completed.filename
+        );
+        let nickname = self.client.peer_manager.display_name(&peer);
+        self.client
+            .send_event(P2pEvent::GroupFileDownloadedByMember {
+                group: completed.group,
+                share_code: share_code.to_string(),
+                filename: completed.filename,
+                peer_id: peer,
+                nickname,
+                completed_count: completed.completed_count,
+                member_count: completed.member_count,
+            });
+    }
+
+    /// Answer a chunk request for a shared file
+    ///
+    /// A chunk that is past the end of the file or shorter than the shared
+    /// `FileInfo` promises means the file changed on disk; the request is
+    /// answered with `FILE_CHANGED_ERROR` instead of bad data.
+    fn serve_chunk(
+        &mut self,
+        peer: PeerId,
+        file_id: &str,
+        chunk_index: usize,
+    ) -> crate::behaviour::FileSharingResponse {
+        use crate::behaviour::{FileSharingResponse, FILE_CHANGED_ERROR, FILE_REVOKED_ERROR};
+
+        // Refused until the file is re-read, so no stale chunk is served
+        if self.is_refreshing(file_id) {
+            return FileSharingResponse::Error(FILE_CHANGED_ERROR.to_string());
+        }
+
+        let read = match self.client.file_manager.shared_files.get(file_id) {
+            Some(shared_file) if shared_file.revoked => {
+                return FileSharingResponse::Error(FILE_REVOKED_ERROR.to_string());
+            }
+            Some(shared_file) if shared_file.info.chunk_span(chunk_index).is_none() => {
+                return FileSharingResponse::Chunk(None);
+            }
+            Some(shared_file) => self
+                .read_served_chunk(peer, shared_file, chunk_index, file_id)
+                .map(|chunk| (chunk, &shared_file.info)),
+            None if self.client.revoked_share_codes.contains(file_id) => {
+                return FileSharingResponse::Error(FILE_REVOKED_ERROR.to_string());
+            }
+            None => return FileSharingResponse::Chunk(None),
+        };
+
+        match read {
+            Ok((chunk, info)) => {
+                if crate::validation::validate_file_chunk_length(
+                    info,
+                    chunk_index,
+                    chunk.data.len(),
+                )
+                .is_ok()
+                {
+                    self.record_uploaded_chunk(peer, file_id, chunk_index);
+                    return FileSharingResponse::Chunk(Some(chunk));
+                }
+            }
+            Err(e) => {
+                if !matches!(
+                    e.downcast_ref::<crate::P2pError>(),
+                    Some(crate::P2pError::ChunkPastEof { .. })
+                ) {
+                    return FileSharingResponse::Error("Failed to read chunk".to_string());
+                }
+            }
+        }
+
+        warn!("Shared file {} changed on disk while serving it", file_id);
+        if let Some(prefetcher) = &self.client.chunk_prefetcher {
+            prefetcher.forget_file(file_id);
+        }
+        self.client.chunk_cache.forget_file(file_id);
+        if self.client.p2p_config.refresh_changed_shares {
+            self.start_share_refresh(file_id);
+        }
+        FileSharingResponse::Error(FILE_CHANGED_ERROR.to_string())
+    }
+
+    /// Whether a changed shared file is being re-read in the background
+    fn is_refreshing(&self, file_id: &str) -> bool {
+        self.client
+            .pending_refreshes
+            .values()
+            .any(|code| code == file_id)
+    }
+
+    /// Re-read a shared file that changed on disk on a blocking thread
+    ///
+    /// Hashing it and finding its content-defined chunks can take long, so
+    /// it must not run on the event loop; `finish_share_refresh` applies the
+    /// result.
+    fn start_share_refresh(&mut self, file_id: &str) {
+        let Some(shared_file) = self.client.file_manager.shared_files.get(file_id) else {
+            return;
+        };
+        let shared_file = shared_file.clone();
+        let share_code = file_id.to_string();
+        let handle = self.client.share_refreshes.spawn_blocking(move || {
+            let result = gigi_file_sharing::FileSharingManager::compute_refresh(&shared_file);
+            (share_code, result)
+        });
+        self.client
+            .pending_refreshes
+            .insert(handle.id(), file_id.to_string());
+    }
+
+    /// Apply a finished background refresh started by `start_share_refresh`
+    pub(super) fn finish_share_refresh(
+        &mut self,
+        result: std::result::Result<ShareRefresh, tokio::task::JoinError>,
+    ) {
+        let (share_code, refreshed) = match result {
+            Ok((share_code, refreshed)) => (share_code, refreshed),
+            Err(e) => {
+                if let Some(share_code) = self.client.pending_refreshes.remove(&e.id()) {
+                    warn!("Refreshing changed file {} failed: {}", share_code, e);
+                }
+                return;
+            }
+        };
+        self.client
+            .pending_refreshes
+            .retain(|_, code| *code != share_code);
+
+        let applied = refreshed.and_then(|updated| match updated {
+            Some(updated) => self.client.file_manager.apply_refresh(&share_code, updated),
+            None => Ok(false),
+        });
+        match applied {
+            Ok(true) => {
+                // Drop chunks read while the refresh was running
+                if let Some(prefetcher) = &self.client.chunk_prefetcher {
+                    prefetcher.forget_file(&share_code);
+                }
+                self.client.chunk_cache.forget_file(&share_code);
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Failed to refresh changed file {}: {}", share_code, e),
+        }
+    }
+
+    /// Read the bytes of a `GetRange` request, capped at `MAX_RANGE_SIZE`
+    fn serve_range(
+        &mut self,
+        file_id: &str,
+        start: u64,
+        len: u64,
+    ) -> crate::behaviour::FileSharingResponse {
+        use crate::behaviour::{
+            FileSharingResponse, FILE_CHANGED_ERROR, FILE_REVOKED_ERROR, MAX_RANGE_SIZE,
+        };
+
+        let shared_file = match self.client.file_manager.shared_files.get(file_id) {
+            Some(shared_file) if shared_file.revoked => {
+                return FileSharingResponse::Error(FILE_REVOKED_ERROR.to_string());
+            }
+            Some(shared_file) => shared_file,
+            None if self.client.revoked_share_codes.contains(file_id) => {
+                return FileSharingResponse::Error(FILE_REVOKED_ERROR.to_string());
+            }
+            None => return FileSharingResponse::Range(None),
+        };
+
+        let len = len.min(MAX_RANGE_SIZE);
+        let expected = len.min(shared_file.info.size.saturating_sub(start));
+        match self
+            .client
+            .download_manager
+            .read_range(shared_file, start, len)
+        {
+            Ok(data) if data.len() as u64 == expected => {
+                FileSharingResponse::Range(Some(crate::events::FileRange {
+                    file_id: file_id.to_string(),
+                    start,
+                    data,
+                }))
+            }
+            Ok(_) => {
+                warn!("Shared file {} changed on disk while serving it", file_id);
+                FileSharingResponse::Error(FILE_CHANGED_ERROR.to_string())
+            }
+            Err(e) => {
+                warn!("Failed to read range of {}: {}", file_id, e);
+                FileSharingResponse::Error("Failed to read range".to_string())
+            }
+        }
+    }
+
     pub fn handle_event(
         &mut self,
         event: libp2p::request_response::Event<
             crate::behaviour::FileSharingRequest,
-            crate::behaviour::FileSharingResponse,
+            crate::behaviour::VersionedResponse,
         >,
     ) -> Result<()> {
         use crate::behaviour::{FileSharingRequest, FileSharingResponse};
@@ -401,40 +1194,47 @@ impl<'a> FileSharingEventHandler<'a> {
                                 .get(&file_id)
                                 .filter(|f| !f.revoked)
                                 .map(|f| f.info.clone());
-                            FileSharingResponse::FileInfo(info)
-                        }
-                        FileSharingRequest::GetChunk(file_id, chunk_index) => {
-                            if let Some(shared_file) =
-                                self.client.file_manager.shared_files.get(&file_id)
-                            {
-                                if !shared_file.revoked {
-                                    match self.client.download_manager.read_chunk(
-                                        &shared_file.path,
-                                        chunk_index,
+                            if let Some(info) = &info {
+                                self.advance_group_distribution(
+                                    &file_id,
+                                    peer,
+                                    MemberDownloadStatus::Requested,
+                                );
+                                // An empty file is complete without any chunk
+                                if info.chunk_count == 0 {
+                                    self.advance_group_distribution(
                                         &file_id,
-                                    ) {
-                                        Ok(chunk) => FileSharingResponse::Chunk(Some(chunk)),
-                                        Err(_) => FileSharingResponse::Error(
-                                            "Failed to read chunk".to_string(),
-                                        ),
-                                    }
-                                } else {
-                                    FileSharingResponse::Error("File has been revoked".to_string())
+                                        peer,
+                                        MemberDownloadStatus::Completed,
+                                    );
                                 }
+                            }
+                            if info.is_none() && self.client.revoked_share_codes.contains(&file_id)
+                            {
+                                FileSharingResponse::Error(
+                                    crate::behaviour::FILE_REVOKED_ERROR.to_string(),
+                                )
                             } else {
-                                FileSharingResponse::Chunk(None)
+                                FileSharingResponse::FileInfo(info)
                             }
                         }
+                        FileSharingRequest::GetChunk(file_id, chunk_index) => {
+                            self.serve_chunk(peer, &file_id, chunk_index)
+                        }
                         FileSharingRequest::ListFiles => {
-                            let files = self
-                                .client
-                                .file_manager
-                                .shared_files
-                                .values()
-                                .filter(|f| !f.revoked)
-                                .map(|f| f.info.clone())
-                                .collect();
-                            FileSharingResponse::FileList(files)
+                            FileSharingResponse::FileList(self.client.announced_files())
+                        }
+                        FileSharingRequest::GetAvatar(avatar_hash) => {
+                            FileSharingResponse::Avatar(self.read_served_avatar(&avatar_hash))
+                        }
+                        FileSharingRequest::GetRange(file_id, start, len) => {
+                            self.serve_range(&file_id, start, len)
+                        }
+                        FileSharingRequest::FilesChanged(files) => {
+                            self.client.remote_files.insert(peer, files.clone());
+                            self.client
+                                .send_event(P2pEvent::RemoteFilesUpdated { from: peer, files });
+                            FileSharingResponse::Ack
                         }
                     };
                     let _ = self
@@ -442,16 +1242,75 @@ impl<'a> FileSharingEventHandler<'a> {
                         .swarm
                         .behaviour_mut()
                         .file_sharing
-                        .send_response(channel, response);
+                        .send_response(channel, response.into());
                 }
                 libp2p::request_response::Message::Response {
                     response,
                     request_id,
                     ..
                 } => {
-                    self.handle_file_response(response, peer, request_id.to_string())?;
+                    self.client
+                        .file_transfer_versions
+                        .insert(peer, response.version);
+                    self.handle_file_response(response.response, peer, request_id.to_string())?;
                 }
             }
+        } else if let libp2p::request_response::Event::OutboundFailure {
+            peer,
+            request_id,
+            error,
+            ..
+        } = event
+        {
+            // Move the download to another source, or fail it, instead of
+            // waiting for a response that never comes
+            let request_id = request_id.to_string();
+            if self.client.avatar_cache.untrack(&request_id).is_some() {
+                warn!("Avatar request to {} failed: {}", peer, error);
+                return Ok(());
+            }
+            if let Some(share_code) = self.client.range_requests.remove(&request_id) {
+                self.client.send_event(P2pEvent::FileRangeFailed {
+                    request_id,
+                    from: peer,
+                    share_code,
+                    error: error.to_string(),
+                });
+                return Ok(());
+            }
+            if let Some(probe) = self
+                .client
+                .download_manager
+                .take_failover_probe(&request_id)
+            {
+                warn!("Failover request to {} failed: {}", peer, error);
+                self.probe_next_source(probe);
+                return Ok(());
+            }
+            self.client.peer_scores.finish_request(&request_id, false);
+            if self.release_helper_request(peer, &request_id) {
+                warn!("Chunk request to helping source {} failed: {}", peer, error);
+                return Ok(());
+            }
+            if let Some(download_id) = self
+                .client
+                .download_manager
+                .get_download_by_request_id(&request_id)
+            {
+                warn!("File request to {} failed: {}", peer, error);
+                let reason = match error {
+                    libp2p::request_response::OutboundFailure::Timeout => {
+                        DownloadFailureReason::Timeout
+                    }
+                    _ => DownloadFailureReason::Other,
+                };
+                self.fail_over_download(
+                    &download_id,
+                    format!("Request to sharer failed: {}", error),
+                    reason,
+                );
+            }
+            self.client.request_more_chunks(peer);
         }
         Ok(())
     }
@@ -464,11 +1323,41 @@ impl<'a> FileSharingEventHandler<'a> {
     ) -> Result<()> {
         use crate::behaviour::FileSharingResponse;
 
+        if let Some(probe) = self
+            .client
+            .download_manager
+            .take_failover_probe(&request_id)
+        {
+            self.handle_failover_response(probe, response, peer);
+            return Ok(());
+        }
+
+        if let Some(share_code) = self.client.range_requests.remove(&request_id) {
+            self.handle_range_response(response, peer, request_id, share_code);
+            return Ok(());
+        }
+
         match response {
             FileSharingResponse::FileInfo(Some(info)) => {
                 self.handle_file_info_response(info, peer, request_id)?;
             }
             FileSharingResponse::FileInfo(None) => {
+                // The probe failed: fail the pending download before any chunk is requested
+                if let Some(download_id) = self
+                    .client
+                    .download_manager
+                    .get_download_by_request_id(&request_id)
+                {
+                    self.client
+                        .download_manager
+                        .cleanup_request_mapping(&request_id);
+                    self.abort_download(
+                        &download_id,
+                        "File not found".to_string(),
+                        DownloadFailureReason::NotFound,
+                    );
+                    return Ok(());
+                }
                 self.client.send_event(P2pEvent::FileDownloadFailed {
                     download_id: "unknown".to_string(),
                     filename: "Unknown".to_string(),
@@ -476,41 +1365,163 @@ impl<'a> FileSharingEventHandler<'a> {
                     from_peer_id: libp2p::PeerId::random(),
                     from_nickname: "Unknown".to_string(),
                     error: "File not found".to_string(),
+                    reason: DownloadFailureReason::NotFound,
                 });
             }
             FileSharingResponse::Chunk(Some(chunk)) => {
                 self.handle_chunk_response(peer, chunk, request_id)?;
             }
             FileSharingResponse::Chunk(None) => {
-                // Chunk not found
+                // The sharer no longer knows the file
+                self.client.peer_scores.finish_request(&request_id, false);
+                if self.release_helper_request(peer, &request_id) {
+                    return Ok(());
+                }
+                if let Some(download_id) = self
+                    .client
+                    .download_manager
+                    .get_download_by_request_id(&request_id)
+                {
+                    self.abort_download(
+                        &download_id,
+                        "File not found".to_string(),
+                        DownloadFailureReason::NotFound,
+                    );
+                }
+                self.client.request_more_chunks(peer);
             }
             FileSharingResponse::FileList(files) => {
+                self.client.remote_files.insert(peer, files.clone());
                 self.client
                     .send_event(P2pEvent::FileListReceived { from: peer, files });
             }
+            FileSharingResponse::Ack | FileSharingResponse::Range(_) => {}
+            FileSharingResponse::Avatar(data) => {
+                self.handle_avatar_response(peer, data, &request_id);
+            }
             FileSharingResponse::Error(error) => {
-                self.client.send_event(P2pEvent::Error(error));
+                self.client.peer_scores.finish_request(&request_id, false);
+                if self.release_helper_request(peer, &request_id) {
+                    return Ok(());
+                }
+                let download_id = self
+                    .client
+                    .download_manager
+                    .get_download_by_request_id(&request_id);
+                match download_id {
+                    Some(download_id) if error == crate::behaviour::FILE_REVOKED_ERROR => {
+                        self.abort_download(
+                            &download_id,
+                            "File is no longer available".to_string(),
+                            DownloadFailureReason::Revoked,
+                        );
+                    }
+                    Some(download_id) if error == crate::behaviour::FILE_CHANGED_ERROR => {
+                        self.abort_download(
+                            &download_id,
+                            "File changed on the sharer's side".to_string(),
+                            DownloadFailureReason::Changed,
+                        );
+                    }
+                    Some(download_id)
+                        if error == crate::behaviour::CONTENT_DEFINED_CHUNKS_ERROR =>
+                    {
+                        self.abort_download(&download_id, error, DownloadFailureReason::Other);
+                    }
+                    _ => self
+                        .client
+                        .send_event(P2pEvent::Error(P2pErrorKind::Transfer(error))),
+                }
+                self.client.request_more_chunks(peer);
             }
         }
         Ok(())
     }
 
+    /// Announce the bytes or the failure of a `request_range` request
+    fn handle_range_response(
+        &mut self,
+        response: crate::behaviour::FileSharingResponse,
+        peer: PeerId,
+        request_id: String,
+        share_code: String,
+    ) {
+        use crate::behaviour::FileSharingResponse;
+
+        let error = match response {
+            FileSharingResponse::Range(Some(range)) => {
+                self.client.send_event(P2pEvent::FileRangeReceived {
+                    request_id,
+                    from: peer,
+                    range,
+                });
+                return;
+            }
+            FileSharingResponse::Range(None) => "File not found".to_string(),
+            FileSharingResponse::Error(error) => error,
+            other => format!("Unexpected response: {:?}", other),
+        };
+        self.client.send_event(P2pEvent::FileRangeFailed {
+            request_id,
+            from: peer,
+            share_code,
+            error,
+        });
+    }
+
+    /// Give up a chunk request to a peer other than the download's source
+    ///
+    /// Peers sharing the same file only help a download along; when one of
+    /// them fails a chunk, the chunk is requested again, from the source or
+    /// another helper, instead of failing the download.
+    ///
+    /// # Returns
+    /// Whether `request_id` was a chunk request to such a helper
+    fn release_helper_request(&mut self, peer: PeerId, request_id: &str) -> bool {
+        let Some(download_id) = self
+            .client
+            .download_manager
+            .get_download_by_request_id(request_id)
+        else {
+            return false;
+        };
+        let Some(source) = self
+            .client
+            .download_manager
+            .get_active_download(&download_id)
+            .map(|download| download.from_peer_id)
+        else {
+            return false;
+        };
+        if source == peer
+            || self
+                .client
+                .download_manager
+                .release_chunk_request(request_id)
+                .is_none()
+        {
+            return false;
+        }
+        self.client.request_more_chunks(source);
+        true
+    }
+
     fn handle_file_info_response(
         &mut self,
-        info: crate::events::FileInfo,
+        mut info: crate::events::FileInfo,
         peer: PeerId,
         request_id: String,
     ) -> Result<()> {
-        use crate::behaviour::FileSharingRequest;
-
         // Find the pending download_id using the request_id
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .map_err(|e| anyhow::anyhow!("System time error: {}", e))?;
-        let pending_download_id = self
+        let mapped_download_id = self
             .client
             .download_manager
-            .get_download_by_request_id(&request_id)
+            .get_download_by_request_id(&request_id);
+        let pending_download_id = mapped_download_id
+            .clone()
             .unwrap_or_else(|| format!("pending_unknown_{}_{}", request_id, now.as_nanos()));
 
         // Clean up the request mapping
@@ -518,6 +1529,17 @@ impl<'a> FileSharingEventHandler<'a> {
             .download_manager
             .cleanup_request_mapping(&request_id);
 
+        // Cancelled or failed while waiting for the file info
+        if mapped_download_id.is_some()
+            && self
+                .client
+                .download_manager
+                .get_active_download(&pending_download_id)
+                .is_none()
+        {
+            return Ok(());
+        }
+
         // Get the download entry to extract share_code
         let share_code = self
             .client
@@ -526,17 +1548,42 @@ impl<'a> FileSharingEventHandler<'a> {
             .map(|d| d.share_code.clone())
             .unwrap_or_else(|| info.id.clone());
 
-        // Get peer nickname
-        let from_nickname = self
+        // Check the file against what the caller expects before any chunk
+        if let Some(request) = self
             .client
-            .peer_manager
-            .get_peer(&peer)
-            .map(|p| p.nickname.clone())
-            .unwrap_or_else(|| peer.to_string());
+            .download_manager
+            .download_request(&pending_download_id)
+        {
+            if let Err(e) = request.apply_to(&mut info) {
+                self.abort_download(
+                    &pending_download_id,
+                    e,
+                    DownloadFailureReason::UnexpectedFile,
+                );
+                return Ok(());
+            }
+        }
+
+        // Without a whole-file hash the download could never be verified
+        if info.hash.is_empty()
+            && self.client.p2p_config.strict_hash
+            && !self.client.skips_hash_for(&peer)
+        {
+            self.abort_download(
+                &pending_download_id,
+                "Sharer provided no file hash".to_string(),
+                DownloadFailureReason::Unverified,
+            );
+            return Ok(());
+        }
+
+        // Get peer nickname
+        let from_nickname = self.client.peer_manager.display_name(&peer);
 
         // Start download when we receive file info, using the pending_download_id for unique temp path
         self.client.download_manager.start_download_file(
             peer,
+            &from_nickname,
             info.clone(),
             Some(&pending_download_id),
         )?;
@@ -566,9 +1613,17 @@ impl<'a> FileSharingEventHandler<'a> {
             share_code,
         });
 
-        // Start requesting initial chunks with optimized concurrency
+        // Zero-byte files have no chunks to request, so no chunk response will ever
+        // arrive to trigger completion. Finalize the empty file right away instead.
+        if info.chunk_count == 0 {
+            return self.finalize_empty_download(&final_download_id);
+        }
+
+        // Start requesting initial chunks with optimized concurrency. With the
+        // peer's slots taken by other downloads, responses to those top this one up
         let file_id = info.id.clone();
-        let initial_requests = std::cmp::min(10, info.chunk_count);
+        let initial_requests =
+            std::cmp::min(10, info.chunk_count).min(self.client.chunk_request_slots(&peer));
         let initial_chunk_indices: Vec<usize> = (0..initial_requests).collect();
 
         // Mark initial chunks as requested using download_id
@@ -576,16 +1631,9 @@ impl<'a> FileSharingEventHandler<'a> {
             .download_manager
             .mark_chunks_requested(&final_download_id, &initial_chunk_indices)?;
 
-        // Send initial requests and map request_id to download_id
         for chunk_index in initial_chunk_indices {
-            let request_id = self.client.swarm.behaviour_mut().file_sharing.send_request(
-                &peer,
-                FileSharingRequest::GetChunk(file_id.clone(), chunk_index),
-            );
-            // Map this chunk request to the download_id so we can route the response correctly
             self.client
-                .download_manager
-                .map_request_to_download(request_id.to_string(), final_download_id.clone());
+                .send_chunk_request(peer, &file_id, chunk_index, &final_download_id);
         }
 
         Ok(())
@@ -597,14 +1645,30 @@ impl<'a> FileSharingEventHandler<'a> {
         chunk: crate::events::ChunkInfo,
         request_id: String,
     ) -> Result<()> {
-        use crate::behaviour::FileSharingRequest;
+        let result = self.process_chunk_response(chunk, request_id);
+        // The response freed one of the peer's request slots
+        self.client.request_more_chunks(peer);
+        result
+    }
 
+    fn process_chunk_response(
+        &mut self,
+        chunk: crate::events::ChunkInfo,
+        request_id: String,
+    ) -> Result<()> {
         // Find download_id using the request_id mapping
-        let download_id = self
+        let Some(download_id) = self
             .client
             .download_manager
             .get_download_by_request_id(&request_id)
-            .ok_or_else(|| anyhow::anyhow!("No download found for request_id: {}", request_id))?;
+        else {
+            // Late response of an aborted download
+            self.client.peer_scores.finish_request(&request_id, true);
+            return Err(anyhow::anyhow!(
+                "No download found for request_id: {}",
+                request_id
+            ));
+        };
 
         // Clean up the request_id mapping after finding the download_id
         self.client
@@ -612,11 +1676,27 @@ impl<'a> FileSharingEventHandler<'a> {
             .cleanup_request_mapping(&request_id);
 
         // Process chunk through DownloadManager using download_id
-        match self.client.download_manager.process_received_chunk(
+        let result = match self.client.download_manager.process_received_chunk(
             &download_id,
             chunk.chunk_index,
             &chunk,
-        )? {
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                self.client.peer_scores.finish_request(&request_id, true);
+                return Err(e);
+            }
+        };
+        // Only bad data counts against the peer, not local write failures
+        let served_well = !matches!(
+            result,
+            super::download_manager::ChunkProcessResult::HashMismatch
+                | super::download_manager::ChunkProcessResult::InvalidLength(_)
+        );
+        self.client
+            .peer_scores
+            .finish_request(&request_id, served_well);
+        match result {
             super::download_manager::ChunkProcessResult::Success {
                 downloaded_count,
                 total_chunks,
@@ -624,53 +1704,42 @@ impl<'a> FileSharingEventHandler<'a> {
                 output_path,
                 temp_path,
                 expected_hash,
+                hash_algo,
             } => {
                 // Update active download progress
                 self.client
                     .download_manager
                     .update_download_progress(&download_id, downloaded_count);
 
-                // Send progress event
-                self.send_progress_event(&download_id, downloaded_count, total_chunks);
+                // Send progress event, throttled to the configured interval
+                if self.client.download_manager.should_report_progress(
+                    &download_id,
+                    downloaded_count,
+                    total_chunks,
+                ) {
+                    self.send_progress_event(&download_id, downloaded_count, total_chunks);
+                }
 
                 // Check if download is complete
                 if is_complete {
-                    self.handle_download_complete(
+                    let modified_at = self
+                        .client
+                        .download_manager
+                        .get_downloading_file(&download_id)
+                        .and_then(|downloading_file| downloading_file.info.modified_at);
+                    let finished = self.handle_download_complete(
                         &temp_path,
                         &output_path,
                         &expected_hash,
+                        hash_algo,
+                        modified_at,
                         &download_id,
                     )?;
-                    // Remove from downloading files
-                    self.client
-                        .download_manager
-                        .remove_downloading_file(&download_id);
-                } else {
-                    // Get next chunks to request
-                    // Use 20 concurrent chunks for better performance (3-5x speedup)
-                    if let Some(next_chunks) = self
-                        .client
-                        .download_manager
-                        .get_next_chunks_to_request(&download_id, 20)
-                    {
-                        // Mark them as requested
+                    // Remove from downloading files unless chunks are being repaired
+                    if finished {
                         self.client
                             .download_manager
-                            .mark_chunks_requested(&download_id, &next_chunks)?;
-
-                        // Send requests
-                        for chunk_idx in next_chunks {
-                            let request_id =
-                                self.client.swarm.behaviour_mut().file_sharing.send_request(
-                                    &peer,
-                                    FileSharingRequest::GetChunk(chunk.file_id.clone(), chunk_idx),
-                                );
-                            // Map this chunk request to download_id
-                            self.client.download_manager.map_request_to_download(
-                                request_id.to_string(),
-                                download_id.clone(),
-                            );
-                        }
+                            .remove_downloading_file(&download_id);
                     }
                 }
             }
@@ -680,6 +1749,20 @@ impl<'a> FileSharingEventHandler<'a> {
                     format!("Chunk {} hash mismatch", chunk.chunk_index),
                 );
             }
+            super::download_manager::ChunkProcessResult::InvalidLength(error) => {
+                // A short chunk means the sharer's file shrank after it was shared
+                let reason = match error {
+                    crate::P2pError::ChunkLengthMismatch {
+                        expected, actual, ..
+                    } if actual < expected => DownloadFailureReason::Changed,
+                    _ => DownloadFailureReason::Other,
+                };
+                self.send_download_failed_event_with_reason(
+                    &download_id,
+                    error.to_string(),
+                    reason,
+                );
+            }
             super::download_manager::ChunkProcessResult::WriteFailed(error) => {
                 self.send_download_failed_event(
                     &download_id,
@@ -691,29 +1774,105 @@ impl<'a> FileSharingEventHandler<'a> {
         Ok(())
     }
 
+    fn finalize_empty_download(&mut self, download_id: &str) -> Result<()> {
+        let Some(downloading_file) = self
+            .client
+            .download_manager
+            .remove_downloading_file(download_id)
+        else {
+            return Ok(());
+        };
+
+        // Create the empty temp file so it goes through the same hash check and rename
+        if let Err(e) = std::fs::File::create(&downloading_file.temp_path) {
+            self.send_download_failed_event(
+                download_id,
+                format!("Failed to create empty file: {}", e),
+            );
+            return Ok(());
+        }
+
+        self.handle_download_complete(
+            &downloading_file.temp_path,
+            &downloading_file.output_path,
+            &downloading_file.info.hash,
+            downloading_file.info.hash_algo,
+            downloading_file.info.modified_at,
+            download_id,
+        )?;
+        Ok(())
+    }
+
+    /// Verify and move a fully received download into place
+    ///
+    /// A download without a whole-file hash only relies on its chunk
+    /// hashes and is not hashed here, unless `strict_hash` refuses it.
+    ///
+    /// # Returns
+    /// `false` while corrupted chunks are re-requested after a hash mismatch,
+    /// `true` once the download completed or failed
     fn handle_download_complete(
         &mut self,
         temp_path: &std::path::Path,
         output_path: &std::path::Path,
         expected_hash: &str,
+        hash_algo: super::file_sharing::HashAlgo,
+        modified_at: Option<i64>,
         download_id: &str,
-    ) -> Result<()> {
-        // Verify file hash
-        match self.client.download_manager.calculate_file_hash(temp_path) {
+    ) -> Result<bool> {
+        let from_peer = self
+            .client
+            .download_manager
+            .get_active_download(download_id)
+            .map(|download| download.from_peer_id);
+        if expected_hash.is_empty()
+            && self.client.p2p_config.strict_hash
+            && !from_peer.is_some_and(|peer| self.client.skips_hash_for(&peer))
+        {
+            self.send_download_failed_event_with_reason(
+                download_id,
+                "Sharer provided no file hash".to_string(),
+                DownloadFailureReason::Unverified,
+            );
+            return Ok(true);
+        }
+
+        // Verify file hash with the algorithm the sharer used
+        let file_hash = if expected_hash.is_empty() {
+            // Nothing to verify against
+            Ok(String::new())
+        } else {
+            self.client
+                .download_manager
+                .calculate_file_hash(temp_path, hash_algo)
+        };
+        match file_hash {
             Ok(file_hash) => {
                 if file_hash == expected_hash {
-                    // Rename temp file to final name
-                    match std::fs::rename(temp_path, output_path) {
+                    // Move temp file to final name
+                    match self.client.download_manager.move_to_output(
+                        temp_path,
+                        output_path,
+                        modified_at,
+                    ) {
                         Ok(_) => {
+                            self.record_downloaded_file(
+                                download_id,
+                                output_path,
+                                file_hash,
+                                hash_algo,
+                            );
                             self.send_download_completed_event(download_id, output_path);
                         }
                         Err(e) => {
                             self.send_download_failed_event(
                                 download_id,
-                                format!("Failed to rename file: {}", e),
+                                format!("Failed to move file: {}", e),
                             );
                         }
                     }
+                } else if self.repair_download(download_id)? {
+                    return Ok(false);
                 } else {
                     self.send_download_failed_event(
                         download_id,
@@ -728,7 +1887,51 @@ impl<'a> FileSharingEventHandler<'a> {
                 );
             }
         }
-        Ok(())
+        Ok(true)
+    }
+
+    /// Re-request the chunks of a download that no longer match their hash
+    ///
+    /// # Returns
+    /// Whether chunks were re-requested; `false` when repair is disabled,
+    /// exhausted or cannot find a corrupted chunk
+    fn repair_download(&mut self, download_id: &str) -> Result<bool> {
+        if self
+            .client
+            .download_manager
+            .get_downloading_file(download_id)
+            .is_none()
+        {
+            return Ok(false);
+        }
+        let Some(peer) = self
+            .client
+            .download_manager
+            .get_active_download(download_id)
+            .map(|download| download.from_peer_id)
+        else {
+            return Ok(false);
+        };
+        let Some(corrupted) = self.client.download_manager.prepare_repair(download_id)? else {
+            return Ok(false);
+        };
+
+        gigi_logging::warn!(
+            "File hash mismatch for {}, re-requesting chunks {:?}",
+            download_id,
+            corrupted
+        );
+        let downloaded_count = self
+            .client
+            .download_manager
+            .get_downloading_file(download_id)
+            .map_or(0, |file| file.downloaded_chunks.len());
+        self.client
+            .download_manager
+            .update_download_progress(download_id, downloaded_count);
+        // The corrupted chunks are missing again and requested like any other
+        self.client.request_more_chunks(peer);
+        Ok(true)
     }
 
     fn send_progress_event(
@@ -753,17 +1956,246 @@ impl<'a> FileSharingEventHandler<'a> {
         });
     }
 
-    fn send_download_failed_event(&mut self, download_id: &str, error: String) {
-        // Mark as failed in download manager first
+    /// Stop a download the user no longer wants
+    ///
+    /// Tears it down like a failure, but records it as cancelled in the
+    /// download history and sends no event.
+    ///
+    /// # Returns
+    /// `true` if the download was in progress
+    pub fn cancel_download(&mut self, download_id: &str) -> bool {
+        let file_size = self
+            .client
+            .download_manager
+            .get_downloading_file(download_id)
+            .map(|downloading_file| downloading_file.info.size);
+        self.client.download_manager.abort_download(download_id);
+        let Some(download) = self
+            .client
+            .download_manager
+            .fail_download(download_id, "Cancelled".to_string())
+        else {
+            return false;
+        };
+        info!("Cancelled download {}", download_id);
+        self.client.forget_share_source(&download.share_code);
+        self.record_download_history(&download, DownloadHistoryStatus::Cancelled, file_size, None);
+        true
+    }
+
+    /// Fail every unfinished download and forget all requests in flight
+    ///
+    /// For when the swarm that sent the requests is replaced: their answers
+    /// can no longer arrive, and the new swarm numbers its requests afresh.
+    pub fn fail_all_downloads(&mut self, error: &str) {
+        for download_id in self.client.download_manager.get_unfinished_ids() {
+            self.abort_download(
+                &download_id,
+                error.to_string(),
+                DownloadFailureReason::Other,
+            );
+        }
+        self.client.download_manager.forget_requests();
+        self.client.peer_scores.forget_requests();
+        self.client.range_requests.clear();
+    }
+
+    /// Move or fail downloads that nothing arrived for within `idle_timeout`
+    pub fn fail_idle_downloads(&mut self, idle_timeout: std::time::Duration) {
+        for download_id in self
+            .client
+            .download_manager
+            .get_idle_downloads(idle_timeout)
+        {
+            warn!(
+                "Download {} made no progress for {:?}",
+                download_id, idle_timeout
+            );
+            self.fail_over_download(
+                &download_id,
+                format!("No data from the sharer for {:?}", idle_timeout),
+                DownloadFailureReason::Timeout,
+            );
+        }
+    }
+
+    /// Move a download whose source stalled to another peer sharing the file
+    ///
+    /// Connected peers whose shared files include the same content, see
+    /// `P2pClient::chunk_sources`, are asked one at a time for the file info
+    /// under their own share code; the first one confirming the content
+    /// takes over the missing chunks. The download only fails with `error`
+    /// once no such peer is left. A download still waiting for its file info
+    /// has nothing to move and fails right away.
+    fn fail_over_download(
+        &mut self,
+        download_id: &str,
+        error: String,
+        reason: DownloadFailureReason,
+    ) {
+        if self.client.download_manager.is_failing_over(download_id) {
+            return;
+        }
+        if !self
+            .client
+            .download_manager
+            .release_stalled_source(download_id)
+        {
+            self.abort_download(download_id, error, reason);
+            return;
+        }
+        self.probe_next_source(FailoverProbe {
+            download_id: download_id.to_string(),
+            error,
+            reason,
+        });
+    }
+
+    /// Ask the next untried peer sharing the same content to take over a
+    /// stalled download
+    fn probe_next_source(&mut self, probe: FailoverProbe) {
+        let Some(source) = self
+            .client
+            .download_manager
+            .get_active_download(&probe.download_id)
+            .map(|download| download.from_peer_id)
+        else {
+            return;
+        };
+        let candidates: Vec<(PeerId, String)> = self
+            .client
+            .chunk_sources(&probe.download_id)
+            .into_iter()
+            .filter(|(peer, _)| *peer != source)
+            .collect();
+        let Some((peer, share_code)) = self
+            .client
+            .download_manager
+            .next_failover_source(&probe.download_id, candidates)
+        else {
+            warn!(
+                "No other peer can serve download {}, giving up",
+                probe.download_id
+            );
+            self.abort_download(&probe.download_id, probe.error, probe.reason);
+            return;
+        };
+
+        info!(
+            "Asking {} to take over download {}",
+            peer, probe.download_id
+        );
+        let request_id = self.client.swarm.behaviour_mut().file_sharing.send_request(
+            &peer,
+            crate::behaviour::FileSharingRequest::GetFileInfo(share_code),
+        );
         self.client
             .download_manager
-            .fail_download(download_id, error.clone());
+            .track_failover_probe(request_id.to_string(), probe);
+    }
+
+    /// Switch a stalled download to `peer` if it shares the same content
+    fn handle_failover_response(
+        &mut self,
+        probe: FailoverProbe,
+        response: crate::behaviour::FileSharingResponse,
+        peer: PeerId,
+    ) {
+        let Some(expected) = self
+            .client
+            .download_manager
+            .get_downloading_file(&probe.download_id)
+            .map(|downloading_file| &downloading_file.info)
+        else {
+            // Cancelled while asking
+            return;
+        };
+        let file_id = match &response {
+            crate::behaviour::FileSharingResponse::FileInfo(Some(info))
+                if info.hash == expected.hash
+                    && info.hash_algo == expected.hash_algo
+                    && info.size == expected.size
+                    && info.chunk_count == expected.chunk_count
+                    && info.chunk_offsets == expected.chunk_offsets =>
+            {
+                info.id.clone()
+            }
+            _ => {
+                self.probe_next_source(probe);
+                return;
+            }
+        };
+
+        info!("Continuing download {} from {}", probe.download_id, peer);
+        let nickname = self.client.peer_manager.display_name(&peer);
+        self.client.download_manager.switch_download_source(
+            &probe.download_id,
+            peer,
+            nickname,
+            file_id,
+        );
+        self.client.request_more_chunks(peer);
+    }
+
+    /// Tear down a download the sharer can no longer serve
+    ///
+    /// Only the first such response fails the download; responses to other
+    /// in-flight chunk requests find it already aborted. A download still
+    /// waiting for its file info has nothing to tear down and is just failed.
+    fn abort_download(&mut self, download_id: &str, error: String, reason: DownloadFailureReason) {
+        let aborted = self
+            .client
+            .download_manager
+            .abort_download(download_id)
+            .is_some();
+        let pending = self
+            .client
+            .download_manager
+            .get_active_download(download_id)
+            .is_some();
+        if aborted || pending {
+            self.send_download_failed_event_with_reason(download_id, error, reason);
+        }
+    }
 
-        // Get info for the event from download manager
+    fn send_download_failed_event(&mut self, download_id: &str, error: String) {
+        self.send_download_failed_event_with_reason(
+            download_id,
+            error,
+            DownloadFailureReason::Other,
+        );
+    }
+
+    fn send_download_failed_event_with_reason(
+        &mut self,
+        download_id: &str,
+        error: String,
+        reason: DownloadFailureReason,
+    ) {
+        // Get info for the event before failing removes the download
         let (actual_download_id, filename, share_code, from_nickname, from_peer_id) = self
             .client
             .download_manager
             .get_download_info_for_event(&Some(download_id.to_string()));
+        // Gone already if the download was aborted
+        let file_size = self
+            .client
+            .download_manager
+            .get_downloading_file(download_id)
+            .map(|downloading_file| downloading_file.info.size);
+
+        let failed_download = self
+            .client
+            .download_manager
+            .fail_download(download_id, error.clone());
+        if let Some(download) = &failed_download {
+            self.record_download_history(
+                download,
+                DownloadHistoryStatus::Failed,
+                file_size,
+                Some(error.clone()),
+            );
+        }
 
         self.client.send_event(P2pEvent::FileDownloadFailed {
             download_id: actual_download_id,
@@ -772,6 +2204,62 @@ impl<'a> FileSharingEventHandler<'a> {
             from_peer_id,
             from_nickname,
             error,
+            reason,
+        });
+    }
+
+    /// Remember a verified download so it can be re-verified later
+    fn record_downloaded_file(
+        &self,
+        download_id: &str,
+        output_path: &std::path::Path,
+        hash: String,
+        hash_algo: super::file_sharing::HashAlgo,
+    ) {
+        let (_, filename, share_code, _, _) = self
+            .client
+            .download_manager
+            .get_download_info_for_event(&Some(download_id.to_string()));
+        let file_size = std::fs::metadata(output_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        self.client.record_downloaded_file(DownloadedFileInfo {
+            file_path: output_path.to_string_lossy().to_string(),
+            share_code,
+            file_name: filename,
+            file_size,
+            hash,
+            hash_algo,
+            downloaded_at: chrono::Utc::now().timestamp(),
+            verified_at: None,
+            verified_ok: true,
+        });
+    }
+
+    /// Add a download that just ended to the download history
+    fn record_download_history(
+        &self,
+        download: &ActiveDownload,
+        status: DownloadHistoryStatus,
+        file_size: Option<u64>,
+        error: Option<String>,
+    ) {
+        let finished_at = chrono::Utc::now().timestamp();
+        self.client.record_download_history(DownloadHistoryEntry {
+            id: None,
+            share_code: download.share_code.clone(),
+            file_name: download.filename.clone(),
+            peer_id: download.from_peer_id.to_string(),
+            peer_nickname: download.from_nickname.clone(),
+            file_size,
+            status,
+            error,
+            started_at: finished_at - download.started_at.elapsed().as_secs() as i64,
+            finished_at,
+            final_path: download
+                .final_path
+                .as_ref()
+                .map(|path| path.to_string_lossy().to_string()),
         });
     }
 
@@ -782,10 +2270,42 @@ impl<'a> FileSharingEventHandler<'a> {
             .get_download_info_for_event(&Some(download_id.to_string()));
 
         // Mark as completed in download manager
-        let _completed_download = self
+        let completed_download = self
             .client
             .download_manager
             .complete_download(&actual_download_id, output_path.to_path_buf());
+        let (duration, chunk_count) = completed_download
+            .as_ref()
+            .map(|download| (download.started_at.elapsed(), download.total_chunks))
+            .unwrap_or_default();
+        let total_bytes = std::fs::metadata(output_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+        if let Some(download) = &completed_download {
+            self.client.forget_share_source(&download.share_code);
+            self.record_download_history(
+                download,
+                DownloadHistoryStatus::Completed,
+                Some(total_bytes),
+                None,
+            );
+        }
+        let average_speed = if duration.is_zero() {
+            total_bytes as f64
+        } else {
+            total_bytes as f64 / duration.as_secs_f64()
+        };
+        self.client.run_download_complete_hook(DownloadDetail {
+            download_id: actual_download_id.clone(),
+            share_code: share_code.clone(),
+            filename: filename.clone(),
+            from_nickname: from_nickname.clone(),
+            downloaded_chunks: chunk_count,
+            total_chunks: chunk_count,
+            bytes_done: total_bytes,
+            speed: average_speed,
+            final_path: Some(output_path.to_path_buf()),
+        });
 
         self.client.send_event(P2pEvent::FileDownloadCompleted {
             download_id: actual_download_id,
@@ -794,6 +2314,10 @@ impl<'a> FileSharingEventHandler<'a> {
             from_peer_id,
             from_nickname,
             path: output_path.to_path_buf(),
+            duration,
+            average_speed,
+            total_bytes,
+            chunk_count,
         });
     }
 }
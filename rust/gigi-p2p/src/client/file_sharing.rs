@@ -1,3 +1,6 @@
 //! File sharing functionality (re-exported from gigi-file-sharing)
 
-pub use gigi_file_sharing::{FileChunkReader, FileSharingManager, CHUNK_SIZE};
+pub use gigi_file_sharing::{
+    DirectoryShare, FileChunkReader, FileSharingManager, HashAlgo, ShareCancelToken,
+    ShareOverwritePolicy, CHUNK_SIZE,
+};
@@ -0,0 +1,165 @@
+//! Sender-side tracking of files shared to a group
+//!
+//! When a file is sent to a group, every member downloads it on its own.
+//! This module follows those downloads from the sharer's side so the sender
+//! can tell how many members have the file:
+//!
+//! - Members on the group's roster when the file is sent start as `NotStarted`
+//! - A member asking for the file info is `Requested`
+//! - A member being served chunks is `InProgress`
+//! - A member served every chunk is `Completed`
+//!
+//! Group members that were offline when the file was sent are added when
+//! they request it. Peers outside the group are not tracked.
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+
+/// How far one group member got downloading a file shared to the group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemberDownloadStatus {
+    /// The member has not asked for the file yet
+    NotStarted,
+    /// The member asked for the file info
+    Requested,
+    /// The member is being served chunks
+    InProgress,
+    /// The member was served every chunk
+    Completed,
+}
+
+/// Download status of a file shared to a group, per member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupFileDistributionStatus {
+    pub group: String,
+    pub share_code: String,
+    pub filename: String,
+    /// Status of each tracked member, sorted by peer ID
+    pub members: Vec<(PeerId, MemberDownloadStatus)>,
+}
+
+impl GroupFileDistributionStatus {
+    /// Members that downloaded the whole file
+    pub fn completed_count(&self) -> usize {
+        self.members
+            .iter()
+            .filter(|(_, status)| *status == MemberDownloadStatus::Completed)
+            .count()
+    }
+
+    /// Status of one member, `None` if it is not tracked
+    pub fn member_status(&self, peer_id: &PeerId) -> Option<MemberDownloadStatus> {
+        self.members
+            .iter()
+            .find(|(member, _)| member == peer_id)
+            .map(|(_, status)| *status)
+    }
+}
+
+/// One file sent to one group
+struct Distribution {
+    group: String,
+    filename: String,
+    members: HashMap<PeerId, MemberDownloadStatus>,
+}
+
+/// A member that just completed a download, for the completion event
+pub(crate) struct MemberCompleted {
+    pub group: String,
+    pub filename: String,
+    pub completed_count: usize,
+    pub member_count: usize,
+}
+
+/// Distributions of files sent to groups, by share code
+#[derive(Default)]
+pub(crate) struct GroupDistributions {
+    distributions: HashMap<String, Distribution>,
+}
+
+impl GroupDistributions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking a file sent to `group` with its current roster
+    ///
+    /// Sending the same file to the same group again keeps the statuses of
+    /// members already tracked.
+    pub fn start(&mut self, share_code: &str, group: &str, filename: &str, roster: &[PeerId]) {
+        let distribution = self
+            .distributions
+            .entry(share_code.to_string())
+            .or_insert_with(|| Distribution {
+                group: group.to_string(),
+                filename: filename.to_string(),
+                members: HashMap::new(),
+            });
+        for peer in roster {
+            distribution
+                .members
+                .entry(*peer)
+                .or_insert(MemberDownloadStatus::NotStarted);
+        }
+    }
+
+    /// Advance a member's status; statuses never go back
+    ///
+    /// `is_member` tells whether a peer belongs to a group, so members who
+    /// were offline when the file was sent are picked up.
+    ///
+    /// # Returns
+    /// The completion, if `peer` just completed the download
+    pub fn advance(
+        &mut self,
+        share_code: &str,
+        peer: PeerId,
+        status: MemberDownloadStatus,
+        is_member: impl Fn(&str, &PeerId) -> bool,
+    ) -> Option<MemberCompleted> {
+        let distribution = self.distributions.get_mut(share_code)?;
+        if !distribution.members.contains_key(&peer) && !is_member(&distribution.group, &peer) {
+            return None;
+        }
+        let current = distribution
+            .members
+            .entry(peer)
+            .or_insert(MemberDownloadStatus::NotStarted);
+        if status <= *current {
+            return None;
+        }
+        *current = status;
+        (status == MemberDownloadStatus::Completed).then(|| MemberCompleted {
+            group: distribution.group.clone(),
+            filename: distribution.filename.clone(),
+            completed_count: distribution
+                .members
+                .values()
+                .filter(|status| **status == MemberDownloadStatus::Completed)
+                .count(),
+            member_count: distribution.members.len(),
+        })
+    }
+
+    /// Current status of a distribution
+    pub fn status(&self, share_code: &str) -> Option<GroupFileDistributionStatus> {
+        let distribution = self.distributions.get(share_code)?;
+        let mut members: Vec<_> = distribution
+            .members
+            .iter()
+            .map(|(peer, status)| (*peer, *status))
+            .collect();
+        members.sort_by_key(|(peer, _)| *peer);
+        Some(GroupFileDistributionStatus {
+            group: distribution.group.clone(),
+            share_code: share_code.to_string(),
+            filename: distribution.filename.clone(),
+            members,
+        })
+    }
+
+    /// Stop tracking a file, e.g. when it is unshared
+    pub fn forget(&mut self, share_code: &str) {
+        self.distributions.remove(share_code);
+    }
+}
@@ -6,6 +6,15 @@
 //! - Group file sharing
 //! - Member tracking
 //!
+//! # Member Roster
+//!
+//! Besides the peers seen posting in a group, each group keeps a live roster
+//! of the peers subscribed to its topic. It follows gossipsub
+//! `Subscribed`/`Unsubscribed` events, drops peers whose last connection
+//! closed, and is reconciled with the swarm's subscription table on every
+//! presence round in case an event was missed. Roster peers are also added
+//! to the group's members.
+//!
 //! # GossipSub Groups
 //!
 //! Groups are implemented as GossipSub topics:
@@ -15,16 +24,69 @@
 //! - Message = Publish to topic
 //!
 //! This provides efficient many-to-many messaging without needing a central server.
+//!
+//! # Large Messages
+//!
+//! Texts whose serialized form exceeds the configured maximum are split into
+//! parts sharing one `message_id`. Receivers buffer the parts and emit a single
+//! `GroupMessage` event once all of them arrived.
 
 use anyhow::Result;
-use futures::channel::mpsc;
 use gigi_logging::{debug, info, instrument, warn};
-use libp2p::{gossipsub::IdentTopic, PeerId, Swarm};
-use std::collections::HashMap;
+use libp2p::{
+    gossipsub::{IdentTopic, PublishError, TopicHash},
+    PeerId, Swarm,
+};
+use std::collections::{HashMap, VecDeque};
+use std::time::Instant;
 
+use super::display_name::UnnamedPeerLabel;
+use super::event_channel::EventSender;
 use crate::behaviour::UnifiedBehaviour;
+use crate::behaviour::GOSSIPSUB_MAX_TRANSMIT_SIZE;
 use crate::error::P2pError;
-use crate::events::{GroupInfo, GroupMessage, P2pEvent};
+use crate::events::{
+    GroupInfo, GroupMessage, GroupSendStatus, Location, MessagePart, P2pEvent, PollMessage,
+};
+
+/// Maximum number of parts a chunked group message may be split into
+const MAX_MESSAGE_PARTS: u32 = 64;
+
+/// Maximum chunked messages reassembled at once; the oldest is dropped first
+const MAX_PARTIAL_MESSAGES: usize = 32;
+
+/// Parts received so far for one chunked group message
+struct PartialMessage {
+    parts: Vec<Option<String>>,
+    received: usize,
+    started_at: Instant,
+}
+
+/// A received group message that needs handling by the client
+pub enum ReceivedGroupMessage {
+    /// A file share, with the share code and the peer that shared it
+    FileShare { share_code: String, source: PeerId },
+    /// A poll or vote, to be stored and reported as `PollUpdated`
+    Poll {
+        group: String,
+        /// Author of the poll or vote, not the peer that relayed it
+        from: PeerId,
+        from_nickname: String,
+        message_id: String,
+        timestamp: u64,
+        poll: PollMessage,
+    },
+    /// A shared location, to be stored and reported as `GroupLocationMessage`
+    Location {
+        group: String,
+        /// Author of the location, not the peer that relayed it
+        from: PeerId,
+        from_nickname: String,
+        message_id: String,
+        timestamp: u64,
+        location: Location,
+    },
+}
 
 /// Group management functionality
 ///
@@ -32,6 +94,19 @@ use crate::events::{GroupInfo, GroupMessage, P2pEvent};
 pub struct GroupManager {
     /// Map of group name to group info
     groups: HashMap<String, GroupInfo>,
+    /// Serialized messages (one payload per part) waiting for a peer to
+    /// subscribe, keyed by group name
+    pending_messages: HashMap<String, VecDeque<VecDeque<Vec<u8>>>>,
+    /// Maximum buffered messages per group (0 disables buffering)
+    buffer_limit: usize,
+    /// Largest serialized message published in one piece
+    max_message_size: usize,
+    /// Whether texts above `max_message_size` are split instead of rejected
+    chunk_large_messages: bool,
+    /// Chunked messages being reassembled, keyed by (group, sender, message id)
+    partial_messages: HashMap<(String, PeerId, String), PartialMessage>,
+    /// Placeholder strategy for senders without a nickname
+    unnamed_peer_label: UnnamedPeerLabel,
 }
 
 impl GroupManager {
@@ -39,9 +114,46 @@ impl GroupManager {
     pub fn new() -> Self {
         Self {
             groups: HashMap::new(),
+            pending_messages: HashMap::new(),
+            buffer_limit: 0,
+            max_message_size: GOSSIPSUB_MAX_TRANSMIT_SIZE / 2,
+            chunk_large_messages: true,
+            partial_messages: HashMap::new(),
+            unnamed_peer_label: UnnamedPeerLabel::default(),
         }
     }
 
+    /// Set the largest serialized message published in one piece
+    ///
+    /// Texts above `max_size` are split into parts when `chunk` is true and
+    /// rejected with `P2pError::MessageTooLarge` otherwise. The size is capped
+    /// at the gossipsub frame limit.
+    pub fn set_message_size_limit(&mut self, max_size: usize, chunk: bool) {
+        self.max_message_size = max_size.min(GOSSIPSUB_MAX_TRANSMIT_SIZE);
+        self.chunk_large_messages = chunk;
+    }
+
+    /// Set how many messages per group are buffered when nobody is subscribed
+    ///
+    /// With a limit of `0` (the default) publishing to a group without
+    /// subscribed peers fails. Otherwise the message is queued, dropping the
+    /// oldest one once the limit is reached, and flushed by `flush_pending`.
+    pub fn set_buffer_limit(&mut self, limit: usize) {
+        self.buffer_limit = limit;
+    }
+
+    /// Set how senders without a nickname are labelled in events
+    pub fn set_unnamed_peer_label(&mut self, label: UnnamedPeerLabel) {
+        self.unnamed_peer_label = label;
+    }
+
+    /// Number of messages buffered for a group
+    pub fn pending_message_count(&self, group_name: &str) -> usize {
+        self.pending_messages
+            .get(group_name)
+            .map_or(0, |queue| queue.len())
+    }
+
     /// Join a GossipSub group by subscribing to its topic
     ///
     /// # Steps
@@ -61,7 +173,26 @@ impl GroupManager {
         &mut self,
         swarm: &mut Swarm<UnifiedBehaviour>,
         group_name: &str,
-        event_sender: &mut mpsc::UnboundedSender<P2pEvent>,
+        event_sender: &mut EventSender,
+    ) -> Result<()> {
+        self.join_group_as(swarm, group_name, group_name, event_sender)
+    }
+
+    /// Join a group whose id differs from the name shown to users
+    ///
+    /// # Arguments
+    ///
+    /// - `swarm`: Swarm for subscribing to topic
+    /// - `group_name`: Id of the group (also topic name)
+    /// - `display_name`: Name shown to users
+    /// - `event_sender`: Channel for emitting P2pEvents
+    #[instrument(skip(self, swarm))]
+    pub fn join_group_as(
+        &mut self,
+        swarm: &mut Swarm<UnifiedBehaviour>,
+        group_name: &str,
+        display_name: &str,
+        event_sender: &mut EventSender,
     ) -> Result<()> {
         info!("Joining group: {}", group_name);
         let topic = IdentTopic::new(group_name);
@@ -76,14 +207,19 @@ impl GroupManager {
 
         let group_info = GroupInfo {
             name: group_name.to_string(),
+            display_name: display_name.to_string(),
             topic,
             joined_at: chrono::Utc::now(),
             members: std::collections::HashSet::new(),
+            online: std::collections::HashSet::new(),
         };
 
         self.groups.insert(group_name.to_string(), group_info);
         info!("Successfully joined group: {}", group_name);
 
+        // Peers may have subscribed before we did
+        self.reconcile_rosters(swarm, event_sender);
+
         Ok(())
     }
 
@@ -131,6 +267,9 @@ impl GroupManager {
     ) -> Result<()> {
         if let Some(group) = self.groups.remove(group_name) {
             swarm.behaviour_mut().gossipsub.unsubscribe(&group.topic);
+            self.pending_messages.remove(group_name);
+            self.partial_messages
+                .retain(|(group, _, _), _| group != group_name);
         } else {
             return Err(P2pError::GroupNotFound(group_name.to_string()).into());
         }
@@ -149,6 +288,7 @@ impl GroupManager {
     /// # Message Format
     ///
     /// Group messages include:
+    /// - `message_id`: Unique id returned to the caller
     /// - `sender_nickname`: Who sent the message
     /// - `content`: The message text
     /// - `timestamp`: When the message was sent
@@ -160,6 +300,11 @@ impl GroupManager {
     /// - `group_name`: Name of group (also topic name)
     /// - `message`: Text content of the message
     /// - `local_nickname`: Sender's nickname (from local peer)
+    ///
+    /// # Returns
+    ///
+    /// Whether the message was sent or queued, with its id. Messages are only
+    /// queued when buffering is enabled and no peer is subscribed to the group.
     #[instrument(skip(self, swarm, message))]
     pub fn send_group_message(
         &mut self,
@@ -167,17 +312,113 @@ impl GroupManager {
         group_name: &str,
         message: String,
         local_nickname: &str,
-    ) -> Result<()> {
+    ) -> Result<GroupSendStatus> {
         debug!("Sending group message to: {}", group_name);
 
+        let message_id = uuid::Uuid::new_v4().to_string();
+        let group_message = Self::new_message(message_id, message, local_nickname);
+        let status = self.send_message(swarm, group_name, group_message)?;
+        debug!("Group message published successfully");
+        Ok(status)
+    }
+
+    /// Send a message built by the caller, such as a forwarded one
+    ///
+    /// Texts are split into parts like in `send_group_message`.
+    ///
+    /// # Arguments
+    ///
+    /// - `swarm`: Swarm for publishing message
+    /// - `group_name`: Name of group (also topic name)
+    /// - `group_message`: The message, usually started with `new_message`
+    ///
+    /// # Returns
+    ///
+    /// Whether the message was sent or queued, with its id
+    pub fn send_message(
+        &mut self,
+        swarm: &mut Swarm<UnifiedBehaviour>,
+        group_name: &str,
+        group_message: GroupMessage,
+    ) -> Result<GroupSendStatus> {
         let group = self
             .groups
             .get(group_name)
             .ok_or_else(|| P2pError::GroupNotFound(group_name.to_string()))?;
 
-        let group_message = GroupMessage {
+        let topic = group.topic.clone();
+        let message_id = group_message.message_id.clone();
+        let payloads = self.encode_text_message(group_message)?.into();
+        self.publish_payloads(swarm, group_name, topic, payloads, message_id)
+    }
+
+    /// Send a poll or a vote to a GossipSub group
+    ///
+    /// Polls are never split into parts; one that does not fit in
+    /// `max_message_size` is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// - `swarm`: Swarm for publishing message
+    /// - `group_name`: Name of group (also topic name)
+    /// - `message_id`: Id of the group message, the poll id for new polls
+    /// - `poll`: The poll or vote
+    /// - `content`: Plain-text fallback for peers that do not know polls
+    /// - `local_nickname`: Sender's nickname (from local peer)
+    ///
+    /// # Returns
+    ///
+    /// Whether the message was sent or queued, with its id
+    pub fn send_group_poll(
+        &mut self,
+        swarm: &mut Swarm<UnifiedBehaviour>,
+        group_name: &str,
+        message_id: String,
+        poll: PollMessage,
+        content: String,
+        local_nickname: &str,
+    ) -> Result<GroupSendStatus> {
+        let mut group_message = Self::new_message(message_id, content, local_nickname);
+        group_message.poll = Some(poll);
+        self.publish_single(swarm, group_name, group_message)
+    }
+
+    /// Send a location to a GossipSub group
+    ///
+    /// # Arguments
+    ///
+    /// - `swarm`: Swarm for publishing message
+    /// - `group_name`: Name of group (also topic name)
+    /// - `message_id`: Id of the group message
+    /// - `location`: The validated location
+    /// - `local_nickname`: Sender's nickname (from local peer)
+    ///
+    /// # Returns
+    ///
+    /// Whether the message was sent or queued, with its id
+    pub fn send_group_location(
+        &mut self,
+        swarm: &mut Swarm<UnifiedBehaviour>,
+        group_name: &str,
+        message_id: String,
+        location: Location,
+        local_nickname: &str,
+    ) -> Result<GroupSendStatus> {
+        let mut group_message =
+            Self::new_message(message_id, location.fallback_text(), local_nickname);
+        group_message.location = Some(location);
+        self.publish_single(swarm, group_name, group_message)
+    }
+
+    /// Group message from the local peer with only its text content set
+    ///
+    /// Structured messages such as polls put a plain-text fallback in
+    /// `content` for peers that do not know them.
+    pub fn new_message(message_id: String, content: String, local_nickname: &str) -> GroupMessage {
+        GroupMessage {
+            message_id,
             sender_nickname: local_nickname.to_string(),
-            content: message,
+            content,
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap_or(std::time::Duration::from_secs(0))
@@ -187,20 +428,259 @@ impl GroupManager {
             filename: None,
             file_size: None,
             file_type: None,
-        };
+            part: None,
+            poll: None,
+            location: None,
+            forwarded_from: None,
+        }
+    }
+
+    /// Publish a message that is never split into parts
+    ///
+    /// One that does not fit in `max_message_size` is rejected.
+    fn publish_single(
+        &mut self,
+        swarm: &mut Swarm<UnifiedBehaviour>,
+        group_name: &str,
+        group_message: GroupMessage,
+    ) -> Result<GroupSendStatus> {
+        let group = self
+            .groups
+            .get(group_name)
+            .ok_or_else(|| P2pError::GroupNotFound(group_name.to_string()))?;
+        let topic = group.topic.clone();
 
         let data = serde_json::to_vec(&group_message)?;
+        if data.len() > self.max_message_size {
+            return Err(P2pError::MessageTooLarge {
+                size: data.len(),
+                limit: self.max_message_size,
+            }
+            .into());
+        }
+        let message_id = group_message.message_id;
+        self.publish_payloads(swarm, group_name, topic, vec![data].into(), message_id)
+    }
 
-        swarm
-            .behaviour_mut()
-            .gossipsub
-            .publish(group.topic.clone(), data)?;
+    /// Publish the payloads of one message, queueing them if nobody listens
+    fn publish_payloads(
+        &mut self,
+        swarm: &mut Swarm<UnifiedBehaviour>,
+        group_name: &str,
+        topic: IdentTopic,
+        mut payloads: VecDeque<Vec<u8>>,
+        message_id: String,
+    ) -> Result<GroupSendStatus> {
+        while let Some(data) = payloads.front() {
+            match swarm
+                .behaviour_mut()
+                .gossipsub
+                .publish(topic.clone(), data.clone())
+            {
+                Ok(_) => {
+                    payloads.pop_front();
+                }
+                Err(PublishError::NoPeersSubscribedToTopic) if self.buffer_limit > 0 => {
+                    let queue = self
+                        .pending_messages
+                        .entry(group_name.to_string())
+                        .or_default();
+                    if queue.len() >= self.buffer_limit {
+                        warn!(
+                            "Group buffer full for {}, dropping oldest message",
+                            group_name
+                        );
+                        queue.pop_front();
+                    }
+                    queue.push_back(payloads);
+                    debug!("No peers subscribed to {}, message queued", group_name);
+                    return Ok(GroupSendStatus::Queued { message_id });
+                }
+                Err(PublishError::MessageTooLarge) => {
+                    return Err(P2pError::MessageTooLarge {
+                        size: data.len(),
+                        limit: self.max_message_size,
+                    }
+                    .into());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
 
-        debug!("Group message published successfully");
-        Ok(())
+        Ok(GroupSendStatus::Sent { message_id })
+    }
+
+    /// Serialize a text message, splitting it into parts when it is too large
+    ///
+    /// # Returns
+    ///
+    /// One payload per gossipsub message, in publishing order
+    fn encode_text_message(&self, mut group_message: GroupMessage) -> Result<Vec<Vec<u8>>> {
+        let data = serde_json::to_vec(&group_message)?;
+        if data.len() <= self.max_message_size {
+            return Ok(vec![data]);
+        }
+        if !self.chunk_large_messages {
+            return Err(P2pError::MessageTooLarge {
+                size: data.len(),
+                limit: self.max_message_size,
+            }
+            .into());
+        }
+
+        // Room left for content once the envelope of the largest part is counted
+        let content = std::mem::take(&mut group_message.content);
+        group_message.part = Some(MessagePart {
+            index: MAX_MESSAGE_PARTS,
+            count: MAX_MESSAGE_PARTS,
+        });
+        let overhead = serde_json::to_vec(&group_message)?.len();
+        let budget = self.max_message_size.saturating_sub(overhead);
+        let too_large = || P2pError::MessageTooLarge {
+            size: data.len(),
+            limit: budget * MAX_MESSAGE_PARTS as usize,
+        };
+        if budget == 0 {
+            return Err(too_large().into());
+        }
+
+        let pieces = split_json_text(&content, budget);
+        if pieces.len() > MAX_MESSAGE_PARTS as usize {
+            return Err(too_large().into());
+        }
+
+        let count = pieces.len() as u32;
+        debug!(
+            "Splitting {} byte group message into {} parts",
+            data.len(),
+            count
+        );
+        pieces
+            .into_iter()
+            .enumerate()
+            .map(|(index, piece)| {
+                let mut part = group_message.clone();
+                part.content = piece;
+                part.part = Some(MessagePart {
+                    index: index as u32,
+                    count,
+                });
+                Ok(serde_json::to_vec(&part)?)
+            })
+            .collect()
+    }
+
+    /// Store one part of a chunked message
+    ///
+    /// # Returns
+    ///
+    /// The full text once every part arrived, `None` while parts are missing
+    /// or when the part is malformed
+    fn reassemble(
+        &mut self,
+        group_name: &str,
+        sender: PeerId,
+        message_id: &str,
+        part: MessagePart,
+        content: String,
+    ) -> Option<String> {
+        if part.count == 0 || part.count > MAX_MESSAGE_PARTS || part.index >= part.count {
+            warn!(
+                "Ignoring malformed message part {}/{} from {}",
+                part.index, part.count, sender
+            );
+            return None;
+        }
+
+        let key = (group_name.to_string(), sender, message_id.to_string());
+        if !self.partial_messages.contains_key(&key)
+            && self.partial_messages.len() >= MAX_PARTIAL_MESSAGES
+        {
+            if let Some(oldest) = self
+                .partial_messages
+                .iter()
+                .min_by_key(|(_, partial)| partial.started_at)
+                .map(|(key, _)| key.clone())
+            {
+                warn!("Dropping incomplete chunked message {}", oldest.2);
+                self.partial_messages.remove(&oldest);
+            }
+        }
+
+        let partial = self
+            .partial_messages
+            .entry(key.clone())
+            .or_insert_with(|| PartialMessage {
+                parts: vec![None; part.count as usize],
+                received: 0,
+                started_at: Instant::now(),
+            });
+        let Some(slot) = partial.parts.get_mut(part.index as usize) else {
+            warn!("Message part count changed for {}", message_id);
+            return None;
+        };
+        if slot.is_none() {
+            partial.received += 1;
+        }
+        *slot = Some(content);
+
+        if partial.received < partial.parts.len() {
+            return None;
+        }
+        let partial = self.partial_messages.remove(&key)?;
+        Some(partial.parts.into_iter().flatten().collect())
+    }
+
+    /// Publish messages buffered for a topic after a peer subscribed to it
+    ///
+    /// Stops at the first failure and keeps the remaining messages queued.
+    ///
+    /// # Returns
+    ///
+    /// Number of messages published
+    pub fn flush_pending(
+        &mut self,
+        swarm: &mut Swarm<UnifiedBehaviour>,
+        topic: &TopicHash,
+    ) -> usize {
+        let group_name = topic.to_string();
+        let Some(queue) = self.pending_messages.get_mut(&group_name) else {
+            return 0;
+        };
+
+        let mut flushed = 0;
+        'messages: while let Some(parts) = queue.front_mut() {
+            while let Some(data) = parts.front() {
+                if let Err(e) = swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .publish(topic.clone(), data.clone())
+                {
+                    warn!("Failed to flush buffered message to {}: {}", group_name, e);
+                    break 'messages;
+                }
+                parts.pop_front();
+            }
+            queue.pop_front();
+            flushed += 1;
+        }
+
+        if queue.is_empty() {
+            self.pending_messages.remove(&group_name);
+        }
+        if flushed > 0 {
+            info!(
+                "Flushed {} buffered messages to group {}",
+                flushed, group_name
+            );
+        }
+        flushed
     }
 
     /// Send file to group using file sharing
+    ///
+    /// # Returns
+    /// The share code of the file
     pub async fn send_group_file(
         &mut self,
         swarm: &mut Swarm<UnifiedBehaviour>,
@@ -208,7 +688,7 @@ impl GroupManager {
         file_path: &std::path::Path,
         file_manager: &mut super::file_sharing::FileSharingManager,
         local_nickname: &str,
-    ) -> Result<()> {
+    ) -> Result<String> {
         let group_topic = {
             let group = self
                 .groups
@@ -232,6 +712,7 @@ impl GroupManager {
 
         // 2. Send message with file share information
         let group_message = GroupMessage {
+            message_id: uuid::Uuid::new_v4().to_string(),
             sender_nickname: local_nickname.to_string(),
             content: format!("Shared file: {}", filename),
             timestamp: std::time::SystemTime::now()
@@ -242,6 +723,10 @@ impl GroupManager {
             filename: Some(filename),
             file_size: Some(file_size),
             file_type: Some(file_type),
+            part: None,
+            poll: None,
+            location: None,
+            forwarded_from: None,
         };
 
         let msg_data = serde_json::to_vec(&group_message)?;
@@ -252,21 +737,142 @@ impl GroupManager {
             .publish(group_topic, msg_data)?;
 
         debug!("Group image message published successfully");
+        Ok(share_code)
+    }
+
+    /// Re-subscribe to every joined group on a freshly built swarm
+    pub fn resubscribe_all(&self, swarm: &mut Swarm<UnifiedBehaviour>) -> Result<()> {
+        for group_info in self.groups.values() {
+            swarm
+                .behaviour_mut()
+                .gossipsub
+                .subscribe(&group_info.topic)?;
+        }
         Ok(())
     }
 
+    /// Get the display name of a joined group
+    pub fn display_name(&self, group_name: &str) -> Option<&str> {
+        self.groups
+            .get(group_name)
+            .map(|group| group.display_name.as_str())
+    }
+
+    /// Get the peers currently subscribed to a group's topic
+    ///
+    /// # Returns
+    ///
+    /// Returns `Err` if the group is not joined.
+    pub fn online_members(&self, group_name: &str) -> Result<Vec<PeerId>> {
+        self.groups
+            .get(group_name)
+            .map(|group| group.online.iter().copied().collect())
+            .ok_or_else(|| P2pError::GroupNotFound(group_name.to_string()).into())
+    }
+
+    /// Whether a peer was ever seen in a joined group
+    pub fn is_member(&self, group_name: &str, peer_id: &PeerId) -> bool {
+        self.groups
+            .get(group_name)
+            .is_some_and(|group| group.members.contains(peer_id))
+    }
+
+    /// Add or remove a peer from a group's roster, reporting actual changes
+    fn set_online(
+        &mut self,
+        topic: &TopicHash,
+        peer_id: PeerId,
+        online: bool,
+        event_sender: &mut EventSender,
+    ) {
+        let group_name = topic.to_string();
+        let Some(group) = self.groups.get_mut(&group_name) else {
+            return;
+        };
+        let changed = if online {
+            group.members.insert(peer_id);
+            group.online.insert(peer_id)
+        } else {
+            group.online.remove(&peer_id)
+        };
+        if changed {
+            debug!(
+                "Peer {} is {} in group {}",
+                peer_id,
+                if online { "online" } else { "offline" },
+                group_name
+            );
+            let _ = event_sender.unbounded_send(P2pEvent::GroupMembershipChanged {
+                group: group_name,
+                peer_id,
+                online,
+                online_count: group.online.len(),
+            });
+        }
+    }
+
+    /// Remove a disconnected peer from every roster
+    pub fn remove_peer(&mut self, peer_id: PeerId, event_sender: &mut EventSender) {
+        let topics: Vec<TopicHash> = self
+            .groups
+            .values()
+            .filter(|group| group.online.contains(&peer_id))
+            .map(|group| group.topic.hash())
+            .collect();
+        for topic in topics {
+            self.set_online(&topic, peer_id, false, event_sender);
+        }
+    }
+
+    /// Bring every roster in line with the swarm's subscription table
+    pub fn reconcile_rosters(
+        &mut self,
+        swarm: &Swarm<UnifiedBehaviour>,
+        event_sender: &mut EventSender,
+    ) {
+        let mut subscribed: HashMap<TopicHash, std::collections::HashSet<PeerId>> = HashMap::new();
+        for (peer_id, topics) in swarm.behaviour().gossipsub.all_peers() {
+            for topic in topics {
+                subscribed
+                    .entry(topic.clone())
+                    .or_default()
+                    .insert(*peer_id);
+            }
+        }
+
+        let mut changes = Vec::new();
+        for group in self.groups.values() {
+            let topic = group.topic.hash();
+            let live = subscribed.remove(&topic).unwrap_or_default();
+            for peer_id in live.difference(&group.online) {
+                changes.push((topic.clone(), *peer_id, true));
+            }
+            for peer_id in group.online.difference(&live) {
+                changes.push((topic.clone(), *peer_id, false));
+            }
+        }
+        for (topic, peer_id, online) in changes {
+            self.set_online(&topic, peer_id, online, event_sender);
+        }
+    }
+
     /// Get joined groups
     pub fn list_groups(&self) -> Vec<&GroupInfo> {
         self.groups.values().collect()
     }
 
     /// Handle gossipsub events related to groups
+    ///
+    /// # Returns
+    ///
+    /// A received file share, poll or location message the client has to act
+    /// on, if any
     pub fn handle_gossipsub_event(
         &mut self,
         event: libp2p::gossipsub::Event,
         peers: &std::collections::HashMap<PeerId, crate::events::PeerInfo>,
-        event_sender: &mut mpsc::UnboundedSender<P2pEvent>,
-    ) -> Result<()> {
+        event_sender: &mut EventSender,
+    ) -> Result<Option<ReceivedGroupMessage>> {
         match event {
             libp2p::gossipsub::Event::Message {
                 propagation_source: peer_id,
@@ -284,10 +890,14 @@ impl GroupManager {
                 }
 
                 if let Ok(group_message) = serde_json::from_slice::<GroupMessage>(&message.data) {
-                    let nickname = peers
-                        .get(&peer_id)
-                        .map(|p| p.nickname.clone())
-                        .unwrap_or_else(|| peer_id.to_string());
+                    let nickname_of = |peer_id: &PeerId| {
+                        peers
+                            .get(peer_id)
+                            .map(|p| p.nickname.clone())
+                            .filter(|nickname| !nickname.is_empty())
+                            .unwrap_or_else(|| self.unnamed_peer_label.label_for(peer_id))
+                    };
+                    let nickname = nickname_of(&peer_id);
 
                     debug!("Parsed group message successfully:");
                     debug!("   - From: {} ({})", nickname, peer_id);
@@ -295,6 +905,47 @@ impl GroupManager {
                     debug!("   - Content: {}", group_message.content);
                     debug!("   - Timestamp: {}", group_message.timestamp);
 
+                    if let Some(poll) = group_message.poll {
+                        if let PollMessage::Poll {
+                            question, options, ..
+                        } = &poll
+                        {
+                            if let Err(e) = crate::validation::validate_poll(question, options) {
+                                warn!("Ignoring invalid poll from {}: {}", peer_id, e);
+                                return Ok(None);
+                            }
+                        }
+                        let from = message.source.unwrap_or(peer_id);
+                        return Ok(Some(ReceivedGroupMessage::Poll {
+                            group: group_name,
+                            from,
+                            from_nickname: nickname_of(&from),
+                            message_id: group_message.message_id,
+                            timestamp: group_message.timestamp,
+                            poll,
+                        }));
+                    }
+
+                    if let Some(location) = group_message.location {
+                        if let Err(e) = crate::validation::validate_location(
+                            location.lat,
+                            location.lon,
+                            location.label.as_deref(),
+                        ) {
+                            warn!("Ignoring invalid location from {}: {}", peer_id, e);
+                            return Ok(None);
+                        }
+                        let from = message.source.unwrap_or(peer_id);
+                        return Ok(Some(ReceivedGroupMessage::Location {
+                            group: group_name,
+                            from,
+                            from_nickname: nickname_of(&from),
+                            message_id: group_message.message_id,
+                            timestamp: group_message.timestamp,
+                            location,
+                        }));
+                    }
+
                     if group_message.has_file_share {
                         if let (
                             Some(share_code),
@@ -311,20 +962,43 @@ impl GroupManager {
                                 from: peer_id,
                                 from_nickname: nickname,
                                 group: group_name,
-                                share_code,
+                                share_code: share_code.clone(),
                                 filename,
                                 file_size,
                                 file_type,
                                 message: group_message.content.clone(),
+                                forwarded_from: group_message.forwarded_from,
                             });
+                            return Ok(Some(ReceivedGroupMessage::FileShare {
+                                share_code,
+                                source: message.source.unwrap_or(peer_id),
+                            }));
                         }
                     } else {
+                        let content = match group_message.part {
+                            Some(part) => {
+                                let sender = message.source.unwrap_or(peer_id);
+                                match self.reassemble(
+                                    &group_name,
+                                    sender,
+                                    &group_message.message_id,
+                                    part,
+                                    group_message.content,
+                                ) {
+                                    Some(content) => content,
+                                    None => return Ok(None),
+                                }
+                            }
+                            None => group_message.content,
+                        };
                         let group_name_clone = group_name.clone();
                         let _ = event_sender.unbounded_send(P2pEvent::GroupMessage {
                             from: peer_id,
                             from_nickname: nickname,
                             group: group_name,
-                            message: group_message.content,
+                            message: content,
+                            message_id: group_message.message_id,
+                            forwarded_from: group_message.forwarded_from,
                         });
                         debug!("Emitted GroupMessage event for group: {}", group_name_clone);
                     }
@@ -333,18 +1007,20 @@ impl GroupManager {
                     debug!("Raw data: {:?}", String::from_utf8(message.data));
                 }
             }
-            libp2p::gossipsub::Event::Subscribed { topic, .. } => {
+            libp2p::gossipsub::Event::Subscribed { peer_id, topic } => {
                 let group_name = topic.to_string();
                 info!("Successfully subscribed to group topic: {}", group_name);
                 let _ = event_sender.unbounded_send(P2pEvent::GroupJoined { group: group_name });
+                self.set_online(&topic, peer_id, true, event_sender);
             }
-            libp2p::gossipsub::Event::Unsubscribed { topic, .. } => {
+            libp2p::gossipsub::Event::Unsubscribed { peer_id, topic } => {
                 let group_name = topic.to_string();
                 let _ = event_sender.unbounded_send(P2pEvent::GroupLeft { group: group_name });
+                self.set_online(&topic, peer_id, false, event_sender);
             }
             _ => {}
         }
-        Ok(())
+        Ok(None)
     }
 }
 
@@ -353,3 +1029,29 @@ impl Default for GroupManager {
         Self::new()
     }
 }
+
+/// Split text into pieces whose JSON-escaped length fits `budget` bytes
+///
+/// Splits on character boundaries so every piece is valid UTF-8.
+fn split_json_text(text: &str, budget: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for c in text.chars() {
+        let len = match c {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\u{8}' | '\u{c}' => 2,
+            c if (c as u32) < 0x20 => 6,
+            c => c.len_utf8(),
+        };
+        if current_len + len > budget && !current.is_empty() {
+            pieces.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        current.push(c);
+        current_len += len;
+    }
+    if !current.is_empty() || pieces.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
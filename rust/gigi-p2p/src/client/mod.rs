@@ -3,10 +3,39 @@ pub mod file_sharing;
 pub mod p2p_client;
 
 // Internal modules (not part of public API)
+mod avatar_cache;
+mod chunk_cache;
+mod chunk_prefetch;
 mod connection_recovery;
+mod diagnostics;
+mod discovery;
+mod display_name;
 mod download_manager;
+mod download_request;
+mod event_channel;
+mod group_distribution;
 mod group_manager;
 mod peer_manager;
+mod peer_scores;
+mod presence;
+mod profile;
+mod progress;
+mod rate_limiter;
 
-pub use file_sharing::{FileChunkReader, FileSharingManager, CHUNK_SIZE};
-pub use p2p_client::{P2pClient, P2pConfig};
+pub use avatar_cache::{AvatarCacheStats, MAX_AVATAR_SIZE};
+pub use chunk_cache::{ChunkCache, ChunkCacheStats};
+pub use chunk_prefetch::{ChunkPrefetcher, PrefetchStats};
+pub use diagnostics::{DiagnosticCheck, DiagnosticResult, DiagnosticStatus, Diagnostics};
+pub use discovery::{Discovery, DiscoveryEvent, StaticDiscovery, StaticPeer};
+pub use display_name::{display_name_for, UnnamedPeerLabel};
+pub use download_request::{DownloadPriority, DownloadRequest};
+pub use event_channel::{event_channel, EventChannelError, EventReceiver, EventSender};
+pub use file_sharing::{
+    DirectoryShare, FileChunkReader, FileSharingManager, HashAlgo, ShareCancelToken,
+    ShareOverwritePolicy, CHUNK_SIZE,
+};
+pub use group_distribution::{GroupFileDistributionStatus, MemberDownloadStatus};
+pub use p2p_client::{ClientCommand, DownloadCompleteHook, P2pClient, P2pConfig};
+pub use peer_scores::{PeerScore, PeerScoreboard};
+pub use progress::ProgressGranularity;
+pub use rate_limiter::InboundRateLimit;
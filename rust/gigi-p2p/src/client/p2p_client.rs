@@ -1,10 +1,10 @@
 //! Main P2P client implementation
 
 use anyhow::Result;
-use futures::channel::mpsc;
 use gigi_dns::GigiDnsConfig;
 use gigi_logging::{error, info, instrument, warn};
 use libp2p::{
+    core::transport::ListenerId,
     identity::Keypair,
     kad,
     multiaddr::Multiaddr,
@@ -13,24 +13,110 @@ use libp2p::{
     swarm::SwarmEvent,
     PeerId, StreamProtocol,
 };
+use lru::LruCache;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::task::JoinSet;
 
 use super::{
-    connection_recovery::ConnectionRecovery, download_manager::DownloadManager,
-    event_handler::SwarmEventHandler, file_sharing::FileSharingManager,
-    group_manager::GroupManager, peer_manager::PeerManager,
+    avatar_cache::{AvatarCache, AvatarCacheStats},
+    chunk_cache::{ChunkCache, ChunkCacheStats},
+    chunk_prefetch::{ChunkPrefetcher, PrefetchStats},
+    connection_recovery::ConnectionRecovery,
+    diagnostics::{self, DiagnosticCheck, DiagnosticResult, DiagnosticStatus, Diagnostics},
+    discovery::{Discovery, DiscoveryEvent},
+    display_name::UnnamedPeerLabel,
+    download_manager::DownloadManager,
+    download_request::DownloadRequest,
+    event_channel::{event_channel, EventReceiver, EventSender},
+    event_handler::{DiscoveryEventHandler, FileSharingEventHandler, SwarmEventHandler},
+    file_sharing::{
+        DirectoryShare, FileSharingManager, HashAlgo, ShareCancelToken, ShareOverwritePolicy,
+    },
+    group_distribution::{GroupDistributions, GroupFileDistributionStatus},
+    group_manager::GroupManager,
+    peer_manager::PeerManager,
+    peer_scores::{PeerScore, PeerScoreboard},
+    presence::PresenceManager,
+    profile::{LocalProfile, PROFILE_PROTOCOL},
+    progress::{upload_id, ProgressGranularity, UploadTracker},
+    rate_limiter::{Admission, InboundRateLimit, InboundRateLimiter},
 };
 use crate::behaviour::{
-    create_gossipsub_behaviour, create_gossipsub_config, DirectMessage, FileSharingRequest,
-    UnifiedBehaviour, UnifiedEvent,
+    create_connection_limits, create_file_sharing_behaviour, create_gossipsub_behaviour,
+    create_gossipsub_config, create_identify_behaviour, DirectMessage, FileSharingRequest,
+    FileTransferVersion, UnifiedBehaviour, UnifiedEvent, MAX_RANGE_SIZE,
 };
 use crate::error::P2pError;
-use crate::events::{ActiveDownload, GroupInfo, P2pEvent, PeerInfo};
+use crate::events::{
+    ActiveDownload, ConnectionStatus, DownloadDetail, ForwardTarget, GroupInfo, GroupSendStatus,
+    Location, P2pErrorKind, P2pEvent, PeerInfo, PollMessage, PollResults, Profile, ResolveResult,
+    StateSnapshot,
+};
 use crate::validation;
-use gigi_store::migration::MigratorTrait;
-use gigi_store::{MessageStore, PersistenceConfig, SyncManager};
+use gigi_store::settings_manager::TRUSTED_PEERS_KEY;
+use gigi_store::{
+    ContactInfo, ContactManager, DownloadHistoryEntry, DownloadHistoryStore, DownloadedFileInfo,
+    FileSharingStore, IntegrityMismatch, MessageStore, PersistenceConfig, SettingsManager,
+    SyncManager, UploadProgressStore,
+};
+
+/// Maximum (peer, file) streams the chunk read-ahead tracks at once
+const MAX_PREFETCH_STREAMS: usize = 16;
+
+/// Unshared share codes remembered to answer downloaders with "revoked";
+/// older ones are answered as not found
+const MAX_REVOKED_SHARE_CODES: usize = 1024;
+
+/// Received share codes whose source peer is remembered for
+/// `download_file_by_code`; older ones must be downloaded by nickname
+const MAX_SHARE_SOURCES: usize = 1024;
+
+/// Default for `P2pConfig::max_connections`
+const DEFAULT_MAX_CONNECTIONS: u32 = 128;
+
+/// Default for `P2pConfig::presence_interval`
+pub const DEFAULT_PRESENCE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default for `P2pConfig::chunk_cache_bytes`, 32 chunks
+const DEFAULT_CHUNK_CACHE_BYTES: usize = 32 * super::file_sharing::CHUNK_SIZE;
+
+/// Default for `P2pConfig::max_group_message_size`, half the gossipsub frame limit
+const DEFAULT_MAX_GROUP_MESSAGE_SIZE: usize = 32 * 1024;
+
+/// Default for `P2pConfig::download_idle_timeout`
+const DEFAULT_DOWNLOAD_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default for `P2pConfig::max_chunk_requests_per_peer`
+const DEFAULT_MAX_CHUNK_REQUESTS_PER_PEER: usize = 16;
+
+/// Chunk requests kept in flight per download, ahead of the last received chunk
+pub(super) const CHUNK_REQUEST_WINDOW: usize = 20;
+
+/// Minimum time between discovery refreshes triggered by `resolve_nickname`
+const DISCOVERY_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Age after which a known but unconnected peer is looked for again
+const NICKNAME_STALE_AFTER: Duration = Duration::from_secs(120);
+
+/// Callback run when a download completes, see `P2pClient::set_download_complete_hook`
+pub type DownloadCompleteHook = Arc<dyn Fn(&DownloadDetail) + Send + Sync>;
+
+/// Work handed to `P2pClient::run` to do on the client it drives
+///
+/// ```rust,ignore
+/// let command: ClientCommand = Box::new(|client| {
+///     Box::pin(async move {
+///         let _ = client.share_file(&path).await;
+///     })
+/// });
+/// ```
+pub type ClientCommand =
+    Box<dyn for<'a> FnOnce(&'a mut P2pClient) -> futures::future::BoxFuture<'a, ()> + Send>;
 
 /// P2P Client configuration
 ///
@@ -46,8 +132,107 @@ pub struct P2pConfig {
     pub enable_relay: bool,
     /// Kademlia mode: Client or Server
     pub kademlia_mode: kad::Mode,
-    /// Listen addresses
+    /// Listen addresses used by `start_configured_listeners`
     pub listen_addrs: Vec<Multiaddr>,
+    /// Save downloads into per-sender subfolders (`<output>/<nickname>/file.ext`)
+    pub organize_downloads_by_sender: bool,
+    /// Give downloaded files the modification time of the shared file, when
+    /// the sharer sends it and the platform supports setting it
+    pub preserve_modified_time: bool,
+    /// Run mDNS discovery over IPv6 in addition to IPv4
+    pub enable_ipv6: bool,
+    /// Group messages buffered per group while no peer is subscribed (0 disables)
+    pub group_message_buffer: usize,
+    /// Directory for in-progress downloads; defaults to the output directory
+    pub download_temp_dir: Option<PathBuf>,
+    /// Chunks read ahead when serving sequential downloads (0 disables)
+    ///
+    /// Read-ahead chunks are kept in the cache of served chunks, so this has
+    /// no effect while `chunk_cache_bytes` is 0.
+    pub chunk_read_ahead: usize,
+    /// Bytes of recently served chunks kept in memory, so peers downloading
+    /// the same file read it from disk only once (0 disables)
+    pub chunk_cache_bytes: usize,
+    /// Largest serialized group message published in one piece, in bytes
+    pub max_group_message_size: usize,
+    /// Split group texts above `max_group_message_size` instead of rejecting them
+    pub chunk_large_group_messages: bool,
+    /// Maximum established connections; from 4 on, a quarter is kept free
+    /// for outbound dials
+    pub max_connections: u32,
+    /// Re-read a shared file's size and hash when it is found changed while
+    /// serving, so later downloads get the new contents. The file is re-read
+    /// on a blocking thread; its chunks are refused until that finishes
+    pub refresh_changed_shares: bool,
+    /// How peers are labelled in events before they announce a nickname
+    pub unnamed_peer_label: UnnamedPeerLabel,
+    /// Queued events above which download progress events are coalesced,
+    /// `None` for an unbounded event channel
+    pub event_channel_capacity: Option<usize>,
+    /// How often `FileDownloadProgress` and `FileUploadProgress` events are
+    /// emitted per transfer; the final 100% event is always emitted
+    pub progress_granularity: ProgressGranularity,
+    /// Save how many chunks were served per upload, so `FileUploadProgress`
+    /// continues from there after a restart; needs persistence
+    pub persist_upload_progress: bool,
+    /// Whole-file hash algorithm for files shared from now on; downloads are
+    /// verified with whatever algorithm the sharer used
+    pub file_hash_algo: HashAlgo,
+    /// Time between rebroadcasts of an unchanged presence status
+    pub presence_interval: Duration,
+    /// Direct and group messages accepted per peer before excess ones are
+    /// dropped, `None` for no limit
+    pub inbound_message_limit: Option<InboundRateLimit>,
+    /// Rounds of re-requesting chunks that no longer match the hash they
+    /// arrived with when a download's whole-file hash mismatches (0 disables)
+    pub hash_mismatch_repair_rounds: usize,
+    /// Hex characters in newly generated share codes, 6 to 32; longer codes
+    /// collide less often but are harder to type
+    pub share_code_length: usize,
+    /// File transfer protocol versions to speak; each connection uses the
+    /// highest one both peers support. Must not be empty
+    pub file_transfer_versions: Vec<FileTransferVersion>,
+    /// Chunk requests in flight to one peer across all downloads from it,
+    /// on top of the per-download window; 0 for no cap
+    pub max_chunk_requests_per_peer: usize,
+    /// DNS name gigi-dns discovers peers under; only peers using the same
+    /// name find each other, so separate apps or deployments can keep their
+    /// discovery apart
+    pub discovery_service_name: String,
+    /// Downloads that receive neither file info nor a chunk for this long
+    /// fail with `DownloadFailureReason::Timeout`; `None` waits forever
+    pub download_idle_timeout: Option<Duration>,
+    /// What sharing a file again after its content changed does to the
+    /// share code recipients already have
+    pub share_overwrite_policy: ShareOverwritePolicy,
+    /// Files hashed at once while sharing, at least 1; bounds disk and CPU
+    /// use when many files are shared together
+    pub max_concurrent_hashes: usize,
+    /// Refuse downloads whose `FileInfo` carries no whole-file hash, as
+    /// content-URI shares do, instead of trusting the chunk hashes alone
+    pub strict_hash: bool,
+    /// Sample a file's size and modification time twice, this far apart,
+    /// before sharing it, and refuse it with `FileSharingError::FileUnstable`
+    /// while another process is still writing it; `None` skips the check
+    pub share_stability_check: Option<Duration>,
+    /// Files shared directly by a peer are downloaded without waiting for
+    /// `download_file_by_code` when at most this many bytes; files from
+    /// trusted peers are accepted regardless of size. `None` only
+    /// auto-accepts files from trusted peers
+    pub auto_accept_max_size: Option<u64>,
+    /// Split newly shared files at content-defined boundaries, so editing a
+    /// large file changes few chunks. Such files need file transfer 1.4;
+    /// peers on earlier versions are refused their `FileInfo`
+    pub content_defined_chunking: bool,
+    /// Skip whole-file hashing with trusted peers: files sent to them with
+    /// `send_direct_file` are shared unhashed, and unhashed files from them
+    /// are accepted even with `strict_hash`. Trades integrity checking for
+    /// speed between a user's own devices; chunk hashes are still checked
+    pub skip_hash_for_trusted: bool,
+    /// Push the list of shared files to each peer as it connects, so its
+    /// `remote_files` view fills at once; off by default, so peers only
+    /// learn the list when it changes or they ask for it
+    pub announce_shared_files_on_connect: bool,
 }
 
 impl Default for P2pConfig {
@@ -60,7 +245,176 @@ impl Default for P2pConfig {
             listen_addrs: vec!["/ip4/0.0.0.0/tcp/0"
                 .parse()
                 .expect("Default multiaddr parse should never fail")],
+            organize_downloads_by_sender: false,
+            preserve_modified_time: true,
+            enable_ipv6: false,
+            group_message_buffer: 0,
+            download_temp_dir: None,
+            chunk_read_ahead: 0,
+            chunk_cache_bytes: DEFAULT_CHUNK_CACHE_BYTES,
+            max_group_message_size: DEFAULT_MAX_GROUP_MESSAGE_SIZE,
+            chunk_large_group_messages: true,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            refresh_changed_shares: false,
+            unnamed_peer_label: UnnamedPeerLabel::default(),
+            event_channel_capacity: None,
+            progress_granularity: ProgressGranularity::default(),
+            persist_upload_progress: false,
+            file_hash_algo: HashAlgo::default(),
+            presence_interval: DEFAULT_PRESENCE_INTERVAL,
+            inbound_message_limit: None,
+            hash_mismatch_repair_rounds: 0,
+            share_code_length: gigi_file_sharing::DEFAULT_SHARE_CODE_LENGTH,
+            file_transfer_versions: FileTransferVersion::ALL.to_vec(),
+            max_chunk_requests_per_peer: DEFAULT_MAX_CHUNK_REQUESTS_PER_PEER,
+            discovery_service_name: GigiDnsConfig::default().service_name,
+            download_idle_timeout: Some(DEFAULT_DOWNLOAD_IDLE_TIMEOUT),
+            share_overwrite_policy: ShareOverwritePolicy::default(),
+            max_concurrent_hashes: gigi_file_sharing::DEFAULT_MAX_CONCURRENT_HASHES,
+            strict_hash: false,
+            share_stability_check: None,
+            auto_accept_max_size: None,
+            content_defined_chunking: false,
+            skip_hash_for_trusted: false,
+            announce_shared_files_on_connect: false,
+        }
+    }
+}
+
+impl P2pConfig {
+    /// Dual-stack configuration: IPv4 and IPv6 discovery and listeners
+    ///
+    /// Useful on networks where IPv4 multicast is filtered. Listens on
+    /// `/ip4/0.0.0.0/tcp/0` and `/ip6/::/tcp/0` via `start_configured_listeners`.
+    pub fn dual_stack() -> Self {
+        let mut config = Self {
+            enable_ipv6: true,
+            ..Default::default()
+        };
+        config.listen_addrs.push(
+            "/ip6/::/tcp/0"
+                .parse()
+                .expect("Default multiaddr parse should never fail"),
+        );
+        config
+    }
+
+    /// Build a TCP listen address for a specific IP and port
+    ///
+    /// Use a loopback IP to accept only local connections, an interface IP to
+    /// bind a single interface, and port `0` to pick a random port.
+    ///
+    /// # Example
+    /// ```rust
+    /// use gigi_p2p::P2pConfig;
+    /// use std::net::{IpAddr, Ipv4Addr};
+    ///
+    /// let addr = P2pConfig::tcp_listen_addr(IpAddr::V4(Ipv4Addr::LOCALHOST), 4001);
+    /// assert_eq!(addr.to_string(), "/ip4/127.0.0.1/tcp/4001");
+    /// ```
+    pub fn tcp_listen_addr(ip: IpAddr, port: u16) -> Multiaddr {
+        Multiaddr::from(ip).with(libp2p::multiaddr::Protocol::Tcp(port))
+    }
+
+    /// Names of the fields that differ between two configurations
+    ///
+    /// # Returns
+    /// Field names such as `"listen_addrs"`, in declaration order
+    pub fn changed_fields(&self, other: &P2pConfig) -> Vec<String> {
+        let mut changed = Vec::new();
+        macro_rules! compare {
+            ($($field:ident),* $(,)?) => {
+                $(
+                    if self.$field != other.$field {
+                        changed.push(stringify!($field).to_string());
+                    }
+                )*
+            };
+        }
+        compare!(
+            bootstrap_nodes,
+            enable_kademlia,
+            enable_relay,
+            kademlia_mode,
+            listen_addrs,
+            organize_downloads_by_sender,
+            preserve_modified_time,
+            enable_ipv6,
+            group_message_buffer,
+            download_temp_dir,
+            chunk_read_ahead,
+            chunk_cache_bytes,
+            max_group_message_size,
+            chunk_large_group_messages,
+            max_connections,
+            refresh_changed_shares,
+            unnamed_peer_label,
+            event_channel_capacity,
+            progress_granularity,
+            persist_upload_progress,
+            file_hash_algo,
+            presence_interval,
+            inbound_message_limit,
+            hash_mismatch_repair_rounds,
+            share_code_length,
+            file_transfer_versions,
+            max_chunk_requests_per_peer,
+            discovery_service_name,
+            download_idle_timeout,
+            share_overwrite_policy,
+            max_concurrent_hashes,
+            strict_hash,
+            share_stability_check,
+            auto_accept_max_size,
+            content_defined_chunking,
+            skip_hash_for_trusted,
+            announce_shared_files_on_connect,
+        );
+        changed
+    }
+}
+
+/// Next input for the client's event loop
+#[allow(clippy::large_enum_variant)] // Short-lived, moved straight into its handler
+enum ClientInput {
+    Swarm(SwarmEvent<UnifiedEvent>),
+    Discovery(DiscoveryEvent),
+    ShareRefreshed(Result<ShareRefresh, tokio::task::JoinError>),
+}
+
+/// Share code and result of re-reading a shared file that changed on disk
+pub(super) type ShareRefresh = (String, Result<Option<crate::events::SharedFile>>);
+
+/// What woke the client's event loop
+#[allow(clippy::large_enum_variant)] // Short-lived, moved straight into its handler
+enum Wakeup {
+    Input(ClientInput),
+    /// The earliest timer fired; the flags tell which ones are due
+    Timers {
+        presence: bool,
+        sync: bool,
+        idle: bool,
+    },
+}
+
+/// Wait for the next swarm event, event of the alternate discovery backend
+/// or finished share refresh
+async fn next_input(
+    swarm: &mut libp2p::swarm::Swarm<UnifiedBehaviour>,
+    discovery: &mut Option<Box<dyn Discovery>>,
+    share_refreshes: &mut JoinSet<ShareRefresh>,
+) -> ClientInput {
+    use futures::StreamExt;
+    let discovery_event = async {
+        match discovery {
+            Some(discovery) => discovery.next().await,
+            None => std::future::pending().await,
         }
+    };
+    tokio::select! {
+        event = swarm.select_next_some() => ClientInput::Swarm(event),
+        Some(event) = discovery_event => ClientInput::Discovery(event),
+        Some(result) = share_refreshes.join_next() => ClientInput::ShareRefreshed(result),
     }
 }
 
@@ -81,6 +435,11 @@ pub struct P2pClient {
     pub(super) swarm: libp2p::swarm::Swarm<UnifiedBehaviour>,
     /// Local peer's nickname for display purposes
     pub(super) local_nickname: String,
+    /// Configuration used to build the swarm, kept for identity rotation
+    pub(super) p2p_config: P2pConfig,
+    /// Addresses passed to `start_listening` and their listeners, re-used
+    /// after identity rotation
+    pub(super) listeners: Vec<(Multiaddr, ListenerId)>,
 
     // Peer management
     /// Manages peer discovery, nickname resolution, and connection tracking
@@ -105,7 +464,7 @@ pub struct P2pClient {
     // Event handling
     /// Channel for sending P2P events to the application layer
     /// Applications receive events through the corresponding receiver
-    pub(super) event_sender: mpsc::UnboundedSender<P2pEvent>,
+    pub(super) event_sender: EventSender,
 
     // Persistence (optional)
     /// Optional message store for offline messaging and conversation history
@@ -116,11 +475,92 @@ pub struct P2pClient {
     /// Manages message synchronization when peers come back online
     #[allow(dead_code)]
     pub(super) sync_manager: Option<SyncManager>,
+    /// Optional file sharing store, also records completed downloads
+    /// so they can be re-verified later
+    pub(super) file_sharing_store: Option<Arc<FileSharingStore>>,
+    /// Optional record of how each download ended
+    pub(super) download_history_store: Option<Arc<DownloadHistoryStore>>,
+    /// Optional served chunk counts of unfinished uploads
+    pub(super) upload_progress_store: Option<Arc<UploadProgressStore>>,
 
     // Connection recovery
     /// Manages automatic reconnection to disconnected peers with exponential backoff
     #[allow(dead_code)]
     pub(super) connection_recovery: ConnectionRecovery,
+
+    /// Last connectivity summary sent as `ConnectivityChanged`
+    pub(super) last_connection_status: ConnectionStatus,
+
+    /// Read-ahead into `chunk_cache` for serving chunks, when `chunk_read_ahead` is set
+    pub(super) chunk_prefetcher: Option<ChunkPrefetcher>,
+    /// Recently served chunks, shared by all peers downloading a file
+    pub(super) chunk_cache: ChunkCache,
+
+    /// Post-processing callback for completed downloads
+    pub(super) download_complete_hook: Option<DownloadCompleteHook>,
+
+    /// Share codes recently unshared in this session, answered as revoked
+    /// to downloaders
+    pub(super) revoked_share_codes: LruCache<String, ()>,
+    /// Shared files that changed on disk being re-read in the background,
+    /// see `P2pConfig::refresh_changed_shares`
+    pub(super) share_refreshes: JoinSet<ShareRefresh>,
+    /// Share codes of the files in `share_refreshes`, by task
+    pub(super) pending_refreshes: HashMap<tokio::task::Id, String>,
+
+    /// Peer each received share code came from, for nickname-free downloads;
+    /// forgotten once the download finishes or is cancelled
+    pub(super) share_sources: LruCache<String, PeerId>,
+
+    /// Files connected peers last announced or listed as shared
+    pub(super) remote_files: HashMap<PeerId, Vec<crate::events::FileInfo>>,
+
+    /// Chunk latency and success scores of download sources
+    pub(super) peer_scores: PeerScoreboard,
+
+    /// File transfer version negotiated with each peer, from its latest response
+    pub(super) file_transfer_versions: HashMap<PeerId, FileTransferVersion>,
+
+    /// Share code of each byte range request in flight, by request_id
+    pub(super) range_requests: HashMap<String, String>,
+
+    /// Protocols each connected peer reported via identify, sorted
+    pub(super) peer_protocols: HashMap<PeerId, Vec<String>>,
+
+    /// When `resolve_nickname` last asked discovery to look for peers
+    last_discovery_refresh: Option<std::time::Instant>,
+
+    /// Member download statuses of files sent to groups
+    pub(super) group_distributions: GroupDistributions,
+
+    /// Chunks served per upload, for upload progress events
+    pub(super) upload_tracker: UploadTracker,
+
+    /// Local presence status and its rebroadcast schedule
+    pub(super) presence: PresenceManager,
+
+    /// Per-peer token buckets for inbound direct and group messages
+    pub(super) rate_limiter: InboundRateLimiter,
+    /// Peers exempt from rate limits whose direct file shares are
+    /// downloaded regardless of size
+    pub(super) trusted_peers: HashSet<PeerId>,
+
+    /// Profile sent to contacts in profile exchanges
+    pub(super) local_profile: LocalProfile,
+    /// Optional contact book, stores profiles received from contacts
+    pub(super) contact_manager: Option<Arc<ContactManager>>,
+    /// Optional settings store, persists the trusted peers
+    settings_manager: Option<Arc<SettingsManager>>,
+    /// Avatar images by hash, in the `.avatars` subfolder of the output directory
+    pub(super) avatar_cache: AvatarCache,
+
+    /// Alternate discovery backend, `None` when gigi-dns discovers peers
+    pub(super) discovery: Option<Box<dyn Discovery>>,
+
+    /// Background store writes not known to be finished, awaited on drain
+    pending_writes: std::sync::Mutex<Vec<tokio::task::JoinHandle<()>>>,
+    /// Set by `drain_and_shutdown`; new work is rejected from then on
+    shutting_down: bool,
 }
 
 impl P2pClient {
@@ -156,7 +596,7 @@ impl P2pClient {
         keypair: Keypair,
         nickname: String,
         output_directory: PathBuf,
-    ) -> Result<(Self, mpsc::UnboundedReceiver<P2pEvent>)> {
+    ) -> Result<(Self, EventReceiver)> {
         Self::new_with_config_and_persistence(keypair, nickname, output_directory, None)
     }
 
@@ -178,7 +618,7 @@ impl P2pClient {
         nickname: String,
         output_directory: PathBuf,
         p2p_config: P2pConfig,
-    ) -> Result<(Self, mpsc::UnboundedReceiver<P2pEvent>)> {
+    ) -> Result<(Self, EventReceiver)> {
         Self::new_with_full_config(keypair, nickname, output_directory, None, p2p_config)
     }
 
@@ -202,7 +642,7 @@ impl P2pClient {
         nickname: String,
         output_directory: PathBuf,
         persistence_config: Option<PersistenceConfig>,
-    ) -> Result<(Self, mpsc::UnboundedReceiver<P2pEvent>)> {
+    ) -> Result<(Self, EventReceiver)> {
         Self::new_with_full_config(
             keypair,
             nickname,
@@ -233,26 +673,298 @@ impl P2pClient {
         output_directory: PathBuf,
         persistence_config: Option<PersistenceConfig>,
         p2p_config: P2pConfig,
-    ) -> Result<(Self, mpsc::UnboundedReceiver<P2pEvent>)> {
-        let (event_sender, event_receiver) = mpsc::unbounded();
+    ) -> Result<(Self, EventReceiver)> {
+        Self::create(
+            keypair,
+            nickname,
+            output_directory,
+            persistence_config,
+            p2p_config,
+            None,
+        )
+    }
+
+    /// Create a new P2P client with an alternate discovery backend
+    ///
+    /// gigi-dns mDNS discovery is disabled; peers are discovered only through
+    /// `discovery`, which is started right away and stopped on `shutdown`.
+    ///
+    /// # Arguments
+    /// * `keypair` - The cryptographic keypair for this peer's identity
+    /// * `nickname` - Display name for this peer in the network
+    /// * `output_directory` - Directory where downloaded files will be saved
+    /// * `persistence_config` - Optional configuration for message persistence
+    /// * `p2p_config` - P2P configuration including bootstrap nodes
+    /// * `discovery` - Backend reporting discovered and expired peers
+    ///
+    /// # Returns
+    /// A tuple containing the P2pClient instance and an event receiver
+    #[instrument(skip(keypair, discovery))]
+    pub fn new_with_discovery(
+        keypair: Keypair,
+        nickname: String,
+        output_directory: PathBuf,
+        persistence_config: Option<PersistenceConfig>,
+        p2p_config: P2pConfig,
+        discovery: Box<dyn Discovery>,
+    ) -> Result<(Self, EventReceiver)> {
+        Self::create(
+            keypair,
+            nickname,
+            output_directory,
+            persistence_config,
+            p2p_config,
+            Some(discovery),
+        )
+    }
+
+    /// Create a client using gigi-dns, or `discovery` when given
+    fn create(
+        keypair: Keypair,
+        nickname: String,
+        output_directory: PathBuf,
+        persistence_config: Option<PersistenceConfig>,
+        p2p_config: P2pConfig,
+        mut discovery: Option<Box<dyn Discovery>>,
+    ) -> Result<(Self, EventReceiver)> {
+        let (event_sender, event_receiver) = event_channel(p2p_config.event_channel_capacity);
+
+        let local_profile = LocalProfile::new(&keypair.public());
+        let swarm = Self::build_swarm(keypair, &nickname, &p2p_config, discovery.is_none())?;
+        if let Some(discovery) = &mut discovery {
+            discovery.start()?;
+        }
+
+        // Log peer ID when swarm starts
+        info!("P2pClient started with peer ID: {}", swarm.local_peer_id());
+
+        let mut file_manager = FileSharingManager::new();
+        file_manager.set_hash_algo(p2p_config.file_hash_algo);
+        file_manager.set_overwrite_policy(p2p_config.share_overwrite_policy);
+        file_manager.set_share_code_length(p2p_config.share_code_length)?;
+        file_manager.set_max_concurrent_hashes(p2p_config.max_concurrent_hashes)?;
+        file_manager.set_stability_check(p2p_config.share_stability_check);
+        file_manager.set_content_defined_chunking(p2p_config.content_defined_chunking);
+        let avatar_cache = AvatarCache::new(output_directory.join(".avatars"));
+        let mut download_manager = DownloadManager::new(output_directory);
+        download_manager.set_organize_by_sender(p2p_config.organize_downloads_by_sender);
+        download_manager.set_preserve_modified_time(p2p_config.preserve_modified_time);
+        download_manager.set_temp_directory(p2p_config.download_temp_dir.clone());
+        download_manager.set_progress_granularity(p2p_config.progress_granularity);
+        download_manager.set_max_repair_rounds(p2p_config.hash_mismatch_repair_rounds);
+        let mut upload_tracker = UploadTracker::new(p2p_config.progress_granularity);
+        let mut group_manager = GroupManager::new();
+        group_manager.set_buffer_limit(p2p_config.group_message_buffer);
+        group_manager.set_unnamed_peer_label(p2p_config.unnamed_peer_label);
+        let mut peer_manager = PeerManager::new();
+        peer_manager.set_unnamed_peer_label(p2p_config.unnamed_peer_label);
+        let presence = PresenceManager::new(p2p_config.presence_interval);
+        let rate_limiter = InboundRateLimiter::new(p2p_config.inbound_message_limit);
+        group_manager.set_message_size_limit(
+            p2p_config.max_group_message_size,
+            p2p_config.chunk_large_group_messages,
+        );
+        let chunk_cache = ChunkCache::new(p2p_config.chunk_cache_bytes);
+        let chunk_prefetcher = (p2p_config.chunk_read_ahead > 0).then(|| {
+            ChunkPrefetcher::new(
+                p2p_config.chunk_read_ahead,
+                MAX_PREFETCH_STREAMS,
+                chunk_cache.clone(),
+            )
+        });
+
+        // Initialize persistence if config provided
+        // This enables offline messaging, conversation history, and shared file persistence
+        let (
+            message_store,
+            sync_manager,
+            file_sharing_store,
+            download_history_store,
+            upload_progress_store,
+            contact_manager,
+            settings_manager,
+        ) = if let Some(config) = persistence_config {
+            let store = Arc::new(tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current()
+                    .block_on(async { MessageStore::new(config.db_path.clone()).await })
+            })?);
+            let sync_state_path = config.db_path.with_extension("sync");
+            let sync = SyncManager::new(store.clone(), nickname.clone(), sync_state_path);
+
+            // Create file sharing store using the same database
+            // Shared files are persisted so they remain available after app restart
+            let db_conn = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    sea_orm::Database::connect(format!(
+                        "sqlite://{}?mode=rwc",
+                        config.db_path.display()
+                    ))
+                    .await
+                })
+            })?;
+            // Run migrations to ensure shared_files table exists
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current()
+                    .block_on(async { gigi_store::migration::migrate(&db_conn).await })
+            })?;
+            let contacts = Arc::new(ContactManager::new(db_conn.clone()));
+            let settings = Arc::new(SettingsManager::new(db_conn.clone()));
+            let history = Arc::new(tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current()
+                    .block_on(async { DownloadHistoryStore::new(db_conn.clone()).await })
+            })?);
+            let upload_progress = Arc::new(tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current()
+                    .block_on(async { UploadProgressStore::new(db_conn.clone()).await })
+            })?);
+            let file_store = Arc::new(tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current()
+                    .block_on(async { gigi_store::FileSharingStore::new(db_conn).await })
+            })?);
+
+            (
+                Some(store),
+                Some(sync),
+                Some(file_store),
+                Some(history),
+                Some(upload_progress),
+                Some(contacts),
+                Some(settings),
+            )
+        } else {
+            (None, None, None, None, None, None, None)
+        };
+
+        // Continue counting uploads that were in progress before a restart
+        if let (true, Some(store)) = (p2p_config.persist_upload_progress, &upload_progress_store) {
+            let saved = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(store.list_upload_progress())
+            })?;
+            for progress in saved {
+                upload_tracker.resume(
+                    &upload_id(&progress.share_code, &progress.peer_id),
+                    progress.served_chunks as usize,
+                );
+            }
+        }
+
+        // Attach file sharing store to file manager if available
+        // This allows shared files to be restored after app restart
+        let file_manager = match &file_sharing_store {
+            Some(store) => file_manager.with_store(Arc::clone(store)),
+            None => file_manager,
+        };
+
+        let mut client = Self {
+            swarm,
+            local_nickname: nickname,
+            p2p_config,
+            listeners: Vec::new(),
+            peer_manager,
+            group_manager,
+            file_manager,
+            download_manager,
+            event_sender,
+            message_store,
+            sync_manager,
+            file_sharing_store: file_sharing_store.clone(),
+            download_history_store,
+            upload_progress_store,
+            connection_recovery: ConnectionRecovery::new(10), // Max 10 reconnection attempts
+            last_connection_status: ConnectionStatus::default(),
+            chunk_prefetcher,
+            chunk_cache,
+            download_complete_hook: None,
+            revoked_share_codes: LruCache::new(NonZeroUsize::new(MAX_REVOKED_SHARE_CODES).unwrap()),
+            share_refreshes: JoinSet::new(),
+            pending_refreshes: HashMap::new(),
+            share_sources: LruCache::new(NonZeroUsize::new(MAX_SHARE_SOURCES).unwrap()),
+            remote_files: HashMap::new(),
+            peer_scores: PeerScoreboard::new(),
+            file_transfer_versions: HashMap::new(),
+            range_requests: HashMap::new(),
+            peer_protocols: HashMap::new(),
+            last_discovery_refresh: None,
+            group_distributions: GroupDistributions::new(),
+            upload_tracker,
+            presence,
+            rate_limiter,
+            trusted_peers: HashSet::new(),
+            local_profile,
+            contact_manager,
+            settings_manager,
+            avatar_cache,
+            discovery,
+            pending_writes: std::sync::Mutex::new(Vec::new()),
+            shutting_down: false,
+        };
+
+        // Load existing shared files from store if available
+        if file_sharing_store.is_some() {
+            let conflicts = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current()
+                    .block_on(async { client.file_manager.load_from_store().await })
+            })?;
+            // The app may want to tell the user which share was dropped
+            for conflict in conflicts {
+                client.send_event(P2pEvent::Error(P2pErrorKind::Storage(format!(
+                    "Duplicate share code {}: kept {}, dropped {}",
+                    conflict.share_code, conflict.kept.name, conflict.dropped.name
+                ))));
+            }
+        }
+
+        // Restore the trusted peers saved by `trust_peer`
+        if let Some(settings) = &client.settings_manager {
+            let saved = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(settings.get(TRUSTED_PEERS_KEY))
+            })?;
+            if let Some(saved) = saved {
+                let peer_ids: Vec<String> = serde_json::from_str(&saved)?;
+                client.trusted_peers = peer_ids
+                    .iter()
+                    .filter_map(|peer_id| peer_id.parse().ok())
+                    .collect();
+            }
+        }
+
+        Ok((client, event_receiver))
+    }
+
+    /// Build the libp2p swarm with all behaviours for the given identity
+    ///
+    /// gigi-dns is only enabled with `enable_mdns`, i.e. when no alternate
+    /// discovery backend is used.
+    fn build_swarm(
+        keypair: Keypair,
+        nickname: &str,
+        p2p_config: &P2pConfig,
+        enable_mdns: bool,
+    ) -> Result<libp2p::swarm::Swarm<UnifiedBehaviour>> {
         let local_peer_id = keypair.public().to_peer_id();
 
         // Create gigi-dns config
         // GigiDns enables peer discovery through a distributed DNS-like service
         // Peers announce their presence with nicknames and capabilities
         let dns_config = GigiDnsConfig {
-            nickname: nickname.clone(),
+            nickname: nickname.to_string(),
             capabilities: vec!["chat".to_string(), "file_sharing".to_string()],
             ttl: Duration::from_secs(360),
             query_interval: Duration::from_secs(300),
             announce_interval: Duration::from_secs(15),
             cleanup_interval: Duration::from_secs(30),
-            enable_ipv6: false,
+            enable_ipv6: p2p_config.enable_ipv6,
+            service_name: p2p_config.discovery_service_name.clone(),
             ..Default::default()
         };
+        validate_discovery_service_name(&p2p_config.discovery_service_name)?;
 
         // Create gigi-dns behaviour
-        let gigi_dns = gigi_dns::GigiDnsBehaviour::new(local_peer_id, dns_config)?;
+        let gigi_dns = if enable_mdns {
+            Some(gigi_dns::GigiDnsBehaviour::new(local_peer_id, dns_config)?)
+        } else {
+            None
+        };
 
         // Create Kademlia DHT behaviour
         let kademlia_config = kad::Config::default();
@@ -288,8 +1000,11 @@ impl P2pClient {
 
         // File sharing: request/response protocol for chunked file transfers
         // Files are split into chunks, transferred sequentially, and verified with BLAKE3 hashes
-        let file_sharing = request_response::cbor::Behaviour::new(
-            [(StreamProtocol::new("/file/1.0.0"), ProtocolSupport::Full)],
+        let file_sharing = create_file_sharing_behaviour(&p2p_config.file_transfer_versions)?;
+
+        // Profile exchange: request/response protocol for swapping profiles with contacts
+        let profile = request_response::cbor::Behaviour::new(
+            [(StreamProtocol::new(PROFILE_PROTOCOL), ProtocolSupport::Full)],
             request_response::Config::default(),
         );
 
@@ -297,17 +1012,20 @@ impl P2pClient {
         // Combines all protocols into a single libp2p behaviour
         // Each protocol handles its own events and message types
         let behaviour = UnifiedBehaviour {
-            gigi_dns,
+            limits: create_connection_limits(p2p_config.max_connections),
+            gigi_dns: gigi_dns.into(),
             kademlia,
             relay,
             direct_msg,
             gossipsub,
             file_sharing,
+            profile,
+            identify: create_identify_behaviour(keypair.public()),
         };
 
         // Build swarm
         // Configure transport: TCP + Noise (encryption) + Yamux (multiplexing)
-        let swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
+        let mut swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
             .with_tokio()
             .with_tcp(
                 libp2p::tcp::Config::default(),
@@ -317,105 +1035,425 @@ impl P2pClient {
             .with_behaviour(|_| behaviour)?
             .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(300)))
             .build();
+        PresenceManager::subscribe(&mut swarm)?;
 
-        // Log peer ID when swarm starts
-        info!("P2pClient started with peer ID: {}", swarm.local_peer_id());
+        Ok(swarm)
+    }
 
-        let file_manager = FileSharingManager::new();
-        let download_manager = DownloadManager::new(output_directory);
+    /// Start listening on given address
+    ///
+    /// Begins accepting incoming connections on the specified address.
+    /// Call this after creating the client to make it discoverable by other peers.
+    ///
+    /// # Arguments
+    /// * `addr` - The multiaddr to listen on (e.g., "/ip4/0.0.0.0/tcp/0")
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// client.start_listening("/ip4/0.0.0.0/tcp/0".parse()?)?;
+    /// ```
+    pub fn start_listening(&mut self, addr: Multiaddr) -> Result<()> {
+        let listener_id = self
+            .swarm
+            .listen_on(addr.clone())
+            .map_err(|e| P2pError::NetworkError(e.to_string()))?;
+        self.listeners.push((addr, listener_id));
+        Ok(())
+    }
 
-        // Initialize persistence if config provided
-        // This enables offline messaging, conversation history, and shared file persistence
-        let (message_store, sync_manager, file_sharing_store) = if let Some(config) =
-            persistence_config
-        {
-            let store = Arc::new(tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current()
-                    .block_on(async { MessageStore::new(config.db_path.clone()).await })
-            })?);
-            let sync_state_path = config.db_path.with_extension("sync");
-            let sync = SyncManager::new(store.clone(), nickname.clone(), sync_state_path);
+    /// Addresses the client is currently listening on
+    ///
+    /// Unlike the addresses passed to `start_listening`, these are the bound
+    /// addresses with concrete IPs and ports, e.g. `/ip4/192.168.1.5/tcp/4001`,
+    /// suitable for sharing out-of-band.
+    pub fn local_listen_addresses(&self) -> Vec<Multiaddr> {
+        self.swarm.listeners().cloned().collect()
+    }
 
-            // Create file sharing store using the same database
-            // Shared files are persisted so they remain available after app restart
-            let db_conn = tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current().block_on(async {
-                    sea_orm::Database::connect(format!(
-                        "sqlite://{}?mode=rwc",
-                        config.db_path.display()
-                    ))
-                    .await
-                })
-            })?;
-            // Run migrations to ensure shared_files table exists
-            tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current()
-                    .block_on(async { gigi_store::migration::Migrator::up(&db_conn, None).await })
-            })?;
-            let file_store = Arc::new(tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current()
-                    .block_on(async { gigi_store::FileSharingStore::new(db_conn).await })
-            })?);
+    /// Start listening on several addresses
+    ///
+    /// Gives control over which interfaces and ports are exposed, e.g. on
+    /// multi-homed machines or behind firewall rules. A `ListeningOn` event is
+    /// emitted for every address actually bound.
+    ///
+    /// # Arguments
+    /// * `addrs` - The multiaddrs to listen on
+    pub fn start_listening_on(&mut self, addrs: Vec<Multiaddr>) -> Result<()> {
+        for addr in addrs {
+            self.start_listening(addr)?;
+        }
+        Ok(())
+    }
 
-            (Some(store), Some(sync), Some(file_store))
-        } else {
-            (None, None, None)
-        };
+    /// Bind the addresses in `addrs` that have no listener yet
+    ///
+    /// Binds them even if none of the configured addresses were bound, e.g.
+    /// when listening was started with `start_listening`. If one fails, the
+    /// listeners started here are closed again.
+    fn bind_new_listen_addrs(&mut self, addrs: &[Multiaddr]) -> Result<()> {
+        let unbound: Vec<Multiaddr> = addrs
+            .iter()
+            .filter(|addr| !self.listeners.iter().any(|(bound, _)| bound == *addr))
+            .cloned()
+            .collect();
+        let already_bound = self.listeners.len();
+        for addr in unbound {
+            if let Err(error) = self.start_listening(addr) {
+                for (_, listener_id) in self.listeners.drain(already_bound..) {
+                    self.swarm.remove_listener(listener_id);
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
 
-        // Attach file sharing store to file manager if available
-        // This allows shared files to be restored after app restart
-        let file_manager = match &file_sharing_store {
-            Some(store) => file_manager.with_store(Arc::clone(store)),
-            None => file_manager,
-        };
+    /// Start listening on the addresses from `P2pConfig::listen_addrs`
+    pub fn start_configured_listeners(&mut self) -> Result<()> {
+        self.start_listening_on(self.p2p_config.listen_addrs.clone())
+    }
 
-        let mut client = Self {
-            swarm,
-            local_nickname: nickname,
-            peer_manager: PeerManager::new(),
-            group_manager: GroupManager::new(),
-            file_manager,
-            download_manager,
-            event_sender,
-            message_store,
-            sync_manager,
-            connection_recovery: ConnectionRecovery::new(10), // Max 10 reconnection attempts
-        };
+    /// Replace the configuration, applying what can change at runtime
+    ///
+    /// - `listen_addrs`: listeners started from the old addresses are closed
+    ///   and the new addresses are bound
+    /// - `download_temp_dir`: the directory is created and used for new downloads
+    /// - Download, group message, read-ahead, labelling, file hash, share
+    ///   code, share overwrite, share stability, presence and inbound rate
+    ///   limit settings apply immediately, as does `max_concurrent_hashes`
+    ///   for hashes not yet started
+    /// - An invalid `share_code_length`, `max_concurrent_hashes` or
+    ///   `discovery_service_name`, an empty `file_transfer_versions`, a
+    ///   `download_temp_dir` that cannot be created or a listen address that
+    ///   cannot be bound is rejected without changing anything
+    /// - `bootstrap_nodes`, `enable_kademlia`, `enable_relay`, `kademlia_mode`,
+    ///   `enable_ipv6`, `max_connections`, `file_transfer_versions` and
+    ///   `discovery_service_name` apply when the swarm is rebuilt
+    ///   by `recreate_with_keypair`; `event_channel_capacity` only at creation
+    ///
+    /// # Events
+    /// Emits `ConfigChanged` if any field changed.
+    ///
+    /// # Returns
+    /// The names of the changed fields
+    pub fn update_config(&mut self, config: P2pConfig) -> Result<Vec<String>> {
+        let changed_fields = self.p2p_config.changed_fields(&config);
+        if changed_fields.is_empty() {
+            return Ok(changed_fields);
+        }
+        // Validate and prepare everything that can fail before anything is
+        // applied, so a rejected configuration leaves the client untouched
+        if config.file_transfer_versions.is_empty() {
+            return Err(P2pError::InvalidInput(
+                "At least one file transfer version must be enabled".to_string(),
+            )
+            .into());
+        }
+        validate_discovery_service_name(&config.discovery_service_name)?;
+        if !(gigi_file_sharing::MIN_SHARE_CODE_LENGTH..=gigi_file_sharing::MAX_SHARE_CODE_LENGTH)
+            .contains(&config.share_code_length)
+        {
+            return Err(gigi_file_sharing::FileSharingError::InvalidShareCodeLength(
+                config.share_code_length,
+            )
+            .into());
+        }
+        if config.max_concurrent_hashes == 0 {
+            return Err(gigi_file_sharing::FileSharingError::InvalidHashConcurrency(0).into());
+        }
+        let changed = |field: &str| changed_fields.iter().any(|name| name == field);
+        if changed("download_temp_dir") {
+            if let Some(directory) = &config.download_temp_dir {
+                std::fs::create_dir_all(directory)?;
+            }
+        }
+        if changed("listen_addrs") {
+            self.bind_new_listen_addrs(&config.listen_addrs)?;
+        }
 
-        // Load existing shared files from store if available
-        if file_sharing_store.is_some() {
-            tokio::task::block_in_place(|| {
-                tokio::runtime::Handle::current()
-                    .block_on(async { client.file_manager.load_from_store().await })
-            })?;
+        self.file_manager
+            .set_share_code_length(config.share_code_length)?;
+        if config.max_concurrent_hashes != self.file_manager.max_concurrent_hashes() {
+            self.file_manager
+                .set_max_concurrent_hashes(config.max_concurrent_hashes)?;
         }
+        let old_config = std::mem::replace(&mut self.p2p_config, config);
 
-        Ok((client, event_receiver))
+        if changed("download_temp_dir") {
+            self.download_manager
+                .set_temp_directory(self.p2p_config.download_temp_dir.clone());
+        }
+        self.download_manager
+            .set_organize_by_sender(self.p2p_config.organize_downloads_by_sender);
+        self.download_manager
+            .set_preserve_modified_time(self.p2p_config.preserve_modified_time);
+        self.download_manager
+            .set_progress_granularity(self.p2p_config.progress_granularity);
+        self.upload_tracker
+            .set_granularity(self.p2p_config.progress_granularity);
+        self.download_manager
+            .set_max_repair_rounds(self.p2p_config.hash_mismatch_repair_rounds);
+        self.group_manager
+            .set_buffer_limit(self.p2p_config.group_message_buffer);
+        self.group_manager.set_message_size_limit(
+            self.p2p_config.max_group_message_size,
+            self.p2p_config.chunk_large_group_messages,
+        );
+        self.group_manager
+            .set_unnamed_peer_label(self.p2p_config.unnamed_peer_label);
+        self.peer_manager
+            .set_unnamed_peer_label(self.p2p_config.unnamed_peer_label);
+        self.file_manager
+            .set_hash_algo(self.p2p_config.file_hash_algo);
+        self.file_manager
+            .set_overwrite_policy(self.p2p_config.share_overwrite_policy);
+        self.file_manager
+            .set_stability_check(self.p2p_config.share_stability_check);
+        self.file_manager
+            .set_content_defined_chunking(self.p2p_config.content_defined_chunking);
+        if changed("presence_interval") {
+            self.presence
+                .set_interval(self.p2p_config.presence_interval);
+        }
+        if changed("inbound_message_limit") {
+            self.rate_limiter
+                .set_limit(self.p2p_config.inbound_message_limit);
+        }
+        if changed("chunk_read_ahead") {
+            self.chunk_prefetcher = (self.p2p_config.chunk_read_ahead > 0).then(|| {
+                ChunkPrefetcher::new(
+                    self.p2p_config.chunk_read_ahead,
+                    MAX_PREFETCH_STREAMS,
+                    self.chunk_cache.clone(),
+                )
+            });
+        }
+        self.chunk_cache
+            .set_max_bytes(self.p2p_config.chunk_cache_bytes);
+
+        if changed("listen_addrs") {
+            // The new addresses are already bound; close the listeners started
+            // from old addresses that are no longer configured
+            let new_addrs = &self.p2p_config.listen_addrs;
+            let (stale, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.listeners)
+                .into_iter()
+                .partition(|(addr, _)| {
+                    old_config.listen_addrs.contains(addr) && !new_addrs.contains(addr)
+                });
+            self.listeners = kept;
+            for (addr, listener_id) in stale {
+                info!("Closing listener on {}", addr);
+                self.swarm.remove_listener(listener_id);
+            }
+        }
+
+        info!("Configuration changed: {}", changed_fields.join(", "));
+        self.send_event(P2pEvent::ConfigChanged {
+            changed_fields: changed_fields.clone(),
+        });
+        Ok(changed_fields)
     }
 
-    /// Start listening on given address
+    /// Start listening for TCP connections on a specific IP and port
     ///
-    /// Begins accepting incoming connections on the specified address.
-    /// Call this after creating the client to make it discoverable by other peers.
+    /// # Arguments
+    /// * `ip` - The IP to bind (e.g. loopback for local-only access)
+    /// * `port` - The TCP port, or `0` for a random port
+    ///
+    /// # Returns
+    /// The multiaddr passed to the listener
+    pub fn listen_on_tcp(&mut self, ip: IpAddr, port: u16) -> Result<Multiaddr> {
+        let addr = P2pConfig::tcp_listen_addr(ip, port);
+        self.start_listening(addr.clone())?;
+        Ok(addr)
+    }
+
+    /// Rotate this client's identity to a new keypair
+    ///
+    /// Rebuilds the swarm with the new keypair while preserving configuration,
+    /// the download folder, the shared-files registry, group memberships,
+    /// persistence and the event channel. Existing connections are closed and
+    /// peers are rediscovered under the new PeerId. Unfinished downloads fail,
+    /// since their requests went out on the old swarm; start them again once
+    /// the peers are back.
     ///
     /// # Arguments
-    /// * `addr` - The multiaddr to listen on (e.g., "/ip4/0.0.0.0/tcp/0")
+    /// * `keypair` - The new cryptographic keypair for this peer's identity
+    ///
+    /// # Events
+    /// Emits `FileDownloadFailed` for each unfinished download, then
+    /// `PeerIdChanged` with the old and new PeerId.
+    pub fn recreate_with_keypair(&mut self, keypair: Keypair) -> Result<()> {
+        let old_peer_id = *self.swarm.local_peer_id();
+        let swarm = Self::build_swarm(
+            keypair,
+            &self.local_nickname,
+            &self.p2p_config,
+            self.discovery.is_none(),
+        )?;
+
+        FileSharingEventHandler::new(self).fail_all_downloads("Local peer ID changed");
+
+        // Dropping the old swarm closes its listeners and connections
+        self.swarm = swarm;
+        self.peer_manager = PeerManager::new();
+        self.peer_manager
+            .set_unnamed_peer_label(self.p2p_config.unnamed_peer_label);
+        self.connection_recovery = ConnectionRecovery::new(10);
+
+        for (addr, listener_id) in &mut self.listeners {
+            *listener_id = self
+                .swarm
+                .listen_on(addr.clone())
+                .map_err(|e| P2pError::NetworkError(e.to_string()))?;
+        }
+        self.group_manager.resubscribe_all(&mut self.swarm)?;
+
+        let new_peer_id = *self.swarm.local_peer_id();
+        info!("Rotated peer ID from {} to {}", old_peer_id, new_peer_id);
+        self.send_event(P2pEvent::PeerIdChanged {
+            old_peer_id,
+            new_peer_id,
+        });
+        Ok(())
+    }
+
+    /// Recover from the device switching networks, e.g. wifi to cellular
+    ///
+    /// Call from the platform's network-change callback. Every listener is
+    /// closed and bound again, so it picks up the current interfaces;
+    /// discovery looks for peers right away, and known peers that are not
+    /// connected are dialed at their last known address. Connections that
+    /// the change broke are redialed by connection recovery once closed.
+    ///
+    /// # Events
+    /// `ListenStopped` for the old and `ListeningOn` for the new listen
+    /// addresses, then the usual discovery and connection events.
+    ///
+    /// # Returns
+    /// The number of peers dialed
+    pub fn on_network_changed(&mut self) -> Result<usize> {
+        self.ensure_running()?;
+        for (addr, listener_id) in &mut self.listeners {
+            self.swarm.remove_listener(*listener_id);
+            *listener_id = self
+                .swarm
+                .listen_on(addr.clone())
+                .map_err(|e| P2pError::NetworkError(e.to_string()))?;
+        }
+
+        // A new network is worth a lookup even right after the last one
+        self.last_discovery_refresh = None;
+        self.refresh_discovery();
+
+        let disconnected: Vec<(PeerId, Multiaddr)> = self
+            .peer_manager
+            .list_peers()
+            .into_iter()
+            .filter(|peer| !peer.connected)
+            .filter_map(|peer| Some((peer.peer_id, peer.addresses.first()?.clone())))
+            .collect();
+        let mut dialed = 0;
+        for (peer_id, address) in disconnected {
+            match self.swarm.dial(address) {
+                Ok(()) => dialed += 1,
+                Err(e) => warn!("Failed to redial {} after network change: {}", peer_id, e),
+            }
+        }
+
+        info!(
+            "Network changed: rebound {} listeners, redialed {} peers",
+            self.listeners.len(),
+            dialed
+        );
+        Ok(dialed)
+    }
+
+    /// Drive the client and handle its events in one loop
+    ///
+    /// Replaces a task polling `handle_next_swarm_event` next to a task
+    /// reading `events`, and the lock both need to share the client. Swarm
+    /// events are handled, each emitted event is passed to `on_event` along
+    /// with the client, and every `ClientCommand` from `commands` runs on
+    /// the client in turn. Emitted events go first when several inputs are
+    /// ready. Errors of single swarm events are logged and the loop goes on.
+    ///
+    /// # Arguments
+    /// * `events` - Receiver returned when creating this client
+    /// * `commands` - Work for the client; the loop goes on once it ends
+    /// * `on_event` - Called for every event; `ControlFlow::Break` stops the loop
+    ///
+    /// # Returns
+    /// Once `on_event` breaks, `events` ends or the client shuts down
     ///
     /// # Example
     /// ```rust,ignore
-    /// client.start_listening("/ip4/0.0.0.0/tcp/0".parse()?)?;
+    /// let (command_tx, command_rx) = futures::channel::mpsc::unbounded();
+    /// client
+    ///     .run(&mut events, command_rx, |_client, event| {
+    ///         ui_tx.send(event).ok();
+    ///         ControlFlow::Continue(())
+    ///     })
+    ///     .await?;
     /// ```
-    pub fn start_listening(&mut self, addr: Multiaddr) -> Result<()> {
-        self.swarm
-            .listen_on(addr)
-            .map_err(|e| P2pError::NetworkError(e.to_string()))?;
-        Ok(())
+    pub async fn run<C, F>(
+        &mut self,
+        events: &mut EventReceiver,
+        commands: C,
+        mut on_event: F,
+    ) -> Result<()>
+    where
+        C: futures::Stream<Item = ClientCommand> + Unpin,
+        F: FnMut(&mut Self, P2pEvent) -> std::ops::ControlFlow<()>,
+    {
+        use futures::StreamExt;
+
+        #[allow(clippy::large_enum_variant)] // Short-lived, handled right away
+        enum Input {
+            Event(Option<P2pEvent>),
+            Command(ClientCommand),
+            Swarm(Result<Wakeup>),
+        }
+
+        let mut commands = commands.fuse();
+        loop {
+            // Only wait inside the select, so no input is dropped halfway
+            // through being handled when another branch wins
+            let input = tokio::select! {
+                biased;
+                event = events.next() => Input::Event(event),
+                Some(command) = commands.next() => Input::Command(command),
+                wakeup = self.next_wakeup() => Input::Swarm(wakeup),
+            };
+            let result = match input {
+                Input::Event(Some(event)) => {
+                    if on_event(self, event).is_break() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Input::Event(None) => return Ok(()),
+                Input::Command(command) => {
+                    command(self).await;
+                    continue;
+                }
+                Input::Swarm(Ok(wakeup)) => self.handle_wakeup(wakeup).await,
+                Input::Swarm(Err(e)) => Err(e),
+            };
+            match result {
+                Ok(()) => {}
+                Err(_) if self.shutting_down => return Ok(()),
+                Err(e) => error!("Error handling swarm event: {}", e),
+            }
+        }
     }
 
     /// Handle the next swarm event (convenient method)
     ///
-    /// Waits for and processes the next event from the libp2p swarm.
-    /// This is the main event loop for P2P networking.
+    /// Waits for and processes the next event from the libp2p swarm, or
+    /// the next due timer. Drive it in a loop when not using `run`; `run`
+    /// instead races `next_wakeup` against its other inputs and passes the
+    /// result to `handle_wakeup`.
     ///
     /// # Example
     /// ```rust,ignore
@@ -424,10 +1462,334 @@ impl P2pClient {
     /// }
     /// ```
     pub async fn handle_next_swarm_event(&mut self) -> Result<()> {
-        use futures::StreamExt;
-        let event = self.swarm.select_next_some().await;
-        self.handle_event(event)?;
-        Ok(())
+        let wakeup = self.next_wakeup().await?;
+        self.handle_wakeup(wakeup).await
+    }
+
+    /// Wait for the next swarm or discovery event, or the next timer
+    ///
+    /// Only waits, so it is cancel-safe and `run` can race it against its
+    /// other inputs; what it returns is handled by `handle_wakeup`.
+    async fn next_wakeup(&mut self) -> Result<Wakeup> {
+        self.ensure_running()?;
+        let presence_at = self.presence.next_broadcast();
+        let sync_at = match &self.sync_manager {
+            Some(sync_manager) => sync_manager.next_sync_at().await,
+            None => None,
+        };
+        let idle_at = self
+            .p2p_config
+            .download_idle_timeout
+            .and_then(|timeout| self.download_manager.next_idle_deadline(timeout))
+            .map(tokio::time::Instant::from_std);
+        let next_input = next_input(
+            &mut self.swarm,
+            &mut self.discovery,
+            &mut self.share_refreshes,
+        );
+        let wakeup = match presence_at.into_iter().chain(sync_at).chain(idle_at).min() {
+            // Rebroadcast the presence status, sync queued messages and give
+            // up on stalled downloads while waiting for events
+            Some(deadline) => tokio::select! {
+                input = next_input => Wakeup::Input(input),
+                _ = tokio::time::sleep_until(deadline) => {
+                    let now = tokio::time::Instant::now();
+                    Wakeup::Timers {
+                        presence: presence_at.is_some_and(|at| at <= now),
+                        sync: sync_at.is_some_and(|at| at <= now),
+                        idle: idle_at.is_some_and(|at| at <= now),
+                    }
+                }
+            },
+            None => Wakeup::Input(next_input.await),
+        };
+        Ok(wakeup)
+    }
+
+    /// Handle what `next_wakeup` returned
+    async fn handle_wakeup(&mut self, wakeup: Wakeup) -> Result<()> {
+        let result = match wakeup {
+            Wakeup::Input(ClientInput::Swarm(event)) => self.handle_event(event),
+            Wakeup::Input(ClientInput::Discovery(event)) => {
+                DiscoveryEventHandler::new(self).handle_event(event)
+            }
+            Wakeup::Input(ClientInput::ShareRefreshed(result)) => {
+                FileSharingEventHandler::new(self).finish_share_refresh(result);
+                return Ok(());
+            }
+            Wakeup::Timers {
+                presence,
+                sync,
+                idle,
+            } => {
+                if presence {
+                    self.presence.broadcast(&mut self.swarm);
+                    self.group_manager
+                        .reconcile_rosters(&self.swarm, &mut self.event_sender);
+                }
+                if sync {
+                    self.sync_queued_messages(tokio::time::Instant::now())
+                        .await?;
+                }
+                if idle {
+                    if let Some(timeout) = self.p2p_config.download_idle_timeout {
+                        FileSharingEventHandler::new(self).fail_idle_downloads(timeout);
+                    }
+                }
+                return Ok(());
+            }
+        };
+        self.emit_connectivity_if_changed();
+        result
+    }
+
+    /// Wait until a peer with the given nickname is discovered and connected
+    ///
+    /// Drives the swarm while waiting, so discovery and other events keep
+    /// flowing to the event receiver. A peer that was discovered earlier but
+    /// is not connected is dialed once.
+    ///
+    /// # Arguments
+    /// * `nickname` - The peer's nickname
+    /// * `timeout` - How long to wait for discovery and connection
+    ///
+    /// # Returns
+    /// The peer's PeerId, or `P2pError::Timeout` if it did not connect in time
+    pub async fn connect_by_nickname(
+        &mut self,
+        nickname: &str,
+        timeout: Duration,
+    ) -> Result<PeerId> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut dialed = false;
+        loop {
+            if let Some(peer_id) = self.peer_manager.get_peer_id_by_nickname(nickname) {
+                if self.swarm.is_connected(&peer_id) {
+                    return Ok(peer_id);
+                }
+                if !dialed {
+                    dialed = true;
+                    let address = self
+                        .peer_manager
+                        .get_peer(&peer_id)
+                        .and_then(|peer| peer.addresses.first().cloned());
+                    if let Some(address) = address {
+                        if let Err(e) = self.swarm.dial(address) {
+                            warn!("Failed to dial {} ({}): {}", nickname, peer_id, e);
+                        }
+                    }
+                }
+            }
+
+            match tokio::time::timeout_at(deadline, self.handle_next_swarm_event()).await {
+                Ok(Err(e)) => warn!("Error while waiting for {}: {}", nickname, e),
+                Ok(Ok(())) => {}
+                Err(_) => {
+                    return Err(P2pError::Timeout(format!(
+                        "Peer '{}' did not connect within {:?}",
+                        nickname, timeout
+                    ))
+                    .into())
+                }
+            }
+        }
+    }
+
+    /// Get read-ahead counters for served chunks
+    ///
+    /// # Returns
+    /// The counters, or `None` when `P2pConfig::chunk_read_ahead` is 0
+    pub fn prefetch_stats(&self) -> Option<PrefetchStats> {
+        self.chunk_prefetcher.as_ref().map(|p| p.stats())
+    }
+
+    /// Get counters of the cache of recently served chunks
+    pub fn chunk_cache_stats(&self) -> ChunkCacheStats {
+        self.chunk_cache.stats()
+    }
+
+    /// Get a summary of the current connectivity
+    ///
+    /// Derived from the swarm listeners, the peer table and the discovery
+    /// interfaces. Changes are also reported as `P2pEvent::ConnectivityChanged`.
+    ///
+    /// # Returns
+    /// The current ConnectionStatus
+    pub fn connection_status(&self) -> ConnectionStatus {
+        ConnectionStatus {
+            listening: self.swarm.listeners().next().is_some(),
+            connected_peers: self.peer_manager.connected_peers_count(),
+            discovered_peers: self.peer_manager.peers_count(),
+            mdns_active: self
+                .swarm
+                .behaviour()
+                .gigi_dns
+                .as_ref()
+                .is_some_and(|gigi_dns| gigi_dns.active_interface_count() > 0),
+        }
+    }
+
+    /// Check the P2P stack and report what is not working
+    ///
+    /// Runs the checks in `DiagnosticCheck`: a bound listener, discovered
+    /// peers, a loopback connection to a TCP listener, a writable download
+    /// directory and a reachable store. Nothing is changed except a probe
+    /// file written to and removed from the download directories.
+    ///
+    /// # Returns
+    /// A Diagnostics report with one result per check
+    pub async fn run_diagnostics(&self) -> Diagnostics {
+        let listen_addresses = self.local_listen_addresses();
+        let results = vec![
+            self.diagnose_listener(&listen_addresses),
+            self.diagnose_discovery(),
+            Self::diagnose_loopback(&listen_addresses).await,
+            self.diagnose_download_directory(),
+            self.diagnose_store().await,
+        ];
+        for result in &results {
+            if result.status == DiagnosticStatus::Failed {
+                warn!(
+                    "Diagnostic check {:?} failed: {}",
+                    result.check, result.detail
+                );
+            }
+        }
+        Diagnostics { results }
+    }
+
+    fn diagnose_listener(&self, listen_addresses: &[Multiaddr]) -> DiagnosticResult {
+        if listen_addresses.is_empty() {
+            return DiagnosticResult::failed(
+                DiagnosticCheck::Listener,
+                "Not listening on any address",
+                "Call start_listening or start_configured_listeners, and check \
+                 that the configured ports are not already in use",
+            );
+        }
+        let addresses: Vec<String> = listen_addresses.iter().map(|a| a.to_string()).collect();
+        DiagnosticResult::passed(
+            DiagnosticCheck::Listener,
+            format!("Listening on {}", addresses.join(", ")),
+        )
+    }
+
+    fn diagnose_discovery(&self) -> DiagnosticResult {
+        let status = self.connection_status();
+        if status.discovered_peers > 0 {
+            return DiagnosticResult::passed(
+                DiagnosticCheck::Discovery,
+                format!("{} peers discovered", status.discovered_peers),
+            );
+        }
+        if self.swarm.behaviour().gigi_dns.is_enabled() && !status.mdns_active {
+            return DiagnosticResult::failed(
+                DiagnosticCheck::Discovery,
+                "mDNS is not running on any network interface",
+                "Connect to a network; mDNS needs an interface with multicast support",
+            );
+        }
+        let hint = if self.discovery.is_some() {
+            "Check that the discovery backend knows about other peers"
+        } else {
+            "Make sure another device is on the same network and that the \
+             network does not block multicast, e.g. through Wi-Fi client isolation"
+        };
+        DiagnosticResult::failed(DiagnosticCheck::Discovery, "No peers discovered", hint)
+    }
+
+    async fn diagnose_loopback(listen_addresses: &[Multiaddr]) -> DiagnosticResult {
+        let Some(target) = listen_addresses
+            .iter()
+            .find_map(diagnostics::tcp_loopback_target)
+        else {
+            return DiagnosticResult::skipped(DiagnosticCheck::Loopback, "No TCP listener");
+        };
+        match diagnostics::check_loopback(target).await {
+            Ok(()) => DiagnosticResult::passed(
+                DiagnosticCheck::Loopback,
+                format!("Connected to {}", target),
+            ),
+            Err(e) => DiagnosticResult::failed(
+                DiagnosticCheck::Loopback,
+                format!("Could not connect to {}: {}", target, e),
+                "A local firewall may be blocking incoming connections to this app",
+            ),
+        }
+    }
+
+    fn diagnose_download_directory(&self) -> DiagnosticResult {
+        let mut directories = vec![self.download_manager.output_directory().to_path_buf()];
+        directories.extend(self.p2p_config.download_temp_dir.clone());
+        for directory in &directories {
+            if let Err(e) = diagnostics::check_directory(directory) {
+                return DiagnosticResult::failed(
+                    DiagnosticCheck::DownloadDirectory,
+                    format!("Cannot write to {}: {}", directory.display(), e),
+                    "Choose a download directory the app has permission to write to, \
+                     on a volume with free space",
+                );
+            }
+        }
+        let directories: Vec<String> = directories
+            .iter()
+            .map(|directory| directory.display().to_string())
+            .collect();
+        DiagnosticResult::passed(
+            DiagnosticCheck::DownloadDirectory,
+            format!("Writable: {}", directories.join(", ")),
+        )
+    }
+
+    async fn diagnose_store(&self) -> DiagnosticResult {
+        let Some(store) = &self.file_sharing_store else {
+            return DiagnosticResult::skipped(DiagnosticCheck::Store, "Persistence is not enabled");
+        };
+        match store.shared_stats().await {
+            Ok(_) => DiagnosticResult::passed(DiagnosticCheck::Store, "Store is reachable"),
+            Err(e) => DiagnosticResult::failed(
+                DiagnosticCheck::Store,
+                format!("Store query failed: {:#}", e),
+                "Check that the database file exists, is writable and is not corrupted",
+            ),
+        }
+    }
+
+    /// Take a snapshot of peers, connectivity, downloads, shares and groups
+    ///
+    /// Everything is read from the client's own state in one call, so the
+    /// parts are consistent with each other.
+    ///
+    /// # Returns
+    /// A StateSnapshot owning copies of the current state
+    pub fn snapshot(&self) -> StateSnapshot {
+        let mut peers: Vec<PeerInfo> = self.list_peers().into_iter().cloned().collect();
+        peers.sort_by(|a, b| a.nickname.cmp(&b.nickname));
+        let mut downloads: Vec<ActiveDownload> =
+            self.get_active_downloads().into_iter().cloned().collect();
+        downloads.sort_by_key(|download| download.started_at);
+        let mut shared_files: Vec<crate::events::SharedFile> =
+            self.list_shared_files().into_iter().cloned().collect();
+        shared_files.sort_by(|a, b| a.share_code.cmp(&b.share_code));
+        let mut groups: Vec<GroupInfo> = self.list_groups().into_iter().cloned().collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+
+        StateSnapshot {
+            peers,
+            connection_status: self.connection_status(),
+            downloads,
+            shared_files,
+            groups,
+        }
+    }
+
+    /// Emit `ConnectivityChanged` when the status differs from the last one sent
+    fn emit_connectivity_if_changed(&mut self) {
+        let status = self.connection_status();
+        if status != self.last_connection_status {
+            self.last_connection_status = status.clone();
+            self.send_event(P2pEvent::ConnectivityChanged(status));
+        }
     }
 
     /// Handle a single swarm event
@@ -465,6 +1827,15 @@ impl P2pClient {
         self.peer_manager.get_peer_nickname(peer_id)
     }
 
+    /// Name to show for a peer, even before it announced a nickname
+    ///
+    /// # Returns
+    /// The nickname if known, otherwise a placeholder chosen by
+    /// `P2pConfig::unnamed_peer_label`
+    pub fn display_name(&self, peer_id: &PeerId) -> String {
+        self.peer_manager.display_name(peer_id)
+    }
+
     /// Get peer info
     ///
     /// Retrieves detailed information about a peer.
@@ -491,6 +1862,82 @@ impl P2pClient {
         self.peer_manager.get_peer_id_by_nickname(nickname)
     }
 
+    /// Resolve a nickname to a peer, looking for it on a miss
+    ///
+    /// A known peer is returned right away with when it was last seen. For
+    /// an unknown nickname, discovery is asked to look for peers (a gigi-dns
+    /// query, or `Discovery::refresh` of an alternate backend) and `Pending`
+    /// is returned; retry once `PeerDiscovered` arrives. A known peer that is
+    /// not connected and was last seen more than two minutes ago is looked
+    /// for as well. Discovery is asked at most every five seconds.
+    ///
+    /// # Arguments
+    /// * `nickname` - The peer's nickname
+    pub fn resolve_nickname(&mut self, nickname: &str) -> ResolveResult {
+        let known = self
+            .peer_manager
+            .get_peer_id_by_nickname(nickname)
+            .and_then(|peer_id| self.peer_manager.get_peer(&peer_id))
+            .map(|peer| (peer.peer_id, peer.last_seen, peer.connected));
+        let Some((peer_id, last_seen, connected)) = known else {
+            self.refresh_discovery();
+            return ResolveResult::Pending;
+        };
+        if !connected && last_seen.elapsed() > NICKNAME_STALE_AFTER {
+            self.refresh_discovery();
+        }
+        ResolveResult::Resolved {
+            peer_id,
+            last_seen,
+            connected,
+        }
+    }
+
+    /// Peer ID of a nickname for sending to it
+    ///
+    /// # Returns
+    /// The peer, or `PeerNotDiscovered` (`NotConnectedToNetwork` when
+    /// offline) after asking discovery to look for it
+    fn peer_id_for_nickname(&mut self, nickname: &str) -> Result<PeerId> {
+        match self.resolve_nickname(nickname) {
+            ResolveResult::Resolved { peer_id, .. } => Ok(peer_id),
+            ResolveResult::Pending => Err(self.unreachable_error(nickname, false).into()),
+        }
+    }
+
+    /// Why the peer with `nickname` cannot be sent to right now
+    ///
+    /// `NotConnectedToNetwork` without listeners and connections, else
+    /// `PeerUnreachable` for a `discovered` peer and `PeerNotDiscovered`
+    /// for an unknown one.
+    fn unreachable_error(&self, nickname: &str, discovered: bool) -> P2pError {
+        let status = self.connection_status();
+        if !status.listening && status.connected_peers == 0 {
+            P2pError::NotConnectedToNetwork
+        } else if discovered {
+            P2pError::PeerUnreachable(nickname.to_string())
+        } else {
+            P2pError::PeerNotDiscovered(nickname.to_string())
+        }
+    }
+
+    /// Ask discovery to look for peers, at most every `DISCOVERY_REFRESH_INTERVAL`
+    fn refresh_discovery(&mut self) {
+        let now = std::time::Instant::now();
+        if self
+            .last_discovery_refresh
+            .is_some_and(|at| now.duration_since(at) < DISCOVERY_REFRESH_INTERVAL)
+        {
+            return;
+        }
+        self.last_discovery_refresh = Some(now);
+        if let Some(discovery) = &mut self.discovery {
+            discovery.refresh();
+        } else if let Some(gigi_dns) = self.swarm.behaviour_mut().gigi_dns.as_mut() {
+            gigi_dns.query_now();
+        }
+    }
+
     /// Remove a peer from the peer list
     ///
     /// Removes a peer from the internal peer tracking.
@@ -510,34 +1957,140 @@ impl P2pClient {
     /// # Returns
     /// Ok on successful shutdown
     pub fn shutdown(&mut self) -> Result<()> {
+        if let Some(discovery) = &mut self.discovery {
+            discovery.stop();
+        }
         self.peer_manager.shutdown(&mut self.event_sender)
     }
 
-    /// List all discovered peers
+    /// Finish outstanding work, persist it, then shut down
     ///
-    /// Returns information about all peers that have been discovered
-    /// through GigiDns, regardless of connection status.
+    /// Stops accepting new work, handles the swarm events already queued,
+    /// sends queued offline messages to connected peers, waits for
+    /// background store writes (shared files, history, messages, upload
+    /// progress) and then shuts down like `shutdown`. Call it before the
+    /// process exits so nothing just shared or received is lost. Queued
+    /// messages for peers that are not connected stay in the store.
     ///
-    /// # Returns
-    /// Vector of peer references
-    pub fn list_peers(&self) -> Vec<&PeerInfo> {
-        self.peer_manager.list_peers()
-    }
-
-    /// Get all connected peers
+    /// Afterwards `handle_next_swarm_event` and methods starting new work,
+    /// such as `share_file`, `download_file` and the send methods, return
+    /// `P2pError::ShuttingDown`.
     ///
-    /// Returns information about peers that are currently connected.
+    /// # Arguments
+    /// * `timeout` - How long to wait for queued events and store writes
     ///
     /// # Returns
-    /// Vector of connected peer references
-    pub fn get_connected_peers(&self) -> Vec<&PeerInfo> {
-        self.peer_manager.get_connected_peers()
+    /// `P2pError::Timeout` if store writes were still running at the
+    /// timeout; the client is shut down either way
+    pub async fn drain_and_shutdown(&mut self, timeout: Duration) -> Result<()> {
+        if self.shutting_down {
+            return Ok(());
+        }
+        self.shutting_down = true;
+        let deadline = tokio::time::Instant::now() + timeout;
+
+        self.handle_queued_inputs(deadline);
+        // Give connected peers their queued messages, then hand the
+        // requests to their connections
+        match tokio::time::timeout_at(deadline, self.sync_offline_queue(None)).await {
+            Ok(Err(e)) => warn!("Failed to flush offline queue: {}", e),
+            Err(_) => warn!("Offline queue still syncing after {:?}", timeout),
+            Ok(Ok(())) => {}
+        }
+        self.handle_queued_inputs(deadline);
+
+        let pending = std::mem::take(&mut *self.pending_writes.lock().unwrap());
+        let flushed = tokio::time::timeout_at(deadline, async {
+            self.file_manager.flush_store().await;
+            for handle in pending {
+                if let Err(e) = handle.await {
+                    error!("Store write task failed: {}", e);
+                }
+            }
+        })
+        .await;
+
+        self.shutdown()?;
+        flushed.map_err(|_| {
+            P2pError::Timeout(format!("Store writes still running after {:?}", timeout)).into()
+        })
     }
 
-    /// Get peers count
-    ///
-    /// Returns the total number of discovered peers.
-    ///
+    /// Handle the swarm and discovery events already queued, without
+    /// waiting for more
+    fn handle_queued_inputs(&mut self, deadline: tokio::time::Instant) {
+        use futures::FutureExt;
+
+        while tokio::time::Instant::now() < deadline {
+            let Some(input) = next_input(
+                &mut self.swarm,
+                &mut self.discovery,
+                &mut self.share_refreshes,
+            )
+            .now_or_never() else {
+                break;
+            };
+            let handled = match input {
+                ClientInput::Swarm(event) => self.handle_event(event),
+                ClientInput::Discovery(event) => {
+                    DiscoveryEventHandler::new(self).handle_event(event)
+                }
+                ClientInput::ShareRefreshed(result) => {
+                    FileSharingEventHandler::new(self).finish_share_refresh(result);
+                    Ok(())
+                }
+            };
+            if let Err(e) = handled {
+                warn!("Failed to handle event while draining: {}", e);
+            }
+        }
+    }
+
+    /// Whether `drain_and_shutdown` was called
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down
+    }
+
+    /// Reject new work once `drain_and_shutdown` was called
+    fn ensure_running(&self) -> Result<()> {
+        if self.shutting_down {
+            return Err(P2pError::ShuttingDown.into());
+        }
+        Ok(())
+    }
+
+    /// Run a store write in the background, awaited by `drain_and_shutdown`
+    fn spawn_store_write(&self, write: impl std::future::Future<Output = ()> + Send + 'static) {
+        let mut pending = self.pending_writes.lock().unwrap();
+        pending.retain(|handle| !handle.is_finished());
+        pending.push(tokio::spawn(write));
+    }
+
+    /// List all discovered peers
+    ///
+    /// Returns information about all peers that have been discovered
+    /// through GigiDns, regardless of connection status.
+    ///
+    /// # Returns
+    /// Vector of peer references
+    pub fn list_peers(&self) -> Vec<&PeerInfo> {
+        self.peer_manager.list_peers()
+    }
+
+    /// Get all connected peers
+    ///
+    /// Returns information about peers that are currently connected.
+    ///
+    /// # Returns
+    /// Vector of connected peer references
+    pub fn get_connected_peers(&self) -> Vec<&PeerInfo> {
+        self.peer_manager.get_connected_peers()
+    }
+
+    /// Get peers count
+    ///
+    /// Returns the total number of discovered peers.
+    ///
     /// # Returns
     /// Count of all discovered peers
     pub fn peers_count(&self) -> usize {
@@ -569,15 +2122,54 @@ impl P2pClient {
     /// * `message` - The message text to send
     ///
     /// # Returns
-    /// Ok if sent successfully or stored for delivery
+    /// The id of the sent message, also used as its store key
     /// Error if peer not found and persistence is disabled
-    pub async fn send_persistent_message(&mut self, nickname: &str, message: String) -> Result<()> {
+    pub async fn send_persistent_message(
+        &mut self,
+        nickname: &str,
+        message: String,
+    ) -> Result<String> {
+        self.send_stored_message(nickname, message, None).await
+    }
+
+    /// Send a disappearing message to peer
+    ///
+    /// Works like `send_persistent_message`, but the message is deleted by the
+    /// store cleanup on both sides once its timer runs out: on the sender
+    /// `after` the message was sent, on the receiver `after` it was read.
+    ///
+    /// # Arguments
+    /// * `nickname` - The recipient's display name
+    /// * `message` - The message text to send
+    /// * `after` - How long the message lives, in whole seconds
+    ///
+    /// # Returns
+    /// The id of the sent message, also used as its store key
+    pub async fn send_disappearing_message(
+        &mut self,
+        nickname: &str,
+        message: String,
+        after: Duration,
+    ) -> Result<String> {
+        self.send_stored_message(nickname, message, Some(after.as_secs()))
+            .await
+    }
+
+    /// Store a direct message and send it, or queue it while the peer is offline
+    async fn send_stored_message(
+        &mut self,
+        nickname: &str,
+        message: String,
+        disappear_after_secs: Option<u64>,
+    ) -> Result<String> {
         // Validate inputs
         validation::validate_nickname(nickname)
             .map_err(|e| anyhow::anyhow!("Invalid nickname: {}", e))?;
         validation::validate_message(&message)
             .map_err(|e| anyhow::anyhow!("Invalid message: {}", e))?;
         let peer_id = self.peer_manager.get_peer_id_by_nickname(nickname);
+        // Same id is sent to the peer and used as the store key
+        let message_id = uuid::Uuid::new_v4().to_string();
 
         match peer_id {
             Some(peer_id) => {
@@ -590,7 +2182,7 @@ impl P2pClient {
                     tokio::task::block_in_place(|| {
                         tokio::runtime::Handle::current().block_on(async {
                             let stored_msg = StoredMessage {
-                                id: uuid::Uuid::new_v4().to_string(),
+                                id: message_id.clone(),
                                 msg_type: MessageType::Direct,
                                 direction: MessageDirection::Sent,
                                 content: MessageContent::Text {
@@ -610,6 +2202,8 @@ impl P2pClient {
                                 sync_attempts: 0,
                                 last_sync_attempt: None,
                                 expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+                                disappear_after_secs,
+                                forwarded_from: None,
                             };
                             message_store.store_message(stored_msg).await
                         })
@@ -618,12 +2212,17 @@ impl P2pClient {
                 }
 
                 // Send the message via P2P
-                self.swarm
-                    .behaviour_mut()
-                    .direct_msg
-                    .send_request(&peer_id, DirectMessage::Text { message });
+                self.swarm.behaviour_mut().direct_msg.send_request(
+                    &peer_id,
+                    DirectMessage::Text {
+                        message,
+                        message_id: message_id.clone(),
+                        disappear_after_secs,
+                        forwarded_from: None,
+                    },
+                );
 
-                Ok(())
+                Ok(message_id)
             }
             None => {
                 // Peer is offline, store message for later delivery
@@ -631,7 +2230,6 @@ impl P2pClient {
                     use gigi_store::{MessageContent, MessageDirection, MessageType, SyncStatus};
 
                     // Create stored message
-                    let message_id = uuid::Uuid::new_v4().to_string();
                     let stored_msg = gigi_store::StoredMessage {
                         id: message_id.clone(),
                         msg_type: MessageType::Direct,
@@ -653,6 +2251,8 @@ impl P2pClient {
                         sync_attempts: 0,
                         last_sync_attempt: None,
                         expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+                        disappear_after_secs,
+                        forwarded_from: None,
                     };
 
                     // Store message and add to offline queue
@@ -668,10 +2268,7 @@ impl P2pClient {
                     })?;
                 }
 
-                Err(anyhow::anyhow!(
-                    "Peer '{}' is not online. Message saved for later delivery.",
-                    nickname
-                ))
+                Err(self.unreachable_error(nickname, false).into())
             }
         }
     }
@@ -687,15 +2284,24 @@ impl P2pClient {
     /// * `message` - The message text to send
     ///
     /// # Returns
-    /// Ok if sent successfully or stored for delivery
-    /// Error if peer not found
-    pub fn send_direct_message(&mut self, nickname: &str, message: String) -> Result<()> {
+    /// The id of the sent message, referenced by delivery and read receipts
+    ///
+    /// # Errors
+    /// The message is stored for later delivery, if persistence is enabled,
+    /// on these errors:
+    /// - `PeerNotDiscovered`: If no peer has the nickname
+    /// - `PeerUnreachable`: If the discovered peer is not connected
+    /// - `NotConnectedToNetwork`: If this node has no listeners and connections
+    pub fn send_direct_message(&mut self, nickname: &str, message: String) -> Result<String> {
+        self.ensure_running()?;
         // Validate inputs
         validation::validate_nickname(nickname)
             .map_err(|e| anyhow::anyhow!("Invalid nickname: {}", e))?;
         validation::validate_message(&message)
             .map_err(|e| anyhow::anyhow!("Invalid message: {}", e))?;
         let peer_id = self.peer_manager.get_peer_id_by_nickname(nickname);
+        // Same id is sent to the peer and used as the store key
+        let message_id = uuid::Uuid::new_v4().to_string();
 
         match peer_id {
             Some(peer_id) => {
@@ -704,13 +2310,17 @@ impl P2pClient {
                     if peer.connected {
                         // Peer is online, send immediately
                         info!("Sending direct message to {} ({})", nickname, peer_id);
-                        let request_id = self
-                            .swarm
-                            .behaviour_mut()
-                            .direct_msg
-                            .send_request(&peer_id, DirectMessage::Text { message });
+                        let request_id = self.swarm.behaviour_mut().direct_msg.send_request(
+                            &peer_id,
+                            DirectMessage::Text {
+                                message,
+                                message_id: message_id.clone(),
+                                disappear_after_secs: None,
+                                forwarded_from: None,
+                            },
+                        );
                         info!("Sent direct message request with ID: {:?}", request_id);
-                        Ok(())
+                        Ok(message_id)
                     } else {
                         // Peer is not connected, store message for later delivery
                         if let Some(message_store) = &self.message_store {
@@ -719,7 +2329,6 @@ impl P2pClient {
                             use gigi_store::MessageDirection;
 
                             // Create stored message
-                            let message_id = uuid::Uuid::new_v4().to_string();
                             let stored_msg = gigi_store::StoredMessage {
                                 id: message_id.clone(),
                                 msg_type: gigi_store::MessageType::Direct,
@@ -745,6 +2354,8 @@ impl P2pClient {
                                 last_sync_attempt: None,
 
                                 expires_at: Utc::now() + chrono::Duration::days(7), // Expire after 7 days
+                                disappear_after_secs: None,
+                                forwarded_from: None,
                             };
 
                             // Store message and add to offline queue
@@ -760,10 +2371,7 @@ impl P2pClient {
                             })?;
                         }
 
-                        Err(anyhow::anyhow!(
-                            "Peer '{}' is not online. Message saved for later delivery.",
-                            nickname
-                        ))
+                        Err(self.unreachable_error(nickname, true).into())
                     }
                 } else {
                     // Peer not found, store message for later delivery
@@ -773,7 +2381,6 @@ impl P2pClient {
                         use gigi_store::MessageDirection;
 
                         // Create stored message
-                        let message_id = uuid::Uuid::new_v4().to_string();
                         let stored_msg = gigi_store::StoredMessage {
                             id: message_id.clone(),
                             msg_type: gigi_store::MessageType::Direct,
@@ -799,6 +2406,8 @@ impl P2pClient {
                             last_sync_attempt: None,
 
                             expires_at: Utc::now() + chrono::Duration::days(7), // Expire after 7 days
+                            disappear_after_secs: None,
+                            forwarded_from: None,
                         };
 
                         // Store message and add to offline queue
@@ -814,10 +2423,7 @@ impl P2pClient {
                         })?;
                     }
 
-                    Err(anyhow::anyhow!(
-                        "Peer '{}' is not online. Message saved for later delivery.",
-                        nickname
-                    ))
+                    Err(self.unreachable_error(nickname, false).into())
                 }
             }
             None => {
@@ -828,7 +2434,6 @@ impl P2pClient {
                     use gigi_store::MessageDirection;
 
                     // Create stored message
-                    let message_id = uuid::Uuid::new_v4().to_string();
                     let stored_msg = gigi_store::StoredMessage {
                         id: message_id.clone(),
                         msg_type: gigi_store::MessageType::Direct,
@@ -854,6 +2459,8 @@ impl P2pClient {
                         last_sync_attempt: None,
 
                         expires_at: Utc::now() + chrono::Duration::days(7), // Expire after 7 days
+                        disappear_after_secs: None,
+                        forwarded_from: None,
                     };
 
                     // Store message and add to offline queue
@@ -869,10 +2476,7 @@ impl P2pClient {
                     })?;
                 }
 
-                Err(anyhow::anyhow!(
-                    "Peer '{}' is not online. Message saved for later delivery.",
-                    nickname
-                ))
+                Err(self.unreachable_error(nickname, false).into())
             }
         }
     }
@@ -892,15 +2496,19 @@ impl P2pClient {
     ///
     /// # Note
     /// This method sends a share code rather than the file data directly.
-    /// The recipient will download the file using the share code.
+    /// The recipient will download the file using the share code. With
+    /// `P2pConfig::skip_hash_for_trusted`, a file sent to a trusted peer is
+    /// shared without a whole-file hash.
     pub async fn send_direct_file(&mut self, nickname: &str, file_path: &Path) -> Result<()> {
-        let peer_id = self
-            .peer_manager
-            .get_peer_id_by_nickname(nickname)
-            .ok_or_else(|| P2pError::NicknameNotFound(nickname.to_string()))?;
+        self.ensure_running()?;
+        let peer_id = self.peer_id_for_nickname(nickname)?;
 
         // 1. Add file to file sharing system
-        let share_code = self.file_manager.share_file(file_path).await?;
+        let share_code = if self.skips_hash_for(&peer_id) {
+            self.file_manager.share_file_unhashed(file_path).await?
+        } else {
+            self.file_manager.share_file(file_path).await?
+        };
         let shared_file = self
             .file_manager
             .shared_files
@@ -920,6 +2528,7 @@ impl P2pClient {
                 filename: shared_file.info.name.clone(),
                 file_size: shared_file.info.size,
                 file_type,
+                forwarded_from: None,
             },
         );
 
@@ -944,10 +2553,7 @@ impl P2pClient {
         group_id: String,
         group_name: String,
     ) -> Result<()> {
-        let peer_id = self
-            .peer_manager
-            .get_peer_id_by_nickname(nickname)
-            .ok_or_else(|| P2pError::NicknameNotFound(nickname.to_string()))?;
+        let peer_id = self.peer_id_for_nickname(nickname)?;
 
         self.swarm.behaviour_mut().direct_msg.send_request(
             &peer_id,
@@ -961,6 +2567,249 @@ impl P2pClient {
         Ok(())
     }
 
+    /// Share a location with a peer
+    ///
+    /// The peer receives a `DirectLocationMessage` event. With persistence
+    /// enabled, the location is stored as `MessageContent::Location` on both
+    /// sides.
+    ///
+    /// # Arguments
+    /// * `nickname` - The recipient's display name
+    /// * `lat` - Latitude in degrees, -90 to 90
+    /// * `lon` - Longitude in degrees, -180 to 180
+    /// * `label` - Optional place name
+    ///
+    /// # Returns
+    /// The id of the sent message
+    /// Error `P2pError::InvalidLocation` if the coordinates are out of range
+    pub fn send_direct_location(
+        &mut self,
+        nickname: &str,
+        lat: f64,
+        lon: f64,
+        label: Option<&str>,
+    ) -> Result<String> {
+        validation::validate_location(lat, lon, label)?;
+        let peer_id = self.peer_id_for_nickname(nickname)?;
+        let message_id = uuid::Uuid::new_v4().to_string();
+        let label = label.map(str::to_string);
+
+        self.swarm.behaviour_mut().direct_msg.send_request(
+            &peer_id,
+            DirectMessage::Location {
+                message_id: message_id.clone(),
+                lat,
+                lon,
+                label: label.clone(),
+            },
+        );
+
+        let mut stored_msg = location_stored_message(
+            message_id.clone(),
+            peer_id,
+            gigi_store::MessageDirection::Sent,
+            self.local_nickname.clone(),
+            Location { lat, lon, label },
+        );
+        stored_msg.recipient_nickname = Some(nickname.to_string());
+        self.record_location_message(stored_msg, None);
+        Ok(message_id)
+    }
+
+    /// Forward a stored message to a peer or group
+    ///
+    /// The copy names the original author in `forwarded_from`, which the
+    /// receivers get with the message event and which is stored with the
+    /// sent copy. Forwarding a forwarded message keeps its original author.
+    ///
+    /// Files are forwarded by share code, so no data is sent until a
+    /// receiver downloads the file from this client: files shared by this
+    /// client keep their code, and downloaded files are shared from their
+    /// local copy.
+    ///
+    /// # Arguments
+    /// * `original_id` - Id of the stored message to forward
+    /// * `to` - The peer or group to forward it to
+    ///
+    /// # Returns
+    /// The id of the forwarded copy
+    ///
+    /// # Note
+    /// Requires persistence to be enabled. Only texts and file shares can be
+    /// forwarded.
+    pub async fn forward_message(
+        &mut self,
+        original_id: &str,
+        to: ForwardTarget,
+    ) -> Result<String> {
+        use gigi_store::{MessageContent, MessageDirection, MessageType};
+
+        let message_store = Arc::clone(
+            self.message_store
+                .as_ref()
+                .ok_or(P2pError::PersistenceNotEnabled)?,
+        );
+        let original = message_store
+            .get_message(original_id)
+            .await?
+            .ok_or_else(|| P2pError::InvalidInput(format!("Message not found: {}", original_id)))?;
+        let forwarded_from = match (original.forwarded_from, original.direction) {
+            (Some(author), _) => author,
+            (None, MessageDirection::Sent) => self.local_nickname.clone(),
+            (None, MessageDirection::Received) => original.sender_nickname,
+        };
+
+        // Content of the copy, and its text for peers and views without files
+        let (content, text) = match original.content {
+            MessageContent::Text { text } => (MessageContent::Text { text: text.clone() }, text),
+            MessageContent::FileShare {
+                share_code,
+                filename,
+                file_size,
+                file_type,
+                ..
+            }
+            | MessageContent::FileShareWithThumbnail {
+                share_code,
+                filename,
+                file_size,
+                file_type,
+                ..
+            } => {
+                let share_code = self.forwardable_share_code(&share_code).await?;
+                let text = format!("Shared file: {}", filename);
+                let content = MessageContent::FileShare {
+                    share_code,
+                    filename,
+                    file_size,
+                    file_type,
+                    from_peer: Some(self.swarm.local_peer_id().to_string()),
+                };
+                (content, text)
+            }
+            _ => {
+                return Err(P2pError::InvalidInput(
+                    "Only texts and file shares can be forwarded".into(),
+                )
+                .into())
+            }
+        };
+
+        let message_id = uuid::Uuid::new_v4().to_string();
+        let mut stored_msg = gigi_store::StoredMessage {
+            id: message_id.clone(),
+            msg_type: MessageType::Direct,
+            direction: MessageDirection::Sent,
+            content: content.clone(),
+            sender_nickname: self.local_nickname.clone(),
+            recipient_nickname: None,
+            group_name: None,
+            peer_id: self.swarm.local_peer_id().to_string(),
+            timestamp: chrono::Utc::now(),
+            created_at: chrono::Utc::now(),
+            delivered: false,
+            delivered_at: None,
+            read: false,
+            read_at: None,
+            sync_status: gigi_store::SyncStatus::Pending,
+            sync_attempts: 0,
+            last_sync_attempt: None,
+            expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+            disappear_after_secs: None,
+            forwarded_from: Some(forwarded_from.clone()),
+        };
+
+        match to {
+            ForwardTarget::Peer(nickname) => {
+                let peer_id = self.peer_id_for_nickname(&nickname)?;
+                let request = match content {
+                    MessageContent::FileShare {
+                        share_code,
+                        filename,
+                        file_size,
+                        file_type,
+                        ..
+                    } => DirectMessage::FileShare {
+                        share_code,
+                        filename,
+                        file_size,
+                        file_type,
+                        forwarded_from: Some(forwarded_from),
+                    },
+                    _ => DirectMessage::Text {
+                        message: text,
+                        message_id: message_id.clone(),
+                        disappear_after_secs: None,
+                        forwarded_from: Some(forwarded_from),
+                    },
+                };
+                self.swarm
+                    .behaviour_mut()
+                    .direct_msg
+                    .send_request(&peer_id, request);
+                stored_msg.recipient_nickname = Some(nickname);
+                stored_msg.peer_id = peer_id.to_string();
+            }
+            ForwardTarget::Group(group_name) => {
+                validation::validate_group_name(&group_name)
+                    .map_err(|e| P2pError::InvalidInput(format!("Invalid group name: {}", e)))?;
+                let mut group_message =
+                    GroupManager::new_message(message_id.clone(), text, &self.local_nickname);
+                if let MessageContent::FileShare {
+                    share_code,
+                    filename,
+                    file_size,
+                    file_type,
+                    ..
+                } = content
+                {
+                    group_message.has_file_share = true;
+                    group_message.share_code = Some(share_code);
+                    group_message.filename = Some(filename);
+                    group_message.file_size = Some(file_size);
+                    group_message.file_type = Some(file_type);
+                }
+                group_message.forwarded_from = Some(forwarded_from);
+                self.group_manager
+                    .send_message(&mut self.swarm, &group_name, group_message)?;
+                stored_msg.msg_type = MessageType::Group;
+                stored_msg.group_name = Some(group_name);
+            }
+        }
+
+        message_store
+            .store_message(stored_msg)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to store forwarded message: {}", e))?;
+        Ok(message_id)
+    }
+
+    /// Share code a forwarded file share can use without sending the file
+    ///
+    /// Receivers of the forward download from this client, so the code must
+    /// be one this client serves.
+    async fn forwardable_share_code(&mut self, share_code: &str) -> Result<String> {
+        if self.file_manager.shared_files.contains_key(share_code) {
+            return Ok(share_code.to_string());
+        }
+        let downloaded = match &self.file_sharing_store {
+            Some(store) => store
+                .list_downloaded_files()
+                .await?
+                .into_iter()
+                .find(|file| file.share_code == share_code && Path::new(&file.file_path).is_file()),
+            None => None,
+        };
+        match downloaded {
+            Some(file) => self.share_file(Path::new(&file.file_path)).await,
+            None => Err(P2pError::InvalidShareCode(format!(
+                "{} is neither shared nor downloaded here",
+                share_code
+            ))
+            .into()),
+        }
+    }
+
     // ===== Group Messaging Methods =====
     // These methods handle GossipSub-based group communication
 
@@ -985,6 +2834,44 @@ impl P2pClient {
             .join_group(&mut self.swarm, group_name, &mut self.event_sender)
     }
 
+    /// Join a group by name and secret salt
+    ///
+    /// The group id is derived from both with
+    /// `gigi_auth::key_derivation::derive_salted_group_id`, so groups that
+    /// share a name but not a salt use different topics. Messages and other
+    /// group methods take the returned id; the name is kept as the group's
+    /// display name.
+    ///
+    /// # Arguments
+    /// * `group_name` - The name shown to users
+    /// * `salt` - The secret salt shared by the group's members
+    ///
+    /// # Returns
+    /// The derived group id
+    pub fn join_group_with_salt(&mut self, group_name: &str, salt: &str) -> Result<String> {
+        validation::validate_group_name(group_name)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid group name: {}", e)))?;
+        if salt.is_empty() {
+            return Err(P2pError::InvalidInput("Group salt cannot be empty".into()).into());
+        }
+        let group_id = gigi_auth::key_derivation::derive_salted_group_id(group_name, salt)?;
+        self.group_manager.join_group_as(
+            &mut self.swarm,
+            &group_id,
+            group_name,
+            &mut self.event_sender,
+        )?;
+        Ok(group_id)
+    }
+
+    /// Get the display name of a joined group
+    ///
+    /// # Arguments
+    /// * `group_id` - The group id passed to or returned by a join
+    pub fn group_display_name(&self, group_id: &str) -> Option<&str> {
+        self.group_manager.display_name(group_id)
+    }
+
     /// Get the number of known members in a group
     ///
     /// Returns the count of peers we've seen participate in the group.
@@ -992,6 +2879,41 @@ impl P2pClient {
         self.group_manager.get_group_member_count(group_name)
     }
 
+    /// Get the peers currently subscribed to a group
+    ///
+    /// The roster follows gossipsub subscriptions and disconnects and emits
+    /// `GroupMembershipChanged` when it changes. Peers that have not told us
+    /// their nickname yet are listed under their placeholder name.
+    ///
+    /// # Arguments
+    /// * `group_name` - The name of the joined group
+    pub fn group_members(&self, group_name: &str) -> Result<Vec<PeerInfo>> {
+        let members = self.group_manager.online_members(group_name)?;
+        Ok(members
+            .into_iter()
+            .map(|peer_id| {
+                self.peer_manager
+                    .get_peer(&peer_id)
+                    .cloned()
+                    .unwrap_or_else(|| PeerInfo {
+                        peer_id,
+                        nickname: self.peer_manager.display_name(&peer_id),
+                        addresses: Vec::new(),
+                        last_seen: std::time::Instant::now(),
+                        connected: self.swarm.is_connected(&peer_id),
+                        status: None,
+                    })
+            })
+            .collect())
+    }
+
+    /// Get the number of messages queued for a group until a peer subscribes
+    ///
+    /// Always `0` unless `P2pConfig::group_message_buffer` is set.
+    pub fn pending_group_message_count(&self, group_name: &str) -> usize {
+        self.group_manager.pending_message_count(group_name)
+    }
+
     /// Leave a group
     ///
     /// Unsubscribes from a GossipSub group and stops receiving group messages.
@@ -1015,8 +2937,13 @@ impl P2pClient {
     /// * `message` - The message text to send
     ///
     /// # Returns
-    /// Ok on success
-    pub fn send_group_message(&mut self, group_name: &str, message: String) -> Result<()> {
+    /// Whether the message was sent or queued for later delivery, with its id
+    pub fn send_group_message(
+        &mut self,
+        group_name: &str,
+        message: String,
+    ) -> Result<GroupSendStatus> {
+        self.ensure_running()?;
         // Validate inputs
         validation::validate_group_name(group_name)
             .map_err(|e| P2pError::InvalidInput(format!("Invalid group name: {}", e)))?;
@@ -1030,162 +2957,1233 @@ impl P2pClient {
         )
     }
 
-    /// Send file to group using file sharing
+    /// Create a poll in a group
     ///
-    /// Shares a file with all members of a group.
-    /// The file is registered and the share code is broadcast to the group.
+    /// Members receive a `PollUpdated` event and can vote with
+    /// `vote_group_poll`. With persistence enabled, the poll and all votes
+    /// are stored and `get_poll_results` aggregates them.
     ///
     /// # Arguments
     /// * `group_name` - The name of the group
-    /// * `file_path` - Path to the file to share
+    /// * `question` - The poll question
+    /// * `options` - 2 to 16 options to vote for
     ///
     /// # Returns
-    /// Ok on success
-    pub async fn send_group_file(&mut self, group_name: &str, file_path: &Path) -> Result<()> {
-        self.group_manager
-            .send_group_file(
-                &mut self.swarm,
-                group_name,
-                file_path,
-                &mut self.file_manager,
-                &self.local_nickname,
-            )
-            .await
+    /// Whether the poll was sent or queued; its message id is the poll id
+    pub fn send_group_poll(
+        &mut self,
+        group_name: &str,
+        question: &str,
+        options: Vec<String>,
+    ) -> Result<GroupSendStatus> {
+        validation::validate_group_name(group_name)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid group name: {}", e)))?;
+        validation::validate_poll(question, &options)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid poll: {}", e)))?;
+        let poll_id = uuid::Uuid::new_v4().to_string();
+        let poll = PollMessage::Poll {
+            id: poll_id.clone(),
+            question: question.to_string(),
+            options,
+        };
+        self.send_poll_message(group_name, poll_id, poll, format!("Poll: {}", question))
     }
 
-    // ===== File Sharing Methods =====
-    // These methods handle file sharing with chunked transfers and hash verification
-
-    /// Share a file
+    /// Vote in a group poll
     ///
-    /// Registers a file for sharing and generates a share code.
-    /// The file can then be downloaded by peers using the share code.
+    /// Voting again replaces the previous vote.
     ///
     /// # Arguments
-    /// * `file_path` - Path to the file to share
+    /// * `group_name` - The group the poll was created in
+    /// * `poll_id` - Id of the poll
+    /// * `option_index` - Index of the chosen option
     ///
     /// # Returns
-    /// The share code that can be used to download this file
-    pub async fn share_file(&mut self, file_path: &Path) -> Result<String> {
-        self.file_manager.share_file(file_path).await
+    /// Whether the vote was sent or queued, with its id
+    pub fn vote_group_poll(
+        &mut self,
+        group_name: &str,
+        poll_id: &str,
+        option_index: u32,
+    ) -> Result<GroupSendStatus> {
+        validation::validate_group_name(group_name)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid group name: {}", e)))?;
+        let poll = PollMessage::PollVote {
+            poll_id: poll_id.to_string(),
+            option_index,
+        };
+        let message_id = uuid::Uuid::new_v4().to_string();
+        self.send_poll_message(group_name, message_id, poll, "Voted in poll".to_string())
     }
 
-    /// Set the chunk reader callback for URI-based files
-    ///
-    /// Sets a callback function for reading chunks from mobile content URIs.
-    /// This is required for sharing files on Android (content://) and iOS (file://).
+    /// Publish a poll or vote and record it like a received one
+    fn send_poll_message(
+        &mut self,
+        group_name: &str,
+        message_id: String,
+        poll: PollMessage,
+        content: String,
+    ) -> Result<GroupSendStatus> {
+        let status = self.group_manager.send_group_poll(
+            &mut self.swarm,
+            group_name,
+            message_id.clone(),
+            poll.clone(),
+            content,
+            &self.local_nickname,
+        )?;
+        self.record_poll_message(
+            group_name.to_string(),
+            *self.swarm.local_peer_id(),
+            self.local_nickname.clone(),
+            message_id,
+            chrono::Utc::now(),
+            poll,
+        );
+        Ok(status)
+    }
+
+    /// Get the vote counts of a poll
     ///
     /// # Arguments
-    /// * `reader` - A callback that reads file chunks by offset and size
+    /// * `poll_id` - Id of the poll
     ///
-    /// # Example
-    /// ```rust,ignore
-    /// client.set_chunk_reader(Box::new(|path, offset, size| {
-    ///     // Platform-specific file reading implementation
-    ///     Ok(data)
-    /// }));
-    /// ```
-    pub fn set_chunk_reader(&mut self, reader: super::file_sharing::FileChunkReader) {
-        self.file_manager.set_chunk_reader(reader.clone());
-        self.download_manager.set_chunk_reader(reader);
+    /// # Returns
+    /// The results, or `None` if the poll has not been received
+    ///
+    /// # Note
+    /// Requires persistence to be enabled
+    pub async fn get_poll_results(&self, poll_id: &str) -> Result<Option<PollResults>> {
+        let message_store = self
+            .message_store
+            .as_ref()
+            .ok_or(P2pError::PersistenceNotEnabled)?;
+        message_store
+            .get_poll_results(poll_id)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get poll results: {}", e))
     }
 
-    /// Share a content URI (Android content:// or iOS file://)
+    /// Store a poll or vote, if persistence is enabled, and emit `PollUpdated`
     ///
-    /// Registers a mobile content URI for sharing.
-    /// This is used on mobile platforms where files are accessed through URIs.
+    /// With persistence the event is sent once the message is stored, with
+    /// the recomputed results.
+    pub(super) fn record_poll_message(
+        &self,
+        group: String,
+        from: PeerId,
+        from_nickname: String,
+        message_id: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        poll: PollMessage,
+    ) {
+        let poll_id = poll.poll_id().to_string();
+        let Some(message_store) = &self.message_store else {
+            self.send_event(P2pEvent::PollUpdated {
+                from,
+                from_nickname,
+                group,
+                poll_id,
+                results: None,
+            });
+            return;
+        };
+
+        use gigi_store::{MessageContent, MessageDirection, MessageType, SyncStatus};
+        let (id, content) = match poll {
+            PollMessage::Poll {
+                id,
+                question,
+                options,
+            } => (
+                id.clone(),
+                MessageContent::Poll {
+                    poll_id: id,
+                    question,
+                    options,
+                },
+            ),
+            PollMessage::PollVote {
+                poll_id,
+                option_index,
+            } => (
+                message_id,
+                MessageContent::PollVote {
+                    poll_id,
+                    option_index,
+                },
+            ),
+        };
+        let direction = if from == *self.swarm.local_peer_id() {
+            MessageDirection::Sent
+        } else {
+            MessageDirection::Received
+        };
+        let now = chrono::Utc::now();
+        let stored_msg = gigi_store::StoredMessage {
+            id,
+            msg_type: MessageType::Group,
+            direction,
+            content,
+            sender_nickname: from_nickname.clone(),
+            recipient_nickname: None,
+            group_name: Some(group.clone()),
+            peer_id: from.to_string(),
+            timestamp,
+            created_at: now,
+            delivered: false,
+            delivered_at: None,
+            read: false,
+            read_at: None,
+            sync_status: SyncStatus::Pending,
+            sync_attempts: 0,
+            last_sync_attempt: None,
+            expires_at: now + chrono::Duration::days(7),
+            disappear_after_secs: None,
+            forwarded_from: None,
+        };
+
+        let message_store = Arc::clone(message_store);
+        let event_sender = self.event_sender.clone();
+        self.spawn_store_write(async move {
+            if let Err(e) = message_store.store_message(stored_msg).await {
+                report_storage_error(&event_sender, "Failed to store poll message", e);
+                return;
+            }
+            let results = match message_store.get_poll_results(&poll_id).await {
+                Ok(results) => results,
+                Err(e) => {
+                    report_storage_error(&event_sender, "Failed to get poll results", e);
+                    None
+                }
+            };
+            let _ = event_sender.unbounded_send(P2pEvent::PollUpdated {
+                from,
+                from_nickname,
+                group,
+                poll_id,
+                results,
+            });
+        });
+    }
+
+    /// Share a location with a group
+    ///
+    /// Members receive a `GroupLocationMessage` event; peers that do not
+    /// know locations see a plain-text fallback.
     ///
     /// # Arguments
-    /// * `uri` - The content URI to share (e.g., "content://...")
-    /// * `name` - The display name for the file
-    /// * `size` - The file size in bytes
+    /// * `group_name` - The name of the group
+    /// * `lat` - Latitude in degrees, -90 to 90
+    /// * `lon` - Longitude in degrees, -180 to 180
+    /// * `label` - Optional place name
     ///
     /// # Returns
-    /// The share code that can be used to download this file
-    ///
-    /// # Note
-    /// Requires `set_chunk_reader` to be called first to provide file reading capability.
-    pub async fn share_content_uri(&mut self, uri: &str, name: &str, size: u64) -> Result<String> {
-        // Validate inputs
-        validation::validate_uri(uri).map_err(|e| anyhow::anyhow!("Invalid URI: {}", e))?;
+    /// Whether the message was sent or queued, with its id
+    /// Error `P2pError::InvalidLocation` if the coordinates are out of range
+    pub fn send_group_location(
+        &mut self,
+        group_name: &str,
+        lat: f64,
+        lon: f64,
+        label: Option<&str>,
+    ) -> Result<GroupSendStatus> {
+        validation::validate_group_name(group_name)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid group name: {}", e)))?;
+        validation::validate_location(lat, lon, label)?;
+        let location = Location {
+            lat,
+            lon,
+            label: label.map(str::to_string),
+        };
+        let message_id = uuid::Uuid::new_v4().to_string();
+        let status = self.group_manager.send_group_location(
+            &mut self.swarm,
+            group_name,
+            message_id.clone(),
+            location.clone(),
+            &self.local_nickname,
+        )?;
+
+        let mut stored_msg = location_stored_message(
+            message_id,
+            *self.swarm.local_peer_id(),
+            gigi_store::MessageDirection::Sent,
+            self.local_nickname.clone(),
+            location,
+        );
+        stored_msg.msg_type = gigi_store::MessageType::Group;
+        stored_msg.group_name = Some(group_name.to_string());
+        self.record_location_message(stored_msg, None);
+        Ok(status)
+    }
+
+    /// Store a shared location, if persistence is enabled, then send `event`
+    ///
+    /// Without persistence the event is sent immediately.
+    pub(super) fn record_location_message(
+        &self,
+        stored_msg: gigi_store::StoredMessage,
+        event: Option<P2pEvent>,
+    ) {
+        let Some(message_store) = &self.message_store else {
+            if let Some(event) = event {
+                self.send_event(event);
+            }
+            return;
+        };
+
+        let message_store = Arc::clone(message_store);
+        let event_sender = self.event_sender.clone();
+        self.spawn_store_write(async move {
+            if let Err(e) = message_store.store_message(stored_msg).await {
+                report_storage_error(&event_sender, "Failed to store location message", e);
+                return;
+            }
+            if let Some(event) = event {
+                let _ = event_sender.unbounded_send(event);
+            }
+        });
+    }
+
+    /// Send file to group using file sharing
+    ///
+    /// Shares a file with all members of a group.
+    /// The file is registered and the share code is broadcast to the group.
+    /// Downloads by the members online now, and by members who request it
+    /// later, are tracked: see `group_file_distribution_status`. A
+    /// `GroupFileDownloadedByMember` event follows each completed download.
+    ///
+    /// # Arguments
+    /// * `group_name` - The name of the group
+    /// * `file_path` - Path to the file to share
+    ///
+    /// # Returns
+    /// The share code of the file
+    pub async fn send_group_file(&mut self, group_name: &str, file_path: &Path) -> Result<String> {
+        self.ensure_running()?;
+        let share_code = self
+            .group_manager
+            .send_group_file(
+                &mut self.swarm,
+                group_name,
+                file_path,
+                &mut self.file_manager,
+                &self.local_nickname,
+            )
+            .await?;
+        let roster = self.group_manager.online_members(group_name)?;
+        let filename = self
+            .file_manager
+            .shared_files
+            .get(&share_code)
+            .map(|file| file.info.name.clone())
+            .unwrap_or_default();
+        self.group_distributions
+            .start(&share_code, group_name, &filename, &roster);
+        Ok(share_code)
+    }
+
+    /// Which members downloaded a file sent to a group
+    ///
+    /// # Arguments
+    /// * `share_code` - Share code returned by `send_group_file`
+    ///
+    /// # Returns
+    /// The status of every tracked member, or `None` if the file was not
+    /// sent to a group or was unshared since
+    pub fn group_file_distribution_status(
+        &self,
+        share_code: &str,
+    ) -> Option<GroupFileDistributionStatus> {
+        self.group_distributions.status(share_code)
+    }
+
+    // ===== File Sharing Methods =====
+    // These methods handle file sharing with chunked transfers and hash verification
+
+    /// Share a file
+    ///
+    /// Registers a file for sharing and generates a share code.
+    /// The file can then be downloaded by peers using the share code.
+    /// Connected peers are sent the new list of shared files and emit
+    /// `RemoteFilesUpdated`.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to share
+    ///
+    /// # Returns
+    /// The share code that can be used to download this file
+    pub async fn share_file(&mut self, file_path: &Path) -> Result<String> {
+        self.ensure_running()?;
+        let share_code = self.file_manager.share_file(file_path).await?;
+        self.announce_shared_files();
+        Ok(share_code)
+    }
+
+    /// Share a file without computing its whole-file hash
+    ///
+    /// For transfers between a user's own devices, where hashing a large
+    /// file costs more than it protects. Recipients do not verify the whole
+    /// file, only its chunks, and refuse it with `strict_hash` unless they
+    /// trust this peer and enabled `P2pConfig::skip_hash_for_trusted`.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to share
+    ///
+    /// # Returns
+    /// The share code that can be used to download this file
+    pub async fn share_file_unhashed(&mut self, file_path: &Path) -> Result<String> {
+        self.ensure_running()?;
+        let share_code = self.file_manager.share_file_unhashed(file_path).await?;
+        self.announce_shared_files();
+        Ok(share_code)
+    }
+
+    /// Share every file in a directory and its subdirectories
+    ///
+    /// Hashing a large directory takes a while; cancel `cancel` (or a clone)
+    /// from another task to stop before the next file. Files shared before
+    /// that stay shared. Each file's `FileInfo::relative_path` records where
+    /// it sits in the directory, so downloads recreate its folders.
+    ///
+    /// # Arguments
+    /// * `directory` - Directory to share
+    /// * `cancel` - Token checked between files
+    ///
+    /// # Returns
+    /// The share codes created, files that failed, and whether it was cancelled
+    pub async fn share_directory(
+        &mut self,
+        directory: &Path,
+        cancel: &ShareCancelToken,
+    ) -> Result<DirectoryShare> {
+        self.ensure_running()?;
+        let share = self.file_manager.share_directory(directory, cancel).await?;
+        if !share.shared.is_empty() {
+            self.announce_shared_files();
+        }
+        Ok(share)
+    }
+
+    /// Set the chunk reader callback for URI-based files
+    ///
+    /// Sets a callback function for reading chunks from mobile content URIs.
+    /// This is required for sharing files on Android (content://) and iOS (file://).
+    ///
+    /// # Arguments
+    /// * `reader` - A callback that reads file chunks by offset and size
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// client.set_chunk_reader(Box::new(|path, offset, size| {
+    ///     // Platform-specific file reading implementation
+    ///     Ok(data)
+    /// }));
+    /// ```
+    pub fn set_chunk_reader(&mut self, reader: super::file_sharing::FileChunkReader) {
+        self.file_manager.set_chunk_reader(reader.clone());
+        self.download_manager.set_chunk_reader(reader);
+    }
+
+    /// Share a content URI (Android content:// or iOS file://)
+    ///
+    /// Registers a mobile content URI for sharing.
+    /// This is used on mobile platforms where files are accessed through URIs.
+    ///
+    /// # Arguments
+    /// * `uri` - The content URI to share (e.g., "content://...")
+    /// * `name` - The display name for the file
+    /// * `size` - The file size in bytes
+    ///
+    /// # Returns
+    /// The share code that can be used to download this file
+    ///
+    /// # Note
+    /// Requires `set_chunk_reader` to be called first to provide file reading capability.
+    pub async fn share_content_uri(&mut self, uri: &str, name: &str, size: u64) -> Result<String> {
+        self.ensure_running()?;
+        // Validate inputs
+        validation::validate_uri(uri).map_err(|e| anyhow::anyhow!("Invalid URI: {}", e))?;
         validation::validate_nickname(name)
             .map_err(|e| anyhow::anyhow!("Invalid file name: {}", e))?;
         validation::validate_file_size(size)
             .map_err(|e| anyhow::anyhow!("Invalid file size: {}", e))?;
 
-        self.file_manager.share_content_uri(uri, name, size).await
+        let share_code = self.file_manager.share_content_uri(uri, name, size).await?;
+        self.announce_shared_files();
+        Ok(share_code)
+    }
+
+    /// List shared files
+    ///
+    /// Returns information about all files currently shared by this peer.
+    ///
+    /// # Returns
+    /// Vector of SharedFile references
+    pub fn list_shared_files(&self) -> Vec<&crate::events::SharedFile> {
+        self.file_manager.list_shared_files()
+    }
+
+    /// Unshare a file by share code
+    ///
+    /// Stops sharing a file and revokes the share code.
+    /// Peers will no longer be able to download the file, and connected
+    /// peers are sent the new list of shared files.
+    ///
+    /// # Arguments
+    /// * `share_code` - The share code of the file to unshare
+    ///
+    /// # Returns
+    /// Ok on success
+    pub fn unshare_file(&mut self, share_code: &str) -> Result<()> {
+        let shared_file = self.file_manager.shared_files.get(share_code);
+        if let Some(shared_file) = shared_file {
+            // Send event that file is revoked
+            self.send_event(P2pEvent::FileRevoked {
+                file_id: shared_file.info.id.clone(),
+            });
+
+            // Save updated shared files
+            self.file_manager.unshare_file(share_code)?;
+            self.revoked_share_codes.put(share_code.to_string(), ());
+            if let Some(prefetcher) = &self.chunk_prefetcher {
+                prefetcher.forget_file(share_code);
+            }
+            self.chunk_cache.forget_file(share_code);
+            self.group_distributions.forget(share_code);
+            self.announce_shared_files();
+        } else {
+            return Err(P2pError::InvalidShareCode(share_code.to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// Unshare every file matching a predicate
+    ///
+    /// Emits a `FileRevoked` event for each unshared file. The files are
+    /// removed from the store in one transaction first; if that fails, the
+    /// error is returned and nothing is unshared.
+    ///
+    /// # Arguments
+    /// * `predicate` - Returns `true` for files that should be unshared
+    ///
+    /// # Returns
+    /// The share codes of all unshared files
+    pub async fn unshare_where(
+        &mut self,
+        predicate: impl Fn(&crate::events::SharedFile) -> bool,
+    ) -> Result<Vec<String>> {
+        // Evaluate the predicate once so the revoked events match the removal
+        let matched: HashMap<String, String> = self
+            .file_manager
+            .shared_files
+            .values()
+            .filter(|file| predicate(file))
+            .map(|file| (file.share_code.clone(), file.info.id.clone()))
+            .collect();
+
+        let share_codes = self
+            .file_manager
+            .unshare_where(|file| matched.contains_key(&file.share_code))
+            .await?;
+        for code in &share_codes {
+            self.revoked_share_codes.put(code.clone(), ());
+        }
+        if let Some(prefetcher) = &self.chunk_prefetcher {
+            share_codes
+                .iter()
+                .for_each(|code| prefetcher.forget_file(code));
+        }
+        for code in &share_codes {
+            self.chunk_cache.forget_file(code);
+            self.group_distributions.forget(code);
+        }
+        for code in &share_codes {
+            if let Some(file_id) = matched.get(code) {
+                self.send_event(P2pEvent::FileRevoked {
+                    file_id: file_id.clone(),
+                });
+            }
+        }
+        if !share_codes.is_empty() {
+            self.announce_shared_files();
+        }
+        Ok(share_codes)
+    }
+
+    /// Files a connected peer last announced or listed as shared
+    ///
+    /// Kept current by the peer's announcements whenever it shares or
+    /// unshares files, and by `FileListReceived` responses to `ListFiles`.
+    ///
+    /// # Returns
+    /// The peer's shared files, or `None` if it has announced none since
+    /// connecting
+    pub fn remote_files(&self, peer_id: &PeerId) -> Option<&[crate::events::FileInfo]> {
+        self.remote_files.get(peer_id).map(Vec::as_slice)
+    }
+
+    /// Push the current list of shared files to every connected peer
+    pub(super) fn announce_shared_files(&mut self) {
+        let files = self.announced_files();
+        let peers: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+        for peer in peers {
+            self.swarm
+                .behaviour_mut()
+                .file_sharing
+                .send_request(&peer, FileSharingRequest::FilesChanged(files.clone()));
+        }
+    }
+
+    /// Push the current list of shared files to one peer
+    ///
+    /// The peer emits `RemoteFilesUpdated` with the list. Called for each
+    /// newly connected peer with `P2pConfig::announce_shared_files_on_connect`;
+    /// call it directly to announce to chosen peers only.
+    ///
+    /// # Arguments
+    /// * `peer_id` - The peer to announce to; must be connected
+    pub fn announce_shared_files_to(&mut self, peer_id: PeerId) {
+        let files = self.announced_files();
+        self.swarm
+            .behaviour_mut()
+            .file_sharing
+            .send_request(&peer_id, FileSharingRequest::FilesChanged(files));
+    }
+
+    /// Info of every shared file that is not revoked
+    pub(super) fn announced_files(&self) -> Vec<crate::events::FileInfo> {
+        self.file_manager
+            .shared_files
+            .values()
+            .filter(|f| !f.revoked)
+            .map(|f| f.info.clone())
+            .collect()
+    }
+
+    // ===== Download Methods =====
+    // These methods handle downloading files from peers with progress tracking
+
+    /// Download file from peer
+    ///
+    /// Initiates a file download from a peer using a share code.
+    /// The download is tracked and progress events are emitted.
+    ///
+    /// # Arguments
+    /// * `nickname` - The peer sharing the file
+    /// * `share_code` - The share code of the file to download
+    ///
+    /// # Returns
+    /// The download_id for tracking this download. If the same file is
+    /// already being downloaded from this peer, its download_id is returned
+    /// instead of starting a second transfer.
+    ///
+    /// # Download Flow
+    /// 1. Request file info from peer
+    /// 2. Download chunks sequentially
+    /// 3. Verify each chunk's BLAKE3 hash
+    /// 4. Assemble chunks into final file
+    /// 5. Verify final SHA256 hash
+    ///
+    /// If the peer stops responding mid-download, another connected peer
+    /// known to share the same content takes over the missing chunks; the
+    /// download only fails when there is none.
+    ///
+    /// # Events
+    /// The client will emit `P2pEvent` updates for download progress.
+    pub fn download_file(&mut self, nickname: &str, share_code: &str) -> Result<String> {
+        self.download_file_with(DownloadRequest::new(share_code).with_nickname(nickname))
+    }
+
+    /// Download a file with the hints of a `DownloadRequest`
+    ///
+    /// The sharer is `request.peer_id`, else the peer with
+    /// `request.nickname`, else the peer a share message with the code came
+    /// from. A file whose size or hash differs from the expected one fails
+    /// with `DownloadFailureReason::UnexpectedFile` before any chunk is
+    /// requested; a file shared without a hash is verified against the
+    /// expected hash instead.
+    ///
+    /// # Returns
+    /// The download_id for tracking this download. If the same file is
+    /// already being downloaded from the peer, its download_id is returned
+    /// and the hints of this request are ignored.
+    ///
+    /// # Errors
+    /// - `InvalidInput`: If the share code, nickname or expected hash is malformed
+    /// - `PeerNotDiscovered`: If no peer has the nickname
+    /// - `PeerUnreachable`: If the discovered sharer is not connected
+    /// - `NotConnectedToNetwork`: If this node has no listeners and connections
+    /// - `InvalidShareCode`: If neither a peer nor a share message names the sharer
+    pub fn download_file_with(&mut self, mut request: DownloadRequest) -> Result<String> {
+        self.ensure_running()?;
+        // Validate inputs
+        if let Some(nickname) = &request.nickname {
+            validation::validate_nickname(nickname)
+                .map_err(|e| P2pError::InvalidInput(format!("Invalid nickname: {}", e)))?;
+        }
+        validation::validate_share_code(&request.share_code)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid share code: {}", e)))?;
+        if let Some(hash) = &mut request.expected_hash {
+            *hash = hash.trim().to_ascii_lowercase();
+            if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(
+                    P2pError::InvalidInput(format!("Invalid expected hash: {}", hash)).into(),
+                );
+            }
+        }
+
+        let (peer_id, nickname) = match (request.peer_id, &request.nickname) {
+            (Some(peer_id), nickname) => (
+                peer_id,
+                nickname
+                    .clone()
+                    .unwrap_or_else(|| self.peer_manager.display_name(&peer_id)),
+            ),
+            (None, Some(nickname)) => (self.peer_id_for_nickname(nickname)?, nickname.clone()),
+            (None, None) => {
+                let peer_id = self.share_source(&request.share_code).ok_or_else(|| {
+                    P2pError::InvalidShareCode(format!(
+                        "No known source for {}",
+                        request.share_code
+                    ))
+                })?;
+                (peer_id, self.peer_manager.display_name(&peer_id))
+            }
+        };
+        if self
+            .peer_manager
+            .get_peer(&peer_id)
+            .is_some_and(|peer| !peer.connected)
+        {
+            return Err(self.unreachable_error(&nickname, true).into());
+        }
+
+        Ok(self.start_download_from(peer_id, &nickname, request))
+    }
+
+    /// Request a byte range of a file shared by a peer, e.g. to seek in a
+    /// remote video without downloading all of it
+    ///
+    /// Unlike downloads, the bytes are not verified against a hash and not
+    /// written to disk.
+    ///
+    /// # Arguments
+    /// * `nickname` - Nickname of the sharer
+    /// * `share_code` - Share code of the file
+    /// * `start` - Offset of the first byte
+    /// * `len` - Number of bytes, 1 to `MAX_RANGE_SIZE`
+    ///
+    /// # Returns
+    /// The request_id of the range request
+    ///
+    /// # Events
+    /// `FileRangeReceived` with the bytes, fewer than `len` at the end of
+    /// the file, or `FileRangeFailed`.
+    ///
+    /// # Errors
+    /// - `InvalidInput`: If the share code or nickname is malformed, `len`
+    ///   is out of bounds, or the peer's version lacks byte range requests
+    /// - `PeerNotDiscovered`: If no peer has the nickname
+    /// - `PeerUnreachable`: If the discovered sharer is not connected
+    /// - `NotConnectedToNetwork`: If this node has no listeners and connections
+    pub fn request_range(
+        &mut self,
+        nickname: &str,
+        share_code: &str,
+        start: u64,
+        len: u64,
+    ) -> Result<String> {
+        self.ensure_running()?;
+        validation::validate_nickname(nickname)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid nickname: {}", e)))?;
+        validation::validate_share_code(share_code)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid share code: {}", e)))?;
+        if len == 0 || len > MAX_RANGE_SIZE {
+            return Err(P2pError::InvalidInput(format!(
+                "Range length must be 1 to {} bytes, got {}",
+                MAX_RANGE_SIZE, len
+            ))
+            .into());
+        }
+
+        let peer_id = self.peer_id_for_nickname(nickname)?;
+        if !self.peer_manager.is_connected(&peer_id) {
+            return Err(self.unreachable_error(nickname, true).into());
+        }
+        let protocols = self.peer_protocols(&peer_id);
+        let serves_ranges = protocols.iter().any(|protocol| {
+            FileTransferVersion::from_protocol(protocol)
+                .is_some_and(|version| version.capabilities().byte_ranges)
+        });
+        if !protocols.is_empty() && !serves_ranges {
+            return Err(P2pError::InvalidInput(format!(
+                "Peer '{}' does not support byte range requests",
+                nickname
+            ))
+            .into());
+        }
+
+        let request_id = self
+            .swarm
+            .behaviour_mut()
+            .file_sharing
+            .send_request(
+                &peer_id,
+                FileSharingRequest::GetRange(share_code.to_string(), start, len),
+            )
+            .to_string();
+        self.range_requests
+            .insert(request_id.clone(), share_code.to_string());
+        Ok(request_id)
+    }
+
+    /// Download a received file from the peer that shared it
+    ///
+    /// Routes the request to the peer a `DirectFileShareMessage` or
+    /// `GroupFileShareMessage` with this share code came from, so the download
+    /// works even if that peer changed its nickname since sharing.
+    ///
+    /// # Arguments
+    /// * `share_code` - Share code from a received file share message
+    ///
+    /// # Returns
+    /// The download_id for tracking this download, or `InvalidShareCode` if
+    /// no share message with this code was received
+    pub fn download_file_by_code(&mut self, share_code: &str) -> Result<String> {
+        self.download_file_with(DownloadRequest::new(share_code))
+    }
+
+    /// Request one chunk of an in-progress download again
+    ///
+    /// Useful to repair a download by hand, e.g. after a chunk failed
+    /// verification. A chunk that was already received is re-verified
+    /// against the hash it arrived with and only requested again if it no
+    /// longer matches. The chunk is written and verified like any other.
+    ///
+    /// # Arguments
+    /// * `file_id` - Share code of the file being downloaded
+    /// * `chunk_index` - Index of the chunk to request
+    ///
+    /// # Returns
+    /// Whether a request was sent; `false` if the chunk is already intact
+    pub fn request_chunk(&mut self, file_id: &str, chunk_index: usize) -> Result<bool> {
+        let download_id = self
+            .download_manager
+            .get_downloading_id_by_file_id(file_id)
+            .ok_or_else(|| {
+                P2pError::InvalidShareCode(format!("No download in progress for {}", file_id))
+            })?;
+        let peer = self
+            .download_manager
+            .get_active_download(&download_id)
+            .map(|download| download.from_peer_id)
+            .ok_or_else(|| anyhow::anyhow!("Download not found: {}", download_id))?;
+        let source_file_id = self
+            .download_manager
+            .get_downloading_file(&download_id)
+            .map(|file| file.source_file_id.clone())
+            .unwrap_or_else(|| file_id.to_string());
+        if !self
+            .download_manager
+            .prepare_chunk_request(&download_id, chunk_index)?
+        {
+            return Ok(false);
+        }
+
+        let downloaded_count = self
+            .download_manager
+            .get_downloading_file(&download_id)
+            .map_or(0, |file| {
+                file.downloaded_chunks.values().filter(|&&v| v).count()
+            });
+        self.download_manager
+            .update_download_progress(&download_id, downloaded_count);
+        info!("Re-requesting chunk {} of {}", chunk_index, file_id);
+        self.send_chunk_request(peer, &source_file_id, chunk_index, &download_id);
+        Ok(true)
+    }
+
+    /// Send a `GetChunk` request for a download and track it
+    pub(super) fn send_chunk_request(
+        &mut self,
+        peer: PeerId,
+        file_id: &str,
+        chunk_index: usize,
+        download_id: &str,
+    ) {
+        let request_id = self.swarm.behaviour_mut().file_sharing.send_request(
+            &peer,
+            FileSharingRequest::GetChunk(file_id.to_string(), chunk_index),
+        );
+        self.peer_scores.start_request(request_id.to_string(), peer);
+        self.download_manager
+            .map_request_to_download(request_id.to_string(), download_id.to_string());
+        self.download_manager
+            .map_request_to_chunk(request_id.to_string(), chunk_index);
+    }
+
+    /// Chunk requests that can still be sent to `peer` under
+    /// `max_chunk_requests_per_peer`
+    pub(super) fn chunk_request_slots(&self, peer: &PeerId) -> usize {
+        match self.p2p_config.max_chunk_requests_per_peer {
+            0 => usize::MAX,
+            cap => cap.saturating_sub(self.peer_scores.in_flight(peer)),
+        }
+    }
+
+    /// Peers able to serve chunks of a download, with the share code each
+    /// knows the file by
+    ///
+    /// The download's source comes first, followed by the connected peers
+    /// whose shared files include the same content: the same whole-file
+    /// hash, size and chunk layout. Peers already given up on as the source
+    /// of a stalled download are left out.
+    pub(super) fn chunk_sources(&self, download_id: &str) -> Vec<(PeerId, String)> {
+        let (Some(download), Some(downloading_file)) = (
+            self.download_manager.get_active_download(download_id),
+            self.download_manager.get_downloading_file(download_id),
+        ) else {
+            return Vec::new();
+        };
+        let info = &downloading_file.info;
+        let mut sources = vec![(
+            download.from_peer_id,
+            downloading_file.source_file_id.clone(),
+        )];
+        if info.hash.is_empty() {
+            return sources;
+        }
+        for (peer, files) in &self.remote_files {
+            if *peer == download.from_peer_id
+                || downloading_file.tried_sources.contains(peer)
+                || !self.peer_manager.is_connected(peer)
+            {
+                continue;
+            }
+            if let Some(file) = files.iter().find(|file| {
+                file.hash == info.hash
+                    && file.hash_algo == info.hash_algo
+                    && file.size == info.size
+                    && file.chunk_count == info.chunk_count
+                    && file.chunk_offsets == info.chunk_offsets
+            }) {
+                sources.push((*peer, file.id.clone()));
+            }
+        }
+        sources.sort_by_key(|(peer, _)| *peer != download.from_peer_id);
+        sources
+    }
+
+    /// Top up the request windows of the downloads `peer` can serve
+    ///
+    /// Requests stay within each download's window and the per-peer cap.
+    /// Downloads with a higher `DownloadPriority` go first, then those with
+    /// the fewest requests in flight, so parallel downloads from one peer
+    /// share its slots. Each chunk goes to the source `PeerScoreboard`
+    /// picks among those with free slots, so most requests go to the
+    /// fastest peers sharing the file.
+    pub(super) fn request_more_chunks(&mut self, peer: PeerId) {
+        let mut downloads: Vec<(String, Vec<(PeerId, String)>)> = self
+            .download_manager
+            .get_downloading_ids()
+            .into_iter()
+            .map(|download_id| {
+                let sources = self.chunk_sources(&download_id);
+                (download_id, sources)
+            })
+            .filter(|(_, sources)| sources.iter().any(|(source, _)| *source == peer))
+            .collect();
+        downloads.sort_by_cached_key(|(download_id, _)| {
+            (
+                std::cmp::Reverse(self.download_manager.download_priority(download_id)),
+                self.download_manager.requests_in_flight(download_id),
+                download_id.clone(),
+            )
+        });
+        for (download_id, sources) in downloads {
+            let Some(next_chunks) = self
+                .download_manager
+                .get_next_chunks_to_request(&download_id, CHUNK_REQUEST_WINDOW)
+            else {
+                continue;
+            };
+            for chunk_index in next_chunks {
+                let candidates: Vec<PeerId> = sources
+                    .iter()
+                    .map(|(source, _)| *source)
+                    .filter(|source| self.chunk_request_slots(source) > 0)
+                    .collect();
+                let Some(chosen) = self.peer_scores.select_source(&candidates) else {
+                    break;
+                };
+                let Some((_, file_id)) = sources.iter().find(|(source, _)| *source == chosen)
+                else {
+                    break;
+                };
+                if let Err(e) = self
+                    .download_manager
+                    .mark_chunks_requested(&download_id, &[chunk_index])
+                {
+                    warn!("Failed to request chunks of {}: {}", download_id, e);
+                    break;
+                }
+                let file_id = file_id.clone();
+                self.send_chunk_request(chosen, &file_id, chunk_index, &download_id);
+            }
+        }
+    }
+
+    /// Chunk requests sent to a peer that are still waiting for a response
+    ///
+    /// Never more than `max_chunk_requests_per_peer` once downloads from the
+    /// peer are running, except for chunks requested by hand with
+    /// `request_chunk`.
+    pub fn chunk_requests_in_flight(&self, peer_id: &PeerId) -> usize {
+        self.peer_scores.in_flight(peer_id)
+    }
+
+    /// File transfer version negotiated with a peer
+    ///
+    /// Known once a file request to the peer was answered; use
+    /// `FileTransferVersion::capabilities` for the features it enables.
+    ///
+    /// # Returns
+    /// The version of the latest response from `peer_id`, or `None` if no
+    /// file request to it was answered yet
+    pub fn file_transfer_version(&self, peer_id: &PeerId) -> Option<FileTransferVersion> {
+        self.file_transfer_versions.get(peer_id).copied()
+    }
+
+    /// Protocols a connected peer supports, as reported via identify
+    ///
+    /// Useful to diagnose interop between app versions, e.g. a peer on an
+    /// older version lacking a file transfer protocol. The list is known
+    /// shortly after connecting, announced by `PeerProtocolsDiscovered`.
+    ///
+    /// # Returns
+    /// The sorted protocol names, or an empty list if the peer is not
+    /// connected or has not identified itself yet
+    pub fn peer_protocols(&self, peer_id: &PeerId) -> Vec<String> {
+        self.peer_protocols
+            .get(peer_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Chunk success rate and latency of every peer downloaded from
+    ///
+    /// Useful for diagnostics; peers serving chunks slowly or with errors
+    /// are marked as demoted.
+    pub fn peer_scores(&self) -> Vec<PeerScore> {
+        self.peer_scores.scores()
+    }
+
+    /// Ask a peer for the files it shares
+    ///
+    /// The answer arrives as `FileListReceived`. Downloads of a file this
+    /// peer also shares fetch chunks from it too.
+    ///
+    /// # Arguments
+    /// * `nickname` - The peer to ask
+    pub fn request_file_list(&mut self, nickname: &str) -> Result<()> {
+        validation::validate_nickname(nickname)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid nickname: {}", e)))?;
+        let peer_id = self
+            .peer_manager
+            .get_peer_id_by_nickname(nickname)
+            .ok_or_else(|| P2pError::NicknameNotFound(nickname.to_string()))?;
+        self.swarm
+            .behaviour_mut()
+            .file_sharing
+            .send_request(&peer_id, FileSharingRequest::ListFiles);
+        Ok(())
+    }
+
+    /// Re-hash every download recorded in the store and report mismatches
+    ///
+    /// Never runs on its own; call it when a background check is wanted.
+    /// Requires persistence to be enabled.
+    ///
+    /// # Returns
+    /// Downloads whose contents no longer match the hash verified on completion
+    pub async fn reverify_downloads(&self) -> Result<Vec<IntegrityMismatch>> {
+        let store = self
+            .file_sharing_store
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Persistence is not enabled"))?;
+        let mismatches = gigi_store::reverify_downloads(store).await?;
+        for mismatch in &mismatches {
+            warn!(
+                "Downloaded file failed re-verification: {}",
+                mismatch.file_path
+            );
+        }
+        Ok(mismatches)
+    }
+
+    /// Record a verified download in the store, if persistence is enabled
+    pub(super) fn record_downloaded_file(&self, info: DownloadedFileInfo) {
+        if let Some(store) = &self.file_sharing_store {
+            let store = Arc::clone(store);
+            let event_sender = self.event_sender.clone();
+            self.spawn_store_write(async move {
+                if let Err(e) = store.record_downloaded_file(&info).await {
+                    report_storage_error(&event_sender, "Failed to record downloaded file", e);
+                }
+            });
+        }
+    }
+
+    /// Record how a download ended in the history, if persistence is enabled
+    pub(super) fn record_download_history(&self, entry: DownloadHistoryEntry) {
+        if let Some(store) = &self.download_history_store {
+            let store = Arc::clone(store);
+            let event_sender = self.event_sender.clone();
+            self.spawn_store_write(async move {
+                if let Err(e) = store.record_download(&entry).await {
+                    report_storage_error(&event_sender, "Failed to record download history", e);
+                }
+            });
+        }
+    }
+
+    /// Save the served chunk count of an upload, if `persist_upload_progress`
+    /// is set; finished uploads are forgotten
+    pub(super) fn save_upload_progress(
+        &self,
+        peer: PeerId,
+        share_code: &str,
+        served_chunks: usize,
+        total_chunks: usize,
+    ) {
+        let Some(store) = &self.upload_progress_store else {
+            return;
+        };
+        if !self.p2p_config.persist_upload_progress {
+            return;
+        }
+        let store = Arc::clone(store);
+        let event_sender = self.event_sender.clone();
+        let peer_id = peer.to_string();
+        let share_code = share_code.to_string();
+        self.spawn_store_write(async move {
+            let saved = if served_chunks >= total_chunks {
+                store
+                    .remove_upload_progress(&peer_id, &share_code)
+                    .await
+                    .map(drop)
+            } else {
+                store
+                    .save_upload_progress(
+                        &peer_id,
+                        &share_code,
+                        served_chunks as u64,
+                        total_chunks as u64,
+                    )
+                    .await
+            };
+            if let Err(e) = saved {
+                report_storage_error(&event_sender, "Failed to save upload progress", e);
+            }
+        });
+    }
+
+    /// List completed, failed and cancelled downloads, most recent first
+    ///
+    /// Requires persistence to be enabled.
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of entries to return
+    /// * `offset` - Number of entries to skip, for paging
+    pub async fn list_download_history(
+        &self,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<DownloadHistoryEntry>> {
+        let store = self
+            .download_history_store
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Persistence is not enabled"))?;
+        store.list_download_history(limit, offset).await
     }
 
-    /// List shared files
+    /// Delete the download history
     ///
-    /// Returns information about all files currently shared by this peer.
+    /// Requires persistence to be enabled.
     ///
     /// # Returns
-    /// Vector of SharedFile references
-    pub fn list_shared_files(&self) -> Vec<&crate::events::SharedFile> {
-        self.file_manager.list_shared_files()
+    /// The number of entries deleted
+    pub async fn clear_download_history(&self) -> Result<u64> {
+        let store = self
+            .download_history_store
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Persistence is not enabled"))?;
+        store.clear_download_history().await
     }
 
-    /// Unshare a file by share code
-    ///
-    /// Stops sharing a file and revokes the share code.
-    /// Peers will no longer be able to download the file.
-    ///
-    /// # Arguments
-    /// * `share_code` - The share code of the file to unshare
+    /// Peer a received share code came from
     ///
     /// # Returns
-    /// Ok on success
-    pub fn unshare_file(&mut self, share_code: &str) -> Result<()> {
-        let shared_file = self.file_manager.shared_files.get(share_code);
-        if let Some(shared_file) = shared_file {
-            // Send event that file is revoked
-            self.send_event(P2pEvent::FileRevoked {
-                file_id: shared_file.info.id.clone(),
-            });
+    /// The source peer, or `None` if no share message with this code was
+    /// received, or its download already finished or was cancelled
+    pub fn share_source(&self, share_code: &str) -> Option<PeerId> {
+        self.share_sources.peek(share_code).copied()
+    }
 
-            // Save updated shared files
-            self.file_manager.unshare_file(share_code)?;
-        } else {
-            return Err(P2pError::InvalidShareCode(share_code.to_string()).into());
-        }
-        Ok(())
+    /// Remember the peer a received share code came from
+    pub(super) fn record_share_source(&mut self, share_code: String, peer_id: PeerId) {
+        self.share_sources.put(share_code, peer_id);
     }
 
-    // ===== Download Methods =====
-    // These methods handle downloading files from peers with progress tracking
+    /// Forget the source of a share code once its download is over
+    pub(super) fn forget_share_source(&mut self, share_code: &str) {
+        self.share_sources.pop(share_code);
+    }
 
-    /// Download file from peer
+    /// Count an inbound message against its author's rate limit
     ///
-    /// Initiates a file download from a peer using a share code.
-    /// The download is tracked and progress events are emitted.
-    ///
-    /// # Arguments
-    /// * `nickname` - The peer sharing the file
-    /// * `share_code` - The share code of the file to download
+    /// Messages from trusted peers are always admitted. Emits `RateLimited`
+    /// for the first message dropped in a run.
     ///
     /// # Returns
-    /// The download_id for tracking this download
-    ///
-    /// # Download Flow
-    /// 1. Request file info from peer
-    /// 2. Download chunks sequentially
-    /// 3. Verify each chunk's BLAKE3 hash
-    /// 4. Assemble chunks into final file
-    /// 5. Verify final SHA256 hash
-    ///
-    /// # Events
-    /// The client will emit `P2pEvent` updates for download progress.
-    pub fn download_file(&mut self, nickname: &str, share_code: &str) -> Result<String> {
-        // Validate inputs
-        validation::validate_nickname(nickname)
-            .map_err(|e| P2pError::InvalidInput(format!("Invalid nickname: {}", e)))?;
-        validation::validate_share_code(share_code)
-            .map_err(|e| P2pError::InvalidInput(format!("Invalid share code: {}", e)))?;
-        let peer_id = self
-            .peer_manager
-            .get_peer_id_by_nickname(nickname)
-            .ok_or_else(|| P2pError::NicknameNotFound(nickname.to_string()))?;
+    /// `true` if the message may be processed
+    pub(super) fn admit_inbound_message(&mut self, peer: PeerId) -> bool {
+        if self.trusted_peers.contains(&peer) {
+            return true;
+        }
+        match self.rate_limiter.admit(peer) {
+            Admission::Accepted => true,
+            Admission::Dropped { first } => {
+                if first {
+                    warn!("Rate limiting messages from {}", peer);
+                    self.send_event(P2pEvent::RateLimited { peer });
+                }
+                false
+            }
+        }
+    }
+
+    /// Track a download and request the file info from `peer_id`
+    fn start_download_from(
+        &mut self,
+        peer_id: PeerId,
+        nickname: &str,
+        request: DownloadRequest,
+    ) -> String {
+        let share_code = request.share_code.as_str();
+        // A second request for the same file would write to the same temp file
+        if let Some(download) = self
+            .download_manager
+            .get_in_flight_download(share_code, &peer_id)
+        {
+            info!(
+                "Download of {} from {} already in progress",
+                share_code, nickname
+            );
+            return download.download_id.clone();
+        }
 
         // Track download request with DownloadManager and get the download_id
         let download_id = self.download_manager.start_download(
@@ -1204,8 +4202,25 @@ impl P2pClient {
         // Map request_id to download_id so we can match the response
         self.download_manager
             .map_request_to_download(request_id.to_string(), download_id.clone());
+        self.download_manager
+            .set_download_request(&download_id, request);
+
+        download_id
+    }
 
-        Ok(download_id)
+    /// Cancel a download
+    ///
+    /// Deletes the partial file and records the download as cancelled in
+    /// the download history. No `FileDownloadFailed` event is sent, and
+    /// responses still in flight for it are ignored.
+    ///
+    /// # Arguments
+    /// * `download_id` - The id returned by `download_file`
+    ///
+    /// # Returns
+    /// `true` if the download was in progress
+    pub fn cancel_download(&mut self, download_id: &str) -> bool {
+        FileSharingEventHandler::new(self).cancel_download(download_id)
     }
 
     /// Send event to event receiver
@@ -1221,6 +4236,13 @@ impl P2pClient {
         }
     }
 
+    /// Number of progress events dropped because the event queue was full
+    ///
+    /// Always 0 unless `P2pConfig::event_channel_capacity` is set.
+    pub fn dropped_events(&self) -> u64 {
+        self.event_sender.dropped_events()
+    }
+
     /// Get local peer ID
     ///
     /// Returns the unique identifier for this peer.
@@ -1241,6 +4263,298 @@ impl P2pClient {
         &self.local_nickname
     }
 
+    /// Broadcast a presence status such as "Available" or "Away"
+    ///
+    /// The status is published on the presence topic right away, to peers
+    /// that subscribe later, and every `P2pConfig::presence_interval`. It is
+    /// not persisted; set it again after a restart.
+    ///
+    /// # Arguments
+    /// * `status` - Free-form status text; an empty string clears the status
+    ///
+    /// # Events
+    /// Peers receive `PresenceChanged`.
+    pub fn set_presence_status(&mut self, status: &str) -> Result<()> {
+        validation::validate_status(status)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid status: {}", e)))?;
+        let status = Some(status.to_string()).filter(|status| !status.is_empty());
+        self.presence.set_status(status, &mut self.swarm);
+        Ok(())
+    }
+
+    /// Clear the local presence status, telling peers it is gone
+    pub fn clear_presence_status(&mut self) {
+        self.presence.set_status(None, &mut self.swarm);
+    }
+
+    /// The local presence status, if one is set
+    pub fn presence_status(&self) -> Option<&str> {
+        self.presence.status()
+    }
+
+    /// Add a contact and exchange profiles with it
+    ///
+    /// Stores the contact, then sends the local profile to the peer, which
+    /// answers with its own. The received profile is stored with the contact
+    /// and reported as `ContactProfileReceived`. Peers that do not support
+    /// the profile exchange are kept as contacts without a profile. Adding an
+    /// existing contact just repeats the exchange. Requires persistence to be
+    /// enabled.
+    ///
+    /// # Arguments
+    /// * `peer_id` - The contact's peer ID
+    /// * `name` - Local name for the contact
+    pub async fn add_contact(&mut self, peer_id: PeerId, name: &str) -> Result<()> {
+        let contacts = self
+            .contact_manager
+            .as_ref()
+            .ok_or(P2pError::PersistenceNotEnabled)?;
+        let key = peer_id.to_string();
+        if !contacts.exists(&key).await? {
+            contacts
+                .add(&key, name)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to add contact: {}", e))?;
+        }
+        self.exchange_profile(peer_id);
+        Ok(())
+    }
+
+    /// Get a stored contact, including its profile once exchanged
+    ///
+    /// # Arguments
+    /// * `peer_id` - The contact's peer ID
+    ///
+    /// # Returns
+    /// The contact, or `None` if the peer is not a contact
+    pub async fn get_contact(&self, peer_id: &PeerId) -> Result<Option<ContactInfo>> {
+        let contacts = self
+            .contact_manager
+            .as_ref()
+            .ok_or(P2pError::PersistenceNotEnabled)?;
+        contacts
+            .get(&peer_id.to_string())
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get contact: {}", e))
+    }
+
+    /// Mark a peer as trusted
+    ///
+    /// Trusted peers are exempt from `inbound_message_limit`, and files they
+    /// share directly are downloaded right away regardless of
+    /// `auto_accept_max_size`. Unlike contacts, trust works without
+    /// persistence; with persistence enabled it is saved in the settings and
+    /// restored at creation.
+    ///
+    /// # Arguments
+    /// * `peer_id` - The peer to trust
+    pub fn trust_peer(&mut self, peer_id: PeerId) {
+        if self.trusted_peers.insert(peer_id) {
+            info!("Trusting peer {}", peer_id);
+            self.save_trusted_peers();
+        }
+    }
+
+    /// Remove a peer from the trusted peers
+    ///
+    /// # Returns
+    /// `true` if the peer was trusted
+    pub fn untrust_peer(&mut self, peer_id: &PeerId) -> bool {
+        let removed = self.trusted_peers.remove(peer_id);
+        if removed {
+            info!("No longer trusting peer {}", peer_id);
+            self.save_trusted_peers();
+        }
+        removed
+    }
+
+    /// Whether `trust_peer` was called for this peer
+    pub fn is_trusted(&self, peer_id: &PeerId) -> bool {
+        self.trusted_peers.contains(peer_id)
+    }
+
+    /// All trusted peers, in no particular order
+    pub fn trusted_peers(&self) -> Vec<PeerId> {
+        self.trusted_peers.iter().copied().collect()
+    }
+
+    /// Write the trusted peers to the settings, if persistence is enabled
+    fn save_trusted_peers(&self) {
+        let Some(settings) = &self.settings_manager else {
+            return;
+        };
+        let mut peer_ids: Vec<String> = self.trusted_peers.iter().map(PeerId::to_string).collect();
+        peer_ids.sort();
+        let value = serde_json::to_string(&peer_ids).expect("Peer ID list serializes");
+        let settings = Arc::clone(settings);
+        let event_sender = self.event_sender.clone();
+        self.spawn_store_write(async move {
+            if let Err(e) = settings.set(TRUSTED_PEERS_KEY, &value).await {
+                report_storage_error(&event_sender, "Failed to save trusted peers", e);
+            }
+        });
+    }
+
+    /// Whether whole-file hashing is skipped for transfers with `peer`
+    pub(super) fn skips_hash_for(&self, peer: &PeerId) -> bool {
+        self.p2p_config.skip_hash_for_trusted && self.trusted_peers.contains(peer)
+    }
+
+    /// Whether a file shared by `peer` is downloaded without asking
+    ///
+    /// True for trusted peers and for files within `auto_accept_max_size`.
+    /// Files shared directly are downloaded right away when this holds;
+    /// apps can use it to decide on group shares the same way.
+    pub fn should_auto_accept(&self, peer: &PeerId, file_size: u64) -> bool {
+        self.trusted_peers.contains(peer)
+            || self
+                .p2p_config
+                .auto_accept_max_size
+                .is_some_and(|max_size| file_size <= max_size)
+    }
+
+    /// Send the local profile to a peer and request its profile in return
+    ///
+    /// Called by `add_contact`; call it directly to refresh the profile of
+    /// an existing contact.
+    ///
+    /// # Arguments
+    /// * `peer_id` - The peer to exchange profiles with
+    pub fn exchange_profile(&mut self, peer_id: PeerId) {
+        let exchange = self.local_profile.exchange(&self.local_nickname);
+        self.swarm
+            .behaviour_mut()
+            .profile
+            .send_request(&peer_id, exchange);
+    }
+
+    /// The profile sent to contacts
+    pub fn local_profile(&self) -> Profile {
+        self.local_profile.profile(&self.local_nickname)
+    }
+
+    /// Set the display name sent to contacts
+    ///
+    /// Contacts receive it on the next profile exchange.
+    ///
+    /// # Arguments
+    /// * `display_name` - The display name, `None` to use the nickname
+    pub fn set_profile_display_name(&mut self, display_name: Option<&str>) -> Result<()> {
+        if let Some(display_name) = display_name {
+            validation::validate_nickname(display_name)
+                .map_err(|e| P2pError::InvalidInput(format!("Invalid display name: {}", e)))?;
+        }
+        self.local_profile
+            .set_display_name(display_name.map(str::to_string));
+        Ok(())
+    }
+
+    /// Set the avatar image sent to contacts
+    ///
+    /// The image is copied into the avatar cache and its hash is put in the
+    /// local profile. Contacts fetch it after their next profile exchange.
+    ///
+    /// # Arguments
+    /// * `image_path` - The image file, at most `MAX_AVATAR_SIZE` bytes
+    ///
+    /// # Returns
+    /// The avatar hash
+    pub fn set_avatar(&mut self, image_path: &Path) -> Result<String> {
+        let data = AvatarCache::read_image(image_path)?;
+        let avatar_hash = AvatarCache::hash(&data);
+        self.avatar_cache.insert(&avatar_hash, &data)?;
+        self.local_profile
+            .set_avatar_hash(Some(avatar_hash.clone()));
+        Ok(avatar_hash)
+    }
+
+    /// Remove the avatar from the local profile
+    pub fn clear_avatar(&mut self) {
+        self.local_profile.set_avatar_hash(None);
+    }
+
+    /// Get an avatar from the cache, or fetch it from a peer
+    ///
+    /// Avatars named in received contact profiles are fetched automatically;
+    /// call this to look one up or to retry a failed fetch.
+    ///
+    /// # Arguments
+    /// * `peer_id` - Peer whose current avatar has this hash
+    /// * `avatar_hash` - Hash of the avatar, as found in the peer's profile
+    ///
+    /// # Returns
+    /// The cached image, or `None` while it is being fetched
+    ///
+    /// # Events
+    /// `AvatarFetched` once a fetched avatar is cached.
+    pub fn fetch_avatar(&mut self, peer_id: PeerId, avatar_hash: &str) -> Result<Option<PathBuf>> {
+        validation::validate_avatar_hash(avatar_hash)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid avatar hash: {}", e)))?;
+        if let Some(path) = self.avatar_cache.lookup(avatar_hash) {
+            return Ok(Some(path));
+        }
+        if !self.avatar_cache.is_pending(avatar_hash) {
+            let request_id = self.swarm.behaviour_mut().file_sharing.send_request(
+                &peer_id,
+                FileSharingRequest::GetAvatar(avatar_hash.to_string()),
+            );
+            self.avatar_cache
+                .track(request_id.to_string(), avatar_hash.to_string());
+        }
+        Ok(None)
+    }
+
+    /// Avatar cache hit and miss counters
+    pub fn avatar_cache_stats(&self) -> AvatarCacheStats {
+        self.avatar_cache.stats()
+    }
+
+    /// Store a profile received from a peer, if the peer is a contact
+    ///
+    /// `ContactProfileReceived` is sent once the profile is stored.
+    pub(super) fn store_contact_profile(&self, peer_id: PeerId, profile: Profile) {
+        let Some(contacts) = &self.contact_manager else {
+            return;
+        };
+        let contacts = Arc::clone(contacts);
+        let event_sender = self.event_sender.clone();
+        self.spawn_store_write(async move {
+            let stored = contacts
+                .update_profile(
+                    &peer_id.to_string(),
+                    &profile.display_name,
+                    &profile.public_key,
+                    profile.avatar_hash.as_deref(),
+                )
+                .await;
+            match stored {
+                Ok(true) => {
+                    let _ = event_sender
+                        .unbounded_send(P2pEvent::ContactProfileReceived { peer_id, profile });
+                }
+                Ok(false) => {}
+                Err(e) => report_storage_error(&event_sender, "Failed to store contact profile", e),
+            }
+        });
+    }
+
+    /// Change the local nickname
+    ///
+    /// The new nickname is announced over gigi-dns right away; peers see a
+    /// `NicknameUpdated` event once they receive the announcement.
+    ///
+    /// # Arguments
+    /// * `nickname` - The new display nickname
+    pub fn set_nickname(&mut self, nickname: &str) -> Result<()> {
+        validation::validate_nickname(nickname)
+            .map_err(|e| P2pError::InvalidInput(format!("Invalid nickname: {}", e)))?;
+        self.local_nickname = nickname.to_string();
+        if let Some(gigi_dns) = self.swarm.behaviour_mut().gigi_dns.as_mut() {
+            gigi_dns.update_nickname(nickname.to_string());
+        }
+        Ok(())
+    }
+
     /// Get joined groups
     ///
     /// Returns information about all groups this peer has joined.
@@ -1265,6 +4579,49 @@ impl P2pClient {
         self.download_manager.get_active_downloads()
     }
 
+    /// Get detailed progress for all active downloads
+    ///
+    /// Combines the tracked download state into one snapshot per download,
+    /// including filename, sender, chunk counts, bytes written and speed.
+    /// Useful for rendering a transfers panel without stitching together events.
+    ///
+    /// # Returns
+    /// A vector of download details
+    pub fn active_downloads_detailed(&self) -> Vec<DownloadDetail> {
+        self.download_manager.get_download_details()
+    }
+
+    /// Set a callback to run when a download completes
+    ///
+    /// The hook is called once the file has been verified and moved to its
+    /// final path, before `FileDownloadCompleted` is sent, e.g. to move the
+    /// file to a media library. It runs on a blocking task so it never stalls
+    /// the event loop; a panic in the hook is logged and otherwise ignored.
+    /// Setting a new hook replaces the previous one.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// client.set_download_complete_hook(Arc::new(|detail| {
+    ///     println!("{} saved to {:?}", detail.filename, detail.final_path);
+    /// }));
+    /// ```
+    pub fn set_download_complete_hook(&mut self, hook: DownloadCompleteHook) {
+        self.download_complete_hook = Some(hook);
+    }
+
+    /// Run the download complete hook, if set, on a blocking task
+    pub(super) fn run_download_complete_hook(&self, detail: DownloadDetail) {
+        if let Some(hook) = &self.download_complete_hook {
+            let hook = Arc::clone(hook);
+            tokio::spawn(async move {
+                let download_id = detail.download_id.clone();
+                if let Err(e) = tokio::task::spawn_blocking(move || hook(&detail)).await {
+                    error!("Download complete hook failed for {}: {}", download_id, e);
+                }
+            });
+        }
+    }
+
     /// Get active download by download_id
     ///
     /// Retrieves a specific download by its unique identifier.
@@ -1356,7 +4713,9 @@ impl P2pClient {
 
     /// Get conversation history with a peer
     ///
-    /// Retrieves the message history with a specific peer.
+    /// Retrieves the message history with a specific peer. For a known
+    /// peer the history is looked up by its peer ID, like
+    /// `get_peer_conversation_history`.
     ///
     /// # Arguments
     /// * `nickname` - The peer's nickname
@@ -1370,6 +4729,9 @@ impl P2pClient {
         &self,
         nickname: &str,
     ) -> Result<Vec<gigi_store::StoredMessage>> {
+        if let Some(peer_id) = self.peer_manager.get_peer_id_by_nickname(nickname) {
+            return self.get_peer_conversation_history(&peer_id).await;
+        }
         let message_store = self
             .message_store
             .as_ref()
@@ -1383,6 +4745,40 @@ impl P2pClient {
             .map_err(|e| anyhow::anyhow!("Failed to get conversation history: {}", e))
     }
 
+    /// Get conversation history with a peer by its peer ID
+    ///
+    /// Unlike nicknames and the placeholder labels of unnamed peers, peer
+    /// IDs are unique, so conversations of peers showing the same name stay
+    /// apart. Messages still queued for the peer's display name are included.
+    ///
+    /// # Arguments
+    /// * `peer_id` - The peer's ID
+    ///
+    /// # Returns
+    /// Vector of StoredMessage records, newest first
+    ///
+    /// # Note
+    /// Requires persistence to be enabled
+    pub async fn get_peer_conversation_history(
+        &self,
+        peer_id: &PeerId,
+    ) -> Result<Vec<gigi_store::StoredMessage>> {
+        let message_store = self
+            .message_store
+            .as_ref()
+            .ok_or(P2pError::PersistenceNotEnabled)?;
+
+        message_store
+            .get_conversation_with_peer(
+                &peer_id.to_string(),
+                &self.peer_manager.display_name(peer_id),
+                100,
+                0,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get conversation history: {}", e))
+    }
+
     /// Mark a message as read
     ///
     /// Marks a specific message as read in the message store.
@@ -1543,9 +4939,15 @@ impl P2pClient {
         let mut sent_count = 0;
         for msg in pending {
             if let gigi_store::MessageContent::Text { text } = msg.content {
+                // Re-send with the stored id so receipts match the original message
                 self.swarm.behaviour_mut().direct_msg.send_request(
                     &peer_id,
-                    crate::behaviour::DirectMessage::Text { message: text },
+                    crate::behaviour::DirectMessage::Text {
+                        message: text,
+                        message_id: msg.id.clone(),
+                        disappear_after_secs: msg.disappear_after_secs,
+                        forwarded_from: None,
+                    },
                 );
 
                 // Update peer_id if it was empty
@@ -1565,6 +4967,80 @@ impl P2pClient {
         Ok(sent_count)
     }
 
+    /// Run the periodic sync batch if it is due
+    ///
+    /// Sends up to `PersistenceConfig::max_batch_size` queued messages to
+    /// connected peers every `PersistenceConfig::sync_interval_seconds`.
+    ///
+    /// # Events
+    /// Emits `SyncProgress` for a batch that attempted messages and
+    /// `SyncFailed` for every message that could not be sent.
+    async fn sync_queued_messages(&mut self, now: tokio::time::Instant) -> Result<()> {
+        self.sync_offline_queue(Some(now)).await
+    }
+
+    /// Send a batch of queued messages to connected peers
+    ///
+    /// # Arguments
+    /// * `now` - The current time to run only a due periodic batch, or
+    ///   `None` to run a batch right away
+    async fn sync_offline_queue(&mut self, now: Option<tokio::time::Instant>) -> Result<()> {
+        let Some(sync_manager) = &self.sync_manager else {
+            return Ok(());
+        };
+        let online: Vec<String> = self
+            .peer_manager
+            .list_peers()
+            .into_iter()
+            .filter(|peer| peer.connected && !peer.nickname.is_empty())
+            .map(|peer| peer.nickname.clone())
+            .collect();
+
+        let peer_manager = &self.peer_manager;
+        let swarm = &mut self.swarm;
+        let deliver = |nickname: &str, msg: &gigi_store::StoredMessage| -> Result<()> {
+            let peer_id = peer_manager
+                .get_peer_id_by_nickname(nickname)
+                .ok_or_else(|| P2pError::NicknameNotFound(nickname.to_string()))?;
+            let gigi_store::MessageContent::Text { text } = &msg.content else {
+                return Err(anyhow::anyhow!("Only text messages are queued for sync"));
+            };
+            // Re-send with the stored id so receipts match the original message
+            swarm.behaviour_mut().direct_msg.send_request(
+                &peer_id,
+                crate::behaviour::DirectMessage::Text {
+                    message: text.clone(),
+                    message_id: msg.id.clone(),
+                    disappear_after_secs: msg.disappear_after_secs,
+                    forwarded_from: msg.forwarded_from.clone(),
+                },
+            );
+            Ok(())
+        };
+        let progress = match now {
+            Some(now) => sync_manager.sync_if_due(now, &online, deliver).await,
+            None => sync_manager.sync_batch(&online, deliver).await.map(Some),
+        }
+        .map_err(|e| anyhow::anyhow!("Failed to sync queued messages: {}", e))?;
+
+        let Some(progress) = progress.filter(|progress| progress.attempted > 0) else {
+            return Ok(());
+        };
+        for failure in &progress.failures {
+            self.send_event(P2pEvent::SyncFailed {
+                message_id: failure.message_id.clone(),
+                nickname: failure.target_nickname.clone(),
+                error: failure.error.clone(),
+            });
+        }
+        self.send_event(P2pEvent::SyncProgress {
+            attempted: progress.attempted,
+            synced: progress.synced,
+            failed: progress.failures.len(),
+        });
+        Ok(())
+    }
+
     /// Clear conversation history with a peer
     ///
     /// Deletes all messages from a specific peer.
@@ -1607,6 +5083,65 @@ impl P2pClient {
     }
 }
 
+/// Stored form of a shared location, as a direct message sent now
+///
+/// Callers set the recipient or group and, for received locations, the
+/// sender's timestamp.
+pub(super) fn location_stored_message(
+    id: String,
+    peer_id: PeerId,
+    direction: gigi_store::MessageDirection,
+    sender_nickname: String,
+    location: Location,
+) -> gigi_store::StoredMessage {
+    let now = chrono::Utc::now();
+    gigi_store::StoredMessage {
+        id,
+        msg_type: gigi_store::MessageType::Direct,
+        direction,
+        content: gigi_store::MessageContent::Location {
+            lat: location.lat,
+            lon: location.lon,
+            label: location.label,
+        },
+        sender_nickname,
+        recipient_nickname: None,
+        group_name: None,
+        peer_id: peer_id.to_string(),
+        timestamp: now,
+        created_at: now,
+        delivered: false,
+        delivered_at: None,
+        read: false,
+        read_at: None,
+        sync_status: gigi_store::SyncStatus::Pending,
+        sync_attempts: 0,
+        last_sync_attempt: None,
+        expires_at: now + chrono::Duration::days(7),
+        disappear_after_secs: None,
+        forwarded_from: None,
+    }
+}
+
+/// Log a failed background store operation and report it as `P2pErrorKind::Storage`
+fn report_storage_error(event_sender: &EventSender, context: &str, error: impl std::fmt::Display) {
+    error!("{}: {}", context, error);
+    let _ = event_sender.unbounded_send(P2pEvent::Error(P2pErrorKind::Storage(format!(
+        "{}: {}",
+        context, error
+    ))));
+}
+
+/// Reject a `P2pConfig::discovery_service_name` gigi-dns cannot announce
+fn validate_discovery_service_name(service_name: &str) -> Result<()> {
+    GigiDnsConfig {
+        service_name: service_name.to_string(),
+        ..Default::default()
+    }
+    .validate()
+    .map_err(|e| P2pError::InvalidInput(e).into())
+}
+
 /// Parse a bootstrap address string
 ///
 /// Parses addresses in the format: "/ip4/x.x.x.x/tcp/port/p2p/peer_id"
@@ -20,6 +20,8 @@ use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::time::{Duration, Instant};
 
+use super::display_name::UnnamedPeerLabel;
+use super::event_channel::EventSender;
 use crate::behaviour::UnifiedBehaviour;
 use crate::error::P2pError;
 use crate::events::{P2pEvent, PeerInfo};
@@ -36,6 +38,8 @@ pub struct PeerManager {
     nickname_to_peer: HashMap<String, PeerId>,
     /// LRU cache for unconnected peers (limited to prevent memory leaks)
     unconnected_peers: LruCache<PeerId, PeerInfo>,
+    /// Placeholder strategy for peers without a nickname
+    unnamed_peer_label: UnnamedPeerLabel,
 }
 
 impl PeerManager {
@@ -47,9 +51,15 @@ impl PeerManager {
             peers: HashMap::new(),
             nickname_to_peer: HashMap::new(),
             unconnected_peers: LruCache::new(capacity),
+            unnamed_peer_label: UnnamedPeerLabel::default(),
         }
     }
 
+    /// Set how peers without a nickname are labelled by `display_name`
+    pub fn set_unnamed_peer_label(&mut self, label: UnnamedPeerLabel) {
+        self.unnamed_peer_label = label;
+    }
+
     /// Handle peer discovery from gigi-dns
     ///
     /// When a peer is discovered via mDNS:
@@ -71,7 +81,7 @@ impl PeerManager {
         addr: Multiaddr,
         swarm: &mut Swarm<UnifiedBehaviour>,
         nickname: &str,
-        event_sender: &mut futures::channel::mpsc::UnboundedSender<P2pEvent>,
+        event_sender: &mut EventSender,
     ) -> Result<()> {
         // Check if peer is already in connected peers
         if !self.peers.contains_key(&peer_id) {
@@ -91,6 +101,7 @@ impl PeerManager {
                     addresses: vec![addr.clone()],
                     last_seen: Instant::now(),
                     connected: false,
+                    status: None,
                 };
 
                 // Store in LRU cache for unconnected peers
@@ -111,11 +122,18 @@ impl PeerManager {
     pub fn handle_peer_expired(
         &mut self,
         peer_id: PeerId,
-        event_sender: &mut futures::channel::mpsc::UnboundedSender<P2pEvent>,
+        event_sender: &mut EventSender,
     ) -> Result<()> {
         if let Some(peer) = self.peers.remove(&peer_id) {
             self.nickname_to_peer.remove(&peer.nickname);
 
+            if peer.status.is_some() {
+                let _ = event_sender.unbounded_send(P2pEvent::PresenceChanged {
+                    peer_id,
+                    nickname: peer.nickname.clone(),
+                    status: None,
+                });
+            }
             let _ = event_sender.unbounded_send(P2pEvent::PeerExpired {
                 peer_id,
                 nickname: peer.nickname,
@@ -129,7 +147,7 @@ impl PeerManager {
         &mut self,
         peer_id: PeerId,
         nickname: String,
-        event_sender: &mut futures::channel::mpsc::UnboundedSender<P2pEvent>,
+        event_sender: &mut EventSender,
     ) {
         if let Some(peer) = self.peers.get_mut(&peer_id) {
             let old_nickname = peer.nickname.clone();
@@ -144,6 +162,39 @@ impl PeerManager {
         }
     }
 
+    /// Update a peer's presence status
+    ///
+    /// Emits `PresenceChanged` if the status differs. Statuses from peers
+    /// that are not tracked (yet) are ignored; they are rebroadcast later.
+    pub fn update_peer_status(
+        &mut self,
+        peer_id: PeerId,
+        status: Option<String>,
+        event_sender: &mut EventSender,
+    ) {
+        let peer = match self.peers.get_mut(&peer_id) {
+            Some(peer) => peer,
+            None => match self.unconnected_peers.get_mut(&peer_id) {
+                Some(peer) => peer,
+                None => return,
+            },
+        };
+        if peer.status == status {
+            return;
+        }
+        peer.status = status.clone();
+        let nickname = match peer.nickname.as_str() {
+            "" => self.unnamed_peer_label.label_for(&peer_id),
+            nickname => nickname.to_string(),
+        };
+
+        let _ = event_sender.unbounded_send(P2pEvent::PresenceChanged {
+            peer_id,
+            nickname,
+            status,
+        });
+    }
+
     /// Get peer by nickname
     pub fn get_peer_by_nickname(&self, nickname: &str) -> Result<&PeerInfo> {
         let peer_id = *self
@@ -170,6 +221,18 @@ impl PeerManager {
         Err(P2pError::PeerNotFound(*peer_id).into())
     }
 
+    /// Name to show for a peer
+    ///
+    /// # Returns
+    /// The peer's nickname, or a placeholder if it is unknown or has not
+    /// announced one yet
+    pub fn display_name(&self, peer_id: &PeerId) -> String {
+        match self.get_peer_nickname(peer_id) {
+            Ok(nickname) if !nickname.is_empty() => nickname,
+            _ => self.unnamed_peer_label.label_for(peer_id),
+        }
+    }
+
     /// Get peer info
     pub fn get_peer(&self, peer_id: &PeerId) -> Option<&PeerInfo> {
         // First check connected peers
@@ -206,10 +269,7 @@ impl PeerManager {
     }
 
     /// Gracefully shutdown all peers and notify
-    pub fn shutdown(
-        &mut self,
-        event_sender: &mut futures::channel::mpsc::UnboundedSender<P2pEvent>,
-    ) -> Result<()> {
+    pub fn shutdown(&mut self, event_sender: &mut EventSender) -> Result<()> {
         // Close all connections and notify peers
         let connected_peers: Vec<PeerId> = self.peers.keys().copied().collect();
         for peer_id in connected_peers {
@@ -236,7 +296,7 @@ impl PeerManager {
     pub fn handle_connection_established(
         &mut self,
         peer_id: PeerId,
-        event_sender: &mut futures::channel::mpsc::UnboundedSender<P2pEvent>,
+        event_sender: &mut EventSender,
     ) {
         // Check if peer is in unconnected cache and move to connected peers
         if let Some(mut peer) = self.unconnected_peers.pop(&peer_id) {
@@ -260,11 +320,7 @@ impl PeerManager {
     }
 
     /// Handle peer connection closed
-    pub fn handle_connection_closed(
-        &mut self,
-        peer_id: PeerId,
-        event_sender: &mut futures::channel::mpsc::UnboundedSender<P2pEvent>,
-    ) {
+    pub fn handle_connection_closed(&mut self, peer_id: PeerId, event_sender: &mut EventSender) {
         if let Some(peer) = self.peers.remove(&peer_id) {
             self.nickname_to_peer.remove(&peer.nickname);
 
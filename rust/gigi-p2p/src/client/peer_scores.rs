@@ -0,0 +1,242 @@
+//! Per-peer download source scoring
+//!
+//! Tracks how well each peer serves chunks (success rate and latency) so a
+//! download with several sources can send most requests to the fastest ones.
+//! This is a small-scale version of BitTorrent-style choking:
+//!
+//! - Peers start unscored and are tried first so every source gets measured
+//! - A peer whose score falls below `DEMOTION_RATIO` of the best candidate is demoted
+//! - Every `PROBE_INTERVAL`-th pick goes to a demoted peer so it can recover
+//! - A peer that is the only candidate is always picked, never abandoned
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Weight of the newest sample in the moving averages
+const SMOOTHING: f64 = 0.2;
+
+/// Candidates scoring below this fraction of the best are demoted
+const DEMOTION_RATIO: f64 = 0.5;
+
+/// Every this many picks a demoted candidate is re-probed
+const PROBE_INTERVAL: u64 = 10;
+
+/// Requests unanswered for this long are counted as failures
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Outcomes recorded for one peer
+#[derive(Debug, Clone)]
+struct PeerRecord {
+    successes: u64,
+    failures: u64,
+    /// Moving average of outcomes, 1.0 = always succeeds
+    success_rate: f64,
+    /// Moving average of chunk latency in seconds
+    avg_latency: Option<f64>,
+    last_picked: Option<Instant>,
+}
+
+impl PeerRecord {
+    fn new() -> Self {
+        Self {
+            successes: 0,
+            failures: 0,
+            success_rate: 1.0,
+            avg_latency: None,
+            last_picked: None,
+        }
+    }
+
+    /// Higher is better; `None` until the peer served or failed a chunk
+    fn score(&self) -> Option<f64> {
+        match self.avg_latency {
+            Some(latency) => Some(self.success_rate / latency.max(0.001)),
+            None if self.failures > 0 => Some(0.0),
+            None => None,
+        }
+    }
+}
+
+/// Snapshot of one peer's score for diagnostics
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerScore {
+    pub peer_id: PeerId,
+    /// Chunks served successfully
+    pub successes: u64,
+    /// Chunk requests that failed or returned bad data
+    pub failures: u64,
+    /// Moving average success rate between 0.0 and 1.0
+    pub success_rate: f64,
+    /// Moving average chunk latency, `None` until a chunk was served
+    pub avg_latency: Option<Duration>,
+    /// Whether the peer is currently demoted relative to the best peer
+    pub demoted: bool,
+}
+
+/// Scores download sources and picks which peer to ask for the next chunk
+#[derive(Debug, Default)]
+pub struct PeerScoreboard {
+    peers: HashMap<PeerId, PeerRecord>,
+    /// Outstanding requests: request id -> (peer, sent at)
+    pending: HashMap<String, (PeerId, Instant)>,
+    picks: u64,
+}
+
+impl PeerScoreboard {
+    /// Create an empty scoreboard
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a chunk served by `peer` after `latency`
+    pub fn record_success(&mut self, peer: PeerId, latency: Duration) {
+        let record = self.peers.entry(peer).or_insert_with(PeerRecord::new);
+        record.successes += 1;
+        record.success_rate += SMOOTHING * (1.0 - record.success_rate);
+        let latency = latency.as_secs_f64();
+        record.avg_latency = Some(match record.avg_latency {
+            Some(avg) => avg + SMOOTHING * (latency - avg),
+            None => latency,
+        });
+    }
+
+    /// Record a failed chunk request or bad chunk from `peer`
+    pub fn record_failure(&mut self, peer: PeerId) {
+        let record = self.peers.entry(peer).or_insert_with(PeerRecord::new);
+        record.failures += 1;
+        record.success_rate -= SMOOTHING * record.success_rate;
+    }
+
+    /// Remember when a chunk request was sent, to measure its latency
+    ///
+    /// Requests left unanswered for `REQUEST_TIMEOUT` are dropped and counted
+    /// as failures.
+    ///
+    /// # Arguments
+    /// * `request_id` - Id of the outgoing request
+    /// * `peer` - Peer the request was sent to
+    pub fn start_request(&mut self, request_id: String, peer: PeerId) {
+        let now = Instant::now();
+        let timed_out: Vec<PeerId> = self
+            .pending
+            .values()
+            .filter(|(_, sent_at)| now.duration_since(*sent_at) > REQUEST_TIMEOUT)
+            .map(|(peer, _)| *peer)
+            .collect();
+        if !timed_out.is_empty() {
+            self.pending
+                .retain(|_, (_, sent_at)| now.duration_since(*sent_at) <= REQUEST_TIMEOUT);
+            for peer in timed_out {
+                self.record_failure(peer);
+            }
+        }
+        self.pending.insert(request_id, (peer, now));
+    }
+
+    /// Record the outcome of a request registered with `start_request`
+    ///
+    /// Unknown request ids are ignored.
+    pub fn finish_request(&mut self, request_id: &str, success: bool) {
+        if let Some((peer, sent_at)) = self.pending.remove(request_id) {
+            if success {
+                self.record_success(peer, sent_at.elapsed());
+            } else {
+                self.record_failure(peer);
+            }
+        }
+    }
+
+    /// Drop all outstanding requests without scoring them
+    pub fn forget_requests(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Requests to `peer` registered with `start_request` and not finished yet
+    pub fn in_flight(&self, peer: &PeerId) -> usize {
+        self.pending
+            .values()
+            .filter(|(pending_peer, _)| pending_peer == peer)
+            .count()
+    }
+
+    /// Pick the peer to request the next chunk from
+    ///
+    /// Unscored candidates are tried first. Otherwise picks alternate between
+    /// the non-demoted candidates, and every `PROBE_INTERVAL`-th pick goes to
+    /// the demoted candidate that waited longest.
+    ///
+    /// # Returns
+    /// The chosen peer, or `None` if `candidates` is empty
+    pub fn select_source(&mut self, candidates: &[PeerId]) -> Option<PeerId> {
+        let chosen = match candidates {
+            [] => return None,
+            [only] => *only,
+            _ => self.choose(candidates),
+        };
+        self.picks += 1;
+        self.peers
+            .entry(chosen)
+            .or_insert_with(PeerRecord::new)
+            .last_picked = Some(Instant::now());
+        Some(chosen)
+    }
+
+    fn choose(&self, candidates: &[PeerId]) -> PeerId {
+        let score = |peer: &PeerId| self.peers.get(peer).and_then(PeerRecord::score);
+        let last_picked = |peer: &PeerId| self.peers.get(peer).and_then(|r| r.last_picked);
+
+        if let Some(unscored) = candidates
+            .iter()
+            .filter(|peer| score(peer).is_none())
+            .min_by_key(|peer| last_picked(peer))
+        {
+            return *unscored;
+        }
+
+        let best = candidates.iter().filter_map(score).fold(0.0_f64, f64::max);
+        let (active, demoted): (Vec<&PeerId>, Vec<&PeerId>) = candidates
+            .iter()
+            .partition(|peer| score(peer).unwrap_or(0.0) >= best * DEMOTION_RATIO);
+
+        if !demoted.is_empty() && self.picks % PROBE_INTERVAL == PROBE_INTERVAL - 1 {
+            if let Some(peer) = demoted.iter().min_by_key(|peer| last_picked(peer)) {
+                return **peer;
+            }
+        }
+        *active[(self.picks as usize) % active.len()]
+    }
+
+    /// Current scores of every peer that served or failed a chunk
+    ///
+    /// `demoted` is relative to the best scoring peer overall.
+    pub fn scores(&self) -> Vec<PeerScore> {
+        let best = self
+            .peers
+            .values()
+            .filter_map(PeerRecord::score)
+            .fold(0.0_f64, f64::max);
+        let mut scores: Vec<PeerScore> = self
+            .peers
+            .iter()
+            .filter(|(_, record)| record.successes + record.failures > 0)
+            .map(|(peer_id, record)| PeerScore {
+                peer_id: *peer_id,
+                successes: record.successes,
+                failures: record.failures,
+                success_rate: record.success_rate,
+                avg_latency: record.avg_latency.map(Duration::from_secs_f64),
+                demoted: record.score().unwrap_or(0.0) < best * DEMOTION_RATIO,
+            })
+            .collect();
+        scores.sort_by_key(|score| score.peer_id);
+        scores
+    }
+
+    /// Score of a single peer
+    pub fn score_of(&self, peer: &PeerId) -> Option<PeerScore> {
+        self.scores()
+            .into_iter()
+            .find(|score| score.peer_id == *peer)
+    }
+}
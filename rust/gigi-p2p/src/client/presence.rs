@@ -0,0 +1,122 @@
+//! Presence status broadcasting
+//!
+//! Every client subscribes to the `PRESENCE_TOPIC` GossipSub topic. The local
+//! status is published there when it changes, when a peer subscribes (so
+//! newcomers learn it without waiting) and periodically at a low rate, since
+//! GossipSub does not replay messages. Received statuses are stored in
+//! `PeerInfo::status` and reported as `PresenceChanged`.
+//!
+//! Presence is ephemeral: it is never written to the message store.
+
+use anyhow::Result;
+use gigi_logging::{debug, warn};
+use libp2p::{gossipsub::IdentTopic, Swarm};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::behaviour::{PresenceMessage, UnifiedBehaviour, PRESENCE_TOPIC};
+
+/// Local presence status and its rebroadcast schedule
+pub struct PresenceManager {
+    status: Option<String>,
+    interval: Duration,
+    next_broadcast: Instant,
+}
+
+impl PresenceManager {
+    /// Create a manager without a status
+    ///
+    /// # Arguments
+    /// * `interval` - Time between rebroadcasts of an unchanged status
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            status: None,
+            interval,
+            next_broadcast: Instant::now() + interval,
+        }
+    }
+
+    /// The presence topic
+    pub fn topic() -> IdentTopic {
+        IdentTopic::new(PRESENCE_TOPIC)
+    }
+
+    /// Subscribe a freshly built swarm to the presence topic
+    pub fn subscribe(swarm: &mut Swarm<UnifiedBehaviour>) -> Result<()> {
+        swarm.behaviour_mut().gossipsub.subscribe(&Self::topic())?;
+        Ok(())
+    }
+
+    /// Change the time between rebroadcasts
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+        self.next_broadcast = Instant::now() + interval;
+    }
+
+    /// The local status, if one is set
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    /// Set or clear the local status, publishing it if it changed
+    ///
+    /// # Returns
+    /// `true` if the status changed
+    pub fn set_status(
+        &mut self,
+        status: Option<String>,
+        swarm: &mut Swarm<UnifiedBehaviour>,
+    ) -> bool {
+        if self.status == status {
+            return false;
+        }
+        self.status = status;
+        self.publish(swarm);
+        true
+    }
+
+    /// When the status is next rebroadcast, `None` while no status is set
+    pub fn next_broadcast(&self) -> Option<Instant> {
+        self.status.as_ref().map(|_| self.next_broadcast)
+    }
+
+    /// Rebroadcast the status, if one is set
+    pub fn broadcast(&mut self, swarm: &mut Swarm<UnifiedBehaviour>) {
+        if self.status.is_some() {
+            self.publish(swarm);
+        }
+    }
+
+    /// Publish the current status; a cleared status is sent as empty
+    fn publish(&mut self, swarm: &mut Swarm<UnifiedBehaviour>) {
+        self.next_broadcast = Instant::now() + self.interval;
+        let message = PresenceMessage {
+            status: self.status.clone().unwrap_or_default(),
+            timestamp_ms: chrono::Utc::now().timestamp_millis() as u64,
+        };
+        let data = match serde_json::to_vec(&message) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to serialize presence: {}", e);
+                return;
+            }
+        };
+        // Fails while no peer is subscribed; the next rebroadcast retries
+        if let Err(e) = swarm.behaviour_mut().gossipsub.publish(Self::topic(), data) {
+            debug!("Presence not published: {}", e);
+        }
+    }
+
+    /// Decode a received presence message
+    ///
+    /// # Returns
+    /// `Some(status)`, where `None` means the peer cleared its status, or
+    /// `None` if the data is not a presence message
+    pub fn decode(data: &[u8]) -> Option<Option<String>> {
+        let message = serde_json::from_slice::<PresenceMessage>(data).ok()?;
+        if crate::validation::validate_status(&message.status).is_err() {
+            return None;
+        }
+        Some(Some(message.status).filter(|status| !status.is_empty()))
+    }
+}
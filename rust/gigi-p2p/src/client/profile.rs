@@ -0,0 +1,121 @@
+//! Contact profile exchange
+//!
+//! Adding a contact with `P2pClient::add_contact` sends the local profile to
+//! the contact over `PROFILE_PROTOCOL`, and the contact answers with its own.
+//! Each side stores the other's profile through `ContactManager`; the
+//! responder only does so if it has the requester as a contact too. A profile
+//! is accepted only if its public key belongs to the peer that sent it.
+//!
+//! Peers without the protocol fail the request with `UnsupportedProtocols`;
+//! the contact is then kept without a profile.
+
+use libp2p::{identity::PublicKey, PeerId};
+
+use crate::behaviour::{ProfileExchange, PROFILE_EXCHANGE_VERSION};
+use crate::events::Profile;
+use crate::validation;
+
+/// Protocol name of the profile exchange
+pub const PROFILE_PROTOCOL: &str = "/gigi/profile/1.0.0";
+
+/// The profile this client sends to its contacts
+pub struct LocalProfile {
+    display_name: Option<String>,
+    public_key: String,
+    avatar_hash: Option<String>,
+}
+
+impl LocalProfile {
+    /// Create a profile that uses the nickname as display name
+    ///
+    /// # Arguments
+    /// * `public_key` - The public key of the client's identity
+    pub fn new(public_key: &PublicKey) -> Self {
+        Self {
+            display_name: None,
+            public_key: encode_public_key(public_key),
+            avatar_hash: None,
+        }
+    }
+
+    /// Set the display name, `None` to follow the nickname
+    pub fn set_display_name(&mut self, display_name: Option<String>) {
+        self.display_name = display_name;
+    }
+
+    /// Set the avatar hash, `None` for no avatar
+    pub fn set_avatar_hash(&mut self, avatar_hash: Option<String>) {
+        self.avatar_hash = avatar_hash;
+    }
+
+    /// Hash of the current avatar, if one is set
+    pub fn avatar_hash(&self) -> Option<&str> {
+        self.avatar_hash.as_deref()
+    }
+
+    /// The profile as sent to contacts
+    ///
+    /// # Arguments
+    /// * `nickname` - Current nickname, used when no display name is set
+    pub fn profile(&self, nickname: &str) -> Profile {
+        Profile {
+            display_name: self
+                .display_name
+                .clone()
+                .unwrap_or_else(|| nickname.to_string()),
+            public_key: self.public_key.clone(),
+            avatar_hash: self.avatar_hash.clone(),
+        }
+    }
+
+    /// The exchange message carrying the profile
+    pub fn exchange(&self, nickname: &str) -> ProfileExchange {
+        ProfileExchange {
+            version: PROFILE_EXCHANGE_VERSION,
+            profile: self.profile(nickname),
+        }
+    }
+}
+
+/// Hex-encode a public key in its protobuf encoding
+pub fn encode_public_key(key: &PublicKey) -> String {
+    key.encode_protobuf()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Decode a key produced by `encode_public_key`
+fn decode_public_key(encoded: &str) -> Option<PublicKey> {
+    if !encoded.len().is_multiple_of(2) || !encoded.is_ascii() {
+        return None;
+    }
+    let bytes = (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    PublicKey::try_decode_protobuf(&bytes).ok()
+}
+
+/// Check a profile received from `peer`
+///
+/// Exchanges of newer versions are accepted; their unknown fields were
+/// already dropped when decoding.
+///
+/// # Returns
+/// The profile, or why it was rejected
+pub fn accept(peer: &PeerId, exchange: ProfileExchange) -> Result<Profile, String> {
+    if exchange.version == 0 {
+        return Err("Unsupported profile exchange version 0".to_string());
+    }
+    let profile = exchange.profile;
+    validation::validate_nickname(&profile.display_name).map_err(|e| e.to_string())?;
+    if let Some(avatar_hash) = &profile.avatar_hash {
+        validation::validate_avatar_hash(avatar_hash).map_err(|e| e.to_string())?;
+    }
+    match decode_public_key(&profile.public_key) {
+        Some(key) if key.to_peer_id() == *peer => Ok(profile),
+        Some(_) => Err("Public key does not match the peer ID".to_string()),
+        None => Err("Invalid public key".to_string()),
+    }
+}
@@ -0,0 +1,166 @@
+//! Throttling of transfer progress events
+//!
+//! Every received or served chunk is a progress update, far more than most
+//! UIs want to redraw. `ProgressGranularity` decides which updates become
+//! `FileDownloadProgress` and `FileUploadProgress` events; the final update
+//! of a transfer is always reported.
+
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// How often transfer progress events are emitted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProgressGranularity {
+    /// One event per chunk
+    PerChunk,
+    /// One event whenever the whole percentage changes, at most 100 per transfer
+    #[default]
+    PerPercent,
+    /// At most one event per interval
+    Interval(Duration),
+}
+
+/// Decides which progress updates of each transfer are reported
+///
+/// A transfer's state is dropped with its final update or `forget`, which
+/// must be called for every transfer that ends without one.
+pub(crate) struct ProgressThrottle {
+    granularity: ProgressGranularity,
+    /// When progress was last reported and at which percentage, per transfer
+    reported: HashMap<String, (Instant, usize)>,
+}
+
+impl ProgressThrottle {
+    pub fn new(granularity: ProgressGranularity) -> Self {
+        Self {
+            granularity,
+            reported: HashMap::new(),
+        }
+    }
+
+    pub fn set_granularity(&mut self, granularity: ProgressGranularity) {
+        self.granularity = granularity;
+    }
+
+    /// Start timing the intervals of transfer `id` from now
+    pub fn start(&mut self, id: &str) {
+        self.reported.insert(id.to_string(), (Instant::now(), 0));
+    }
+
+    /// Whether a progress update of transfer `id` should be emitted as an event
+    pub fn should_report(&mut self, id: &str, done: usize, total: usize) -> bool {
+        if done >= total {
+            self.reported.remove(id);
+            return true;
+        }
+
+        let now = Instant::now();
+        let percent = done * 100 / total;
+        let (reported_at, reported_percent) =
+            self.reported.entry(id.to_string()).or_insert((now, 0));
+        let report = match self.granularity {
+            ProgressGranularity::PerChunk => true,
+            ProgressGranularity::PerPercent => percent != *reported_percent,
+            ProgressGranularity::Interval(interval) => now.duration_since(*reported_at) >= interval,
+        };
+        if report {
+            *reported_at = now;
+            *reported_percent = percent;
+        }
+        report
+    }
+
+    /// Drop the state of a transfer that ended without its final update
+    pub fn forget(&mut self, id: &str) {
+        self.reported.remove(id);
+    }
+}
+
+/// Id of the upload of `share_code` to `peer`
+pub(crate) fn upload_id(share_code: &str, peer: &impl std::fmt::Display) -> String {
+    format!("{}_{}", share_code, peer)
+}
+
+/// Chunks served per upload, for `FileUploadProgress` events
+///
+/// An upload is one peer downloading one share code. A chunk requested again
+/// is only counted once. Uploads to a peer that disconnects are forgotten
+/// with `forget_peer`.
+pub(crate) struct UploadTracker {
+    served: HashMap<String, HashSet<usize>>,
+    /// Chunks served before a restart, counted on top of `served`
+    resumed: HashMap<String, usize>,
+    throttle: ProgressThrottle,
+}
+
+impl UploadTracker {
+    pub fn new(granularity: ProgressGranularity) -> Self {
+        Self {
+            served: HashMap::new(),
+            resumed: HashMap::new(),
+            throttle: ProgressThrottle::new(granularity),
+        }
+    }
+
+    pub fn set_granularity(&mut self, granularity: ProgressGranularity) {
+        self.throttle.set_granularity(granularity);
+    }
+
+    /// Continue counting `upload_id` from `served_chunks` served before a restart
+    ///
+    /// The peer is assumed to resume its download, so chunks served from now
+    /// on are counted on top, up to the total.
+    pub fn resume(&mut self, upload_id: &str, served_chunks: usize) {
+        self.resumed.insert(upload_id.to_string(), served_chunks);
+    }
+
+    /// Record a served chunk of `upload_id`
+    ///
+    /// # Returns
+    /// The number of distinct chunks served, including those resumed from,
+    /// if an event should be emitted
+    pub fn record_chunk(
+        &mut self,
+        upload_id: &str,
+        chunk_index: usize,
+        total_chunks: usize,
+    ) -> Option<usize> {
+        let served = self.served.entry(upload_id.to_string()).or_default();
+        if !served.insert(chunk_index) {
+            return None;
+        }
+        let resumed = self.resumed.get(upload_id).copied().unwrap_or(0);
+        let uploaded = (resumed + served.len()).min(total_chunks);
+        if uploaded >= total_chunks {
+            self.served.remove(upload_id);
+            self.resumed.remove(upload_id);
+        }
+        self.throttle
+            .should_report(upload_id, uploaded, total_chunks)
+            .then_some(uploaded)
+    }
+
+    /// Drop the uploads to `peer`, which disconnected
+    ///
+    /// With `keep_counts`, the number of chunks served is kept as if resumed
+    /// after a restart, so saved progress is not counted down when the peer
+    /// comes back; otherwise nothing of the uploads is kept.
+    pub fn forget_peer(&mut self, peer: &impl std::fmt::Display, keep_counts: bool) {
+        let suffix = format!("_{}", peer);
+        let ids: Vec<String> = self
+            .served
+            .keys()
+            .chain(self.resumed.keys())
+            .filter(|id| id.ends_with(&suffix))
+            .cloned()
+            .collect();
+        for id in ids {
+            let served = self.served.remove(&id).map_or(0, |served| served.len());
+            let resumed = self.resumed.remove(&id).unwrap_or(0);
+            if keep_counts {
+                self.resumed.insert(id.clone(), resumed + served);
+            }
+            self.throttle.forget(&id);
+        }
+    }
+}
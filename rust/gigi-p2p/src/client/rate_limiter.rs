@@ -0,0 +1,107 @@
+//! Per-peer inbound message rate limiting
+//!
+//! Each peer gets a token bucket holding up to `burst` messages that refills
+//! at `per_second` messages per second. A direct or group message that finds
+//! its author's bucket empty is dropped, and the first drop of a run is
+//! reported as `RateLimited`; the peer stays connected. Group messages count
+//! against their author, not the peer that relayed them.
+//!
+//! Presence broadcasts are not counted. Each part of a chunked group message
+//! counts as one message, so `burst` should allow for the largest expected
+//! message.
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Buckets kept before full ones are pruned
+const MAX_TRACKED_PEERS: usize = 1024;
+
+/// Inbound message rate allowed per peer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InboundRateLimit {
+    /// Sustained messages per second
+    pub per_second: f64,
+    /// Messages accepted at once after a quiet period
+    pub burst: u32,
+}
+
+/// Whether an inbound message may be processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Accepted,
+    /// Dropped; `first` is set for the first drop since the last accepted message
+    Dropped {
+        first: bool,
+    },
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    limited: bool,
+}
+
+/// Token buckets of the peers that sent messages recently
+#[derive(Debug, Default)]
+pub struct InboundRateLimiter {
+    limit: Option<InboundRateLimit>,
+    buckets: HashMap<PeerId, Bucket>,
+}
+
+impl InboundRateLimiter {
+    /// Create a limiter, `None` to accept every message
+    pub fn new(limit: Option<InboundRateLimit>) -> Self {
+        Self {
+            limit,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Change the limit; every peer starts again with a full bucket
+    pub fn set_limit(&mut self, limit: Option<InboundRateLimit>) {
+        self.limit = limit;
+        self.buckets.clear();
+    }
+
+    /// Count a message from `peer` against its bucket
+    pub fn admit(&mut self, peer: PeerId) -> Admission {
+        let Some(limit) = self.limit else {
+            return Admission::Accepted;
+        };
+        let now = Instant::now();
+        let burst = f64::from(limit.burst);
+        if self.buckets.len() >= MAX_TRACKED_PEERS && !self.buckets.contains_key(&peer) {
+            self.prune(limit, now);
+        }
+
+        let bucket = self.buckets.entry(peer).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            limited: false,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * limit.per_second).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.limited = false;
+            Admission::Accepted
+        } else {
+            let first = !bucket.limited;
+            bucket.limited = true;
+            Admission::Dropped { first }
+        }
+    }
+
+    /// Forget buckets that have refilled, as they behave like new ones
+    fn prune(&mut self, limit: InboundRateLimit, now: Instant) {
+        let burst = f64::from(limit.burst);
+        self.buckets.retain(|_, bucket| {
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens + elapsed * limit.per_second < burst
+        });
+    }
+}
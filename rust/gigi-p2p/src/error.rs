@@ -57,6 +57,28 @@ pub enum P2pError {
     #[error("Nickname not found: {0}")]
     NicknameNotFound(String),
 
+    /// Peer has not been discovered
+    ///
+    /// Occurs when sending to or downloading from a nickname that no
+    /// discovered peer has, e.g. because the peer is not online yet.
+    /// Discovery is asked to look for it.
+    #[error("Peer '{0}' has not been discovered")]
+    PeerNotDiscovered(String),
+
+    /// Peer was discovered but is not connected
+    ///
+    /// Occurs when sending to or downloading from a known peer whose
+    /// connection was lost, e.g. because it went offline.
+    #[error("Peer '{0}' is not online")]
+    PeerUnreachable(String),
+
+    /// This node is not connected to any network
+    ///
+    /// Occurs when sending or downloading while the swarm has no listeners
+    /// and no connections, e.g. because no network interface is up.
+    #[error("Not connected to the network")]
+    NotConnectedToNetwork,
+
     /// Group not found in group manager
     ///
     /// Occurs when attempting to send a message to a group that
@@ -132,4 +154,44 @@ pub enum P2pError {
     /// Occurs when user input fails validation (e.g., malicious content, too long).
     #[error("Invalid input: {0}")]
     InvalidInput(String),
+
+    /// Received chunk has the wrong length
+    ///
+    /// Occurs when a chunk's data length doesn't match the length expected
+    /// for its index (`CHUNK_SIZE` for all but the last chunk), e.g. when a
+    /// peer pads the final chunk to full size.
+    #[error("Chunk {chunk_index} length mismatch: expected {expected} bytes, got {actual}")]
+    ChunkLengthMismatch {
+        chunk_index: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// Message exceeds the maximum size
+    ///
+    /// Occurs when a group message is larger than the configured maximum
+    /// and chunking is disabled, or when it needs more parts than allowed.
+    #[error("Message too large: {size} bytes (limit {limit})")]
+    MessageTooLarge { size: usize, limit: usize },
+
+    /// Requested chunk starts beyond the end of the file
+    ///
+    /// Occurs when serving a chunk of a shared file that was truncated
+    /// after it was shared.
+    #[error("Chunk {chunk_index} is past the end of the file ({file_size} bytes)")]
+    ChunkPastEof { chunk_index: usize, file_size: u64 },
+
+    /// Location coordinates out of range
+    ///
+    /// Occurs when a shared location has a latitude outside -90..=90 or a
+    /// longitude outside -180..=180, or either is not a finite number.
+    #[error("Location out of range: lat {lat}, lon {lon}")]
+    InvalidLocation { lat: f64, lon: f64 },
+
+    /// Client is shutting down
+    ///
+    /// Occurs when new work is started, or the swarm is driven, after
+    /// `drain_and_shutdown` was called.
+    #[error("Client is shutting down")]
+    ShuttingDown,
 }
@@ -8,6 +8,7 @@ use std::path::PathBuf;
 
 // Re-export types from gigi-file-sharing for compatibility
 pub use gigi_file_sharing::{FileInfo, FilePath, SharedFile};
+pub use gigi_store::PollResults;
 
 /// Unified P2P event
 #[derive(Debug, Clone)]
@@ -26,12 +27,44 @@ pub enum P2pEvent {
         peer_id: PeerId,
         nickname: String,
     },
+    /// A peer broadcast a new presence status, or its status was cleared
+    /// because it cleared it or expired
+    PresenceChanged {
+        peer_id: PeerId,
+        nickname: String,
+        status: Option<String>,
+    },
+
+    // Contact events
+    /// A contact's profile was received in a profile exchange and stored
+    ContactProfileReceived {
+        peer_id: PeerId,
+        profile: Profile,
+    },
+    /// An avatar image was fetched from a peer and cached
+    AvatarFetched {
+        peer_id: PeerId,
+        avatar_hash: String,
+        path: PathBuf,
+    },
 
     // Direct messaging events
     DirectMessage {
         from: PeerId,
         from_nickname: String,
         message: String,
+        message_id: String,
+        /// Set for disappearing messages; store with `StoredMessage::disappear_after_secs`
+        disappear_after_secs: Option<u64>,
+        /// Original author of a forwarded message; store with `StoredMessage::forwarded_from`
+        forwarded_from: Option<String>,
+    },
+    /// Connectivity summary changed; see `P2pClient::connection_status`
+    ConnectivityChanged(ConnectionStatus),
+    /// An inbound connection was refused because a connection limit was reached
+    InboundConnectionRejected {
+        address: Multiaddr,
+        limit: u32,
     },
     DirectFileShareMessage {
         from: PeerId,
@@ -40,6 +73,8 @@ pub enum P2pEvent {
         filename: String,
         file_size: u64,
         file_type: String,
+        /// Original author of a forwarded file share
+        forwarded_from: Option<String>,
     },
     DirectGroupShareMessage {
         from: PeerId,
@@ -47,6 +82,19 @@ pub enum P2pEvent {
         group_id: String,
         group_name: String,
     },
+    /// A peer shared a location; stored first if persistence is enabled
+    DirectLocationMessage {
+        from: PeerId,
+        from_nickname: String,
+        message_id: String,
+        location: Location,
+    },
+
+    /// Messages from a peer exceed `P2pConfig::inbound_message_limit` and
+    /// are being dropped; sent once per run of dropped messages
+    RateLimited {
+        peer: PeerId,
+    },
 
     // Group messaging events
     GroupMessage {
@@ -54,6 +102,9 @@ pub enum P2pEvent {
         from_nickname: String,
         group: String,
         message: String,
+        message_id: String,
+        /// Original author of a forwarded message
+        forwarded_from: Option<String>,
     },
     GroupFileShareMessage {
         from: PeerId,
@@ -64,6 +115,27 @@ pub enum P2pEvent {
         file_size: u64,
         file_type: String,
         message: String,
+        /// Original author of a forwarded file share
+        forwarded_from: Option<String>,
+    },
+    /// A group member shared a location; stored first if persistence is enabled
+    GroupLocationMessage {
+        from: PeerId,
+        from_nickname: String,
+        group: String,
+        message_id: String,
+        location: Location,
+    },
+    /// A poll was created or voted on in a group, locally or by a peer
+    ///
+    /// `results` are recomputed from the stored votes, so they are `None`
+    /// without persistence or while the poll itself has not been received.
+    PollUpdated {
+        from: PeerId,
+        from_nickname: String,
+        group: String,
+        poll_id: String,
+        results: Option<PollResults>,
     },
     GroupJoined {
         group: String,
@@ -71,6 +143,15 @@ pub enum P2pEvent {
     GroupLeft {
         group: String,
     },
+    /// A peer subscribed to or left a joined group's topic
+    GroupMembershipChanged {
+        group: String,
+        peer_id: PeerId,
+        /// `true` if the peer is now subscribed
+        online: bool,
+        /// Peers subscribed to the topic after the change
+        online_count: usize,
+    },
 
     // File transfer events
     FileShareRequest {
@@ -101,6 +182,12 @@ pub enum P2pEvent {
         from: PeerId,
         files: Vec<FileInfo>,
     },
+    /// A connected peer announced that the files it shares changed
+    RemoteFilesUpdated {
+        from: PeerId,
+        /// Every file the peer shares now
+        files: Vec<FileInfo>,
+    },
     FileDownloadStarted {
         from: PeerId,
         from_nickname: String,
@@ -124,6 +211,14 @@ pub enum P2pEvent {
         from_peer_id: libp2p::PeerId,
         from_nickname: String,
         path: PathBuf,
+        /// Time from requesting the file to the verified download
+        duration: std::time::Duration,
+        /// Average transfer rate in bytes per second
+        average_speed: f64,
+        /// Size of the downloaded file in bytes
+        total_bytes: u64,
+        /// Number of chunks the file was transferred in
+        chunk_count: usize,
     },
     FileDownloadFailed {
         download_id: String,
@@ -132,12 +227,56 @@ pub enum P2pEvent {
         from_peer_id: libp2p::PeerId,
         from_nickname: String,
         error: String,
+        reason: DownloadFailureReason,
+    },
+    /// Bytes requested with `P2pClient::request_range` arrived; fewer than
+    /// requested at the end of the file
+    FileRangeReceived {
+        request_id: String,
+        from: PeerId,
+        range: FileRange,
+    },
+    /// A `P2pClient::request_range` request failed
+    FileRangeFailed {
+        request_id: String,
+        from: PeerId,
+        share_code: String,
+        error: String,
+    },
+    /// A peer downloaded more chunks of a file shared by this client
+    FileUploadProgress {
+        share_code: String,
+        filename: String,
+        to_peer_id: libp2p::PeerId,
+        to_nickname: String,
+        uploaded_chunks: usize,
+        total_chunks: usize,
+    },
+    /// A group member was served every chunk of a file sent to the group
+    GroupFileDownloadedByMember {
+        group: String,
+        share_code: String,
+        filename: String,
+        peer_id: PeerId,
+        nickname: String,
+        /// Members that downloaded the whole file so far
+        completed_count: usize,
+        /// Members tracked for the file
+        member_count: usize,
     },
 
     // System events
     ListeningOn {
         address: Multiaddr,
     },
+    /// A listen address is no longer bound, e.g. its interface went down
+    ListenStopped {
+        address: Multiaddr,
+    },
+    /// `P2pClient::update_config` changed these configuration fields
+    ConfigChanged {
+        changed_fields: Vec<String>,
+    },
     Connected {
         peer_id: PeerId,
         nickname: String,
@@ -146,13 +285,37 @@ pub enum P2pEvent {
         peer_id: PeerId,
         nickname: String,
     },
-    Error(String),
+    /// A connected peer identified itself with these protocols, sorted;
+    /// sent again only if they change
+    PeerProtocolsDiscovered {
+        peer_id: PeerId,
+        nickname: String,
+        protocols: Vec<String>,
+    },
+    /// A failure not tied to a call the application made, by category
+    Error(P2pErrorKind),
+    PeerIdChanged {
+        old_peer_id: PeerId,
+        new_peer_id: PeerId,
+    },
 
     // Persistence events
     PendingMessagesAvailable {
         peer: PeerId,
         nickname: String,
     },
+    /// A periodic sync batch sent queued messages to online peers
+    SyncProgress {
+        attempted: usize,
+        synced: usize,
+        failed: usize,
+    },
+    /// A queued message could not be synced and will be retried
+    SyncFailed {
+        message_id: String,
+        nickname: String,
+        error: String,
+    },
 }
 
 /// File information
@@ -160,10 +323,23 @@ pub enum P2pEvent {
 pub struct ChunkInfo {
     pub file_id: String,
     pub chunk_index: usize,
+    /// Serialized as a byte string; read from a byte string or, from peers
+    /// before file transfer 1.2, an array of integers
+    #[serde(with = "crate::behaviour::chunk_data")]
     pub data: Vec<u8>,
     pub hash: String,
 }
 
+/// Bytes of a file, as served for a byte range request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileRange {
+    pub file_id: String,
+    /// Offset of the first byte in the file
+    pub start: u64,
+    #[serde(with = "crate::behaviour::chunk_data")]
+    pub data: Vec<u8>,
+}
+
 /// Peer information
 #[derive(Debug, Clone)]
 pub struct PeerInfo {
@@ -172,20 +348,59 @@ pub struct PeerInfo {
     pub addresses: Vec<Multiaddr>,
     pub last_seen: std::time::Instant,
     pub connected: bool,
+    /// Last presence status the peer broadcast, not persisted
+    pub status: Option<String>,
+}
+
+/// Outcome of `P2pClient::resolve_nickname`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveResult {
+    /// The nickname belongs to a known peer
+    Resolved {
+        peer_id: PeerId,
+        /// When the peer was last discovered or connected
+        last_seen: std::time::Instant,
+        /// Whether the peer is connected right now
+        connected: bool,
+    },
+    /// The nickname is unknown; discovery was asked to look for peers, and
+    /// `PeerDiscovered` follows if the peer is found
+    Pending,
+}
+
+/// Profile metadata a peer tells its contacts about itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    /// Name the peer chose for itself, its nickname by default
+    pub display_name: String,
+    /// Hex-encoded protobuf public key, matching the peer ID
+    pub public_key: String,
+    /// Hash of the peer's avatar image, if it has one
+    #[serde(default)]
+    pub avatar_hash: Option<String>,
 }
 
 /// Group information
 #[derive(Debug, Clone)]
 pub struct GroupInfo {
+    /// Group id, also the GossipSub topic name
     pub name: String,
+    /// Name shown to users; equal to `name` unless the group id is derived
+    pub display_name: String,
     pub topic: IdentTopic,
     pub joined_at: DateTime<Utc>,
+    /// Peers seen participating in the group
     pub members: std::collections::HashSet<PeerId>,
+    /// Peers currently subscribed to the group's topic
+    pub online: std::collections::HashSet<PeerId>,
 }
 
 /// Group message format
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GroupMessage {
+    /// Sender-generated message id, empty for messages from older peers
+    #[serde(default)]
+    pub message_id: String,
     pub sender_nickname: String,
     pub content: String,
     pub timestamp: u64,
@@ -194,6 +409,83 @@ pub struct GroupMessage {
     pub filename: Option<String>,
     pub file_size: Option<u64>,
     pub file_type: Option<String>,
+    /// Set when a long text was split; parts share the same `message_id`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub part: Option<MessagePart>,
+    /// Set for polls and votes; `content` then holds a plain-text fallback
+    /// for peers that do not know polls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll: Option<PollMessage>,
+    /// Set for shared locations; `content` then holds a plain-text fallback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    /// Nickname of the original author, set on forwarded messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forwarded_from: Option<String>,
+}
+
+/// Where `P2pClient::forward_message` sends a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardTarget {
+    /// A peer, by nickname
+    Peer(String),
+    /// A joined group, by name
+    Group(String),
+}
+
+/// A pinned location shared in a direct or group message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    /// Latitude in degrees, -90 to 90
+    pub lat: f64,
+    /// Longitude in degrees, -180 to 180
+    pub lon: f64,
+    /// Optional place name
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl Location {
+    /// Plain-text form for peers and views that do not know locations
+    pub fn fallback_text(&self) -> String {
+        match &self.label {
+            Some(label) => format!("Location: {} ({}, {})", label, self.lat, self.lon),
+            None => format!("Location: {}, {}", self.lat, self.lon),
+        }
+    }
+}
+
+/// Poll or vote carried by a group message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PollMessage {
+    /// A new poll; its id is also the group message id
+    Poll {
+        id: String,
+        question: String,
+        options: Vec<String>,
+    },
+    /// A vote for one option of a poll; a peer's latest vote replaces
+    /// its earlier ones
+    PollVote { poll_id: String, option_index: u32 },
+}
+
+impl PollMessage {
+    /// Id of the poll this message creates or votes in
+    pub fn poll_id(&self) -> &str {
+        match self {
+            Self::Poll { id, .. } => id,
+            Self::PollVote { poll_id, .. } => poll_id,
+        }
+    }
+}
+
+/// Position of one part of a chunked group message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessagePart {
+    /// Zero-based index of this part
+    pub index: u32,
+    /// Total number of parts
+    pub count: u32,
 }
 
 /// Active download tracking for mobile UI applications
@@ -207,12 +499,130 @@ pub struct ActiveDownload {
     pub total_chunks: usize,
     pub downloaded_chunks: usize,
     pub started_at: std::time::Instant,
+    /// When the file info or a chunk last arrived, or the download started
+    pub last_progress_at: std::time::Instant,
     pub completed: bool,
     pub failed: bool,
     pub error_message: Option<String>,
     pub final_path: Option<PathBuf>,
 }
 
+/// Detailed progress snapshot of a tracked download for transfer lists
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadDetail {
+    pub download_id: String,
+    pub share_code: String,
+    pub filename: String,
+    pub from_nickname: String,
+    pub downloaded_chunks: usize,
+    pub total_chunks: usize,
+    /// Bytes written so far
+    pub bytes_done: u64,
+    /// Average transfer speed in bytes per second since the download started
+    pub speed: f64,
+    /// Where the file was written, once the download completed
+    pub final_path: Option<PathBuf>,
+}
+
+/// Why a download failed, for user-facing messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadFailureReason {
+    /// The sharer revoked the file; it is no longer available
+    Revoked,
+    /// The sharer does not know the share code
+    NotFound,
+    /// The shared file changed on the sharer's disk during the transfer
+    Changed,
+    /// Nothing arrived from the sharer for `P2pConfig::download_idle_timeout`
+    Timeout,
+    /// The sharer gave no whole-file hash and `P2pConfig::strict_hash` is on
+    Unverified,
+    /// The sharer's file did not have the size or hash of the `DownloadRequest`
+    UnexpectedFile,
+    /// Transfer, verification or local I/O error; see the error message
+    Other,
+}
+
+/// Category of a failure reported through `P2pEvent::Error`
+///
+/// Each variant carries a human-readable description of what went wrong.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum P2pErrorKind {
+    /// A request to a peer could not be delivered
+    #[error("Network error: {0}")]
+    Network(String),
+    /// A peer answered a file transfer request with an error
+    #[error("Transfer error: {0}")]
+    Transfer(String),
+    /// Writing to or reading from the persistence store failed
+    #[error("Storage error: {0}")]
+    Storage(String),
+    /// A peer sent data that was rejected as invalid
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+}
+
+impl P2pErrorKind {
+    /// Description of the failure, without the category
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Network(message)
+            | Self::Transfer(message)
+            | Self::Storage(message)
+            | Self::Protocol(message) => message,
+        }
+    }
+}
+
+/// Summary of the client's connectivity for status indicators
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionStatus {
+    /// Whether at least one listener is active
+    pub listening: bool,
+    /// Number of known peers with an open connection
+    pub connected_peers: usize,
+    /// Number of peers known from discovery, connected or not
+    pub discovered_peers: usize,
+    /// Whether mDNS discovery is running on at least one interface
+    pub mdns_active: bool,
+}
+
+/// Copy of the client state at one point in time
+///
+/// Lets a frontend resync in one call, e.g. after a reload, instead of
+/// replaying events.
+#[derive(Debug, Clone)]
+pub struct StateSnapshot {
+    /// Known peers sorted by nickname; `connected` is their connection state
+    pub peers: Vec<PeerInfo>,
+    /// Connectivity summary
+    pub connection_status: ConnectionStatus,
+    /// Tracked downloads sorted by start time
+    pub downloads: Vec<ActiveDownload>,
+    /// Shared files sorted by share code
+    pub shared_files: Vec<SharedFile>,
+    /// Joined groups sorted by name
+    pub groups: Vec<GroupInfo>,
+}
+
+/// Outcome of sending a group message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GroupSendStatus {
+    /// Published to the GossipSub topic
+    Sent { message_id: String },
+    /// No subscribed peers yet; buffered until one subscribes to the topic
+    Queued { message_id: String },
+}
+
+impl GroupSendStatus {
+    /// Id of the message regardless of whether it was sent or queued
+    pub fn message_id(&self) -> &str {
+        match self {
+            Self::Sent { message_id } | Self::Queued { message_id } => message_id,
+        }
+    }
+}
+
 // ============================================================================
 // Message Persistence Types
 // ============================================================================
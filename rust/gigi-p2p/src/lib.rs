@@ -1,7 +1,8 @@
 //! Gigi P2P - A comprehensive peer-to-peer networking library
 //!
 //! This library provides unified P2P functionality for the Gigi ecosystem including:
-//! - **Auto Discovery**: Automatic peer discovery via gigi-dns (local network) and Kademlia DHT (WAN)
+//! - **Auto Discovery**: Automatic peer discovery via gigi-dns (local network) and Kademlia DHT (WAN),
+//!   or through a custom [`Discovery`] backend
 //! - **NAT Traversal**: Circuit relay for connecting peers behind routers
 //! - **Direct Messaging**: 1-to-1 peer communication via request-response protocol
 //! - **Group Messaging**: Publish-subscribe model using GossipSub for group chats
@@ -29,10 +30,12 @@
 //! | Direct Messaging | 1-to-1 communication | Request-Response (CBOR) |
 //! | Group Messaging | Group chat with pub/sub | GossipSub |
 //! | File Sharing | Chunked file transfer | Request-Response (CBOR) |
+//! | Identify | Supported protocols of each peer | Identify |
 //!
 //! # Event-Driven Architecture
 //!
-//! All P2P activities are emitted as events via an `mpsc::UnboundedSender<P2pEvent>`:
+//! All P2P activities are emitted as events via an [`EventReceiver`] stream,
+//! unbounded unless `P2pConfig::event_channel_capacity` is set:
 //!
 //! ```no_run
 //! use gigi_p2p::P2pClient;
@@ -79,6 +82,7 @@
 //! 3. **Request**: Receiver uses `download_file()` with the share code
 //! 4. **Transfer**: File split into 256KB chunks, transferred on-demand
 //! 5. **Verify**: Each chunk verified with Blake3 hash, final file verified with SHA256
+//!    (or BLAKE3 when the sharer sets `P2pConfig::file_hash_algo`)
 //!
 //! This pull-based approach is efficient for group chats:
 //! - No need to broadcast large files
@@ -154,7 +158,8 @@ pub mod validation;
 ///
 /// This is a convenience function for consumers who want to use default
 /// logging configuration. Advanced users should set up their own logging
-/// with custom filters and formatters using gigi-logging.
+/// with custom filters and formatters using gigi-logging, or pass a
+/// `LogConfig` to `init_logging_with_config`.
 ///
 /// # Example
 ///
@@ -182,30 +187,70 @@ pub fn init_logging() {
 /// gigi_p2p::init_logging_with_level(Level::DEBUG);
 /// ```
 pub fn init_logging_with_level(level: tracing::Level) {
-    use gigi_logging::{init_logging_with_config, LogConfig};
-
-    let config = LogConfig {
+    init_logging_with_config(LogConfig {
         level,
         ..Default::default()
-    };
+    });
+}
 
-    init_logging_with_config(config);
+/// Initialize logging with a custom level, format and output
+///
+/// Does nothing if logging was already initialized or the app installed
+/// its own global tracing subscriber, so an app and a plugin embedding
+/// gigi may both call it.
+///
+/// # Returns
+/// Whether this call installed the subscriber
+///
+/// # Example
+///
+/// ```no_run
+/// use gigi_p2p::{LogConfig, LogOutput};
+/// use tracing::Level;
+///
+/// gigi_p2p::init_logging_with_config(LogConfig {
+///     output: LogOutput::Console,
+///     level: Level::DEBUG,
+///     json: true,
+///     include_spans: false,
+/// });
+/// ```
+pub fn init_logging_with_config(config: LogConfig) -> bool {
+    gigi_logging::try_init_logging_with_config(config)
 }
 
 // Re-export public API
+pub use behaviour::{FileTransferCapabilities, FileTransferVersion, MAX_RANGE_SIZE};
+pub use client::InboundRateLimit;
 pub use client::P2pClient;
 pub use client::P2pConfig;
-pub use client::CHUNK_SIZE;
+pub use client::ProgressGranularity;
+pub use client::{display_name_for, UnnamedPeerLabel};
+pub use client::{event_channel, EventChannelError, EventReceiver, EventSender};
+pub use client::{AvatarCacheStats, MAX_AVATAR_SIZE};
+pub use client::{ChunkCache, ChunkCacheStats, ChunkPrefetcher, PrefetchStats};
+pub use client::{ClientCommand, DownloadCompleteHook};
+pub use client::{DiagnosticCheck, DiagnosticResult, DiagnosticStatus, Diagnostics};
+pub use client::{DirectoryShare, HashAlgo, ShareCancelToken, ShareOverwritePolicy, CHUNK_SIZE};
+pub use client::{Discovery, DiscoveryEvent, StaticDiscovery, StaticPeer};
+pub use client::{DownloadPriority, DownloadRequest};
+pub use client::{GroupFileDistributionStatus, MemberDownloadStatus};
+pub use client::{PeerScore, PeerScoreboard};
 pub use error::P2pError;
+pub use gigi_logging::{LogConfig, LogOutput};
 
 // Re-export persistence types from gigi-store
 pub use gigi_store::{
-    MessageContent, MessageDirection, MessageStore, PersistenceConfig, StoredMessage, SyncManager,
+    ContactInfo, DownloadHistoryEntry, DownloadHistoryStatus, MessageContent, MessageDirection,
+    MessageStore, PersistenceConfig, StoredMessage, SyncManager,
 };
 
 // Re-export other event types
 pub use events::{
-    ActiveDownload, ChunkInfo, FileInfo, GroupInfo, GroupMessage, P2pEvent, PeerInfo, SharedFile,
+    ActiveDownload, ChunkInfo, ConnectionStatus, DownloadDetail, DownloadFailureReason, FileInfo,
+    FileRange, ForwardTarget, GroupInfo, GroupMessage, GroupSendStatus, Location, MessagePart,
+    P2pErrorKind, P2pEvent, PeerInfo, PollMessage, PollResults, Profile, ResolveResult, SharedFile,
+    StateSnapshot,
 };
 
 /// Re-export commonly used libp2p types for convenience
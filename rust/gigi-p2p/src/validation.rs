@@ -5,7 +5,7 @@
 
 use crate::P2pError;
 use libp2p::PeerId;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 const MAX_NICKNAME_LENGTH: usize = 64;
 const MAX_MESSAGE_LENGTH: usize = 100_000; // 100KB
@@ -13,6 +13,12 @@ const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024 * 1024; // 5GB
 const MAX_GROUP_NAME_LENGTH: usize = 128;
 const MAX_SHARE_CODE_LENGTH: usize = 256;
 const MAX_URI_LENGTH: usize = 2048;
+const MAX_STATUS_LENGTH: usize = 128;
+const MAX_AVATAR_HASH_LENGTH: usize = 128;
+const MAX_POLL_QUESTION_LENGTH: usize = 512;
+const MAX_POLL_OPTION_LENGTH: usize = 128;
+const MAX_POLL_OPTIONS: usize = 16;
+const MAX_LOCATION_LABEL_LENGTH: usize = 256;
 
 /// Validate a nickname
 ///
@@ -134,6 +140,133 @@ pub fn validate_group_name(group_name: &str) -> Result<(), P2pError> {
     Ok(())
 }
 
+/// Validate a presence status
+///
+/// Statuses are free-form text, so only the length and control characters
+/// are checked. An empty status is valid and clears the status.
+///
+/// # Arguments
+/// * `status` - The status to validate
+///
+/// # Returns
+/// Ok if valid, Err(P2pError) if invalid
+pub fn validate_status(status: &str) -> Result<(), P2pError> {
+    if status.len() > MAX_STATUS_LENGTH {
+        return Err(P2pError::InvalidInput(format!(
+            "Status too long (max {} characters)",
+            MAX_STATUS_LENGTH
+        )));
+    }
+
+    if status.chars().any(char::is_control) {
+        return Err(P2pError::InvalidInput(
+            "Status contains control characters".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validate an avatar hash
+///
+/// Ensures the hash is short and alphanumeric, as hex or base58 digests are.
+///
+/// # Arguments
+/// * `avatar_hash` - The avatar hash to validate
+///
+/// # Returns
+/// Ok if valid, Err(P2pError) if invalid
+pub fn validate_avatar_hash(avatar_hash: &str) -> Result<(), P2pError> {
+    if avatar_hash.is_empty() || avatar_hash.len() > MAX_AVATAR_HASH_LENGTH {
+        return Err(P2pError::InvalidInput(format!(
+            "Avatar hash must be 1 to {} characters",
+            MAX_AVATAR_HASH_LENGTH
+        )));
+    }
+
+    if !avatar_hash.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(P2pError::InvalidInput(
+            "Avatar hash contains invalid characters".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validate a poll
+///
+/// Ensures the poll has a question and 2 to 16 non-empty options, all
+/// within length limits.
+///
+/// # Arguments
+/// * `question` - The poll question
+/// * `options` - The options to vote for
+///
+/// # Returns
+/// Ok if valid, Err(P2pError) if invalid
+pub fn validate_poll(question: &str, options: &[String]) -> Result<(), P2pError> {
+    if question.trim().is_empty() {
+        return Err(P2pError::InvalidInput(
+            "Poll question cannot be empty".into(),
+        ));
+    }
+
+    if question.len() > MAX_POLL_QUESTION_LENGTH {
+        return Err(P2pError::InvalidInput(format!(
+            "Poll question too long (max {} characters)",
+            MAX_POLL_QUESTION_LENGTH
+        )));
+    }
+
+    if !(2..=MAX_POLL_OPTIONS).contains(&options.len()) {
+        return Err(P2pError::InvalidInput(format!(
+            "Poll must have 2 to {} options",
+            MAX_POLL_OPTIONS
+        )));
+    }
+
+    for option in options {
+        if option.trim().is_empty() || option.len() > MAX_POLL_OPTION_LENGTH {
+            return Err(P2pError::InvalidInput(format!(
+                "Poll options must be 1 to {} characters",
+                MAX_POLL_OPTION_LENGTH
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Validate a shared location
+///
+/// Ensures the coordinates are finite and within range, and the optional
+/// label is not empty and within length limits.
+///
+/// # Arguments
+/// * `lat` - Latitude in degrees, -90 to 90
+/// * `lon` - Longitude in degrees, -180 to 180
+/// * `label` - Optional place name
+///
+/// # Returns
+/// Ok if valid, Err(P2pError::InvalidLocation) for out-of-range coordinates,
+/// Err(P2pError::InvalidInput) for an invalid label
+pub fn validate_location(lat: f64, lon: f64, label: Option<&str>) -> Result<(), P2pError> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(P2pError::InvalidLocation { lat, lon });
+    }
+
+    if let Some(label) = label {
+        if label.trim().is_empty() || label.len() > MAX_LOCATION_LABEL_LENGTH {
+            return Err(P2pError::InvalidInput(format!(
+                "Location label must be 1 to {} characters",
+                MAX_LOCATION_LABEL_LENGTH
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 /// Validate a share code
 ///
 /// Ensures share code format is valid.
@@ -256,6 +389,71 @@ pub fn validate_file_path(path: &Path) -> Result<(), P2pError> {
     Ok(())
 }
 
+/// Validate the length of a received chunk
+///
+/// Every chunk is `CHUNK_SIZE` bytes except the last, which holds the
+/// remainder of the file. Rejects chunks that are short, padded, or past
+/// the end of the file so they never overrun the output file.
+///
+/// # Arguments
+/// * `file_size` - Total size of the file in bytes
+/// * `chunk_index` - Index of the received chunk
+/// * `actual_len` - Length of the received chunk data
+///
+/// # Returns
+/// Ok if valid, Err(P2pError::ChunkLengthMismatch) if invalid
+pub fn validate_chunk_length(
+    file_size: u64,
+    chunk_index: usize,
+    actual_len: usize,
+) -> Result<(), P2pError> {
+    let chunk_size = gigi_file_sharing::CHUNK_SIZE as u64;
+    let offset = (chunk_index as u64).saturating_mul(chunk_size);
+    let expected = file_size.saturating_sub(offset).min(chunk_size) as usize;
+
+    if actual_len != expected {
+        return Err(P2pError::ChunkLengthMismatch {
+            chunk_index,
+            expected,
+            actual: actual_len,
+        });
+    }
+
+    Ok(())
+}
+
+/// Validate the length of a received chunk of a file
+///
+/// Like `validate_chunk_length`, but content-defined chunks must span
+/// exactly from their offset in `FileInfo::chunk_offsets` to the next one,
+/// and indices past the last offset are rejected.
+///
+/// # Arguments
+/// * `info` - The file the chunk belongs to
+/// * `chunk_index` - Index of the received chunk
+/// * `actual_len` - Length of the received chunk data
+///
+/// # Returns
+/// Ok if valid, Err(P2pError::ChunkLengthMismatch) if invalid
+pub fn validate_file_chunk_length(
+    info: &crate::events::FileInfo,
+    chunk_index: usize,
+    actual_len: usize,
+) -> Result<(), P2pError> {
+    if info.chunk_offsets.is_none() {
+        return validate_chunk_length(info.size, chunk_index, actual_len);
+    }
+
+    match info.chunk_span(chunk_index) {
+        Some((_, expected)) if expected == actual_len => Ok(()),
+        span => Err(P2pError::ChunkLengthMismatch {
+            chunk_index,
+            expected: span.map_or(0, |(_, len)| len),
+            actual: actual_len,
+        }),
+    }
+}
+
 /// Sanitize a string for safe display
 ///
 /// Removes or escapes potentially dangerous characters.
@@ -273,3 +471,98 @@ pub fn sanitize_string(input: &str) -> String {
         .replace('\'', "&#x27;")
         .replace('&', "&amp;")
 }
+
+/// Sanitize a nickname for use as a folder name
+///
+/// Replaces path separators and other characters that are unsafe in file
+/// names with underscores, and falls back to `"unknown"` when nothing
+/// usable remains.
+///
+/// # Arguments
+/// * `name` - The nickname to sanitize
+///
+/// # Returns
+/// A single safe path component
+pub fn sanitize_folder_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let sanitized = sanitized.trim().trim_matches('.');
+
+    if sanitized.is_empty() {
+        "unknown".to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Folders of a relative path received from a peer, as a safe relative path
+///
+/// Drops the last component, which names the file and is sanitized with
+/// `sanitize_filename`. Empty, `.` and `..` segments are skipped, so absolute
+/// paths and traversal stay below the download directory, and each remaining
+/// segment is sanitized like a folder name.
+///
+/// # Arguments
+/// * `relative_path` - The untrusted path, e.g. `FileInfo::relative_path`,
+///   split on `/` and `\`
+///
+/// # Returns
+/// The folders to create, empty when there are none
+pub fn sanitize_relative_directory(relative_path: &str) -> PathBuf {
+    let mut segments: Vec<&str> = relative_path.split(['/', '\\']).collect();
+    segments.pop();
+    segments
+        .into_iter()
+        .map(str::trim)
+        .filter(|segment| !matches!(*segment, "" | "." | ".."))
+        .map(sanitize_folder_name)
+        .collect()
+}
+
+/// Sanitize a filename received from a peer
+///
+/// Keeps only the last path component, so directory parts, `..` segments and
+/// absolute paths (Unix or Windows style) cannot escape the download
+/// directory. Null bytes and other control characters are dropped, characters
+/// that are unsafe in file names are replaced with underscores, and the
+/// result is capped at 255 bytes. Falls back to `"download"` when nothing
+/// usable remains.
+///
+/// # Arguments
+/// * `name` - The untrusted filename, e.g. `FileInfo::name`
+///
+/// # Returns
+/// A single safe path component
+pub fn sanitize_filename(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let mut sanitized: String = base
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c {
+            ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string();
+
+    if sanitized.len() > 255 {
+        let mut end = 255;
+        while !sanitized.is_char_boundary(end) {
+            end -= 1;
+        }
+        sanitized.truncate(end);
+    }
+
+    if sanitized.chars().all(|c| c == '.') {
+        "download".to_string()
+    } else {
+        sanitized
+    }
+}
@@ -0,0 +1,129 @@
+//! Tests for the cache of recently served chunks
+
+mod common;
+
+use common::{connect, create_peer};
+use futures::StreamExt;
+use gigi_p2p::{ChunkCache, ChunkCacheStats, ChunkInfo, P2pEvent, CHUNK_SIZE};
+use std::time::Duration;
+use tokio::time::Instant;
+
+fn chunk(file_id: &str, chunk_index: usize, size: usize) -> ChunkInfo {
+    ChunkInfo {
+        file_id: file_id.to_string(),
+        chunk_index,
+        data: vec![chunk_index as u8; size],
+        hash: String::new(),
+    }
+}
+
+#[test]
+fn test_cache_is_bounded_by_bytes() {
+    let cache = ChunkCache::new(100);
+    cache.insert(chunk("file", 0, 40), "hash");
+    cache.insert(chunk("file", 1, 40), "hash");
+    // Using chunk 0 makes chunk 1 the least recently used
+    assert!(cache.get("file", 0, "hash").is_some());
+    cache.insert(chunk("file", 2, 40), "hash");
+
+    assert!(cache.get("file", 1, "hash").is_none());
+    assert_eq!(cache.get("file", 0, "hash").unwrap().data, vec![0; 40]);
+    assert_eq!(cache.get("file", 2, "hash").unwrap().data, vec![2; 40]);
+    assert_eq!(
+        cache.stats(),
+        ChunkCacheStats {
+            hits: 3,
+            misses: 1,
+            cached_chunks: 2,
+            cached_bytes: 80,
+        }
+    );
+
+    // Larger than the whole cache: never cached
+    cache.insert(chunk("file", 3, 101), "hash");
+    assert!(cache.get("file", 3, "hash").is_none());
+    assert_eq!(cache.stats().cached_bytes, 80);
+
+    // Shrinking evicts what no longer fits
+    cache.set_max_bytes(40);
+    assert_eq!(cache.stats().cached_chunks, 1);
+}
+
+#[test]
+fn test_cache_invalidated_when_file_changes() {
+    let cache = ChunkCache::new(1024);
+    cache.insert(chunk("file", 0, 10), "old-hash");
+    cache.insert(chunk("other", 0, 10), "hash");
+
+    // Re-shared with new contents
+    assert!(cache.get("file", 0, "new-hash").is_none());
+    assert!(cache.get("file", 0, "old-hash").is_none());
+
+    cache.forget_file("other");
+    assert!(cache.get("other", 0, "hash").is_none());
+    assert_eq!(cache.stats().cached_bytes, 0);
+
+    // Disabled cache keeps nothing
+    let disabled = ChunkCache::new(0);
+    disabled.insert(chunk("file", 0, 10), "hash");
+    assert!(disabled.get("file", 0, "hash").is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_concurrent_downloads_share_cached_chunks() {
+    let mut alice = create_peer("alice-cache");
+    let mut bob = create_peer("bob-cache");
+    let mut carol = create_peer("carol-cache");
+    connect(&mut alice, &mut bob).await;
+    connect(&mut alice, &mut carol).await;
+
+    let chunk_count = 4;
+    let contents: Vec<u8> = (0..CHUNK_SIZE * chunk_count)
+        .map(|i| (i % 251) as u8)
+        .collect();
+    let file = alice.dir.path().join("popular.bin");
+    std::fs::write(&file, &contents).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+
+    let bob_download = bob
+        .client
+        .download_file("alice-cache", &share_code)
+        .unwrap();
+    let carol_download = carol
+        .client
+        .download_file("alice-cache", &share_code)
+        .unwrap();
+
+    let mut completed = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while completed.len() < 2 {
+        tokio::select! {
+            _ = alice.client.handle_next_swarm_event() => {}
+            _ = bob.client.handle_next_swarm_event() => {}
+            _ = carol.client.handle_next_swarm_event() => {}
+            Some(_) = alice.events.next() => {}
+            Some(event) = bob.events.next() => {
+                if let P2pEvent::FileDownloadCompleted { download_id, path, .. } = event {
+                    assert_eq!(download_id, bob_download);
+                    completed.push(path);
+                }
+            }
+            Some(event) = carol.events.next() => {
+                if let P2pEvent::FileDownloadCompleted { download_id, path, .. } = event {
+                    assert_eq!(download_id, carol_download);
+                    completed.push(path);
+                }
+            }
+            _ = tokio::time::sleep_until(deadline) => panic!("Downloads did not complete"),
+        }
+    }
+
+    for path in completed {
+        assert_eq!(std::fs::read(path).unwrap(), contents);
+    }
+    // Every chunk was read from disk once; the second download of it was a hit
+    let stats = alice.client.chunk_cache_stats();
+    assert_eq!(stats.misses, chunk_count as u64);
+    assert_eq!(stats.hits, chunk_count as u64);
+    assert_eq!(stats.cached_chunks, chunk_count);
+}
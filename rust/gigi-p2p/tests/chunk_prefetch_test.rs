@@ -0,0 +1,126 @@
+//! Server-side chunk read-ahead tests for gigi-p2p
+
+use gigi_p2p::{ChunkCache, ChunkInfo, ChunkPrefetcher, PeerId, PrefetchStats};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const FILE_ID: &str = "share-code";
+const FILE_HASH: &str = "file-hash";
+const TOTAL_CHUNKS: usize = 20;
+
+/// Loader producing a chunk filled with its index, counting disk reads
+fn loader(reads: &Arc<AtomicUsize>) -> impl Fn(usize) -> anyhow::Result<ChunkInfo> + Send {
+    let reads = Arc::clone(reads);
+    move |index| {
+        reads.fetch_add(1, Ordering::SeqCst);
+        Ok(ChunkInfo {
+            file_id: FILE_ID.to_string(),
+            chunk_index: index,
+            data: vec![index as u8; 16],
+            hash: String::new(),
+        })
+    }
+}
+
+/// Wait until no background read is running
+async fn settle(prefetcher: &ChunkPrefetcher) -> PrefetchStats {
+    for _ in 0..200 {
+        let stats = prefetcher.stats();
+        if stats.in_flight == 0 {
+            return stats;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("Background reads did not finish");
+}
+
+/// Serve a chunk the way the file sharing handler does
+async fn serve(
+    prefetcher: &ChunkPrefetcher,
+    peer: PeerId,
+    index: usize,
+    reads: &Arc<AtomicUsize>,
+) -> ChunkInfo {
+    let chunk = match prefetcher.get(peer, FILE_ID, index, FILE_HASH) {
+        Some(chunk) => chunk,
+        None => loader(reads)(index).unwrap(),
+    };
+    prefetcher.prefetch_after(peer, FILE_ID, index, TOTAL_CHUNKS, FILE_HASH, loader(reads));
+    settle(prefetcher).await;
+    chunk
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sequential_requests_hit_cache() {
+    let cache = ChunkCache::new(1024);
+    let prefetcher = ChunkPrefetcher::new(4, 8, cache.clone());
+    let reads = Arc::new(AtomicUsize::new(0));
+    let peer = PeerId::random();
+
+    for index in 0..TOTAL_CHUNKS {
+        let chunk = serve(&prefetcher, peer, index, &reads).await;
+        assert_eq!(chunk.chunk_index, index);
+        assert_eq!(chunk.data, vec![index as u8; 16]);
+    }
+
+    let stats = prefetcher.stats();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.hits, TOTAL_CHUNKS as u64 - 1);
+    // Each chunk is read exactly once, into the shared cache
+    assert_eq!(reads.load(Ordering::SeqCst), TOTAL_CHUNKS);
+    assert_eq!(cache.stats().hits, TOTAL_CHUNKS as u64 - 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_in_flight_chunks_do_not_block() {
+    let cache = ChunkCache::new(1024);
+    let prefetcher = ChunkPrefetcher::new(4, 8, cache);
+    let peer = PeerId::random();
+    let (release, released) = std::sync::mpsc::channel::<()>();
+    let released = std::sync::Mutex::new(released);
+
+    // The background read of chunk 1 is held until the lookup returned
+    prefetcher.prefetch_after(peer, FILE_ID, 0, TOTAL_CHUNKS, FILE_HASH, move |index| {
+        let _ = released.lock().unwrap().recv();
+        loader(&Arc::new(AtomicUsize::new(0)))(index)
+    });
+    assert!(prefetcher.stats().in_flight > 0);
+    assert!(prefetcher.get(peer, FILE_ID, 1, FILE_HASH).is_none());
+    assert_eq!(prefetcher.stats().misses, 1);
+
+    drop(release);
+    settle(&prefetcher).await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_random_access_stays_bounded() {
+    let read_ahead = 3;
+    let cache = ChunkCache::new(16 * 8);
+    let prefetcher = ChunkPrefetcher::new(read_ahead, 2, cache.clone());
+    let reads = Arc::new(AtomicUsize::new(0));
+    let peers: Vec<PeerId> = (0..5).map(|_| PeerId::random()).collect();
+
+    let mut index = 7;
+    for step in 0..50 {
+        index = (index * 13 + 5) % TOTAL_CHUNKS;
+        serve(&prefetcher, peers[step % peers.len()], index, &reads).await;
+
+        let stats = prefetcher.stats();
+        assert!(stats.in_flight <= read_ahead * 2);
+        assert!(cache.stats().cached_bytes <= 16 * 8);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_nothing_read_ahead_without_cache() {
+    let prefetcher = ChunkPrefetcher::new(4, 8, ChunkCache::new(0));
+    let reads = Arc::new(AtomicUsize::new(0));
+    let peer = PeerId::random();
+
+    serve(&prefetcher, peer, 0, &reads).await;
+    serve(&prefetcher, peer, 1, &reads).await;
+
+    assert_eq!(prefetcher.stats().hits, 0);
+    assert_eq!(reads.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,187 @@
+//! Shared helpers for end-to-end tests
+//!
+//! Clients listen on the local machine and discover each other via gigi-dns.
+
+#![allow(dead_code)]
+
+use futures::StreamExt;
+use gigi_p2p::{
+    Discovery, EventReceiver, Keypair, P2pClient, P2pConfig, P2pEvent, PersistenceConfig,
+};
+use tempfile::TempDir;
+use tokio::time::{Duration, Instant};
+
+/// A client together with its event receiver and download directory
+pub struct TestPeer {
+    pub client: P2pClient,
+    pub events: EventReceiver,
+    pub dir: TempDir,
+}
+
+/// Create a listening test peer with its own download directory
+pub fn create_peer(nickname: &str) -> TestPeer {
+    create_peer_with_config(nickname, P2pConfig::default())
+}
+
+/// Create a listening test peer using a custom configuration
+pub fn create_peer_with_config(nickname: &str, config: P2pConfig) -> TestPeer {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    let (mut client, events) = P2pClient::new_with_config(
+        Keypair::generate_ed25519(),
+        nickname.to_string(),
+        dir.path().to_path_buf(),
+        config,
+    )
+    .expect("Failed to create client");
+    client
+        .start_configured_listeners()
+        .expect("Failed to start listening");
+    TestPeer {
+        client,
+        events,
+        dir,
+    }
+}
+
+/// Create a test peer that discovers peers through `discovery` instead of mDNS
+///
+/// Listens on a random loopback port.
+pub fn create_peer_with_discovery(nickname: &str, discovery: Box<dyn Discovery>) -> TestPeer {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    let config = P2pConfig {
+        listen_addrs: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
+        ..Default::default()
+    };
+    let (mut client, events) = P2pClient::new_with_discovery(
+        Keypair::generate_ed25519(),
+        nickname.to_string(),
+        dir.path().to_path_buf(),
+        None,
+        config,
+        discovery,
+    )
+    .expect("Failed to create client");
+    client
+        .start_configured_listeners()
+        .expect("Failed to start listening");
+    TestPeer {
+        client,
+        events,
+        dir,
+    }
+}
+
+/// Create a listening test peer with a database in its download directory
+pub fn create_persistent_peer(nickname: &str) -> TestPeer {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    let persistence = PersistenceConfig {
+        db_path: dir.path().join("gigi.db"),
+        ..Default::default()
+    };
+    let (mut client, events) = P2pClient::new_with_full_config(
+        Keypair::generate_ed25519(),
+        nickname.to_string(),
+        dir.path().to_path_buf(),
+        Some(persistence),
+        P2pConfig::default(),
+    )
+    .expect("Failed to create client");
+    client
+        .start_configured_listeners()
+        .expect("Failed to start listening");
+    TestPeer {
+        client,
+        events,
+        dir,
+    }
+}
+
+/// Drive both swarms until an event from either peer matches `done`
+pub async fn drive_until<F>(a: &mut TestPeer, b: &mut TestPeer, mut done: F) -> Option<P2pEvent>
+where
+    F: FnMut(&P2pEvent) -> bool,
+{
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        tokio::select! {
+            _ = a.client.handle_next_swarm_event() => {}
+            _ = b.client.handle_next_swarm_event() => {}
+            Some(event) = a.events.next() => {
+                if done(&event) {
+                    return Some(event);
+                }
+            }
+            Some(event) = b.events.next() => {
+                if done(&event) {
+                    return Some(event);
+                }
+            }
+            _ = tokio::time::sleep_until(deadline) => return None,
+        }
+    }
+}
+
+/// Drive both swarms until an event from `b` matches `done`
+///
+/// Events from `a` are discarded.
+pub async fn drive_until_from<F>(
+    a: &mut TestPeer,
+    b: &mut TestPeer,
+    mut done: F,
+) -> Option<P2pEvent>
+where
+    F: FnMut(&P2pEvent) -> bool,
+{
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        tokio::select! {
+            _ = a.client.handle_next_swarm_event() => {}
+            _ = b.client.handle_next_swarm_event() => {}
+            Some(_) = a.events.next() => {}
+            Some(event) = b.events.next() => {
+                if done(&event) {
+                    return Some(event);
+                }
+            }
+            _ = tokio::time::sleep_until(deadline) => return None,
+        }
+    }
+}
+
+/// Drive a single swarm until one of its events matches `done`
+pub async fn drive_peer_until<F>(peer: &mut TestPeer, mut done: F) -> Option<P2pEvent>
+where
+    F: FnMut(&P2pEvent) -> bool,
+{
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        tokio::select! {
+            _ = peer.client.handle_next_swarm_event() => {}
+            Some(event) = peer.events.next() => {
+                if done(&event) {
+                    return Some(event);
+                }
+            }
+            _ = tokio::time::sleep_until(deadline) => return None,
+        }
+    }
+}
+
+/// Drive both swarms until each peer has discovered and connected to the other
+///
+/// Waiting for both sides matters because a peer drops direct messages from
+/// connections it cannot resolve to a discovered nickname yet.
+pub async fn connect(sharer: &mut TestPeer, downloader: &mut TestPeer) {
+    let sharer_nickname = sharer.client.local_nickname().to_string();
+    let downloader_nickname = downloader.client.local_nickname().to_string();
+    let (mut sharer_seen, mut downloader_seen) = (false, false);
+    let connected = drive_until(sharer, downloader, |event| {
+        if let P2pEvent::Connected { nickname, .. } = event {
+            sharer_seen |= *nickname == sharer_nickname;
+            downloader_seen |= *nickname == downloader_nickname;
+        }
+        sharer_seen && downloader_seen
+    })
+    .await;
+    assert!(connected.is_some(), "Peers should connect");
+}
@@ -9,13 +9,7 @@ use tempfile::TempDir;
 use tokio::time::{timeout, Duration};
 
 /// Helper function to create a test P2P client
-fn create_test_client(
-    nickname: &str,
-    temp_dir: &Path,
-) -> (
-    P2pClient,
-    futures::channel::mpsc::UnboundedReceiver<gigi_p2p::P2pEvent>,
-) {
+fn create_test_client(nickname: &str, temp_dir: &Path) -> (P2pClient, gigi_p2p::EventReceiver) {
     let keypair = Keypair::generate_ed25519();
     P2pClient::new(keypair, nickname.to_string(), temp_dir.to_path_buf())
         .expect("Failed to create client")
@@ -48,6 +42,46 @@ async fn test_start_listening() {
     assert!(result.is_ok(), "Should be able to start listening");
 }
 
+#[tokio::test]
+async fn test_listen_on_fixed_loopback_port() {
+    use futures::StreamExt;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let download_dir = temp_dir.path().to_path_buf();
+
+    let (mut client, mut event_receiver) = create_test_client("Alice", &download_dir);
+
+    // Reserve a free port, then release it for the client to bind
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let addr = client
+        .listen_on_tcp(IpAddr::V4(Ipv4Addr::LOCALHOST), port)
+        .expect("Should be able to listen on loopback");
+    assert_eq!(addr.to_string(), format!("/ip4/127.0.0.1/tcp/{}", port));
+
+    let bound = timeout(Duration::from_secs(5), async {
+        loop {
+            tokio::select! {
+                _ = client.handle_next_swarm_event() => {}
+                Some(event) = event_receiver.next() => {
+                    if let gigi_p2p::P2pEvent::ListeningOn { address } = event {
+                        return address;
+                    }
+                }
+            }
+        }
+    })
+    .await
+    .expect("Should report the bound address");
+
+    assert_eq!(bound, addr);
+}
+
 #[tokio::test]
 async fn test_group_lifecycle() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -75,6 +109,41 @@ async fn test_group_lifecycle() {
     assert!(result.is_ok(), "Should be able to rejoin group");
 }
 
+#[tokio::test]
+async fn test_salted_group_ids_use_distinct_topics() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let download_dir = temp_dir.path().to_path_buf();
+
+    let (mut client, _event_receiver) = create_test_client("Alice", &download_dir);
+
+    let first = client
+        .join_group_with_salt("friends", "first-salt")
+        .expect("Failed to join salted group");
+    let second = client
+        .join_group_with_salt("friends", "second-salt")
+        .expect("Failed to join salted group");
+    assert_ne!(first, second, "Different salts should give different ids");
+    assert_eq!(
+        first,
+        gigi_auth::key_derivation::derive_salted_group_id("friends", "first-salt").unwrap()
+    );
+
+    // Both groups are joined under their own topic but keep the shared name
+    let groups = client.list_groups();
+    let topic_of = |id: &str| {
+        groups
+            .iter()
+            .find(|g| g.name == id)
+            .map(|g| g.topic.hash())
+            .expect("Group should be in the list")
+    };
+    assert_ne!(topic_of(&first), topic_of(&second));
+    assert_eq!(client.group_display_name(&first), Some("friends"));
+    assert_eq!(client.group_display_name(&second), Some("friends"));
+
+    assert!(client.join_group_with_salt("friends", "").is_err());
+}
+
 #[tokio::test]
 async fn test_file_sharing() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -108,6 +177,54 @@ async fn test_file_sharing() {
     assert!(!found, "File should be removed from shared files list");
 }
 
+#[tokio::test]
+async fn test_recreate_with_keypair_preserves_state() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let download_dir = temp_dir.path().to_path_buf();
+
+    let (mut client, mut event_receiver) = create_test_client("Alice", &download_dir);
+    client
+        .start_listening("/ip4/127.0.0.1/tcp/0".parse().unwrap())
+        .unwrap();
+    client.join_group("rotation-group").unwrap();
+
+    let test_file_path = temp_dir.path().join("library.txt");
+    std::fs::write(&test_file_path, b"shared library").expect("Failed to write test file");
+    let share_code = client.share_file(&test_file_path).await.unwrap();
+
+    let old_peer_id = client.local_peer_id();
+    let new_keypair = Keypair::generate_ed25519();
+    let expected_peer_id = new_keypair.public().to_peer_id();
+
+    client
+        .recreate_with_keypair(new_keypair)
+        .expect("Should be able to rotate identity");
+
+    // Identity changed, everything else is preserved
+    assert_eq!(client.local_peer_id(), expected_peer_id);
+    assert_eq!(client.local_nickname(), "Alice");
+    assert!(client
+        .list_shared_files()
+        .iter()
+        .any(|f| f.share_code == share_code));
+    assert!(client
+        .list_groups()
+        .iter()
+        .any(|g| g.name == "rotation-group"));
+
+    let mut rotated = None;
+    while let Ok(event) = event_receiver.try_recv() {
+        if let gigi_p2p::P2pEvent::PeerIdChanged {
+            old_peer_id,
+            new_peer_id,
+        } = event
+        {
+            rotated = Some((old_peer_id, new_peer_id));
+        }
+    }
+    assert_eq!(rotated, Some((old_peer_id, expected_peer_id)));
+}
+
 #[tokio::test]
 async fn test_multiple_files_sharing() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -0,0 +1,492 @@
+//! End-to-end connectivity status tests for gigi-p2p
+//!
+//! Two clients discover each other via gigi-dns on the local machine and the
+//! connectivity summary is checked as they connect and disconnect.
+
+mod common;
+
+use common::{
+    connect, create_peer, create_peer_with_config, create_peer_with_discovery, drive_peer_until,
+    drive_until_from,
+};
+use futures::StreamExt;
+use gigi_p2p::{
+    ConnectionStatus, FileTransferVersion, Keypair, P2pClient, P2pConfig, P2pError, P2pEvent,
+    PeerId, StaticDiscovery, StaticPeer,
+};
+use tempfile::TempDir;
+use tokio::time::{Duration, Instant};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_connection_status_tracks_peers() {
+    let mut alice = create_peer("alice-status");
+    let mut bob = create_peer("bob-status");
+    assert_eq!(alice.client.connection_status().connected_peers, 0);
+
+    connect(&mut bob, &mut alice).await;
+
+    let status = alice.client.connection_status();
+    assert!(status.listening);
+    assert!(status.mdns_active);
+    assert!(status.connected_peers >= 1);
+    assert!(status.discovered_peers >= status.connected_peers);
+
+    // Closing bob's swarm disconnects alice from it
+    drop(bob);
+    let changed = drive_peer_until(&mut alice, |event| {
+        matches!(
+            event,
+            P2pEvent::ConnectivityChanged(ConnectionStatus {
+                connected_peers: 0,
+                ..
+            })
+        )
+    })
+    .await;
+
+    assert!(changed.is_some(), "Disconnect should change connectivity");
+    assert_eq!(alice.client.connection_status().connected_peers, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_inbound_connections_beyond_limit_rejected() {
+    // A single slot, taken by the first connection either way
+    let mut alice = create_peer_with_config(
+        "alice-limit",
+        P2pConfig {
+            max_connections: 1,
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer("bob-limit");
+    let mut carol = create_peer("carol-limit");
+
+    // Alice keeps at most one of them, so the other one's dial is rejected
+    let deadline = Instant::now() + Duration::from_secs(60);
+    let rejected = loop {
+        tokio::select! {
+            _ = alice.client.handle_next_swarm_event() => {}
+            _ = bob.client.handle_next_swarm_event() => {}
+            _ = carol.client.handle_next_swarm_event() => {}
+            Some(event) = alice.events.next() => {
+                if let P2pEvent::InboundConnectionRejected { limit, .. } = event {
+                    break Some(limit);
+                }
+            }
+            Some(_) = bob.events.next() => {}
+            Some(_) = carol.events.next() => {}
+            _ = tokio::time::sleep_until(deadline) => break None,
+        }
+    };
+
+    assert!(rejected.is_some(), "Inbound connection should be rejected");
+    assert!(alice.client.connection_status().connected_peers <= 1);
+}
+
+#[test]
+fn test_small_connection_limits_allow_inbound() {
+    use gigi_p2p::behaviour::{
+        reserved_outbound_connections, MIN_CONNECTIONS_FOR_OUTBOUND_RESERVE,
+    };
+
+    // Below the minimum every slot is open to inbound connections
+    for max_connections in 0..MIN_CONNECTIONS_FOR_OUTBOUND_RESERVE {
+        assert_eq!(reserved_outbound_connections(max_connections), 0);
+    }
+    assert_eq!(reserved_outbound_connections(4), 1);
+    assert_eq!(reserved_outbound_connections(7), 1);
+    assert_eq!(reserved_outbound_connections(100), 25);
+    for max_connections in 1..64 {
+        assert!(reserved_outbound_connections(max_connections) < max_connections);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_listening_on_reported_after_start_listening() {
+    let mut alice = create_peer("alice-listen");
+    alice
+        .client
+        .listen_on_tcp(std::net::Ipv4Addr::LOCALHOST.into(), 0)
+        .unwrap();
+
+    let listening = drive_peer_until(&mut alice, |event| {
+        matches!(event, P2pEvent::ListeningOn { address }
+            if address.to_string().starts_with("/ip4/127.0.0.1/tcp/"))
+    })
+    .await;
+    let Some(P2pEvent::ListeningOn { address }) = listening else {
+        panic!("Expected ListeningOn for the loopback listener");
+    };
+
+    // The reported address has a concrete port and is listed as bound
+    assert!(!address.to_string().ends_with("/tcp/0"));
+    assert!(alice.client.local_listen_addresses().contains(&address));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_config_rebinds_listener_on_port_change() {
+    let loopback = |port| P2pConfig::tcp_listen_addr(std::net::Ipv4Addr::LOCALHOST.into(), port);
+    let mut alice = create_peer_with_config(
+        "alice-reconfig",
+        P2pConfig {
+            listen_addrs: vec![loopback(0)],
+            ..Default::default()
+        },
+    );
+    let listening = drive_peer_until(&mut alice, |event| {
+        matches!(event, P2pEvent::ListeningOn { .. })
+    })
+    .await;
+    let Some(P2pEvent::ListeningOn {
+        address: old_address,
+    }) = listening
+    else {
+        panic!("Expected initial listener");
+    };
+
+    // Pick a free port for the new configuration
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut config = P2pConfig {
+        listen_addrs: vec![loopback(port)],
+        ..Default::default()
+    };
+    assert_eq!(
+        alice.client.update_config(config.clone()).unwrap(),
+        vec!["listen_addrs".to_string()]
+    );
+
+    let mut changed = None;
+    let mut stopped = false;
+    let rebound = drive_peer_until(&mut alice, |event| match event {
+        P2pEvent::ConfigChanged { changed_fields } => {
+            changed = Some(changed_fields.clone());
+            false
+        }
+        P2pEvent::ListenStopped { address } => {
+            stopped |= *address == old_address;
+            false
+        }
+        P2pEvent::ListeningOn { address } => *address == loopback(port),
+        _ => false,
+    })
+    .await;
+    assert!(rebound.is_some(), "Listener should rebind to port {}", port);
+    assert_eq!(changed, Some(vec!["listen_addrs".to_string()]));
+
+    // The old listener may report closing after the new one is up
+    if !stopped {
+        let closed = drive_peer_until(
+            &mut alice,
+            |event| matches!(event, P2pEvent::ListenStopped { address } if *address == old_address),
+        )
+        .await;
+        assert!(closed.is_some(), "Old listener should be closed");
+    }
+    assert_eq!(alice.client.local_listen_addresses(), vec![loopback(port)]);
+
+    // Applying the same configuration again changes nothing
+    config.listen_addrs = vec![loopback(port)];
+    assert!(alice.client.update_config(config).unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_config_binds_listen_addrs_without_configured_listeners() {
+    let loopback = |port| P2pConfig::tcp_listen_addr(std::net::Ipv4Addr::LOCALHOST.into(), port);
+    // Listening is started by hand, so no configured address is bound
+    let mut alice = create_peer_with_config(
+        "alice-bind-new",
+        P2pConfig {
+            listen_addrs: Vec::new(),
+            ..Default::default()
+        },
+    );
+    alice.client.start_listening(loopback(0)).unwrap();
+    let listening = drive_peer_until(&mut alice, |event| {
+        matches!(event, P2pEvent::ListeningOn { .. })
+    })
+    .await;
+    let Some(P2pEvent::ListeningOn {
+        address: manual_address,
+    }) = listening
+    else {
+        panic!("Expected the manual listener");
+    };
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    alice
+        .client
+        .update_config(P2pConfig {
+            listen_addrs: vec![loopback(port)],
+            ..Default::default()
+        })
+        .unwrap();
+
+    let bound = drive_peer_until(
+        &mut alice,
+        |event| matches!(event, P2pEvent::ListeningOn { address } if *address == loopback(port)),
+    )
+    .await;
+    assert!(bound.is_some(), "New address should be bound");
+    // The listener started by hand is left alone
+    let addresses = alice.client.local_listen_addresses();
+    assert!(addresses.contains(&manual_address));
+    assert!(addresses.contains(&loopback(port)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_connect_by_nickname_waits_for_discovery() {
+    let mut alice = create_peer("alice-wait");
+
+    // Bob starts shortly after alice begins waiting for them
+    let bob_task = tokio::spawn(async {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let mut bob = create_peer("bob-wait");
+        loop {
+            tokio::select! {
+                _ = bob.client.handle_next_swarm_event() => {}
+                Some(_) = bob.events.next() => {}
+            }
+        }
+    });
+
+    let peer_id = alice
+        .client
+        .connect_by_nickname("bob-wait", Duration::from_secs(60))
+        .await
+        .expect("Bob should be discovered and connected");
+    assert_eq!(
+        alice
+            .client
+            .get_peer_by_nickname("bob-wait")
+            .unwrap()
+            .peer_id,
+        peer_id
+    );
+    bob_task.abort();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_connect_by_nickname_times_out() {
+    let mut alice = create_peer("alice-nobody");
+
+    let err = alice
+        .client
+        .connect_by_nickname("nobody-here", Duration::from_millis(500))
+        .await
+        .expect_err("Unknown nickname should time out");
+    assert!(matches!(
+        err.downcast_ref::<P2pError>(),
+        Some(P2pError::Timeout(_))
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_snapshot_reflects_peers_and_shares() {
+    let mut alice = create_peer("alice-snapshot");
+    let mut bob = create_peer("bob-snapshot");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("snapshot.txt");
+    std::fs::write(&file, b"shared").unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    alice.client.join_group("snapshot-group").unwrap();
+
+    let snapshot = alice.client.snapshot();
+    let bob_peer = snapshot
+        .peers
+        .iter()
+        .find(|peer| peer.nickname == "bob-snapshot")
+        .expect("Bob should be in the snapshot");
+    assert_eq!(bob_peer.peer_id, bob.client.local_peer_id());
+    assert!(bob_peer.connected);
+    assert_eq!(snapshot.connection_status, alice.client.connection_status());
+    assert!(snapshot.connection_status.connected_peers >= 1);
+    assert_eq!(snapshot.shared_files.len(), 1);
+    assert_eq!(snapshot.shared_files[0].share_code, share_code);
+    assert_eq!(snapshot.shared_files[0].info.name, "snapshot.txt");
+    assert!(snapshot
+        .groups
+        .iter()
+        .any(|group| group.name == "snapshot-group"));
+    assert!(snapshot.downloads.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_peer_protocols_reported_via_identify() {
+    let mut alice = create_peer("alice-protocols");
+    let mut bob = create_peer("bob-protocols");
+    let alice_peer_id = alice.client.local_peer_id();
+    assert!(bob.client.peer_protocols(&alice_peer_id).is_empty());
+
+    let discovered = drive_until_from(&mut alice, &mut bob, |event| {
+        matches!(
+            event,
+            P2pEvent::PeerProtocolsDiscovered { peer_id, .. } if *peer_id == alice_peer_id
+        )
+    })
+    .await;
+    let Some(P2pEvent::PeerProtocolsDiscovered { protocols, .. }) = discovered else {
+        panic!("Bob should learn Alice's protocols");
+    };
+
+    let file_transfer = FileTransferVersion::V1_1.protocol().to_string();
+    assert!(protocols.contains(&file_transfer), "{:?}", protocols);
+    assert!(protocols.contains(&"/direct/1.0.0".to_string()));
+    assert_eq!(bob.client.peer_protocols(&alice_peer_id), protocols);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_config_rejects_invalid_discovery_service_name() {
+    let mut alice = create_peer("alice-service-name");
+
+    let invalid = P2pConfig {
+        discovery_service_name: "_my-app..local".to_string(),
+        ..Default::default()
+    };
+    let error = alice.client.update_config(invalid).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<P2pError>(),
+        Some(P2pError::InvalidInput(_))
+    ));
+
+    let valid = P2pConfig {
+        discovery_service_name: "_my-app._udp.local".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        alice.client.update_config(valid).unwrap(),
+        vec!["discovery_service_name".to_string()]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_config_late_failure_keeps_earlier_settings() {
+    let loopback = |port| P2pConfig::tcp_listen_addr(std::net::Ipv4Addr::LOCALHOST.into(), port);
+    let original = P2pConfig {
+        listen_addrs: vec![loopback(0)],
+        ..Default::default()
+    };
+    let mut alice = create_peer_with_config("alice-late-failure", original.clone());
+
+    // Share code length, temp dir and the TCP address are valid, but QUIC
+    // is not supported, so binding fails after the TCP listener was started
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let temp_dir = alice.dir.path().join("partial");
+    let rejected = P2pConfig {
+        share_code_length: 16,
+        max_concurrent_hashes: 1,
+        download_temp_dir: Some(temp_dir),
+        listen_addrs: vec![
+            loopback(port),
+            "/ip4/127.0.0.1/udp/0/quic-v1".parse().unwrap(),
+        ],
+        ..original.clone()
+    };
+    assert!(alice.client.update_config(rejected).is_err());
+
+    // Nothing was applied: the original configuration is still current
+    assert!(alice.client.update_config(original).unwrap().is_empty());
+    let file = alice.dir.path().join("late-failure.txt");
+    std::fs::write(&file, b"shared").unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    assert_eq!(
+        share_code.len(),
+        gigi_file_sharing::DEFAULT_SHARE_CODE_LENGTH
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_send_errors_distinguish_offline_and_unknown_peers() {
+    let is = |error: anyhow::Error, expected: fn(&P2pError) -> bool| {
+        let p2p_error = error
+            .downcast_ref::<P2pError>()
+            .unwrap_or_else(|| panic!("Expected a P2pError, got {}", error));
+        assert!(expected(p2p_error), "Unexpected error: {:?}", p2p_error);
+    };
+
+    // Without listeners and connections the node itself is offline
+    let dir = TempDir::new().unwrap();
+    let (mut offline, _events) = P2pClient::new_with_config(
+        Keypair::generate_ed25519(),
+        "offline-errors".to_string(),
+        dir.path().to_path_buf(),
+        P2pConfig::default(),
+    )
+    .unwrap();
+    is(
+        offline
+            .send_direct_message("ghost-errors", "hi".to_string())
+            .unwrap_err(),
+        |e| matches!(e, P2pError::NotConnectedToNetwork),
+    );
+    is(
+        offline
+            .download_file("ghost-errors", "code0001")
+            .unwrap_err(),
+        |e| matches!(e, P2pError::NotConnectedToNetwork),
+    );
+
+    // A peer discovered at an address nobody listens on cannot be connected
+    let ghost = StaticPeer {
+        peer_id: PeerId::random(),
+        nickname: "ghost-errors".to_string(),
+        address: "/ip4/127.0.0.1/tcp/1".parse().unwrap(),
+    };
+    let mut alice =
+        create_peer_with_discovery("alice-errors", Box::new(StaticDiscovery::new(vec![ghost])));
+    let mut listening = false;
+    let mut discovered = false;
+    let ready = drive_peer_until(&mut alice, |event| {
+        listening |= matches!(event, P2pEvent::ListeningOn { .. });
+        discovered |= matches!(event, P2pEvent::PeerDiscovered { .. });
+        listening && discovered
+    })
+    .await;
+    assert!(
+        ready.is_some(),
+        "Alice should listen and discover the ghost"
+    );
+
+    is(
+        alice
+            .client
+            .send_direct_message("ghost-errors", "hi".to_string())
+            .unwrap_err(),
+        |e| matches!(e, P2pError::PeerUnreachable(nickname) if nickname == "ghost-errors"),
+    );
+    is(
+        alice
+            .client
+            .download_file("ghost-errors", "code0001")
+            .unwrap_err(),
+        |e| matches!(e, P2pError::PeerUnreachable(_)),
+    );
+
+    // A nickname nobody uses has not been discovered
+    is(
+        alice
+            .client
+            .send_direct_message("nobody-errors", "hi".to_string())
+            .unwrap_err(),
+        |e| matches!(e, P2pError::PeerNotDiscovered(nickname) if nickname == "nobody-errors"),
+    );
+    is(
+        alice
+            .client
+            .download_file("nobody-errors", "code0001")
+            .unwrap_err(),
+        |e| matches!(e, P2pError::PeerNotDiscovered(_)),
+    );
+}
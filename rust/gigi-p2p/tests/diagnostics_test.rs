@@ -0,0 +1,92 @@
+//! Tests for `P2pClient::run_diagnostics`
+
+mod common;
+
+use common::{create_peer, create_persistent_peer, drive_peer_until};
+use gigi_p2p::{DiagnosticCheck, DiagnosticStatus, Keypair, P2pClient, P2pConfig, P2pEvent};
+use tempfile::TempDir;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_diagnostics_pass_for_working_setup() {
+    let mut peer = create_persistent_peer("alice-diag");
+    let listening = drive_peer_until(&mut peer, |event| {
+        matches!(event, P2pEvent::ListeningOn { .. })
+    })
+    .await;
+    assert!(listening.is_some(), "Peer should start listening");
+
+    let report = peer.client.run_diagnostics().await;
+    for check in [
+        DiagnosticCheck::Listener,
+        DiagnosticCheck::Loopback,
+        DiagnosticCheck::DownloadDirectory,
+        DiagnosticCheck::Store,
+    ] {
+        let result = report.get(check).unwrap();
+        assert_eq!(result.status, DiagnosticStatus::Passed, "{:?}", result);
+        assert!(result.hint.is_none());
+    }
+    // The probe file is cleaned up
+    assert!(std::fs::read_dir(peer.dir.path())
+        .unwrap()
+        .all(|entry| !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".probe")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_diagnostics_skip_store_without_persistence() {
+    let peer = create_peer("bob-diag");
+
+    let report = peer.client.run_diagnostics().await;
+    let store = report.get(DiagnosticCheck::Store).unwrap();
+    assert_eq!(store.status, DiagnosticStatus::Skipped);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_diagnostics_report_unwritable_download_directory() {
+    let dir = TempDir::new().unwrap();
+    // A regular file where the download directory should be
+    let output = dir.path().join("not-a-directory");
+    std::fs::write(&output, b"file").unwrap();
+    let (client, _events) = P2pClient::new_with_config(
+        Keypair::generate_ed25519(),
+        "carol-diag".to_string(),
+        output.clone(),
+        P2pConfig::default(),
+    )
+    .unwrap();
+
+    let report = client.run_diagnostics().await;
+    let result = report.get(DiagnosticCheck::DownloadDirectory).unwrap();
+    assert_eq!(result.status, DiagnosticStatus::Failed);
+    assert!(
+        result.detail.contains("not-a-directory"),
+        "{}",
+        result.detail
+    );
+    assert!(result.hint.is_some());
+    assert!(!report.all_passed());
+    // Not listening either
+    assert_eq!(
+        report.get(DiagnosticCheck::Listener).unwrap().status,
+        DiagnosticStatus::Failed
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_diagnostics_report_unreachable_store() {
+    let peer = create_persistent_peer("dave-diag");
+    // Overwrite the database underneath the open connection
+    std::fs::write(peer.dir.path().join("gigi.db"), vec![0xAB; 8192]).unwrap();
+
+    let report = peer.client.run_diagnostics().await;
+    let result = report.get(DiagnosticCheck::Store).unwrap();
+    assert_eq!(result.status, DiagnosticStatus::Failed, "{:?}", result);
+    assert!(result.hint.is_some());
+    assert!(report
+        .failures()
+        .any(|failure| failure.check == DiagnosticCheck::Store));
+}
@@ -0,0 +1,208 @@
+//! Pluggable discovery backend tests for gigi-p2p
+//!
+//! Clients discover each other through a `StaticDiscovery` peer list instead
+//! of gigi-dns, so no mDNS traffic is involved.
+
+mod common;
+
+use common::{
+    create_peer_with_discovery, drive_peer_until, drive_until, drive_until_from, TestPeer,
+};
+use futures::Stream;
+use gigi_p2p::{Discovery, DiscoveryEvent, P2pEvent, ResolveResult, StaticDiscovery, StaticPeer};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// `StaticDiscovery` that counts how often it is asked to refresh
+struct CountingDiscovery {
+    inner: StaticDiscovery,
+    refreshes: Arc<AtomicUsize>,
+}
+
+impl Discovery for CountingDiscovery {
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.inner.start()
+    }
+
+    fn stop(&mut self) {
+        self.inner.stop();
+    }
+
+    fn refresh(&mut self) {
+        self.refreshes.fetch_add(1, Ordering::SeqCst);
+        self.inner.refresh();
+    }
+}
+
+impl Stream for CountingDiscovery {
+    type Item = DiscoveryEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+/// Start a peer with an empty `StaticDiscovery` and wait until it listens
+async fn listening_peer(nickname: &str) -> (TestPeer, StaticPeer) {
+    let mut peer = create_peer_with_discovery(nickname, Box::new(StaticDiscovery::new(vec![])));
+    let listening = drive_peer_until(&mut peer, |event| {
+        matches!(event, P2pEvent::ListeningOn { .. })
+    })
+    .await;
+    let Some(P2pEvent::ListeningOn { address }) = listening else {
+        panic!("{} should listen", nickname);
+    };
+    let static_peer = StaticPeer {
+        peer_id: peer.client.local_peer_id(),
+        nickname: nickname.to_string(),
+        address,
+    };
+    (peer, static_peer)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_static_discovery_finds_fixed_peers() {
+    let (mut bob, bob_peer) = listening_peer("bob-static").await;
+    let mut alice = create_peer_with_discovery(
+        "alice-static",
+        Box::new(StaticDiscovery::new(vec![bob_peer.clone()])),
+    );
+
+    let mut discovered = Vec::new();
+    let connected = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::PeerDiscovered {
+            peer_id, nickname, ..
+        } => {
+            discovered.push((*peer_id, nickname.clone()));
+            false
+        }
+        P2pEvent::Connected { nickname, .. } => nickname == "bob-static",
+        _ => false,
+    })
+    .await;
+    assert!(connected.is_some(), "Alice should connect to bob");
+
+    // Only the listed peer is found, and mDNS stays off
+    assert_eq!(discovered, vec![(bob_peer.peer_id, bob_peer.nickname)]);
+    assert_eq!(
+        alice.client.get_peer_id_by_nickname("bob-static"),
+        Some(bob.client.local_peer_id())
+    );
+    assert!(!alice.client.connection_status().mdns_active);
+    assert!(!bob.client.connection_status().mdns_active);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_resolve_nickname_hits_cache_and_refreshes_on_miss() {
+    let (mut bob, bob_peer) = listening_peer("bob-resolve").await;
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let mut alice = create_peer_with_discovery(
+        "alice-resolve",
+        Box::new(CountingDiscovery {
+            inner: StaticDiscovery::new(vec![bob_peer.clone()]),
+            refreshes: refreshes.clone(),
+        }),
+    );
+    let connected = drive_until(
+        &mut alice,
+        &mut bob,
+        |event| matches!(event, P2pEvent::Connected { nickname, .. } if nickname == "bob-resolve"),
+    )
+    .await;
+    assert!(connected.is_some(), "Alice should connect to bob");
+
+    // A known peer resolves right away without asking discovery
+    let ResolveResult::Resolved {
+        peer_id,
+        last_seen,
+        connected,
+    } = alice.client.resolve_nickname("bob-resolve")
+    else {
+        panic!("Bob should be resolved from the cache");
+    };
+    assert_eq!(peer_id, bob_peer.peer_id);
+    assert!(connected);
+    assert!(last_seen.elapsed() < std::time::Duration::from_secs(60));
+    assert_eq!(refreshes.load(Ordering::SeqCst), 0);
+
+    // A miss asks discovery to look for peers, but not again right away
+    assert_eq!(
+        alice.client.resolve_nickname("carol-resolve"),
+        ResolveResult::Pending
+    );
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        alice.client.resolve_nickname("carol-resolve"),
+        ResolveResult::Pending
+    );
+    assert!(alice
+        .client
+        .send_direct_message("carol-resolve", "hi".to_string())
+        .is_err());
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_on_network_changed_rebinds_and_rediscovers() {
+    let (mut bob, bob_peer) = listening_peer("bob-network").await;
+    // A known peer nobody listens for, so it stays disconnected
+    let closed_port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let ghost = StaticPeer {
+        peer_id: gigi_p2p::Keypair::generate_ed25519().public().to_peer_id(),
+        nickname: "ghost-network".to_string(),
+        address: format!("/ip4/127.0.0.1/tcp/{}", closed_port)
+            .parse()
+            .unwrap(),
+    };
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let mut alice = create_peer_with_discovery(
+        "alice-network",
+        Box::new(CountingDiscovery {
+            inner: StaticDiscovery::new(vec![bob_peer.clone(), ghost]),
+            refreshes: refreshes.clone(),
+        }),
+    );
+    let connected = drive_until(
+        &mut alice,
+        &mut bob,
+        |event| matches!(event, P2pEvent::Connected { nickname, .. } if nickname == "bob-network"),
+    )
+    .await;
+    assert!(connected.is_some(), "Alice should connect to bob");
+    let old_addresses = alice.client.local_listen_addresses();
+    assert_eq!(old_addresses.len(), 1);
+
+    // Only the disconnected ghost is dialed; bob's connection is left alone
+    assert_eq!(alice.client.on_network_changed().unwrap(), 1);
+    assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+
+    // The old listener goes away and a new one is bound, in either order
+    let mut stopped = false;
+    let mut rebound = None;
+    let done = drive_until_from(&mut bob, &mut alice, |event| {
+        match event {
+            P2pEvent::ListenStopped { address } => stopped |= old_addresses.contains(address),
+            P2pEvent::ListeningOn { address } if !old_addresses.contains(address) => {
+                rebound = Some(address.clone())
+            }
+            _ => {}
+        }
+        stopped && rebound.is_some()
+    })
+    .await;
+    assert!(done.is_some(), "Alice should rebind her listener");
+    assert_eq!(
+        alice.client.local_listen_addresses(),
+        vec![rebound.unwrap()]
+    );
+    assert!(alice
+        .client
+        .get_peer_id_by_nickname("bob-network")
+        .is_some());
+}
@@ -0,0 +1,57 @@
+//! Tests for placeholder names of peers without a nickname
+
+use gigi_p2p::{display_name_for, Keypair, PeerId, UnnamedPeerLabel};
+
+fn peer_from_seed(seed: u8) -> PeerId {
+    Keypair::ed25519_from_bytes([seed; 32])
+        .unwrap()
+        .public()
+        .to_peer_id()
+}
+
+#[test]
+fn test_display_name_is_stable_and_readable() {
+    let peer = peer_from_seed(1);
+    let name = display_name_for(&peer);
+
+    // Same peer, same name, even for a separately constructed PeerId
+    assert_eq!(name, display_name_for(&peer_from_seed(1)));
+    assert_eq!(
+        name,
+        display_name_for(&peer.to_string().parse::<PeerId>().unwrap())
+    );
+
+    let mut parts = name.splitn(3, '-');
+    let (adjective, animal, suffix) = (
+        parts.next().unwrap(),
+        parts.next().expect("adjective-animal"),
+        parts.next().expect("peer ID suffix"),
+    );
+    assert!(!adjective.is_empty() && !animal.is_empty());
+    assert!(format!("{}{}", adjective, animal)
+        .chars()
+        .all(|c| c.is_ascii_lowercase()));
+    // Ends in the peer ID, so it can be told apart from other peers
+    assert_eq!(suffix.len(), 4);
+    assert!(peer.to_string().ends_with(suffix));
+    assert!(name.len() < 20, "Name should be short: {}", name);
+}
+
+#[test]
+fn test_display_names_vary_between_peers() {
+    let names: std::collections::HashSet<String> = (0..=255)
+        .map(|seed| display_name_for(&peer_from_seed(seed)))
+        .collect();
+    assert_eq!(names.len(), 256, "Names should not collide");
+}
+
+#[test]
+fn test_unnamed_peer_label_strategies() {
+    let peer = peer_from_seed(7);
+    assert_eq!(UnnamedPeerLabel::default(), UnnamedPeerLabel::Derived);
+    assert_eq!(
+        UnnamedPeerLabel::Derived.label_for(&peer),
+        display_name_for(&peer)
+    );
+    assert_eq!(UnnamedPeerLabel::PeerId.label_for(&peer), peer.to_string());
+}
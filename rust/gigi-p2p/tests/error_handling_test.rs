@@ -26,6 +26,18 @@ fn test_nickname_not_found_error() {
     assert!(error.to_string().contains("Alice"));
 }
 
+#[test]
+fn test_reachability_errors() {
+    let error = P2pError::PeerNotDiscovered("Alice".to_string());
+    assert_eq!(error.to_string(), "Peer 'Alice' has not been discovered");
+
+    let error = P2pError::PeerUnreachable("Alice".to_string());
+    assert_eq!(error.to_string(), "Peer 'Alice' is not online");
+
+    let error = P2pError::NotConnectedToNetwork;
+    assert_eq!(error.to_string(), "Not connected to the network");
+}
+
 #[test]
 fn test_group_not_found_error() {
     let group_name = "test-group".to_string();
@@ -0,0 +1,126 @@
+//! Event channel capacity tests for gigi-p2p
+
+use futures::StreamExt;
+use gigi_p2p::{event_channel, DownloadFailureReason, EventChannelError, P2pEvent, PeerId};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+fn progress(download_id: &str, downloaded_chunks: usize) -> P2pEvent {
+    P2pEvent::FileDownloadProgress {
+        download_id: download_id.to_string(),
+        filename: format!("{}.bin", download_id),
+        share_code: download_id.to_string(),
+        from_peer_id: PeerId::random(),
+        from_nickname: "alice".to_string(),
+        downloaded_chunks,
+        total_chunks: 10_000,
+    }
+}
+
+fn completed(download_id: &str) -> P2pEvent {
+    P2pEvent::FileDownloadCompleted {
+        download_id: download_id.to_string(),
+        filename: format!("{}.bin", download_id),
+        share_code: download_id.to_string(),
+        from_peer_id: PeerId::random(),
+        from_nickname: "alice".to_string(),
+        path: PathBuf::from(format!("/tmp/{}.bin", download_id)),
+        duration: std::time::Duration::from_secs(1),
+        average_speed: 1024.0,
+        total_bytes: 1024,
+        chunk_count: 1,
+    }
+}
+
+#[tokio::test]
+async fn test_bounded_channel_coalesces_progress_under_flood() {
+    let capacity = 8;
+    let (sender, mut receiver) = event_channel(Some(capacity));
+    let downloads = ["a", "b", "c"];
+
+    // The consumer is stalled while a busy transfer floods progress events
+    for chunk in 1..=10_000 {
+        for download_id in downloads {
+            sender.unbounded_send(progress(download_id, chunk)).unwrap();
+        }
+        assert!(receiver.len() <= capacity);
+    }
+    sender.unbounded_send(completed("a")).unwrap();
+    sender
+        .unbounded_send(P2pEvent::FileDownloadFailed {
+            download_id: "b".to_string(),
+            filename: "b.bin".to_string(),
+            share_code: "b".to_string(),
+            from_peer_id: PeerId::random(),
+            from_nickname: "alice".to_string(),
+            error: "boom".to_string(),
+            reason: DownloadFailureReason::Other,
+        })
+        .unwrap();
+    assert!(receiver.len() <= capacity + 2);
+    assert!(sender.dropped_events() > 29_000);
+    drop(sender);
+
+    let mut latest = HashMap::new();
+    let mut completed_a = false;
+    let mut failed_b = false;
+    while let Some(event) = receiver.next().await {
+        match event {
+            P2pEvent::FileDownloadProgress {
+                download_id,
+                downloaded_chunks,
+                ..
+            } => {
+                latest.insert(download_id, downloaded_chunks);
+            }
+            P2pEvent::FileDownloadCompleted { download_id, .. } => {
+                completed_a = download_id == "a";
+            }
+            P2pEvent::FileDownloadFailed { download_id, .. } => {
+                failed_b = download_id == "b";
+            }
+            other => panic!("Unexpected event {:?}", other),
+        }
+    }
+
+    // Critical events survive and the latest progress of each download is kept
+    assert!(completed_a && failed_b);
+    assert_eq!(latest.get("c"), Some(&10_000));
+    assert_eq!(receiver.try_recv().unwrap_err(), EventChannelError::Closed);
+}
+
+#[test]
+fn test_critical_events_never_dropped_when_full() {
+    let (sender, mut receiver) = event_channel(Some(2));
+    for index in 0..10 {
+        sender
+            .unbounded_send(completed(&format!("done-{}", index)))
+            .unwrap();
+    }
+    assert_eq!(sender.dropped_events(), 0);
+    for index in 0..10 {
+        match receiver.try_recv() {
+            Ok(P2pEvent::FileDownloadCompleted { download_id, .. }) => {
+                assert_eq!(download_id, format!("done-{}", index));
+            }
+            other => panic!("Expected completion, got {:?}", other),
+        }
+    }
+    assert_eq!(receiver.try_recv().unwrap_err(), EventChannelError::Empty);
+}
+
+#[test]
+fn test_unbounded_channel_keeps_every_event() {
+    let (sender, receiver) = event_channel(None);
+    for chunk in 0..1_000 {
+        sender.unbounded_send(progress("a", chunk)).unwrap();
+    }
+    assert_eq!(receiver.len(), 1_000);
+    assert_eq!(sender.dropped_events(), 0);
+
+    drop(receiver);
+    assert_eq!(
+        sender.unbounded_send(progress("a", 0)).unwrap_err(),
+        EventChannelError::ReceiverDropped
+    );
+}
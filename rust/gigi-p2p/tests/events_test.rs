@@ -2,7 +2,7 @@
 //!
 //! Tests all P2pEvent variants and event data structures
 
-use gigi_p2p::{ChunkInfo, FileInfo, P2pEvent};
+use gigi_p2p::{ChunkInfo, DownloadFailureReason, FileInfo, P2pErrorKind, P2pEvent};
 use libp2p::{Multiaddr, PeerId};
 use std::path::PathBuf;
 
@@ -75,6 +75,9 @@ fn test_direct_message_event() {
         from: PeerId::random(),
         from_nickname: "Alice".to_string(),
         message: "Hello".to_string(),
+        message_id: "msg-1".to_string(),
+        disappear_after_secs: None,
+        forwarded_from: None,
     };
 
     match event {
@@ -97,6 +100,8 @@ fn test_group_message_event() {
         from_nickname: "Alice".to_string(),
         group: "test-group".to_string(),
         message: "Group hello".to_string(),
+        message_id: "msg-2".to_string(),
+        forwarded_from: None,
     };
 
     match event {
@@ -203,6 +208,10 @@ fn test_file_download_completed_event() {
         from_peer_id: PeerId::random(),
         from_nickname: "Alice".to_string(),
         path: PathBuf::from("/downloads/test.txt"),
+        duration: std::time::Duration::from_secs(2),
+        average_speed: 512.0,
+        total_bytes: 1024,
+        chunk_count: 1,
     };
 
     match event {
@@ -229,6 +238,7 @@ fn test_file_download_failed_event() {
         from_peer_id: PeerId::random(),
         from_nickname: "Alice".to_string(),
         error: "Connection timeout".to_string(),
+        reason: DownloadFailureReason::Other,
     };
 
     match event {
@@ -236,11 +246,13 @@ fn test_file_download_failed_event() {
             download_id,
             filename,
             error,
+            reason,
             ..
         } => {
             assert_eq!(download_id, "dl-1");
             assert_eq!(filename, "test.txt");
             assert_eq!(error, "Connection timeout");
+            assert_eq!(reason, DownloadFailureReason::Other);
         }
         _ => panic!("Wrong event type"),
     }
@@ -316,6 +328,28 @@ fn test_chunk_info() {
     assert_eq!(chunk.hash, "abc123");
 }
 
+#[test]
+fn test_chunk_info_serializes_data_compactly() {
+    let chunk = ChunkInfo {
+        file_id: "file-123".to_string(),
+        chunk_index: 3,
+        data: (0..=255u8).cycle().take(256 * 1024).collect(),
+        hash: "abc123".to_string(),
+    };
+
+    let encoded = cbor4ii::serde::to_vec(Vec::new(), &chunk).unwrap();
+    assert!(
+        encoded.len() < chunk.data.len() + chunk.data.len() / 100,
+        "{} bytes for {} bytes of data",
+        encoded.len(),
+        chunk.data.len()
+    );
+
+    let decoded: ChunkInfo = cbor4ii::serde::from_slice(&encoded).unwrap();
+    assert_eq!(decoded.data, chunk.data);
+    assert_eq!(decoded.chunk_index, 3);
+}
+
 #[test]
 fn test_file_info() {
     let file_info = FileInfo {
@@ -323,8 +357,12 @@ fn test_file_info() {
         name: "test.txt".to_string(),
         size: 1024,
         hash: "abc123def456".to_string(),
+        hash_algo: Default::default(),
         chunk_count: 4,
         created_at: 1234567890,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: None,
     };
 
     assert_eq!(file_info.id, "file-123");
@@ -355,6 +393,9 @@ fn test_event_variants_count() {
             from: peer_id,
             from_nickname: "Alice".to_string(),
             message: "Hello".to_string(),
+            message_id: "msg-1".to_string(),
+            disappear_after_secs: None,
+            forwarded_from: None,
         },
         P2pEvent::GroupJoined {
             group: "group-1".to_string(),
@@ -373,7 +414,7 @@ fn test_event_variants_count() {
             peer_id,
             nickname: "Alice".to_string(),
         },
-        P2pEvent::Error("Test error".to_string()),
+        P2pEvent::Error(P2pErrorKind::Network("Test error".to_string())),
     ];
 
     assert_eq!(events.len(), 10);
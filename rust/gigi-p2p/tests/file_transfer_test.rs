@@ -0,0 +1,2477 @@
+//! End-to-end file transfer tests for gigi-p2p
+//!
+//! Two clients discover each other via gigi-dns on the local machine and
+//! transfer files over the `/file/1.0.0` request-response protocol.
+
+mod common;
+
+use common::{
+    connect, create_peer, create_peer_with_config, create_persistent_peer, drive_peer_until,
+    drive_until, drive_until_from, TestPeer,
+};
+use gigi_p2p::behaviour::{FileSharingCodec, FileSharingResponse};
+use gigi_p2p::{
+    ChunkInfo, DownloadDetail, DownloadFailureReason, DownloadHistoryStatus, DownloadPriority,
+    DownloadRequest, FileTransferVersion, HashAlgo, Keypair, P2pClient, P2pConfig, P2pErrorKind,
+    P2pEvent, PersistenceConfig, ProgressGranularity, ShareCancelToken, CHUNK_SIZE, MAX_RANGE_SIZE,
+};
+use libp2p::request_response::Codec;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Share `path` from `sharer` and download it on `downloader`, returning the final event
+async fn transfer(sharer: &mut TestPeer, downloader: &mut TestPeer, path: &Path) -> P2pEvent {
+    let share_code = sharer.client.share_file(path).await.unwrap();
+    let sharer_nickname = sharer.client.local_nickname().to_string();
+    let download_id = downloader
+        .client
+        .download_file(&sharer_nickname, &share_code)
+        .unwrap();
+
+    drive_until(sharer, downloader, |event| match event {
+        P2pEvent::FileDownloadCompleted {
+            download_id: id, ..
+        }
+        | P2pEvent::FileDownloadFailed {
+            download_id: id, ..
+        } => *id == download_id,
+        _ => false,
+    })
+    .await
+    .expect("Download should finish")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_zero_byte_file() {
+    let mut alice = create_peer("alice-empty");
+    let mut bob = create_peer("bob-empty");
+    connect(&mut alice, &mut bob).await;
+
+    let empty_file = alice.dir.path().join("empty.txt");
+    std::fs::write(&empty_file, b"").unwrap();
+
+    match transfer(&mut alice, &mut bob, &empty_file).await {
+        P2pEvent::FileDownloadCompleted { path, filename, .. } => {
+            assert_eq!(filename, "empty.txt");
+            assert!(path.starts_with(bob.dir.path()));
+            assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_traversal_filename_stays_in_output_dir() {
+    let mut alice = create_peer("alice-traversal");
+    let mut bob = create_peer("bob-traversal");
+    connect(&mut alice, &mut bob).await;
+
+    // Backslashes are legal in Unix file names but are separators on Windows
+    let file = alice.dir.path().join("..\\..\\evil.txt");
+    std::fs::write(&file, b"payload").unwrap();
+
+    match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted { path, .. } => {
+            assert_eq!(path, bob.dir.path().join("evil.txt"));
+            assert_eq!(std::fs::read(&path).unwrap(), b"payload");
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_directory_share_recreates_folders() {
+    let mut alice = create_peer("alice-album");
+    let mut bob = create_peer("bob-album");
+    connect(&mut alice, &mut bob).await;
+
+    let album = alice.dir.path().join("album");
+    std::fs::create_dir_all(album.join("2024")).unwrap();
+    std::fs::write(album.join("cover.jpg"), b"cover").unwrap();
+    std::fs::write(album.join("2024").join("beach.jpg"), b"beach").unwrap();
+
+    let shared = alice
+        .client
+        .share_directory(&album, &ShareCancelToken::new())
+        .await
+        .unwrap();
+    assert_eq!(shared.shared.len(), 2);
+
+    let mut paths = Vec::new();
+    for (_, share_code) in shared.shared {
+        let download_id = bob
+            .client
+            .download_file("alice-album", &share_code)
+            .unwrap();
+        let event = drive_until(&mut alice, &mut bob, |event| match event {
+            P2pEvent::FileDownloadCompleted {
+                download_id: id, ..
+            }
+            | P2pEvent::FileDownloadFailed {
+                download_id: id, ..
+            } => *id == download_id,
+            _ => false,
+        })
+        .await
+        .expect("Download should finish");
+        match event {
+            P2pEvent::FileDownloadCompleted { path, .. } => paths.push(path),
+            other => panic!("Expected completed download, got {:?}", other),
+        }
+    }
+
+    let beach = bob.dir.path().join("album").join("2024").join("beach.jpg");
+    let cover = bob.dir.path().join("album").join("cover.jpg");
+    assert_eq!(paths, vec![beach.clone(), cover.clone()]);
+    assert_eq!(std::fs::read(&beach).unwrap(), b"beach");
+    assert_eq!(std::fs::read(&cover).unwrap(), b"cover");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_downloads_organized_by_sender() {
+    let mut carol = create_peer("carol-folders");
+    let mut dave = create_peer("dave-folders");
+    let mut bob = create_peer_with_config(
+        "bob-folders",
+        P2pConfig {
+            organize_downloads_by_sender: true,
+            ..Default::default()
+        },
+    );
+
+    let mut paths = Vec::new();
+    for sharer in [&mut carol, &mut dave] {
+        connect(sharer, &mut bob).await;
+
+        let file = sharer.dir.path().join("photo.jpg");
+        std::fs::write(&file, sharer.client.local_nickname().as_bytes()).unwrap();
+
+        match transfer(sharer, &mut bob, &file).await {
+            P2pEvent::FileDownloadCompleted { path, .. } => paths.push(path),
+            other => panic!("Expected completed download, got {:?}", other),
+        }
+    }
+
+    assert_eq!(paths[0], bob.dir.path().join("carol-folders/photo.jpg"));
+    assert_eq!(paths[1], bob.dir.path().join("dave-folders/photo.jpg"));
+    assert_eq!(std::fs::read(&paths[0]).unwrap(), b"carol-folders");
+    assert_eq!(std::fs::read(&paths[1]).unwrap(), b"dave-folders");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_active_downloads_detailed() {
+    let mut alice = create_peer("alice-detail");
+    let mut bob = create_peer("bob-detail");
+    connect(&mut alice, &mut bob).await;
+
+    // Large enough that the download is still running when it starts
+    let file = alice.dir.path().join("large.bin");
+    std::fs::write(&file, vec![7u8; CHUNK_SIZE * 40 + 10]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+
+    let download_id = bob
+        .client
+        .download_file("alice-detail", &share_code)
+        .unwrap();
+
+    let details = bob.client.active_downloads_detailed();
+    assert_eq!(details.len(), 1);
+    assert_eq!(details[0].download_id, download_id);
+    assert_eq!(details[0].share_code, share_code);
+    assert_eq!(details[0].from_nickname, "alice-detail");
+    assert_eq!(details[0].bytes_done, 0);
+
+    let started = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadStarted { .. })
+    })
+    .await;
+    assert!(started.is_some(), "Download should start");
+
+    let detail = bob
+        .client
+        .active_downloads_detailed()
+        .into_iter()
+        .find(|d| d.download_id == download_id)
+        .expect("Download should still be tracked");
+    assert_eq!(detail.filename, "large.bin");
+    assert_eq!(detail.total_chunks, 41);
+    assert_eq!(detail.share_code, share_code);
+    assert!(detail.bytes_done <= (CHUNK_SIZE * 40 + 10) as u64);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rotating_identity_fails_unfinished_downloads() {
+    let mut alice = create_peer("alice-rotate-dl");
+    let mut bob = create_peer("bob-rotate-dl");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("large.bin");
+    std::fs::write(&file, vec![3u8; CHUNK_SIZE * 40]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-rotate-dl", &share_code)
+        .unwrap();
+    let started = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadStarted { .. })
+    })
+    .await;
+    assert!(started.is_some(), "Download should start");
+
+    bob.client
+        .recreate_with_keypair(Keypair::generate_ed25519())
+        .unwrap();
+
+    // Chunk requests sent on the old swarm are not waited for
+    let mut failed = None;
+    while let Ok(event) = bob.events.try_recv() {
+        if let P2pEvent::FileDownloadFailed {
+            download_id: failed_id,
+            ..
+        } = event
+        {
+            failed = Some(failed_id);
+        }
+    }
+    assert_eq!(failed, Some(download_id));
+    assert!(bob.client.active_downloads_detailed().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_sends_most_chunks_to_faster_source() {
+    use futures::StreamExt;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    let mut alice = create_peer("alice-fast-source");
+    let mut carol = create_peer("carol-slow-source");
+    let mut bob = create_peer("bob-two-sources");
+    connect(&mut alice, &mut bob).await;
+    connect(&mut carol, &mut bob).await;
+
+    // Both share the same content, and bob lists the files of both
+    let contents: Vec<u8> = (0..CHUNK_SIZE * 24).map(|i| (i % 239) as u8).collect();
+    let mut share_codes = Vec::new();
+    for sharer in [&mut alice, &mut carol] {
+        let file = sharer.dir.path().join("movie.bin");
+        std::fs::write(&file, &contents).unwrap();
+        share_codes.push(sharer.client.share_file(&file).await.unwrap());
+        let sharer_id = sharer.client.local_peer_id();
+        bob.client
+            .request_file_list(sharer.client.local_nickname())
+            .unwrap();
+        let listed = drive_until(
+            sharer,
+            &mut bob,
+            |event| matches!(event, P2pEvent::FileListReceived { from, .. } if *from == sharer_id),
+        )
+        .await;
+        assert!(listed.is_some(), "Bob should receive the file list");
+    }
+    let (alice_id, carol_id) = (alice.client.local_peer_id(), carol.client.local_peer_id());
+
+    // Downloading from carol, who only handles an event every 50ms
+    let download_id = bob
+        .client
+        .download_file("carol-slow-source", &share_codes[1])
+        .unwrap();
+    let mut carol_turns = tokio::time::interval(Duration::from_millis(50));
+    let deadline = Instant::now() + Duration::from_secs(120);
+    let finished = loop {
+        tokio::select! {
+            _ = alice.client.handle_next_swarm_event() => {}
+            _ = bob.client.handle_next_swarm_event() => {}
+            _ = carol_turns.tick() => {
+                let _ = tokio::time::timeout(
+                    Duration::from_millis(10),
+                    carol.client.handle_next_swarm_event(),
+                )
+                .await;
+            }
+            Some(_) = alice.events.next() => {}
+            Some(_) = carol.events.next() => {}
+            Some(event) = bob.events.next() => {
+                if matches!(
+                    event,
+                    P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. }
+                ) {
+                    break event;
+                }
+            }
+            _ = tokio::time::sleep_until(deadline) => panic!("Download did not finish"),
+        }
+    };
+    match finished {
+        P2pEvent::FileDownloadCompleted {
+            download_id: id,
+            path,
+            ..
+        } => {
+            assert_eq!(id, download_id);
+            assert_eq!(std::fs::read(path).unwrap(), contents);
+        }
+        other => panic!("Expected a completed download, got {:?}", other),
+    }
+
+    let served = |peer| {
+        bob.client
+            .peer_scores()
+            .into_iter()
+            .find(|score| score.peer_id == peer)
+            .map_or(0, |score| score.successes)
+    };
+    let (from_alice, from_carol) = (served(alice_id), served(carol_id));
+    assert_eq!(from_alice + from_carol, 24);
+    assert!(
+        from_alice > from_carol,
+        "alice served {}, carol {}",
+        from_alice,
+        from_carol
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_duplicate_download_reuses_in_flight_transfer() {
+    let mut alice = create_peer("alice-dedup");
+    let mut bob = create_peer("bob-dedup");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("twice.bin");
+    std::fs::write(&file, vec![3u8; CHUNK_SIZE * 20 + 5]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+
+    let first = bob
+        .client
+        .download_file("alice-dedup", &share_code)
+        .unwrap();
+    let second = bob
+        .client
+        .download_file("alice-dedup", &share_code)
+        .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(bob.client.active_downloads_detailed().len(), 1);
+
+    let mut started = 0;
+    let finished = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::FileDownloadStarted { .. } => {
+            started += 1;
+            false
+        }
+        P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. } => true,
+        _ => false,
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted {
+            download_id, path, ..
+        }) => {
+            assert_eq!(download_id, first);
+            assert_eq!(path.file_name().unwrap(), "twice.bin");
+            assert_eq!(std::fs::read(&path).unwrap(), std::fs::read(&file).unwrap());
+        }
+        other => panic!("Expected FileDownloadCompleted, got {:?}", other),
+    }
+    assert_eq!(started, 1);
+    // No second transfer is left running
+    assert!(bob.client.active_downloads_detailed().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_discovery_over_ipv6() {
+    use libp2p::multiaddr::Protocol;
+
+    let ipv6_only = || P2pConfig {
+        enable_ipv6: true,
+        listen_addrs: vec!["/ip6/::/tcp/0".parse().unwrap()],
+        ..Default::default()
+    };
+    let mut alice = create_peer_with_config("alice-ipv6", ipv6_only());
+    let mut bob = create_peer_with_config("bob-ipv6", ipv6_only());
+
+    let listening = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::ListeningOn { address }
+            if matches!(address.iter().next(), Some(Protocol::Ip6(_))))
+    })
+    .await;
+    assert!(listening.is_some(), "IPv6 listener should be created");
+
+    // Skip when the host has no IPv6 network to discover peers on
+    let has_ipv6 = std::net::UdpSocket::bind("[::]:0")
+        .and_then(|socket| socket.connect("[2001:db8::1]:9"))
+        .is_ok();
+    if !has_ipv6 {
+        return;
+    }
+
+    // Whichever peer discovers the other first dials, so watch both sides
+    let is_pair = |nickname: &str| nickname == "alice-ipv6" || nickname == "bob-ipv6";
+    let mut discovered = false;
+    let connected = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::PeerDiscovered {
+            nickname, address, ..
+        } if is_pair(nickname) => {
+            discovered |= matches!(address.iter().next(), Some(Protocol::Ip6(_)));
+            false
+        }
+        P2pEvent::Connected { nickname, .. } => is_pair(nickname),
+        _ => false,
+    })
+    .await;
+    assert!(discovered, "Peer should be discovered over IPv6");
+    assert!(connected.is_some(), "Peers should connect over IPv6");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_uses_separate_temp_dir() {
+    let scratch = tempfile::TempDir::new().unwrap();
+    let temp_dir = scratch.path().join("partial");
+    let mut alice = create_peer("alice-tempdir");
+    let mut bob = create_peer_with_config(
+        "bob-tempdir",
+        P2pConfig {
+            download_temp_dir: Some(temp_dir.clone()),
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("notes.txt");
+    std::fs::write(&file, vec![b'x'; CHUNK_SIZE + 42]).unwrap();
+
+    match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted { path, .. } => {
+            assert_eq!(path, bob.dir.path().join("notes.txt"));
+            assert_eq!(
+                std::fs::metadata(&path).unwrap().len(),
+                CHUNK_SIZE as u64 + 42
+            );
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+
+    // The temp file was created in the temp dir and moved out on completion
+    assert!(temp_dir.is_dir());
+    assert_eq!(std::fs::read_dir(&temp_dir).unwrap().count(), 0);
+    let leftovers = std::fs::read_dir(bob.dir.path())
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == "downloading")
+        })
+        .count();
+    assert_eq!(leftovers, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_served_with_read_ahead() {
+    let mut alice = create_peer_with_config(
+        "alice-readahead",
+        P2pConfig {
+            chunk_read_ahead: 4,
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer("bob-readahead");
+    connect(&mut alice, &mut bob).await;
+
+    // More chunks than the downloader requests at once, so later requests
+    // arrive after the chunks were read ahead
+    let content: Vec<u8> = (0..CHUNK_SIZE * 48 + 100).map(|i| i as u8).collect();
+    let file = alice.dir.path().join("sequential.bin");
+    std::fs::write(&file, &content).unwrap();
+
+    match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted { path, .. } => {
+            assert_eq!(std::fs::read(&path).unwrap(), content);
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+
+    let stats = alice.client.prefetch_stats().expect("Read-ahead enabled");
+    assert_eq!(stats.hits + stats.misses, 49);
+    assert!(stats.hits > 0, "Sequential chunks should be prefetched");
+    assert!(bob.client.prefetch_stats().is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_content_defined_chunks() {
+    let mut alice = create_peer_with_config(
+        "alice-cdc",
+        P2pConfig {
+            content_defined_chunking: true,
+            chunk_read_ahead: 2,
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer("bob-cdc");
+    connect(&mut alice, &mut bob).await;
+
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let content: Vec<u8> = (0..CHUNK_SIZE * 6)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let file = alice.dir.path().join("edited.bin");
+    std::fs::write(&file, &content).unwrap();
+
+    match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted { path, .. } => {
+            assert_eq!(std::fs::read(&path).unwrap(), content);
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+    let shared = alice.client.list_shared_files();
+    assert!(shared[0].info.chunk_offsets.is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_content_defined_chunks_refused_to_older_peers() {
+    let mut alice = create_peer_with_config(
+        "alice-cdc-old",
+        P2pConfig {
+            content_defined_chunking: true,
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer_with_config(
+        "bob-cdc-old",
+        P2pConfig {
+            file_transfer_versions: vec![FileTransferVersion::V1_3],
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("edited.bin");
+    std::fs::write(&file, vec![9u8; CHUNK_SIZE * 3]).unwrap();
+    match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadFailed { reason, error, .. } => {
+            assert_eq!(reason, DownloadFailureReason::Other);
+            assert_eq!(error, gigi_p2p::behaviour::CONTENT_DEFINED_CHUNKS_ERROR);
+        }
+        other => panic!("Expected refused download, got {:?}", other),
+    }
+    assert!(
+        !FileTransferVersion::V1_3
+            .capabilities()
+            .content_defined_chunks
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_details_count_content_defined_chunk_bytes() {
+    let mut alice = create_peer_with_config(
+        "alice-cdc-bytes",
+        P2pConfig {
+            content_defined_chunking: true,
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer("bob-cdc-bytes");
+    connect(&mut alice, &mut bob).await;
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let content: Vec<u8> = (0..CHUNK_SIZE * 24)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let file = alice.dir.path().join("varied.bin");
+    std::fs::write(&file, &content).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let info = alice.client.list_shared_files()[0].info.clone();
+    let offsets = info.chunk_offsets.clone().expect("Content-defined chunks");
+    let mut lens: Vec<u64> = offsets
+        .iter()
+        .zip(offsets.iter().skip(1).chain([&info.size]))
+        .map(|(start, end)| end - start)
+        .collect();
+    lens.sort_unstable();
+
+    let download_id = bob
+        .client
+        .download_file("alice-cdc-bytes", &share_code)
+        .unwrap();
+    // Received chunks vary in size, so their bytes lie between the sums
+    // of as many of the smallest and of the largest chunks
+    let mut checked = 0;
+    loop {
+        let event = drive_until(&mut alice, &mut bob, |event| {
+            matches!(
+                event,
+                P2pEvent::FileDownloadProgress { .. } | P2pEvent::FileDownloadCompleted { .. }
+            )
+        })
+        .await;
+        match event {
+            Some(P2pEvent::FileDownloadProgress { .. }) => {}
+            Some(P2pEvent::FileDownloadCompleted { .. }) => break,
+            other => panic!("Expected download progress, got {:?}", other),
+        }
+        let Some(detail) = bob
+            .client
+            .active_downloads_detailed()
+            .into_iter()
+            .find(|d| d.download_id == download_id)
+        else {
+            continue;
+        };
+        let k = detail.downloaded_chunks;
+        let least: u64 = lens[..k].iter().sum();
+        let most: u64 = lens[lens.len() - k..].iter().sum();
+        assert!(
+            (least..=most).contains(&detail.bytes_done),
+            "{} bytes for {} chunks",
+            detail.bytes_done,
+            k
+        );
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_revoked_mid_download_is_torn_down() {
+    let mut alice = create_peer("alice-revoke");
+    let mut bob = create_peer("bob-revoke");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("revoked.bin");
+    std::fs::write(&file, vec![3u8; CHUNK_SIZE * 64]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-revoke", &share_code)
+        .unwrap();
+
+    let started = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadStarted { .. })
+    })
+    .await;
+    assert!(started.is_some(), "Download should start");
+    alice.client.unshare_file(&share_code).unwrap();
+
+    let finished = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadFailed { download_id: id, .. }
+            | P2pEvent::FileDownloadCompleted { download_id: id, .. } if *id == download_id)
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadFailed { reason, .. }) => {
+            assert_eq!(reason, DownloadFailureReason::Revoked);
+        }
+        other => panic!("Expected revoked download, got {:?}", other),
+    }
+
+    assert!(bob.client.active_downloads_detailed().is_empty());
+    let leftovers: Vec<_> = std::fs::read_dir(bob.dir.path())
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "downloading"))
+        .collect();
+    assert!(leftovers.is_empty(), "Temp files left: {:?}", leftovers);
+}
+
+#[tokio::test]
+async fn test_unshare_where_checks_each_file_once() {
+    let mut alice = create_peer("alice-unshare-where");
+    for name in ["a.png", "b.png", "c.txt"] {
+        let file = alice.dir.path().join(name);
+        std::fs::write(&file, name.as_bytes()).unwrap();
+        alice.client.share_file(&file).await.unwrap();
+    }
+    while alice.events.try_recv().is_ok() {}
+
+    let calls = std::cell::Cell::new(0);
+    let removed = alice
+        .client
+        .unshare_where(|file| {
+            calls.set(calls.get() + 1);
+            file.info.name.ends_with(".png")
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(calls.get(), 3);
+    assert_eq!(removed.len(), 2);
+    assert_eq!(alice.client.list_shared_files().len(), 1);
+    let mut revoked = 0;
+    while let Ok(event) = alice.events.try_recv() {
+        if matches!(event, P2pEvent::FileRevoked { .. }) {
+            revoked += 1;
+        }
+    }
+    assert_eq!(revoked, 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_truncated_mid_download_fails_cleanly() {
+    let mut alice = create_peer_with_config(
+        "alice-truncate",
+        P2pConfig {
+            refresh_changed_shares: true,
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer("bob-truncate");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("truncated.bin");
+    std::fs::write(&file, vec![5u8; CHUNK_SIZE * 64]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-truncate", &share_code)
+        .unwrap();
+
+    let started = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadStarted { .. })
+    })
+    .await;
+    assert!(started.is_some(), "Download should start");
+    let truncated = vec![5u8; CHUNK_SIZE * 2 + 10];
+    std::fs::write(&file, &truncated).unwrap();
+
+    let finished = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadFailed { download_id: id, .. }
+            | P2pEvent::FileDownloadCompleted { download_id: id, .. } if *id == download_id)
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadFailed { reason, error, .. }) => {
+            assert_eq!(reason, DownloadFailureReason::Changed, "{}", error);
+        }
+        other => panic!("Expected changed download, got {:?}", other),
+    }
+    assert!(!bob.dir.path().join("truncated.bin").exists());
+
+    // The share is re-read in the background and refreshed once that finishes
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(30);
+    while alice.client.list_shared_files()[0].info.size != truncated.len() as u64 {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "Share should be refreshed"
+        );
+        let _ = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            alice.client.handle_next_swarm_event(),
+        )
+        .await;
+    }
+
+    // Downloading again gets the new contents
+    let download_id = bob
+        .client
+        .download_file("alice-truncate", &share_code)
+        .unwrap();
+    let finished = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadFailed { download_id: id, .. }
+            | P2pEvent::FileDownloadCompleted { download_id: id, .. } if *id == download_id)
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(std::fs::read(&path).unwrap(), truncated);
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_progress_events_are_throttled() {
+    let mut alice = create_peer("alice-progress");
+    let mut bob = create_peer_with_config(
+        "bob-progress",
+        P2pConfig {
+            progress_granularity: ProgressGranularity::Interval(std::time::Duration::from_secs(
+                3600,
+            )),
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+
+    let total = 96;
+    let file = alice.dir.path().join("progress.bin");
+    std::fs::write(&file, vec![9u8; CHUNK_SIZE * total]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-progress", &share_code)
+        .unwrap();
+
+    let mut progress = Vec::new();
+    let finished = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::FileDownloadProgress {
+            downloaded_chunks, ..
+        } => {
+            progress.push(*downloaded_chunks);
+            false
+        }
+        P2pEvent::FileDownloadFailed {
+            download_id: id, ..
+        }
+        | P2pEvent::FileDownloadCompleted {
+            download_id: id, ..
+        } => *id == download_id,
+        _ => false,
+    })
+    .await;
+    assert!(
+        matches!(finished, Some(P2pEvent::FileDownloadCompleted { .. })),
+        "Expected completed download, got {:?}",
+        finished
+    );
+
+    // Only the final 100% update gets through an interval longer than the transfer
+    assert_eq!(progress, vec![total]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_per_percent_progress_for_uploads_and_downloads() {
+    let config = P2pConfig {
+        progress_granularity: ProgressGranularity::PerPercent,
+        ..Default::default()
+    };
+    let mut alice = create_peer_with_config("alice-percent", config.clone());
+    let mut bob = create_peer_with_config("bob-percent", config);
+    connect(&mut alice, &mut bob).await;
+
+    // More chunks than percentages, so some updates must be coalesced
+    let total = 250;
+    let file = alice.dir.path().join("percent.bin");
+    std::fs::write(&file, vec![4u8; CHUNK_SIZE * total]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-percent", &share_code)
+        .unwrap();
+
+    let (mut downloaded, mut uploaded) = (Vec::new(), Vec::new());
+    let finished = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::FileDownloadProgress {
+            downloaded_chunks, ..
+        } => {
+            downloaded.push(*downloaded_chunks);
+            false
+        }
+        P2pEvent::FileUploadProgress {
+            uploaded_chunks, ..
+        } => {
+            uploaded.push(*uploaded_chunks);
+            false
+        }
+        P2pEvent::FileDownloadFailed {
+            download_id: id, ..
+        }
+        | P2pEvent::FileDownloadCompleted {
+            download_id: id, ..
+        } => *id == download_id,
+        _ => false,
+    })
+    .await;
+    assert!(
+        matches!(finished, Some(P2pEvent::FileDownloadCompleted { .. })),
+        "Expected completed download, got {:?}",
+        finished
+    );
+    if uploaded.last() != Some(&total) {
+        let last = drive_peer_until(&mut alice, |event| {
+            if let P2pEvent::FileUploadProgress {
+                uploaded_chunks, ..
+            } = event
+            {
+                uploaded.push(*uploaded_chunks);
+            }
+            uploaded.last() == Some(&total)
+        })
+        .await;
+        assert!(last.is_some(), "Upload should report completion");
+    }
+
+    for progress in [&downloaded, &uploaded] {
+        assert!(progress.len() <= 100, "{} progress events", progress.len());
+        assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(progress.last(), Some(&total));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_by_code_after_sender_renames() {
+    let mut alice = create_peer("alice-rename");
+    let mut bob = create_peer("bob-rename");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("renamed.txt");
+    std::fs::write(&file, b"routed by peer id").unwrap();
+    alice
+        .client
+        .send_direct_file("bob-rename", &file)
+        .await
+        .unwrap();
+
+    let shared = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::DirectFileShareMessage { .. })
+    })
+    .await;
+    let share_code = match shared {
+        Some(P2pEvent::DirectFileShareMessage { share_code, .. }) => share_code,
+        other => panic!("Expected file share message, got {:?}", other),
+    };
+    assert_eq!(
+        bob.client.share_source(&share_code),
+        Some(alice.client.local_peer_id())
+    );
+
+    alice.client.set_nickname("alice-renamed").unwrap();
+    let updated = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::NicknameUpdated { nickname, .. } if nickname == "alice-renamed")
+    })
+    .await;
+    assert!(updated.is_some(), "Rename should reach bob");
+    assert!(bob
+        .client
+        .download_file("alice-rename", &share_code)
+        .is_err());
+
+    let download_id = bob.client.download_file_by_code(&share_code).unwrap();
+    let finished = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadCompleted { download_id: id, .. }
+            | P2pEvent::FileDownloadFailed { download_id: id, .. } if *id == download_id)
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(std::fs::read(&path).unwrap(), b"routed by peer id");
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+    // The source is forgotten once the download finished
+    assert_eq!(bob.client.share_source(&share_code), None);
+    assert!(bob.client.download_file_by_code("unknown-code").is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_trusted_peer_large_file_auto_downloads() {
+    let mut alice = create_peer("alice-trusted");
+    let mut bob = create_persistent_peer("bob-trusted");
+    bob.client
+        .update_config(P2pConfig {
+            auto_accept_max_size: Some(16),
+            ..P2pConfig::default()
+        })
+        .unwrap();
+    connect(&mut alice, &mut bob).await;
+    let alice_id = alice.client.local_peer_id();
+
+    // Too large to auto-accept from an untrusted peer
+    let untrusted = alice.dir.path().join("untrusted.txt");
+    std::fs::write(&untrusted, b"larger than the auto-accept limit").unwrap();
+    alice
+        .client
+        .send_direct_file("bob-trusted", &untrusted)
+        .await
+        .unwrap();
+    let shared = drive_until(
+        &mut alice,
+        &mut bob,
+        |event| matches!(event, P2pEvent::DirectFileShareMessage { from, .. } if *from == alice_id),
+    )
+    .await;
+    assert!(shared.is_some(), "Share message should reach bob");
+    assert!(bob.client.get_active_downloads().is_empty());
+
+    bob.client.trust_peer(alice_id);
+    assert!(bob.client.is_trusted(&alice_id));
+    let trusted = alice.dir.path().join("trusted.txt");
+    std::fs::write(&trusted, b"also larger than the auto-accept limit").unwrap();
+    alice
+        .client
+        .send_direct_file("bob-trusted", &trusted)
+        .await
+        .unwrap();
+    let finished = drive_until(&mut alice, &mut bob, |event| {
+        matches!(
+            event,
+            P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. }
+        )
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(
+                std::fs::read(&path).unwrap(),
+                b"also larger than the auto-accept limit"
+            );
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+
+    // Trust survives a restart
+    bob.client
+        .drain_and_shutdown(std::time::Duration::from_secs(5))
+        .await
+        .unwrap();
+    let (restarted, _events) = P2pClient::new_with_full_config(
+        Keypair::generate_ed25519(),
+        "bob-trusted".to_string(),
+        bob.dir.path().to_path_buf(),
+        Some(PersistenceConfig {
+            db_path: bob.dir.path().join("gigi.db"),
+            ..Default::default()
+        }),
+        P2pConfig::default(),
+    )
+    .unwrap();
+    assert_eq!(restarted.trusted_peers(), vec![alice_id]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_unknown_share_code_fails_promptly() {
+    let mut alice = create_peer("alice-unknown");
+    let mut bob = create_peer("bob-unknown");
+    connect(&mut alice, &mut bob).await;
+
+    let requested = tokio::time::Instant::now();
+    let download_id = bob
+        .client
+        .download_file("alice-unknown", "deadbeef")
+        .unwrap();
+    let failed = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadFailed { download_id: id, .. } if *id == download_id)
+    })
+    .await;
+    match failed {
+        Some(P2pEvent::FileDownloadFailed {
+            share_code, reason, ..
+        }) => {
+            assert_eq!(share_code, "deadbeef");
+            assert_eq!(reason, DownloadFailureReason::NotFound);
+        }
+        other => panic!("Expected failed download, got {:?}", other),
+    }
+    // The file info probe fails the download without waiting for any timeout
+    assert!(requested.elapsed() < std::time::Duration::from_secs(10));
+    assert!(bob.client.active_downloads_detailed().is_empty());
+
+    // A code the sharer has revoked is reported as such
+    let file = alice.dir.path().join("gone.txt");
+    std::fs::write(&file, b"no longer shared").unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    alice.client.unshare_file(&share_code).unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-unknown", &share_code)
+        .unwrap();
+    let failed = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadFailed { download_id: id, .. } if *id == download_id)
+    })
+    .await;
+    match failed {
+        Some(P2pEvent::FileDownloadFailed { reason, .. }) => {
+            assert_eq!(reason, DownloadFailureReason::Revoked);
+        }
+        other => panic!("Expected failed download, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stalled_download_fails_after_idle_timeout() {
+    let mut alice = create_peer("alice-stall");
+    let mut bob = create_peer_with_config(
+        "bob-stall",
+        P2pConfig {
+            download_idle_timeout: Some(std::time::Duration::from_secs(2)),
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("stalls.bin");
+    std::fs::write(&file, vec![9u8; CHUNK_SIZE * 64]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-stall", &share_code)
+        .unwrap();
+    let progress = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadProgress { download_id: id, .. } if *id == download_id)
+    })
+    .await;
+    assert!(progress.is_some(), "Download should make progress");
+
+    // The sharer stops responding; only the downloader keeps running
+    let stalled = tokio::time::Instant::now();
+    let failed = drive_peer_until(&mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadFailed { download_id: id, .. } if *id == download_id)
+    })
+    .await;
+    match failed {
+        Some(P2pEvent::FileDownloadFailed { reason, error, .. }) => {
+            assert_eq!(reason, DownloadFailureReason::Timeout);
+            assert!(error.contains("No data from the sharer"), "{}", error);
+        }
+        other => panic!("Expected failed download, got {:?}", other),
+    }
+    // Well before the 10s request timeout
+    assert!(stalled.elapsed() < std::time::Duration::from_secs(8));
+    assert!(bob.client.active_downloads_detailed().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_finished_downloads_are_kept_in_history() {
+    let mut alice = create_peer("alice-history");
+    let mut bob = create_persistent_peer("bob-history");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("kept.bin");
+    std::fs::write(&file, vec![3u8; CHUNK_SIZE + 5]).unwrap();
+    let completed_path = match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted { path, .. } => path,
+        other => panic!("Expected completed download, got {:?}", other),
+    };
+
+    let download_id = bob
+        .client
+        .download_file("alice-history", "deadbeef")
+        .unwrap();
+    drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadFailed { download_id: id, .. } if *id == download_id)
+    })
+    .await
+    .expect("Download should fail");
+
+    // History is written in the background
+    let mut history = Vec::new();
+    for _ in 0..100 {
+        history = bob.client.list_download_history(10, 0).await.unwrap();
+        if history.len() == 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(history.len(), 2, "{:?}", history);
+    let failed = history
+        .iter()
+        .find(|entry| entry.status == DownloadHistoryStatus::Failed)
+        .unwrap();
+    assert_eq!(failed.share_code, "deadbeef");
+    assert_eq!(failed.peer_nickname, "alice-history");
+    assert_eq!(failed.error.as_deref(), Some("File not found"));
+    assert!(failed.final_path.is_none());
+    let completed = history
+        .iter()
+        .find(|entry| entry.status == DownloadHistoryStatus::Completed)
+        .unwrap();
+    assert_eq!(completed.file_name, "kept.bin");
+    assert_eq!(completed.peer_id, alice.client.local_peer_id().to_string());
+    assert_eq!(completed.file_size, Some(CHUNK_SIZE as u64 + 5));
+    assert_eq!(
+        completed.final_path.as_deref(),
+        Some(completed_path.to_string_lossy().as_ref())
+    );
+    assert!(completed.started_at <= completed.finished_at);
+
+    assert_eq!(bob.client.clear_download_history().await.unwrap(), 2);
+    assert!(bob
+        .client
+        .list_download_history(10, 0)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cancelled_download_is_kept_in_history() {
+    let mut alice = create_peer("alice-cancel");
+    let mut bob = create_persistent_peer("bob-cancel");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("unwanted.bin");
+    std::fs::write(&file, vec![4u8; CHUNK_SIZE * 64]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-cancel", &share_code)
+        .unwrap();
+    drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadProgress { .. })
+    })
+    .await
+    .expect("Download should make progress");
+
+    assert!(bob.client.cancel_download(&download_id));
+    assert!(!bob.client.cancel_download(&download_id));
+    assert!(bob.client.active_downloads_detailed().is_empty());
+
+    // Chunks still in flight neither revive nor finish the download
+    let finished = drive_until(&mut alice, &mut bob, |event| {
+        matches!(
+            event,
+            P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. }
+        )
+    });
+    assert!(
+        tokio::time::timeout(std::time::Duration::from_secs(2), finished)
+            .await
+            .is_err()
+    );
+    assert!(bob.client.active_downloads_detailed().is_empty());
+
+    // History is written in the background
+    let mut history = Vec::new();
+    for _ in 0..100 {
+        history = bob.client.list_download_history(10, 0).await.unwrap();
+        if !history.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(history.len(), 1, "{:?}", history);
+    assert_eq!(history[0].status, DownloadHistoryStatus::Cancelled);
+    assert_eq!(history[0].share_code, share_code);
+    assert_eq!(history[0].file_name, "unwanted.bin");
+    assert_eq!(history[0].file_size, Some(CHUNK_SIZE as u64 * 64));
+    assert!(history[0].final_path.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_preserves_modification_time() {
+    let mut alice = create_peer("alice-mtime");
+    let mut bob = create_peer("bob-mtime");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("holiday.jpg");
+    std::fs::write(&file, vec![5u8; CHUNK_SIZE + 9]).unwrap();
+    let modified = std::time::UNIX_EPOCH + std::time::Duration::new(1_577_836_800, 123_456_789);
+    std::fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted { path, .. } => {
+            let downloaded = std::fs::metadata(&path).unwrap().modified().unwrap();
+            assert_eq!(downloaded, modified);
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_verified_with_sharer_hash_algo() {
+    let mut alice = create_peer_with_config(
+        "alice-blake3",
+        P2pConfig {
+            file_hash_algo: HashAlgo::Blake3,
+            ..Default::default()
+        },
+    );
+    // Bob keeps the SHA256 default and still verifies alice's BLAKE3 hash
+    let mut bob = create_peer("bob-blake3");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("media.bin");
+    let contents = vec![9u8; CHUNK_SIZE + 17];
+    std::fs::write(&file, &contents).unwrap();
+
+    match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted { path, .. } => {
+            assert_eq!(std::fs::read(&path).unwrap(), contents);
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_completed_download_reports_transfer_stats() {
+    let mut alice = create_peer("alice-stats");
+    let mut bob = create_peer("bob-stats");
+    connect(&mut alice, &mut bob).await;
+
+    let size = CHUNK_SIZE * 2 + 100;
+    let file = alice.dir.path().join("stats.bin");
+    std::fs::write(&file, vec![7u8; size]).unwrap();
+
+    let started = std::time::Instant::now();
+    let finished = transfer(&mut alice, &mut bob, &file).await;
+    let elapsed = started.elapsed();
+
+    match finished {
+        P2pEvent::FileDownloadCompleted {
+            duration,
+            average_speed,
+            total_bytes,
+            chunk_count,
+            ..
+        } => {
+            assert_eq!(total_bytes, size as u64);
+            assert_eq!(chunk_count, 3);
+            // The download ran inside the timed window
+            assert!(!duration.is_zero());
+            assert!(duration <= elapsed);
+            let expected_speed = total_bytes as f64 / duration.as_secs_f64();
+            assert!((average_speed - expected_speed).abs() <= expected_speed * 1e-6);
+            assert!(average_speed >= total_bytes as f64 / elapsed.as_secs_f64());
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_corrupted_chunk_is_re_requested_on_hash_mismatch() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let mut alice = create_peer("alice-repair");
+    let mut bob = create_peer_with_config(
+        "bob-repair",
+        P2pConfig {
+            hash_mismatch_repair_rounds: 2,
+            progress_granularity: ProgressGranularity::PerChunk,
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+
+    let total = 32;
+    let contents = vec![6u8; CHUNK_SIZE * total];
+    let file = alice.dir.path().join("repair.bin");
+    std::fs::write(&file, &contents).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-repair", &share_code)
+        .unwrap();
+    let temp_path = bob.dir.path().join(format!("{}.downloading", download_id));
+
+    // Corrupt the first chunk on disk once it has been written and verified
+    let mut corrupted = None;
+    let mut progress = Vec::new();
+    let finished = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::FileDownloadProgress {
+            downloaded_chunks, ..
+        } => {
+            progress.push(*downloaded_chunks);
+            if corrupted.is_none() {
+                let written = std::fs::read(&temp_path).unwrap();
+                corrupted = written.chunks(CHUNK_SIZE).position(|chunk| chunk[0] == 6);
+                if let Some(index) = corrupted {
+                    let mut temp = std::fs::OpenOptions::new()
+                        .write(true)
+                        .open(&temp_path)
+                        .unwrap();
+                    temp.seek(SeekFrom::Start((index * CHUNK_SIZE) as u64))
+                        .unwrap();
+                    temp.write_all(b"corrupted").unwrap();
+                }
+            }
+            false
+        }
+        P2pEvent::FileDownloadFailed {
+            download_id: id, ..
+        }
+        | P2pEvent::FileDownloadCompleted {
+            download_id: id, ..
+        } => *id == download_id,
+        _ => false,
+    })
+    .await;
+
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(std::fs::read(&path).unwrap(), contents);
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+    assert!(corrupted.is_some(), "A chunk should have been corrupted");
+
+    // Only the corrupted chunk was fetched again after the first full pass
+    let first_full = progress.iter().position(|&count| count == total).unwrap();
+    assert_eq!(progress[first_full + 1..], [total]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_request_chunk_rewrites_corrupted_chunk() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let mut alice = create_peer("alice-rechunk");
+    let mut bob = create_peer_with_config(
+        "bob-rechunk",
+        P2pConfig {
+            progress_granularity: ProgressGranularity::PerChunk,
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+
+    let total = 64;
+    let contents = vec![8u8; CHUNK_SIZE * total];
+    let file = alice.dir.path().join("rechunk.bin");
+    std::fs::write(&file, &contents).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let download_id = bob
+        .client
+        .download_file("alice-rechunk", &share_code)
+        .unwrap();
+    let temp_path = bob.dir.path().join(format!("{}.downloading", download_id));
+
+    // Swarms are not driven between the checks below, so no other chunk arrives
+    let progress = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileDownloadProgress { .. })
+    })
+    .await;
+    assert!(progress.is_some(), "Download should make progress");
+    let written = std::fs::read(&temp_path).unwrap();
+    let index = written
+        .chunks(CHUNK_SIZE)
+        .position(|chunk| chunk[0] == 8)
+        .expect("A chunk should be written");
+
+    // An intact chunk is not requested again
+    assert!(!bob.client.request_chunk(&share_code, index).unwrap());
+    assert!(bob.client.request_chunk(&share_code, total).is_err());
+    assert!(bob.client.request_chunk("unknown-code", 0).is_err());
+
+    let mut temp = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&temp_path)
+        .unwrap();
+    temp.seek(SeekFrom::Start((index * CHUNK_SIZE) as u64))
+        .unwrap();
+    temp.write_all(b"corrupted").unwrap();
+    assert!(bob.client.request_chunk(&share_code, index).unwrap());
+
+    // Without repair rounds the whole-file hash only matches if the chunk was rewritten
+    let finished = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::FileDownloadFailed {
+            download_id: id, ..
+        }
+        | P2pEvent::FileDownloadCompleted {
+            download_id: id, ..
+        } => *id == download_id,
+        _ => false,
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(std::fs::read(&path).unwrap(), contents);
+        }
+        other => panic!("Expected completed download, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_transfer_falls_back_to_older_version() {
+    let mut alice = create_peer_with_config(
+        "alice-v10",
+        P2pConfig {
+            file_transfer_versions: vec![FileTransferVersion::V1_0],
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer("bob-v11");
+    connect(&mut alice, &mut bob).await;
+    let alice_peer_id = alice.client.local_peer_id();
+    assert_eq!(bob.client.file_transfer_version(&alice_peer_id), None);
+
+    let file = alice.dir.path().join("old.txt");
+    std::fs::write(&file, b"spoken over 1.0").unwrap();
+    match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted { path, .. } => {
+            assert_eq!(std::fs::read(&path).unwrap(), b"spoken over 1.0");
+        }
+        other => panic!("Expected FileDownloadCompleted, got {:?}", other),
+    }
+
+    let version = bob.client.file_transfer_version(&alice_peer_id);
+    assert_eq!(version, Some(FileTransferVersion::V1_0));
+    assert!(!version.unwrap().capabilities().hash_algo);
+    assert!(!version.unwrap().capabilities().byte_string_chunks);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_transfer_negotiates_highest_version() {
+    let mut alice = create_peer("alice-v14");
+    let mut bob = create_peer("bob-v14-all");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("new.txt");
+    std::fs::write(&file, b"spoken over 1.4").unwrap();
+    let finished = transfer(&mut alice, &mut bob, &file).await;
+    assert!(matches!(finished, P2pEvent::FileDownloadCompleted { .. }));
+
+    let version = bob
+        .client
+        .file_transfer_version(&alice.client.local_peer_id());
+    assert_eq!(version, Some(FileTransferVersion::V1_4));
+    assert!(version.unwrap().capabilities().hash_algo);
+    assert!(version.unwrap().capabilities().byte_string_chunks);
+    assert!(version.unwrap().capabilities().byte_ranges);
+    assert!(version.unwrap().capabilities().content_defined_chunks);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_request_range_returns_middle_of_file() {
+    let mut alice = create_peer("alice-range");
+    let mut bob = create_peer("bob-range");
+    connect(&mut alice, &mut bob).await;
+
+    let contents: Vec<u8> = (0..2 * CHUNK_SIZE + 999).map(|i| (i % 253) as u8).collect();
+    let file = alice.dir.path().join("movie.bin");
+    std::fs::write(&file, &contents).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+
+    assert!(bob
+        .client
+        .request_range("alice-range", &share_code, 0, MAX_RANGE_SIZE + 1)
+        .is_err());
+
+    // A range across a chunk boundary
+    let start = CHUNK_SIZE as u64 - 1000;
+    let request_id = bob
+        .client
+        .request_range("alice-range", &share_code, start, 5000)
+        .unwrap();
+    let received = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileRangeReceived { request_id: id, .. } | P2pEvent::FileRangeFailed { request_id: id, .. } if *id == request_id)
+    })
+    .await;
+    match received {
+        Some(P2pEvent::FileRangeReceived { from, range, .. }) => {
+            assert_eq!(from, alice.client.local_peer_id());
+            assert_eq!(range.file_id, share_code);
+            assert_eq!(range.start, start);
+            assert_eq!(range.data, &contents[start as usize..start as usize + 5000]);
+        }
+        other => panic!("Expected FileRangeReceived, got {:?}", other),
+    }
+
+    // The end of the file is cut short
+    let start = contents.len() as u64 - 10;
+    let request_id = bob
+        .client
+        .request_range("alice-range", &share_code, start, 100)
+        .unwrap();
+    let received = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileRangeReceived { request_id: id, .. } | P2pEvent::FileRangeFailed { request_id: id, .. } if *id == request_id)
+    })
+    .await;
+    match received {
+        Some(P2pEvent::FileRangeReceived { range, .. }) => {
+            assert_eq!(range.data, &contents[contents.len() - 10..]);
+        }
+        other => panic!("Expected FileRangeReceived, got {:?}", other),
+    }
+
+    // Unknown files fail
+    let request_id = bob
+        .client
+        .request_range("alice-range", "0badc0de", 0, 100)
+        .unwrap();
+    let failed = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::FileRangeReceived { request_id: id, .. } | P2pEvent::FileRangeFailed { request_id: id, .. } if *id == request_id)
+    })
+    .await;
+    assert!(
+        matches!(failed, Some(P2pEvent::FileRangeFailed { ref share_code, .. }) if share_code == "0badc0de"),
+        "{:?}",
+        failed
+    );
+}
+
+/// Encode a full chunk response the way it is sent over `version`
+async fn encoded_chunk(version: FileTransferVersion, data: &[u8]) -> Vec<u8> {
+    let response = FileSharingResponse::Chunk(Some(ChunkInfo {
+        file_id: "a1b2c3d4".to_string(),
+        chunk_index: 7,
+        data: data.to_vec(),
+        hash: "chunk-hash".to_string(),
+    }));
+    let mut encoded = futures::io::Cursor::new(Vec::new());
+    FileSharingCodec::default()
+        .write_response(&version.protocol(), &mut encoded, response.into())
+        .await
+        .unwrap();
+    encoded.into_inner()
+}
+
+#[tokio::test]
+async fn test_chunk_encoding_is_close_to_raw_size() {
+    let data: Vec<u8> = (0..CHUNK_SIZE).map(|i| (i * 31 % 251) as u8).collect();
+
+    let compact = encoded_chunk(FileTransferVersion::V1_2, &data).await;
+    assert!(
+        compact.len() < data.len() + 256,
+        "{} bytes for a {} byte chunk",
+        compact.len(),
+        data.len()
+    );
+    let legacy = encoded_chunk(FileTransferVersion::V1_1, &data).await;
+    assert!(legacy.len() > data.len() * 3 / 2);
+
+    // Both encodings read back to the same chunk
+    for (version, encoded) in [
+        (FileTransferVersion::V1_2, compact),
+        (FileTransferVersion::V1_1, legacy),
+    ] {
+        let response = FileSharingCodec::default()
+            .read_response(&version.protocol(), &mut futures::io::Cursor::new(encoded))
+            .await
+            .unwrap();
+        assert_eq!(response.version, version);
+        match response.response {
+            FileSharingResponse::Chunk(Some(chunk)) => {
+                assert_eq!(chunk.chunk_index, 7);
+                assert_eq!(chunk.data, data);
+            }
+            other => panic!("Expected a chunk, got {:?}", other),
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sharer_read_failure_surfaces_as_transfer_error() {
+    let mut alice = create_peer("alice-read-error");
+    let mut bob = create_peer("bob-read-error");
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("vanished.txt");
+    std::fs::write(&file, b"gone before it is read").unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    // Still shared, but the sharer can no longer read it
+    std::fs::remove_file(&file).unwrap();
+    bob.client
+        .download_file("alice-read-error", &share_code)
+        .unwrap();
+
+    let error = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::Error(_))
+    })
+    .await;
+    match error {
+        Some(P2pEvent::Error(P2pErrorKind::Transfer(message))) => {
+            assert_eq!(message, "Failed to read chunk");
+        }
+        other => panic!("Expected a transfer error, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_complete_hook_receives_details() {
+    let mut alice = create_peer("alice-hook");
+    let mut bob = create_peer("bob-hook");
+    connect(&mut alice, &mut bob).await;
+
+    let (hook_sender, mut hook_receiver) = tokio::sync::mpsc::unbounded_channel();
+    bob.client
+        .set_download_complete_hook(Arc::new(move |detail: &DownloadDetail| {
+            // The file is already in place when the hook runs
+            let contents = std::fs::read(detail.final_path.as_ref().unwrap()).unwrap();
+            hook_sender.send((detail.clone(), contents)).unwrap();
+        }));
+
+    let contents = vec![7u8; CHUNK_SIZE + 10];
+    let file = alice.dir.path().join("hooked.bin");
+    std::fs::write(&file, &contents).unwrap();
+    let (download_id, share_code, path) = match transfer(&mut alice, &mut bob, &file).await {
+        P2pEvent::FileDownloadCompleted {
+            download_id,
+            share_code,
+            path,
+            ..
+        } => (download_id, share_code, path),
+        other => panic!("Expected FileDownloadCompleted, got {:?}", other),
+    };
+
+    let (detail, hooked_contents) =
+        tokio::time::timeout(std::time::Duration::from_secs(10), hook_receiver.recv())
+            .await
+            .expect("Hook should run")
+            .unwrap();
+    assert_eq!(detail.download_id, download_id);
+    assert_eq!(detail.share_code, share_code);
+    assert_eq!(detail.filename, "hooked.bin");
+    assert_eq!(detail.from_nickname, "alice-hook");
+    assert_eq!(detail.total_chunks, 2);
+    assert_eq!(detail.downloaded_chunks, 2);
+    assert_eq!(detail.bytes_done, contents.len() as u64);
+    assert_eq!(detail.final_path, Some(path));
+    assert_eq!(hooked_contents, contents);
+}
+
+/// Start a persistent sharer in `dir` that saves upload progress
+fn start_uploader(nickname: &str, keypair: Keypair, dir: tempfile::TempDir) -> TestPeer {
+    let persistence = PersistenceConfig {
+        db_path: dir.path().join("gigi.db"),
+        ..Default::default()
+    };
+    let config = P2pConfig {
+        progress_granularity: ProgressGranularity::PerChunk,
+        persist_upload_progress: true,
+        ..Default::default()
+    };
+    let (mut client, events) = P2pClient::new_with_full_config(
+        keypair,
+        nickname.to_string(),
+        dir.path().to_path_buf(),
+        Some(persistence),
+        config,
+    )
+    .unwrap();
+    client.start_configured_listeners().unwrap();
+    TestPeer {
+        client,
+        events,
+        dir,
+    }
+}
+
+/// Start a downloader with a fixed identity, so it is the same peer after a restart
+fn start_downloader(nickname: &str, keypair: Keypair) -> TestPeer {
+    let dir = tempfile::TempDir::new().unwrap();
+    let (mut client, events) = P2pClient::new_with_config(
+        keypair,
+        nickname.to_string(),
+        dir.path().to_path_buf(),
+        P2pConfig::default(),
+    )
+    .unwrap();
+    client.start_configured_listeners().unwrap();
+    TestPeer {
+        client,
+        events,
+        dir,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upload_progress_resumes_after_restart() {
+    let (alice_keypair, bob_keypair) = (Keypair::generate_ed25519(), Keypair::generate_ed25519());
+    let mut alice = start_uploader(
+        "alice-resume-upload",
+        alice_keypair.clone(),
+        tempfile::TempDir::new().unwrap(),
+    );
+    let mut bob = start_downloader("bob-resume-upload", bob_keypair.clone());
+    connect(&mut alice, &mut bob).await;
+
+    let total = 64;
+    let file = alice.dir.path().join("served.bin");
+    std::fs::write(&file, vec![6u8; CHUNK_SIZE * total]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    bob.client
+        .download_file("alice-resume-upload", &share_code)
+        .unwrap();
+    let served_before = match drive_until_from(&mut bob, &mut alice, |event| {
+        matches!(event, P2pEvent::FileUploadProgress { .. })
+    })
+    .await
+    {
+        Some(P2pEvent::FileUploadProgress {
+            uploaded_chunks, ..
+        }) => uploaded_chunks,
+        other => panic!("Expected upload progress, got {:?}", other),
+    };
+    assert!(served_before < total);
+
+    // Restart both peers with the same identities; the sharer keeps its database
+    let TestPeer {
+        client,
+        events,
+        dir,
+    } = alice;
+    drop((client, events, bob));
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    let mut alice = start_uploader("alice-resume-upload", alice_keypair, dir);
+    let mut bob = start_downloader("bob-resume-upload", bob_keypair);
+    connect(&mut alice, &mut bob).await;
+
+    bob.client
+        .download_file("alice-resume-upload", &share_code)
+        .unwrap();
+    let resumed = drive_until_from(&mut bob, &mut alice, |event| {
+        matches!(event, P2pEvent::FileUploadProgress { .. })
+    })
+    .await;
+    match resumed {
+        Some(P2pEvent::FileUploadProgress {
+            uploaded_chunks,
+            total_chunks,
+            ..
+        }) => {
+            assert!(
+                uploaded_chunks > served_before,
+                "Progress restarted at {} after {} chunks were served",
+                uploaded_chunks,
+                served_before
+            );
+            assert_eq!(total_chunks, total);
+        }
+        other => panic!("Expected upload progress, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upload_progress_restarts_after_peer_disconnects() {
+    let bob_keypair = Keypair::generate_ed25519();
+    let mut alice = create_peer_with_config(
+        "alice-forget-upload",
+        P2pConfig {
+            progress_granularity: ProgressGranularity::PerChunk,
+            ..Default::default()
+        },
+    );
+    let mut bob = start_downloader("bob-forget-upload", bob_keypair.clone());
+    connect(&mut alice, &mut bob).await;
+
+    let file = alice.dir.path().join("served.bin");
+    std::fs::write(&file, vec![8u8; CHUNK_SIZE * 64]).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    bob.client
+        .download_file("alice-forget-upload", &share_code)
+        .unwrap();
+    let served = drive_until_from(&mut bob, &mut alice, |event| {
+        matches!(event, P2pEvent::FileUploadProgress { uploaded_chunks, .. } if *uploaded_chunks >= 4)
+    })
+    .await;
+    assert!(served.is_some(), "Chunks should be served");
+
+    // Bob goes away; without persisted progress alice forgets the upload
+    let bob_id = bob.client.local_peer_id();
+    drop(bob);
+    let disconnected = drive_peer_until(
+        &mut alice,
+        |event| matches!(event, P2pEvent::Disconnected { peer_id, .. } if *peer_id == bob_id),
+    )
+    .await;
+    assert!(disconnected.is_some(), "Bob should disconnect");
+
+    // Downloading again is counted from the first chunk. Alice serves
+    // without knowing bob's nickname, so only bob waits to find her
+    let mut bob = start_downloader("bob-forget-upload", bob_keypair);
+    let reconnected = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::Connected { nickname, .. } if nickname == "alice-forget-upload")
+    })
+    .await;
+    assert!(reconnected.is_some(), "Bob should reconnect");
+    bob.client
+        .download_file("alice-forget-upload", &share_code)
+        .unwrap();
+    match drive_until_from(&mut bob, &mut alice, |event| {
+        matches!(event, P2pEvent::FileUploadProgress { .. })
+    })
+    .await
+    {
+        Some(P2pEvent::FileUploadProgress {
+            uploaded_chunks, ..
+        }) => assert_eq!(uploaded_chunks, 1),
+        other => panic!("Expected upload progress, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_chunk_requests_capped_per_peer() {
+    use futures::StreamExt;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    let cap = 2;
+    let mut alice = create_peer("alice-cap");
+    let mut carol = create_peer("carol-cap");
+    let mut bob = create_peer_with_config(
+        "bob-cap",
+        P2pConfig {
+            max_chunk_requests_per_peer: cap,
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+    connect(&mut carol, &mut bob).await;
+    let alice_id = alice.client.local_peer_id();
+    let carol_id = carol.client.local_peer_id();
+
+    // Two downloads from alice share her slots, one from carol
+    let mut expected = HashMap::new();
+    for (seed, name) in [(1u8, "first.bin"), (2, "second.bin"), (3, "third.bin")] {
+        let sharer = if seed < 3 { &mut alice } else { &mut carol };
+        let contents: Vec<u8> = (0..CHUNK_SIZE * 6)
+            .map(|i| (i % 251) as u8 ^ seed)
+            .collect();
+        let file = sharer.dir.path().join(name);
+        std::fs::write(&file, &contents).unwrap();
+        let share_code = sharer.client.share_file(&file).await.unwrap();
+        let nickname = sharer.client.local_nickname().to_string();
+        let download_id = bob.client.download_file(&nickname, &share_code).unwrap();
+        expected.insert(download_id, contents);
+    }
+
+    let mut most_in_flight = (0, 0);
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while !expected.is_empty() {
+        tokio::select! {
+            _ = alice.client.handle_next_swarm_event() => {}
+            _ = carol.client.handle_next_swarm_event() => {}
+            _ = bob.client.handle_next_swarm_event() => {}
+            Some(_) = alice.events.next() => {}
+            Some(_) = carol.events.next() => {}
+            Some(event) = bob.events.next() => match event {
+                P2pEvent::FileDownloadCompleted { download_id, path, .. } => {
+                    let contents = expected.remove(&download_id).unwrap();
+                    assert_eq!(std::fs::read(path).unwrap(), contents);
+                }
+                P2pEvent::FileDownloadFailed { error, .. } => panic!("Download failed: {}", error),
+                _ => {}
+            },
+            _ = tokio::time::sleep_until(deadline) => panic!("Downloads did not complete"),
+        }
+        let in_flight = (
+            bob.client.chunk_requests_in_flight(&alice_id),
+            bob.client.chunk_requests_in_flight(&carol_id),
+        );
+        assert!(in_flight.0 <= cap && in_flight.1 <= cap, "{:?}", in_flight);
+        most_in_flight = (
+            most_in_flight.0.max(in_flight.0),
+            most_in_flight.1.max(in_flight.1),
+        );
+    }
+
+    // Each peer was kept busy up to the cap, below the download window
+    assert_eq!(most_in_flight, (cap, cap));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_group_file_distribution_tracks_each_member() {
+    use futures::StreamExt;
+    use gigi_p2p::MemberDownloadStatus;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    let mut alice = create_peer("alice-distro");
+    let mut bob = create_peer("bob-distro");
+    let mut carol = create_peer("carol-distro");
+    connect(&mut alice, &mut bob).await;
+    connect(&mut alice, &mut carol).await;
+    for peer in [&mut alice, &mut bob, &mut carol] {
+        peer.client.join_group("distro").unwrap();
+    }
+    let (bob_id, carol_id) = (bob.client.local_peer_id(), carol.client.local_peer_id());
+
+    let contents: Vec<u8> = (0..CHUNK_SIZE * 3).map(|i| (i % 251) as u8).collect();
+    let file = alice.dir.path().join("agenda.bin");
+    std::fs::write(&file, &contents).unwrap();
+
+    let mut share_code = None;
+    let mut downloads = Vec::new();
+    let mut completions = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while completions.len() < 2 {
+        // Send once both receivers are on alice's roster
+        if share_code.is_none() && alice.client.group_members("distro").unwrap().len() == 2 {
+            let code = alice.client.send_group_file("distro", &file).await.unwrap();
+            let status = alice.client.group_file_distribution_status(&code).unwrap();
+            assert_eq!(
+                status.member_status(&bob_id),
+                Some(MemberDownloadStatus::NotStarted)
+            );
+            assert_eq!(status.completed_count(), 0);
+            share_code = Some(code);
+        }
+        tokio::select! {
+            _ = alice.client.handle_next_swarm_event() => {}
+            _ = bob.client.handle_next_swarm_event() => {}
+            _ = carol.client.handle_next_swarm_event() => {}
+            Some(event) = alice.events.next() => {
+                if let P2pEvent::GroupFileDownloadedByMember {
+                    group, peer_id, completed_count, member_count, ..
+                } = event {
+                    assert_eq!(group, "distro");
+                    assert_eq!(member_count, 2);
+                    completions.push((peer_id, completed_count));
+                }
+            }
+            Some(event) = bob.events.next() => {
+                if let P2pEvent::GroupFileShareMessage { share_code, .. } = event {
+                    downloads.push(bob.client.download_file_by_code(&share_code).unwrap());
+                }
+            }
+            Some(event) = carol.events.next() => {
+                if let P2pEvent::GroupFileShareMessage { share_code, .. } = event {
+                    downloads.push(carol.client.download_file_by_code(&share_code).unwrap());
+                }
+            }
+            _ = tokio::time::sleep_until(deadline) => panic!("Members did not download the file"),
+        }
+    }
+
+    assert_eq!(downloads.len(), 2);
+    assert_eq!(
+        completions
+            .iter()
+            .map(|(_, count)| *count)
+            .collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    let mut completed: Vec<_> = completions.iter().map(|(peer, _)| *peer).collect();
+    completed.sort();
+    let mut expected = vec![bob_id, carol_id];
+    expected.sort();
+    assert_eq!(completed, expected);
+
+    let status = alice
+        .client
+        .group_file_distribution_status(&share_code.unwrap())
+        .unwrap();
+    assert_eq!(status.filename, "agenda.bin");
+    assert_eq!(status.completed_count(), 2);
+    assert_eq!(
+        status.members,
+        expected
+            .iter()
+            .map(|peer| (*peer, MemberDownloadStatus::Completed))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_fails_over_to_backup_peer() {
+    use futures::StreamExt;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    let mut alice = create_peer("alice-failover");
+    let mut backup = create_peer("carol-failover");
+    let mut bob = create_peer("bob-failover");
+    connect(&mut alice, &mut bob).await;
+    connect(&mut backup, &mut bob).await;
+
+    // Alice and carol share the same content independently, each under
+    // its own share code
+    let contents: Vec<u8> = (0..CHUNK_SIZE * 24).map(|i| (i % 241) as u8).collect();
+    let mut share_codes = Vec::new();
+    for sharer in [&mut alice, &mut backup] {
+        let file = sharer.dir.path().join("failover.bin");
+        std::fs::write(&file, &contents).unwrap();
+        share_codes.push(sharer.client.share_file(&file).await.unwrap());
+        let sharer_id = sharer.client.local_peer_id();
+        let updated = drive_until(sharer, &mut bob, |event| {
+            matches!(event, P2pEvent::RemoteFilesUpdated { from, .. } if *from == sharer_id)
+        })
+        .await;
+        assert!(updated.is_some(), "Bob should learn the shared files");
+    }
+    assert_ne!(share_codes[0], share_codes[1]);
+    let share_code = share_codes[0].clone();
+    let backup_id = backup.client.local_peer_id();
+
+    let download_id = bob
+        .client
+        .download_file("alice-failover", &share_code)
+        .unwrap();
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        tokio::select! {
+            _ = alice.client.handle_next_swarm_event() => {}
+            _ = bob.client.handle_next_swarm_event() => {}
+            _ = backup.client.handle_next_swarm_event() => {}
+            Some(_) = alice.events.next() => {}
+            Some(_) = backup.events.next() => {}
+            Some(event) = bob.events.next() => {
+                if matches!(event, P2pEvent::FileDownloadStarted { .. }) {
+                    break;
+                }
+            }
+            _ = tokio::time::sleep_until(deadline) => panic!("Download did not start"),
+        }
+    }
+
+    // Alice goes away with chunk requests in flight
+    drop(alice);
+    let finished = drive_until(&mut bob, &mut backup, |event| {
+        matches!(
+            event,
+            P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. }
+        )
+    })
+    .await;
+
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted {
+            download_id: id,
+            from_peer_id,
+            path,
+            ..
+        }) => {
+            assert_eq!(id, download_id);
+            assert_eq!(from_peer_id, backup_id);
+            assert_eq!(std::fs::read(path).unwrap(), contents);
+        }
+        other => panic!(
+            "Expected the backup to complete the download, got {:?}",
+            other
+        ),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_strict_hash_rejects_unhashed_share() {
+    let mut alice = create_peer("alice-strict");
+    let mut bob = create_peer("bob-strict");
+    let mut carol = create_peer_with_config(
+        "carol-strict",
+        P2pConfig {
+            strict_hash: true,
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+    connect(&mut alice, &mut carol).await;
+
+    // Content-URI shares carry no whole-file hash
+    let contents: Arc<Vec<u8>> = Arc::new((0..CHUNK_SIZE + 100).map(|i| i as u8).collect());
+    let served = contents.clone();
+    alice
+        .client
+        .set_chunk_reader(Arc::new(move |_, offset, size| {
+            let start = (offset as usize).min(served.len());
+            let end = (start + size).min(served.len());
+            Ok(served[start..end].to_vec())
+        }));
+    let share_code = alice
+        .client
+        .share_content_uri(
+            "content://media/document/1",
+            "unhashed",
+            contents.len() as u64,
+        )
+        .await
+        .unwrap();
+
+    // Chunk hashes alone are accepted by default
+    bob.client
+        .download_file("alice-strict", &share_code)
+        .unwrap();
+    let completed = drive_until(&mut alice, &mut bob, |event| {
+        matches!(
+            event,
+            P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. }
+        )
+    })
+    .await;
+    match completed {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(std::fs::read(path).unwrap(), *contents);
+        }
+        other => panic!("Expected the download to complete, got {:?}", other),
+    }
+
+    let download_id = carol
+        .client
+        .download_file("alice-strict", &share_code)
+        .unwrap();
+    let failed = drive_until(&mut alice, &mut carol, |event| {
+        matches!(
+            event,
+            P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. }
+        )
+    })
+    .await;
+    match failed {
+        Some(P2pEvent::FileDownloadFailed {
+            download_id: id,
+            reason,
+            ..
+        }) => {
+            assert_eq!(id, download_id);
+            assert_eq!(reason, DownloadFailureReason::Unverified);
+        }
+        other => panic!("Expected the strict download to fail, got {:?}", other),
+    }
+    assert!(std::fs::read_dir(carol.dir.path())
+        .unwrap()
+        .all(|entry| entry.unwrap().file_name() != "unhashed"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sharing_announces_remote_files() {
+    let mut alice = create_peer("alice-announce");
+    let mut bob = create_peer("bob-announce");
+    connect(&mut alice, &mut bob).await;
+    let alice_id = alice.client.local_peer_id();
+    assert!(bob.client.remote_files(&alice_id).is_none());
+
+    let file = alice.dir.path().join("announced.txt");
+    std::fs::write(&file, b"announced").unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let updated = drive_until_from(
+        &mut alice,
+        &mut bob,
+        |event| matches!(event, P2pEvent::RemoteFilesUpdated { from, .. } if *from == alice_id),
+    )
+    .await;
+    match updated {
+        Some(P2pEvent::RemoteFilesUpdated { from, files }) => {
+            assert_eq!(from, alice_id);
+            assert_eq!(files.len(), 1);
+            assert_eq!(files[0].id, share_code);
+            assert_eq!(files[0].name, "announced.txt");
+        }
+        other => panic!("Expected a remote files update, got {:?}", other),
+    }
+    let cached = bob.client.remote_files(&alice_id).unwrap();
+    assert_eq!(cached.len(), 1);
+    assert_eq!(cached[0].id, share_code);
+
+    // Unsharing announces the shorter list
+    alice.client.unshare_file(&share_code).unwrap();
+    let updated = drive_until_from(
+        &mut alice,
+        &mut bob,
+        |event| matches!(event, P2pEvent::RemoteFilesUpdated { from, .. } if *from == alice_id),
+    )
+    .await;
+    assert!(
+        matches!(updated, Some(P2pEvent::RemoteFilesUpdated { files, .. }) if files.is_empty())
+    );
+    assert!(bob.client.remote_files(&alice_id).unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_download_request_hints() {
+    let mut alice = create_peer("alice-request");
+    let mut bob = create_peer("bob-request");
+    connect(&mut alice, &mut bob).await;
+    let alice_id = alice.client.local_peer_id();
+
+    let contents: Vec<u8> = (0..CHUNK_SIZE + 321).map(|i| (i % 251) as u8).collect();
+    let file = alice.dir.path().join("requested.bin");
+    std::fs::write(&file, &contents).unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    let hash = alice.client.list_shared_files()[0].info.hash.clone();
+
+    let finish = |download_id: String| {
+        move |event: &P2pEvent| match event {
+            P2pEvent::FileDownloadCompleted {
+                download_id: id, ..
+            }
+            | P2pEvent::FileDownloadFailed {
+                download_id: id, ..
+            } => *id == download_id,
+            _ => false,
+        }
+    };
+
+    // A file of another size fails before any chunk is requested
+    let download_id = bob
+        .client
+        .download_file_with(
+            DownloadRequest::new(&share_code)
+                .with_peer_id(alice_id)
+                .with_expected_size(contents.len() as u64 + 1),
+        )
+        .unwrap();
+    match drive_until(&mut alice, &mut bob, finish(download_id)).await {
+        Some(P2pEvent::FileDownloadFailed { reason, .. }) => {
+            assert_eq!(reason, DownloadFailureReason::UnexpectedFile);
+        }
+        other => panic!("Expected a size mismatch, got {:?}", other),
+    }
+
+    // So does a file with another hash
+    let download_id = bob
+        .client
+        .download_file_with(
+            DownloadRequest::new(&share_code)
+                .with_nickname("alice-request")
+                .with_expected_hash("00ff"),
+        )
+        .unwrap();
+    match drive_until(&mut alice, &mut bob, finish(download_id)).await {
+        Some(P2pEvent::FileDownloadFailed { reason, .. }) => {
+            assert_eq!(reason, DownloadFailureReason::UnexpectedFile);
+        }
+        other => panic!("Expected a hash mismatch, got {:?}", other),
+    }
+    assert!(bob
+        .client
+        .download_file_with(DownloadRequest::new(&share_code).with_expected_hash("not hex"))
+        .is_err());
+
+    // Matching hints download into the target directory
+    let inbox = bob.dir.path().join("inbox");
+    let download_id = bob
+        .client
+        .download_file_with(
+            DownloadRequest::new(&share_code)
+                .with_peer_id(alice_id)
+                .with_expected_hash(hash.to_uppercase())
+                .with_expected_size(contents.len() as u64)
+                .with_target_directory(&inbox)
+                .with_priority(DownloadPriority::High),
+        )
+        .unwrap();
+    match drive_until(&mut alice, &mut bob, finish(download_id)).await {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(path, inbox.join("requested.bin"));
+            assert_eq!(std::fs::read(&path).unwrap(), contents);
+        }
+        other => panic!("Expected the download to complete, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_trusted_transfer_skips_whole_file_hash() {
+    let skipping = P2pConfig {
+        skip_hash_for_trusted: true,
+        strict_hash: true,
+        ..P2pConfig::default()
+    };
+    let mut alice = create_peer_with_config("alice-skip-hash", skipping.clone());
+    let mut bob = create_peer_with_config("bob-skip-hash", skipping);
+    let mut carol = create_peer_with_config(
+        "carol-skip-hash",
+        P2pConfig {
+            strict_hash: true,
+            ..P2pConfig::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+    connect(&mut alice, &mut carol).await;
+    let alice_id = alice.client.local_peer_id();
+    alice.client.trust_peer(bob.client.local_peer_id());
+    bob.client.trust_peer(alice_id);
+
+    let contents: Vec<u8> = (0..CHUNK_SIZE * 2 + 17).map(|i| (i % 241) as u8).collect();
+    let file = alice.dir.path().join("own-device.bin");
+    std::fs::write(&file, &contents).unwrap();
+
+    // Sent to a trusted peer, the file is shared and downloaded unhashed
+    alice
+        .client
+        .send_direct_file("bob-skip-hash", &file)
+        .await
+        .unwrap();
+    let mut received_hash = None;
+    let finished = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::FileInfoReceived { info, .. } => {
+            received_hash = Some(info.hash.clone());
+            false
+        }
+        P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. } => true,
+        _ => false,
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(std::fs::read(&path).unwrap(), contents);
+        }
+        other => panic!("Expected the trusted download to complete, got {:?}", other),
+    }
+    assert_eq!(received_hash.as_deref(), Some(""));
+    let shared = alice.client.list_shared_files();
+    assert_eq!(shared.len(), 1);
+    assert!(shared[0].info.hash.is_empty());
+    let unhashed_code = shared[0].share_code.clone();
+
+    // An untrusted peer with strict hashing refuses the unhashed share
+    carol
+        .client
+        .download_file("alice-skip-hash", &unhashed_code)
+        .unwrap();
+    match drive_until(&mut alice, &mut carol, |event| {
+        matches!(
+            event,
+            P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. }
+        )
+    })
+    .await
+    {
+        Some(P2pEvent::FileDownloadFailed { reason, .. }) => {
+            assert_eq!(reason, DownloadFailureReason::Unverified);
+        }
+        other => panic!("Expected the unhashed download to fail, got {:?}", other),
+    }
+
+    // A normal share of the same file is hashed and verified
+    let hashed_code = alice.client.share_file(&file).await.unwrap();
+    assert_ne!(hashed_code, unhashed_code);
+    let hash = alice
+        .client
+        .list_shared_files()
+        .into_iter()
+        .find(|shared| shared.share_code == hashed_code)
+        .map(|shared| shared.info.hash.clone())
+        .unwrap();
+    assert_eq!(hash.len(), 64);
+    carol
+        .client
+        .download_file("alice-skip-hash", &hashed_code)
+        .unwrap();
+    let mut received_hash = None;
+    let finished = drive_until(&mut alice, &mut carol, |event| match event {
+        P2pEvent::FileInfoReceived { info, .. } => {
+            received_hash = Some(info.hash.clone());
+            false
+        }
+        P2pEvent::FileDownloadCompleted { .. } | P2pEvent::FileDownloadFailed { .. } => true,
+        _ => false,
+    })
+    .await;
+    match finished {
+        Some(P2pEvent::FileDownloadCompleted { path, .. }) => {
+            assert_eq!(std::fs::read(&path).unwrap(), contents);
+        }
+        other => panic!("Expected the hashed download to complete, got {:?}", other),
+    }
+    assert_eq!(received_hash, Some(hash));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_shared_files_announced_to_newly_connected_peer() {
+    let mut alice = create_peer_with_config(
+        "alice-announce-connect",
+        P2pConfig {
+            announce_shared_files_on_connect: true,
+            ..P2pConfig::default()
+        },
+    );
+    let alice_id = alice.client.local_peer_id();
+
+    // Shared before bob is around, so no change announcement reaches him
+    let file = alice.dir.path().join("early.txt");
+    std::fs::write(&file, b"shared early").unwrap();
+    let share_code = alice.client.share_file(&file).await.unwrap();
+
+    let mut bob = create_peer("bob-announce-connect");
+    let updated = drive_until_from(
+        &mut alice,
+        &mut bob,
+        |event| matches!(event, P2pEvent::RemoteFilesUpdated { from, .. } if *from == alice_id),
+    )
+    .await;
+    match updated {
+        Some(P2pEvent::RemoteFilesUpdated { files, .. }) => {
+            assert_eq!(files.len(), 1);
+            assert_eq!(files[0].id, share_code);
+            assert_eq!(files[0].name, "early.txt");
+        }
+        other => panic!("Expected the shared files on connect, got {:?}", other),
+    }
+    assert_eq!(
+        bob.client.remote_files(&alice_id).unwrap()[0].id,
+        share_code
+    );
+}
@@ -0,0 +1,623 @@
+//! End-to-end messaging tests for gigi-p2p
+//!
+//! Two clients discover each other via gigi-dns on the local machine and
+//! exchange direct and group messages.
+
+mod common;
+
+use common::{
+    connect, create_peer, create_peer_with_config, create_persistent_peer, drive_peer_until,
+    drive_until, drive_until_from,
+};
+use gigi_p2p::{
+    ClientCommand, ForwardTarget, GroupSendStatus, InboundRateLimit, MessageContent, P2pConfig,
+    P2pError, P2pEvent,
+};
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_direct_message_id_matches_received_message() {
+    let mut alice = create_peer("alice-msgid");
+    let mut bob = create_peer("bob-msgid");
+    connect(&mut alice, &mut bob).await;
+
+    let sent_id = bob
+        .client
+        .send_direct_message("alice-msgid", "Hello Alice".to_string())
+        .expect("Peer should be online");
+    assert!(!sent_id.is_empty());
+
+    let received = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::DirectMessage { .. })
+    })
+    .await;
+
+    match received {
+        Some(P2pEvent::DirectMessage {
+            from_nickname,
+            message,
+            message_id,
+            ..
+        }) => {
+            assert_eq!(from_nickname, "bob-msgid");
+            assert_eq!(message, "Hello Alice");
+            assert_eq!(message_id, sent_id);
+        }
+        other => panic!("Expected direct message, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_group_message_buffered_until_peer_subscribes() {
+    let mut alice = create_peer_with_config(
+        "alice-buffer",
+        P2pConfig {
+            group_message_buffer: 10,
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer("bob-buffer");
+    connect(&mut alice, &mut bob).await;
+
+    alice.client.join_group("buffered").unwrap();
+    let status = alice
+        .client
+        .send_group_message("buffered", "Anyone here?".to_string())
+        .expect("Message should be queued");
+    let sent_id = match status {
+        GroupSendStatus::Queued { message_id } => message_id,
+        other => panic!("Expected queued message, got {:?}", other),
+    };
+    assert_eq!(alice.client.pending_group_message_count("buffered"), 1);
+
+    bob.client.join_group("buffered").unwrap();
+    let received = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::GroupMessage { .. })
+    })
+    .await;
+
+    match received {
+        Some(P2pEvent::GroupMessage {
+            from_nickname,
+            group,
+            message,
+            message_id,
+            ..
+        }) => {
+            assert_eq!(from_nickname, "alice-buffer");
+            assert_eq!(group, "buffered");
+            assert_eq!(message, "Anyone here?");
+            assert_eq!(message_id, sent_id);
+        }
+        other => panic!("Expected buffered group message, got {:?}", other),
+    }
+    assert_eq!(alice.client.pending_group_message_count("buffered"), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subscribed_peer_appears_in_group_roster() {
+    let mut alice = create_peer("alice-roster");
+    let mut bob = create_peer("bob-roster");
+    connect(&mut alice, &mut bob).await;
+
+    alice.client.join_group("roster").unwrap();
+    assert!(alice.client.group_members("roster").unwrap().is_empty());
+
+    bob.client.join_group("roster").unwrap();
+    let bob_id = bob.client.local_peer_id();
+    let changed = drive_until_from(&mut bob, &mut alice, |event| {
+        matches!(event, P2pEvent::GroupMembershipChanged { online: true, .. })
+    })
+    .await;
+    match changed {
+        Some(P2pEvent::GroupMembershipChanged {
+            group,
+            peer_id,
+            online_count,
+            ..
+        }) => {
+            assert_eq!(group, "roster");
+            assert_eq!(peer_id, bob_id);
+            assert_eq!(online_count, 1);
+        }
+        other => panic!("Expected membership change, got {:?}", other),
+    }
+
+    let members = alice.client.group_members("roster").unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].peer_id, bob_id);
+    assert_eq!(members[0].nickname, "bob-roster");
+    assert!(alice.client.group_members("unknown").is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_group_message_without_buffer_fails_with_no_peers() {
+    let mut alice = create_peer("alice-nobuffer");
+
+    alice.client.join_group("lonely").unwrap();
+    assert!(alice
+        .client
+        .send_group_message("lonely", "Hello?".to_string())
+        .is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_large_group_message_is_chunked() {
+    let mut alice = create_peer_with_config(
+        "alice-chunked",
+        P2pConfig {
+            group_message_buffer: 10,
+            max_group_message_size: 4096,
+            ..Default::default()
+        },
+    );
+    let mut bob = create_peer("bob-chunked");
+    connect(&mut alice, &mut bob).await;
+
+    // Quotes and multi-byte characters grow when JSON-escaped
+    let text: String = (0..6000)
+        .map(|i| format!("{}\"é{}", i % 10, if i % 50 == 0 { '\n' } else { ' ' }))
+        .collect();
+    assert!(text.len() > 4096 * 4);
+
+    alice.client.join_group("chunked").unwrap();
+    let sent_id = alice
+        .client
+        .send_group_message("chunked", text.clone())
+        .expect("Large message should be accepted")
+        .message_id()
+        .to_string();
+
+    bob.client.join_group("chunked").unwrap();
+    let received = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::GroupMessage { .. })
+    })
+    .await;
+
+    match received {
+        Some(P2pEvent::GroupMessage {
+            message,
+            message_id,
+            ..
+        }) => {
+            assert_eq!(message_id, sent_id);
+            assert_eq!(message, text);
+        }
+        other => panic!("Expected reassembled group message, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_large_group_message_rejected_without_chunking() {
+    let mut alice = create_peer_with_config(
+        "alice-toolarge",
+        P2pConfig {
+            max_group_message_size: 1024,
+            chunk_large_group_messages: false,
+            ..Default::default()
+        },
+    );
+
+    alice.client.join_group("strict").unwrap();
+    let err = alice
+        .client
+        .send_group_message("strict", "x".repeat(2000))
+        .unwrap_err();
+    match err.downcast_ref::<P2pError>() {
+        Some(P2pError::MessageTooLarge { size, limit }) => {
+            assert!(*size > 2000);
+            assert_eq!(*limit, 1024);
+        }
+        other => panic!("Expected MessageTooLarge, got {:?}", other),
+    }
+
+    // Small messages still go through the normal publish path
+    let err = alice
+        .client
+        .send_group_message("strict", "short".to_string())
+        .unwrap_err();
+    assert!(!matches!(
+        err.downcast_ref::<P2pError>(),
+        Some(P2pError::MessageTooLarge { .. })
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_disappearing_message_carries_timer() {
+    let mut alice = create_peer("alice-disappear");
+    let mut bob = create_peer("bob-disappear");
+    connect(&mut alice, &mut bob).await;
+
+    let sent_id = bob
+        .client
+        .send_disappearing_message(
+            "alice-disappear",
+            "Burn after reading".to_string(),
+            std::time::Duration::from_secs(30),
+        )
+        .await
+        .expect("Peer should be online");
+
+    let received = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::DirectMessage { .. })
+    })
+    .await;
+
+    match received {
+        Some(P2pEvent::DirectMessage {
+            message_id,
+            disappear_after_secs,
+            ..
+        }) => {
+            assert_eq!(message_id, sent_id);
+            assert_eq!(disappear_after_secs, Some(30));
+        }
+        other => panic!("Expected direct message, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_group_poll_votes_are_aggregated() {
+    let mut alice = create_persistent_peer("alice-poll");
+    let mut bob = create_persistent_peer("bob-poll");
+    connect(&mut alice, &mut bob).await;
+    let bob_id = bob.client.local_peer_id();
+
+    alice.client.join_group("polls").unwrap();
+    bob.client.join_group("polls").unwrap();
+    // Each side can publish once it has seen the other subscribe
+    let mut joined = 0;
+    let subscribed = drive_until(&mut alice, &mut bob, |event| {
+        joined += matches!(event, P2pEvent::GroupJoined { group } if group == "polls") as u32;
+        joined == 2
+    })
+    .await;
+    assert!(subscribed.is_some(), "Peers should subscribe to the group");
+
+    let options = vec!["Yes".to_string(), "No".to_string()];
+    let poll_id = alice
+        .client
+        .send_group_poll("polls", "Ship it?", options.clone())
+        .unwrap()
+        .message_id()
+        .to_string();
+
+    let received = drive_until_from(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::PollUpdated { .. })
+    })
+    .await;
+    match received {
+        Some(P2pEvent::PollUpdated {
+            from_nickname,
+            group,
+            poll_id: received_id,
+            results,
+            ..
+        }) => {
+            assert_eq!(from_nickname, "alice-poll");
+            assert_eq!(group, "polls");
+            assert_eq!(received_id, poll_id);
+            assert_eq!(results.unwrap().votes, vec![0, 0]);
+        }
+        other => panic!("Expected poll update, got {:?}", other),
+    }
+    let results = bob
+        .client
+        .get_poll_results(&poll_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(results.question, "Ship it?");
+    assert_eq!(results.options, options);
+    assert_eq!(results.votes, vec![0, 0]);
+
+    // Each vote from Bob replaces the previous one on Alice's side
+    for (option_index, expected) in [(1, vec![0, 1]), (0, vec![1, 0])] {
+        bob.client
+            .vote_group_poll("polls", &poll_id, option_index)
+            .unwrap();
+        let updated = drive_until_from(&mut bob, &mut alice, |event| match event {
+            P2pEvent::PollUpdated {
+                from,
+                results: Some(results),
+                ..
+            } => *from == bob_id && results.votes == expected && results.total_votes == 1,
+            _ => false,
+        })
+        .await;
+        assert!(updated.is_some(), "Vote {} should be counted", option_index);
+    }
+    let results = alice
+        .client
+        .get_poll_results(&poll_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(results.votes, vec![1, 0]);
+    assert_eq!(results.total_votes, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_direct_location_is_validated_and_stored() {
+    let mut alice = create_persistent_peer("alice-location");
+    let mut bob = create_persistent_peer("bob-location");
+    connect(&mut alice, &mut bob).await;
+
+    let err = alice
+        .client
+        .send_direct_location("bob-location", 91.0, 0.0, None)
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<P2pError>(),
+        Some(P2pError::InvalidLocation { .. })
+    ));
+
+    let sent_id = alice
+        .client
+        .send_direct_location("bob-location", 52.52, 13.405, Some("Station"))
+        .unwrap();
+    let received = drive_until_from(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::DirectLocationMessage { .. })
+    })
+    .await;
+    match received {
+        Some(P2pEvent::DirectLocationMessage {
+            from_nickname,
+            message_id,
+            location,
+            ..
+        }) => {
+            assert_eq!(from_nickname, "alice-location");
+            assert_eq!(message_id, sent_id);
+            assert_eq!((location.lat, location.lon), (52.52, 13.405));
+            assert_eq!(location.label.as_deref(), Some("Station"));
+        }
+        other => panic!("Expected location message, got {:?}", other),
+    }
+
+    // The event is sent once the location is stored
+    let history = bob
+        .client
+        .get_conversation_history("alice-location")
+        .await
+        .unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].id, sent_id);
+    assert!(matches!(
+        history[0].content,
+        MessageContent::Location { lat, .. } if lat == 52.52
+    ));
+    // Stored under alice's peer ID, not only her nickname
+    let by_peer = bob
+        .client
+        .get_peer_conversation_history(&alice.client.local_peer_id())
+        .await
+        .unwrap();
+    assert_eq!(by_peer.len(), 1);
+    assert_eq!(by_peer[0].id, sent_id);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_forwarded_message_keeps_provenance() {
+    let mut alice = create_persistent_peer("alice-forward");
+    let mut bob = create_persistent_peer("bob-forward");
+    connect(&mut alice, &mut bob).await;
+
+    // A message Bob received from Carol, stored as the app does on receipt
+    let original = gigi_store::StoredMessage {
+        id: "from-carol".to_string(),
+        msg_type: gigi_store::MessageType::Direct,
+        direction: gigi_store::MessageDirection::Received,
+        content: MessageContent::Text {
+            text: "Meet at noon".to_string(),
+        },
+        sender_nickname: "carol-forward".to_string(),
+        recipient_nickname: Some("bob-forward".to_string()),
+        group_name: None,
+        peer_id: "carol-peer".to_string(),
+        timestamp: chrono::Utc::now(),
+        created_at: chrono::Utc::now(),
+        delivered: true,
+        delivered_at: None,
+        read: false,
+        read_at: None,
+        sync_status: gigi_store::SyncStatus::Delivered,
+        sync_attempts: 0,
+        last_sync_attempt: None,
+        expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+        disappear_after_secs: None,
+        forwarded_from: None,
+    };
+    bob.client.store_message(original).await.unwrap();
+
+    let forwarded_id = bob
+        .client
+        .forward_message(
+            "from-carol",
+            ForwardTarget::Peer("alice-forward".to_string()),
+        )
+        .await
+        .unwrap();
+    let received = drive_until_from(&mut bob, &mut alice, |event| {
+        matches!(event, P2pEvent::DirectMessage { .. })
+    })
+    .await;
+    match received {
+        Some(P2pEvent::DirectMessage {
+            from_nickname,
+            message,
+            message_id,
+            forwarded_from,
+            ..
+        }) => {
+            assert_eq!(from_nickname, "bob-forward");
+            assert_eq!(message, "Meet at noon");
+            assert_eq!(message_id, forwarded_id);
+            assert_eq!(forwarded_from.as_deref(), Some("carol-forward"));
+        }
+        other => panic!("Expected forwarded message, got {:?}", other),
+    }
+
+    let history = bob
+        .client
+        .get_conversation_history("alice-forward")
+        .await
+        .unwrap();
+    let copy = history
+        .iter()
+        .find(|msg| msg.id == forwarded_id)
+        .expect("Forwarded copy should be stored");
+    assert_eq!(copy.forwarded_from.as_deref(), Some("carol-forward"));
+
+    // Forwarding the copy again still names Carol
+    let again_id = bob
+        .client
+        .forward_message(
+            &forwarded_id,
+            ForwardTarget::Peer("alice-forward".to_string()),
+        )
+        .await
+        .unwrap();
+    let history = bob
+        .client
+        .get_conversation_history("alice-forward")
+        .await
+        .unwrap();
+    let again = history.iter().find(|msg| msg.id == again_id).unwrap();
+    assert_eq!(again.forwarded_from.as_deref(), Some("carol-forward"));
+
+    assert!(bob
+        .client
+        .forward_message("missing", ForwardTarget::Peer("alice-forward".to_string()))
+        .await
+        .is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_inbound_burst_above_rate_limit_is_dropped() {
+    let mut alice = create_peer("alice-ratelimit");
+    let mut bob = create_peer_with_config(
+        "bob-ratelimit",
+        P2pConfig {
+            inbound_message_limit: Some(InboundRateLimit {
+                per_second: 0.001,
+                burst: 3,
+            }),
+            ..Default::default()
+        },
+    );
+    connect(&mut alice, &mut bob).await;
+    let alice_id = alice.client.local_peer_id();
+
+    for i in 0..10 {
+        alice
+            .client
+            .send_direct_message("bob-ratelimit", format!("Spam {}", i))
+            .unwrap();
+    }
+
+    let mut received = 0;
+    let limited = drive_until_from(&mut alice, &mut bob, |event| match event {
+        P2pEvent::DirectMessage { .. } => {
+            received += 1;
+            false
+        }
+        P2pEvent::RateLimited { peer } => *peer == alice_id,
+        _ => false,
+    })
+    .await;
+    assert!(limited.is_some(), "Bob should report Alice as rate limited");
+    assert_eq!(received, 3);
+
+    // The rest of the burst is dropped, not delivered late
+    let late = tokio::time::timeout(
+        Duration::from_secs(2),
+        drive_until_from(&mut alice, &mut bob, |event| {
+            matches!(event, P2pEvent::DirectMessage { .. })
+        }),
+    )
+    .await;
+    assert!(late.is_err(), "Excess messages should be dropped");
+
+    let alice_info = bob.client.get_peer(&alice_id).expect("Alice stays known");
+    assert!(alice_info.connected, "Rate limiting should not disconnect");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_run_drives_client_and_handles_events() {
+    use futures::StreamExt;
+    use std::ops::ControlFlow;
+
+    let mut alice = create_peer("alice-run");
+    let mut bob = create_peer("bob-run");
+    connect(&mut alice, &mut bob).await;
+
+    // Alice is driven by run() alone, with no polling task or shared lock
+    let (command_tx, command_rx) = futures::channel::mpsc::unbounded::<ClientCommand>();
+    let mut alice_task = tokio::spawn(async move {
+        let mut received = Vec::new();
+        let result = alice
+            .client
+            .run(&mut alice.events, command_rx, |client, event| {
+                let P2pEvent::DirectMessage { message, .. } = event else {
+                    return ControlFlow::Continue(());
+                };
+                if message == "ping" {
+                    client
+                        .send_direct_message("bob-run", "pong".to_string())
+                        .unwrap();
+                }
+                let done = message == "bye";
+                received.push(message);
+                if done {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .await;
+        (result, received)
+    });
+
+    // Commands run on the driven client
+    let command: ClientCommand = Box::new(|client| {
+        Box::pin(async move {
+            client
+                .send_direct_message("bob-run", "hello".to_string())
+                .unwrap();
+        })
+    });
+    command_tx.unbounded_send(command).unwrap();
+    let hello = drive_peer_until(
+        &mut bob,
+        |event| matches!(event, P2pEvent::DirectMessage { message, .. } if message == "hello"),
+    )
+    .await;
+    assert!(hello.is_some(), "Command should send a message");
+
+    // The event handler acts on the client it is given
+    bob.client
+        .send_direct_message("alice-run", "ping".to_string())
+        .unwrap();
+    let pong = drive_peer_until(
+        &mut bob,
+        |event| matches!(event, P2pEvent::DirectMessage { message, .. } if message == "pong"),
+    )
+    .await;
+    assert!(pong.is_some(), "Event handler should answer");
+
+    bob.client
+        .send_direct_message("alice-run", "bye".to_string())
+        .unwrap();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(60);
+    let (result, received) = loop {
+        tokio::select! {
+            _ = bob.client.handle_next_swarm_event() => {}
+            Some(_) = bob.events.next() => {}
+            joined = &mut alice_task => break joined.unwrap(),
+            _ = tokio::time::sleep_until(deadline) => panic!("run() did not stop"),
+        }
+    };
+    result.unwrap();
+    assert_eq!(received, ["ping", "bye"]);
+}
@@ -0,0 +1,99 @@
+//! Download source scoring tests for gigi-p2p
+
+use gigi_p2p::{PeerId, PeerScoreboard};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Pick a source for each of `chunks` chunks, recording the simulated latency
+fn simulate(
+    scoreboard: &mut PeerScoreboard,
+    latencies: &HashMap<PeerId, Duration>,
+    chunks: usize,
+) -> HashMap<PeerId, usize> {
+    let candidates: Vec<PeerId> = latencies.keys().copied().collect();
+    let mut served = HashMap::new();
+    for _ in 0..chunks {
+        let peer = scoreboard.select_source(&candidates).unwrap();
+        scoreboard.record_success(peer, latencies[&peer]);
+        *served.entry(peer).or_insert(0) += 1;
+    }
+    served
+}
+
+#[test]
+fn test_fast_peer_serves_majority() {
+    let fast = PeerId::random();
+    let slow = PeerId::random();
+    let latencies = HashMap::from([
+        (fast, Duration::from_millis(10)),
+        (slow, Duration::from_millis(200)),
+    ]);
+
+    let mut scoreboard = PeerScoreboard::new();
+    let served = simulate(&mut scoreboard, &latencies, 100);
+
+    assert!(served[&fast] > 80, "Fast peer served {:?}", served);
+    // Demoted peers are still re-probed occasionally
+    assert!(served[&slow] >= 5, "Slow peer served {:?}", served);
+
+    let slow_score = scoreboard.score_of(&slow).unwrap();
+    assert!(slow_score.demoted);
+    assert!(!scoreboard.score_of(&fast).unwrap().demoted);
+    assert!(slow_score.avg_latency.unwrap() > Duration::from_millis(100));
+}
+
+#[test]
+fn test_failing_peer_is_demoted() {
+    let good = PeerId::random();
+    let flaky = PeerId::random();
+    let mut scoreboard = PeerScoreboard::new();
+    scoreboard.record_success(good, Duration::from_millis(20));
+    scoreboard.record_success(flaky, Duration::from_millis(20));
+    for _ in 0..10 {
+        scoreboard.record_failure(flaky);
+    }
+
+    let flaky_score = scoreboard.score_of(&flaky).unwrap();
+    assert!(flaky_score.demoted);
+    assert_eq!(flaky_score.failures, 10);
+    assert!(flaky_score.success_rate < 0.5);
+
+    let picks: Vec<PeerId> = (0..9)
+        .map(|_| scoreboard.select_source(&[good, flaky]).unwrap())
+        .collect();
+    assert!(picks.iter().all(|peer| *peer == good));
+}
+
+#[test]
+fn test_only_source_never_abandoned() {
+    let slow = PeerId::random();
+    let mut scoreboard = PeerScoreboard::new();
+    for _ in 0..5 {
+        scoreboard.record_failure(slow);
+    }
+
+    for _ in 0..20 {
+        assert_eq!(scoreboard.select_source(&[slow]), Some(slow));
+    }
+    assert_eq!(scoreboard.select_source(&[]), None);
+}
+
+#[test]
+fn test_request_latency_measured() {
+    let peer = PeerId::random();
+    let mut scoreboard = PeerScoreboard::new();
+
+    scoreboard.start_request("req-1".to_string(), peer);
+    std::thread::sleep(Duration::from_millis(20));
+    scoreboard.finish_request("req-1", true);
+    scoreboard.start_request("req-2".to_string(), peer);
+    scoreboard.finish_request("req-2", false);
+    // Unknown request ids are ignored
+    scoreboard.finish_request("req-3", true);
+
+    let score = scoreboard.score_of(&peer).unwrap();
+    assert_eq!(score.successes, 1);
+    assert_eq!(score.failures, 1);
+    assert!(score.avg_latency.unwrap() >= Duration::from_millis(20));
+    assert_eq!(scoreboard.scores().len(), 1);
+}
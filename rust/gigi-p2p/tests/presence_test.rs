@@ -0,0 +1,57 @@
+//! End-to-end presence status tests for gigi-p2p
+//!
+//! One client broadcasts a status over the presence topic and the other
+//! observes it as `PresenceChanged` events and in its peer table.
+
+mod common;
+
+use common::{connect, create_peer, drive_until};
+use gigi_p2p::P2pEvent;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_presence_status_reaches_peer() {
+    let mut alice = create_peer("alice-presence");
+    let mut bob = create_peer("bob-presence");
+    connect(&mut alice, &mut bob).await;
+    let alice_id = alice.client.local_peer_id();
+
+    alice.client.set_presence_status("Away").unwrap();
+    assert_eq!(alice.client.presence_status(), Some("Away"));
+    // Subscriptions to the presence topic are exchanged before it arrives
+    let mut groups = Vec::new();
+    let changed = drive_until(&mut alice, &mut bob, |event| match event {
+        P2pEvent::GroupJoined { group } => {
+            groups.push(group.clone());
+            false
+        }
+        P2pEvent::PresenceChanged { peer_id, .. } => *peer_id == alice_id,
+        _ => false,
+    })
+    .await;
+    match changed {
+        Some(P2pEvent::PresenceChanged {
+            nickname, status, ..
+        }) => {
+            assert_eq!(nickname, "alice-presence");
+            assert_eq!(status.as_deref(), Some("Away"));
+        }
+        other => panic!("Expected presence change, got {:?}", other),
+    }
+    let peer = bob.client.get_peer(&alice_id).unwrap();
+    assert_eq!(peer.status.as_deref(), Some("Away"));
+    assert!(
+        groups.is_empty(),
+        "Presence topic reported as group: {:?}",
+        groups
+    );
+
+    // Clearing the status is broadcast as well
+    alice.client.clear_presence_status();
+    let cleared = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::PresenceChanged { peer_id, status: None, .. }
+            if *peer_id == alice_id)
+    })
+    .await;
+    assert!(cleared.is_some(), "Cleared status should reach bob");
+    assert_eq!(bob.client.get_peer(&alice_id).unwrap().status, None);
+}
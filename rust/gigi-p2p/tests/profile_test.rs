@@ -0,0 +1,105 @@
+//! End-to-end contact profile exchange tests for gigi-p2p
+//!
+//! Adding a contact swaps profiles over the profile exchange protocol and
+//! stores the contact's profile in the contact book. Avatars named in the
+//! profile are fetched and cached by hash.
+
+mod common;
+
+use common::{connect, create_peer, create_persistent_peer, drive_until};
+use gigi_p2p::{P2pEvent, MAX_AVATAR_SIZE};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_add_contact_stores_profile() {
+    let mut alice = create_persistent_peer("alice-profile");
+    let mut bob = create_persistent_peer("bob-profile");
+    connect(&mut alice, &mut bob).await;
+    let bob_id = bob.client.local_peer_id();
+    bob.client
+        .set_profile_display_name(Some("Bob Builder"))
+        .unwrap();
+    let avatar_path = bob.dir.path().join("avatar.png");
+    std::fs::write(&avatar_path, b"not really a png").unwrap();
+    let avatar_hash = bob.client.set_avatar(&avatar_path).unwrap();
+
+    alice.client.add_contact(bob_id, "Bob").await.unwrap();
+    let received = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::ContactProfileReceived { .. })
+    })
+    .await;
+    match received {
+        Some(P2pEvent::ContactProfileReceived { peer_id, profile }) => {
+            assert_eq!(peer_id, bob_id);
+            assert_eq!(profile, bob.client.local_profile());
+        }
+        other => panic!("Expected contact profile, got {:?}", other),
+    }
+
+    let contact = alice.client.get_contact(&bob_id).await.unwrap().unwrap();
+    assert_eq!(contact.name, "Bob", "Local name should be kept");
+    assert_eq!(contact.display_name.as_deref(), Some("Bob Builder"));
+    assert_eq!(
+        contact.public_key,
+        Some(bob.client.local_profile().public_key)
+    );
+    assert_eq!(contact.avatar_hash, Some(avatar_hash));
+
+    // Bob has not added Alice, so her profile is not stored on his side
+    let alice_id = alice.client.local_peer_id();
+    assert!(bob.client.get_contact(&alice_id).await.unwrap().is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_add_contact_requires_persistence() {
+    let mut alice = create_peer("alice-no-contacts");
+    let bob_id = gigi_p2p::Keypair::generate_ed25519().public().to_peer_id();
+    assert!(alice.client.add_contact(bob_id, "Bob").await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_contact_avatar_is_fetched_and_cached() {
+    let mut alice = create_persistent_peer("alice-avatar");
+    let mut bob = create_persistent_peer("bob-avatar");
+    connect(&mut alice, &mut bob).await;
+    let bob_id = bob.client.local_peer_id();
+    let image: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+    let avatar_path = bob.dir.path().join("avatar.png");
+    std::fs::write(&avatar_path, &image).unwrap();
+    let avatar_hash = bob.client.set_avatar(&avatar_path).unwrap();
+
+    alice.client.add_contact(bob_id, "Bob").await.unwrap();
+    let fetched = drive_until(&mut alice, &mut bob, |event| {
+        matches!(event, P2pEvent::AvatarFetched { .. })
+    })
+    .await;
+    let cached_path = match fetched {
+        Some(P2pEvent::AvatarFetched {
+            peer_id,
+            avatar_hash: fetched_hash,
+            path,
+        }) => {
+            assert_eq!(peer_id, bob_id);
+            assert_eq!(fetched_hash, avatar_hash);
+            path
+        }
+        other => panic!("Expected fetched avatar, got {:?}", other),
+    };
+    assert_eq!(std::fs::read(&cached_path).unwrap(), image);
+    let stats = alice.client.avatar_cache_stats();
+    assert_eq!((stats.hits, stats.misses), (0, 1));
+
+    // The unchanged avatar is served from the cache without a request
+    let again = alice.client.fetch_avatar(bob_id, &avatar_hash).unwrap();
+    assert_eq!(again, Some(cached_path));
+    let stats = alice.client.avatar_cache_stats();
+    assert_eq!((stats.hits, stats.misses), (1, 1));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_set_avatar_rejects_large_images() {
+    let mut alice = create_peer("alice-large-avatar");
+    let avatar_path = alice.dir.path().join("avatar.png");
+    std::fs::write(&avatar_path, vec![0u8; MAX_AVATAR_SIZE + 1]).unwrap();
+    assert!(alice.client.set_avatar(&avatar_path).is_err());
+    assert_eq!(alice.client.local_profile().avatar_hash, None);
+}
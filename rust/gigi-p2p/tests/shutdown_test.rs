@@ -0,0 +1,86 @@
+//! Tests for draining outstanding work before shutting down
+
+mod common;
+
+use common::{connect, create_peer, create_persistent_peer, drive_peer_until};
+use gigi_p2p::{Keypair, P2pClient, P2pConfig, P2pError, P2pEvent, PersistenceConfig};
+use std::time::Duration;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_drain_and_shutdown_persists_new_share() {
+    let mut alice = create_persistent_peer("alice-drain");
+    let file = alice.dir.path().join("shared.txt");
+    std::fs::write(&file, b"still here after restart").unwrap();
+
+    // Shut down right after sharing, before the background save ran
+    let share_code = alice.client.share_file(&file).await.unwrap();
+    alice
+        .client
+        .drain_and_shutdown(Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert!(alice.client.is_shutting_down());
+
+    // No new work is accepted
+    let error = alice.client.share_file(&file).await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<P2pError>(),
+        Some(P2pError::ShuttingDown)
+    ));
+    let error = alice.client.handle_next_swarm_event().await.unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<P2pError>(),
+        Some(P2pError::ShuttingDown)
+    ));
+    // Draining again is a no-op
+    alice
+        .client
+        .drain_and_shutdown(Duration::from_secs(5))
+        .await
+        .unwrap();
+    drop(alice.client);
+
+    // A restarted client loads the share from the store
+    let (restarted, _events) = P2pClient::new_with_full_config(
+        Keypair::generate_ed25519(),
+        "alice-drain".to_string(),
+        alice.dir.path().to_path_buf(),
+        Some(PersistenceConfig {
+            db_path: alice.dir.path().join("gigi.db"),
+            ..Default::default()
+        }),
+        P2pConfig::default(),
+    )
+    .unwrap();
+    assert!(restarted
+        .list_shared_files()
+        .iter()
+        .any(|shared| shared.share_code == share_code));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_drain_and_shutdown_flushes_offline_queue() {
+    let mut alice = create_persistent_peer("alice-drain-queue");
+
+    // Queued while bob is unknown
+    let queued = alice
+        .client
+        .send_persistent_message("bob-drain-queue", "sent on the way out".to_string())
+        .await;
+    assert!(queued.is_err());
+
+    // Bob connects, but the periodic sync is not due before alice quits
+    let mut bob = create_peer("bob-drain-queue");
+    connect(&mut alice, &mut bob).await;
+    alice
+        .client
+        .drain_and_shutdown(Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    let received = drive_peer_until(&mut bob, |event| {
+        matches!(event, P2pEvent::DirectMessage { message, .. } if message == "sent on the way out")
+    })
+    .await;
+    assert!(received.is_some(), "Queued message should reach bob");
+}
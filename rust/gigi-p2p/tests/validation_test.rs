@@ -2,7 +2,7 @@
 //!
 //! Tests all input validation functions to prevent security issues.
 
-use gigi_p2p::validation;
+use gigi_p2p::{validation, P2pError, CHUNK_SIZE};
 use std::path::Path;
 
 #[test]
@@ -173,6 +173,21 @@ fn test_validate_group_name_invalid_too_long() {
     assert!(result.unwrap_err().to_string().contains("too long"));
 }
 
+#[test]
+fn test_validate_status() {
+    assert!(validation::validate_status("").is_ok());
+    assert!(validation::validate_status("Away 🌴 back at 3pm").is_ok());
+    assert!(validation::validate_status(&"A".repeat(128)).is_ok());
+
+    let result = validation::validate_status(&"A".repeat(129));
+    assert!(result.unwrap_err().to_string().contains("too long"));
+    let result = validation::validate_status("line\nbreak");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("control characters"));
+}
+
 #[test]
 fn test_validate_share_code_valid() {
     let max_code = "A".repeat(256);
@@ -430,3 +445,194 @@ fn test_validate_all_inputs_with_edge_cases() {
     assert!(validation::validate_share_code("A").is_ok());
     assert!(validation::validate_uri("A").is_ok());
 }
+
+#[test]
+fn test_validate_chunk_length_valid() {
+    let file_size = (CHUNK_SIZE * 2 + 100) as u64;
+
+    assert!(validation::validate_chunk_length(file_size, 0, CHUNK_SIZE).is_ok());
+    assert!(validation::validate_chunk_length(file_size, 1, CHUNK_SIZE).is_ok());
+    // Last chunk only holds the remainder
+    assert!(validation::validate_chunk_length(file_size, 2, 100).is_ok());
+    // Exact multiple of CHUNK_SIZE has a full-size last chunk
+    assert!(validation::validate_chunk_length(CHUNK_SIZE as u64, 0, CHUNK_SIZE).is_ok());
+}
+
+#[test]
+fn test_validate_chunk_length_rejects_padded_last_chunk() {
+    let file_size = (CHUNK_SIZE + 100) as u64;
+
+    match validation::validate_chunk_length(file_size, 1, CHUNK_SIZE) {
+        Err(P2pError::ChunkLengthMismatch {
+            chunk_index,
+            expected,
+            actual,
+        }) => {
+            assert_eq!(chunk_index, 1);
+            assert_eq!(expected, 100);
+            assert_eq!(actual, CHUNK_SIZE);
+        }
+        other => panic!("Expected ChunkLengthMismatch, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_validate_chunk_length_rejects_short_and_out_of_range_chunks() {
+    let file_size = (CHUNK_SIZE + 100) as u64;
+
+    // Non-final chunk must be full size
+    assert!(validation::validate_chunk_length(file_size, 0, CHUNK_SIZE - 1).is_err());
+    // Last chunk must not be truncated
+    assert!(validation::validate_chunk_length(file_size, 1, 99).is_err());
+    // Chunks past the end of the file carry no data
+    assert!(validation::validate_chunk_length(file_size, 2, 1).is_err());
+}
+
+#[test]
+fn test_validate_file_chunk_length_content_defined() {
+    let info = gigi_p2p::FileInfo {
+        id: "cdc".to_string(),
+        name: "cdc.bin".to_string(),
+        size: 250_000,
+        hash: String::new(),
+        hash_algo: Default::default(),
+        chunk_count: 2,
+        created_at: 0,
+        modified_at: None,
+        relative_path: None,
+        chunk_offsets: Some(vec![0, 100_000]),
+    };
+
+    assert!(validation::validate_file_chunk_length(&info, 0, 100_000).is_ok());
+    assert!(validation::validate_file_chunk_length(&info, 1, 150_000).is_ok());
+    // Chunks follow the offsets, not CHUNK_SIZE
+    assert!(validation::validate_file_chunk_length(&info, 0, 150_000).is_err());
+    assert!(validation::validate_file_chunk_length(&info, 2, 0).is_err());
+}
+
+#[test]
+fn test_sanitize_folder_name() {
+    assert_eq!(validation::sanitize_folder_name("Alice"), "Alice");
+    assert_eq!(validation::sanitize_folder_name("Alice Bob"), "Alice Bob");
+    // Path separators can't escape the downloads directory
+    assert_eq!(validation::sanitize_folder_name("../etc"), "_etc");
+    assert_eq!(validation::sanitize_folder_name("a/b\\c"), "a_b_c");
+    assert_eq!(validation::sanitize_folder_name("C:"), "C_");
+    // Names with nothing usable fall back to a placeholder
+    assert_eq!(validation::sanitize_folder_name(""), "unknown");
+    assert_eq!(validation::sanitize_folder_name("  "), "unknown");
+    assert_eq!(validation::sanitize_folder_name(".."), "unknown");
+}
+
+#[test]
+fn test_sanitize_filename() {
+    assert_eq!(validation::sanitize_filename("photo.jpg"), "photo.jpg");
+    assert_eq!(validation::sanitize_filename(".hidden"), ".hidden");
+    // Traversal and absolute paths keep only the base name
+    assert_eq!(validation::sanitize_filename("../../etc/passwd"), "passwd");
+    assert_eq!(validation::sanitize_filename("/etc/passwd"), "passwd");
+    assert_eq!(
+        validation::sanitize_filename("..\\..\\evil.exe"),
+        "evil.exe"
+    );
+    assert_eq!(
+        validation::sanitize_filename("C:\\Windows\\win.ini"),
+        "win.ini"
+    );
+    assert_eq!(validation::sanitize_filename("C:evil.txt"), "C_evil.txt");
+    // Null bytes and control characters are dropped
+    assert_eq!(validation::sanitize_filename("a\0b\n.txt"), "ab.txt");
+    // Names with nothing usable fall back to a placeholder
+    assert_eq!(validation::sanitize_filename(".."), "download");
+    assert_eq!(validation::sanitize_filename("dir/"), "download");
+    assert_eq!(validation::sanitize_filename(""), "download");
+    // Overlong names are cut on a character boundary
+    let long = validation::sanitize_filename(&"é".repeat(200));
+    assert!(long.len() <= 255);
+    assert!(long.chars().all(|c| c == 'é'));
+}
+
+#[test]
+fn test_sanitize_relative_directory() {
+    assert_eq!(
+        validation::sanitize_relative_directory("album/2024/beach.jpg"),
+        Path::new("album").join("2024")
+    );
+    // A bare filename has no folders
+    assert_eq!(
+        validation::sanitize_relative_directory("beach.jpg"),
+        Path::new("")
+    );
+    // Traversal and absolute paths stay below the download directory
+    assert_eq!(
+        validation::sanitize_relative_directory("../../etc/passwd"),
+        Path::new("etc")
+    );
+    assert_eq!(
+        validation::sanitize_relative_directory("/album/./x.jpg"),
+        Path::new("album")
+    );
+    assert_eq!(
+        validation::sanitize_relative_directory("C:\\Users\\..\\x.jpg"),
+        Path::new("C_").join("Users")
+    );
+}
+
+#[test]
+fn test_validate_avatar_hash() {
+    assert!(validation::validate_avatar_hash("a1b2c3").is_ok());
+    assert!(validation::validate_avatar_hash(&"f".repeat(128)).is_ok());
+
+    assert!(validation::validate_avatar_hash("").is_err());
+    assert!(validation::validate_avatar_hash(&"f".repeat(129)).is_err());
+    let result = validation::validate_avatar_hash("sha256:abc");
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("invalid characters"));
+}
+
+#[test]
+fn test_validate_poll() {
+    let options = |n: usize| (0..n).map(|i| format!("Option {}", i)).collect::<Vec<_>>();
+    assert!(validation::validate_poll("Lunch?", &options(2)).is_ok());
+    assert!(validation::validate_poll("Lunch?", &options(16)).is_ok());
+
+    assert!(validation::validate_poll("  ", &options(2)).is_err());
+    assert!(validation::validate_poll(&"?".repeat(513), &options(2)).is_err());
+    let result = validation::validate_poll("Lunch?", &options(1));
+    assert!(result.unwrap_err().to_string().contains("2 to 16 options"));
+    assert!(validation::validate_poll("Lunch?", &options(17)).is_err());
+    let blank = vec!["Pizza".to_string(), "".to_string()];
+    assert!(validation::validate_poll("Lunch?", &blank).is_err());
+}
+
+#[test]
+fn test_validate_location() {
+    assert!(validation::validate_location(0.0, 0.0, None).is_ok());
+    assert!(validation::validate_location(90.0, 180.0, Some("North Pole")).is_ok());
+    assert!(validation::validate_location(-90.0, -180.0, None).is_ok());
+
+    for (lat, lon) in [
+        (90.5, 0.0),
+        (-91.0, 0.0),
+        (0.0, 180.1),
+        (0.0, -200.0),
+        (f64::NAN, 0.0),
+        (0.0, f64::INFINITY),
+    ] {
+        let result = validation::validate_location(lat, lon, None);
+        assert!(
+            matches!(result, Err(P2pError::InvalidLocation { .. })),
+            "({}, {}) should be out of range",
+            lat,
+            lon
+        );
+    }
+
+    assert!(matches!(
+        validation::validate_location(1.0, 1.0, Some(" ")),
+        Err(P2pError::InvalidInput(_))
+    ));
+    assert!(validation::validate_location(1.0, 1.0, Some(&"x".repeat(257))).is_err());
+}
@@ -8,6 +8,7 @@ use sea_orm::{
 use serde::{Deserialize, Serialize};
 
 use crate::entities::contacts;
+use crate::error::StoreError;
 
 /// Contact information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,6 +16,18 @@ pub struct ContactInfo {
     pub peer_id: String,
     pub name: String,
     pub added_at: i64,
+    /// Display name from the contact's own profile, if exchanged
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Contact's public key from its profile, if exchanged
+    #[serde(default)]
+    pub public_key: Option<String>,
+    /// Hash of the contact's avatar image, if it has one
+    #[serde(default)]
+    pub avatar_hash: Option<String>,
+    /// When the contact was last renamed, `0` if unknown
+    #[serde(default)]
+    pub updated_at: i64,
 }
 
 impl From<contacts::Model> for ContactInfo {
@@ -23,10 +36,25 @@ impl From<contacts::Model> for ContactInfo {
             peer_id: model.peer_id,
             name: model.name,
             added_at: model.added_at,
+            display_name: model.display_name,
+            public_key: model.public_key,
+            avatar_hash: model.avatar_hash,
+            updated_at: model.updated_at,
         }
     }
 }
 
+/// Outcome of importing contacts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContactImportSummary {
+    /// Contacts that were not known yet
+    pub added: usize,
+    /// Known contacts whose details changed
+    pub updated: usize,
+    /// Exact duplicates and entries older than the local contact
+    pub skipped: usize,
+}
+
 /// Contact manager
 pub struct ContactManager {
     db: DatabaseConnection,
@@ -46,6 +74,8 @@ impl ContactManager {
             peer_id: Set(peer_id.to_string()),
             name: Set(name.to_string()),
             added_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
         };
 
         // Try to insert, ignoring RecordNotFound errors from the return value
@@ -65,6 +95,10 @@ impl ContactManager {
         contacts::Entity::update_many()
             .filter(contacts::Column::PeerId.eq(peer_id))
             .col_expr(contacts::Column::Name, Expr::value(name))
+            .col_expr(
+                contacts::Column::UpdatedAt,
+                Expr::value(chrono::Utc::now().timestamp_millis()),
+            )
             .exec(&self.db)
             .await?;
 
@@ -72,6 +106,38 @@ impl ContactManager {
         Ok(())
     }
 
+    /// Store the profile a contact sent about itself
+    ///
+    /// # Arguments
+    /// * `peer_id` - The contact's peer ID
+    /// * `display_name` - Name the contact chose for itself
+    /// * `public_key` - The contact's encoded public key
+    /// * `avatar_hash` - Hash of the contact's avatar, `None` if it has none
+    ///
+    /// # Returns
+    /// `false` if the peer is not a contact, in which case nothing is stored
+    pub async fn update_profile(
+        &self,
+        peer_id: &str,
+        display_name: &str,
+        public_key: &str,
+        avatar_hash: Option<&str>,
+    ) -> Result<bool, DbErr> {
+        let result = contacts::Entity::update_many()
+            .filter(contacts::Column::PeerId.eq(peer_id))
+            .col_expr(contacts::Column::DisplayName, Expr::value(display_name))
+            .col_expr(contacts::Column::PublicKey, Expr::value(public_key))
+            .col_expr(
+                contacts::Column::AvatarHash,
+                Expr::value(avatar_hash.map(str::to_string)),
+            )
+            .exec(&self.db)
+            .await?;
+
+        debug!("Updated contact profile: {} -> {}", peer_id, display_name);
+        Ok(result.rows_affected > 0)
+    }
+
     /// Remove a contact
     pub async fn remove(&self, peer_id: &str) -> Result<(), DbErr> {
         contacts::Entity::delete_many()
@@ -108,6 +174,254 @@ impl ContactManager {
 
         Ok(count > 0)
     }
+
+    /// Export all contacts as a JSON array of `ContactInfo`
+    pub async fn export_contacts(&self) -> Result<String, DbErr> {
+        let contacts = self.get_all().await?;
+        serde_json::to_string(&contacts).map_err(|e| DbErr::Custom(e.to_string()))
+    }
+
+    /// Export all contacts as vCard 4.0 entries
+    ///
+    /// Only the fields Gigi uses are written: `FN`, `NICKNAME` and `KEY`, plus
+    /// `X-GIGI-*` properties for the peer ID, avatar hash, and added and
+    /// updated times.
+    pub async fn export_contacts_vcard(&self) -> Result<String, DbErr> {
+        let contacts = self.get_all().await?;
+        Ok(contacts.iter().map(contact_to_vcard).collect())
+    }
+
+    /// Import contacts exported by `export_contacts` or `export_contacts_vcard`
+    ///
+    /// Contacts are matched by peer ID, then by public key. On a match the
+    /// newer entry wins: the imported details replace the local ones unless
+    /// the local contact was updated later. An entry without an update time,
+    /// such as a vCard from another app, counts as older than any local
+    /// contact. Fields missing from the import keep their local value, and an
+    /// import that changes nothing is skipped as a duplicate.
+    pub async fn import_contacts(&self, data: &str) -> Result<ContactImportSummary, StoreError> {
+        let data = data.trim_start();
+        let imported = if data.starts_with("BEGIN:VCARD") {
+            parse_vcards(data)?
+        } else {
+            serde_json::from_str::<Vec<ContactInfo>>(data)
+                .map_err(|e| StoreError::InvalidContactData(e.to_string()))?
+        };
+
+        let mut summary = ContactImportSummary::default();
+        for contact in imported {
+            let Some(existing) = self.find_match(&contact).await? else {
+                let model = contacts::ActiveModel {
+                    peer_id: Set(contact.peer_id),
+                    name: Set(contact.name),
+                    added_at: Set(contact.added_at),
+                    display_name: Set(contact.display_name),
+                    public_key: Set(contact.public_key),
+                    avatar_hash: Set(contact.avatar_hash),
+                    updated_at: Set(contact.updated_at),
+                };
+                // Same RecordNotFound quirk as in `add`
+                match model.insert(&self.db).await {
+                    Ok(_) | Err(DbErr::RecordNotFound(_)) => {}
+                    Err(e) => return Err(e.into()),
+                }
+                summary.added += 1;
+                continue;
+            };
+
+            let merged = contacts::Model {
+                peer_id: existing.peer_id.clone(),
+                name: contact.name,
+                added_at: existing.added_at,
+                display_name: contact.display_name.or(existing.display_name.clone()),
+                public_key: contact.public_key.or(existing.public_key.clone()),
+                avatar_hash: contact.avatar_hash.or(existing.avatar_hash.clone()),
+                updated_at: existing.updated_at,
+            };
+            if merged == existing || existing.updated_at > contact.updated_at {
+                summary.skipped += 1;
+                continue;
+            }
+
+            let mut active: contacts::ActiveModel = existing.into();
+            active.name = Set(merged.name);
+            active.display_name = Set(merged.display_name);
+            active.public_key = Set(merged.public_key);
+            active.avatar_hash = Set(merged.avatar_hash);
+            active.updated_at = Set(contact.updated_at);
+            active.update(&self.db).await?;
+            summary.updated += 1;
+        }
+
+        info!(
+            "Imported contacts: {} added, {} updated, {} skipped",
+            summary.added, summary.updated, summary.skipped
+        );
+        Ok(summary)
+    }
+
+    /// Find the local contact an imported one refers to
+    async fn find_match(&self, contact: &ContactInfo) -> Result<Option<contacts::Model>, DbErr> {
+        if let Some(model) = contacts::Entity::find_by_id(contact.peer_id.clone())
+            .one(&self.db)
+            .await?
+        {
+            return Ok(Some(model));
+        }
+        match &contact.public_key {
+            Some(public_key) => {
+                contacts::Entity::find()
+                    .filter(contacts::Column::PublicKey.eq(public_key.as_str()))
+                    .one(&self.db)
+                    .await
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Write a contact as a vCard entry
+fn contact_to_vcard(contact: &ContactInfo) -> String {
+    let mut card = String::from("BEGIN:VCARD\r\nVERSION:4.0\r\n");
+    card.push_str(&format!("FN:{}\r\n", escape_vcard(&contact.name)));
+    if let Some(display_name) = &contact.display_name {
+        card.push_str(&format!("NICKNAME:{}\r\n", escape_vcard(display_name)));
+    }
+    if let Some(public_key) = &contact.public_key {
+        card.push_str(&format!("KEY:{}\r\n", escape_vcard(public_key)));
+    }
+    card.push_str(&format!(
+        "X-GIGI-PEER-ID:{}\r\n",
+        escape_vcard(&contact.peer_id)
+    ));
+    if let Some(avatar_hash) = &contact.avatar_hash {
+        card.push_str(&format!(
+            "X-GIGI-AVATAR-HASH:{}\r\n",
+            escape_vcard(avatar_hash)
+        ));
+    }
+    card.push_str(&format!("X-GIGI-ADDED-AT:{}\r\n", contact.added_at));
+    card.push_str(&format!("X-GIGI-UPDATED-AT:{}\r\n", contact.updated_at));
+    card.push_str("END:VCARD\r\n");
+    card
+}
+
+/// Parse vCard entries, ignoring properties Gigi does not use
+fn parse_vcards(data: &str) -> Result<Vec<ContactInfo>, StoreError> {
+    // Unfold continuation lines, which start with a space or a tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in data.lines().map(|line| line.trim_end_matches('\r')) {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut contacts = Vec::new();
+    let mut card: Option<Vec<(String, String)>> = None;
+    for line in lines.iter().filter(|line| !line.is_empty()) {
+        let (property, value) = line
+            .split_once(':')
+            .ok_or_else(|| StoreError::InvalidContactData(format!("Malformed line: {}", line)))?;
+        // Drop parameters such as `FN;CHARSET=UTF-8`
+        let name = property
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .to_uppercase();
+        match (name.as_str(), card.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VCARD") => card = Some(Vec::new()),
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VCARD") => {
+                let properties = card.take().unwrap_or_default();
+                contacts.push(vcard_to_contact(properties)?);
+            }
+            ("BEGIN" | "END", _) => {
+                return Err(StoreError::InvalidContactData(format!(
+                    "Unbalanced vCard line: {}",
+                    line
+                )))
+            }
+            (_, Some(properties)) => properties.push((name, unescape_vcard(value))),
+            (_, None) => {
+                return Err(StoreError::InvalidContactData(format!(
+                    "Line outside of a vCard: {}",
+                    line
+                )))
+            }
+        }
+    }
+    if card.is_some() {
+        return Err(StoreError::InvalidContactData(
+            "vCard is missing END:VCARD".to_string(),
+        ));
+    }
+    Ok(contacts)
+}
+
+/// Build a contact from the properties of one vCard
+fn vcard_to_contact(properties: Vec<(String, String)>) -> Result<ContactInfo, StoreError> {
+    let mut peer_id = None;
+    let mut name = None;
+    let mut contact = ContactInfo {
+        peer_id: String::new(),
+        name: String::new(),
+        added_at: chrono::Utc::now().timestamp_millis(),
+        display_name: None,
+        public_key: None,
+        avatar_hash: None,
+        updated_at: 0,
+    };
+    for (property, value) in properties {
+        match property.as_str() {
+            "X-GIGI-PEER-ID" => peer_id = Some(value),
+            "FN" => name = Some(value),
+            "NICKNAME" => contact.display_name = Some(value),
+            "KEY" => contact.public_key = Some(value),
+            "X-GIGI-AVATAR-HASH" => contact.avatar_hash = Some(value),
+            "X-GIGI-ADDED-AT" => {
+                contact.added_at = value.parse().map_err(|_| {
+                    StoreError::InvalidContactData(format!("Invalid X-GIGI-ADDED-AT: {}", value))
+                })?
+            }
+            "X-GIGI-UPDATED-AT" => {
+                contact.updated_at = value.parse().map_err(|_| {
+                    StoreError::InvalidContactData(format!("Invalid X-GIGI-UPDATED-AT: {}", value))
+                })?
+            }
+            _ => {}
+        }
+    }
+    contact.peer_id = peer_id
+        .ok_or_else(|| StoreError::InvalidContactData("vCard has no X-GIGI-PEER-ID".to_string()))?;
+    contact.name = name.unwrap_or_else(|| contact.peer_id.clone());
+    Ok(contact)
+}
+
+/// Escape a vCard text value
+fn escape_vcard(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace('\n', "\\n")
+}
+
+/// Undo `escape_vcard`
+fn unescape_vcard(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
 }
 
 #[cfg(test)]
@@ -120,6 +434,10 @@ mod tests {
             peer_id: "12D3KooW...".to_string(),
             name: "Alice".to_string(),
             added_at: 1700000000000,
+            display_name: Some("Alice".to_string()),
+            public_key: None,
+            avatar_hash: None,
+            updated_at: 1700000000000,
         };
 
         let json = serde_json::to_string(&contact).unwrap();
@@ -128,5 +446,6 @@ mod tests {
         assert_eq!(deserialized.peer_id, contact.peer_id);
         assert_eq!(deserialized.name, contact.name);
         assert_eq!(deserialized.added_at, contact.added_at);
+        assert_eq!(deserialized.display_name, contact.display_name);
     }
 }
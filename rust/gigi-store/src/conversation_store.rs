@@ -4,8 +4,8 @@ use crate::entities::conversations;
 use anyhow::{Context, Result};
 use chrono::{DateTime, TimeZone, Utc};
 use gigi_logging::info;
+use sea_orm::prelude::Expr;
 use sea_orm::*;
-use sea_orm_migration::MigratorTrait;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
@@ -29,7 +29,7 @@ impl ConversationStore {
             .context("Failed to connect to database")?;
 
         // Run migrations
-        crate::migration::Migrator::up(&db, None)
+        crate::migration::migrate(&db)
             .await
             .context("Failed to run migrations")?;
 
@@ -215,6 +215,34 @@ impl ConversationStore {
         Ok(())
     }
 
+    /// Reset the unread count of every conversation in one update
+    pub async fn mark_all_as_read(&self) -> Result<()> {
+        let result = conversations::Entity::update_many()
+            .col_expr(conversations::Column::UnreadCount, Expr::value(0))
+            .col_expr(
+                conversations::Column::UpdatedAt,
+                Expr::value(Utc::now().timestamp_millis()),
+            )
+            .filter(conversations::Column::UnreadCount.gt(0))
+            .exec(&self.db)
+            .await?;
+
+        info!("Marked {} conversations as read", result.rows_affected);
+        Ok(())
+    }
+
+    /// Total unread messages across all conversations, for the app badge
+    pub async fn total_unread(&self) -> Result<u32> {
+        let total: Option<Option<i64>> = conversations::Entity::find()
+            .select_only()
+            .column_as(conversations::Column::UnreadCount.sum(), "total")
+            .into_tuple()
+            .one(&self.db)
+            .await?;
+
+        Ok(total.flatten().unwrap_or(0).max(0) as u32)
+    }
+
     /// Delete a conversation
     pub async fn delete_conversation(&self, id: &str) -> Result<()> {
         conversations::Entity::delete_many()
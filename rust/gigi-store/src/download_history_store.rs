@@ -0,0 +1,159 @@
+//! Download history store - Record how downloads ended
+
+use anyhow::{Context, Result};
+use gigi_logging::info;
+use sea_orm::{
+    ActiveModelTrait, DatabaseConnection, EntityTrait, NotSet, QueryOrder, QuerySelect, Set,
+};
+use serde::{Deserialize, Serialize};
+
+/// How a download ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DownloadHistoryStatus {
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl DownloadHistoryStatus {
+    /// Name stored in the `status` column
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    /// Parse a stored status name; unknown names are read as `Failed`
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "completed" => Self::Completed,
+            "cancelled" => Self::Cancelled,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// One finished download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadHistoryEntry {
+    /// Row id, `None` until recorded
+    pub id: Option<i32>,
+    pub share_code: String,
+    pub file_name: String,
+    pub peer_id: String,
+    pub peer_nickname: String,
+    /// File size in bytes, if known when the download ended
+    pub file_size: Option<u64>,
+    pub status: DownloadHistoryStatus,
+    /// Why the download failed or was cancelled
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: i64,
+    /// Where the completed file was written
+    pub final_path: Option<String>,
+}
+
+impl From<crate::entities::download_history::Model> for DownloadHistoryEntry {
+    fn from(data: crate::entities::download_history::Model) -> Self {
+        Self {
+            id: Some(data.id),
+            share_code: data.share_code,
+            file_name: data.file_name,
+            peer_id: data.peer_id,
+            peer_nickname: data.peer_nickname,
+            file_size: data.file_size.map(|size| size as u64),
+            status: DownloadHistoryStatus::from_name(&data.status),
+            error: data.error,
+            started_at: data.started_at,
+            finished_at: data.finished_at,
+            final_path: data.final_path,
+        }
+    }
+}
+
+/// Download history store - keeps a record of completed, failed and cancelled downloads
+pub struct DownloadHistoryStore {
+    db: DatabaseConnection,
+}
+
+impl DownloadHistoryStore {
+    /// Create a new download history store
+    pub async fn new(db: DatabaseConnection) -> Result<Self> {
+        Ok(Self { db })
+    }
+
+    /// Record a finished download
+    ///
+    /// # Returns
+    /// The id of the new history entry
+    pub async fn record_download(&self, entry: &DownloadHistoryEntry) -> Result<i32> {
+        use crate::entities::download_history;
+
+        let model = download_history::ActiveModel {
+            id: NotSet,
+            share_code: Set(entry.share_code.clone()),
+            file_name: Set(entry.file_name.clone()),
+            peer_id: Set(entry.peer_id.clone()),
+            peer_nickname: Set(entry.peer_nickname.clone()),
+            file_size: Set(entry.file_size.map(|size| size as i64)),
+            status: Set(entry.status.as_str().to_string()),
+            error: Set(entry.error.clone()),
+            started_at: Set(entry.started_at),
+            finished_at: Set(entry.finished_at),
+            final_path: Set(entry.final_path.clone()),
+        };
+        let inserted = model
+            .insert(&self.db)
+            .await
+            .context("Failed to record download history")?;
+
+        info!(
+            "Recorded {} download: {} from {}",
+            entry.status.as_str(),
+            entry.file_name,
+            entry.peer_nickname
+        );
+        Ok(inserted.id)
+    }
+
+    /// List history entries, most recently finished first
+    pub async fn list_download_history(
+        &self,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<DownloadHistoryEntry>> {
+        use crate::entities::download_history;
+
+        let results = download_history::Entity::find()
+            .order_by_desc(download_history::Column::FinishedAt)
+            .order_by_desc(download_history::Column::Id)
+            .limit(limit)
+            .offset(offset)
+            .all(&self.db)
+            .await
+            .context("Failed to list download history")?;
+
+        Ok(results
+            .into_iter()
+            .map(DownloadHistoryEntry::from)
+            .collect())
+    }
+
+    /// Delete all history entries
+    ///
+    /// # Returns
+    /// The number of entries deleted
+    pub async fn clear_download_history(&self) -> Result<u64> {
+        use crate::entities::download_history;
+
+        let result = download_history::Entity::delete_many()
+            .exec(&self.db)
+            .await
+            .context("Failed to clear download history")?;
+
+        info!("Cleared {} download history entries", result.rows_affected);
+        Ok(result.rows_affected)
+    }
+}
@@ -9,6 +9,10 @@ pub struct Model {
     pub peer_id: String,
     pub name: String,
     pub added_at: i64,
+    pub display_name: Option<String>,
+    pub public_key: Option<String>,
+    pub avatar_hash: Option<String>,
+    pub updated_at: i64,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
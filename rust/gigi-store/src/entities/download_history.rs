@@ -0,0 +1,26 @@
+//! Download history entity recording how each download ended
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "download_history")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub share_code: String,
+    pub file_name: String,
+    pub peer_id: String,
+    pub peer_nickname: String,
+    pub file_size: Option<i64>,
+    pub status: String,
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub final_path: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,24 @@
+//! Downloaded files entity for tracking received files and their integrity
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "downloaded_files")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub file_path: String,
+    pub share_code: String,
+    pub file_name: String,
+    pub file_size: i64,
+    pub hash: String,
+    pub hash_algo: String,
+    pub downloaded_at: i64,
+    pub verified_at: Option<i64>,
+    pub verified_ok: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -26,6 +26,9 @@ pub struct Model {
     pub sync_attempts: u32,
     pub last_sync_attempt: Option<i64>,
     pub expires_at: i64,
+    pub disappear_after_secs: Option<i64>, // Disappearing message timer, started on send/read
+    pub forwarded_from: Option<String>,    // Nickname of the original author of a forwarded message
+    pub receive_seq: i64, // Local order messages were stored in, immune to clock skew
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
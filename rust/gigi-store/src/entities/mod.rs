@@ -2,18 +2,24 @@
 
 pub mod contacts;
 pub mod conversations;
+pub mod download_history;
+pub mod downloaded_files;
 pub mod message_acknowledgments;
 pub mod messages;
 pub mod offline_queue;
 pub mod settings;
 pub mod shared_files;
 pub mod thumbnails;
+pub mod upload_progress;
 
 pub use contacts::Entity as Contacts;
 pub use conversations::Entity as Conversation;
+pub use download_history::Entity as DownloadHistory;
+pub use downloaded_files::Entity as DownloadedFiles;
 pub use message_acknowledgments::Entity as MessageAcknowledgment;
 pub use messages::Entity as Message;
 pub use offline_queue::Entity as OfflineQueue;
 pub use settings::Entity as Settings;
 pub use shared_files::Entity as SharedFiles;
 pub use thumbnails::Entity as Thumbnails;
+pub use upload_progress::Entity as UploadProgress;
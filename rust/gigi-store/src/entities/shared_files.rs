@@ -12,10 +12,14 @@ pub struct Model {
     pub file_path: String,
     pub file_size: i64,
     pub hash: String,
+    pub hash_algo: String,
     pub chunk_count: i32,
     pub thumbnail_path: Option<String>,
     pub created_at: i64,
     pub revoked: bool,
+    pub modified_at: Option<i64>,
+    pub relative_path: Option<String>,
+    pub chunk_offsets: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,21 @@
+//! Upload progress entity recording chunks served per peer and share code
+
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "upload_progress")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub peer_id: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub share_code: String,
+    pub served_chunks: i64,
+    pub total_chunks: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,25 @@
+//! Error types for gigi-store
+
+use sea_orm::DbErr;
+
+/// Errors opening, upgrading or importing into the store database
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    /// The database was written by a newer version of the app
+    #[error(
+        "Database schema version {found} is newer than the supported version {supported}; \
+         update the app to open this database"
+    )]
+    IncompatibleSchema { found: u32, supported: u32 },
+
+    /// The stored schema version could not be parsed
+    #[error("Invalid stored schema version: {0}")]
+    InvalidSchemaVersion(String),
+
+    /// Imported data is neither a contact JSON array nor vCards
+    #[error("Invalid contact data: {0}")]
+    InvalidContactData(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] DbErr),
+}
@@ -26,6 +26,15 @@ pub enum SyncStatus {
     Acknowledged,
 }
 
+/// Sync tracking fields of a stored message
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageSyncInfo {
+    pub sync_status: SyncStatus,
+    /// Delivery attempts made by the periodic sync
+    pub sync_attempts: u32,
+    pub last_sync_attempt: Option<DateTime<Utc>>,
+}
+
 /// Text message content
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextContent {
@@ -33,12 +42,28 @@ pub struct TextContent {
 }
 
 /// File share message content
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FileShareContent {
     pub share_code: String,
     pub filename: String,
     pub file_size: u64,
+    /// MIME type of the shared file
     pub file_type: String,
+    /// Peer id of the peer serving the file
+    #[serde(default)]
+    pub from_peer: Option<String>,
+}
+
+impl From<FileShareContent> for MessageContent {
+    fn from(content: FileShareContent) -> Self {
+        MessageContent::FileShare {
+            share_code: content.share_code,
+            filename: content.filename,
+            file_size: content.file_size,
+            file_type: content.file_type,
+            from_peer: content.from_peer,
+        }
+    }
 }
 
 /// Group share message content
@@ -61,6 +86,9 @@ pub enum MessageContent {
         filename: String,
         file_size: u64,
         file_type: String,
+        /// Peer id of the peer serving the file
+        #[serde(default)]
+        from_peer: Option<String>,
     },
     /// File share with optional thumbnail path (used after download)
     FileShareWithThumbnail {
@@ -69,12 +97,73 @@ pub enum MessageContent {
         file_size: u64,
         file_type: String,
         thumbnail_path: Option<String>,
+        #[serde(default)]
+        from_peer: Option<String>,
     },
     ShareGroup {
         group_id: String,
         group_name: String,
         inviter_nickname: String,
     },
+    /// Group poll; stored with the poll id as message id
+    Poll {
+        poll_id: String,
+        question: String,
+        options: Vec<String>,
+    },
+    /// Vote for one option of a poll; a peer's latest vote replaces earlier ones
+    PollVote {
+        poll_id: String,
+        option_index: u32,
+    },
+    /// Pinned location with an optional place name
+    Location {
+        lat: f64,
+        lon: f64,
+        label: Option<String>,
+    },
+}
+
+impl MessageContent {
+    /// File attachment of a file share message, with or without thumbnail
+    pub fn file_share(&self) -> Option<FileShareContent> {
+        match self {
+            MessageContent::FileShare {
+                share_code,
+                filename,
+                file_size,
+                file_type,
+                from_peer,
+            }
+            | MessageContent::FileShareWithThumbnail {
+                share_code,
+                filename,
+                file_size,
+                file_type,
+                from_peer,
+                ..
+            } => Some(FileShareContent {
+                share_code: share_code.clone(),
+                filename: filename.clone(),
+                file_size: *file_size,
+                file_type: file_type.clone(),
+                from_peer: from_peer.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Vote counts of a poll, computed from the stored votes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PollResults {
+    pub poll_id: String,
+    pub question: String,
+    pub options: Vec<String>,
+    /// Votes per option, in the order of `options`
+    pub votes: Vec<u64>,
+    /// Number of peers with a valid vote
+    pub total_votes: u64,
 }
 
 /// Stored message (persistent)
@@ -102,6 +191,45 @@ pub struct StoredMessage {
     pub last_sync_attempt: Option<DateTime<Utc>>,
 
     pub expires_at: DateTime<Utc>,
+    /// Disappearing message timer in seconds, set by the sender
+    ///
+    /// Sent messages expire this long after being sent, received ones this
+    /// long after being read, whichever is earlier than `expires_at`.
+    #[serde(default)]
+    pub disappear_after_secs: Option<u64>,
+    /// Nickname of the original author, set on forwarded messages
+    #[serde(default)]
+    pub forwarded_from: Option<String>,
+}
+
+/// Order of messages returned by history queries, newest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageOrder {
+    /// By message timestamp, which received messages take from the
+    /// sender's clock
+    #[default]
+    Timestamp,
+    /// By the order messages were stored locally, unaffected by clock skew
+    /// between peers
+    ReceiveOrder,
+}
+
+/// Conversation to export, keyed the way messages are stored
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversationKey {
+    /// Direct messages exchanged with a peer, by nickname
+    Direct(String),
+    /// Messages of a group, by group name
+    Group(String),
+}
+
+/// Output format of a conversation export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One `[timestamp] sender: content` line per message
+    Text,
+    /// JSON array of message objects
+    Json,
 }
 
 /// Queue status
@@ -1,8 +1,13 @@
 //! File sharing store - Store and retrieve shared file information
 
+use crate::integrity::HashAlgo;
+use crate::settings_manager::SettingsManager;
 use anyhow::{Context, Result};
 use gigi_logging::info;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, EntityTrait, QueryFilter,
+    Set, TransactionTrait,
+};
 use serde::{Deserialize, Serialize};
 
 /// Shared file information stored in gigi-store
@@ -13,10 +18,19 @@ pub struct SharedFileInfo {
     pub file_path: String,
     pub file_size: u64,
     pub hash: String,
+    /// Algorithm of `hash`
+    pub hash_algo: HashAlgo,
     pub chunk_count: usize,
     pub thumbnail_path: Option<String>,
     pub created_at: i64,
     pub revoked: bool,
+    /// File modification time (ns since the Unix epoch) the hash was computed at
+    pub modified_at: Option<i64>,
+    /// Path within a shared directory, `/`-separated; `None` for single files
+    pub relative_path: Option<String>,
+    /// Chunk start offsets for content-defined chunking; `None` for fixed
+    /// size chunks
+    pub chunk_offsets: Option<Vec<u64>>,
 }
 
 impl SharedFileInfo {
@@ -36,15 +50,73 @@ impl SharedFileInfo {
             file_path,
             file_size,
             hash,
+            hash_algo: HashAlgo::default(),
             chunk_count,
             thumbnail_path: None,
             created_at,
             revoked: false,
+            modified_at: None,
+            relative_path: None,
+            chunk_offsets: None,
         }
     }
 }
 
+/// Aggregate counts over the shared files table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShareStats {
+    /// Files currently shared (not revoked)
+    pub file_count: u64,
+    /// Total size of the files currently shared, in bytes
+    pub total_bytes: u64,
+    /// Revoked entries not yet cleaned up
+    pub revoked_count: u64,
+}
+
+/// Record of a file received from a peer, used for integrity re-verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadedFileInfo {
+    pub file_path: String,
+    pub share_code: String,
+    pub file_name: String,
+    pub file_size: u64,
+    /// Hex digest verified when the download completed
+    pub hash: String,
+    /// Algorithm of `hash`
+    pub hash_algo: HashAlgo,
+    pub downloaded_at: i64,
+    /// When the file was last re-verified, if ever
+    pub verified_at: Option<i64>,
+    /// Result of the last verification
+    pub verified_ok: bool,
+}
+
+impl From<crate::entities::downloaded_files::Model> for DownloadedFileInfo {
+    fn from(data: crate::entities::downloaded_files::Model) -> Self {
+        Self {
+            file_path: data.file_path,
+            share_code: data.share_code,
+            file_name: data.file_name,
+            file_size: data.file_size as u64,
+            hash: data.hash,
+            hash_algo: HashAlgo::from_name(&data.hash_algo),
+            downloaded_at: data.downloaded_at,
+            verified_at: data.verified_at,
+            verified_ok: data.verified_ok,
+        }
+    }
+}
+
+/// Settings key holding when the `shared_files` table was last changed
+pub const SHARED_FILES_MODIFIED_KEY: &str = "shared_files_last_modified";
+
 /// File sharing store - handles storage and retrieval of shared file information
+///
+/// Every change to the shared files bumps a `last_modified` stamp in the
+/// database, so other instances using the same database (another window, or
+/// the plugin and a separate process) can poll
+/// [`shared_files_changed_since`](Self::shared_files_changed_since) and
+/// refresh their lists.
 pub struct FileSharingStore {
     db: DatabaseConnection,
 }
@@ -74,9 +146,13 @@ impl FileSharingStore {
             active_model.file_path = Set(info.file_path.clone());
             active_model.file_size = Set(info.file_size as i64);
             active_model.hash = Set(info.hash.clone());
+            active_model.hash_algo = Set(info.hash_algo.as_str().to_string());
             active_model.chunk_count = Set(info.chunk_count as i32);
             active_model.thumbnail_path = Set(info.thumbnail_path.clone());
             active_model.revoked = Set(info.revoked);
+            active_model.modified_at = Set(info.modified_at);
+            active_model.relative_path = Set(info.relative_path.clone());
+            active_model.chunk_offsets = Set(encode_chunk_offsets(&info.chunk_offsets));
             active_model
                 .update(&self.db)
                 .await
@@ -89,10 +165,14 @@ impl FileSharingStore {
                 file_path: Set(info.file_path.clone()),
                 file_size: Set(info.file_size as i64),
                 hash: Set(info.hash.clone()),
+                hash_algo: Set(info.hash_algo.as_str().to_string()),
                 chunk_count: Set(info.chunk_count as i32),
                 thumbnail_path: Set(info.thumbnail_path.clone()),
                 created_at: Set(info.created_at),
                 revoked: Set(info.revoked),
+                modified_at: Set(info.modified_at),
+                relative_path: Set(info.relative_path.clone()),
+                chunk_offsets: Set(encode_chunk_offsets(&info.chunk_offsets)),
             };
             // Ignore RecordNotFound error - insert likely succeeded
             match new_file.insert(&self.db).await {
@@ -101,6 +181,7 @@ impl FileSharingStore {
             }
         }
 
+        self.mark_shared_files_modified().await?;
         info!(
             "Stored shared file: {} ({})",
             info.file_name, info.share_code
@@ -124,10 +205,14 @@ impl FileSharingStore {
             file_path: data.file_path,
             file_size: data.file_size as u64,
             hash: data.hash,
+            hash_algo: HashAlgo::from_name(&data.hash_algo),
             chunk_count: data.chunk_count as usize,
             thumbnail_path: data.thumbnail_path,
             created_at: data.created_at,
             revoked: data.revoked,
+            modified_at: data.modified_at,
+            relative_path: data.relative_path,
+            chunk_offsets: decode_chunk_offsets(data.chunk_offsets.as_deref()),
         }))
     }
 
@@ -148,10 +233,90 @@ impl FileSharingStore {
                 file_path: data.file_path,
                 file_size: data.file_size as u64,
                 hash: data.hash,
+                hash_algo: HashAlgo::from_name(&data.hash_algo),
+                chunk_count: data.chunk_count as usize,
+                thumbnail_path: data.thumbnail_path,
+                created_at: data.created_at,
+                revoked: data.revoked,
+                modified_at: data.modified_at,
+                relative_path: data.relative_path,
+                chunk_offsets: decode_chunk_offsets(data.chunk_offsets.as_deref()),
+            })
+            .collect())
+    }
+
+    /// Count shared files and sum their sizes with a single aggregate query
+    ///
+    /// Rows are not loaded, so this stays cheap for large libraries.
+    pub async fn shared_stats(&self) -> Result<ShareStats> {
+        use crate::entities::shared_files;
+        use sea_orm::sea_query::Expr;
+        use sea_orm::QuerySelect;
+
+        let (file_count, total_bytes, revoked_count) = shared_files::Entity::find()
+            .select_only()
+            .column_as(
+                Expr::cust("COALESCE(SUM(CASE WHEN revoked THEN 0 ELSE 1 END), 0)"),
+                "file_count",
+            )
+            .column_as(
+                Expr::cust("COALESCE(SUM(CASE WHEN revoked THEN 0 ELSE file_size END), 0)"),
+                "total_bytes",
+            )
+            .column_as(
+                Expr::cust("COALESCE(SUM(CASE WHEN revoked THEN 1 ELSE 0 END), 0)"),
+                "revoked_count",
+            )
+            .into_tuple::<(i64, i64, i64)>()
+            .one(&self.db)
+            .await
+            .context("Failed to aggregate shared files")?
+            .unwrap_or_default();
+
+        Ok(ShareStats {
+            file_count: file_count as u64,
+            total_bytes: total_bytes as u64,
+            revoked_count: revoked_count as u64,
+        })
+    }
+
+    /// Search shared files whose name contains `query` (case-insensitive)
+    ///
+    /// `%` and `_` in `query` match themselves, not any characters.
+    pub async fn search_shared_files(&self, query: &str) -> Result<Vec<SharedFileInfo>> {
+        use crate::entities::shared_files;
+        use sea_orm::sea_query::{Expr, Func, LikeExpr};
+
+        let escaped = query
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let pattern = LikeExpr::new(format!("%{}%", escaped)).escape('\\');
+        let results = shared_files::Entity::find()
+            .filter(
+                Expr::expr(Func::lower(Expr::col(shared_files::Column::FileName))).like(pattern),
+            )
+            .all(&self.db)
+            .await
+            .context("Failed to search shared files")?;
+
+        Ok(results
+            .into_iter()
+            .map(|data| SharedFileInfo {
+                share_code: data.share_code,
+                file_name: data.file_name,
+                file_path: data.file_path,
+                file_size: data.file_size as u64,
+                hash: data.hash,
+                hash_algo: HashAlgo::from_name(&data.hash_algo),
                 chunk_count: data.chunk_count as usize,
                 thumbnail_path: data.thumbnail_path,
                 created_at: data.created_at,
                 revoked: data.revoked,
+                modified_at: data.modified_at,
+                relative_path: data.relative_path,
+                chunk_offsets: decode_chunk_offsets(data.chunk_offsets.as_deref()),
             })
             .collect())
     }
@@ -165,11 +330,46 @@ impl FileSharingStore {
             .exec(&self.db)
             .await
             .context("Failed to delete shared file")?;
+        if result.rows_affected > 0 {
+            self.mark_shared_files_modified().await?;
+        }
 
         info!("Deleted shared file with code: {}", share_code);
         Ok(result.rows_affected > 0)
     }
 
+    /// Delete several shared files by share code in a single transaction
+    ///
+    /// Either all matching entries are removed and the modification time is
+    /// bumped, or nothing changes.
+    pub async fn delete_shared_files(&self, share_codes: &[String]) -> Result<u64> {
+        use crate::entities::shared_files;
+
+        if share_codes.is_empty() {
+            return Ok(0);
+        }
+
+        let txn = self
+            .db
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+        let result = shared_files::Entity::delete_many()
+            .filter(shared_files::Column::ShareCode.is_in(share_codes.iter().cloned()))
+            .exec(&txn)
+            .await
+            .context("Failed to delete shared files")?;
+        if result.rows_affected > 0 {
+            Self::mark_shared_files_modified_on(&txn).await?;
+        }
+        txn.commit()
+            .await
+            .context("Failed to commit shared file deletion")?;
+
+        info!("Deleted {} shared files", result.rows_affected);
+        Ok(result.rows_affected)
+    }
+
     /// Mark a file as revoked
     pub async fn revoke_shared_file(&self, share_code: &str) -> Result<bool> {
         use crate::entities::shared_files;
@@ -187,6 +387,7 @@ impl FileSharingStore {
                 .update(&self.db)
                 .await
                 .context("Failed to revoke shared file")?;
+            self.mark_shared_files_modified().await?;
             info!("Revoked shared file with code: {}", share_code);
             Ok(true)
         } else {
@@ -203,6 +404,9 @@ impl FileSharingStore {
             .exec(&self.db)
             .await
             .context("Failed to cleanup revoked files")?;
+        if result.rows_affected > 0 {
+            self.mark_shared_files_modified().await?;
+        }
 
         info!("Cleaned up {} revoked shared files", result.rows_affected);
         Ok(result.rows_affected)
@@ -229,6 +433,7 @@ impl FileSharingStore {
                 .update(&self.db)
                 .await
                 .context("Failed to update thumbnail path")?;
+            self.mark_shared_files_modified().await?;
             info!("Updated thumbnail path for: {}", share_code);
         }
 
@@ -247,4 +452,164 @@ impl FileSharingStore {
 
         Ok(result.and_then(|r| r.thumbnail_path))
     }
+
+    /// When the shared files were last changed by any instance using this database
+    ///
+    /// # Returns
+    /// Milliseconds since the epoch, or `None` if they were never changed
+    pub async fn shared_files_last_modified(&self) -> Result<Option<i64>> {
+        let value = SettingsManager::new(self.db.clone())
+            .get(SHARED_FILES_MODIFIED_KEY)
+            .await
+            .context("Failed to read shared files modification time")?;
+        Ok(value.and_then(|value| value.parse().ok()))
+    }
+
+    /// Whether the shared files changed after `since`
+    ///
+    /// Poll with the last value of
+    /// [`shared_files_last_modified`](Self::shared_files_last_modified) to
+    /// learn when to reload the list.
+    pub async fn shared_files_changed_since(&self, since: i64) -> Result<bool> {
+        Ok(self
+            .shared_files_last_modified()
+            .await?
+            .is_some_and(|modified| modified > since))
+    }
+
+    /// Bump the shared files modification stamp
+    ///
+    /// The stamp is the current time, but always moves forward so that two
+    /// changes within the same millisecond are both seen by pollers.
+    async fn mark_shared_files_modified(&self) -> Result<()> {
+        Self::mark_shared_files_modified_on(&self.db).await
+    }
+
+    /// Bump the modification time through `db`, e.g. inside a transaction
+    async fn mark_shared_files_modified_on(db: &impl ConnectionTrait) -> Result<()> {
+        use crate::entities::settings;
+        use sea_orm::sea_query::OnConflict;
+
+        let previous = settings::Entity::find_by_id(SHARED_FILES_MODIFIED_KEY)
+            .one(db)
+            .await
+            .context("Failed to read shared files modification time")?
+            .and_then(|setting| setting.value.parse::<i64>().ok())
+            .unwrap_or(0);
+        let now = chrono::Utc::now().timestamp_millis();
+        let modified = now.max(previous + 1);
+        let model = settings::ActiveModel {
+            key: Set(SHARED_FILES_MODIFIED_KEY.to_string()),
+            value: Set(modified.to_string()),
+            updated_at: Set(now),
+        };
+        settings::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(settings::Column::Key)
+                    .update_columns([settings::Column::Value, settings::Column::UpdatedAt])
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await
+            .context("Failed to record shared files modification time")?;
+        Ok(())
+    }
+
+    /// Record a completed download, replacing any record for the same path
+    pub async fn record_downloaded_file(&self, info: &DownloadedFileInfo) -> Result<()> {
+        use crate::entities::downloaded_files;
+        use sea_orm::sea_query::OnConflict;
+
+        let model = downloaded_files::ActiveModel {
+            file_path: Set(info.file_path.clone()),
+            share_code: Set(info.share_code.clone()),
+            file_name: Set(info.file_name.clone()),
+            file_size: Set(info.file_size as i64),
+            hash: Set(info.hash.clone()),
+            hash_algo: Set(info.hash_algo.as_str().to_string()),
+            downloaded_at: Set(info.downloaded_at),
+            verified_at: Set(info.verified_at),
+            verified_ok: Set(info.verified_ok),
+        };
+        downloaded_files::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(downloaded_files::Column::FilePath)
+                    .update_columns([
+                        downloaded_files::Column::ShareCode,
+                        downloaded_files::Column::FileName,
+                        downloaded_files::Column::FileSize,
+                        downloaded_files::Column::Hash,
+                        downloaded_files::Column::HashAlgo,
+                        downloaded_files::Column::DownloadedAt,
+                        downloaded_files::Column::VerifiedAt,
+                        downloaded_files::Column::VerifiedOk,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await
+            .context("Failed to record downloaded file")?;
+
+        info!(
+            "Recorded downloaded file: {} ({})",
+            info.file_name, info.file_path
+        );
+        Ok(())
+    }
+
+    /// List all recorded downloads, oldest first
+    pub async fn list_downloaded_files(&self) -> Result<Vec<DownloadedFileInfo>> {
+        use crate::entities::downloaded_files;
+        use sea_orm::QueryOrder;
+
+        let results = downloaded_files::Entity::find()
+            .order_by_asc(downloaded_files::Column::DownloadedAt)
+            .all(&self.db)
+            .await
+            .context("Failed to list downloaded files")?;
+
+        Ok(results.into_iter().map(DownloadedFileInfo::from).collect())
+    }
+
+    /// Store the result of re-verifying a downloaded file
+    ///
+    /// # Returns
+    /// `true` if a record for `file_path` exists
+    pub async fn record_verification(
+        &self,
+        file_path: &str,
+        verified_ok: bool,
+        verified_at: i64,
+    ) -> Result<bool> {
+        use crate::entities::downloaded_files;
+        use sea_orm::sea_query::Expr;
+
+        let result = downloaded_files::Entity::update_many()
+            .col_expr(
+                downloaded_files::Column::VerifiedOk,
+                Expr::value(verified_ok),
+            )
+            .col_expr(
+                downloaded_files::Column::VerifiedAt,
+                Expr::value(Some(verified_at)),
+            )
+            .filter(downloaded_files::Column::FilePath.eq(file_path))
+            .exec(&self.db)
+            .await
+            .context("Failed to record verification")?;
+
+        Ok(result.rows_affected > 0)
+    }
+}
+
+/// Encode chunk offsets for the `chunk_offsets` column, a JSON array
+fn encode_chunk_offsets(offsets: &Option<Vec<u64>>) -> Option<String> {
+    offsets
+        .as_ref()
+        .map(|offsets| serde_json::to_string(offsets).expect("Offsets serialize"))
+}
+
+/// Decode the `chunk_offsets` column; an unreadable value counts as absent
+fn decode_chunk_offsets(column: Option<&str>) -> Option<Vec<u64>> {
+    column.and_then(|json| serde_json::from_str(json).ok())
 }
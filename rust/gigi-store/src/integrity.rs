@@ -0,0 +1,142 @@
+//! Re-verification of downloaded files against their recorded hashes
+//!
+//! Downloads are verified once when they complete. Files can later be damaged
+//! on disk, so `reverify_downloads` re-hashes every download recorded in the
+//! `downloaded_files` table and reports the ones that no longer match.
+//! Nothing runs automatically; callers decide when to re-verify.
+
+use crate::file_sharing_store::FileSharingStore;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+
+/// Algorithm of a whole-file hash
+///
+/// SHA256 is the default so peers and records that predate the choice keep
+/// verifying. BLAKE3 is markedly faster on large files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgo {
+    /// Name stored in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+        }
+    }
+
+    /// Parse a stored name, falling back to SHA256 for unknown values
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "blake3" => Self::Blake3,
+            _ => Self::Sha256,
+        }
+    }
+
+    /// Compute the hex digest of a file with this algorithm
+    pub fn hash_file(self, path: &Path) -> Result<String> {
+        match self {
+            Self::Sha256 => hash_reader(path, Sha256::new(), |hasher, data| {
+                hasher.update(data);
+            })
+            .map(|hasher| hex::encode(hasher.finalize())),
+            Self::Blake3 => hash_reader(path, blake3::Hasher::new(), |hasher, data| {
+                hasher.update(data);
+            })
+            .map(|hasher| hasher.finalize().to_hex().to_string()),
+        }
+    }
+}
+
+/// Feed a file through `update` in 64KB blocks
+fn hash_reader<H>(path: &Path, mut hasher: H, update: impl Fn(&mut H, &[u8])) -> Result<H> {
+    let mut file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        update(&mut hasher, &buffer[..read]);
+    }
+    Ok(hasher)
+}
+
+/// A downloaded file whose contents no longer match the recorded hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityMismatch {
+    pub file_path: String,
+    pub share_code: String,
+    pub expected_hash: String,
+    /// Hash of the file on disk, `None` if it could not be read
+    pub actual_hash: Option<String>,
+}
+
+/// Compute the SHA256 hex digest of a file
+pub fn hash_file(path: &Path) -> Result<String> {
+    HashAlgo::Sha256.hash_file(path)
+}
+
+/// Check a file against an expected SHA256 hex digest
+///
+/// # Arguments
+/// * `path` - File to hash
+/// * `expected_hash` - SHA256 hex digest, compared case-insensitively
+///
+/// # Returns
+/// `true` if the file matches, error if it cannot be read
+pub fn verify_download(path: &Path, expected_hash: &str) -> Result<bool> {
+    Ok(hash_file(path)?.eq_ignore_ascii_case(expected_hash))
+}
+
+/// Re-verify every recorded download and store the results
+///
+/// Hashing runs on the blocking thread pool. Files that are missing or
+/// unreadable are reported with `actual_hash: None`. Downloads of unhashed
+/// shares have no hash to check against and are skipped.
+///
+/// # Returns
+/// The downloads that failed verification
+pub async fn reverify_downloads(store: &FileSharingStore) -> Result<Vec<IntegrityMismatch>> {
+    let mut mismatches = Vec::new();
+
+    for file in store.list_downloaded_files().await? {
+        if file.hash.is_empty() {
+            continue;
+        }
+        let path = std::path::PathBuf::from(&file.file_path);
+        let algo = file.hash_algo;
+        let actual_hash = tokio::task::spawn_blocking(move || algo.hash_file(&path).ok())
+            .await
+            .context("Hashing task failed")?;
+        let ok = actual_hash
+            .as_deref()
+            .is_some_and(|hash| hash.eq_ignore_ascii_case(&file.hash));
+
+        store
+            .record_verification(&file.file_path, ok, chrono::Utc::now().timestamp())
+            .await?;
+
+        if !ok {
+            mismatches.push(IntegrityMismatch {
+                file_path: file.file_path,
+                share_code: file.share_code,
+                expected_hash: file.hash,
+                actual_hash,
+            });
+        }
+    }
+
+    Ok(mismatches)
+}
@@ -9,8 +9,10 @@
 //!
 //! - **MessageStore**: Core message persistence, offline queuing, and sync status
 //! - **ConversationStore**: Chat/conversation metadata and last message tracking
-//! - **ContactManager**: Contact book management (add, update, remove contacts)
+//! - **ContactManager**: Contact book management (add, update, remove, import, export)
 //! - **FileSharingStore**: Shared file metadata and transfer tracking
+//! - **DownloadHistoryStore**: Record of completed, failed and cancelled downloads
+//! - **UploadProgressStore**: Chunks served per upload, to resume upload progress
 //! - **ThumbnailStore**: Mapping between original files and generated thumbnails
 //! - **SettingsManager**: Application-wide settings
 //! - **SyncManager**: Message synchronization and acknowledgment tracking
@@ -24,6 +26,9 @@
 //! - `conversations`: Chat/conversation metadata and unread counts
 //! - `contacts`: Contact book entries
 //! - `shared_files`: File share metadata (hash, chunks, transfer status)
+//! - `downloaded_files`: Received files and their last integrity check
+//! - `download_history`: How each download ended, with peer, size and timestamps
+//! - `upload_progress`: Chunks served to each peer per share code
 //! - `thumbnails`: File-to-thumbnail path mappings
 //! - `settings`: Key-value settings storage
 //! - `message_acknowledgments`: Read receipts and delivery confirmations
@@ -78,6 +83,8 @@
 //!     sync_attempts: 0,
 //!     last_sync_attempt: None,
 //!     expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+//!     disappear_after_secs: None,
+//!     forwarded_from: None,
 //! };
 //! store.store_message(msg).await?;
 //! # Ok(())
@@ -86,32 +93,45 @@
 
 pub mod contact_manager;
 pub mod conversation_store;
+pub mod download_history_store;
 pub mod entities;
+pub mod error;
 pub mod file_sharing_store;
+pub mod integrity;
 pub mod message_store;
 pub mod migration;
 pub mod settings_manager;
 pub mod sync_manager;
 pub mod thumbnail;
 pub mod thumbnail_store;
+pub mod upload_progress_store;
 
 // Re-export from gigi-auth
 pub use gigi_auth::{AccountInfo, AuthManager, GroupInfo, GroupManager, LoginResult};
 
-pub use contact_manager::{ContactInfo, ContactManager};
+pub use contact_manager::{ContactImportSummary, ContactInfo, ContactManager};
 pub use conversation_store::{Conversation, ConversationStore};
-pub use file_sharing_store::{FileSharingStore, SharedFileInfo};
+pub use download_history_store::{
+    DownloadHistoryEntry, DownloadHistoryStatus, DownloadHistoryStore,
+};
+pub use error::StoreError;
+pub use file_sharing_store::{DownloadedFileInfo, FileSharingStore, ShareStats, SharedFileInfo};
+pub use integrity::{hash_file, reverify_downloads, verify_download, HashAlgo, IntegrityMismatch};
 pub use message_store::MessageStore;
 pub use settings_manager::SettingsManager;
-pub use sync_manager::{AckType, SyncAction, SyncManager, SyncMessage, SyncMessageHandler};
+pub use sync_manager::{
+    AckType, SyncAction, SyncFailure, SyncManager, SyncMessage, SyncMessageHandler, SyncProgress,
+};
 pub use thumbnail_store::ThumbnailStore;
+pub use upload_progress_store::{UploadProgress, UploadProgressStore};
 
 mod events;
 
 pub use events::{
-    AckType as EventAckType, FileShareContent, GroupShareContent, MessageAcknowledgment,
-    MessageContent, MessageDirection, MessageType, OfflineQueueItem, QueueStatus, StoredMessage,
-    SyncStatus, TextContent,
+    AckType as EventAckType, ConversationKey, ExportFormat, FileShareContent, GroupShareContent,
+    MessageAcknowledgment, MessageContent, MessageDirection, MessageOrder, MessageSyncInfo,
+    MessageType, OfflineQueueItem, PollResults, QueueStatus, StoredMessage, SyncStatus,
+    TextContent,
 };
 
 /// Configuration for persistence layer
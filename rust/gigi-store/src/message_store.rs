@@ -7,6 +7,9 @@
 //! - Message synchronization status tracking
 //! - Conversation history queries
 //! - Expiration and cleanup of old messages
+//! - Disappearing messages with a per-message timer
+//! - Group poll results aggregated from stored votes
+//! - Conversation export as a text or JSON transcript
 //!
 //! # Retry Logic
 //!
@@ -28,23 +31,34 @@
 //!
 //! The store uses SQLite indexes for efficient queries:
 //! - Timestamp indexing for history queries
+//! - Receive order indexing for history queries immune to clock skew
 //! - Sender/recipient indexing for conversation queries
 //! - Group name indexing for group messages
 //! - Sync status indexing for pending message queries
 //! - Expiration indexing for cleanup operations
 
 use crate::entities::{messages, offline_queue};
-use crate::events::StoredMessage;
+use crate::events::{
+    ConversationKey, ExportFormat, MessageContent, MessageOrder, MessageSyncInfo, PollResults,
+    StoredMessage, SyncStatus,
+};
 use crate::PersistenceConfig;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use gigi_logging::{debug, error, info};
+use gigi_logging::{debug, error, info, warn};
 use sea_orm::prelude::Expr;
 use sea_orm::*;
-use sea_orm_migration::MigratorTrait;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
+/// Messages read per query while exporting a conversation
+const EXPORT_PAGE_SIZE: u64 = 500;
+
+/// Received timestamps further ahead of the local clock than this are
+/// treated as a wrong sender clock and clamped to the time of storing
+const MAX_FUTURE_SKEW_SECS: i64 = 60;
+
 /// Message store - manages persistent message storage
 ///
 /// This struct handles all message-related database operations including:
@@ -85,7 +99,7 @@ impl MessageStore {
     /// * `db` - Existing Sea-ORM database connection
     pub async fn with_connection(db: DatabaseConnection) -> Result<Self> {
         // Run migrations
-        crate::migration::Migrator::up(&db, None)
+        crate::migration::migrate(&db)
             .await
             .context("Failed to run migrations")?;
 
@@ -131,7 +145,7 @@ impl MessageStore {
             .context("Failed to connect to database")?;
 
         // Run migrations
-        crate::migration::Migrator::up(&db, None)
+        crate::migration::migrate(&db)
             .await
             .context("Failed to run migrations")?;
 
@@ -175,6 +189,9 @@ impl MessageStore {
     }
 
     /// Store a message
+    ///
+    /// Sent disappearing messages start their timer immediately, so their
+    /// `expires_at` is shortened to `timestamp + disappear_after_secs`.
     pub async fn store_message(&self, msg: StoredMessage) -> Result<()> {
         // Convert MessageType and MessageDirection to string directly instead of JSON
         let msg_type_str = match msg.msg_type {
@@ -185,6 +202,18 @@ impl MessageStore {
             crate::events::MessageDirection::Sent => "Sent".to_string(),
             crate::events::MessageDirection::Received => "Received".to_string(),
         };
+        let timestamp = match msg.direction {
+            crate::events::MessageDirection::Received => {
+                clamp_future_timestamp(msg.timestamp, Utc::now())
+            }
+            crate::events::MessageDirection::Sent => msg.timestamp,
+        };
+        let expires_at = match (&msg.direction, msg.disappear_after_secs) {
+            (crate::events::MessageDirection::Sent, Some(secs)) => msg
+                .expires_at
+                .min(msg.timestamp + chrono::Duration::seconds(secs as i64)),
+            _ => msg.expires_at,
+        };
 
         let new_msg = messages::ActiveModel {
             id: Set(msg.id.clone()),
@@ -195,28 +224,29 @@ impl MessageStore {
                 crate::MessageContent::FileShare { .. } => "FileShare".to_string(),
                 crate::MessageContent::FileShareWithThumbnail { .. } => "FileShare".to_string(),
                 crate::MessageContent::ShareGroup { .. } => "ShareGroup".to_string(),
+                crate::MessageContent::Poll { .. } => "Poll".to_string(),
+                crate::MessageContent::PollVote { .. } => "PollVote".to_string(),
+                crate::MessageContent::Location { .. } => "Location".to_string(),
             }),
             content_json: Set(serde_json::to_string(&msg.content)?),
             sender_nickname: Set(msg.sender_nickname),
             recipient_nickname: Set(msg.recipient_nickname),
             group_name: Set(msg.group_name),
             peer_id: Set(msg.peer_id),
-            timestamp: Set(msg.timestamp.timestamp_millis()),
+            timestamp: Set(timestamp.timestamp_millis()),
             created_at: Set(msg.created_at.timestamp_millis()),
             delivered: Set(msg.delivered),
             delivered_at: Set(msg.delivered_at.map(|t| t.timestamp_millis())),
             read: Set(msg.read),
             read_at: Set(msg.read_at.map(|t| t.timestamp_millis())),
             // Convert SyncStatus to string directly instead of JSON
-            sync_status: Set(match msg.sync_status {
-                crate::events::SyncStatus::Pending => "Pending".to_string(),
-                crate::events::SyncStatus::Synced => "Synced".to_string(),
-                crate::events::SyncStatus::Delivered => "Delivered".to_string(),
-                crate::events::SyncStatus::Acknowledged => "Acknowledged".to_string(),
-            }),
+            sync_status: Set(sync_status_name(&msg.sync_status).to_string()),
             sync_attempts: Set(msg.sync_attempts),
             last_sync_attempt: Set(msg.last_sync_attempt.map(|t| t.timestamp_millis())),
-            expires_at: Set(msg.expires_at.timestamp_millis()),
+            expires_at: Set(expires_at.timestamp_millis()),
+            disappear_after_secs: Set(msg.disappear_after_secs.map(|secs| secs as i64)),
+            forwarded_from: Set(msg.forwarded_from),
+            receive_seq: Set(0),
         };
 
         // Use insert() without expecting a return value
@@ -233,6 +263,18 @@ impl MessageStore {
             }
         }
 
+        // Number the message in one statement, so concurrent writers never
+        // get the same receive order
+        messages::Entity::update_many()
+            .col_expr(
+                messages::Column::ReceiveSeq,
+                Expr::cust("(SELECT COALESCE(MAX(receive_seq), 0) + 1 FROM messages)"),
+            )
+            .filter(messages::Column::Id.eq(msg.id.as_str()))
+            .exec(&self.db)
+            .await
+            .context("Failed to record message receive order")?;
+
         debug!("Stored message: {}", msg.id);
         Ok(())
     }
@@ -300,12 +342,161 @@ impl MessageStore {
         Ok(messages)
     }
 
+    /// Get the sync tracking fields of a message
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(None)` if no message has the id.
+    pub async fn get_sync_status(&self, message_id: &str) -> Result<Option<MessageSyncInfo>> {
+        let Some(model) = messages::Entity::find_by_id(message_id.to_string())
+            .one(&self.db)
+            .await
+            .context("Failed to fetch message")?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(MessageSyncInfo {
+            sync_status: parse_sync_status(&model.sync_status)?,
+            sync_attempts: model.sync_attempts,
+            last_sync_attempt: model
+                .last_sync_attempt
+                .map(|t| {
+                    DateTime::from_timestamp_millis(t)
+                        .context("Invalid last_sync_attempt timestamp")
+                        .map(|dt| dt.with_timezone(&Utc))
+                })
+                .transpose()?,
+        }))
+    }
+
+    /// List messages in a sync status, oldest first
+    ///
+    /// Messages whose sync failed stay `Pending` with `sync_attempts` above
+    /// zero, so stuck messages can be found among the pending ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The sync status to filter by
+    /// * `limit` - Maximum number of messages to return
+    pub async fn list_messages_by_sync_status(
+        &self,
+        status: SyncStatus,
+        limit: usize,
+    ) -> Result<Vec<StoredMessage>> {
+        let result = messages::Entity::find()
+            .filter(messages::Column::SyncStatus.eq(sync_status_name(&status)))
+            .order_by_asc(messages::Column::Timestamp)
+            .limit(limit as u64)
+            .all(&self.db)
+            .await
+            .context("Failed to fetch messages by sync status")?;
+
+        result
+            .into_iter()
+            .map(|m| self.model_to_stored_message(m))
+            .collect()
+    }
+
+    /// Get the next batch of queued messages to sync to online peers
+    ///
+    /// Skips messages whose retry backoff has not elapsed yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_nicknames` - Nicknames of the peers messages can be sent to
+    /// * `limit` - Maximum number of messages to return
+    ///
+    /// # Returns
+    ///
+    /// Returns `(target_nickname, message)` pairs, oldest queued first.
+    pub async fn get_sync_batch(
+        &self,
+        target_nicknames: &[String],
+        limit: usize,
+    ) -> Result<Vec<(String, StoredMessage)>> {
+        if target_nicknames.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let now = Utc::now().timestamp_millis();
+
+        let items = offline_queue::Entity::find()
+            .filter(offline_queue::Column::Status.eq("Pending"))
+            .filter(offline_queue::Column::TargetNickname.is_in(target_nicknames.iter().cloned()))
+            .filter(
+                Condition::any()
+                    .add(offline_queue::Column::RetryCount.eq(0))
+                    .add(offline_queue::Column::NextRetryAt.lte(now)),
+            )
+            .order_by_asc(offline_queue::Column::QueuedAt)
+            .limit(limit as u64)
+            .all(&self.db)
+            .await
+            .context("Failed to fetch sync batch")?;
+
+        let mut batch = Vec::with_capacity(items.len());
+        for item in items {
+            if let Some(message) = self.get_message(&item.message_id).await? {
+                batch.push((item.target_nickname, message));
+            }
+        }
+
+        debug!("Selected {} messages to sync", batch.len());
+        Ok(batch)
+    }
+
+    /// Record an attempt to sync a queued message
+    ///
+    /// Bumps `sync_attempts`. A successful attempt marks the message as
+    /// `Synced` and removes it from the offline queue; a failed one schedules
+    /// a retry with backoff, see [`MessageStore::update_retry`].
+    pub async fn record_sync_attempt(&self, message_id: &str, success: bool) -> Result<()> {
+        let message = messages::Entity::find_by_id(message_id.to_string())
+            .one(&self.db)
+            .await
+            .context("Failed to fetch message")?
+            .ok_or_else(|| anyhow::anyhow!("Message {} not found", message_id))?;
+
+        let mut active: messages::ActiveModel = message.into();
+        let attempts = active.sync_attempts.take().unwrap_or_default();
+        active.sync_attempts = Set(attempts + 1);
+        active.last_sync_attempt = Set(Some(Utc::now().timestamp_millis()));
+        if success {
+            active.sync_status = Set("Synced".to_string());
+        }
+        active
+            .update(&self.db)
+            .await
+            .context("Failed to record sync attempt")?;
+
+        if success {
+            self.mark_message_sent(message_id).await
+        } else {
+            self.update_retry(message_id, false).await
+        }
+    }
+
     /// Get conversation history
     pub async fn get_conversation(
         &self,
         peer_nickname: &str,
         limit: usize,
         offset: usize,
+    ) -> Result<Vec<StoredMessage>> {
+        self.get_conversation_ordered(peer_nickname, limit, offset, MessageOrder::Timestamp)
+            .await
+    }
+
+    /// Get conversation history, newest first in the given order
+    ///
+    /// `MessageOrder::ReceiveOrder` keeps messages from a peer with a skewed
+    /// clock in the order they arrived.
+    pub async fn get_conversation_ordered(
+        &self,
+        peer_nickname: &str,
+        limit: usize,
+        offset: usize,
+        order: MessageOrder,
     ) -> Result<Vec<StoredMessage>> {
         let result = messages::Entity::find()
             .filter(
@@ -314,7 +505,7 @@ impl MessageStore {
                     .add(messages::Column::RecipientNickname.eq(peer_nickname)),
             )
             .filter(messages::Column::MsgType.eq("Direct"))
-            .order_by_desc(messages::Column::Timestamp)
+            .order_by_desc(order_column(order))
             .paginate(&self.db, limit as u64)
             .fetch_page(offset as u64)
             .await
@@ -362,16 +553,72 @@ impl MessageStore {
         Ok(messages)
     }
 
+    /// Get conversation history with a peer, newest first
+    ///
+    /// Matches messages by `peer_id` rather than by nickname, so peers
+    /// sharing a nickname or placeholder label keep separate conversations
+    /// and messages stay with a peer that changes its nickname. Messages
+    /// still queued for `nickname`, sent before its peer ID was known, are
+    /// included.
+    pub async fn get_conversation_with_peer(
+        &self,
+        peer_id: &str,
+        nickname: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<StoredMessage>> {
+        let result = messages::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(messages::Column::PeerId.eq(peer_id))
+                    .add(
+                        Condition::all()
+                            .add(messages::Column::PeerId.eq(""))
+                            .add(messages::Column::RecipientNickname.eq(nickname)),
+                    ),
+            )
+            .filter(messages::Column::MsgType.eq("Direct"))
+            .order_by_desc(messages::Column::Timestamp)
+            .paginate(&self.db, limit as u64)
+            .fetch_page(offset as u64)
+            .await
+            .context("Failed to fetch conversation with peer")?;
+
+        let messages: Vec<StoredMessage> = result
+            .into_iter()
+            .map(|m| self.model_to_stored_message(m))
+            .collect::<Result<Vec<_>>>()?;
+
+        debug!(
+            "Retrieved {} messages from conversation with peer_id {}",
+            messages.len(),
+            peer_id
+        );
+        Ok(messages)
+    }
+
     /// Get group messages
     pub async fn get_group_messages(
         &self,
         group_name: &str,
         limit: usize,
         offset: usize,
+    ) -> Result<Vec<StoredMessage>> {
+        self.get_group_messages_ordered(group_name, limit, offset, MessageOrder::Timestamp)
+            .await
+    }
+
+    /// Get group messages, newest first in the given order
+    pub async fn get_group_messages_ordered(
+        &self,
+        group_name: &str,
+        limit: usize,
+        offset: usize,
+        order: MessageOrder,
     ) -> Result<Vec<StoredMessage>> {
         let result = messages::Entity::find()
             .filter(messages::Column::GroupName.eq(group_name))
-            .order_by_desc(messages::Column::Timestamp)
+            .order_by_desc(order_column(order))
             .paginate(&self.db, limit as u64)
             .fetch_page(offset as u64)
             .await
@@ -390,6 +637,82 @@ impl MessageStore {
         Ok(messages)
     }
 
+    /// Export a conversation as a transcript, oldest message first
+    ///
+    /// Messages are read `EXPORT_PAGE_SIZE` at a time, so only the transcript
+    /// itself is held in memory. File shares are exported by filename, size
+    /// and thumbnail path; no file data is included.
+    pub async fn export_conversation(
+        &self,
+        conversation: &ConversationKey,
+        format: ExportFormat,
+    ) -> Result<String> {
+        let query = match conversation {
+            ConversationKey::Direct(nickname) => messages::Entity::find()
+                .filter(
+                    Condition::any()
+                        .add(messages::Column::SenderNickname.eq(nickname.as_str()))
+                        .add(messages::Column::RecipientNickname.eq(nickname.as_str())),
+                )
+                .filter(messages::Column::MsgType.eq("Direct")),
+            ConversationKey::Group(group_name) => {
+                messages::Entity::find().filter(messages::Column::GroupName.eq(group_name.as_str()))
+            }
+        };
+        let mut pages = query
+            .order_by_asc(messages::Column::Timestamp)
+            .order_by_asc(messages::Column::Id)
+            .paginate(&self.db, EXPORT_PAGE_SIZE);
+
+        let mut output = String::new();
+        let mut exported = 0;
+        if format == ExportFormat::Json {
+            output.push('[');
+        }
+        while let Some(page) = pages
+            .fetch_and_next()
+            .await
+            .context("Failed to fetch messages to export")?
+        {
+            for model in page {
+                let message = self.model_to_stored_message(model)?;
+                match format {
+                    ExportFormat::Text => {
+                        output.push_str(&format!(
+                            "[{}] {}: {}\n",
+                            message.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                            message.sender_nickname,
+                            describe_content(&message.content)
+                        ));
+                    }
+                    ExportFormat::Json => {
+                        if exported > 0 {
+                            output.push(',');
+                        }
+                        let entry = serde_json::json!({
+                            "id": message.id,
+                            "timestamp": message.timestamp.to_rfc3339(),
+                            "sender": message.sender_nickname,
+                            "recipient": message.recipient_nickname,
+                            "group": message.group_name,
+                            "direction": message.direction,
+                            "content": message.content,
+                            "forwarded_from": message.forwarded_from,
+                        });
+                        output.push_str(&entry.to_string());
+                    }
+                }
+                exported += 1;
+            }
+        }
+        if format == ExportFormat::Json {
+            output.push(']');
+        }
+
+        debug!("Exported {} messages of {:?}", exported, conversation);
+        Ok(output)
+    }
+
     /// Mark message as sent (remove from offline queue)
     pub async fn mark_message_sent(&self, message_id: &str) -> Result<()> {
         // Update queue status to "Sent" so it won't be picked up again
@@ -467,31 +790,19 @@ impl MessageStore {
             if let Ok(content) =
                 serde_json::from_str::<crate::events::MessageContent>(&msg.content_json)
             {
-                let (code, filename, file_size, file_type) = match content {
-                    crate::events::MessageContent::FileShare {
-                        share_code,
-                        filename,
-                        file_size,
-                        file_type,
-                    } => (share_code, filename, file_size, file_type),
-                    crate::events::MessageContent::FileShareWithThumbnail {
-                        share_code,
-                        filename,
-                        file_size,
-                        file_type,
-                        ..
-                    } => (share_code, filename, file_size, file_type),
-                    _ => continue,
+                let Some(file) = content.file_share() else {
+                    continue;
                 };
 
-                if code == share_code {
+                if file.share_code == share_code {
                     // Update content with thumbnail_path
                     let updated_content = crate::events::MessageContent::FileShareWithThumbnail {
-                        share_code: code,
-                        filename,
-                        file_size,
-                        file_type,
+                        share_code: file.share_code,
+                        filename: file.filename,
+                        file_size: file.file_size,
+                        file_type: file.file_type,
                         thumbnail_path: Some(thumbnail_path.to_string()),
+                        from_peer: file.from_peer,
                     };
                     let updated_json = serde_json::to_string(&updated_content)?;
 
@@ -528,6 +839,7 @@ impl MessageStore {
         .update(&self.db)
         .await
         .context("Failed to mark message as read")?;
+        self.start_disappearing_timers(now).await?;
 
         debug!("Marked message {} as read", message_id);
         Ok(())
@@ -546,11 +858,31 @@ impl MessageStore {
             .exec(&self.db)
             .await
             .context("Failed to mark conversation as read")?;
+        self.start_disappearing_timers(now).await?;
 
         debug!("Marked all messages from {} as read", nickname);
         Ok(())
     }
 
+    /// Shorten `expires_at` of disappearing messages just marked read to
+    /// `read_at + timer`
+    ///
+    /// Only touches the rows marked read at `read_at`, and is idempotent:
+    /// messages whose timer already started are left unchanged.
+    async fn start_disappearing_timers(&self, read_at: i64) -> Result<()> {
+        self.db
+            .execute(Statement::from_sql_and_values(
+                DatabaseBackend::Sqlite,
+                "UPDATE messages \
+                 SET expires_at = MIN(expires_at, read_at + disappear_after_secs * 1000) \
+                 WHERE disappear_after_secs IS NOT NULL AND read_at = ?",
+                [read_at.into()],
+            ))
+            .await
+            .context("Failed to start disappearing message timers")?;
+        Ok(())
+    }
+
     /// Update retry information for a message
     pub async fn update_retry(&self, message_id: &str, success: bool) -> Result<()> {
         if success {
@@ -692,10 +1024,14 @@ impl MessageStore {
             .context("Failed to cleanup expired queue items")?
             .rows_affected;
 
-        // Clean up expired delivered messages
+        // Clean up expired delivered messages and expired disappearing messages
         let msg_count = messages::Entity::delete_many()
             .filter(messages::Column::ExpiresAt.lt(now))
-            .filter(messages::Column::Delivered.eq(true))
+            .filter(
+                Condition::any()
+                    .add(messages::Column::Delivered.eq(true))
+                    .add(messages::Column::DisappearAfterSecs.is_not_null()),
+            )
             .exec(&self.db)
             .await
             .context("Failed to cleanup expired messages")?
@@ -709,6 +1045,119 @@ impl MessageStore {
         Ok(total)
     }
 
+    /// Delete thumbnail files of image messages for both incoming and outgoing images
+    async fn delete_message_thumbnails(
+        &self,
+        models: &[messages::Model],
+        file_sharing_store: &crate::file_sharing_store::FileSharingStore,
+        thumbnail_store: &crate::ThumbnailStore,
+    ) {
+        for msg in models {
+            // Parse content from JSON
+            let Ok(
+                crate::events::MessageContent::FileShare {
+                    share_code,
+                    file_type,
+                    ..
+                }
+                | crate::events::MessageContent::FileShareWithThumbnail {
+                    share_code,
+                    file_type,
+                    ..
+                },
+            ) = serde_json::from_str::<crate::events::MessageContent>(&msg.content_json)
+            else {
+                continue;
+            };
+            // Only process images
+            if !file_type.starts_with("image/") {
+                continue;
+            }
+
+            // Direction is stored as a plain string, not JSON
+            match msg.direction.as_str() {
+                "Received" => {
+                    // INCOMING messages: Get thumbnail path from file_sharing_store
+                    if let Ok(Some(thumbnail_path)) =
+                        file_sharing_store.get_thumbnail_path(&share_code).await
+                    {
+                        // Delete the thumbnail file
+                        let thumbnail_file = PathBuf::from(&thumbnail_path);
+                        if thumbnail_file.exists() {
+                            if let Err(e) = fs::remove_file(&thumbnail_file) {
+                                error!("Failed to delete thumbnail file {}: {}", thumbnail_path, e);
+                            } else {
+                                info!(
+                                    "Deleted thumbnail file for incoming image: {}",
+                                    thumbnail_path
+                                );
+                            }
+                        }
+                    }
+                }
+                "Sent" => {
+                    // OUTGOING messages: Get file path from file_sharing_store,
+                    // then get thumbnail path from thumbnail_store
+                    let Ok(Some(shared_file)) =
+                        file_sharing_store.get_shared_file(&share_code).await
+                    else {
+                        continue;
+                    };
+                    let file_path = shared_file.file_path;
+                    // Look up thumbnail path from thumbnail_store
+                    if let Ok(Some(thumbnail_path)) =
+                        thumbnail_store.get_thumbnail(&file_path).await
+                    {
+                        // Delete the thumbnail file
+                        let thumbnail_file = PathBuf::from(&thumbnail_path);
+                        if thumbnail_file.exists() {
+                            if let Err(e) = fs::remove_file(&thumbnail_file) {
+                                error!(
+                                    "Failed to delete thumbnail file for outgoing image {}: {}",
+                                    thumbnail_path, e
+                                );
+                            } else {
+                                info!(
+                                    "Deleted thumbnail file for outgoing image: {}",
+                                    thumbnail_path
+                                );
+                            }
+                        }
+                        // Delete the thumbnail mapping from thumbnail_store
+                        if let Err(e) = thumbnail_store.delete_thumbnail(&file_path).await {
+                            error!(
+                                "Failed to delete thumbnail mapping for {}: {}",
+                                file_path, e
+                            );
+                        }
+                    }
+                }
+                _ => warn!("Invalid direction on message {}: {}", msg.id, msg.direction),
+            }
+        }
+    }
+
+    /// Clean up expired messages, deleting thumbnails of expired disappearing messages
+    ///
+    /// Same as `cleanup_expired`, but also removes the thumbnail files of image
+    /// messages that disappear so no trace of them is left behind.
+    pub async fn cleanup_expired_with_thumbnails(
+        &self,
+        file_sharing_store: &crate::file_sharing_store::FileSharingStore,
+        thumbnail_store: &crate::ThumbnailStore,
+    ) -> Result<u64> {
+        let expired = messages::Entity::find()
+            .filter(messages::Column::ExpiresAt.lt(Utc::now().timestamp_millis()))
+            .filter(messages::Column::DisappearAfterSecs.is_not_null())
+            .all(&self.db)
+            .await
+            .context("Failed to fetch expired disappearing messages")?;
+
+        self.delete_message_thumbnails(&expired, file_sharing_store, thumbnail_store)
+            .await;
+        self.cleanup_expired().await
+    }
+
     /// Get unread message count for a peer
     pub async fn get_unread_count(&self, peer_nickname: &str) -> Result<u64> {
         let count = messages::Entity::find()
@@ -732,6 +1181,95 @@ impl MessageStore {
         result.map(|m| self.model_to_stored_message(m)).transpose()
     }
 
+    /// Get the vote counts of a poll
+    ///
+    /// Counts are recomputed from the `PollVote` messages stored in the
+    /// poll's group. Only each peer's latest vote counts, by timestamp and
+    /// then by arrival; votes for options the poll does not have are ignored.
+    ///
+    /// # Arguments
+    /// * `poll_id` - Id of the poll, which is also its message id
+    ///
+    /// # Returns
+    /// The results, or `None` if the poll is not stored
+    pub async fn get_poll_results(&self, poll_id: &str) -> Result<Option<PollResults>> {
+        let Some(poll) = self.get_message(poll_id).await? else {
+            return Ok(None);
+        };
+        let crate::events::MessageContent::Poll {
+            question, options, ..
+        } = poll.content
+        else {
+            return Ok(None);
+        };
+
+        let votes = messages::Entity::find().filter(messages::Column::ContentType.eq("PollVote"));
+        let votes = match &poll.group_name {
+            Some(group_name) => votes.filter(messages::Column::GroupName.eq(group_name)),
+            None => votes.filter(messages::Column::GroupName.is_null()),
+        };
+        let votes = votes
+            .order_by_asc(messages::Column::Timestamp)
+            .order_by_asc(messages::Column::CreatedAt)
+            .all(&self.db)
+            .await
+            .context("Failed to fetch poll votes")?;
+
+        let mut latest: HashMap<String, u32> = HashMap::new();
+        for vote in votes {
+            let Ok(crate::events::MessageContent::PollVote {
+                poll_id: vote_poll_id,
+                option_index,
+            }) = serde_json::from_str(&vote.content_json)
+            else {
+                continue;
+            };
+            if vote_poll_id == poll_id && (option_index as usize) < options.len() {
+                latest.insert(vote.peer_id, option_index);
+            }
+        }
+
+        let mut counts = vec![0; options.len()];
+        for option_index in latest.values() {
+            counts[*option_index as usize] += 1;
+        }
+        Ok(Some(PollResults {
+            poll_id: poll_id.to_string(),
+            question,
+            options,
+            votes: counts,
+            total_votes: latest.len() as u64,
+        }))
+    }
+
+    /// Find file share messages whose filename contains `query`, newest first
+    ///
+    /// Matching is case-insensitive. Covers direct and group messages, with
+    /// or without a downloaded thumbnail.
+    pub async fn search_file_shares(&self, query: &str) -> Result<Vec<StoredMessage>> {
+        let needle = query.to_lowercase();
+        let models = messages::Entity::find()
+            .filter(messages::Column::ContentType.eq("FileShare"))
+            .filter(messages::Column::ContentJson.contains(query))
+            .order_by_desc(messages::Column::Timestamp)
+            .all(&self.db)
+            .await
+            .context("Failed to search file shares")?;
+
+        let mut results = Vec::new();
+        for model in models {
+            let msg = self.model_to_stored_message(model)?;
+            let matches = msg
+                .content
+                .file_share()
+                .is_some_and(|file| file.filename.to_lowercase().contains(&needle));
+            if matches {
+                results.push(msg);
+            }
+        }
+        Ok(results)
+    }
+
     /// Convert Sea-ORM model to StoredMessage
     fn model_to_stored_message(&self, model: messages::Model) -> Result<StoredMessage> {
         // Parse msg_type and direction from string instead of JSON
@@ -782,18 +1320,7 @@ impl MessageStore {
                 })
                 .transpose()?,
             // Parse sync_status from string instead of JSON
-            sync_status: match model.sync_status.as_str() {
-                "Pending" => crate::events::SyncStatus::Pending,
-                "Synced" => crate::events::SyncStatus::Synced,
-                "Delivered" => crate::events::SyncStatus::Delivered,
-                "Acknowledged" => crate::events::SyncStatus::Acknowledged,
-                _ => {
-                    return Err(anyhow::anyhow!(
-                        "Invalid sync_status: {}",
-                        model.sync_status
-                    ))
-                }
-            },
+            sync_status: parse_sync_status(&model.sync_status)?,
             sync_attempts: model.sync_attempts,
             last_sync_attempt: model
                 .last_sync_attempt
@@ -806,6 +1333,8 @@ impl MessageStore {
             expires_at: DateTime::from_timestamp_millis(model.expires_at)
                 .context("Invalid expires_at timestamp")?
                 .with_timezone(&Utc),
+            disappear_after_secs: model.disappear_after_secs.map(|secs| secs as u64),
+            forwarded_from: model.forwarded_from,
         })
     }
 
@@ -868,90 +1397,8 @@ impl MessageStore {
         );
 
         // 2. Delete thumbnail files for both incoming and outgoing images
-        for msg in &image_messages {
-            // Parse content from JSON
-            if let Ok(
-                crate::events::MessageContent::FileShare {
-                    share_code,
-                    file_type,
-                    ..
-                }
-                | crate::events::MessageContent::FileShareWithThumbnail {
-                    share_code,
-                    file_type,
-                    ..
-                },
-            ) = serde_json::from_str::<crate::events::MessageContent>(&msg.content_json)
-            {
-                // Only process images
-                if file_type.starts_with("image/") {
-                    // Check if message is incoming or outgoing
-                    if let Ok(direction) =
-                        serde_json::from_str::<crate::events::MessageDirection>(&msg.direction)
-                    {
-                        if matches!(direction, crate::events::MessageDirection::Received) {
-                            // INCOMING messages: Get thumbnail path from file_sharing_store
-                            if let Ok(Some(thumbnail_path)) =
-                                file_sharing_store.get_thumbnail_path(&share_code).await
-                            {
-                                // Delete the thumbnail file
-                                let thumbnail_file = PathBuf::from(&thumbnail_path);
-                                if thumbnail_file.exists() {
-                                    if let Err(e) = fs::remove_file(&thumbnail_file) {
-                                        error!(
-                                            "Failed to delete thumbnail file {}: {}",
-                                            thumbnail_path, e
-                                        );
-                                    } else {
-                                        info!(
-                                            "Deleted thumbnail file for incoming image: {}",
-                                            thumbnail_path
-                                        );
-                                    }
-                                }
-                            }
-                        } else {
-                            // OUTGOING messages: Get file path from file_sharing_store,
-                            // then get thumbnail path from thumbnail_store
-                            if let Ok(Some(shared_file)) =
-                                file_sharing_store.get_shared_file(&share_code).await
-                            {
-                                let file_path = shared_file.file_path;
-                                // Look up thumbnail path from thumbnail_store
-                                if let Ok(Some(thumbnail_path)) =
-                                    thumbnail_store.get_thumbnail(&file_path).await
-                                {
-                                    // Delete the thumbnail file
-                                    let thumbnail_file = PathBuf::from(&thumbnail_path);
-                                    if thumbnail_file.exists() {
-                                        if let Err(e) = fs::remove_file(&thumbnail_file) {
-                                            error!(
-                                                        "Failed to delete thumbnail file for outgoing image {}: {}",
-                                                        thumbnail_path, e
-                                                    );
-                                        } else {
-                                            info!(
-                                                "Deleted thumbnail file for outgoing image: {}",
-                                                thumbnail_path
-                                            );
-                                        }
-                                    }
-                                    // Delete the thumbnail mapping from thumbnail_store
-                                    if let Err(e) =
-                                        thumbnail_store.delete_thumbnail(&file_path).await
-                                    {
-                                        error!(
-                                            "Failed to delete thumbnail mapping for {}: {}",
-                                            file_path, e
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        self.delete_message_thumbnails(&image_messages, file_sharing_store, thumbnail_store)
+            .await;
 
         // 3. Clear the conversation
         // For direct chats: delete where peer_id matches AND msg_type is Direct
@@ -984,6 +1431,93 @@ impl MessageStore {
     }
 }
 
+/// Column a history query sorts by
+fn order_column(order: MessageOrder) -> messages::Column {
+    match order {
+        MessageOrder::Timestamp => messages::Column::Timestamp,
+        MessageOrder::ReceiveOrder => messages::Column::ReceiveSeq,
+    }
+}
+
+/// Pull a received timestamp from a clock running ahead back to `now`
+fn clamp_future_timestamp(timestamp: DateTime<Utc>, now: DateTime<Utc>) -> DateTime<Utc> {
+    if timestamp > now + chrono::Duration::seconds(MAX_FUTURE_SKEW_SECS) {
+        now
+    } else {
+        timestamp
+    }
+}
+
+/// One-line description of a message for a text transcript
+fn describe_content(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text { text } => text.clone(),
+        MessageContent::FileShare {
+            filename,
+            file_size,
+            ..
+        }
+        | MessageContent::FileShareWithThumbnail {
+            thumbnail_path: None,
+            filename,
+            file_size,
+            ..
+        } => format!("[file: {}, {} bytes]", filename, file_size),
+        MessageContent::FileShareWithThumbnail {
+            thumbnail_path: Some(thumbnail_path),
+            filename,
+            file_size,
+            ..
+        } => format!(
+            "[file: {}, {} bytes, thumbnail: {}]",
+            filename, file_size, thumbnail_path
+        ),
+        MessageContent::ShareGroup {
+            group_name,
+            inviter_nickname,
+            ..
+        } => format!("[group invite: {} from {}]", group_name, inviter_nickname),
+        MessageContent::Poll {
+            question, options, ..
+        } => format!("[poll: {} ({})]", question, options.join(" / ")),
+        MessageContent::PollVote {
+            poll_id,
+            option_index,
+        } => format!("[vote: option {} in poll {}]", option_index, poll_id),
+        MessageContent::Location {
+            lat,
+            lon,
+            label: Some(label),
+        } => format!("[location: {} ({}, {})]", label, lat, lon),
+        MessageContent::Location {
+            lat,
+            lon,
+            label: None,
+        } => format!("[location: {}, {}]", lat, lon),
+    }
+}
+
+/// Column value stored for a sync status
+fn sync_status_name(status: &SyncStatus) -> &'static str {
+    match status {
+        SyncStatus::Pending => "Pending",
+        SyncStatus::Synced => "Synced",
+        SyncStatus::Delivered => "Delivered",
+        SyncStatus::Acknowledged => "Acknowledged",
+    }
+}
+
+/// Parse a stored sync status column value
+fn parse_sync_status(value: &str) -> Result<SyncStatus> {
+    match value {
+        "Pending" => Ok(SyncStatus::Pending),
+        "Synced" => Ok(SyncStatus::Synced),
+        "Delivered" => Ok(SyncStatus::Delivered),
+        "Acknowledged" => Ok(SyncStatus::Acknowledged),
+        _ => Err(anyhow::anyhow!("Invalid sync_status: {}", value)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1018,6 +1552,8 @@ mod tests {
             sync_attempts: 0,
             last_sync_attempt: None,
             expires_at: Utc::now() + chrono::Duration::days(1),
+            disappear_after_secs: None,
+            forwarded_from: None,
         };
 
         store.store_message(msg.clone()).await.unwrap();
@@ -1058,6 +1594,8 @@ mod tests {
             sync_attempts: 0,
             last_sync_attempt: None,
             expires_at: Utc::now() + chrono::Duration::days(1),
+            disappear_after_secs: None,
+            forwarded_from: None,
         };
 
         store.store_message(msg.clone()).await.unwrap();
@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum DownloadedFiles {
+    Table,
+    FilePath,
+    ShareCode,
+    FileName,
+    FileSize,
+    Hash,
+    DownloadedAt,
+    VerifiedAt,
+    VerifiedOk,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251015_000002_create_downloaded_files_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DownloadedFiles::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DownloadedFiles::FilePath)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadedFiles::ShareCode)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadedFiles::FileName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadedFiles::FileSize)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(DownloadedFiles::Hash).string().not_null())
+                    .col(
+                        ColumnDef::new(DownloadedFiles::DownloadedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadedFiles::VerifiedAt)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadedFiles::VerifiedOk)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DownloadedFiles::Table).to_owned())
+            .await
+    }
+}
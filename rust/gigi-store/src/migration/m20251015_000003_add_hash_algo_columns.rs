@@ -0,0 +1,75 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum SharedFiles {
+    Table,
+    HashAlgo,
+}
+
+#[derive(DeriveIden)]
+enum DownloadedFiles {
+    Table,
+    HashAlgo,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251015_000003_add_hash_algo_columns"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Existing hashes were all computed with SHA256
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SharedFiles::Table)
+                    .add_column(
+                        ColumnDef::new(SharedFiles::HashAlgo)
+                            .string()
+                            .not_null()
+                            .default("sha256"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DownloadedFiles::Table)
+                    .add_column(
+                        ColumnDef::new(DownloadedFiles::HashAlgo)
+                            .string()
+                            .not_null()
+                            .default("sha256"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DownloadedFiles::Table)
+                    .drop_column(DownloadedFiles::HashAlgo)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SharedFiles::Table)
+                    .drop_column(SharedFiles::HashAlgo)
+                    .to_owned(),
+            )
+            .await
+    }
+}
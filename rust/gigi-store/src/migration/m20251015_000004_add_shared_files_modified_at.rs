@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum SharedFiles {
+    Table,
+    ModifiedAt,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251015_000004_add_shared_files_modified_at"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SharedFiles::Table)
+                    .add_column(ColumnDef::new(SharedFiles::ModifiedAt).big_integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SharedFiles::Table)
+                    .drop_column(SharedFiles::ModifiedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum Contacts {
+    Table,
+    DisplayName,
+    PublicKey,
+    AvatarHash,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251015_000005_add_contact_profile_columns"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one column per ALTER TABLE
+        for column in [
+            Contacts::DisplayName,
+            Contacts::PublicKey,
+            Contacts::AvatarHash,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Contacts::Table)
+                        .add_column(ColumnDef::new(column).string().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Contacts::AvatarHash,
+            Contacts::PublicKey,
+            Contacts::DisplayName,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Contacts::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum DownloadHistory {
+    Table,
+    Id,
+    ShareCode,
+    FileName,
+    PeerId,
+    PeerNickname,
+    FileSize,
+    Status,
+    Error,
+    StartedAt,
+    FinishedAt,
+    FinalPath,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251015_000007_create_download_history_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DownloadHistory::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DownloadHistory::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadHistory::ShareCode)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadHistory::FileName)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(DownloadHistory::PeerId).string().not_null())
+                    .col(
+                        ColumnDef::new(DownloadHistory::PeerNickname)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadHistory::FileSize)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(DownloadHistory::Status).string().not_null())
+                    .col(ColumnDef::new(DownloadHistory::Error).string().null())
+                    .col(
+                        ColumnDef::new(DownloadHistory::StartedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DownloadHistory::FinishedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(DownloadHistory::FinalPath).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_download_history_finished_at")
+                    .table(DownloadHistory::Table)
+                    .col(DownloadHistory::FinishedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DownloadHistory::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum UploadProgress {
+    Table,
+    PeerId,
+    ShareCode,
+    ServedChunks,
+    TotalChunks,
+    UpdatedAt,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251015_000008_create_upload_progress_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UploadProgress::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(UploadProgress::PeerId).string().not_null())
+                    .col(
+                        ColumnDef::new(UploadProgress::ShareCode)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadProgress::ServedChunks)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadProgress::TotalChunks)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadProgress::UpdatedAt)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(UploadProgress::PeerId)
+                            .col(UploadProgress::ShareCode),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UploadProgress::Table).to_owned())
+            .await
+    }
+}
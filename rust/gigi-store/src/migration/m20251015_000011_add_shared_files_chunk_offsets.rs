@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum SharedFiles {
+    Table,
+    ChunkOffsets,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251015_000011_add_shared_files_chunk_offsets"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SharedFiles::Table)
+                    .add_column(ColumnDef::new(SharedFiles::ChunkOffsets).text().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SharedFiles::Table)
+                    .drop_column(SharedFiles::ChunkOffsets)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum Contacts {
+    Table,
+    UpdatedAt,
+}
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20251015_000012_add_contacts_updated_at"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Contacts::Table)
+                    .add_column(
+                        ColumnDef::new(Contacts::UpdatedAt)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing contacts were last changed when they were added, as far as we know
+        manager
+            .get_connection()
+            .execute_unprepared("UPDATE contacts SET updated_at = added_at")
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Contacts::Table)
+                    .drop_column(Contacts::UpdatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
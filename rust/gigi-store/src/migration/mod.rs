@@ -1,7 +1,20 @@
 //! Sea-ORM migrations for gigi-store database schema
+//!
+//! The number of migrations this build knows is its schema version. After
+//! migrating, [`migrate`] stamps that version into the `settings` table, and
+//! refuses to open a database stamped with a newer version, so a downgraded
+//! app fails clearly instead of running against a schema it does not know.
 
 pub use sea_orm_migration::prelude::*;
 
+use sea_orm::{DatabaseConnection, Statement};
+
+use crate::error::StoreError;
+use crate::settings_manager::SettingsManager;
+
+/// Settings key holding the schema version a database was migrated to
+pub const SCHEMA_VERSION_KEY: &str = "schema_version";
+
 mod m20250113_000001_create_messages_table;
 mod m20250113_000002_create_offline_queue_table;
 mod m20250113_000003_create_message_acknowledgments_table;
@@ -11,6 +24,18 @@ mod m20250117_000001_create_thumbnails_table;
 mod m20250117_000002_create_conversations_table;
 mod m20250120_000001_create_settings_table;
 mod m20250121_000001_create_contacts_table;
+mod m20251015_000001_add_messages_disappear_after;
+mod m20251015_000002_create_downloaded_files_table;
+mod m20251015_000003_add_hash_algo_columns;
+mod m20251015_000004_add_shared_files_modified_at;
+mod m20251015_000005_add_contact_profile_columns;
+mod m20251015_000006_add_messages_forwarded_from;
+mod m20251015_000007_create_download_history_table;
+mod m20251015_000008_create_upload_progress_table;
+mod m20251015_000009_add_messages_receive_seq;
+mod m20251015_000010_add_shared_files_relative_path;
+mod m20251015_000011_add_shared_files_chunk_offsets;
+mod m20251015_000012_add_contacts_updated_at;
 
 pub struct Migrator;
 
@@ -27,6 +52,67 @@ impl MigratorTrait for Migrator {
             Box::new(m20250117_000002_create_conversations_table::Migration),
             Box::new(m20250120_000001_create_settings_table::Migration),
             Box::new(m20250121_000001_create_contacts_table::Migration),
+            Box::new(m20251015_000001_add_messages_disappear_after::Migration),
+            Box::new(m20251015_000002_create_downloaded_files_table::Migration),
+            Box::new(m20251015_000003_add_hash_algo_columns::Migration),
+            Box::new(m20251015_000004_add_shared_files_modified_at::Migration),
+            Box::new(m20251015_000005_add_contact_profile_columns::Migration),
+            Box::new(m20251015_000006_add_messages_forwarded_from::Migration),
+            Box::new(m20251015_000007_create_download_history_table::Migration),
+            Box::new(m20251015_000008_create_upload_progress_table::Migration),
+            Box::new(m20251015_000009_add_messages_receive_seq::Migration),
+            Box::new(m20251015_000010_add_shared_files_relative_path::Migration),
+            Box::new(m20251015_000011_add_shared_files_chunk_offsets::Migration),
+            Box::new(m20251015_000012_add_contacts_updated_at::Migration),
         ]
     }
 }
+
+/// Schema version of this build, the number of known migrations
+pub fn supported_schema_version() -> u32 {
+    Migrator::migrations().len() as u32
+}
+
+/// Migrate a database to this build's schema version
+///
+/// # Errors
+///
+/// Returns `StoreError::IncompatibleSchema` without touching the database
+/// if it was stamped by a newer build.
+pub async fn migrate(db: &DatabaseConnection) -> Result<(), StoreError> {
+    let supported = supported_schema_version();
+    if let Some(found) = stored_schema_version(db).await? {
+        if found > supported {
+            return Err(StoreError::IncompatibleSchema { found, supported });
+        }
+    }
+
+    Migrator::up(db, None).await?;
+    SettingsManager::new(db.clone())
+        .set(SCHEMA_VERSION_KEY, &supported.to_string())
+        .await?;
+    Ok(())
+}
+
+/// Read the stamped schema version, `None` for new or unstamped databases
+async fn stored_schema_version(db: &DatabaseConnection) -> Result<Option<u32>, StoreError> {
+    let settings_table = db
+        .query_one(Statement::from_string(
+            db.get_database_backend(),
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'settings'",
+        ))
+        .await?;
+    if settings_table.is_none() {
+        return Ok(None);
+    }
+
+    SettingsManager::new(db.clone())
+        .get(SCHEMA_VERSION_KEY)
+        .await?
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| StoreError::InvalidSchemaVersion(value))
+        })
+        .transpose()
+}
@@ -20,6 +20,9 @@ pub const GROUP_ID_KEY: &str = "group_id";
 /// Key for storing encrypted password hash
 pub const PASSWORD_HASH_KEY: &str = "password_hash";
 
+/// Key for storing the JSON list of trusted peer IDs
+pub const TRUSTED_PEERS_KEY: &str = "trusted_peers";
+
 /// Settings manager for storing and retrieving application settings
 pub struct SettingsManager {
     db: DatabaseConnection,
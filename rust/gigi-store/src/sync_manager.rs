@@ -1,7 +1,14 @@
 //! Sync manager - coordinates message synchronization between peers
+//!
+//! Besides reacting to peers coming online, the sync manager retries queued
+//! messages on a schedule: every `sync_interval_seconds` it takes up to
+//! `max_batch_size` queued messages for online peers, hands each to a
+//! delivery callback and records the outcome in `sync_status` and
+//! `sync_attempts`. The caller supplies the current time, so the schedule can
+//! be driven by a mock clock.
 
 use crate::events::StoredMessage;
-use crate::{MessageStore, PersistenceConfig};
+use crate::{FileSharingStore, MessageStore, PersistenceConfig, ThumbnailStore};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use gigi_logging::{debug, error, info};
@@ -10,6 +17,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio::time::Instant;
 
 /// Sync state for a peer
 #[derive(Debug, Clone)]
@@ -28,11 +36,32 @@ impl Default for SyncState {
     }
 }
 
+/// A queued message that could not be synced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncFailure {
+    pub message_id: String,
+    pub target_nickname: String,
+    pub error: String,
+}
+
+/// Outcome of one sync batch
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncProgress {
+    /// Messages handed to the delivery callback
+    pub attempted: usize,
+    /// Messages delivered and marked as synced
+    pub synced: usize,
+    /// Messages that failed and were scheduled for retry
+    pub failures: Vec<SyncFailure>,
+}
+
 /// Sync manager - handles synchronization of offline messages
 pub struct SyncManager {
     message_store: Arc<MessageStore>,
     sync_states: Arc<Mutex<HashMap<String, SyncState>>>,
     config: PersistenceConfig,
+    /// When the next periodic batch is due, `None` if periodic sync is off
+    next_sync: Mutex<Option<Instant>>,
     #[allow(dead_code)]
     sync_state_path: std::path::PathBuf,
 }
@@ -44,14 +73,115 @@ impl SyncManager {
         _local_nickname: String,
         sync_state_path: std::path::PathBuf,
     ) -> Self {
+        let config = message_store.config.clone();
+        let next_sync = (config.sync_interval_seconds > 0)
+            .then(|| Instant::now() + Duration::from_secs(config.sync_interval_seconds));
         Self {
-            message_store: message_store.clone(),
+            message_store,
             sync_states: Arc::new(Mutex::new(HashMap::new())),
-            config: message_store.config.clone(),
+            config,
+            next_sync: Mutex::new(next_sync),
             sync_state_path,
         }
     }
 
+    /// When the next periodic sync batch is due
+    ///
+    /// # Returns
+    ///
+    /// `None` if `sync_interval_seconds` is 0, which disables periodic sync.
+    pub async fn next_sync_at(&self) -> Option<Instant> {
+        *self.next_sync.lock().await
+    }
+
+    /// Sync a batch if the periodic sync is due at `now`
+    ///
+    /// The next batch is scheduled `sync_interval_seconds` after `now`.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time
+    /// * `online` - Nicknames of the peers messages can be sent to
+    /// * `deliver` - Sends a message to the peer with the given nickname
+    ///
+    /// # Returns
+    ///
+    /// The batch outcome, or `None` if no batch was due.
+    pub async fn sync_if_due<F>(
+        &self,
+        now: Instant,
+        online: &[String],
+        deliver: F,
+    ) -> Result<Option<SyncProgress>>
+    where
+        F: FnMut(&str, &StoredMessage) -> Result<()>,
+    {
+        {
+            let mut next_sync = self.next_sync.lock().await;
+            match *next_sync {
+                Some(due) if due <= now => {
+                    *next_sync = Some(now + Duration::from_secs(self.config.sync_interval_seconds));
+                }
+                _ => return Ok(None),
+            }
+        }
+        self.sync_batch(online, deliver).await.map(Some)
+    }
+
+    /// Sync up to `max_batch_size` queued messages to online peers
+    ///
+    /// Each message goes to `deliver`; successes are marked as synced and
+    /// failures are scheduled for retry with backoff.
+    ///
+    /// # Arguments
+    ///
+    /// * `online` - Nicknames of the peers messages can be sent to
+    /// * `deliver` - Sends a message to the peer with the given nickname
+    pub async fn sync_batch<F>(&self, online: &[String], mut deliver: F) -> Result<SyncProgress>
+    where
+        F: FnMut(&str, &StoredMessage) -> Result<()>,
+    {
+        let batch = self
+            .message_store
+            .get_sync_batch(online, self.config.max_batch_size)
+            .await?;
+
+        let mut progress = SyncProgress::default();
+        for (target_nickname, message) in batch {
+            progress.attempted += 1;
+            match deliver(&target_nickname, &message) {
+                Ok(()) => {
+                    self.message_store
+                        .record_sync_attempt(&message.id, true)
+                        .await?;
+                    progress.synced += 1;
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to sync message {} to {}: {}",
+                        message.id, target_nickname, e
+                    );
+                    self.message_store
+                        .record_sync_attempt(&message.id, false)
+                        .await?;
+                    progress.failures.push(SyncFailure {
+                        message_id: message.id,
+                        target_nickname,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        if progress.attempted > 0 {
+            info!(
+                "Synced {} of {} queued messages",
+                progress.synced, progress.attempted
+            );
+        }
+        Ok(progress)
+    }
+
     /// Handle peer coming online
     pub async fn on_peer_online(
         &self,
@@ -186,16 +316,41 @@ impl SyncManager {
         }
     }
 
+    /// Delete expired messages and queue items
+    ///
+    /// One pass of the periodic cleanup. Thumbnails of expired disappearing
+    /// image messages are deleted with them, on the sending and the
+    /// receiving side.
+    ///
+    /// # Returns
+    /// The number of messages and queue items removed
+    pub async fn cleanup_expired(
+        &self,
+        file_sharing_store: &FileSharingStore,
+        thumbnail_store: &ThumbnailStore,
+    ) -> Result<u64> {
+        self.message_store
+            .cleanup_expired_with_thumbnails(file_sharing_store, thumbnail_store)
+            .await
+    }
+
     /// Start periodic cleanup task
     #[allow(dead_code)]
-    pub async fn start_cleanup_task(&self) {
+    pub async fn start_cleanup_task(
+        &self,
+        file_sharing_store: &FileSharingStore,
+        thumbnail_store: &ThumbnailStore,
+    ) {
         let mut interval =
             tokio::time::interval(Duration::from_secs(self.config.cleanup_interval_seconds));
 
         loop {
             interval.tick().await;
 
-            if let Err(e) = self.message_store.cleanup_expired().await {
+            if let Err(e) = self
+                .cleanup_expired(file_sharing_store, thumbnail_store)
+                .await
+            {
                 error!("Cleanup task failed: {}", e);
             }
         }
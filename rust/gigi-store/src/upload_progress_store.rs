@@ -0,0 +1,109 @@
+//! Upload progress store - Remember how many chunks were served per upload
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+
+/// Chunks of one share code served to one peer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadProgress {
+    pub peer_id: String,
+    pub share_code: String,
+    /// Distinct chunks served so far
+    pub served_chunks: u64,
+    pub total_chunks: u64,
+    /// When the progress was last saved, in milliseconds since the epoch
+    pub updated_at: i64,
+}
+
+impl From<crate::entities::upload_progress::Model> for UploadProgress {
+    fn from(data: crate::entities::upload_progress::Model) -> Self {
+        Self {
+            peer_id: data.peer_id,
+            share_code: data.share_code,
+            served_chunks: data.served_chunks as u64,
+            total_chunks: data.total_chunks as u64,
+            updated_at: data.updated_at,
+        }
+    }
+}
+
+/// Upload progress store - keeps served chunk counts of unfinished uploads
+/// so upload progress continues across restarts
+pub struct UploadProgressStore {
+    db: DatabaseConnection,
+}
+
+impl UploadProgressStore {
+    /// Create a new upload progress store
+    pub async fn new(db: DatabaseConnection) -> Result<Self> {
+        Ok(Self { db })
+    }
+
+    /// Save the progress of an upload, replacing what was saved before
+    pub async fn save_upload_progress(
+        &self,
+        peer_id: &str,
+        share_code: &str,
+        served_chunks: u64,
+        total_chunks: u64,
+    ) -> Result<()> {
+        use crate::entities::upload_progress;
+        use sea_orm::sea_query::OnConflict;
+
+        let model = upload_progress::ActiveModel {
+            peer_id: Set(peer_id.to_string()),
+            share_code: Set(share_code.to_string()),
+            served_chunks: Set(served_chunks as i64),
+            total_chunks: Set(total_chunks as i64),
+            updated_at: Set(Utc::now().timestamp_millis()),
+        };
+        upload_progress::Entity::insert(model)
+            .on_conflict(
+                OnConflict::columns([
+                    upload_progress::Column::PeerId,
+                    upload_progress::Column::ShareCode,
+                ])
+                .update_columns([
+                    upload_progress::Column::ServedChunks,
+                    upload_progress::Column::TotalChunks,
+                    upload_progress::Column::UpdatedAt,
+                ])
+                .to_owned(),
+            )
+            .exec(&self.db)
+            .await
+            .context("Failed to save upload progress")?;
+        Ok(())
+    }
+
+    /// List the progress of all unfinished uploads, most recently updated first
+    pub async fn list_upload_progress(&self) -> Result<Vec<UploadProgress>> {
+        use crate::entities::upload_progress;
+
+        let results = upload_progress::Entity::find()
+            .order_by_desc(upload_progress::Column::UpdatedAt)
+            .all(&self.db)
+            .await
+            .context("Failed to list upload progress")?;
+
+        Ok(results.into_iter().map(UploadProgress::from).collect())
+    }
+
+    /// Forget the progress of an upload, e.g. once it finished
+    ///
+    /// # Returns
+    /// Whether progress was saved for the upload
+    pub async fn remove_upload_progress(&self, peer_id: &str, share_code: &str) -> Result<bool> {
+        use crate::entities::upload_progress;
+
+        let result = upload_progress::Entity::delete_many()
+            .filter(upload_progress::Column::PeerId.eq(peer_id))
+            .filter(upload_progress::Column::ShareCode.eq(share_code))
+            .exec(&self.db)
+            .await
+            .context("Failed to remove upload progress")?;
+        Ok(result.rows_affected > 0)
+    }
+}
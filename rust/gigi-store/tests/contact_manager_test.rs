@@ -2,7 +2,7 @@
 //
 // Comprehensive tests for ContactManager
 
-use gigi_store::ContactManager;
+use gigi_store::{ContactImportSummary, ContactManager, StoreError};
 use sea_orm::DatabaseConnection;
 use tempfile::NamedTempFile;
 
@@ -181,3 +181,190 @@ async fn test_duplicate_contact_handling() {
 
     assert!(result.is_err(), "Adding duplicate contact should fail");
 }
+
+#[tokio::test]
+async fn test_update_contact_profile() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+
+    let manager = ContactManager::new(db);
+
+    // Profiles of strangers are not stored
+    let stored = manager
+        .update_profile("12D3KooW...", "Alice", "08011220aa", None)
+        .await
+        .expect("Failed to update profile");
+    assert!(!stored, "Profile of a non-contact should not be stored");
+
+    manager
+        .add("12D3KooW...", "Alice")
+        .await
+        .expect("Failed to add contact");
+
+    let contact = manager.get("12D3KooW...").await.unwrap().unwrap();
+    assert_eq!(contact.display_name, None);
+
+    let stored = manager
+        .update_profile("12D3KooW...", "Alice Smith", "08011220aa", Some("abc123"))
+        .await
+        .expect("Failed to update profile");
+    assert!(stored);
+
+    let contact = manager.get("12D3KooW...").await.unwrap().unwrap();
+    assert_eq!(contact.name, "Alice", "Local name should be kept");
+    assert_eq!(contact.display_name.as_deref(), Some("Alice Smith"));
+    assert_eq!(contact.public_key.as_deref(), Some("08011220aa"));
+    assert_eq!(contact.avatar_hash.as_deref(), Some("abc123"));
+}
+
+#[tokio::test]
+async fn test_export_import_round_trip() {
+    let source_file = NamedTempFile::new().unwrap();
+    let source = ContactManager::new(create_test_db(&source_file).await);
+    source.add("peer-alice", "Alice").await.unwrap();
+    source.add("peer-bob", "Bob").await.unwrap();
+    source
+        .update_profile("peer-bob", "Bobby", "bob-key", Some("bob-avatar"))
+        .await
+        .unwrap();
+
+    let target_file = NamedTempFile::new().unwrap();
+    let target = ContactManager::new(create_test_db(&target_file).await);
+
+    let exported = source.export_contacts().await.unwrap();
+    let summary = target.import_contacts(&exported).await.unwrap();
+    assert_eq!(
+        summary,
+        ContactImportSummary {
+            added: 2,
+            updated: 0,
+            skipped: 0,
+        }
+    );
+
+    let bob = target.get("peer-bob").await.unwrap().unwrap();
+    let original = source.get("peer-bob").await.unwrap().unwrap();
+    assert_eq!(bob.name, "Bob");
+    assert_eq!(bob.added_at, original.added_at);
+    assert_eq!(bob.display_name.as_deref(), Some("Bobby"));
+    assert_eq!(bob.public_key.as_deref(), Some("bob-key"));
+    assert_eq!(bob.avatar_hash.as_deref(), Some("bob-avatar"));
+
+    // Importing the same data again only finds duplicates
+    let summary = target.import_contacts(&exported).await.unwrap();
+    assert_eq!(summary.added, 0);
+    assert_eq!(summary.skipped, 2);
+}
+
+#[tokio::test]
+async fn test_import_merges_changed_nicknames() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let manager = ContactManager::new(create_test_db(&temp_file).await);
+    manager.add("peer-alice", "Alice").await.unwrap();
+    manager
+        .update_profile("peer-alice", "Ali", "alice-key", None)
+        .await
+        .unwrap();
+    let local = manager.get("peer-alice").await.unwrap().unwrap();
+
+    let mut renamed = local.clone();
+    renamed.name = "Alice Smith".to_string();
+    let mut stale = local.clone();
+    stale.name = "Old Alice".to_string();
+    stale.updated_at -= 1000;
+    // Same public key under another peer ID is the same contact
+    let mut by_key = local.clone();
+    by_key.peer_id = "peer-alice-2".to_string();
+    by_key.name = "Alice S.".to_string();
+    by_key.display_name = None;
+
+    let data = serde_json::to_string(&vec![renamed]).unwrap();
+    let summary = manager.import_contacts(&data).await.unwrap();
+    assert_eq!(summary.updated, 1);
+    let alice = manager.get("peer-alice").await.unwrap().unwrap();
+    assert_eq!(alice.name, "Alice Smith");
+
+    // The local contact is newer than the imported one
+    let data = serde_json::to_string(&vec![stale]).unwrap();
+    let summary = manager.import_contacts(&data).await.unwrap();
+    assert_eq!(summary.skipped, 1);
+    assert_eq!(
+        manager.get("peer-alice").await.unwrap().unwrap().name,
+        "Alice Smith"
+    );
+
+    let data = serde_json::to_string(&vec![by_key]).unwrap();
+    let summary = manager.import_contacts(&data).await.unwrap();
+    assert_eq!(summary.updated, 1);
+    assert!(!manager.exists("peer-alice-2").await.unwrap());
+    let alice = manager.get("peer-alice").await.unwrap().unwrap();
+    assert_eq!(alice.name, "Alice S.");
+    // Fields missing from the import are kept
+    assert_eq!(alice.display_name.as_deref(), Some("Ali"));
+}
+
+#[tokio::test]
+async fn test_vcard_round_trip() {
+    let source_file = NamedTempFile::new().unwrap();
+    let source = ContactManager::new(create_test_db(&source_file).await);
+    source
+        .add("peer-carol", "Carol; the \\ one, really")
+        .await
+        .unwrap();
+    source
+        .update_profile("peer-carol", "Caro", "carol-key", None)
+        .await
+        .unwrap();
+
+    let vcard = source.export_contacts_vcard().await.unwrap();
+    assert!(vcard.starts_with("BEGIN:VCARD\r\nVERSION:4.0\r\n"));
+    assert!(vcard.contains("X-GIGI-PEER-ID:peer-carol\r\n"));
+
+    let target_file = NamedTempFile::new().unwrap();
+    let target = ContactManager::new(create_test_db(&target_file).await);
+    let summary = target.import_contacts(&vcard).await.unwrap();
+    assert_eq!(summary.added, 1);
+
+    let carol = target.get("peer-carol").await.unwrap().unwrap();
+    assert_eq!(carol.name, "Carol; the \\ one, really");
+    assert_eq!(carol.display_name.as_deref(), Some("Caro"));
+    assert_eq!(carol.public_key.as_deref(), Some("carol-key"));
+    assert_eq!(carol.avatar_hash, None);
+
+    // A card without a peer ID cannot be imported
+    let error = target
+        .import_contacts("BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Dave\r\nEND:VCARD\r\n")
+        .await
+        .unwrap_err();
+    assert!(matches!(error, StoreError::InvalidContactData(_)));
+}
+
+#[tokio::test]
+async fn test_import_keeps_contacts_renamed_after_export() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let manager = ContactManager::new(create_test_db(&temp_file).await);
+    manager.add("peer-erin", "Erin").await.unwrap();
+    let exported = manager.export_contacts().await.unwrap();
+    let vcard = manager.export_contacts_vcard().await.unwrap();
+
+    // Renamed after the export: the export is now older, although the
+    // contact was added at the same time
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    manager.update_name("peer-erin", "Erin B.").await.unwrap();
+    let renamed = manager.get("peer-erin").await.unwrap().unwrap();
+    assert!(renamed.updated_at > renamed.added_at);
+
+    let summary = manager.import_contacts(&exported).await.unwrap();
+    assert_eq!(summary.skipped, 1);
+    let summary = manager.import_contacts(&vcard).await.unwrap();
+    assert_eq!(summary.skipped, 1);
+
+    // A vCard without Gigi timestamps is older than the local contact
+    let foreign = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Someone else\r\nX-GIGI-PEER-ID:peer-erin\r\nEND:VCARD\r\n";
+    let summary = manager.import_contacts(foreign).await.unwrap();
+    assert_eq!(summary.skipped, 1);
+    assert_eq!(
+        manager.get("peer-erin").await.unwrap().unwrap().name,
+        "Erin B."
+    );
+}
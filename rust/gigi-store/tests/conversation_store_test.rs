@@ -0,0 +1,74 @@
+// Copyright 2024 Gigi Team.
+//
+// Tests for ConversationStore
+
+use gigi_store::ConversationStore;
+use tempfile::NamedTempFile;
+
+async fn create_store_with_unread(
+    path: &NamedTempFile,
+    unread: &[(&str, u32)],
+) -> ConversationStore {
+    let store = ConversationStore::new(path.path().to_path_buf())
+        .await
+        .expect("Failed to create conversation store");
+
+    for (id, count) in unread {
+        store
+            .upsert_conversation(
+                id.to_string(),
+                id.to_string(),
+                false,
+                id.to_string(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        for _ in 0..*count {
+            store.increment_unread(id).await.unwrap();
+        }
+    }
+    store
+}
+
+#[tokio::test]
+async fn test_total_unread_sums_all_conversations() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store =
+        create_store_with_unread(&temp_file, &[("alice", 3), ("bob", 0), ("carol", 2)]).await;
+
+    let expected: i32 = store
+        .get_conversations()
+        .await
+        .unwrap()
+        .iter()
+        .map(|conversation| conversation.unread_count)
+        .sum();
+    assert_eq!(expected, 5);
+    assert_eq!(store.total_unread().await.unwrap(), 5);
+
+    store.mark_as_read("alice").await.unwrap();
+    assert_eq!(store.total_unread().await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn test_total_unread_without_conversations() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = create_store_with_unread(&temp_file, &[]).await;
+
+    assert_eq!(store.total_unread().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_mark_all_as_read() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = create_store_with_unread(&temp_file, &[("alice", 3), ("bob", 1)]).await;
+
+    store.mark_all_as_read().await.unwrap();
+
+    assert_eq!(store.total_unread().await.unwrap(), 0);
+    for conversation in store.get_conversations().await.unwrap() {
+        assert_eq!(conversation.unread_count, 0);
+    }
+}
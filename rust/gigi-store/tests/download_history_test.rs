@@ -0,0 +1,99 @@
+// Copyright 2024 Gigi Team.
+//
+// Tests for DownloadHistoryStore
+
+use gigi_store::{DownloadHistoryEntry, DownloadHistoryStatus, DownloadHistoryStore};
+use sea_orm::DatabaseConnection;
+use tempfile::NamedTempFile;
+
+async fn create_test_db(path: &tempfile::NamedTempFile) -> DatabaseConnection {
+    let db = sea_orm::Database::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        path.path().to_str().unwrap().replace("\\", "/")
+    ))
+    .await
+    .expect("Failed to connect to database");
+
+    <gigi_store::migration::Migrator as gigi_store::migration::MigratorTrait>::up(&db, None)
+        .await
+        .expect("Failed to run migrations");
+
+    db
+}
+
+fn entry(file_name: &str, status: DownloadHistoryStatus, finished_at: i64) -> DownloadHistoryEntry {
+    DownloadHistoryEntry {
+        id: None,
+        share_code: format!("code-{}", file_name),
+        file_name: file_name.to_string(),
+        peer_id: "peer123".to_string(),
+        peer_nickname: "Alice".to_string(),
+        file_size: None,
+        status,
+        error: None,
+        started_at: finished_at - 10,
+        finished_at,
+        final_path: None,
+    }
+}
+
+#[tokio::test]
+async fn test_record_and_list_download_history() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = DownloadHistoryStore::new(db).await.unwrap();
+
+    let completed = DownloadHistoryEntry {
+        file_size: Some(2048),
+        final_path: Some("/downloads/report.pdf".to_string()),
+        ..entry("report.pdf", DownloadHistoryStatus::Completed, 100)
+    };
+    let failed = DownloadHistoryEntry {
+        error: Some("Peer disconnected".to_string()),
+        ..entry("photo.jpg", DownloadHistoryStatus::Failed, 200)
+    };
+    let completed_id = store.record_download(&completed).await.unwrap();
+    let failed_id = store.record_download(&failed).await.unwrap();
+
+    // Most recently finished first
+    let history = store.list_download_history(10, 0).await.unwrap();
+    assert_eq!(
+        history,
+        vec![
+            DownloadHistoryEntry {
+                id: Some(failed_id),
+                ..failed
+            },
+            DownloadHistoryEntry {
+                id: Some(completed_id),
+                ..completed
+            },
+        ]
+    );
+
+    let page = store.list_download_history(1, 1).await.unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].file_name, "report.pdf");
+    assert!(store.list_download_history(10, 2).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_clear_download_history() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = DownloadHistoryStore::new(db).await.unwrap();
+
+    for (name, status) in [
+        ("a.txt", DownloadHistoryStatus::Completed),
+        ("b.txt", DownloadHistoryStatus::Cancelled),
+    ] {
+        store
+            .record_download(&entry(name, status, 1))
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(store.clear_download_history().await.unwrap(), 2);
+    assert!(store.list_download_history(10, 0).await.unwrap().is_empty());
+    assert_eq!(store.clear_download_history().await.unwrap(), 0);
+}
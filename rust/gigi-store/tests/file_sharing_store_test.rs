@@ -0,0 +1,279 @@
+// Copyright 2024 Gigi Team.
+//
+// Tests for FileSharingStore
+
+use gigi_store::{FileSharingStore, ShareStats, SharedFileInfo};
+use sea_orm::DatabaseConnection;
+use tempfile::NamedTempFile;
+
+async fn create_test_db(path: &tempfile::NamedTempFile) -> DatabaseConnection {
+    let db = sea_orm::Database::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        path.path().to_str().unwrap().replace("\\", "/")
+    ))
+    .await
+    .expect("Failed to connect to database");
+
+    // Run migrations
+    <gigi_store::migration::Migrator as gigi_store::migration::MigratorTrait>::up(&db, None)
+        .await
+        .expect("Failed to run migrations");
+
+    db
+}
+
+fn shared_file(share_code: &str, file_name: &str) -> SharedFileInfo {
+    SharedFileInfo::new(
+        share_code.to_string(),
+        file_name.to_string(),
+        format!("/tmp/{}", file_name),
+        1024,
+        "hash".to_string(),
+        1,
+        0,
+    )
+}
+
+#[tokio::test]
+async fn test_search_shared_files() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    for (code, name) in [
+        ("code0001", "Report-2024.pdf"),
+        ("code0002", "annual_report.docx"),
+        ("code0003", "photo.jpg"),
+    ] {
+        store
+            .store_shared_file(&shared_file(code, name))
+            .await
+            .unwrap();
+    }
+
+    let mut codes: Vec<_> = store
+        .search_shared_files("REPORT")
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|info| info.share_code)
+        .collect();
+    codes.sort();
+    assert_eq!(codes, vec!["code0001", "code0002"]);
+
+    assert!(store
+        .search_shared_files("missing")
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(store.search_shared_files("").await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_search_shared_files_matches_wildcards_literally() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    for (code, name) in [
+        ("code0001", "annual_report.docx"),
+        ("code0002", "annual-report.docx"),
+        ("code0003", "100% done.txt"),
+        ("code0004", "100 percent.txt"),
+        ("code0005", "back\\slash.txt"),
+    ] {
+        store
+            .store_shared_file(&shared_file(code, name))
+            .await
+            .unwrap();
+    }
+
+    let search = |query: &'static str| {
+        let store = &store;
+        async move {
+            store
+                .search_shared_files(query)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|info| info.share_code)
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(search("l_r").await, vec!["code0001"]);
+    assert_eq!(search("0%").await, vec!["code0003"]);
+    assert_eq!(search("k\\s").await, vec!["code0005"]);
+}
+
+#[tokio::test]
+async fn test_delete_shared_files() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    for (code, name) in [
+        ("code0001", "a.png"),
+        ("code0002", "b.png"),
+        ("code0003", "c.txt"),
+    ] {
+        store
+            .store_shared_file(&shared_file(code, name))
+            .await
+            .unwrap();
+    }
+
+    let deleted = store
+        .delete_shared_files(&["code0001".to_string(), "code0002".to_string()])
+        .await
+        .unwrap();
+    assert_eq!(deleted, 2);
+
+    let remaining = store.list_shared_files().await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].share_code, "code0003");
+
+    assert_eq!(store.delete_shared_files(&[]).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_shared_stats_matches_manual_sum() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    // An empty table aggregates to zero
+    assert_eq!(store.shared_stats().await.unwrap(), ShareStats::default());
+
+    for (code, name, size) in [
+        ("code0001", "movie.mkv", 3_000_000_000u64),
+        ("code0002", "notes.txt", 1_234),
+        ("code0003", "song.mp3", 4_500_000),
+        ("code0004", "old.zip", 777),
+    ] {
+        let mut info = shared_file(code, name);
+        info.file_size = size;
+        store.store_shared_file(&info).await.unwrap();
+    }
+    store.revoke_shared_file("code0004").await.unwrap();
+
+    let files = store.list_shared_files().await.unwrap();
+    let active: Vec<_> = files.iter().filter(|file| !file.revoked).collect();
+    let expected = ShareStats {
+        file_count: active.len() as u64,
+        total_bytes: active.iter().map(|file| file.file_size).sum(),
+        revoked_count: files.iter().filter(|file| file.revoked).count() as u64,
+    };
+
+    let stats = store.shared_stats().await.unwrap();
+    assert_eq!(stats, expected);
+    assert_eq!(stats.file_count, 3);
+    assert_eq!(stats.total_bytes, 3_004_501_234);
+    assert_eq!(stats.revoked_count, 1);
+}
+
+#[tokio::test]
+async fn test_shared_file_modified_at_round_trip() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    let mut info = shared_file("code0001", "large.bin");
+    assert_eq!(info.modified_at, None);
+    info.modified_at = Some(1_700_000_000_123_456_789);
+    store.store_shared_file(&info).await.unwrap();
+
+    let stored = store.get_shared_file("code0001").await.unwrap().unwrap();
+    assert_eq!(stored.modified_at, Some(1_700_000_000_123_456_789));
+
+    // A re-hashed file without a usable mtime clears it
+    info.modified_at = None;
+    store.store_shared_file(&info).await.unwrap();
+    let stored = store.get_shared_file("code0001").await.unwrap().unwrap();
+    assert_eq!(stored.modified_at, None);
+}
+
+#[tokio::test]
+async fn test_shared_file_relative_path_round_trip() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    let mut info = shared_file("code0001", "beach.jpg");
+    assert_eq!(info.relative_path, None);
+    info.relative_path = Some("album/2024/beach.jpg".to_string());
+    store.store_shared_file(&info).await.unwrap();
+
+    let stored = store.get_shared_file("code0001").await.unwrap().unwrap();
+    assert_eq!(
+        stored.relative_path.as_deref(),
+        Some("album/2024/beach.jpg")
+    );
+}
+
+#[tokio::test]
+async fn test_shared_file_chunk_offsets_round_trip() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    let mut info = shared_file("code0001", "video.mp4");
+    assert_eq!(info.chunk_offsets, None);
+    info.chunk_offsets = Some(vec![0, 70_000, 400_123]);
+    store.store_shared_file(&info).await.unwrap();
+
+    let stored = store.get_shared_file("code0001").await.unwrap().unwrap();
+    assert_eq!(stored.chunk_offsets, Some(vec![0, 70_000, 400_123]));
+    let listed = store.list_shared_files().await.unwrap();
+    assert_eq!(listed[0].chunk_offsets, Some(vec![0, 70_000, 400_123]));
+}
+
+#[tokio::test]
+async fn test_changes_are_visible_to_other_instances() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    // Two windows sharing one database
+    let window = FileSharingStore::new(db.clone()).await.unwrap();
+    let other_window = FileSharingStore::new(db).await.unwrap();
+    assert_eq!(
+        other_window.shared_files_last_modified().await.unwrap(),
+        None
+    );
+    assert!(!other_window.shared_files_changed_since(0).await.unwrap());
+
+    window
+        .store_shared_file(&shared_file("code0001", "a.txt"))
+        .await
+        .unwrap();
+    let first = other_window
+        .shared_files_last_modified()
+        .await
+        .unwrap()
+        .expect("Sharing should be recorded");
+    assert!(other_window.shared_files_changed_since(0).await.unwrap());
+    assert!(!other_window
+        .shared_files_changed_since(first)
+        .await
+        .unwrap());
+
+    // Reads do not count as changes
+    window.list_shared_files().await.unwrap();
+    assert!(!window.delete_shared_file("missing").await.unwrap());
+    assert!(!other_window
+        .shared_files_changed_since(first)
+        .await
+        .unwrap());
+
+    // Changes within the same millisecond still move the stamp forward
+    window.revoke_shared_file("code0001").await.unwrap();
+    let second = other_window
+        .shared_files_last_modified()
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(second > first);
+    window.cleanup_revoked_files().await.unwrap();
+    assert!(other_window
+        .shared_files_changed_since(second)
+        .await
+        .unwrap());
+}
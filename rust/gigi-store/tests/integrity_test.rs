@@ -0,0 +1,108 @@
+// Copyright 2024 Gigi Team.
+//
+// Tests for download integrity re-verification
+
+use gigi_store::{
+    hash_file, reverify_downloads, verify_download, DownloadedFileInfo, FileSharingStore, HashAlgo,
+};
+use sea_orm::DatabaseConnection;
+use tempfile::{NamedTempFile, TempDir};
+
+async fn create_test_db(path: &tempfile::NamedTempFile) -> DatabaseConnection {
+    let db = sea_orm::Database::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        path.path().to_str().unwrap().replace("\\", "/")
+    ))
+    .await
+    .expect("Failed to connect to database");
+
+    <gigi_store::migration::Migrator as gigi_store::migration::MigratorTrait>::up(&db, None)
+        .await
+        .expect("Failed to run migrations");
+
+    db
+}
+
+#[tokio::test]
+async fn test_reverify_detects_corrupted_download() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("photo.jpg");
+    std::fs::write(&path, b"original contents").unwrap();
+    let hash = hash_file(&path).unwrap();
+    assert!(verify_download(&path, &hash).unwrap());
+
+    store
+        .record_downloaded_file(&DownloadedFileInfo {
+            file_path: path.to_string_lossy().to_string(),
+            share_code: "code-1".to_string(),
+            file_name: "photo.jpg".to_string(),
+            file_size: 17,
+            hash: hash.clone(),
+            hash_algo: HashAlgo::Sha256,
+            downloaded_at: 1,
+            verified_at: None,
+            verified_ok: true,
+        })
+        .await
+        .unwrap();
+
+    // Intact file passes
+    assert!(reverify_downloads(&store).await.unwrap().is_empty());
+    let recorded = store.list_downloaded_files().await.unwrap();
+    assert!(recorded[0].verified_at.is_some());
+    assert!(recorded[0].verified_ok);
+
+    // Corrupt the file on disk
+    std::fs::write(&path, b"0riginal contents").unwrap();
+    assert!(!verify_download(&path, &hash).unwrap());
+
+    let mismatches = reverify_downloads(&store).await.unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].share_code, "code-1");
+    assert_eq!(mismatches[0].expected_hash, hash);
+    assert!(mismatches[0].actual_hash.is_some());
+    assert_ne!(mismatches[0].actual_hash.as_deref(), Some(hash.as_str()));
+    assert!(!store.list_downloaded_files().await.unwrap()[0].verified_ok);
+
+    // A missing file is reported without a hash
+    std::fs::remove_file(&path).unwrap();
+    let mismatches = reverify_downloads(&store).await.unwrap();
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].actual_hash, None);
+}
+
+#[tokio::test]
+async fn test_reverify_skips_unhashed_download() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = FileSharingStore::new(db).await.unwrap();
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("unhashed.bin");
+    std::fs::write(&path, b"shared without a hash").unwrap();
+
+    store
+        .record_downloaded_file(&DownloadedFileInfo {
+            file_path: path.to_string_lossy().to_string(),
+            share_code: "code-2".to_string(),
+            file_name: "unhashed.bin".to_string(),
+            file_size: 21,
+            hash: String::new(),
+            hash_algo: HashAlgo::Sha256,
+            downloaded_at: 1,
+            verified_at: None,
+            verified_ok: true,
+        })
+        .await
+        .unwrap();
+
+    // Nothing to check the file against, so it is not reported corrupted
+    assert!(reverify_downloads(&store).await.unwrap().is_empty());
+    let recorded = store.list_downloaded_files().await.unwrap();
+    assert_eq!(recorded.len(), 1);
+    assert!(recorded[0].verified_ok);
+}
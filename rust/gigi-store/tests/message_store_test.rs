@@ -3,8 +3,8 @@
 // Comprehensive tests for MessageStore
 
 use gigi_store::{
-    message_store::MessageStore, MessageContent, MessageDirection, MessageType, PersistenceConfig,
-    SyncStatus,
+    message_store::MessageStore, ConversationKey, ExportFormat, FileShareContent, MessageContent,
+    MessageDirection, MessageOrder, MessageType, PersistenceConfig, SyncStatus,
 };
 use tempfile::NamedTempFile;
 use uuid::Uuid;
@@ -116,6 +116,49 @@ async fn test_conversation_history() {
     }
 }
 
+#[tokio::test]
+async fn test_conversation_with_peer_keeps_same_label_apart() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+
+    // Two peers received under the same placeholder label
+    for (id, peer_id) in [("m1", "peer-a"), ("m2", "peer-b"), ("m3", "peer-a")] {
+        let mut msg = create_test_message(id, id);
+        msg.direction = MessageDirection::Received;
+        msg.sender_nickname = "quick-fox".to_string();
+        msg.recipient_nickname = Some("Alice".to_string());
+        msg.peer_id = peer_id.to_string();
+        store.store_message(msg).await.unwrap();
+    }
+    // Queued for the label before a peer ID was known
+    let mut queued = create_test_message("m4", "queued");
+    queued.recipient_nickname = Some("quick-fox".to_string());
+    queued.peer_id = String::new();
+    store.store_message(queued).await.unwrap();
+
+    let mut ids: Vec<String> = store
+        .get_conversation_with_peer("peer-a", "quick-fox", 10, 0)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|msg| msg.id)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, vec!["m1", "m3", "m4"]);
+
+    // By nickname alone, both peers' messages are mixed
+    assert_eq!(
+        store
+            .get_conversation("quick-fox", 10, 0)
+            .await
+            .unwrap()
+            .len(),
+        4
+    );
+}
+
 #[tokio::test]
 async fn test_mark_delivered() {
     let temp_file = NamedTempFile::new().unwrap();
@@ -346,6 +389,7 @@ async fn test_message_content_types() {
         filename: "test.jpg".to_string(),
         file_size: 1024,
         file_type: "image/jpeg".to_string(),
+        from_peer: None,
     };
     store.store_message(msg2).await.unwrap();
     let retrieved2 = store.get_message(&msg_id2).await.unwrap().unwrap();
@@ -460,6 +504,124 @@ async fn test_custom_config() {
     assert!(retrieved.is_some());
 }
 
+#[tokio::test]
+async fn test_disappearing_message_expires_before_global_ttl() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .unwrap();
+
+    let regular_id = Uuid::new_v4().to_string();
+    store
+        .store_message(create_test_message(&regular_id, "Stays"))
+        .await
+        .unwrap();
+
+    let disappearing_id = Uuid::new_v4().to_string();
+    let mut msg = create_test_message(&disappearing_id, "Gone soon");
+    msg.disappear_after_secs = Some(1);
+    store.store_message(msg).await.unwrap();
+
+    let stored = store.get_message(&disappearing_id).await.unwrap().unwrap();
+    assert_eq!(stored.disappear_after_secs, Some(1));
+    assert!(stored.expires_at < chrono::Utc::now() + chrono::Duration::seconds(2));
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
+    assert_eq!(store.cleanup_expired().await.unwrap(), 1);
+
+    assert!(store.get_message(&disappearing_id).await.unwrap().is_none());
+    assert!(store.get_message(&regular_id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_received_disappearing_message_timer_starts_on_read() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .unwrap();
+
+    let msg_id = Uuid::new_v4().to_string();
+    let mut msg = create_test_message(&msg_id, "Read me once");
+    msg.direction = MessageDirection::Received;
+    msg.disappear_after_secs = Some(1);
+    store.store_message(msg).await.unwrap();
+
+    // Unread messages are kept until the global TTL
+    tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
+    store.cleanup_expired().await.unwrap();
+    assert!(store.get_message(&msg_id).await.unwrap().is_some());
+
+    store.mark_read(&msg_id).await.unwrap();
+    tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
+    store.cleanup_expired().await.unwrap();
+    assert!(store.get_message(&msg_id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_query_messages_by_sync_status() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+
+    let states = [
+        ("pending-old", SyncStatus::Pending, 30),
+        ("pending-new", SyncStatus::Pending, 10),
+        ("synced", SyncStatus::Synced, 20),
+        ("delivered", SyncStatus::Delivered, 20),
+    ];
+    for (id, status, seconds_ago) in states {
+        let mut msg = create_test_message(id, "Sync state");
+        msg.sync_status = status;
+        msg.timestamp = chrono::Utc::now() - chrono::Duration::seconds(seconds_ago);
+        store.store_message(msg).await.unwrap();
+    }
+    store
+        .enqueue_offline("pending-new".to_string(), "Bob".to_string())
+        .await
+        .unwrap();
+    store
+        .record_sync_attempt("pending-new", false)
+        .await
+        .unwrap();
+
+    // Per-message accessor
+    let info = store.get_sync_status("pending-new").await.unwrap().unwrap();
+    assert_eq!(info.sync_status, SyncStatus::Pending);
+    assert_eq!(info.sync_attempts, 1);
+    assert!(info.last_sync_attempt.is_some());
+    let info = store.get_sync_status("synced").await.unwrap().unwrap();
+    assert_eq!(info.sync_status, SyncStatus::Synced);
+    assert_eq!(info.sync_attempts, 0);
+    assert!(info.last_sync_attempt.is_none());
+    assert!(store.get_sync_status("missing").await.unwrap().is_none());
+
+    // Filtered lists, oldest first
+    let ids = |messages: Vec<gigi_store::StoredMessage>| {
+        messages.into_iter().map(|m| m.id).collect::<Vec<_>>()
+    };
+    let pending = store
+        .list_messages_by_sync_status(SyncStatus::Pending, 10)
+        .await
+        .unwrap();
+    assert_eq!(ids(pending), vec!["pending-old", "pending-new"]);
+    let pending = store
+        .list_messages_by_sync_status(SyncStatus::Pending, 1)
+        .await
+        .unwrap();
+    assert_eq!(ids(pending), vec!["pending-old"]);
+    let delivered = store
+        .list_messages_by_sync_status(SyncStatus::Delivered, 10)
+        .await
+        .unwrap();
+    assert_eq!(ids(delivered), vec!["delivered"]);
+    let acknowledged = store
+        .list_messages_by_sync_status(SyncStatus::Acknowledged, 10)
+        .await
+        .unwrap();
+    assert!(acknowledged.is_empty());
+}
+
 // Helper function to create test messages
 fn create_test_message(id: &str, text: &str) -> gigi_store::StoredMessage {
     gigi_store::StoredMessage {
@@ -483,5 +645,353 @@ fn create_test_message(id: &str, text: &str) -> gigi_store::StoredMessage {
         sync_attempts: 0,
         last_sync_attempt: None,
         expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+        disappear_after_secs: None,
+        forwarded_from: None,
+    }
+}
+
+// Helper function to create a group poll or vote message
+fn create_poll_message(
+    id: &str,
+    peer_id: &str,
+    content: MessageContent,
+    seconds_ago: i64,
+) -> gigi_store::StoredMessage {
+    let mut msg = create_test_message(id, "");
+    msg.msg_type = MessageType::Group;
+    msg.recipient_nickname = None;
+    msg.group_name = Some("PollGroup".to_string());
+    msg.peer_id = peer_id.to_string();
+    msg.content = content;
+    msg.timestamp = chrono::Utc::now() - chrono::Duration::seconds(seconds_ago);
+    msg
+}
+
+fn vote(poll_id: &str, option_index: u32) -> MessageContent {
+    MessageContent::PollVote {
+        poll_id: poll_id.to_string(),
+        option_index,
     }
 }
+
+#[tokio::test]
+async fn test_poll_vote_aggregation() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+
+    assert!(store.get_poll_results("poll-1").await.unwrap().is_none());
+
+    let poll = MessageContent::Poll {
+        poll_id: "poll-1".to_string(),
+        question: "Lunch?".to_string(),
+        options: vec![
+            "Pizza".to_string(),
+            "Sushi".to_string(),
+            "Salad".to_string(),
+        ],
+    };
+    store
+        .store_message(create_poll_message("poll-1", "alice", poll, 60))
+        .await
+        .unwrap();
+
+    let votes = [
+        ("alice", vote("poll-1", 0)),
+        ("bob", vote("poll-1", 1)),
+        ("carol", vote("poll-1", 1)),
+        // Ignored: out of range, and a vote in another poll
+        ("dave", vote("poll-1", 7)),
+        ("erin", vote("poll-2", 2)),
+    ];
+    for (peer_id, content) in votes {
+        let id = Uuid::new_v4().to_string();
+        store
+            .store_message(create_poll_message(&id, peer_id, content, 30))
+            .await
+            .unwrap();
+    }
+
+    let results = store.get_poll_results("poll-1").await.unwrap().unwrap();
+    assert_eq!(results.question, "Lunch?");
+    assert_eq!(results.options.len(), 3);
+    assert_eq!(results.votes, vec![1, 2, 0]);
+    assert_eq!(results.total_votes, 3);
+}
+
+#[tokio::test]
+async fn test_poll_last_vote_wins() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+
+    let poll = MessageContent::Poll {
+        poll_id: "poll-1".to_string(),
+        question: "Ship it?".to_string(),
+        options: vec!["Yes".to_string(), "No".to_string()],
+    };
+    store
+        .store_message(create_poll_message("poll-1", "alice", poll, 60))
+        .await
+        .unwrap();
+
+    // Bob changes their mind; the earlier vote arrives last but is older
+    let bob_votes = [(vote("poll-1", 1), 10), (vote("poll-1", 0), 30)];
+    for (content, seconds_ago) in bob_votes {
+        let id = Uuid::new_v4().to_string();
+        store
+            .store_message(create_poll_message(&id, "bob", content, seconds_ago))
+            .await
+            .unwrap();
+    }
+
+    let results = store.get_poll_results("poll-1").await.unwrap().unwrap();
+    assert_eq!(results.votes, vec![0, 1]);
+    assert_eq!(results.total_votes, 1);
+}
+
+#[tokio::test]
+async fn test_location_message_round_trip() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+
+    let msg_id = Uuid::new_v4().to_string();
+    let mut msg = create_test_message(&msg_id, "");
+    msg.content = MessageContent::Location {
+        lat: 48.858_37,
+        lon: -2.294_481,
+        label: Some("Meeting point".to_string()),
+    };
+    store.store_message(msg).await.unwrap();
+
+    let retrieved = store.get_message(&msg_id).await.unwrap().unwrap();
+    match retrieved.content {
+        MessageContent::Location { lat, lon, label } => {
+            assert_eq!(lat, 48.858_37);
+            assert_eq!(lon, -2.294_481);
+            assert_eq!(label.as_deref(), Some("Meeting point"));
+        }
+        other => panic!("Expected a location, got {:?}", other),
+    }
+}
+
+// Helper function to store a conversation with Bob out of chronological order,
+// plus a message to Carol that must not be exported
+async fn store_conversation_with_bob(store: &MessageStore) {
+    let base = chrono::Utc::now() - chrono::Duration::hours(1);
+
+    let mut reply = create_test_message("msg-2", "Hi Alice");
+    reply.direction = MessageDirection::Received;
+    reply.sender_nickname = "Bob".to_string();
+    reply.recipient_nickname = Some("Alice".to_string());
+    reply.timestamp = base + chrono::Duration::seconds(20);
+
+    let mut photo = create_test_message("msg-3", "");
+    photo.content = MessageContent::FileShareWithThumbnail {
+        share_code: "share-1".to_string(),
+        filename: "beach.jpg".to_string(),
+        file_size: 2048,
+        file_type: "image/jpeg".to_string(),
+        thumbnail_path: Some("thumbs/beach.jpg".to_string()),
+        from_peer: None,
+    };
+    photo.timestamp = base + chrono::Duration::seconds(40);
+
+    let mut greeting = create_test_message("msg-1", "Hello Bob");
+    greeting.timestamp = base;
+
+    let mut other = create_test_message("msg-carol", "Hello Carol");
+    other.recipient_nickname = Some("Carol".to_string());
+    other.timestamp = base + chrono::Duration::seconds(10);
+
+    for msg in [reply, photo, greeting, other] {
+        store.store_message(msg).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_export_conversation_as_text() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+    store_conversation_with_bob(&store).await;
+
+    let transcript = store
+        .export_conversation(
+            &ConversationKey::Direct("Bob".to_string()),
+            ExportFormat::Text,
+        )
+        .await
+        .unwrap();
+    let lines: Vec<&str> = transcript.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with('['));
+    assert!(lines[0].ends_with("] Alice: Hello Bob"));
+    assert!(lines[1].ends_with("] Bob: Hi Alice"));
+    assert!(
+        lines[2].ends_with("] Alice: [file: beach.jpg, 2048 bytes, thumbnail: thumbs/beach.jpg]")
+    );
+}
+
+#[tokio::test]
+async fn test_export_conversation_as_json() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+    store_conversation_with_bob(&store).await;
+
+    let transcript = store
+        .export_conversation(
+            &ConversationKey::Direct("Bob".to_string()),
+            ExportFormat::Json,
+        )
+        .await
+        .unwrap();
+    let exported: Vec<serde_json::Value> = serde_json::from_str(&transcript).unwrap();
+
+    let ids: Vec<&str> = exported
+        .iter()
+        .map(|entry| entry["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["msg-1", "msg-2", "msg-3"]);
+    assert_eq!(exported[1]["sender"], "Bob");
+    assert_eq!(exported[1]["direction"], "Received");
+    assert_eq!(exported[0]["content"]["text"], "Hello Bob");
+    assert_eq!(exported[2]["content"]["filename"], "beach.jpg");
+    assert_eq!(exported[2]["content"]["thumbnail_path"], "thumbs/beach.jpg");
+
+    // An unknown conversation exports as an empty array
+    let empty = store
+        .export_conversation(
+            &ConversationKey::Group("Nobody".to_string()),
+            ExportFormat::Json,
+        )
+        .await
+        .unwrap();
+    assert_eq!(empty, "[]");
+}
+
+#[tokio::test]
+async fn test_receive_order_survives_skewed_sender_clock() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let store = MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+
+    // Bob's clock runs ten minutes behind ours
+    let question = create_test_message("question", "Lunch?");
+    store.store_message(question).await.unwrap();
+
+    let mut answer = create_test_message("answer", "Sure");
+    answer.direction = MessageDirection::Received;
+    answer.sender_nickname = "Bob".to_string();
+    answer.recipient_nickname = Some("Alice".to_string());
+    answer.timestamp = chrono::Utc::now() - chrono::Duration::minutes(10);
+    store.store_message(answer).await.unwrap();
+
+    // Then a message from a clock an hour ahead
+    let mut follow_up = create_test_message("follow-up", "Noon?");
+    follow_up.direction = MessageDirection::Received;
+    follow_up.sender_nickname = "Bob".to_string();
+    follow_up.recipient_nickname = Some("Alice".to_string());
+    follow_up.timestamp = chrono::Utc::now() + chrono::Duration::hours(1);
+    store.store_message(follow_up).await.unwrap();
+
+    let ids = |messages: Vec<gigi_store::StoredMessage>| {
+        messages
+            .into_iter()
+            .map(|message| message.id)
+            .collect::<Vec<_>>()
+    };
+
+    // By timestamp, the skewed answer sorts before the question it answers
+    let by_timestamp = store.get_conversation("Bob", 10, 0).await.unwrap();
+    assert_eq!(ids(by_timestamp), vec!["follow-up", "question", "answer"]);
+
+    let by_receive_order = store
+        .get_conversation_ordered("Bob", 10, 0, MessageOrder::ReceiveOrder)
+        .await
+        .unwrap();
+    assert_eq!(
+        ids(by_receive_order),
+        vec!["follow-up", "answer", "question"]
+    );
+
+    // The future timestamp was clamped to the time it was stored
+    let follow_up = store.get_message("follow-up").await.unwrap().unwrap();
+    assert!(follow_up.timestamp <= chrono::Utc::now());
+}
+
+#[tokio::test]
+async fn test_file_share_message_round_trip_and_search() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db_path = temp_file.path().to_path_buf();
+    let attachment = FileShareContent {
+        share_code: "share-report".to_string(),
+        filename: "Quarterly-Report.pdf".to_string(),
+        file_size: 48_213,
+        file_type: "application/pdf".to_string(),
+        from_peer: Some("12D3KooWBobPeer".to_string()),
+    };
+
+    {
+        let store = MessageStore::new(db_path.clone())
+            .await
+            .expect("Failed to create message store");
+
+        let mut shared = create_test_message("file-msg", "");
+        shared.direction = MessageDirection::Received;
+        shared.content = attachment.clone().into();
+        store.store_message(shared).await.unwrap();
+
+        let mut other = create_test_message("other-file", "");
+        other.content = MessageContent::FileShare {
+            share_code: "share-photo".to_string(),
+            filename: "holiday.jpg".to_string(),
+            file_size: 1024,
+            file_type: "image/jpeg".to_string(),
+            from_peer: None,
+        };
+        store.store_message(other).await.unwrap();
+
+        // Mentions the filename but is not a file share
+        let text = create_test_message("text-msg", "see Quarterly-Report.pdf");
+        store.store_message(text).await.unwrap();
+    }
+
+    // Reopen to check the attachment survives a restart
+    let store = MessageStore::new(db_path)
+        .await
+        .expect("Failed to reopen message store");
+    let retrieved = store.get_message("file-msg").await.unwrap().unwrap();
+    assert_eq!(retrieved.content.file_share(), Some(attachment.clone()));
+
+    let found = store.search_file_shares("quarterly").await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, "file-msg");
+
+    // A downloaded thumbnail keeps the rest of the attachment
+    store
+        .update_message_thumbnail_path("share-report", "thumbs/report.png")
+        .await
+        .unwrap();
+    let retrieved = store.get_message("file-msg").await.unwrap().unwrap();
+    assert!(matches!(
+        retrieved.content,
+        MessageContent::FileShareWithThumbnail { .. }
+    ));
+    assert_eq!(retrieved.content.file_share(), Some(attachment));
+    assert_eq!(store.search_file_shares("report").await.unwrap().len(), 1);
+    assert!(store
+        .search_file_shares("missing")
+        .await
+        .unwrap()
+        .is_empty());
+}
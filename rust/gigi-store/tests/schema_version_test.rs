@@ -0,0 +1,61 @@
+// Copyright 2024 Gigi Team.
+//
+// Tests for the schema version check run before migrations
+
+use gigi_store::migration::{supported_schema_version, SCHEMA_VERSION_KEY};
+use gigi_store::{MessageStore, SettingsManager, StoreError};
+use sea_orm::Database;
+use tempfile::NamedTempFile;
+
+#[tokio::test]
+async fn test_new_database_is_stamped_with_schema_version() {
+    let temp_file = NamedTempFile::new().unwrap();
+    MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+
+    let db = Database::connect(format!("sqlite:{}?mode=rwc", temp_file.path().display()))
+        .await
+        .unwrap();
+    let version = SettingsManager::new(db)
+        .get(SCHEMA_VERSION_KEY)
+        .await
+        .unwrap();
+    assert_eq!(version, Some(supported_schema_version().to_string()));
+
+    // Reopening a database of the same version works
+    MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to reopen message store");
+}
+
+#[tokio::test]
+async fn test_database_from_newer_version_is_rejected() {
+    let temp_file = NamedTempFile::new().unwrap();
+    MessageStore::new(temp_file.path().to_path_buf())
+        .await
+        .expect("Failed to create message store");
+
+    // Stamp the database as written by a future build
+    let future = supported_schema_version() + 1;
+    let db = Database::connect(format!("sqlite:{}?mode=rwc", temp_file.path().display()))
+        .await
+        .unwrap();
+    SettingsManager::new(db)
+        .set(SCHEMA_VERSION_KEY, &future.to_string())
+        .await
+        .unwrap();
+
+    let err = match MessageStore::new(temp_file.path().to_path_buf()).await {
+        Ok(_) => panic!("Opening a newer database should fail"),
+        Err(err) => err,
+    };
+    match err.downcast_ref::<StoreError>() {
+        Some(StoreError::IncompatibleSchema { found, supported }) => {
+            assert_eq!(*found, future);
+            assert_eq!(*supported, supported_schema_version());
+        }
+        other => panic!("Expected IncompatibleSchema, got {:?}", other),
+    }
+    assert!(format!("{:#}", err).contains("newer than the supported version"));
+}
@@ -0,0 +1,210 @@
+// Copyright 2024 Gigi Team.
+//
+// Tests for periodic message sync in SyncManager
+
+use gigi_store::{
+    message_store::MessageStore, FileSharingStore, MessageContent, MessageDirection, MessageType,
+    PersistenceConfig, SharedFileInfo, SyncManager, SyncStatus, ThumbnailStore,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tempfile::NamedTempFile;
+
+async fn create_sync_manager(temp_file: &NamedTempFile) -> (Arc<MessageStore>, SyncManager) {
+    let config = PersistenceConfig {
+        db_path: temp_file.path().to_path_buf(),
+        sync_interval_seconds: 30,
+        max_batch_size: 2,
+        ..Default::default()
+    };
+    let store = Arc::new(
+        MessageStore::with_config(config)
+            .await
+            .expect("Failed to create message store"),
+    );
+    let sync = SyncManager::new(
+        store.clone(),
+        "Alice".to_string(),
+        temp_file.path().with_extension("sync"),
+    );
+    (store, sync)
+}
+
+async fn queue_message(store: &MessageStore, id: &str, target: &str) {
+    store
+        .store_message(create_test_message(id, target))
+        .await
+        .unwrap();
+    store
+        .enqueue_offline(id.to_string(), target.to_string())
+        .await
+        .unwrap();
+    // Keep queue order deterministic
+    tokio::time::sleep(Duration::from_millis(5)).await;
+}
+
+#[tokio::test]
+async fn test_sync_batch_runs_on_schedule() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let (store, sync) = create_sync_manager(&temp_file).await;
+    for id in ["m1", "m2", "m3"] {
+        queue_message(&store, id, "Bob").await;
+    }
+    queue_message(&store, "offline", "Carol").await;
+
+    let online = vec!["Bob".to_string()];
+    let due = sync.next_sync_at().await.expect("Periodic sync is enabled");
+    let mut delivered = Vec::new();
+
+    // Nothing happens before the interval elapsed
+    let early = sync
+        .sync_if_due(due - Duration::from_secs(1), &online, |_, msg| {
+            delivered.push(msg.id.clone());
+            Ok(())
+        })
+        .await
+        .unwrap();
+    assert!(early.is_none());
+    assert!(delivered.is_empty());
+
+    // The first batch is limited to max_batch_size
+    let first = sync
+        .sync_if_due(due, &online, |nickname, msg| {
+            assert_eq!(nickname, "Bob");
+            delivered.push(msg.id.clone());
+            Ok(())
+        })
+        .await
+        .unwrap()
+        .expect("Batch should run when due");
+    assert_eq!((first.attempted, first.synced), (2, 2));
+    assert_eq!(delivered, vec!["m1", "m2"]);
+
+    let synced = store.get_message("m1").await.unwrap().unwrap();
+    assert_eq!(synced.sync_status, SyncStatus::Synced);
+    assert_eq!(synced.sync_attempts, 1);
+    assert!(synced.last_sync_attempt.is_some());
+
+    // The next batch waits for another interval
+    let between = sync
+        .sync_if_due(due + Duration::from_secs(29), &online, |_, _| Ok(()))
+        .await
+        .unwrap();
+    assert!(between.is_none());
+
+    // A failed delivery is reported and backs off instead of retrying next round
+    let second = sync
+        .sync_if_due(due + Duration::from_secs(30), &online, |_, msg| {
+            assert_eq!(msg.id, "m3");
+            Err(anyhow::anyhow!("stream closed"))
+        })
+        .await
+        .unwrap()
+        .expect("Batch should run when due");
+    assert_eq!((second.attempted, second.synced), (1, 0));
+    assert_eq!(second.failures.len(), 1);
+    assert_eq!(second.failures[0].message_id, "m3");
+    assert_eq!(second.failures[0].error, "stream closed");
+
+    let failed = store.get_message("m3").await.unwrap().unwrap();
+    assert_eq!(failed.sync_status, SyncStatus::Pending);
+    assert_eq!(failed.sync_attempts, 1);
+
+    // Messages for offline peers are left queued
+    let third = sync
+        .sync_if_due(due + Duration::from_secs(60), &online, |_, _| Ok(()))
+        .await
+        .unwrap()
+        .expect("Batch should run when due");
+    assert_eq!(third.attempted, 0);
+    assert_eq!(
+        store.get_pending_messages("Carol", 10).await.unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_received_disappearing_image_expires_with_thumbnail() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let (store, sync) = create_sync_manager(&temp_file).await;
+    let db = sea_orm::Database::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        temp_file.path().to_str().unwrap().replace("\\", "/")
+    ))
+    .await
+    .expect("Failed to connect to database");
+    let file_sharing_store = FileSharingStore::new(db.clone()).await.unwrap();
+    let thumbnail_store = ThumbnailStore::new(db).await.unwrap();
+
+    // The receiver keeps the thumbnail of the incoming image under its share code
+    let thumbnail_dir = tempfile::TempDir::new().unwrap();
+    let thumbnail_path = thumbnail_dir.path().join("thumb_photo.jpg");
+    std::fs::write(&thumbnail_path, b"thumbnail").unwrap();
+    file_sharing_store
+        .store_shared_file(&SharedFileInfo::new(
+            "photo001".to_string(),
+            "photo.jpg".to_string(),
+            "/tmp/photo.jpg".to_string(),
+            1024,
+            "hash".to_string(),
+            1,
+            0,
+        ))
+        .await
+        .unwrap();
+    file_sharing_store
+        .update_thumbnail_path("photo001", thumbnail_path.to_str().unwrap())
+        .await
+        .unwrap();
+
+    let mut msg = create_test_message("photo-msg", "Alice");
+    msg.direction = MessageDirection::Received;
+    msg.sender_nickname = "Bob".to_string();
+    msg.content = MessageContent::FileShareWithThumbnail {
+        share_code: "photo001".to_string(),
+        filename: "photo.jpg".to_string(),
+        file_size: 1024,
+        file_type: "image/jpeg".to_string(),
+        thumbnail_path: Some(thumbnail_path.to_string_lossy().to_string()),
+        from_peer: None,
+    };
+    msg.disappear_after_secs = Some(1);
+    store.store_message(msg).await.unwrap();
+
+    // The timer starts when the receiver reads the message
+    store.mark_read("photo-msg").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    sync.cleanup_expired(&file_sharing_store, &thumbnail_store)
+        .await
+        .unwrap();
+
+    assert!(store.get_message("photo-msg").await.unwrap().is_none());
+    assert!(!thumbnail_path.exists());
+}
+
+fn create_test_message(id: &str, recipient: &str) -> gigi_store::StoredMessage {
+    gigi_store::StoredMessage {
+        id: id.to_string(),
+        msg_type: MessageType::Direct,
+        direction: MessageDirection::Sent,
+        content: MessageContent::Text {
+            text: format!("Queued message {}", id),
+        },
+        sender_nickname: "Alice".to_string(),
+        recipient_nickname: Some(recipient.to_string()),
+        group_name: None,
+        peer_id: String::new(),
+        timestamp: chrono::Utc::now(),
+        created_at: chrono::Utc::now(),
+        delivered: false,
+        delivered_at: None,
+        read: false,
+        read_at: None,
+        sync_status: SyncStatus::Pending,
+        sync_attempts: 0,
+        last_sync_attempt: None,
+        expires_at: chrono::Utc::now() + chrono::Duration::days(7),
+        disappear_after_secs: None,
+        forwarded_from: None,
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2024 Gigi Team.
+//
+// Tests for UploadProgressStore
+
+use gigi_store::UploadProgressStore;
+use sea_orm::DatabaseConnection;
+use tempfile::NamedTempFile;
+
+async fn create_test_db(path: &tempfile::NamedTempFile) -> DatabaseConnection {
+    let db = sea_orm::Database::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        path.path().to_str().unwrap().replace("\\", "/")
+    ))
+    .await
+    .expect("Failed to connect to database");
+
+    <gigi_store::migration::Migrator as gigi_store::migration::MigratorTrait>::up(&db, None)
+        .await
+        .expect("Failed to run migrations");
+
+    db
+}
+
+#[tokio::test]
+async fn test_save_replaces_upload_progress() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = UploadProgressStore::new(db).await.unwrap();
+
+    store
+        .save_upload_progress("peer1", "code1", 3, 10)
+        .await
+        .unwrap();
+    store
+        .save_upload_progress("peer1", "code1", 7, 10)
+        .await
+        .unwrap();
+    store
+        .save_upload_progress("peer2", "code1", 1, 10)
+        .await
+        .unwrap();
+
+    let mut progress = store.list_upload_progress().await.unwrap();
+    progress.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+    assert_eq!(progress.len(), 2);
+    assert_eq!(progress[0].peer_id, "peer1");
+    assert_eq!(progress[0].share_code, "code1");
+    assert_eq!(progress[0].served_chunks, 7);
+    assert_eq!(progress[0].total_chunks, 10);
+    assert_eq!(progress[1].served_chunks, 1);
+}
+
+#[tokio::test]
+async fn test_remove_upload_progress() {
+    let temp_file = NamedTempFile::new().unwrap();
+    let db = create_test_db(&temp_file).await;
+    let store = UploadProgressStore::new(db).await.unwrap();
+
+    store
+        .save_upload_progress("peer1", "code1", 3, 10)
+        .await
+        .unwrap();
+    store
+        .save_upload_progress("peer1", "code2", 5, 10)
+        .await
+        .unwrap();
+
+    assert!(store
+        .remove_upload_progress("peer1", "code1")
+        .await
+        .unwrap());
+    assert!(!store
+        .remove_upload_progress("peer1", "code1")
+        .await
+        .unwrap());
+    let progress = store.list_upload_progress().await.unwrap();
+    assert_eq!(progress.len(), 1);
+    assert_eq!(progress[0].share_code, "code2");
+}